@@ -0,0 +1,32 @@
+#!/usr/bin/env python3
+"""Give the running session more time, e.g. from a home automation hook:
+
+    extend_session.py 10    # ten more minutes
+
+Must run as the shepherdd user or root, since extending is an admin command.
+"""
+
+import datetime
+import sys
+
+from shepherd_client import Client, ShepherdError
+
+
+def main():
+    minutes = int(sys.argv[1]) if len(sys.argv) > 1 else 10
+    with Client() as shepherd:
+        session = shepherd.get_state()["current_session"]
+        if session is None:
+            print("Nothing is running")
+            return 0
+        try:
+            shepherd.extend_current(datetime.timedelta(minutes=minutes))
+        except ShepherdError as e:
+            print("Couldn't extend %s: %s" % (session["label"], e.message), file=sys.stderr)
+            return 1
+        print("Gave %s %d more minutes" % (session["label"], minutes))
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -0,0 +1,35 @@
+"""Python client for shepherdd.
+
+A thin wrapper over the JSON protocol on shepherdd's Unix socket, for
+scripting automations without writing Rust::
+
+    import datetime
+    from shepherd_client import Client
+
+    with Client() as shepherd:
+        shepherd.extend_current(datetime.timedelta(minutes=10))
+"""
+
+from .client import (
+    API_VERSION,
+    Client,
+    ConnectionClosed,
+    ShepherdError,
+    default_socket_path,
+    duration_ms,
+    parse_duration,
+    parse_timestamp,
+    timestamp,
+)
+
+__all__ = [
+    "API_VERSION",
+    "Client",
+    "ConnectionClosed",
+    "ShepherdError",
+    "default_socket_path",
+    "duration_ms",
+    "parse_duration",
+    "parse_timestamp",
+    "timestamp",
+]
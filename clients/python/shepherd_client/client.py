@@ -0,0 +1,236 @@
+"""Connection to shepherdd over its Unix socket.
+
+Messages are one JSON object per line. Requests carry a ``request_id`` that
+the service echoes in its response; events carry a ``payload`` and arrive
+on the same connection once it has subscribed. See ``shepherdd
+--print-schema`` for every message.
+"""
+
+import datetime
+import json
+import os
+import re
+import socket
+import uuid
+
+#: Protocol version this client speaks
+API_VERSION = 1
+
+#: Environment variable overriding the socket path
+SOCKET_ENV = "SHEPHERD_SOCKET"
+
+
+def default_socket_path():
+    """The socket shepherdd listens on, found the same way as the Rust clients:
+    ``$SHEPHERD_SOCKET``, then ``$XDG_RUNTIME_DIR/shepherdd/shepherdd.sock``,
+    then ``/tmp/shepherdd-$USER/shepherdd.sock``."""
+    if os.environ.get(SOCKET_ENV):
+        return os.environ[SOCKET_ENV]
+    runtime_dir = os.environ.get("XDG_RUNTIME_DIR")
+    if runtime_dir:
+        return os.path.join(runtime_dir, "shepherdd", "shepherdd.sock")
+    user = os.environ.get("USER", "unknown")
+    return "/tmp/shepherdd-%s/shepherdd.sock" % user
+
+
+def duration_ms(value):
+    """Encode a ``timedelta`` or a number of seconds as milliseconds."""
+    if isinstance(value, datetime.timedelta):
+        value = value.total_seconds()
+    if value < 0:
+        raise ValueError("durations must not be negative")
+    return int(round(value * 1000))
+
+
+def seat_field(seat):
+    """The optional ``seat`` of a command, left out for the client's own."""
+    return {"seat": seat} if seat is not None else {}
+
+
+def parse_duration(ms):
+    """Decode a duration in milliseconds, keeping None (unlimited) as None."""
+    return None if ms is None else datetime.timedelta(milliseconds=ms)
+
+
+def timestamp(value):
+    """Encode an aware ``datetime`` as RFC 3339. Naive datetimes are taken
+    as local time."""
+    if value.tzinfo is None:
+        value = value.astimezone()
+    return value.isoformat()
+
+
+def parse_timestamp(text):
+    """Decode an RFC 3339 timestamp, keeping None as None."""
+    if text is None:
+        return None
+    if text.endswith("Z"):
+        text = text[:-1] + "+00:00"
+    # shepherdd writes up to nanoseconds; older Pythons take exactly 3 or 6 digits
+    text = re.sub(r"\.(\d+)", lambda m: "." + m.group(1)[:6].ljust(6, "0"), text, count=1)
+    return datetime.datetime.fromisoformat(text)
+
+
+class ShepherdError(Exception):
+    """A request the service answered with an error.
+
+    ``code`` is the protocol's error code, e.g. ``"permission_denied"`` or
+    ``"no_active_session"``."""
+
+    #: Codes worth retrying after a short wait
+    RETRYABLE = ("unavailable", "rate_limited")
+
+    def __init__(self, code, message, spawn=None):
+        super().__init__("%s: %s" % (code, message))
+        self.code = code
+        self.message = message
+        self.spawn = spawn
+
+    @property
+    def retryable(self):
+        return self.code in self.RETRYABLE
+
+
+class ConnectionClosed(Exception):
+    """shepherdd closed the connection."""
+
+
+class Client:
+    """A connection to shepherdd.
+
+    What a client may do depends on who runs it: the service's own user
+    and root are admins, who can extend sessions, grant stars and put
+    launches on hold.
+
+    Use it as a context manager, or call ``close()`` when done::
+
+        with Client() as shepherd:
+            state = shepherd.get_state()
+    """
+
+    def __init__(self, socket_path=None, timeout=10.0):
+        self._sock = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+        self._sock.settimeout(timeout)
+        self._sock.connect(socket_path or default_socket_path())
+        self._reader = self._sock.makefile("r", encoding="utf-8", newline="\n")
+        self._next_request_id = 1
+        self._events = []
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *exc):
+        self.close()
+
+    def close(self):
+        self._reader.close()
+        self._sock.close()
+
+    def request(self, command_type, **fields):
+        """Send a command and return the response payload as a dict.
+
+        ``command_type`` is the command's snake_case name, e.g.
+        ``"get_state"``. Raises ``ShepherdError`` if the service refuses.
+        Events that arrive first are kept for ``events()``."""
+        request_id = self._next_request_id
+        self._next_request_id += 1
+        command = dict(fields, type=command_type)
+        line = json.dumps(
+            {"request_id": request_id, "api_version": API_VERSION, "command": command}
+        )
+        self._sock.sendall(line.encode("utf-8") + b"\n")
+
+        while True:
+            message = self._read()
+            if "payload" in message:
+                self._events.append(message)
+                continue
+            if message.get("request_id") != request_id:
+                continue
+            result = message["result"]
+            if "err" in result:
+                error = result["err"]
+                raise ShepherdError(error["code"], error["message"], error.get("spawn"))
+            return result["ok"]
+
+    def events(self):
+        """Subscribe and yield events as dicts, forever.
+
+        Each event has ``seq``, ``timestamp`` and a ``payload`` whose
+        ``type`` says what happened (``session_started``,
+        ``warning_issued``, ...). Use a separate client for requests while
+        iterating."""
+        self.request("subscribe_events")
+        while True:
+            while self._events:
+                yield self._events.pop(0)
+            self._sock.settimeout(None)
+            yield self._read()
+
+    def _read(self):
+        line = self._reader.readline()
+        if not line:
+            raise ConnectionClosed()
+        return json.loads(line)
+
+    # Commands
+
+    def ping(self):
+        return self.request("ping")
+
+    def get_state(self, seat=None):
+        """The service state snapshot: current session, entries, hold, ...
+        Admins can ask about another ``seat`` than their own."""
+        return self.request("get_state", **seat_field(seat))
+
+    def get_health(self):
+        return self.request("get_health")
+
+    def get_capabilities(self):
+        """shepherdd's version, what its host supports and any update."""
+        return self.request("get_capabilities")
+
+    def list_entries(self, at_time=None):
+        """Entries with whether each can be launched now and why not, or at
+        ``at_time`` (a ``datetime``) for a preview."""
+        fields = {} if at_time is None else {"at_time": timestamp(at_time)}
+        return self.request("list_entries", **fields)["entries"]
+
+    def launch(self, entry_id, idempotency_key=None):
+        """Launch an entry. The payload's ``type`` is ``launch_approved``,
+        ``launch_denied`` (with ``reasons``) or
+        ``launch_needs_confirmation``. Retries with the same
+        ``idempotency_key`` get the first answer instead of a second
+        session."""
+        return self.request(
+            "launch",
+            entry_id=entry_id,
+            idempotency_key=idempotency_key or str(uuid.uuid4()),
+        )
+
+    def stop_current(self, force=False, seat=None):
+        """Stop the running session. Admins can stop one on another
+        ``seat``."""
+        return self.request(
+            "stop_current",
+            mode="force" if force else "graceful",
+            **seat_field(seat),
+        )
+
+    def extend_current(self, by, seat=None):
+        """Give the running session more time (admin only). ``by`` is a
+        ``timedelta`` or a number of seconds."""
+        return self.request("extend_current", by=duration_ms(by), **seat_field(seat))
+
+    def grant_reward(self, stars, reason=None):
+        """Grant reward stars (admin only)."""
+        return self.request("grant_reward", stars=stars, reason=reason)
+
+    def set_hold(self, enabled, message=None, until=None):
+        """Block or allow all launches (admin only)."""
+        return self.request(
+            "set_hold",
+            enabled=enabled,
+            message=message,
+            until=None if until is None else timestamp(until),
+        )
@@ -0,0 +1,147 @@
+"""Tests against a fake shepherdd on a temporary socket.
+
+Run with ``python3 -m unittest discover -s tests`` from clients/python.
+"""
+
+import datetime
+import json
+import os
+import socket
+import sys
+import tempfile
+import threading
+import unittest
+
+sys.path.insert(0, os.path.join(os.path.dirname(__file__), ".."))
+
+from shepherd_client import (  # noqa: E402
+    Client,
+    ShepherdError,
+    duration_ms,
+    parse_duration,
+    parse_timestamp,
+)
+
+
+class FakeService:
+    """Answers each request with ``handler(command)``, which returns
+    ``(result, events)``; events are sent before the response."""
+
+    def __init__(self, handler):
+        self.handler = handler
+        self.requests = []
+        self.dir = tempfile.TemporaryDirectory()
+        self.path = os.path.join(self.dir.name, "shepherdd.sock")
+        self.server = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+        self.server.bind(self.path)
+        self.server.listen(1)
+        self.thread = threading.Thread(target=self._serve, daemon=True)
+        self.thread.start()
+
+    def _serve(self):
+        conn, _ = self.server.accept()
+        with conn, conn.makefile("r") as reader:
+            for line in reader:
+                request = json.loads(line)
+                self.requests.append(request)
+                result, events = self.handler(request["command"])
+                for event in events:
+                    conn.sendall((json.dumps(event) + "\n").encode())
+                response = {
+                    "request_id": request["request_id"],
+                    "api_version": 1,
+                    "result": result,
+                }
+                conn.sendall((json.dumps(response) + "\n").encode())
+
+    def close(self):
+        self.server.close()
+        self.dir.cleanup()
+
+
+def event(seq, payload):
+    return {
+        "api_version": 1,
+        "timestamp": "2025-06-02T16:00:00+02:00",
+        "seq": seq,
+        "payload": payload,
+    }
+
+
+class ClientTest(unittest.TestCase):
+    def test_requests(self):
+        def handler(command):
+            if command["type"] == "extend_current":
+                return {"ok": {"type": "extended", "new_deadline": None}}, []
+            if command["type"] == "grant_reward":
+                return {"err": {"code": "permission_denied", "message": "admin only"}}, []
+            return {"ok": {"type": "pong"}}, [event(1, {"type": "volume_changed"})]
+
+        service = FakeService(handler)
+        self.addCleanup(service.close)
+
+        with Client(service.path) as shepherd:
+            self.assertEqual(shepherd.ping(), {"type": "pong"})
+            shepherd.extend_current(datetime.timedelta(minutes=10))
+            with self.assertRaises(ShepherdError) as raised:
+                shepherd.grant_reward(1)
+            self.assertEqual(raised.exception.code, "permission_denied")
+            self.assertFalse(raised.exception.retryable)
+
+        commands = [r["command"] for r in service.requests]
+        self.assertEqual(commands[1], {"type": "extend_current", "by": 600000})
+        self.assertEqual([r["request_id"] for r in service.requests], [1, 2, 3])
+
+    def test_list_entries(self):
+        def handler(command):
+            entries = [{"entry_id": "minecraft", "enabled": True}]
+            return {"ok": {"type": "entries", "entries": entries}}, []
+
+        service = FakeService(handler)
+        self.addCleanup(service.close)
+
+        evening = datetime.datetime(2025, 6, 2, 18, 30, tzinfo=datetime.timezone.utc)
+        with Client(service.path) as shepherd:
+            self.assertEqual(shepherd.list_entries()[0]["entry_id"], "minecraft")
+            shepherd.list_entries(at_time=evening)
+
+        commands = [r["command"] for r in service.requests]
+        self.assertEqual(commands[0], {"type": "list_entries"})
+        self.assertEqual(
+            commands[1],
+            {"type": "list_entries", "at_time": "2025-06-02T18:30:00+00:00"},
+        )
+
+    def test_events(self):
+        def handler(command):
+            return {"ok": {"type": "subscribed", "client_id": "x"}}, [
+                event(1, {"type": "state_changed"}),
+                event(2, {"type": "session_expiring", "session_id": "s"}),
+            ]
+
+        service = FakeService(handler)
+        self.addCleanup(service.close)
+
+        with Client(service.path) as shepherd:
+            events = shepherd.events()
+            self.assertEqual(next(events)["seq"], 1)
+            self.assertEqual(next(events)["payload"]["type"], "session_expiring")
+
+    def test_encoding(self):
+        self.assertEqual(duration_ms(90.5), 90500)
+        self.assertEqual(parse_duration(90500), datetime.timedelta(seconds=90.5))
+        self.assertIsNone(parse_duration(None))
+        self.assertEqual(
+            parse_timestamp("2025-06-02T14:00:00Z"),
+            datetime.datetime(2025, 6, 2, 14, tzinfo=datetime.timezone.utc),
+        )
+        self.assertEqual(
+            parse_timestamp("2025-06-02T16:00:00.123456789+02:00").microsecond,
+            123456,
+        )
+        with self.assertRaises(ValueError):
+            duration_ms(-1)
+
+
+if __name__ == "__main__":
+    unittest.main()
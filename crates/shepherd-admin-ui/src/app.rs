@@ -0,0 +1,459 @@
+//! Main admin window
+//!
+//! Lists entries on the left and edits the selected one on the right.
+//! Every edit is previewed with SimulatePolicy before it's applied.
+
+use chrono::{Datelike, Timelike};
+use gtk4::glib;
+use gtk4::prelude::*;
+use shepherd_api::{EntryView, Locale, PolicyPatch, PolicyView, UpdateInfo};
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tracing::{error, info};
+
+use crate::client::AdminClient;
+use crate::editor::{DefaultsEditor, EntryEditor};
+use crate::form::preview_time;
+
+/// Wait after the last keystroke before refreshing the preview
+const PREVIEW_DELAY: Duration = Duration::from_millis(400);
+
+pub struct AdminApp {
+    socket_path: PathBuf,
+}
+
+impl AdminApp {
+    pub fn new(socket_path: PathBuf) -> Self {
+        Self { socket_path }
+    }
+
+    pub fn run(&self) -> i32 {
+        let app = gtk4::Application::builder()
+            .application_id("org.shepherd.admin")
+            .build();
+
+        let socket_path = self.socket_path.clone();
+
+        app.connect_activate(move |app| {
+            let window = AdminWindow::new(app, &socket_path);
+            window.reload();
+            window.window.present();
+        });
+
+        app.run_with_args::<&str>(&[]).into()
+    }
+}
+
+struct AdminWindow {
+    window: gtk4::ApplicationWindow,
+    runtime: Arc<Runtime>,
+    client: Arc<AdminClient>,
+    policy: RefCell<Option<PolicyView>>,
+    /// Selected entry index, or None for the defaults
+    selected: Cell<Option<usize>>,
+    entry_list: gtk4::ListBox,
+    editors: gtk4::Stack,
+    entry_editor: EntryEditor,
+    defaults_editor: DefaultsEditor,
+    apply_button: gtk4::Button,
+    status: gtk4::Label,
+    /// Shown when a newer shepherdd release is available
+    update_banner: gtk4::Label,
+    preview_day: gtk4::DropDown,
+    preview_hour: gtk4::SpinButton,
+    preview_minute: gtk4::SpinButton,
+    preview_list: gtk4::ListBox,
+    preview_generation: Cell<u64>,
+}
+
+impl AdminWindow {
+    fn new(app: &gtk4::Application, socket_path: &Path) -> Rc<Self> {
+        let window = gtk4::ApplicationWindow::builder()
+            .application(app)
+            .title("Shepherd Settings")
+            .default_width(1000)
+            .default_height(700)
+            .build();
+
+        let now = shepherd_util::now();
+        let day_names: Vec<String> = (0..7)
+            .map(|offset| match offset {
+                0 => "Today".to_string(),
+                1 => "Tomorrow".to_string(),
+                _ => (now + chrono::Duration::days(offset)).weekday().to_string(),
+            })
+            .collect();
+        let day_refs: Vec<&str> = day_names.iter().map(String::as_str).collect();
+
+        let this = Rc::new(Self {
+            window,
+            runtime: Arc::new(Runtime::new().expect("Failed to create tokio runtime")),
+            client: Arc::new(AdminClient::new(socket_path)),
+            policy: RefCell::new(None),
+            selected: Cell::new(None),
+            entry_list: gtk4::ListBox::new(),
+            editors: gtk4::Stack::new(),
+            entry_editor: EntryEditor::new(),
+            defaults_editor: DefaultsEditor::new(),
+            apply_button: gtk4::Button::with_label("Apply"),
+            status: gtk4::Label::new(None),
+            update_banner: gtk4::Label::new(None),
+            preview_day: gtk4::DropDown::from_strings(&day_refs),
+            preview_hour: gtk4::SpinButton::with_range(0.0, 23.0, 1.0),
+            preview_minute: gtk4::SpinButton::with_range(0.0, 59.0, 15.0),
+            preview_list: gtk4::ListBox::new(),
+            preview_generation: Cell::new(0),
+        });
+        this.build_layout();
+        this.connect_signals();
+        this
+    }
+
+    fn build_layout(&self) {
+        let now = shepherd_util::now();
+
+        let list_scroll = gtk4::ScrolledWindow::new();
+        list_scroll.set_width_request(240);
+        list_scroll.set_child(Some(&self.entry_list));
+
+        self.editors.add_named(&self.entry_editor.root, Some("entry"));
+        self.editors.add_named(&self.defaults_editor.root, Some("defaults"));
+
+        let reload_button = gtk4::Button::with_label("Reload");
+        reload_button.set_action_name(Some("win.reload"));
+
+        let buttons = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+        buttons.set_halign(gtk4::Align::End);
+        buttons.set_margin_end(16);
+        self.apply_button.add_css_class("suggested-action");
+        buttons.append(&reload_button);
+        buttons.append(&self.apply_button);
+
+        self.status.set_halign(gtk4::Align::Start);
+        self.status.set_margin_start(16);
+        self.status.set_wrap(true);
+
+        let preview_title = gtk4::Label::new(Some("Preview"));
+        preview_title.add_css_class("heading");
+        preview_title.set_halign(gtk4::Align::Start);
+
+        self.preview_hour.set_value(now.hour() as f64);
+        self.preview_minute.set_value(0.0);
+        let preview_controls = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+        preview_controls.append(&preview_title);
+        preview_controls.append(&self.preview_day);
+        preview_controls.append(&self.preview_hour);
+        preview_controls.append(&gtk4::Label::new(Some(":")));
+        preview_controls.append(&self.preview_minute);
+
+        let preview_scroll = gtk4::ScrolledWindow::new();
+        preview_scroll.set_vexpand(true);
+        preview_scroll.set_child(Some(&self.preview_list));
+
+        let preview = gtk4::Box::new(gtk4::Orientation::Vertical, 8);
+        preview.set_margin_start(16);
+        preview.set_margin_end(16);
+        preview.set_margin_bottom(16);
+        preview.append(&preview_controls);
+        preview.append(&preview_scroll);
+
+        self.update_banner.set_halign(gtk4::Align::Start);
+        self.update_banner.set_margin_start(16);
+        self.update_banner.set_margin_top(8);
+        self.update_banner.add_css_class("dim-label");
+        self.update_banner.set_visible(false);
+
+        let right = gtk4::Box::new(gtk4::Orientation::Vertical, 8);
+        right.append(&self.update_banner);
+        right.append(&self.editors);
+        right.append(&buttons);
+        right.append(&self.status);
+        right.append(&gtk4::Separator::new(gtk4::Orientation::Horizontal));
+        right.append(&preview);
+
+        let paned = gtk4::Paned::new(gtk4::Orientation::Horizontal);
+        paned.set_start_child(Some(&list_scroll));
+        paned.set_end_child(Some(&right));
+        paned.set_shrink_start_child(false);
+        self.window.set_child(Some(&paned));
+    }
+
+    fn connect_signals(self: &Rc<Self>) {
+        let reload = gtk4::gio::SimpleAction::new("reload", None);
+        let this = Rc::downgrade(self);
+        reload.connect_activate(move |_, _| {
+            if let Some(this) = this.upgrade() {
+                this.reload();
+            }
+        });
+        self.window.add_action(&reload);
+
+        let this = Rc::downgrade(self);
+        self.entry_list.connect_row_selected(move |_, row| {
+            if let (Some(this), Some(row)) = (this.upgrade(), row) {
+                this.select(row.index());
+            }
+        });
+
+        let this = Rc::downgrade(self);
+        self.apply_button.connect_clicked(move |_| {
+            if let Some(this) = this.upgrade() {
+                this.apply();
+            }
+        });
+
+        let schedule = {
+            let this = Rc::downgrade(self);
+            move || {
+                if let Some(this) = this.upgrade() {
+                    this.schedule_preview();
+                }
+            }
+        };
+        self.entry_editor.connect_changed(schedule.clone());
+        self.defaults_editor.connect_changed(schedule.clone());
+        let cb = schedule.clone();
+        self.preview_day.connect_selected_notify(move |_| cb());
+        let cb = schedule.clone();
+        self.preview_hour.connect_value_changed(move |_| cb());
+        self.preview_minute.connect_value_changed(move |_| schedule());
+    }
+
+    /// Run `future` on the tokio runtime and hand its result to `done` on
+    /// the GTK main loop
+    fn spawn<T, F, D>(&self, future: F, done: D)
+    where
+        T: Send + 'static,
+        F: Future<Output = T> + Send + 'static,
+        D: FnOnce(T) + 'static,
+    {
+        let handle = self.runtime.spawn(future);
+        glib::spawn_future_local(async move {
+            match handle.await {
+                Ok(result) => done(result),
+                Err(e) => error!(error = %e, "Admin request task failed"),
+            }
+        });
+    }
+
+    /// Fetch the policy and rebuild the entry list
+    fn reload(self: &Rc<Self>) {
+        self.refresh_update_banner();
+
+        let client = self.client.clone();
+        let this = Rc::downgrade(self);
+        self.spawn(async move { client.get_policy().await }, move |result| {
+            let Some(this) = this.upgrade() else {
+                return;
+            };
+            match result {
+                Ok(policy) => {
+                    info!(entries = policy.entries.len(), "Loaded policy");
+                    this.status.set_text("");
+                    this.show_policy(policy);
+                }
+                Err(e) => {
+                    error!(error = %e, "Failed to load policy");
+                    this.status.set_text(&format!("Could not load settings: {}", e));
+                }
+            }
+        });
+    }
+
+    fn refresh_update_banner(self: &Rc<Self>) {
+        let client = self.client.clone();
+        let this = Rc::downgrade(self);
+        self.spawn(async move { client.get_health().await }, move |result| {
+            let Some(this) = this.upgrade() else {
+                return;
+            };
+            match result.map(|health| health.update_available) {
+                Ok(Some(update)) => {
+                    this.update_banner.set_text(&update_text(&update));
+                    this.update_banner.set_visible(true);
+                }
+                Ok(None) => this.update_banner.set_visible(false),
+                Err(e) => error!(error = %e, "Failed to check for updates"),
+            }
+        });
+    }
+
+    fn show_policy(&self, policy: PolicyView) {
+        while let Some(child) = self.entry_list.first_child() {
+            self.entry_list.remove(&child);
+        }
+
+        self.entry_list.append(&list_label("Defaults"));
+        for entry in &policy.entries {
+            self.entry_list.append(&list_label(&entry.label));
+        }
+
+        let row = self.selected.get().map_or(0, |i| i as i32 + 1);
+        *self.policy.borrow_mut() = Some(policy);
+        self.entry_list.select_row(self.entry_list.row_at_index(row).as_ref());
+        // Selecting the same row again doesn't notify
+        self.select(row);
+    }
+
+    fn select(&self, row: i32) {
+        let policy = self.policy.borrow();
+        let Some(policy) = policy.as_ref() else {
+            return;
+        };
+
+        let entry = usize::try_from(row - 1)
+            .ok()
+            .and_then(|i| policy.entries.get(i).map(|e| (i, e)));
+        match entry {
+            Some((index, entry)) => {
+                self.selected.set(Some(index));
+                self.entry_editor.load(entry);
+                self.editors.set_visible_child_name("entry");
+            }
+            None => {
+                self.selected.set(None);
+                self.defaults_editor.load(policy);
+                self.editors.set_visible_child_name("defaults");
+            }
+        }
+    }
+
+    fn current_ops(&self) -> Result<Vec<PolicyPatch>, String> {
+        match self.selected.get() {
+            Some(_) => self.entry_editor.ops(),
+            None => self.defaults_editor.ops(),
+        }
+    }
+
+    fn apply(self: &Rc<Self>) {
+        let ops = match self.current_ops() {
+            Ok(ops) => ops,
+            Err(message) => {
+                self.status.set_text(&message);
+                return;
+            }
+        };
+
+        self.apply_button.set_sensitive(false);
+        self.status.set_text("Saving...");
+
+        let client = self.client.clone();
+        let this = Rc::downgrade(self);
+        self.spawn(async move { client.patch_policy(ops).await }, move |result| {
+            let Some(this) = this.upgrade() else {
+                return;
+            };
+            this.apply_button.set_sensitive(true);
+            match result {
+                Ok(policy) => {
+                    info!("Policy saved");
+                    this.status.set_text("Saved");
+                    this.show_policy(policy);
+                }
+                Err(e) => {
+                    error!(error = %e, "Failed to save policy");
+                    this.status.set_text(&format!("Could not save: {}", e));
+                }
+            }
+        });
+    }
+
+    /// Refresh the preview once edits have settled
+    fn schedule_preview(self: &Rc<Self>) {
+        let generation = self.preview_generation.get() + 1;
+        self.preview_generation.set(generation);
+
+        let this = Rc::downgrade(self);
+        glib::timeout_add_local_once(PREVIEW_DELAY, move || {
+            if let Some(this) = this.upgrade()
+                && this.preview_generation.get() == generation
+            {
+                this.run_preview();
+            }
+        });
+    }
+
+    fn run_preview(self: &Rc<Self>) {
+        let ops = match self.current_ops() {
+            Ok(ops) => ops,
+            Err(message) => {
+                self.show_preview(Err(message));
+                return;
+            }
+        };
+        let at_time = preview_time(
+            shepherd_util::now(),
+            self.preview_day.selected(),
+            self.preview_hour.value_as_int() as u32,
+            self.preview_minute.value_as_int() as u32,
+        );
+
+        let client = self.client.clone();
+        let this = Rc::downgrade(self);
+        self.spawn(
+            async move { client.simulate_policy(ops, at_time).await },
+            move |result| {
+                if let Some(this) = this.upgrade() {
+                    this.show_preview(result.map_err(|e| e.to_string()));
+                }
+            },
+        );
+    }
+
+    fn show_preview(&self, result: Result<Vec<EntryView>, String>) {
+        while let Some(child) = self.preview_list.first_child() {
+            self.preview_list.remove(&child);
+        }
+
+        match result {
+            Ok(entries) => {
+                for entry in entries {
+                    self.preview_list.append(&list_label(&preview_text(&entry)));
+                }
+            }
+            Err(message) => self.preview_list.append(&list_label(&message)),
+        }
+    }
+}
+
+fn list_label(text: &str) -> gtk4::Label {
+    let label = gtk4::Label::new(Some(text));
+    label.set_halign(gtk4::Align::Start);
+    label.set_margin_top(6);
+    label.set_margin_bottom(6);
+    label.set_margin_start(12);
+    label.set_margin_end(12);
+    label
+}
+
+fn update_text(update: &UpdateInfo) -> String {
+    if update.staged {
+        format!(
+            "Shepherd {} is downloaded and will be installed when nothing is running (now {})",
+            update.available_version, update.current_version
+        )
+    } else {
+        format!(
+            "Shepherd {} is available (now {})",
+            update.available_version, update.current_version
+        )
+    }
+}
+
+fn preview_text(entry: &EntryView) -> String {
+    if entry.enabled {
+        match entry.max_run_if_started_now {
+            Some(max_run) => format!("✓ {} — up to {} min", entry.label, max_run.as_secs() / 60),
+            None => format!("✓ {} — no limit", entry.label),
+        }
+    } else {
+        let locale = Locale::from_env();
+        let reasons: Vec<_> = entry.reasons.iter().map(|r| r.message(locale)).collect();
+        format!("✗ {} — {}", entry.label, reasons.join(", "))
+    }
+}
@@ -0,0 +1,60 @@
+//! IPC client wrapper for the admin UI
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use shepherd_api::{Command, EntryView, HealthStatus, PolicyPatch, PolicyView, ResponsePayload, ResponseResult};
+use shepherd_ipc::IpcClient;
+use std::path::{Path, PathBuf};
+
+/// Sends one command per connection, like the launcher's command client
+pub struct AdminClient {
+    socket_path: PathBuf,
+}
+
+impl AdminClient {
+    pub fn new(socket_path: impl AsRef<Path>) -> Self {
+        Self {
+            socket_path: socket_path.as_ref().to_path_buf(),
+        }
+    }
+
+    async fn send(&self, command: Command) -> Result<ResponsePayload> {
+        let mut client = IpcClient::connect(&self.socket_path).await?;
+        match client.send(command).await?.result {
+            ResponseResult::Ok(payload) => Ok(payload),
+            ResponseResult::Err(e) => anyhow::bail!("{}", e.message),
+        }
+    }
+
+    pub async fn get_policy(&self) -> Result<PolicyView> {
+        match self.send(Command::GetPolicy).await? {
+            ResponsePayload::Policy(policy) => Ok(policy),
+            other => anyhow::bail!("Unexpected response: {:?}", other),
+        }
+    }
+
+    pub async fn get_health(&self) -> Result<HealthStatus> {
+        match self.send(Command::GetHealth).await? {
+            ResponsePayload::Health(health) => Ok(health),
+            other => anyhow::bail!("Unexpected response: {:?}", other),
+        }
+    }
+
+    pub async fn patch_policy(&self, ops: Vec<PolicyPatch>) -> Result<PolicyView> {
+        match self.send(Command::PatchPolicy { ops }).await? {
+            ResponsePayload::Policy(policy) => Ok(policy),
+            other => anyhow::bail!("Unexpected response: {:?}", other),
+        }
+    }
+
+    pub async fn simulate_policy(
+        &self,
+        ops: Vec<PolicyPatch>,
+        at_time: Option<DateTime<Local>>,
+    ) -> Result<Vec<EntryView>> {
+        match self.send(Command::SimulatePolicy { ops, at_time }).await? {
+            ResponsePayload::Entries { entries } => Ok(entries),
+            other => anyhow::bail!("Unexpected response: {:?}", other),
+        }
+    }
+}
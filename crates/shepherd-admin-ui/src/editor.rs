@@ -0,0 +1,266 @@
+//! Editor forms for entry policy and service defaults
+//!
+//! Each form turns its fields into a full set of PolicyPatch operations, so
+//! applying the same form twice is harmless.
+
+use gtk4::prelude::*;
+use shepherd_api::{EntryPolicyView, PolicyPatch, PolicyView, WarningThreshold};
+use shepherd_util::EntryId;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::form::{
+    duration_minutes, format_warnings, format_windows, minutes_duration, parse_warnings,
+    parse_windows,
+};
+
+/// Callback invoked whenever a field changes
+type ChangedCallback = Rc<dyn Fn()>;
+
+/// Form for one entry's availability, limits and warnings
+pub struct EntryEditor {
+    pub root: gtk4::Grid,
+    entry_id: RefCell<Option<EntryId>>,
+    disabled_reason: RefCell<Option<String>>,
+    previous_warnings: RefCell<Vec<WarningThreshold>>,
+    label: gtk4::Entry,
+    enabled: gtk4::Switch,
+    windows: gtk4::TextView,
+    inherit_max_run: gtk4::CheckButton,
+    max_run: gtk4::SpinButton,
+    daily_quota: gtk4::SpinButton,
+    cooldown: gtk4::SpinButton,
+    inherit_warnings: gtk4::CheckButton,
+    warnings: gtk4::Entry,
+}
+
+impl EntryEditor {
+    pub fn new() -> Self {
+        let editor = Self {
+            root: form_grid(),
+            entry_id: RefCell::new(None),
+            disabled_reason: RefCell::new(None),
+            previous_warnings: RefCell::new(Vec::new()),
+            label: gtk4::Entry::new(),
+            enabled: gtk4::Switch::new(),
+            windows: gtk4::TextView::new(),
+            inherit_max_run: gtk4::CheckButton::with_label("Use default"),
+            max_run: minutes_spin(600.0),
+            daily_quota: minutes_spin(1440.0),
+            cooldown: minutes_spin(1440.0),
+            inherit_warnings: gtk4::CheckButton::with_label("Use default"),
+            warnings: gtk4::Entry::new(),
+        };
+
+        editor.enabled.set_halign(gtk4::Align::Start);
+        editor.windows.set_monospace(true);
+        editor.windows.set_height_request(96);
+        editor.warnings.set_placeholder_text(Some("Minutes before time is up, e.g. 5, 1"));
+
+        let grid = &editor.root;
+        attach_row(grid, 0, "Name", &editor.label);
+        attach_row(grid, 1, "Enabled", &editor.enabled);
+        attach_row(grid, 2, "Available", &editor.windows);
+        attach_hint(grid, 3, "One window per line, e.g. \"weekdays 15:00-18:00\". Empty means always.");
+        attach_row(grid, 4, "Max session (min)", &with_toggle(&editor.max_run, &editor.inherit_max_run));
+        attach_hint(grid, 5, "0 means no limit");
+        attach_row(grid, 6, "Daily limit (min)", &editor.daily_quota);
+        attach_hint(grid, 7, "0 means no limit");
+        attach_row(grid, 8, "Cooldown (min)", &editor.cooldown);
+        attach_row(grid, 9, "Warnings", &with_toggle(&editor.warnings, &editor.inherit_warnings));
+
+        let max_run = editor.max_run.clone();
+        editor
+            .inherit_max_run
+            .connect_toggled(move |check| max_run.set_sensitive(!check.is_active()));
+        let warnings = editor.warnings.clone();
+        editor
+            .inherit_warnings
+            .connect_toggled(move |check| warnings.set_sensitive(!check.is_active()));
+
+        editor
+    }
+
+    /// Show an entry's settings
+    pub fn load(&self, entry: &EntryPolicyView) {
+        *self.entry_id.borrow_mut() = Some(entry.entry_id.clone());
+        *self.disabled_reason.borrow_mut() = entry.disabled_reason.clone();
+        *self.previous_warnings.borrow_mut() = entry.warnings.clone().unwrap_or_default();
+
+        self.label.set_text(&entry.label);
+        self.enabled.set_active(!entry.disabled);
+        self.windows.buffer().set_text(&format_windows(&entry.windows));
+        self.inherit_max_run.set_active(entry.max_run.is_none());
+        self.max_run.set_value(duration_minutes(entry.max_run));
+        self.daily_quota.set_value(duration_minutes(entry.daily_quota));
+        self.cooldown.set_value(duration_minutes(entry.cooldown));
+        self.inherit_warnings.set_active(entry.warnings.is_none());
+        self.warnings
+            .set_text(&format_warnings(entry.warnings.as_deref().unwrap_or_default()));
+    }
+
+    /// Edits that make the live policy match the form
+    pub fn ops(&self) -> Result<Vec<PolicyPatch>, String> {
+        let Some(entry_id) = self.entry_id.borrow().clone() else {
+            return Ok(Vec::new());
+        };
+
+        let label = self.label.text().trim().to_string();
+        if label.is_empty() {
+            return Err("The name can't be empty".into());
+        }
+
+        let buffer = self.windows.buffer();
+        let windows = parse_windows(&buffer.text(&buffer.start_iter(), &buffer.end_iter(), false))?;
+
+        let max_run = (!self.inherit_max_run.is_active()).then(|| {
+            std::time::Duration::from_secs(self.max_run.value_as_int().max(0) as u64 * 60)
+        });
+
+        let warnings = if self.inherit_warnings.is_active() {
+            None
+        } else {
+            Some(parse_warnings(&self.warnings.text(), &self.previous_warnings.borrow())?)
+        };
+
+        let disabled = !self.enabled.is_active();
+        Ok(vec![
+            PolicyPatch::SetLabel {
+                entry_id: entry_id.clone(),
+                label,
+            },
+            PolicyPatch::SetDisabled {
+                entry_id: entry_id.clone(),
+                disabled,
+                reason: self.disabled_reason.borrow().clone(),
+            },
+            PolicyPatch::SetWindows {
+                entry_id: entry_id.clone(),
+                windows,
+            },
+            PolicyPatch::SetLimits {
+                entry_id: entry_id.clone(),
+                max_run,
+                daily_quota: minutes_duration(self.daily_quota.value_as_int()),
+                cooldown: minutes_duration(self.cooldown.value_as_int()),
+            },
+            PolicyPatch::SetWarnings { entry_id, warnings },
+        ])
+    }
+
+    /// Call `callback` whenever a field changes
+    pub fn connect_changed<F: Fn() + 'static>(&self, callback: F) {
+        let callback: ChangedCallback = Rc::new(callback);
+
+        for entry in [&self.label, &self.warnings] {
+            let callback = callback.clone();
+            entry.connect_changed(move |_| callback());
+        }
+        for check in [&self.inherit_max_run, &self.inherit_warnings] {
+            let callback = callback.clone();
+            check.connect_toggled(move |_| callback());
+        }
+        for spin in [&self.max_run, &self.daily_quota, &self.cooldown] {
+            let callback = callback.clone();
+            spin.connect_value_changed(move |_| callback());
+        }
+        let cb = callback.clone();
+        self.enabled.connect_active_notify(move |_| cb());
+        self.windows.buffer().connect_changed(move |_| callback());
+    }
+}
+
+/// Form for the service-wide defaults
+pub struct DefaultsEditor {
+    pub root: gtk4::Grid,
+    previous_warnings: RefCell<Vec<WarningThreshold>>,
+    max_run: gtk4::SpinButton,
+    warnings: gtk4::Entry,
+}
+
+impl DefaultsEditor {
+    pub fn new() -> Self {
+        let editor = Self {
+            root: form_grid(),
+            previous_warnings: RefCell::new(Vec::new()),
+            max_run: minutes_spin(600.0),
+            warnings: gtk4::Entry::new(),
+        };
+
+        editor.warnings.set_placeholder_text(Some("Minutes before time is up, e.g. 5, 1"));
+
+        let grid = &editor.root;
+        attach_row(grid, 0, "Max session (min)", &editor.max_run);
+        attach_hint(grid, 1, "Used by entries without their own. 0 means no limit.");
+        attach_row(grid, 2, "Warnings", &editor.warnings);
+        attach_hint(grid, 3, "Used by entries without their own");
+
+        editor
+    }
+
+    pub fn load(&self, policy: &PolicyView) {
+        *self.previous_warnings.borrow_mut() = policy.default_warnings.clone();
+        self.max_run.set_value(duration_minutes(policy.default_max_run));
+        self.warnings.set_text(&format_warnings(&policy.default_warnings));
+    }
+
+    pub fn ops(&self) -> Result<Vec<PolicyPatch>, String> {
+        Ok(vec![
+            PolicyPatch::SetDefaultMaxRun {
+                max_run: minutes_duration(self.max_run.value_as_int()),
+            },
+            PolicyPatch::SetDefaultWarnings {
+                warnings: parse_warnings(&self.warnings.text(), &self.previous_warnings.borrow())?,
+            },
+        ])
+    }
+
+    pub fn connect_changed<F: Fn() + 'static>(&self, callback: F) {
+        let callback: ChangedCallback = Rc::new(callback);
+        let cb = callback.clone();
+        self.max_run.connect_value_changed(move |_| cb());
+        self.warnings.connect_changed(move |_| callback());
+    }
+}
+
+fn form_grid() -> gtk4::Grid {
+    let grid = gtk4::Grid::new();
+    grid.set_row_spacing(8);
+    grid.set_column_spacing(16);
+    grid.set_margin_top(16);
+    grid.set_margin_bottom(16);
+    grid.set_margin_start(16);
+    grid.set_margin_end(16);
+    grid
+}
+
+fn minutes_spin(max: f64) -> gtk4::SpinButton {
+    let spin = gtk4::SpinButton::with_range(0.0, max, 5.0);
+    spin.set_digits(0);
+    spin.set_halign(gtk4::Align::Start);
+    spin
+}
+
+fn with_toggle(field: &impl IsA<gtk4::Widget>, toggle: &gtk4::CheckButton) -> gtk4::Box {
+    let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 12);
+    row.append(field);
+    row.append(toggle);
+    row
+}
+
+fn attach_row(grid: &gtk4::Grid, row: i32, title: &str, field: &impl IsA<gtk4::Widget>) {
+    let label = gtk4::Label::new(Some(title));
+    label.set_halign(gtk4::Align::End);
+    label.set_valign(gtk4::Align::Start);
+    grid.attach(&label, 0, row, 1, 1);
+    field.set_hexpand(true);
+    grid.attach(field, 1, row, 1, 1);
+}
+
+fn attach_hint(grid: &gtk4::Grid, row: i32, text: &str) {
+    let hint = gtk4::Label::new(Some(text));
+    hint.add_css_class("dim-label");
+    hint.set_halign(gtk4::Align::Start);
+    hint.set_wrap(true);
+    grid.attach(&hint, 1, row, 1, 1);
+}
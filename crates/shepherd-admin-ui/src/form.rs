@@ -0,0 +1,146 @@
+//! Conversions between editor text fields and policy values
+
+use chrono::{DateTime, Duration as ChronoDuration, Local, NaiveTime, TimeZone};
+use shepherd_api::{WarningSeverity, WarningThreshold};
+use shepherd_config::{format_window, parse_window};
+use shepherd_util::TimeWindow;
+use std::time::Duration;
+
+/// Windows one per line, e.g. "weekdays 15:00-18:00"
+pub fn format_windows(windows: &[TimeWindow]) -> String {
+    windows.iter().map(format_window).collect::<Vec<_>>().join("\n")
+}
+
+/// Parse windows one per line. No lines means always available.
+pub fn parse_windows(text: &str) -> Result<Vec<TimeWindow>, String> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_window)
+        .collect()
+}
+
+/// Warnings as minutes before expiry, e.g. "5, 1"
+pub fn format_warnings(warnings: &[WarningThreshold]) -> String {
+    warnings
+        .iter()
+        .map(|w| format_minutes(w.seconds_before))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Parse warnings as minutes before expiry.
+///
+/// Thresholds that already existed keep their severity and message; new
+/// ones are critical when they're the last warning and plain warnings
+/// otherwise.
+pub fn parse_warnings(
+    text: &str,
+    previous: &[WarningThreshold],
+) -> Result<Vec<WarningThreshold>, String> {
+    let mut seconds = text
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse::<f64>()
+                .ok()
+                .filter(|m| *m > 0.0)
+                .map(|m| (m * 60.0).round() as u64)
+                .ok_or_else(|| format!("'{}' is not a number of minutes", s))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    seconds.sort_unstable_by(|a, b| b.cmp(a));
+    seconds.dedup();
+
+    let last = seconds.last().copied();
+    Ok(seconds
+        .into_iter()
+        .map(|seconds_before| {
+            previous
+                .iter()
+                .find(|w| w.seconds_before == seconds_before)
+                .cloned()
+                .unwrap_or(WarningThreshold {
+                    seconds_before,
+                    severity: if Some(seconds_before) == last {
+                        WarningSeverity::Critical
+                    } else {
+                        WarningSeverity::Warn
+                    },
+                    message_template: None,
+                })
+        })
+        .collect())
+}
+
+fn format_minutes(seconds: u64) -> String {
+    if seconds.is_multiple_of(60) {
+        (seconds / 60).to_string()
+    } else {
+        format!("{:.1}", seconds as f64 / 60.0)
+    }
+}
+
+/// Whole minutes for a spin button
+pub fn duration_minutes(duration: Option<Duration>) -> f64 {
+    duration.map_or(0.0, |d| (d.as_secs() / 60) as f64)
+}
+
+/// Duration from a spin button value in minutes; zero becomes None
+pub fn minutes_duration(minutes: i32) -> Option<Duration> {
+    (minutes > 0).then(|| Duration::from_secs(minutes as u64 * 60))
+}
+
+/// Preview time `days_ahead` days from `now` at the given wall-clock time
+pub fn preview_time(
+    now: DateTime<Local>,
+    days_ahead: u32,
+    hour: u32,
+    minute: u32,
+) -> Option<DateTime<Local>> {
+    let date = now.date_naive() + ChronoDuration::days(days_ahead as i64);
+    let time = NaiveTime::from_hms_opt(hour, minute, 0)?;
+    // Skipped by a DST change: preview an hour later
+    Local
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .or_else(|| {
+            Local
+                .from_local_datetime(&date.and_time(time + ChronoDuration::hours(1)))
+                .earliest()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, Timelike};
+
+    #[test]
+    fn test_parse_warnings() {
+        let previous = vec![WarningThreshold {
+            seconds_before: 300,
+            severity: WarningSeverity::Info,
+            message_template: Some("Five minutes left".into()),
+        }];
+
+        let warnings = parse_warnings("1, 5, 0.5", &previous).unwrap();
+        let seconds: Vec<_> = warnings.iter().map(|w| w.seconds_before).collect();
+        assert_eq!(seconds, vec![300, 60, 30]);
+        assert_eq!(warnings[0].severity, WarningSeverity::Info);
+        assert_eq!(warnings[1].severity, WarningSeverity::Warn);
+        assert_eq!(warnings[2].severity, WarningSeverity::Critical);
+        assert_eq!(format_warnings(&warnings), "5, 1, 0.5");
+
+        assert!(parse_warnings("soon", &[]).is_err());
+        assert!(parse_warnings("", &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_preview_time() {
+        let now = shepherd_util::now();
+        let at = preview_time(now, 1, 15, 30).unwrap();
+        assert_eq!(at.date_naive().day(), (now.date_naive() + ChronoDuration::days(1)).day());
+        assert_eq!((at.hour(), at.minute()), (15, 30));
+    }
+}
@@ -0,0 +1,53 @@
+//! Shepherd Admin - Parent-facing settings app
+//!
+//! Edits entries, availability windows, limits and warnings through the
+//! shepherdd GetPolicy/PatchPolicy commands, with a live preview of which
+//! entries would be available.
+
+mod app;
+mod client;
+mod editor;
+mod form;
+
+use anyhow::Result;
+use clap::Parser;
+use shepherd_util::default_socket_path;
+use std::path::PathBuf;
+use tracing_subscriber::EnvFilter;
+
+/// Shepherd Admin - Edit shepherdd policy
+#[derive(Parser, Debug)]
+#[command(name = "shepherd-admin")]
+#[command(about = "GTK4 settings app for shepherdd", long_about = None)]
+struct Args {
+    /// Socket path for shepherdd connection (or set SHEPHERD_SOCKET env var)
+    #[arg(short, long, env = "SHEPHERD_SOCKET")]
+    socket: Option<PathBuf>,
+
+    /// Log level
+    #[arg(short, long, default_value = "info")]
+    log_level: String,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    // Initialize logging
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new(&args.log_level)),
+        )
+        .init();
+
+    tracing::info!("Starting Shepherd Admin");
+
+    // Determine socket path with fallback to default
+    let socket_path = args.socket.unwrap_or_else(default_socket_path);
+
+    // Run GTK application
+    let application = app::AdminApp::new(socket_path);
+    let exit_code = application.run();
+
+    std::process::exit(exit_code);
+}
@@ -1,14 +1,15 @@
 //! Command types for the shepherdd protocol
 
 use chrono::{DateTime, Local};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use shepherd_util::{ClientId, EntryId};
+use shepherd_util::{ClientId, EntryId, ProfileId, SeatId};
 use std::time::Duration;
 
-use crate::{ClientRole, StopMode, API_VERSION};
+use crate::{ClientKind, ClientRole, SpawnDiagnostics, StopMode, API_VERSION};
 
 /// Request wrapper with metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Request {
     /// Request ID for correlation
     pub request_id: u64,
@@ -29,7 +30,7 @@ impl Request {
 }
 
 /// Response wrapper
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Response {
     /// Corresponding request ID
     pub request_id: u64,
@@ -57,7 +58,9 @@ impl Response {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Responses are short-lived wire messages; boxing the state snapshot isn't worth it
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ResponseResult {
     Ok(ResponsePayload),
@@ -65,10 +68,13 @@ pub enum ResponseResult {
 }
 
 /// Error information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ErrorInfo {
     pub code: ErrorCode,
     pub message: String,
+    /// Why the session couldn't be started, for failed launches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn: Option<SpawnDiagnostics>,
 }
 
 impl ErrorInfo {
@@ -76,12 +82,18 @@ impl ErrorInfo {
         Self {
             code,
             message: message.into(),
+            spawn: None,
         }
     }
+
+    pub fn with_spawn_diagnostics(mut self, diagnostics: Option<SpawnDiagnostics>) -> Self {
+        self.spawn = diagnostics;
+        self
+    }
 }
 
 /// Error codes for the protocol
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ErrorCode {
     InvalidRequest,
@@ -94,30 +106,78 @@ pub enum ErrorCode {
     ConfigError,
     HostError,
     InternalError,
+    /// A session, device or record that doesn't exist
+    NotFound,
+    /// Not possible on this host
+    Unsupported,
+    /// Temporarily unavailable; the same request may succeed later
+    Unavailable,
+    /// The usage store failed or holds unreadable data
+    StorageError,
+}
+
+impl ErrorCode {
+    /// Whether retrying the same request later can succeed
+    pub fn is_retryable(self) -> bool {
+        matches!(self, ErrorCode::RateLimited | ErrorCode::Unavailable)
+    }
 }
 
 /// All possible commands from clients
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Command {
-    /// Get current service state
-    GetState,
+    /// Get current service state. Admins can ask about another `seat`
+    /// than their own.
+    GetState {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seat: Option<SeatId>,
+    },
 
     /// List available entries
     ListEntries {
         /// Optional: evaluate at a specific time (for preview)
+        #[schemars(with = "Option<crate::encoding::Timestamp>")]
+        #[serde(default, with = "crate::encoding::option_rfc3339")]
         at_time: Option<DateTime<Local>>,
     },
 
     /// Request to launch an entry
-    Launch { entry_id: EntryId },
+    Launch {
+        entry_id: EntryId,
+        /// Profiles sharing a joint session (admin only). Empty for a normal launch.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        participants: Vec<ProfileId>,
+        /// Unique per launch attempt, and reused when retrying it. A retry
+        /// within a few minutes gets the original response instead of
+        /// launching again.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        idempotency_key: Option<String>,
+    },
+
+    /// Go ahead with a launch answered by `LaunchNeedsConfirmation`
+    ConfirmLaunch { session_id: shepherd_util::SessionId },
 
-    /// Stop the current session
-    StopCurrent { mode: StopMode },
+    /// Stop the current session. Admins can stop one on another `seat`.
+    StopCurrent {
+        mode: StopMode,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seat: Option<SeatId>,
+    },
 
     /// Reload configuration
     ReloadConfig,
 
+    /// Say which program this client is and which seat it serves (default
+    /// `seat0`). A newer launcher, HUD or shell takes over from an older
+    /// instance on the same seat still connected, whose connections are
+    /// demoted to observers.
+    Identify {
+        kind: ClientKind,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seat: Option<SeatId>,
+    },
+
     /// Subscribe to events (returns immediately, events stream separately)
     SubscribeEvents,
 
@@ -127,6 +187,9 @@ pub enum Command {
     /// Get health status
     GetHealth,
 
+    /// What this shepherdd and its host support, and whether it's out of date
+    GetCapabilities,
+
     // Volume control commands
 
     /// Get current volume status
@@ -141,38 +204,167 @@ pub enum Command {
     /// Set mute state explicitly
     SetMute { muted: bool },
 
+    /// List paired/connected input devices (e.g., Bluetooth controllers)
+    GetInputDevices,
+
+    /// Get the host battery status
+    GetBattery,
+
+    /// Get what the running session's media player is playing
+    GetMedia,
+
+    /// Get what happened during a session, oldest first
+    GetSessionTimeline { session_id: shepherd_util::SessionId },
+
+    /// Get today's usage per entry and the windows still to come
+    GetDayOverview,
+
+    /// Answer the post-session feelings check-in. `None` skips it.
+    SubmitCheckIn {
+        session_id: shepherd_util::SessionId,
+        mood: Option<crate::Mood>,
+    },
+
+    /// Cancel a pending curfew shutdown/suspend countdown
+    CancelPowerAction,
+
+    /// Give keyboard and mouse back during a controller-only session.
+    /// Admins need no PIN; anyone else needs the admin PIN.
+    ReleaseInput {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pin: Option<String>,
+    },
+
     // Admin commands
 
-    /// Extend the current session (admin only)
-    ExtendCurrent { by: Duration },
+    /// Extend the current session (admin only), on the admin's own seat
+    /// unless `seat` is given
+    ExtendCurrent {
+        #[schemars(with = "crate::encoding::DurationMs")]
+        #[serde(with = "crate::encoding::duration_ms")]
+        by: Duration,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seat: Option<SeatId>,
+    },
+
+    /// Grant reward stars (admin only)
+    GrantReward {
+        stars: u32,
+        reason: Option<String>,
+    },
+
+    /// Re-probe every entry and disable broken ones (admin only)
+    CheckEntryHealth,
+
+    /// Block or allow all launches (admin only). A running session is left
+    /// alone. `message` and `until` only apply when enabling.
+    SetHold {
+        enabled: bool,
+        message: Option<String>,
+        #[schemars(with = "Option<crate::encoding::Timestamp>")]
+        #[serde(default, with = "crate::encoding::option_rfc3339")]
+        until: Option<DateTime<Local>>,
+    },
+
+    /// List apps installed on the host, for first-run setup (admin only)
+    DiscoverApps,
+
+    /// List the clients connected to shepherdd (admin only)
+    ListClients,
+
+    /// Validate a config file, write it to the config path and load it (admin only)
+    SaveConfig { config: String },
+
+    /// Get the editable policy settings (admin only)
+    GetPolicy,
+
+    /// Apply policy edits, save them to the config file and load them (admin only)
+    PatchPolicy { ops: Vec<crate::PolicyPatch> },
+
+    /// Evaluate entries as if the edits were applied, without saving (admin only)
+    SimulatePolicy {
+        ops: Vec<crate::PolicyPatch>,
+        /// Evaluate at this time instead of now
+        #[schemars(with = "Option<crate::encoding::Timestamp>")]
+        #[serde(default, with = "crate::encoding::option_rfc3339")]
+        at_time: Option<DateTime<Local>>,
+    },
+
+    /// Add usage history exported from another parental control (admin
+    /// only). Totals are kept per `source`, so importing the same export
+    /// again adds nothing.
+    ImportUsage {
+        /// Where the usage comes from, like "family-link"
+        source: String,
+        /// Profile to charge the usage to as well
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        profile_id: Option<ProfileId>,
+        usage: Vec<crate::ImportedUsage>,
+    },
+
+    /// Check the audit log's hash chain (admin only). `known_head` is a
+    /// head hash from an earlier check, to confirm the log still has it.
+    VerifyAudit {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        known_head: Option<String>,
+    },
+
+    /// Set, advance, freeze or reset the mock clock (admin only, debug builds)
+    SetMockTime { op: crate::MockTimeOp },
 
     /// Ping for keepalive
     Ping,
 }
 
 /// Response payloads
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ResponsePayload {
     State(crate::ServiceStateSnapshot),
-    Entries(Vec<crate::EntryView>),
+    Entries { entries: Vec<crate::EntryView> },
     LaunchApproved {
         session_id: shepherd_util::SessionId,
         /// Deadline for the session. None means unlimited.
+        #[schemars(with = "Option<crate::encoding::Timestamp>")]
+        #[serde(default, with = "crate::encoding::option_rfc3339")]
         deadline: Option<DateTime<Local>>,
     },
     LaunchDenied {
         reasons: Vec<crate::ReasonCode>,
     },
+    /// The entry asks before starting. Nothing runs until the client sends
+    /// `ConfirmLaunch` with this session ID.
+    LaunchNeedsConfirmation {
+        session_id: shepherd_util::SessionId,
+        entry_id: EntryId,
+        label: String,
+        /// Time the session will get. None means unlimited.
+        #[schemars(with = "Option<crate::encoding::DurationMs>")]
+        #[serde(default, with = "crate::encoding::option_duration_ms")]
+        max_duration: Option<Duration>,
+        /// When the session would end if confirmed now
+        #[schemars(with = "Option<crate::encoding::Timestamp>")]
+        #[serde(default, with = "crate::encoding::option_rfc3339")]
+        deadline: Option<DateTime<Local>>,
+    },
     Stopped,
     ConfigReloaded,
     Subscribed {
         client_id: ClientId,
     },
+    /// The client's role from now on: `Observer` if a newer instance of
+    /// its kind has taken over
+    Identified {
+        role: ClientRole,
+    },
     Unsubscribed,
     Health(crate::HealthStatus),
+    Capabilities(crate::ServiceCapabilities),
     Extended {
         /// New deadline. None if session is unlimited (can't be extended).
+        #[schemars(with = "Option<crate::encoding::Timestamp>")]
+        #[serde(default, with = "crate::encoding::option_rfc3339")]
         new_deadline: Option<DateTime<Local>>,
     },
     Volume(crate::VolumeInfo),
@@ -180,11 +372,59 @@ pub enum ResponsePayload {
     VolumeDenied {
         reason: String,
     },
+    InputDevices {
+        devices: Vec<crate::InputDeviceInfo>,
+    },
+    PowerActionCancelled,
+    InputReleased,
+    CheckInRecorded,
+    RewardGranted {
+        total_stars: u32,
+    },
+    HoldSet {
+        hold: Option<crate::Hold>,
+    },
+    EntryHealth {
+        entries: Vec<crate::EntryHealth>,
+    },
+    DiscoveredApps {
+        apps: Vec<crate::DiscoveredApp>,
+    },
+    Clients {
+        clients: Vec<ClientInfo>,
+    },
+    ConfigSaved {
+        entry_count: usize,
+    },
+    Policy(crate::PolicyView),
+    Battery {
+        battery: crate::BatteryInfo,
+        /// Below the configured warning threshold and not charging
+        low: bool,
+    },
+    MockTime {
+        /// Current time on the daemon's clock
+        #[schemars(with = "crate::encoding::Timestamp")]
+        #[serde(with = "crate::encoding::rfc3339")]
+        now: DateTime<Local>,
+        frozen: bool,
+    },
+    SessionTimeline(crate::SessionTimeline),
+    DayOverview(crate::DayOverview),
+    Media {
+        /// None when the session has no media player
+        media: Option<crate::MediaInfo>,
+    },
+    UsageImported {
+        /// Daily totals added or raised; zero for an export already imported
+        changed: usize,
+    },
+    AuditVerified(crate::AuditVerification),
     Pong,
 }
 
 /// Client connection info (set by IPC layer)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ClientInfo {
     pub client_id: ClientId,
     pub role: ClientRole,
@@ -192,6 +432,15 @@ pub struct ClientInfo {
     pub uid: Option<u32>,
     /// Process name if available
     pub process_name: Option<String>,
+    /// Process ID if available
+    #[serde(default)]
+    pub pid: Option<u32>,
+    /// What the client said it is with `Identify`
+    #[serde(default)]
+    pub kind: Option<ClientKind>,
+    /// The seat the client serves, from `Identify`. None means `seat0`.
+    #[serde(default)]
+    pub seat: Option<SeatId>,
 }
 
 impl ClientInfo {
@@ -201,6 +450,9 @@ impl ClientInfo {
             role,
             uid: None,
             process_name: None,
+            pid: None,
+            kind: None,
+            seat: None,
         }
     }
 
@@ -216,12 +468,37 @@ mod tests {
 
     #[test]
     fn request_serialization() {
-        let req = Request::new(1, Command::GetState);
+        let req = Request::new(1, Command::GetState { seat: None });
         let json = serde_json::to_string(&req).unwrap();
         let parsed: Request = serde_json::from_str(&json).unwrap();
 
         assert_eq!(parsed.request_id, 1);
-        assert!(matches!(parsed.command, Command::GetState));
+        assert!(matches!(parsed.command, Command::GetState { seat: None }));
+    }
+
+    #[test]
+    fn seat_defaults_to_none() {
+        let json = r#"{"request_id":1,"api_version":1,"command":{"type":"get_state"}}"#;
+        let parsed: Request = serde_json::from_str(json).unwrap();
+        assert!(matches!(parsed.command, Command::GetState { seat: None }));
+
+        let json = r#"{"request_id":1,"api_version":1,"command":{"type":"stop_current","mode":"force","seat":"seat1"}}"#;
+        let parsed: Request = serde_json::from_str(json).unwrap();
+        assert!(matches!(
+            parsed.command,
+            Command::StopCurrent { seat: Some(seat), .. } if seat == SeatId::new("seat1")
+        ));
+    }
+
+    #[test]
+    fn launch_participants_default_to_empty() {
+        let json = r#"{"request_id":1,"api_version":1,"command":{"type":"launch","entry_id":"game"}}"#;
+        let parsed: Request = serde_json::from_str(json).unwrap();
+
+        assert!(matches!(
+            parsed.command,
+            Command::Launch { ref participants, .. } if participants.is_empty()
+        ));
     }
 
     #[test]
@@ -231,9 +508,15 @@ mod tests {
             ResponsePayload::State(crate::ServiceStateSnapshot {
                 api_version: API_VERSION,
                 policy_loaded: true,
+                seat: Default::default(),
                 current_session: None,
                 entry_count: 5,
                 entries: vec![],
+                pending_power_action: None,
+                pending_check_in: None,
+                total_stars: 0,
+                hold: None,
+                theme: crate::Theme::Dark,
             }),
         );
 
@@ -242,4 +525,17 @@ mod tests {
 
         assert_eq!(parsed.request_id, 1);
     }
+
+    #[test]
+    fn entries_response_serialization() {
+        // Internally tagged enums can't serialize a bare sequence
+        let resp = Response::success(1, ResponsePayload::Entries { entries: vec![] });
+        let json = serde_json::to_string(&resp).unwrap();
+        let parsed: Response = serde_json::from_str(&json).unwrap();
+
+        assert!(matches!(
+            parsed.result,
+            ResponseResult::Ok(ResponsePayload::Entries { ref entries }) if entries.is_empty()
+        ));
+    }
 }
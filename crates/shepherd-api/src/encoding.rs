@@ -0,0 +1,263 @@
+//! Serde helpers for the wire encoding of times
+//!
+//! Every duration in the API is an integer number of milliseconds and every
+//! timestamp an RFC 3339 string with its UTC offset, so clients in other
+//! languages don't need to know how Rust types serialize:
+//!
+//! ```json
+//! {"time_remaining": 90000, "deadline": "2025-06-02T16:30:00+02:00"}
+//! ```
+//!
+//! Durations written before this encoding (`{"secs": 90, "nanos": 0}`)
+//! are still read, so old audit records keep loading.
+//!
+//! [`DurationMs`] and [`Timestamp`] describe the encoding in the JSON
+//! Schema: pair each helper with `#[schemars(with = ...)]`.
+
+use chrono::{DateTime, Local, SecondsFormat};
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserializer, Serializer};
+use std::borrow::Cow;
+use std::fmt;
+use std::time::Duration;
+
+/// A `Duration` as integer milliseconds
+pub mod duration_ms {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis().try_into().unwrap_or(u64::MAX))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        deserializer.deserialize_any(DurationVisitor)
+    }
+}
+
+/// An `Option<Duration>` as integer milliseconds or null. Use with
+/// `#[serde(default)]` so a missing field reads as None.
+pub mod option_duration_ms {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(d) => duration_ms::serialize(d, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        deserializer.deserialize_option(OptionVisitor(DurationVisitor))
+    }
+}
+
+/// A `DateTime<Local>` as an RFC 3339 string
+pub mod rfc3339 {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(time: &DateTime<Local>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.to_rfc3339_opts(SecondsFormat::AutoSi, false))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Local>, D::Error> {
+        deserializer.deserialize_str(TimestampVisitor)
+    }
+}
+
+/// An `Option<DateTime<Local>>` as an RFC 3339 string or null. Use with
+/// `#[serde(default)]` so a missing field reads as None.
+pub mod option_rfc3339 {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        time: &Option<DateTime<Local>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(t) => rfc3339::serialize(t, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Local>>, D::Error> {
+        deserializer.deserialize_option(OptionVisitor(TimestampVisitor))
+    }
+}
+
+/// Schema of a duration encoded with [`duration_ms`]
+pub struct DurationMs;
+
+impl JsonSchema for DurationMs {
+    fn schema_name() -> Cow<'static, str> {
+        "DurationMs".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "integer",
+            "minimum": 0,
+            "description": "A duration in milliseconds"
+        })
+    }
+}
+
+/// Schema of a timestamp encoded with [`rfc3339`]
+pub struct Timestamp;
+
+impl JsonSchema for Timestamp {
+    fn schema_name() -> Cow<'static, str> {
+        "Timestamp".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "format": "date-time",
+            "description": "An RFC 3339 timestamp with a UTC offset"
+        })
+    }
+}
+
+struct DurationVisitor;
+
+impl<'de> Visitor<'de> for DurationVisitor {
+    type Value = Duration;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a duration in milliseconds")
+    }
+
+    fn visit_u64<E: de::Error>(self, ms: u64) -> Result<Duration, E> {
+        Ok(Duration::from_millis(ms))
+    }
+
+    fn visit_i64<E: de::Error>(self, ms: i64) -> Result<Duration, E> {
+        u64::try_from(ms)
+            .map(Duration::from_millis)
+            .map_err(|_| E::custom("duration must not be negative"))
+    }
+
+    /// The `{"secs": .., "nanos": ..}` form serde gives `Duration` by default
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Duration, A::Error> {
+        let mut secs = None;
+        let mut nanos = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "secs" => secs = Some(map.next_value::<u64>()?),
+                "nanos" => nanos = Some(map.next_value::<u32>()?),
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        let secs = secs.ok_or_else(|| de::Error::missing_field("secs"))?;
+        Ok(Duration::new(secs, nanos.unwrap_or(0)))
+    }
+}
+
+struct TimestampVisitor;
+
+impl<'de> Visitor<'de> for TimestampVisitor {
+    type Value = DateTime<Local>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an RFC 3339 timestamp")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<DateTime<Local>, E> {
+        DateTime::parse_from_rfc3339(s)
+            .map(|t| t.with_timezone(&Local))
+            .map_err(E::custom)
+    }
+}
+
+struct OptionVisitor<V>(V);
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for OptionVisitor<V> {
+    type Value = Option<V::Value>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.expecting(f)?;
+        f.write_str(" or null")
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self.0).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Times {
+        #[serde(with = "duration_ms")]
+        elapsed: Duration,
+        #[serde(default, with = "option_duration_ms")]
+        remaining: Option<Duration>,
+        #[serde(with = "rfc3339")]
+        started: DateTime<Local>,
+        #[serde(default, with = "option_rfc3339")]
+        deadline: Option<DateTime<Local>>,
+    }
+
+    #[test]
+    fn round_trip() {
+        let started = Local.with_ymd_and_hms(2025, 6, 2, 16, 0, 0).unwrap();
+        let times = Times {
+            elapsed: Duration::from_millis(90_500),
+            remaining: Some(Duration::from_secs(600)),
+            started,
+            deadline: None,
+        };
+
+        let json: serde_json::Value = serde_json::to_value(&times).unwrap();
+        assert_eq!(json["elapsed"], 90_500);
+        assert_eq!(json["remaining"], 600_000);
+        assert_eq!(json["deadline"], serde_json::Value::Null);
+        let started_json = json["started"].as_str().unwrap();
+        assert_eq!(
+            DateTime::parse_from_rfc3339(started_json).unwrap(),
+            started
+        );
+
+        let parsed: Times = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, times);
+    }
+
+    #[test]
+    fn reads_other_forms() {
+        // Old {secs, nanos} durations, a missing optional field, UTC times
+        let parsed: Times = serde_json::from_str(
+            r#"{"elapsed": {"secs": 90, "nanos": 500000000}, "started": "2025-06-02T14:00:00Z"}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.elapsed, Duration::from_millis(90_500));
+        assert_eq!(parsed.remaining, None);
+        assert_eq!(parsed.started, chrono::Utc.with_ymd_and_hms(2025, 6, 2, 14, 0, 0).unwrap());
+
+        assert!(serde_json::from_str::<Times>(r#"{"elapsed": -1, "started": "2025-06-02T14:00:00Z"}"#).is_err());
+        assert!(serde_json::from_str::<Times>(r#"{"elapsed": 1, "started": "yesterday"}"#).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+//! Error taxonomy shared by the host adapter, store and protocol
+//!
+//! Crates keep their own error types, but each one reports an [`ErrorKind`],
+//! and every kind maps to exactly one [`ErrorCode`]. Clients decide between
+//! retrying and showing the error from the code alone; tests assert on the
+//! kind instead of matching message text.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::ErrorCode;
+
+/// What kind of failure occurred, independent of where
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The session, device or record doesn't exist
+    NotFound,
+    /// This host can't do that at all (no backlight, no battery, no tool)
+    Unsupported,
+    /// Not allowed for this user or client
+    PermissionDenied,
+    /// The request or its arguments are wrong
+    InvalidInput,
+    /// Temporarily unavailable (busy, timed out, disconnected); worth retrying
+    Unavailable,
+    /// An external command ran and reported failure
+    CommandFailed,
+    /// Stored data couldn't be read back
+    Corrupt,
+    /// A bug or an unexpected state
+    Internal,
+}
+
+impl ErrorKind {
+    /// The protocol error code clients see for this kind
+    pub fn code(self) -> ErrorCode {
+        match self {
+            ErrorKind::NotFound => ErrorCode::NotFound,
+            ErrorKind::Unsupported => ErrorCode::Unsupported,
+            ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+            ErrorKind::InvalidInput => ErrorCode::InvalidRequest,
+            ErrorKind::Unavailable => ErrorCode::Unavailable,
+            ErrorKind::CommandFailed => ErrorCode::HostError,
+            ErrorKind::Corrupt => ErrorCode::StorageError,
+            ErrorKind::Internal => ErrorCode::InternalError,
+        }
+    }
+
+    pub fn is_retryable(self) -> bool {
+        self.code().is_retryable()
+    }
+
+    /// Classify an I/O error
+    pub fn from_io(error: &std::io::Error) -> Self {
+        use std::io::ErrorKind as Io;
+
+        match error.kind() {
+            Io::NotFound => ErrorKind::NotFound,
+            Io::PermissionDenied | Io::ReadOnlyFilesystem => ErrorKind::PermissionDenied,
+            Io::InvalidInput | Io::InvalidData => ErrorKind::InvalidInput,
+            Io::Unsupported => ErrorKind::Unsupported,
+            Io::TimedOut
+            | Io::WouldBlock
+            | Io::Interrupted
+            | Io::ConnectionRefused
+            | Io::ConnectionReset
+            | Io::ConnectionAborted
+            | Io::NotConnected
+            | Io::BrokenPipe
+            | Io::ResourceBusy => ErrorKind::Unavailable,
+            _ => ErrorKind::Internal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_to_code() {
+        assert_eq!(ErrorKind::Unavailable.code(), ErrorCode::Unavailable);
+        assert_eq!(ErrorKind::InvalidInput.code(), ErrorCode::InvalidRequest);
+        assert_eq!(ErrorKind::CommandFailed.code(), ErrorCode::HostError);
+
+        assert!(ErrorKind::Unavailable.is_retryable());
+        assert!(!ErrorKind::Unsupported.is_retryable());
+        assert!(!ErrorKind::Internal.is_retryable());
+    }
+
+    #[test]
+    fn test_from_io() {
+        let error = |kind| std::io::Error::new(kind, "test");
+        assert_eq!(ErrorKind::from_io(&error(std::io::ErrorKind::NotFound)), ErrorKind::NotFound);
+        assert_eq!(ErrorKind::from_io(&error(std::io::ErrorKind::TimedOut)), ErrorKind::Unavailable);
+        assert_eq!(ErrorKind::from_io(&error(std::io::ErrorKind::Other)), ErrorKind::Internal);
+    }
+}
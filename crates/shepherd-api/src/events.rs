@@ -1,17 +1,33 @@
 //! Event types for shepherdd -> client streaming
 
 use chrono::{DateTime, Local};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use shepherd_util::{EntryId, SessionId};
+use shepherd_util::{ClientId, EntryId, ProfileId, SeatId, SessionId};
 use std::time::Duration;
 
-use crate::{ServiceStateSnapshot, SessionEndReason, WarningSeverity, API_VERSION};
+use crate::{BatteryInfo, ClientKind, DeviceAccess, MediaInfo, PowerAction, ServiceStateSnapshot, SessionEndReason, SessionSummary, Theme, VolumeRestrictions, WarningSeverity, API_VERSION};
 
 /// Event envelope
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Event {
     pub api_version: u32,
+    /// When the event was emitted, by the service's clock
+    #[schemars(with = "crate::encoding::Timestamp")]
+    #[serde(with = "crate::encoding::rfc3339")]
     pub timestamp: DateTime<Local>,
+    /// Position in the service's event stream. Numbering starts at 1 each
+    /// time shepherdd starts, so a reconnecting client can tell which
+    /// events it has already seen; 0 means not numbered by the service.
+    #[serde(default)]
+    pub seq: u64,
+    /// The client request that caused this event, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation: Option<Correlation>,
+    /// The seat this event is about. Only that seat's clients and admins
+    /// get it; None goes to everyone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seat: Option<SeatId>,
     pub payload: EventPayload,
 }
 
@@ -20,13 +36,37 @@ impl Event {
         Self {
             api_version: API_VERSION,
             timestamp: shepherd_util::now(),
+            seq: 0,
+            correlation: None,
+            seat: None,
             payload,
         }
     }
+
+    /// Address the event to one seat's clients
+    pub fn for_seat(mut self, seat: Option<SeatId>) -> Self {
+        self.seat = seat;
+        self
+    }
+
+    /// Attribute the event to the request that caused it
+    pub fn caused_by(mut self, correlation: Option<Correlation>) -> Self {
+        self.correlation = correlation;
+        self
+    }
+}
+
+/// A request, identified by the client that sent it and its request ID.
+/// Logs for the request carry the same two fields.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Correlation {
+    pub client_id: ClientId,
+    pub request_id: u64,
 }
 
 /// All possible events from the service to clients
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum EventPayload {
     /// Full state snapshot (sent on subscribe and major changes)
@@ -38,18 +78,39 @@ pub enum EventPayload {
         entry_id: EntryId,
         label: String,
         /// Deadline for session. None means unlimited.
+        #[schemars(with = "Option<crate::encoding::Timestamp>")]
+        #[serde(default, with = "crate::encoding::option_rfc3339")]
         deadline: Option<DateTime<Local>>,
+        /// Camera/microphone access granted to the session
+        #[serde(default)]
+        device_access: DeviceAccess,
     },
 
     /// Warning issued for current session
     WarningIssued {
         session_id: SessionId,
         threshold_seconds: u64,
+        #[schemars(with = "crate::encoding::DurationMs")]
+        #[serde(with = "crate::encoding::duration_ms")]
         time_remaining: Duration,
         severity: WarningSeverity,
         message: Option<String>,
     },
 
+    /// Time left in the current session, by the service's count. Sent
+    /// every 30 seconds, every second in the last five minutes, and right
+    /// after the session is extended, so countdowns don't drift.
+    TimeRemaining {
+        session_id: SessionId,
+        #[schemars(with = "crate::encoding::DurationMs")]
+        #[serde(with = "crate::encoding::duration_ms")]
+        time_remaining: Duration,
+        /// When the session ends if it keeps running
+        #[schemars(with = "Option<crate::encoding::Timestamp>")]
+        #[serde(default, with = "crate::encoding::option_rfc3339")]
+        deadline: Option<DateTime<Local>>,
+    },
+
     /// Session is expiring (termination initiated)
     SessionExpiring {
         session_id: SessionId,
@@ -60,9 +121,37 @@ pub enum EventPayload {
         session_id: SessionId,
         entry_id: EntryId,
         reason: SessionEndReason,
+        #[schemars(with = "crate::encoding::DurationMs")]
+        #[serde(with = "crate::encoding::duration_ms")]
         duration: Duration,
     },
 
+    /// What the session that just ended used, sent right after
+    /// `SessionEnded`
+    SessionSummary(SessionSummary),
+
+    /// A newer instance of a client took over from process `pid`, which
+    /// can only observe from now on and should exit
+    ClientReplaced {
+        kind: ClientKind,
+        pid: u32,
+        by_pid: u32,
+    },
+
+    /// A profile used `percent` of its weekly budget; sent once as the
+    /// session that crossed the threshold ends, for parents
+    BudgetAlert {
+        profile_id: ProfileId,
+        label: String,
+        percent: u8,
+        #[schemars(with = "crate::encoding::DurationMs")]
+        #[serde(with = "crate::encoding::duration_ms")]
+        used: Duration,
+        #[schemars(with = "crate::encoding::DurationMs")]
+        #[serde(with = "crate::encoding::duration_ms")]
+        budget: Duration,
+    },
+
     /// Policy was reloaded
     PolicyReloaded {
         entry_count: usize,
@@ -80,6 +169,40 @@ pub enum EventPayload {
         muted: bool,
     },
 
+    /// The volume restrictions in effect changed, e.g. quiet hours started
+    VolumeRestrictionsChanged {
+        restrictions: VolumeRestrictions,
+    },
+
+    /// What the session's media player is playing changed. None when no
+    /// player is running.
+    MediaChanged {
+        media: Option<MediaInfo>,
+    },
+
+    /// Host battery status changed
+    BatteryStatus {
+        battery: BatteryInfo,
+        /// Below the configured warning threshold and not charging
+        low: bool,
+    },
+
+    /// Curfew shutdown/suspend countdown started
+    PowerActionScheduled {
+        action: PowerAction,
+        #[schemars(with = "crate::encoding::Timestamp")]
+        #[serde(with = "crate::encoding::rfc3339")]
+        deadline: DateTime<Local>,
+    },
+
+    /// Curfew countdown was cancelled (by a user, or because a session started)
+    PowerActionCancelled,
+
+    /// The configured launcher/HUD theme changed
+    ThemeChanged {
+        theme: Theme,
+    },
+
     /// Service is shutting down
     Shutdown,
 
@@ -101,6 +224,7 @@ mod tests {
             entry_id: EntryId::new("game-1"),
             label: "Test Game".into(),
             deadline: Some(shepherd_util::now()),
+            device_access: DeviceAccess::default(),
         });
 
         let json = serde_json::to_string(&event).unwrap();
@@ -108,6 +232,26 @@ mod tests {
 
         assert_eq!(parsed.api_version, API_VERSION);
         assert!(matches!(parsed.payload, EventPayload::SessionStarted { .. }));
+        assert!(!json.contains("correlation"));
+    }
+
+    #[test]
+    fn event_correlation() {
+        let correlation = Correlation {
+            client_id: ClientId::new(),
+            request_id: 7,
+        };
+        let event = Event::new(EventPayload::PowerActionCancelled).caused_by(Some(correlation.clone()));
+
+        let json = serde_json::to_string(&event).unwrap();
+        let parsed: Event = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.correlation, Some(correlation));
+
+        // Events from services that predate numbering still parse
+        let old = r#"{"api_version":1,"timestamp":"2025-06-02T16:00:00+00:00","payload":{"type":"power_action_cancelled"}}"#;
+        let parsed: Event = serde_json::from_str(old).unwrap();
+        assert_eq!(parsed.seq, 0);
+        assert!(parsed.correlation.is_none());
     }
 
     #[test]
@@ -118,6 +262,7 @@ mod tests {
             entry_id: EntryId::new("game-1"),
             label: "Unlimited Game".into(),
             deadline: None,
+            device_access: DeviceAccess::default(),
         });
 
         let json = serde_json::to_string(&event).unwrap();
@@ -4,14 +4,25 @@
 //! - Commands (requests from clients)
 //! - Responses
 //! - Events (service -> clients)
+//! - Error kinds shared across crates
+//! - Child-facing messages for reason codes
+//! - The JSON encoding of durations and timestamps
+//! - A JSON Schema of the protocol for clients in other languages
 //! - Versioning
 
 mod commands;
+pub mod encoding;
+mod error;
 mod events;
+mod messages;
+mod schema;
 mod types;
 
 pub use commands::*;
+pub use error::*;
 pub use events::*;
+pub use messages::*;
+pub use schema::*;
 pub use types::*;
 
 /// Current API version
@@ -0,0 +1,295 @@
+//! Child-facing wording for reason codes
+//!
+//! The launcher, HUD and admin tools all explain why something can't be
+//! played or why a session ended. They take their text from here so a
+//! reason reads the same everywhere and is translated in one place.
+
+use crate::{CooldownScope, ReasonCode, SessionEndReason};
+use chrono::{DateTime, Local};
+use std::time::Duration;
+
+/// Languages the catalog has wording for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    English,
+    German,
+}
+
+impl Locale {
+    /// Parse a POSIX locale name such as `de_DE.UTF-8`, or a bare
+    /// language code. Unknown languages fall back to English.
+    pub fn from_tag(tag: &str) -> Self {
+        let language = tag
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "de" => Locale::German,
+            _ => Locale::English,
+        }
+    }
+
+    /// The locale for messages, from `LC_ALL`, `LC_MESSAGES` or `LANG`
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .map(|value| Self::from_tag(&value))
+            .unwrap_or_default()
+    }
+}
+
+fn minutes(duration: Duration) -> u64 {
+    duration.as_secs().div_ceil(60)
+}
+
+fn clock(time: &DateTime<Local>) -> String {
+    time.format("%H:%M").to_string()
+}
+
+impl ReasonCode {
+    /// A short sentence explaining the reason to the child at the launcher
+    pub fn message(&self, locale: Locale) -> String {
+        match locale {
+            Locale::English => self.message_en(),
+            Locale::German => self.message_de(),
+        }
+    }
+
+    fn message_en(&self) -> String {
+        match self {
+            ReasonCode::OutsideTimeWindow {
+                next_window_start: Some(start),
+            } => format!("Not now. You can play again at {}", clock(start)),
+            ReasonCode::OutsideTimeWindow { .. } => "Not available right now".into(),
+            ReasonCode::QuotaExhausted { .. } => "All your time for today is used up".into(),
+            ReasonCode::CooldownActive { available_at, scope } => match scope {
+                CooldownScope::Entry => format!("Take a break. Back at {}", clock(available_at)),
+                CooldownScope::Tag { tag } => {
+                    format!("Take a break from {}. Back at {}", tag, clock(available_at))
+                }
+                CooldownScope::Global => {
+                    format!("Time for a screen break. Back at {}", clock(available_at))
+                }
+            },
+            ReasonCode::SessionActive { .. } => "Something else is already running".into(),
+            ReasonCode::UnsupportedKind { .. } => "This can't run on this computer".into(),
+            ReasonCode::Disabled { reason: Some(reason) } => reason.clone(),
+            ReasonCode::Disabled { reason: None } => "This is switched off".into(),
+            ReasonCode::LowBattery { percent } => {
+                format!("Battery is low ({}%). Plug in the charger", percent)
+            }
+            ReasonCode::RewardLocked { stars_required, stars } => format!(
+                "Earn {} more stars to unlock this",
+                stars_required.saturating_sub(*stars)
+            ),
+            ReasonCode::UnknownProfile { .. } => "Ask a grown-up to add this player".into(),
+            ReasonCode::InUseOnOtherSeat { .. } => "Someone else is playing this right now".into(),
+            ReasonCode::MissingBinary { .. } => "This isn't installed. Ask a grown-up".into(),
+            ReasonCode::MissingEnv { .. } | ReasonCode::LowDiskSpace { .. } => {
+                "This needs fixing. Ask a grown-up".into()
+            }
+            ReasonCode::DisplayUnavailable => "The screen isn't ready. Ask a grown-up".into(),
+            ReasonCode::HeadphonesRequired => "Put your headphones on first".into(),
+            ReasonCode::NotEnoughTime {
+                available,
+                next_full_session: Some(next),
+                ..
+            } => format!(
+                "Only {} min left, not enough to start. Try again at {}",
+                minutes(*available),
+                clock(next)
+            ),
+            ReasonCode::NotEnoughTime { available, .. } => {
+                format!("Only {} min left, not enough to start", minutes(*available))
+            }
+            ReasonCode::Downtime { label, until } => format!(
+                "{} until {}",
+                label.as_deref().unwrap_or("Screen-free time"),
+                clock(until)
+            ),
+            ReasonCode::OnHold {
+                message: Some(message),
+                ..
+            } => message.clone(),
+            ReasonCode::OnHold {
+                until: Some(until), ..
+            } => format!("Paused by a grown-up until {}", clock(until)),
+            ReasonCode::OnHold { .. } => "Paused by a grown-up".into(),
+        }
+    }
+
+    fn message_de(&self) -> String {
+        match self {
+            ReasonCode::OutsideTimeWindow {
+                next_window_start: Some(start),
+            } => format!("Jetzt nicht. Ab {} Uhr geht es wieder", clock(start)),
+            ReasonCode::OutsideTimeWindow { .. } => "Gerade nicht verfügbar".into(),
+            ReasonCode::QuotaExhausted { .. } => "Deine Zeit für heute ist aufgebraucht".into(),
+            ReasonCode::CooldownActive { available_at, scope } => match scope {
+                CooldownScope::Entry => {
+                    format!("Mach eine Pause. Ab {} Uhr geht es weiter", clock(available_at))
+                }
+                CooldownScope::Tag { tag } => format!(
+                    "Mach eine Pause von {}. Ab {} Uhr geht es weiter",
+                    tag,
+                    clock(available_at)
+                ),
+                CooldownScope::Global => format!(
+                    "Zeit für eine Bildschirmpause. Ab {} Uhr geht es weiter",
+                    clock(available_at)
+                ),
+            },
+            ReasonCode::SessionActive { .. } => "Es läuft schon etwas anderes".into(),
+            ReasonCode::UnsupportedKind { .. } => "Das läuft auf diesem Computer nicht".into(),
+            ReasonCode::Disabled { reason: Some(reason) } => reason.clone(),
+            ReasonCode::Disabled { reason: None } => "Das ist ausgeschaltet".into(),
+            ReasonCode::LowBattery { percent } => {
+                format!("Der Akku ist fast leer ({}%). Schließ das Ladegerät an", percent)
+            }
+            ReasonCode::RewardLocked { stars_required, stars } => format!(
+                "Sammle noch {} Sterne, um das freizuschalten",
+                stars_required.saturating_sub(*stars)
+            ),
+            ReasonCode::UnknownProfile { .. } => {
+                "Frag einen Erwachsenen, ob er diesen Spieler anlegt".into()
+            }
+            ReasonCode::InUseOnOtherSeat { .. } => "Das spielt gerade jemand anderes".into(),
+            ReasonCode::MissingBinary { .. } => {
+                "Das ist nicht installiert. Frag einen Erwachsenen".into()
+            }
+            ReasonCode::MissingEnv { .. } | ReasonCode::LowDiskSpace { .. } => {
+                "Hier muss etwas repariert werden. Frag einen Erwachsenen".into()
+            }
+            ReasonCode::DisplayUnavailable => {
+                "Der Bildschirm ist nicht bereit. Frag einen Erwachsenen".into()
+            }
+            ReasonCode::HeadphonesRequired => "Setz zuerst deine Kopfhörer auf".into(),
+            ReasonCode::NotEnoughTime {
+                available,
+                next_full_session: Some(next),
+                ..
+            } => format!(
+                "Nur noch {} Min. übrig, das reicht nicht. Versuch es um {} Uhr wieder",
+                minutes(*available),
+                clock(next)
+            ),
+            ReasonCode::NotEnoughTime { available, .. } => {
+                format!("Nur noch {} Min. übrig, das reicht nicht", minutes(*available))
+            }
+            ReasonCode::Downtime { label, until } => format!(
+                "{} bis {} Uhr",
+                label.as_deref().unwrap_or("Bildschirmfreie Zeit"),
+                clock(until)
+            ),
+            ReasonCode::OnHold {
+                message: Some(message),
+                ..
+            } => message.clone(),
+            ReasonCode::OnHold {
+                until: Some(until), ..
+            } => format!("Von einem Erwachsenen pausiert bis {} Uhr", clock(until)),
+            ReasonCode::OnHold { .. } => "Von einem Erwachsenen pausiert".into(),
+        }
+    }
+}
+
+impl SessionEndReason {
+    /// A short sentence telling the child why their session ended
+    pub fn message(&self, locale: Locale) -> String {
+        let text = match (locale, self) {
+            (Locale::English, SessionEndReason::Expired) => "Time's up!",
+            (Locale::English, SessionEndReason::UserStop) => "You stopped playing",
+            (Locale::English, SessionEndReason::AdminStop) => "A grown-up stopped this",
+            (Locale::English, SessionEndReason::ProcessExited { .. }) => "The game closed",
+            (Locale::English, SessionEndReason::PolicyStop) => "The rules changed",
+            (Locale::English, SessionEndReason::ServiceShutdown) => "The computer is shutting down",
+            (Locale::English, SessionEndReason::LaunchFailed { .. }) => {
+                "It didn't start. Ask a grown-up"
+            }
+            (Locale::English, SessionEndReason::LowBattery) => "Battery is low. Plug in the charger",
+            (Locale::German, SessionEndReason::Expired) => "Die Zeit ist um!",
+            (Locale::German, SessionEndReason::UserStop) => "Du hast aufgehört",
+            (Locale::German, SessionEndReason::AdminStop) => "Ein Erwachsener hat das beendet",
+            (Locale::German, SessionEndReason::ProcessExited { .. }) => "Das Spiel wurde beendet",
+            (Locale::German, SessionEndReason::PolicyStop) => "Die Regeln haben sich geändert",
+            (Locale::German, SessionEndReason::ServiceShutdown) => "Der Computer fährt herunter",
+            (Locale::German, SessionEndReason::LaunchFailed { .. }) => {
+                "Das ist nicht gestartet. Frag einen Erwachsenen"
+            }
+            (Locale::German, SessionEndReason::LowBattery) => {
+                "Der Akku ist fast leer. Schließ das Ladegerät an"
+            }
+        };
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn locale_from_tag() {
+        assert_eq!(Locale::from_tag("de_DE.UTF-8"), Locale::German);
+        assert_eq!(Locale::from_tag("de-AT"), Locale::German);
+        assert_eq!(Locale::from_tag("en_GB.UTF-8"), Locale::English);
+        assert_eq!(Locale::from_tag("C"), Locale::English);
+        assert_eq!(Locale::from_tag(""), Locale::English);
+    }
+
+    #[test]
+    fn reason_messages() {
+        let four = Local.with_ymd_and_hms(2025, 6, 2, 16, 0, 0).unwrap();
+        let outside = ReasonCode::OutsideTimeWindow {
+            next_window_start: Some(four),
+        };
+        assert_eq!(
+            outside.message(Locale::English),
+            "Not now. You can play again at 16:00"
+        );
+        assert_eq!(
+            outside.message(Locale::German),
+            "Jetzt nicht. Ab 16:00 Uhr geht es wieder"
+        );
+
+        let reward = ReasonCode::RewardLocked {
+            stars_required: 5,
+            stars: 3,
+        };
+        assert_eq!(reward.message(Locale::English), "Earn 2 more stars to unlock this");
+
+        let downtime = ReasonCode::Downtime {
+            label: Some("Homework".into()),
+            until: four,
+        };
+        assert_eq!(downtime.message(Locale::English), "Homework until 16:00");
+
+        let not_enough = ReasonCode::NotEnoughTime {
+            available: Duration::from_secs(250),
+            min_run: Duration::from_secs(600),
+            next_full_session: None,
+        };
+        assert_eq!(
+            not_enough.message(Locale::English),
+            "Only 5 min left, not enough to start"
+        );
+
+        // An admin's own words are shown as they are
+        let held = ReasonCode::OnHold {
+            message: Some("Dinner!".into()),
+            until: Some(four),
+        };
+        assert_eq!(held.message(Locale::German), "Dinner!");
+    }
+
+    #[test]
+    fn end_reason_messages() {
+        assert_eq!(SessionEndReason::Expired.message(Locale::English), "Time's up!");
+        assert_eq!(SessionEndReason::Expired.message(Locale::German), "Die Zeit ist um!");
+    }
+}
@@ -0,0 +1,61 @@
+//! JSON Schema for the IPC protocol
+//!
+//! Clients written in other languages can validate messages against this
+//! schema or generate their types from it. Every line on the socket is one
+//! of the three top-level messages: a `Request` from the client, or a
+//! `Response` or `Event` from the service.
+
+use crate::{Event, Request, Response, API_VERSION};
+use schemars::generate::SchemaSettings;
+use serde_json::{json, Value};
+
+/// The protocol schema (JSON Schema draft 2020-12), with `Request`,
+/// `Response` and `Event` and every type they use under `$defs`
+pub fn protocol_schema() -> Value {
+    let mut generator = SchemaSettings::draft2020_12().into_generator();
+    let messages = [
+        generator.subschema_for::<Request>(),
+        generator.subschema_for::<Response>(),
+        generator.subschema_for::<Event>(),
+    ];
+    let definitions = generator.take_definitions(true);
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "shepherdd IPC protocol",
+        "description": format!(
+            "Newline-delimited JSON messages on the shepherdd socket, API version {}",
+            API_VERSION
+        ),
+        "oneOf": messages,
+        "$defs": definitions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_covers_the_protocol() {
+        let schema = protocol_schema();
+        let defs = schema["$defs"].as_object().unwrap();
+        for name in ["Request", "Response", "Event", "Command", "EventPayload", "ReasonCode"] {
+            assert!(defs.contains_key(name), "{} missing from $defs", name);
+        }
+        assert_eq!(defs["DurationMs"]["type"], "integer");
+        assert_eq!(defs["Timestamp"]["format"], "date-time");
+
+        // Every reference resolves
+        let text = schema.to_string();
+        for reference in text.split("\"$ref\":\"#/$defs/").skip(1) {
+            let name = reference.split('"').next().unwrap();
+            assert!(defs.contains_key(name), "dangling reference to {}", name);
+        }
+
+        // Command variants are tagged by "type"
+        let commands = defs["Command"].to_string();
+        assert!(commands.contains("\"const\":\"launch\""));
+        assert!(commands.contains("\"const\":\"extend_current\""));
+    }
+}
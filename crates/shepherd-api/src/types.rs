@@ -1,14 +1,19 @@
 //! Shared types for the shepherdd API
+//!
+//! On the wire, durations are integer milliseconds and timestamps are
+//! RFC 3339 strings with a UTC offset; see [`crate::encoding`]. Every
+//! `Duration` and `DateTime` field here is annotated with one of its helpers.
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveDate};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use shepherd_util::{EntryId, SessionId};
+use shepherd_util::{EntryId, ProfileId, SeatId, SessionId, TimeWindow};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::time::Duration;
 
 /// Entry kind tag for capability matching
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum EntryKindTag {
     Process,
@@ -16,11 +21,12 @@ pub enum EntryKindTag {
     Flatpak,
     Vm,
     Media,
+    Retro,
     Custom,
 }
 
 /// Entry kind with launch details
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum EntryKind {
     Process {
@@ -68,6 +74,14 @@ pub enum EntryKind {
         #[serde(default)]
         args: HashMap<String, serde_json::Value>,
     },
+    /// Game played in RetroArch, fullscreen with a libretro core
+    Retro {
+        /// Core library path, or a core name like "snes9x" found in
+        /// RetroArch's core directories
+        core: String,
+        /// The game's ROM file
+        rom_path: PathBuf,
+    },
     Custom {
         type_name: String,
         payload: serde_json::Value,
@@ -82,13 +96,14 @@ impl EntryKind {
             EntryKind::Flatpak { .. } => EntryKindTag::Flatpak,
             EntryKind::Vm { .. } => EntryKindTag::Vm,
             EntryKind::Media { .. } => EntryKindTag::Media,
+            EntryKind::Retro { .. } => EntryKindTag::Retro,
             EntryKind::Custom { .. } => EntryKindTag::Custom,
         }
     }
 }
 
 /// View of an entry for UI display
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct EntryView {
     pub entry_id: EntryId,
     pub label: String,
@@ -99,31 +114,50 @@ pub struct EntryView {
     /// Maximum run duration if started now. None means:
     /// - If enabled=false: entry is not available
     /// - If enabled=true: entry has no time limit (unlimited)
+    #[schemars(with = "Option<crate::encoding::DurationMs>")]
+    #[serde(default, with = "crate::encoding::option_duration_ms")]
     pub max_run_if_started_now: Option<Duration>,
+    /// Time earned today (e.g. from learning) and added to the daily quota
+    #[schemars(with = "Option<crate::encoding::DurationMs>")]
+    #[serde(default, with = "crate::encoding::option_duration_ms")]
+    pub time_bank: Option<Duration>,
 }
 
 /// Structured reason codes for why an entry is unavailable
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "code", rename_all = "snake_case")]
 pub enum ReasonCode {
     /// Outside allowed time window
     OutsideTimeWindow {
         /// When the next window opens (if known)
+        #[schemars(with = "Option<crate::encoding::Timestamp>")]
+        #[serde(default, with = "crate::encoding::option_rfc3339")]
         next_window_start: Option<DateTime<Local>>,
     },
     /// Daily quota exhausted
     QuotaExhausted {
+        #[schemars(with = "crate::encoding::DurationMs")]
+        #[serde(with = "crate::encoding::duration_ms")]
         used: Duration,
+        #[schemars(with = "crate::encoding::DurationMs")]
+        #[serde(with = "crate::encoding::duration_ms")]
         quota: Duration,
     },
     /// Cooldown period active
     CooldownActive {
+        #[schemars(with = "crate::encoding::Timestamp")]
+        #[serde(with = "crate::encoding::rfc3339")]
         available_at: DateTime<Local>,
+        /// What the cooldown pauses
+        #[serde(default)]
+        scope: CooldownScope,
     },
     /// Another session is active
     SessionActive {
         entry_id: EntryId,
         /// Time remaining in current session. None means unlimited.
+        #[schemars(with = "Option<crate::encoding::DurationMs>")]
+        #[serde(default, with = "crate::encoding::option_duration_ms")]
         remaining: Option<Duration>,
     },
     /// Host doesn't support this entry kind
@@ -134,10 +168,75 @@ pub enum ReasonCode {
     Disabled {
         reason: Option<String>,
     },
+    /// Battery is below the configured stop threshold and not charging
+    LowBattery {
+        percent: u8,
+    },
+    /// Entry is a reward that needs more stars
+    RewardLocked {
+        stars_required: u32,
+        stars: u32,
+    },
+    /// A joint session participant isn't a configured profile
+    UnknownProfile {
+        profile_id: ProfileId,
+    },
+    /// The entry is running on another seat
+    InUseOnOtherSeat {
+        seat: SeatId,
+    },
+    /// The program to launch is missing or not executable
+    MissingBinary {
+        command: String,
+    },
+    /// A required environment variable is not set
+    MissingEnv {
+        name: String,
+    },
+    /// Not enough free disk space
+    LowDiskSpace {
+        path: PathBuf,
+        available_mb: u64,
+        required_mb: u64,
+    },
+    /// No Wayland or X11 display is reachable
+    DisplayUnavailable,
+    /// The entry, or the time of day, needs headphones and sound is going
+    /// to the speakers
+    HeadphonesRequired,
+    /// Less time is left than the entry's minimum session length
+    NotEnoughTime {
+        #[schemars(with = "crate::encoding::DurationMs")]
+        #[serde(with = "crate::encoding::duration_ms")]
+        available: Duration,
+        #[schemars(with = "crate::encoding::DurationMs")]
+        #[serde(with = "crate::encoding::duration_ms")]
+        min_run: Duration,
+        /// When a session of at least `min_run` can next start, if within a week
+        #[schemars(with = "Option<crate::encoding::Timestamp>")]
+        #[serde(default, with = "crate::encoding::option_rfc3339")]
+        next_full_session: Option<DateTime<Local>>,
+    },
+    /// Inside a system-wide downtime block (homework hours), or a family
+    /// calendar event closing the entry
+    Downtime {
+        label: Option<String>,
+        /// When the downtime ends, counting back-to-back blocks as one
+        #[schemars(with = "crate::encoding::Timestamp")]
+        #[serde(with = "crate::encoding::rfc3339")]
+        until: DateTime<Local>,
+    },
+    /// An admin put all launches on hold
+    OnHold {
+        message: Option<String>,
+        #[schemars(with = "Option<crate::encoding::Timestamp>")]
+        #[serde(default, with = "crate::encoding::option_rfc3339")]
+        until: Option<DateTime<Local>>,
+    },
 }
 
 /// Warning severity level
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum WarningSeverity {
     Info,
@@ -146,7 +245,7 @@ pub enum WarningSeverity {
 }
 
 /// Warning threshold configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WarningThreshold {
     /// Seconds before expiry to issue this warning
     pub seconds_before: u64,
@@ -155,7 +254,7 @@ pub struct WarningThreshold {
 }
 
 /// Session end reason
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum SessionEndReason {
     /// Session expired (time limit reached)
@@ -171,11 +270,106 @@ pub enum SessionEndReason {
     /// Service shutdown
     ServiceShutdown,
     /// Launch failed
-    LaunchFailed { error: String },
+    LaunchFailed {
+        error: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        diagnostics: Option<SpawnDiagnostics>,
+    },
+    /// Battery dropped below the configured stop threshold
+    LowBattery,
+}
+
+/// Why a session's program couldn't be started
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SpawnFailureCause {
+    /// The program isn't installed or isn't on PATH
+    NotFound,
+    /// The program exists but can't be executed
+    PermissionDenied,
+    /// There's no Wayland or X11 display to draw on
+    NoDisplay,
+    /// The entry's snap isn't installed
+    SnapNotInstalled,
+    /// The entry's Flatpak app isn't installed
+    FlatpakNotInstalled,
+    /// The entry's pre-launch hook failed
+    HookFailed,
+    /// The program started, then failed right away
+    ExitedEarly,
+    Other,
+}
+
+/// What went wrong starting a session, for the launcher and audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SpawnDiagnostics {
+    pub cause: SpawnFailureCause,
+    /// The program that failed
+    pub program: String,
+    /// The OS error or exit status
+    pub detail: String,
+    /// The last lines the program wrote to stderr
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stderr: Option<String>,
+}
+
+impl SpawnDiagnostics {
+    /// One line to show the user
+    pub fn summary(&self) -> String {
+        match self.cause {
+            SpawnFailureCause::NotFound => format!("{} is not installed", self.program),
+            SpawnFailureCause::PermissionDenied => {
+                format!("{} is not allowed to run", self.program)
+            }
+            SpawnFailureCause::NoDisplay => {
+                format!("{} could not open the display", self.program)
+            }
+            SpawnFailureCause::SnapNotInstalled => {
+                format!("Snap {} is not installed", self.program)
+            }
+            SpawnFailureCause::FlatpakNotInstalled => {
+                format!("Flatpak {} is not installed", self.program)
+            }
+            SpawnFailureCause::HookFailed => format!("Setup for {} failed", self.program),
+            SpawnFailureCause::ExitedEarly => {
+                format!("{} stopped right after starting", self.program)
+            }
+            SpawnFailureCause::Other => format!("{} failed to start", self.program),
+        }
+    }
+}
+
+impl std::fmt::Display for SpawnDiagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.summary(), self.detail)
+    }
+}
+
+/// What a cooldown pauses once it starts
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CooldownScope {
+    /// Only the entry that was played
+    #[default]
+    Entry,
+    /// Every entry with this tag
+    Tag { tag: String },
+    /// Every entry
+    Global,
+}
+
+/// Why a session's time wasn't charged to the quota
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RefundReason {
+    /// Ended within the entry's `refund_under_seconds`
+    ShortSession,
+    /// Exited with an error within the entry's `refund_crash_under_seconds`
+    Crashed { exit_code: Option<i32> },
 }
 
 /// Current session state
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum SessionState {
     Launching,
@@ -186,34 +380,320 @@ pub enum SessionState {
 }
 
 /// Active session information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SessionInfo {
     pub session_id: SessionId,
     pub entry_id: EntryId,
     pub label: String,
     pub state: SessionState,
+    #[schemars(with = "crate::encoding::Timestamp")]
+    #[serde(with = "crate::encoding::rfc3339")]
     pub started_at: DateTime<Local>,
     /// Session deadline. None means unlimited (no time limit).
+    #[schemars(with = "Option<crate::encoding::Timestamp>")]
+    #[serde(default, with = "crate::encoding::option_rfc3339")]
     pub deadline: Option<DateTime<Local>>,
     /// Time remaining. None means unlimited.
+    #[schemars(with = "Option<crate::encoding::DurationMs>")]
+    #[serde(default, with = "crate::encoding::option_duration_ms")]
     pub time_remaining: Option<Duration>,
+    /// Time since the session started, including time spent paused
+    #[schemars(with = "crate::encoding::DurationMs")]
+    #[serde(default, with = "crate::encoding::duration_ms")]
+    pub elapsed: Duration,
+    /// Time charged against the session's limit and the entry's usage
+    #[schemars(with = "crate::encoding::DurationMs")]
+    #[serde(default, with = "crate::encoding::duration_ms")]
+    pub charged: Duration,
     pub warnings_issued: Vec<u64>,
+    /// Camera/microphone access granted to this session
+    #[serde(default)]
+    pub device_access: DeviceAccess,
+    /// Profiles sharing a joint session. Empty for a normal session.
+    #[serde(default)]
+    pub participants: Vec<ProfileId>,
+    /// The seat the session runs on
+    #[serde(default)]
+    pub seat: SeatId,
+}
+
+/// What a finished session used, for an end-of-session card
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SessionSummary {
+    pub session_id: SessionId,
+    pub entry_id: EntryId,
+    pub label: String,
+    pub reason: SessionEndReason,
+    /// Time the session was charged for, not counting time paused
+    #[schemars(with = "crate::encoding::DurationMs")]
+    #[serde(with = "crate::encoding::duration_ms")]
+    pub duration: Duration,
+    /// Part of `duration` added to today's usage. Zero if it was refunded.
+    #[schemars(with = "crate::encoding::DurationMs")]
+    #[serde(with = "crate::encoding::duration_ms")]
+    pub quota_used: Duration,
+    /// The entry's quota left today. None means it has no quota.
+    #[schemars(with = "Option<crate::encoding::DurationMs>")]
+    #[serde(default, with = "crate::encoding::option_duration_ms")]
+    pub remaining_today: Option<Duration>,
+    /// Warning thresholds shown during the session (seconds before expiry)
+    #[serde(default)]
+    pub warnings_shown: Vec<u64>,
+    /// When the entry can be launched again, if not right away. None if it
+    /// can be launched now, or it isn't known when.
+    #[schemars(with = "Option<crate::encoding::Timestamp>")]
+    #[serde(default, with = "crate::encoding::option_rfc3339")]
+    pub next_available: Option<DateTime<Local>>,
+    /// How long the launcher should show the summary; zero skips it
+    #[schemars(with = "crate::encoding::DurationMs")]
+    #[serde(default, with = "crate::encoding::duration_ms")]
+    pub show_for: Duration,
+}
+
+/// Today's play time and the windows still to come, for the child-facing
+/// "how much time is left today" screen
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DayOverview {
+    /// Entries grouped by their first tag, untagged entries last
+    pub categories: Vec<UsageCategory>,
+    /// Time windows that haven't ended yet today, earliest first
+    pub windows: Vec<UpcomingWindow>,
+}
+
+/// Entries sharing a tag, each shown as a bar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct UsageCategory {
+    /// The tag. None for entries without one.
+    pub name: Option<String>,
+    pub entries: Vec<EntryUsage>,
+}
+
+/// One entry's use today against its quota
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct EntryUsage {
+    pub entry_id: EntryId,
+    pub label: String,
+    /// Time used today, including the running session
+    #[schemars(with = "crate::encoding::DurationMs")]
+    #[serde(with = "crate::encoding::duration_ms")]
+    pub used: Duration,
+    /// Today's quota with any bonus and banked time. None means unlimited.
+    #[schemars(with = "Option<crate::encoding::DurationMs>")]
+    #[serde(default, with = "crate::encoding::option_duration_ms")]
+    pub quota: Option<Duration>,
+}
+
+impl EntryUsage {
+    /// Quota left today. None means unlimited.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.quota.map(|quota| quota.saturating_sub(self.used))
+    }
+}
+
+/// Time an entry was used on one day, from another parental control's export
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ImportedUsage {
+    pub entry_id: EntryId,
+    pub day: NaiveDate,
+    #[schemars(with = "crate::encoding::DurationMs")]
+    #[serde(with = "crate::encoding::duration_ms")]
+    pub duration: Duration,
+}
+
+/// Result of checking the audit log's hash chain. Each row stores the hash
+/// of the row before it and its own contents, so editing or deleting a row
+/// breaks the chain at that point.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AuditVerification {
+    /// Rows in the audit log
+    pub rows: u64,
+    /// Rows written before the log was hashed, which can't be checked
+    pub unhashed: u64,
+    /// IDs of rows that don't match their hash, oldest first: edited rows,
+    /// and the rows right after deleted ones
+    pub mismatched: Vec<i64>,
+    /// Hash of the newest row. Removing the newest rows, or rewriting the
+    /// whole chain, only shows against a head noted down earlier.
+    pub head: Option<String>,
+    /// Whether a head noted down earlier is still in the chain; None when
+    /// none was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub known_head_found: Option<bool>,
+}
+
+impl AuditVerification {
+    /// Nothing in the log shows signs of editing
+    pub fn is_intact(&self) -> bool {
+        self.mismatched.is_empty() && self.known_head_found != Some(false)
+    }
+}
+
+/// A time window later today and the entries it opens
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct UpcomingWindow {
+    #[schemars(with = "crate::encoding::Timestamp")]
+    #[serde(with = "crate::encoding::rfc3339")]
+    pub start: DateTime<Local>,
+    #[schemars(with = "crate::encoding::Timestamp")]
+    #[serde(with = "crate::encoding::rfc3339")]
+    pub end: DateTime<Local>,
+    pub labels: Vec<String>,
+}
+
+/// Camera and microphone access granted to a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceAccess {
+    pub camera: bool,
+    pub microphone: bool,
+}
+
+impl Default for DeviceAccess {
+    /// Access is allowed unless policy says otherwise
+    fn default() -> Self {
+        Self {
+            camera: true,
+            microphone: true,
+        }
+    }
 }
 
 /// Full service state snapshot
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ServiceStateSnapshot {
     pub api_version: u32,
     pub policy_loaded: bool,
+    /// The seat this snapshot is for: its session and its entries' view
+    #[serde(default)]
+    pub seat: SeatId,
     pub current_session: Option<SessionInfo>,
     pub entry_count: usize,
     /// Available entries for UI display
     #[serde(default)]
     pub entries: Vec<EntryView>,
+    /// Scheduled curfew shutdown/suspend, if a countdown is running
+    #[serde(default)]
+    pub pending_power_action: Option<PendingPowerAction>,
+    /// Ended session awaiting a feelings check-in, if enabled
+    #[serde(default)]
+    pub pending_check_in: Option<CheckInRequest>,
+    /// Reward stars earned so far
+    #[serde(default)]
+    pub total_stars: u32,
+    /// Launch hold in effect, if any
+    #[serde(default)]
+    pub hold: Option<Hold>,
+    /// Look of the launcher and HUD
+    #[serde(default)]
+    pub theme: Theme,
+}
+
+/// Named look shared by the launcher and HUD
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+    /// Dark, with accents that follow the time of year
+    Seasonal,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 4] = [Theme::Dark, Theme::Light, Theme::HighContrast, Theme::Seasonal];
+
+    /// Name used in the config file
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+            Theme::HighContrast => "high_contrast",
+            Theme::Seasonal => "seasonal",
+        }
+    }
+}
+
+/// How the child feels after a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Mood {
+    Great,
+    Good,
+    Okay,
+    Sad,
+    Upset,
+}
+
+impl Mood {
+    /// All moods, in the order they are offered
+    pub const ALL: [Mood; 5] = [Mood::Great, Mood::Good, Mood::Okay, Mood::Sad, Mood::Upset];
+
+    /// Emoji shown for this mood
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Mood::Great => "😄",
+            Mood::Good => "🙂",
+            Mood::Okay => "😐",
+            Mood::Sad => "😢",
+            Mood::Upset => "😠",
+        }
+    }
+
+    /// Stable name used for storage
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mood::Great => "great",
+            Mood::Good => "good",
+            Mood::Okay => "okay",
+            Mood::Sad => "sad",
+            Mood::Upset => "upset",
+        }
+    }
+
+    /// Parse a stored mood name
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.as_str() == s)
+    }
+}
+
+/// A just-ended session the launcher should ask about
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CheckInRequest {
+    pub session_id: SessionId,
+    pub entry_id: EntryId,
+    pub label: String,
+}
+
+/// Power action taken when the machine is idle during curfew
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerAction {
+    Poweroff,
+    Suspend,
+}
+
+/// A power action counting down to its deadline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct PendingPowerAction {
+    pub action: PowerAction,
+    #[schemars(with = "crate::encoding::Timestamp")]
+    #[serde(with = "crate::encoding::rfc3339")]
+    pub deadline: DateTime<Local>,
+}
+
+/// All launches are blocked by an admin ("Grounded until Friday")
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Hold {
+    /// Shown on the launcher
+    pub message: Option<String>,
+    /// When the hold lifts by itself. None means until turned off.
+    #[schemars(with = "Option<crate::encoding::Timestamp>")]
+    #[serde(default, with = "crate::encoding::option_rfc3339")]
+    pub until: Option<DateTime<Local>>,
 }
 
 /// Role for authorization
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ClientRole {
     /// UI/HUD - can view state, launch entries, stop current
@@ -240,10 +720,77 @@ impl ClientRole {
     pub fn can_reload_config(&self) -> bool {
         matches!(self, ClientRole::Admin)
     }
+
+    pub fn can_cancel_power_action(&self) -> bool {
+        matches!(self, ClientRole::Shell | ClientRole::Admin)
+    }
+
+    pub fn can_submit_check_in(&self) -> bool {
+        matches!(self, ClientRole::Shell | ClientRole::Admin)
+    }
+
+    pub fn can_grant_reward(&self) -> bool {
+        matches!(self, ClientRole::Admin)
+    }
+
+    pub fn can_launch_joint(&self) -> bool {
+        matches!(self, ClientRole::Admin)
+    }
+
+    pub fn can_check_entry_health(&self) -> bool {
+        matches!(self, ClientRole::Admin)
+    }
+
+    pub fn can_set_mock_time(&self) -> bool {
+        matches!(self, ClientRole::Admin)
+    }
+
+    pub fn can_set_hold(&self) -> bool {
+        matches!(self, ClientRole::Admin)
+    }
+
+    pub fn can_import_usage(&self) -> bool {
+        matches!(self, ClientRole::Admin)
+    }
+
+    pub fn can_list_clients(&self) -> bool {
+        matches!(self, ClientRole::Admin)
+    }
+
+    pub fn can_verify_audit(&self) -> bool {
+        matches!(self, ClientRole::Admin)
+    }
+
+    /// Whether the client can release blocked input without the admin PIN
+    pub fn can_release_input(&self) -> bool {
+        matches!(self, ClientRole::Admin)
+    }
+
+    /// Whether the client can act on another seat than its own
+    pub fn can_pick_seat(&self) -> bool {
+        matches!(self, ClientRole::Admin)
+    }
+}
+
+/// Which program a client is, so only one instance of each does the job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientKind {
+    Launcher,
+    Hud,
+    /// Launcher and HUD in one process
+    Shell,
+}
+
+impl ClientKind {
+    /// Whether instances of the two kinds would do the same job
+    pub fn overlaps(self, other: ClientKind) -> bool {
+        self == other || self == ClientKind::Shell || other == ClientKind::Shell
+    }
 }
 
 /// Stop mode for session termination
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum StopMode {
     /// Try graceful termination first
@@ -253,17 +800,198 @@ pub enum StopMode {
 }
 
 /// Health status
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct HealthStatus {
     pub live: bool,
     pub ready: bool,
     pub policy_loaded: bool,
     pub host_adapter_ok: bool,
     pub store_ok: bool,
+    #[serde(default)]
+    pub store: StoreHealth,
+    /// Connected IPC clients, including the one asking
+    #[serde(default)]
+    pub ipc_clients: usize,
+    /// Milliseconds since the host process monitor last ran, when it runs
+    /// in-process
+    #[serde(default)]
+    pub host_monitor_age_ms: Option<u64>,
+    #[serde(default)]
+    pub host_connection: HostConnection,
+    /// When the config file was last modified, if it exists
+    #[schemars(with = "Option<crate::encoding::Timestamp>")]
+    #[serde(default, with = "crate::encoding::option_rfc3339")]
+    pub config_modified: Option<DateTime<Local>>,
+    /// A newer shepherdd release, when update checks are enabled
+    #[serde(default)]
+    pub update_available: Option<UpdateInfo>,
+    /// Likely mistakes in the loaded config. They don't affect health.
+    #[serde(default)]
+    pub config_warnings: Vec<String>,
+    /// Ways out of the kiosk found at startup. They don't affect health.
+    #[serde(default)]
+    pub escape_routes: Vec<EscapeRoute>,
+    #[serde(default)]
+    pub tick: TickHealth,
+}
+
+/// A way out of the kiosk found by the startup hardening audit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct EscapeRoute {
+    pub kind: EscapeRouteKind,
+    /// What was found, e.g. the key binding and its command
+    pub detail: String,
+    /// Whether shepherdd closed it
+    pub mitigated: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EscapeRouteKind {
+    /// Login prompts on other virtual terminals (Ctrl+Alt+F1-F12)
+    VtSwitching,
+    /// A compositor key binding that opens a terminal or app launcher
+    TerminalBinding,
+    /// The GTK inspector, which can run arbitrary code in the launcher
+    GtkInspector,
+}
+
+impl HealthStatus {
+    /// Whether every subsystem reports healthy
+    pub fn is_healthy(&self) -> bool {
+        self.live && self.ready && self.policy_loaded && self.host_adapter_ok && self.store_ok
+    }
+}
+
+/// Store details for health checks
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct StoreHealth {
+    /// How long a trivial query took, in microseconds
+    pub latency_us: u64,
+    /// When an audit event last reached the database
+    #[schemars(with = "Option<crate::encoding::Timestamp>")]
+    #[serde(default, with = "crate::encoding::option_rfc3339")]
+    pub last_audit_write: Option<DateTime<Local>>,
+    /// Writes held in memory while the database is unavailable
+    pub pending_writes: usize,
+}
+
+/// Engine tick details for health checks. Ticks are scheduled for the next
+/// warning, expiry or schedule boundary rather than run at a fixed rate.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TickHealth {
+    /// Ticks since the service started
+    pub ticks: u64,
+    /// How long the last tick took, in microseconds
+    pub last_duration_us: u64,
+    /// Longest tick so far, in microseconds
+    pub max_duration_us: u64,
+    /// Milliseconds between the last two ticks
+    #[serde(default)]
+    pub last_interval_ms: Option<u64>,
+    /// Milliseconds until the next scheduled tick
+    #[serde(default)]
+    pub next_tick_in_ms: Option<u64>,
+}
+
+/// Everything recorded about one session, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SessionTimeline {
+    pub session_id: SessionId,
+    pub events: Vec<TimelineEvent>,
+}
+
+/// One step of a session's timeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TimelineEvent {
+    #[schemars(with = "crate::encoding::Timestamp")]
+    #[serde(with = "crate::encoding::rfc3339")]
+    pub timestamp: DateTime<Local>,
+    #[serde(flatten)]
+    pub kind: TimelineEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TimelineEventKind {
+    Started {
+        entry_id: EntryId,
+        label: String,
+        /// None means unlimited
+        #[schemars(with = "Option<crate::encoding::Timestamp>")]
+        #[serde(default, with = "crate::encoding::option_rfc3339")]
+        deadline: Option<DateTime<Local>>,
+    },
+    Warning {
+        threshold_seconds: u64,
+    },
+    Extended {
+        #[schemars(with = "crate::encoding::DurationMs")]
+        #[serde(with = "crate::encoding::duration_ms")]
+        by: Duration,
+        #[schemars(with = "crate::encoding::Timestamp")]
+        #[serde(with = "crate::encoding::rfc3339")]
+        new_deadline: DateTime<Local>,
+    },
+    Ended {
+        reason: SessionEndReason,
+        #[schemars(with = "crate::encoding::DurationMs")]
+        #[serde(with = "crate::encoding::duration_ms")]
+        duration: Duration,
+    },
+    /// The session's time wasn't charged
+    Refunded {
+        reason: RefundReason,
+        #[schemars(with = "crate::encoding::DurationMs")]
+        #[serde(with = "crate::encoding::duration_ms")]
+        duration: Duration,
+    },
+    CheckIn {
+        mood: Mood,
+    },
+}
+
+/// A newer shepherdd release than the one running
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct UpdateInfo {
+    pub current_version: String,
+    pub available_version: String,
+    /// Downloaded and verified; installed at the next gap between sessions
+    pub staged: bool,
+}
+
+/// What the running shepherdd and its host can do
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ServiceCapabilities {
+    /// shepherdd's own version
+    pub version: String,
+    /// Entry kinds the host can launch
+    pub spawn_kinds: Vec<EntryKindTag>,
+    pub can_freeze_session: bool,
+    pub can_inhibit_input: bool,
+    pub can_read_battery: bool,
+    pub can_detect_idle: bool,
+    pub can_control_media: bool,
+    /// A newer shepherdd release, when update checks are enabled
+    #[serde(default)]
+    pub update_available: Option<UpdateInfo>,
+}
+
+/// How shepherdd reaches the host adapter
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HostConnection {
+    /// Host operations run inside shepherdd
+    #[default]
+    InProcess,
+    /// Connected to shepherd-host-helper
+    Connected,
+    /// Lost the connection to shepherd-host-helper
+    Disconnected,
 }
 
 /// Volume status information
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct VolumeInfo {
     /// Volume percentage (0-100)
     pub percent: u8,
@@ -275,10 +1003,26 @@ pub struct VolumeInfo {
     pub backend: Option<String>,
     /// Current restrictions on volume
     pub restrictions: VolumeRestrictions,
+    /// Where sound is going
+    #[serde(default)]
+    pub output: AudioOutput,
+}
+
+/// The kind of device the default audio output plays through
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioOutput {
+    /// Not known, e.g. on ALSA or a device that doesn't say
+    #[default]
+    Unknown,
+    /// Headphones or a headset, wired or Bluetooth
+    Headphones,
+    /// Anything else: built-in speakers, HDMI, line out
+    Speakers,
 }
 
 /// Volume restrictions that are currently in effect
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct VolumeRestrictions {
     /// Maximum volume percentage allowed
     pub max_volume: Option<u8>,
@@ -288,6 +1032,9 @@ pub struct VolumeRestrictions {
     pub allow_mute: bool,
     /// Whether volume changes are allowed at all
     pub allow_change: bool,
+    /// Sound may only play through headphones; the speakers are muted
+    #[serde(default)]
+    pub require_headphones: bool,
 }
 
 impl VolumeRestrictions {
@@ -298,9 +1045,15 @@ impl VolumeRestrictions {
             min_volume: None,
             allow_mute: true,
             allow_change: true,
+            require_headphones: false,
         }
     }
 
+    /// Whether sound through `output` has to be muted
+    pub fn must_mute(&self, output: AudioOutput) -> bool {
+        self.require_headphones && output == AudioOutput::Speakers
+    }
+
     /// Clamp a volume value to the allowed range
     pub fn clamp_volume(&self, percent: u8) -> u8 {
         let min = self.min_volume.unwrap_or(0);
@@ -324,6 +1077,245 @@ impl VolumeInfo {
     }
 }
 
+/// What a media player in the running session is playing (MPRIS on Linux)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct MediaInfo {
+    /// The player's name, e.g. "vlc" or "firefox"
+    pub player: String,
+    pub status: PlaybackStatus,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+}
+
+impl MediaInfo {
+    /// "Title - Artist", or whichever of the two the player reports
+    pub fn track(&self) -> Option<String> {
+        match (&self.title, &self.artist) {
+            (Some(title), Some(artist)) => Some(format!("{} - {}", title, artist)),
+            (Some(title), None) => Some(title.clone()),
+            (None, Some(artist)) => Some(artist.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Whether a media player is playing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+/// Category of an input device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InputDeviceKind {
+    Gamepad,
+    Keyboard,
+    Mouse,
+    Other,
+}
+
+/// A paired or connected input device (e.g., a Bluetooth controller)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct InputDeviceInfo {
+    /// Human-readable device name
+    pub name: String,
+    /// Hardware address (e.g., Bluetooth MAC)
+    pub address: String,
+    pub kind: InputDeviceKind,
+    pub paired: bool,
+    pub connected: bool,
+    /// Battery percentage (0-100) if the device reports it
+    pub battery_percent: Option<u8>,
+}
+
+impl InputDeviceInfo {
+    /// Get an icon name for this device
+    pub fn icon_name(&self) -> &'static str {
+        match self.kind {
+            InputDeviceKind::Gamepad => "input-gaming-symbolic",
+            InputDeviceKind::Keyboard => "input-keyboard-symbolic",
+            InputDeviceKind::Mouse => "input-mouse-symbolic",
+            InputDeviceKind::Other => "bluetooth-symbolic",
+        }
+    }
+}
+
+/// An installed app found on the host, offered by first-run setup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DiscoveredApp {
+    /// Suggested entry ID, from the desktop file name
+    pub id: String,
+    pub label: String,
+    /// Icon name or path from the desktop file
+    pub icon: Option<String>,
+    /// How to launch the app
+    pub kind: EntryKind,
+}
+
+/// Editable policy settings, as returned by GetPolicy
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PolicyView {
+    /// Max run for entries without their own. None means unlimited.
+    #[schemars(with = "Option<crate::encoding::DurationMs>")]
+    #[serde(default, with = "crate::encoding::option_duration_ms")]
+    pub default_max_run: Option<Duration>,
+    pub default_warnings: Vec<WarningThreshold>,
+    pub entries: Vec<EntryPolicyView>,
+}
+
+/// Editable settings of one entry. Overrides are None when the entry
+/// inherits the service default.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EntryPolicyView {
+    pub entry_id: EntryId,
+    pub label: String,
+    pub kind_tag: EntryKindTag,
+    pub disabled: bool,
+    pub disabled_reason: Option<String>,
+    /// Availability windows. Empty means always available.
+    pub windows: Vec<TimeWindow>,
+    /// Max run override. Zero means unlimited.
+    #[schemars(with = "Option<crate::encoding::DurationMs>")]
+    #[serde(default, with = "crate::encoding::option_duration_ms")]
+    pub max_run: Option<Duration>,
+    /// Daily quota. None means unlimited.
+    #[schemars(with = "Option<crate::encoding::DurationMs>")]
+    #[serde(default, with = "crate::encoding::option_duration_ms")]
+    pub daily_quota: Option<Duration>,
+    #[schemars(with = "Option<crate::encoding::DurationMs>")]
+    #[serde(default, with = "crate::encoding::option_duration_ms")]
+    pub cooldown: Option<Duration>,
+    /// Warning override
+    pub warnings: Option<Vec<WarningThreshold>>,
+}
+
+/// A single policy edit, applied by PatchPolicy and SimulatePolicy
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PolicyPatch {
+    SetLabel {
+        entry_id: EntryId,
+        label: String,
+    },
+    /// Replace the availability windows. Empty means always available.
+    SetWindows {
+        entry_id: EntryId,
+        windows: Vec<TimeWindow>,
+    },
+    /// Replace the limits. A None max run inherits the default; zero is unlimited.
+    SetLimits {
+        entry_id: EntryId,
+        #[schemars(with = "Option<crate::encoding::DurationMs>")]
+        #[serde(default, with = "crate::encoding::option_duration_ms")]
+        max_run: Option<Duration>,
+        #[schemars(with = "Option<crate::encoding::DurationMs>")]
+        #[serde(default, with = "crate::encoding::option_duration_ms")]
+        daily_quota: Option<Duration>,
+        #[schemars(with = "Option<crate::encoding::DurationMs>")]
+        #[serde(default, with = "crate::encoding::option_duration_ms")]
+        cooldown: Option<Duration>,
+    },
+    /// Replace the warnings. None inherits the default warnings.
+    SetWarnings {
+        entry_id: EntryId,
+        warnings: Option<Vec<WarningThreshold>>,
+    },
+    SetDisabled {
+        entry_id: EntryId,
+        disabled: bool,
+        reason: Option<String>,
+    },
+    /// Max run for entries without their own. None means unlimited.
+    SetDefaultMaxRun {
+        #[schemars(with = "Option<crate::encoding::DurationMs>")]
+        #[serde(default, with = "crate::encoding::option_duration_ms")]
+        max_run: Option<Duration>,
+    },
+    SetDefaultWarnings {
+        warnings: Vec<WarningThreshold>,
+    },
+}
+
+impl PolicyPatch {
+    /// Entry this edit applies to, or None for service defaults
+    pub fn entry_id(&self) -> Option<&EntryId> {
+        match self {
+            PolicyPatch::SetLabel { entry_id, .. }
+            | PolicyPatch::SetWindows { entry_id, .. }
+            | PolicyPatch::SetLimits { entry_id, .. }
+            | PolicyPatch::SetWarnings { entry_id, .. }
+            | PolicyPatch::SetDisabled { entry_id, .. } => Some(entry_id),
+            PolicyPatch::SetDefaultMaxRun { .. } | PolicyPatch::SetDefaultWarnings { .. } => None,
+        }
+    }
+}
+
+/// A change to the daemon's mock clock (debug builds only)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum MockTimeOp {
+    /// Jump to a time. The clock keeps running from there unless frozen.
+    Set {
+        #[schemars(with = "crate::encoding::Timestamp")]
+        #[serde(with = "crate::encoding::rfc3339")]
+        time: DateTime<Local>,
+    },
+    /// Move the clock forward, or back for negative seconds
+    Advance { seconds: i64 },
+    /// Stop the clock
+    Freeze,
+    /// Restart a frozen clock from where it stopped
+    Resume,
+    /// Return to the real clock
+    Clear,
+}
+
+/// Result of probing whether an entry can run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct EntryHealth {
+    pub entry_id: EntryId,
+    /// Why the entry is broken, or None if it's healthy
+    pub problem: Option<String>,
+}
+
+/// Host battery status
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct BatteryInfo {
+    /// Battery percentage (0-100), None if no battery is present
+    pub percent: Option<u8>,
+    /// Whether the battery is charging (or full on AC)
+    pub charging: bool,
+    /// Whether AC power is connected
+    pub ac_connected: bool,
+}
+
+impl BatteryInfo {
+    /// Get an icon name for the current battery status
+    pub fn icon_name(&self) -> &'static str {
+        match (self.percent, self.charging) {
+            (None, _) => "battery-missing-symbolic",
+            (Some(p), true) if p >= 90 => "battery-full-charging-symbolic",
+            (Some(p), true) if p >= 60 => "battery-good-charging-symbolic",
+            (Some(p), true) if p >= 30 => "battery-low-charging-symbolic",
+            (Some(_), true) => "battery-caution-charging-symbolic",
+            (Some(p), false) if p >= 90 => "battery-full-symbolic",
+            (Some(p), false) if p >= 60 => "battery-good-symbolic",
+            (Some(p), false) if p >= 30 => "battery-low-symbolic",
+            (Some(p), false) if p >= 10 => "battery-caution-symbolic",
+            (Some(_), false) => "battery-empty-symbolic",
+        }
+    }
+
+    /// Check if the battery is discharging below the given percentage
+    pub fn is_below(&self, percent: u8) -> bool {
+        matches!(self.percent, Some(p) if p < percent && !self.charging)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -353,4 +1345,91 @@ mod tests {
         let json = serde_json::to_string(&reason).unwrap();
         assert!(json.contains("quota_exhausted"));
     }
+
+    #[test]
+    fn time_encoding() {
+        use chrono::TimeZone;
+
+        let until = Local.with_ymd_and_hms(2025, 6, 2, 18, 0, 0).unwrap();
+        let reasons = vec![
+            ReasonCode::QuotaExhausted {
+                used: Duration::from_secs(3600),
+                quota: Duration::from_millis(3_600_500),
+            },
+            ReasonCode::Downtime {
+                label: None,
+                until,
+            },
+            ReasonCode::OnHold {
+                message: None,
+                until: None,
+            },
+        ];
+
+        let json = serde_json::to_value(&reasons).unwrap();
+        assert_eq!(json[0]["used"], 3_600_000);
+        assert_eq!(json[0]["quota"], 3_600_500);
+        assert_eq!(
+            json[1]["until"].as_str().unwrap(),
+            until.to_rfc3339()
+        );
+
+        let parsed: Vec<ReasonCode> = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, reasons);
+    }
+
+    #[test]
+    fn media_track() {
+        let mut media = MediaInfo {
+            player: "vlc".into(),
+            status: PlaybackStatus::Playing,
+            title: Some("Big Buck Bunny".into()),
+            artist: Some("Blender Foundation".into()),
+        };
+        assert_eq!(media.track().unwrap(), "Big Buck Bunny - Blender Foundation");
+        media.artist = None;
+        assert_eq!(media.track().unwrap(), "Big Buck Bunny");
+        media.title = None;
+        assert_eq!(media.track(), None);
+
+        let json = serde_json::to_value(&media).unwrap();
+        assert_eq!(json["status"], "playing");
+    }
+
+    #[test]
+    fn battery_icon_names() {
+        let battery = BatteryInfo {
+            percent: Some(95),
+            charging: false,
+            ac_connected: false,
+        };
+        assert_eq!(battery.icon_name(), "battery-full-symbolic");
+
+        let battery = BatteryInfo {
+            percent: Some(50),
+            charging: true,
+            ac_connected: true,
+        };
+        assert_eq!(battery.icon_name(), "battery-low-charging-symbolic");
+        assert!(!battery.is_below(60));
+
+        let battery = BatteryInfo {
+            percent: Some(5),
+            charging: false,
+            ac_connected: false,
+        };
+        assert_eq!(battery.icon_name(), "battery-empty-symbolic");
+        assert!(battery.is_below(10));
+    }
+
+    #[test]
+    fn health_status_from_older_daemon() {
+        let json = r#"{"live":true,"ready":true,"policy_loaded":true,"host_adapter_ok":true,"store_ok":false}"#;
+        let health: HealthStatus = serde_json::from_str(json).unwrap();
+        assert!(!health.is_healthy());
+        assert_eq!(health.host_connection, HostConnection::InProcess);
+        assert_eq!(health.store.pending_writes, 0);
+        assert!(health.config_modified.is_none());
+        assert!(health.update_available.is_none());
+    }
 }
@@ -63,6 +63,9 @@ fn main() -> ExitCode {
                         EntryKind::Media { library_id, .. } => {
                             format!("media ({})", library_id)
                         }
+                        EntryKind::Retro { rom_path, .. } => {
+                            format!("retro ({})", rom_path.display())
+                        }
                         EntryKind::Custom { type_name, .. } => {
                             format!("custom ({})", type_name)
                         }
@@ -0,0 +1,287 @@
+//! Format-preserving config write-back
+//!
+//! Policy edits are written by changing only the touched keys of the
+//! parent's file with toml_edit, so comments, ordering and formatting of
+//! everything else survive.
+
+use crate::schema::{RawConfig, RawEntry};
+use serde::Serialize;
+use shepherd_api::PolicyPatch;
+use toml_edit::{Array, ArrayOfTables, DocumentMut, InlineTable, Item, Table, TableLike, Value};
+
+/// Rewrite `content` so every field touched by `ops` matches `patched`
+pub fn patch_document(
+    content: &str,
+    patched: &RawConfig,
+    ops: &[PolicyPatch],
+) -> Result<String, String> {
+    let mut doc: DocumentMut = content
+        .parse()
+        .map_err(|e| format!("Failed to parse config: {}", e))?;
+
+    for op in ops {
+        match op.entry_id() {
+            Some(entry_id) => {
+                let raw = patched
+                    .entries
+                    .iter()
+                    .find(|e| e.id == entry_id.as_str())
+                    .ok_or_else(|| format!("No entry '{}'", entry_id))?;
+                let fields = entry_fields(raw, op)?;
+                let table = entry_table(&mut doc, entry_id.as_str())
+                    .ok_or_else(|| format!("Entry '{}' not found in config file", entry_id))?;
+                for (key, value) in fields {
+                    set_key(table, key, value);
+                }
+            }
+            None => {
+                let (key, value) = match op {
+                    PolicyPatch::SetDefaultMaxRun { .. } => (
+                        "default_max_run_seconds",
+                        to_value(&patched.service.default_max_run_seconds)?,
+                    ),
+                    _ => ("default_warnings", to_value(&patched.service.default_warnings)?),
+                };
+                set_key(service_table(&mut doc)?, key, value);
+            }
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+/// Keys of an entry that `op` changes, with their new values
+fn entry_fields(
+    raw: &RawEntry,
+    op: &PolicyPatch,
+) -> Result<Vec<(&'static str, Option<Value>)>, String> {
+    Ok(match op {
+        PolicyPatch::SetLabel { .. } => vec![("label", Some(Value::from(raw.label.as_str())))],
+        PolicyPatch::SetWindows { .. } => vec![("availability", to_value(&raw.availability)?)],
+        PolicyPatch::SetLimits { .. } => vec![("limits", to_value(&raw.limits)?)],
+        PolicyPatch::SetWarnings { .. } => vec![("warnings", to_value(&raw.warnings)?)],
+        PolicyPatch::SetDisabled { .. } => vec![
+            ("disabled", raw.disabled.then(|| Value::from(true))),
+            ("disabled_reason", raw.disabled_reason.as_deref().map(Value::from)),
+        ],
+        PolicyPatch::SetDefaultMaxRun { .. } | PolicyPatch::SetDefaultWarnings { .. } => Vec::new(),
+    })
+}
+
+fn to_value<T: Serialize>(value: &Option<T>) -> Result<Option<Value>, String> {
+    value
+        .as_ref()
+        .map(|v| v.serialize(toml_edit::ser::ValueSerializer::new()))
+        .transpose()
+        .map_err(|e| format!("Failed to serialize config value: {}", e))
+}
+
+/// Find an entry by ID in `[[entries]]` tables or an inline `entries` array
+fn entry_table<'a>(doc: &'a mut DocumentMut, id: &str) -> Option<&'a mut dyn TableLike> {
+    match doc.get_mut("entries")? {
+        Item::ArrayOfTables(tables) => tables
+            .iter_mut()
+            .find(|t| t.get("id").and_then(Item::as_str) == Some(id))
+            .map(|t| t as &mut dyn TableLike),
+        Item::Value(Value::Array(array)) => array
+            .iter_mut()
+            .filter_map(Value::as_inline_table_mut)
+            .find(|t| t.get("id").and_then(Value::as_str) == Some(id))
+            .map(|t| t as &mut dyn TableLike),
+        _ => None,
+    }
+}
+
+/// The `[service]` table, under its `daemon` alias if that's what the file uses
+fn service_table(doc: &mut DocumentMut) -> Result<&mut dyn TableLike, String> {
+    let key = if doc.contains_key("service") {
+        "service"
+    } else if doc.contains_key("daemon") {
+        "daemon"
+    } else {
+        doc.insert("service", Item::Table(Table::new()));
+        "service"
+    };
+    doc[key]
+        .as_table_like_mut()
+        .ok_or_else(|| format!("'{}' is not a table", key))
+}
+
+/// Set or remove a key, keeping the existing formatting where possible
+fn set_key(table: &mut dyn TableLike, key: &str, value: Option<Value>) {
+    match (table.get_mut(key), value) {
+        (_, None) => {
+            table.remove(key);
+        }
+        (Some(item), Some(value)) if !item.is_none() => merge_item(item, value),
+        (_, Some(value)) => {
+            table.insert(key, Item::Value(value));
+        }
+    }
+}
+
+fn merge_item(item: &mut Item, new: Value) {
+    match (item, new) {
+        (Item::Table(table), Value::InlineTable(new)) => merge_table(table, new),
+        (Item::Value(Value::InlineTable(table)), Value::InlineTable(new)) => merge_table(table, new),
+        (Item::ArrayOfTables(tables), Value::Array(new))
+            if new.iter().all(Value::is_inline_table) =>
+        {
+            merge_array_of_tables(tables, new)
+        }
+        (Item::Value(existing), new) => replace_value(existing, new),
+        (item, new) => *item = Item::Value(new),
+    }
+}
+
+fn merge_table(table: &mut dyn TableLike, new: InlineTable) {
+    let stale: Vec<String> = table
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| !new.contains_key(key))
+        .collect();
+    for key in stale {
+        table.remove(&key);
+    }
+    for (key, value) in new {
+        set_key(table, &key, Some(value));
+    }
+}
+
+fn merge_array_of_tables(tables: &mut ArrayOfTables, new: Array) {
+    let new: Vec<InlineTable> = new
+        .into_iter()
+        .filter_map(|v| match v {
+            Value::InlineTable(table) => Some(table),
+            _ => None,
+        })
+        .collect();
+
+    while tables.len() > new.len() {
+        tables.remove(tables.len() - 1);
+    }
+    for (index, table) in new.into_iter().enumerate() {
+        match tables.get_mut(index) {
+            Some(existing) => merge_table(existing, table),
+            None => tables.push(table.into_table()),
+        }
+    }
+}
+
+/// Replace a value unless it's unchanged, keeping its surrounding comments
+fn replace_value(existing: &mut Value, new: Value) {
+    let mut bare = existing.clone();
+    bare.decor_mut().clear();
+    if bare.to_string() == new.to_string() {
+        return;
+    }
+
+    let decor = existing.decor().clone();
+    *existing = new;
+    *existing.decor_mut() = decor;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_config, patch_config};
+    use shepherd_util::EntryId;
+    use std::time::Duration;
+
+    const CONFIG: &str = r#"# Family config
+config_version = 1
+
+[service]
+default_max_run_seconds = 3600 # one hour
+
+[[entries]]
+id = "game"
+label = "Game"   # shown on the tile
+kind = { type = "process", command = "/bin/game" }
+
+# After school only
+[entries.availability]
+[[entries.availability.windows]]
+days = "weekdays"
+start = "15:00"
+end = "18:00"
+
+[entries.limits]
+max_run_seconds = 1800 # half an hour
+daily_quota_seconds = 7200
+
+[[entries]]
+id = "video"
+label = "Video"
+kind = { type = "process", command = "/bin/video" }
+"#;
+
+    fn patch(ops: &[PolicyPatch]) -> String {
+        let raw = toml::from_str(CONFIG).unwrap();
+        let patched = patch_config(&raw, ops).unwrap();
+        patch_document(CONFIG, &patched.raw, ops).unwrap()
+    }
+
+    #[test]
+    fn test_patch_document_preserves_comments() {
+        let game = EntryId::new("game");
+        let output = patch(&[
+            PolicyPatch::SetLabel {
+                entry_id: game.clone(),
+                label: "Racing".into(),
+            },
+            PolicyPatch::SetLimits {
+                entry_id: game.clone(),
+                max_run: Some(Duration::from_secs(2700)),
+                daily_quota: Some(Duration::from_secs(7200)),
+                cooldown: None,
+            },
+        ]);
+
+        assert!(output.starts_with("# Family config\n"));
+        assert!(output.contains("label = \"Racing\"   # shown on the tile"));
+        assert!(output.contains("max_run_seconds = 2700 # half an hour"));
+        assert!(output.contains("daily_quota_seconds = 7200\n"));
+        assert!(output.contains("# After school only"));
+        assert!(output.contains("default_max_run_seconds = 3600 # one hour"));
+
+        let policy = parse_config(&output).unwrap();
+        let entry = policy.get_entry(&game).unwrap();
+        assert_eq!(entry.label, "Racing");
+        assert_eq!(entry.limits.max_run, Some(Duration::from_secs(2700)));
+    }
+
+    #[test]
+    fn test_patch_document_structure() {
+        let video = EntryId::new("video");
+        let output = patch(&[
+            PolicyPatch::SetWindows {
+                entry_id: EntryId::new("game"),
+                windows: Vec::new(),
+            },
+            PolicyPatch::SetDisabled {
+                entry_id: video.clone(),
+                disabled: true,
+                reason: Some("Broken".into()),
+            },
+            PolicyPatch::SetLimits {
+                entry_id: video.clone(),
+                max_run: Some(Duration::from_secs(600)),
+                daily_quota: None,
+                cooldown: None,
+            },
+            PolicyPatch::SetDefaultMaxRun { max_run: None },
+        ]);
+
+        assert!(!output.contains("[[entries.availability.windows]]"));
+        assert!(output.contains("default_max_run_seconds = 0 # one hour"));
+
+        let policy = parse_config(&output).unwrap();
+        assert!(policy.get_entry(&EntryId::new("game")).unwrap().availability.windows.is_empty());
+        let entry = policy.get_entry(&video).unwrap();
+        assert!(entry.disabled);
+        assert_eq!(entry.disabled_reason.as_deref(), Some("Broken"));
+        assert_eq!(entry.limits.max_run, Some(Duration::from_secs(600)));
+        assert_eq!(policy.default_max_run, None);
+    }
+}
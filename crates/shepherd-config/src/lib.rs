@@ -5,15 +5,26 @@
 //! - Entry definitions with availability policies
 //! - Time windows, limits, and warnings
 //! - Validation with clear error messages
+//! - Starter configs from the first-run setup wizard
+//! - Runtime edits applied through PatchPolicy, written back without
+//!   losing the file's comments and formatting
 
+mod document;
+mod patch;
 mod policy;
 mod schema;
+mod setup;
 mod validation;
 
+pub use document::*;
+pub use patch::*;
 pub use policy::*;
 pub use schema::*;
+pub use setup::*;
 pub use validation::*;
 
+use std::fs::File;
+use std::io::Write;
 use std::path::Path;
 use thiserror::Error;
 
@@ -41,6 +52,55 @@ pub fn load_config(path: impl AsRef<Path>) -> ConfigResult<Policy> {
     parse_config(&content)
 }
 
+/// Read a config file without validating it, for editing
+pub fn load_raw_config(path: impl AsRef<Path>) -> ConfigResult<RawConfig> {
+    let content = std::fs::read_to_string(path)?;
+    parse_raw_config(&content)
+}
+
+/// Parse a config without validating it, for editing
+pub fn parse_raw_config(content: &str) -> ConfigResult<RawConfig> {
+    Ok(toml::from_str(content)?)
+}
+
+/// Write a config file atomically.
+///
+/// The content goes to a temporary file next to the config, which then
+/// replaces it, so a crash never leaves a half-written config behind.
+pub fn write_config_file(path: impl AsRef<Path>, content: &str) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let dir = path
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let file_name = path.file_name().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "config path has no file name")
+    })?;
+    std::fs::create_dir_all(dir)?;
+
+    let tmp_path = dir.join(format!(".{}.tmp", file_name.to_string_lossy()));
+    let written = (|| {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        // Keep the original's permissions, e.g. a config only root can read
+        if let Ok(metadata) = std::fs::metadata(path) {
+            std::fs::set_permissions(&tmp_path, metadata.permissions())?;
+        }
+        std::fs::rename(&tmp_path, path)
+    })();
+    if written.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+        return written;
+    }
+
+    // Make the rename itself durable
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
 /// Parse and validate configuration from a TOML string
 pub fn parse_config(content: &str) -> ConfigResult<Policy> {
     let raw: RawConfig = toml::from_str(content)?;
@@ -83,6 +143,31 @@ mod tests {
         assert_eq!(policy.entries[0].id.as_str(), "test-game");
     }
 
+    #[test]
+    fn parse_config_without_volume() {
+        let policy = parse_config("config_version = 1\n").unwrap();
+        // No [service.volume]: the volume is left alone
+        assert!(policy.volume.allow_change && policy.volume.allow_mute);
+        assert_eq!(policy.volume.max_volume, None);
+    }
+
+    #[test]
+    fn write_config_file_replaces_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("config.toml");
+
+        write_config_file(&path, "config_version = 1\n").unwrap();
+        write_config_file(&path, "config_version = 1\nentries = []\n").unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "config_version = 1\nentries = []\n"
+        );
+        // No temporary file left behind
+        let files: Vec<_> = std::fs::read_dir(path.parent().unwrap()).unwrap().collect();
+        assert_eq!(files.len(), 1);
+    }
+
     #[test]
     fn reject_wrong_version() {
         let config = r#"
@@ -97,4 +182,1235 @@ mod tests {
         let result = parse_config(config);
         assert!(matches!(result, Err(ConfigError::UnsupportedVersion(99))));
     }
+
+    #[test]
+    fn parse_gamescope_entry() {
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "celeste"
+            label = "Celeste"
+            kind = { type = "process", command = "celeste" }
+
+            [entries.gamescope]
+            width = 1280
+            height = 720
+            fps_limit = 60
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let gamescope = policy.entries[0].gamescope.as_ref().unwrap();
+        assert_eq!(gamescope.resolution, Some((1280, 720)));
+        assert_eq!(gamescope.fps_limit, Some(60));
+        assert!(gamescope.fullscreen);
+
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "celeste"
+            label = "Celeste"
+            kind = { type = "process", command = "celeste" }
+
+            [entries.gamescope]
+            width = 1280
+        "#;
+
+        assert!(matches!(
+            parse_config(config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_minecraft_entry() {
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "minecraft"
+            label = "Minecraft"
+            kind = { type = "flatpak", app_id = "org.prismlauncher.PrismLauncher" }
+
+            [entries.minecraft]
+            game_dir = "~/.var/app/org.prismlauncher.PrismLauncher/data/PrismLauncher/instances/1.21/.minecraft"
+            disable_chat = true
+            servers = [
+                { name = "Family", address = "mc.example.net" },
+                { name = "Cousins", address = "192.168.1.20:25566" },
+            ]
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let minecraft = policy.entries[0].minecraft.as_ref().unwrap();
+        assert!(minecraft.disable_chat);
+        assert_eq!(minecraft.backups, 5);
+        let servers = minecraft.servers.as_ref().unwrap();
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[1].address, "192.168.1.20:25566");
+
+        let config = config.replace("192.168.1.20:25566", "192.168.1.20:99999");
+        assert!(matches!(
+            parse_config(&config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_wine_entry() {
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "celeste"
+            label = "Celeste"
+            kind = { type = "snap", snap_name = "steam", args = ["steam://rungameid/504230"] }
+
+            [entries.wine]
+            prefix = "~/snap/steam/common/.local/share/Steam/steamapps/compatdata/504230/pfx"
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let wine = policy.entries[0].wine.as_ref().unwrap();
+        assert!(wine.prefix.ends_with("compatdata/504230/pfx"));
+        assert_eq!(wine.start_timeout, std::time::Duration::from_secs(60));
+
+        let config = config.replace("~/snap/steam/common/.local/share/Steam/steamapps/compatdata/504230/pfx", " ");
+        assert!(matches!(
+            parse_config(&config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_retro_entry() {
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "mario-world"
+            label = "Super Mario World"
+            kind = { type = "retro", core = "snes9x", rom_path = "~/roms/snes/mario-world.sfc" }
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        assert_eq!(
+            policy.entries[0].kind,
+            shepherd_api::EntryKind::Retro {
+                core: "snes9x".into(),
+                rom_path: "~/roms/snes/mario-world.sfc".into(),
+            }
+        );
+
+        let config = config.replace("\"snes9x\"", "\"\"");
+        assert!(matches!(
+            parse_config(&config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_media_config() {
+        let policy = parse_config("config_version = 1").unwrap();
+        assert!(!policy.media.pause_on_warnings);
+        assert!(policy.media.pause_on_expiry);
+
+        let config = r#"
+            config_version = 1
+
+            [service.media]
+            pause_on_warnings = true
+            pause_on_expiry = false
+        "#;
+        let policy = parse_config(config).unwrap();
+        assert!(policy.media.pause_on_warnings);
+        assert!(!policy.media.pause_on_expiry);
+    }
+
+    #[test]
+    fn parse_warning_ducking() {
+        let config = r#"
+            config_version = 1
+
+            [service.volume]
+            duck_warnings = 30
+
+            [[entries]]
+            id = "game"
+            label = "Game"
+            kind = { type = "process", command = "/usr/bin/game" }
+            volume = { max_volume = 60, duck_warnings = 10 }
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        assert_eq!(policy.volume.duck_warnings, Some(30));
+        assert_eq!(policy.entries[0].volume.as_ref().unwrap().duck_warnings, Some(10));
+
+        let config = config.replace("duck_warnings = 10", "duck_warnings = 150");
+        assert!(matches!(
+            parse_config(&config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn lint_warnings() {
+        let config = r#"
+            config_version = 1
+
+            [service]
+            default_max_run_seconds = 600
+
+            [[service.default_warnings]]
+            seconds_before = 300
+
+            [[service.default_warnings]]
+            seconds_before = 900
+
+            [[entries]]
+            id = "short"
+            label = "Game"
+            kind = { type = "process", command = "/bin/short" }
+            limits = { max_run_seconds = 120 }
+
+            [[entries]]
+            id = "other"
+            label = "Game"
+            kind = { type = "process", command = "/bin/other" }
+
+            [[entries]]
+            id = "evening"
+            label = "Evening"
+            kind = { type = "process", command = "/bin/evening" }
+            limits = { max_run_seconds = 0, min_run_seconds = 7200 }
+
+            [[entries.availability.windows]]
+            days = []
+            start = "10:00"
+            end = "12:00"
+
+            [[entries.availability.windows]]
+            days = "weekends"
+            start = "23:30"
+            end = "00:30"
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let warnings = &policy.warnings;
+        assert!(warnings.contains(&ValidationWarning::DuplicateLabel {
+            label: "Game".into(),
+            entry_ids: vec!["short".into(), "other".into()],
+        }));
+        assert!(warnings.contains(&ValidationWarning::WindowNeverOpens {
+            entry_id: "evening".into(),
+            start: "10:00".into(),
+            end: "12:00".into(),
+        }));
+        assert!(warnings.contains(&ValidationWarning::MinRunExceedsWindows {
+            entry_id: "evening".into(),
+            min_run: 7200,
+            longest: 3600,
+        }));
+        // Both defaults are past the 120s session, only the later one past 600s
+        let default_warnings: Vec<_> = warnings
+            .iter()
+            .filter_map(|w| match w {
+                ValidationWarning::DefaultWarningExceedsMaxRun { entry_id, seconds, .. } => {
+                    Some((entry_id.as_str(), *seconds))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(default_warnings, [("short", 300), ("short", 900), ("other", 900)]);
+
+        assert!(parse_config("config_version = 1").unwrap().warnings.is_empty());
+    }
+
+    #[test]
+    fn entries_sharing_a_target() {
+        let config = |second_window: &str, second_quota: u64| {
+            format!(
+                r#"
+                config_version = 1
+
+                [[entries]]
+                id = "minecraft"
+                label = "Minecraft"
+                kind = {{ type = "snap", snap_name = "mc-installer" }}
+                limits = {{ daily_quota_seconds = 3600 }}
+
+                [[entries.availability.windows]]
+                days = "weekdays"
+                start = "15:00"
+                end = "18:00"
+
+                [[entries]]
+                id = "minecraft-weekend"
+                label = "Minecraft (weekend)"
+                kind = {{ type = "snap", snap_name = "mc-installer" }}
+                limits = {{ daily_quota_seconds = {} }}
+
+                [[entries.availability.windows]]
+                {}
+                "#,
+                second_quota, second_window
+            )
+        };
+
+        // Different limits at different times is fine, but worth a warning
+        let policy =
+            parse_config(&config("days = \"weekends\"\nstart = \"10:00\"\nend = \"20:00\"", 7200))
+                .unwrap();
+        assert_eq!(
+            policy.warnings,
+            [ValidationWarning::DuplicateTarget {
+                target: "snap 'mc-installer'".into(),
+                entry_ids: vec!["minecraft".into(), "minecraft-weekend".into()],
+            }]
+        );
+
+        // Overlapping on Friday evening, with a bigger quota
+        let overlapping = config("days = [\"fri\", \"sat\"]\nstart = \"17:00\"\nend = \"21:00\"", 7200);
+        let warnings = parse_config(&overlapping).unwrap().warnings;
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(
+            warnings[1].to_string(),
+            "Entries 'minecraft' (entries[0]) and 'minecraft-weekend' (entries[1]) both launch \
+             snap 'mc-installer' with overlapping availability but different limits: \
+             daily_quota 3600s vs 7200s"
+        );
+
+        // Same limits may overlap
+        let same_limits = config("days = [\"fri\"]\nstart = \"17:00\"\nend = \"21:00\"", 3600);
+        assert_eq!(parse_config(&same_limits).unwrap().warnings.len(), 1);
+    }
+
+    #[test]
+    fn parse_device_access() {
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "game"
+            label = "Game"
+            kind = { type = "process", command = "game" }
+            allow_microphone = false
+
+            [[entries]]
+            id = "other"
+            label = "Other"
+            kind = { type = "process", command = "other" }
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        assert!(policy.entries[0].device_access.camera);
+        assert!(!policy.entries[0].device_access.microphone);
+        assert_eq!(policy.entries[1].device_access, Default::default());
+    }
+
+    #[test]
+    fn parse_display_schedule() {
+        use chrono::TimeZone;
+
+        let config = r#"
+            config_version = 1
+
+            [service.display]
+            default_brightness = 90
+            night_light_temperature = 3500
+
+            [[service.display.windows]]
+            days = "all"
+            start = "19:00"
+            end = "07:00"
+            brightness = 40
+            night_light = true
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        assert_eq!(policy.display.windows.len(), 1);
+
+        let evening = chrono::Local.with_ymd_and_hms(2025, 1, 6, 20, 0, 0).unwrap();
+        assert_eq!(
+            policy.display.settings_at(&policy.service.timezone, &evening),
+            DisplaySettings { brightness: Some(40), night_light: Some(3500) }
+        );
+
+        let afternoon = chrono::Local.with_ymd_and_hms(2025, 1, 6, 14, 0, 0).unwrap();
+        assert_eq!(
+            policy.display.settings_at(&policy.service.timezone, &afternoon),
+            DisplaySettings { brightness: Some(90), night_light: None }
+        );
+
+        let config = r#"
+            config_version = 1
+
+            [service.display]
+            default_brightness = 0
+        "#;
+
+        assert!(matches!(
+            parse_config(config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_volume_quiet_hours() {
+        use chrono::TimeZone;
+
+        let config = r#"
+            config_version = 1
+
+            [service.volume]
+            max_volume = 80
+
+            [[service.volume.windows]]
+            days = "all"
+            start = "20:00"
+            end = "07:00"
+            max_volume = 40
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let tz = &policy.service.timezone;
+        let evening = chrono::Local.with_ymd_and_hms(2025, 1, 6, 21, 0, 0).unwrap();
+        assert_eq!(policy.volume.quiet_hours_at(tz, &evening).max_volume, Some(40));
+        let afternoon = chrono::Local.with_ymd_and_hms(2025, 1, 6, 14, 0, 0).unwrap();
+        assert_eq!(policy.volume.quiet_hours_at(tz, &afternoon), QuietHours::default());
+        assert!(!policy.volume.require_headphones);
+
+        // Quiet hours are global, not per entry
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "game"
+            label = "Game"
+            kind = { type = "process", command = "/usr/bin/game" }
+
+            [entries.volume]
+            [[entries.volume.windows]]
+            days = "all"
+            start = "20:00"
+            end = "07:00"
+            max_volume = 40
+        "#;
+
+        assert!(matches!(
+            parse_config(config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_headphones_only() {
+        use chrono::TimeZone;
+
+        let config = r#"
+            config_version = 1
+
+            [[service.volume.windows]]
+            days = "weekdays"
+            start = "06:00"
+            end = "08:00"
+            require_headphones = true
+
+            [[entries]]
+            id = "game"
+            label = "Game"
+            kind = { type = "process", command = "/usr/bin/game" }
+            volume = { require_headphones = true }
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        assert!(policy.entries[0].volume.as_ref().unwrap().require_headphones);
+        let morning = chrono::Local.with_ymd_and_hms(2025, 1, 6, 7, 0, 0).unwrap();
+        let quiet = policy.volume.quiet_hours_at(&policy.service.timezone, &morning);
+        assert!(quiet.require_headphones);
+        assert_eq!(quiet.max_volume, None);
+
+        // A window has to limit something
+        let config = r#"
+            config_version = 1
+
+            [[service.volume.windows]]
+            days = "all"
+            start = "20:00"
+            end = "07:00"
+        "#;
+
+        assert!(matches!(
+            parse_config(config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_curfew() {
+        let config = r#"
+            config_version = 1
+
+            [service.curfew]
+            start = "21:00"
+            end = "07:00"
+            action = "suspend"
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let curfew = policy.curfew.unwrap();
+        assert_eq!(curfew.action, shepherd_api::PowerAction::Suspend);
+        assert_eq!(curfew.delay, std::time::Duration::from_secs(600));
+
+        let config = r#"
+            config_version = 1
+
+            [service.curfew]
+            start = "21:00"
+            end = "07:00"
+            action = "hibernate"
+        "#;
+
+        assert!(matches!(
+            parse_config(config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_timezone() {
+        let config = r#"
+            config_version = 1
+
+            [service]
+            timezone = "CET-1CEST,M3.5.0,M10.5.0/3"
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        assert_eq!(policy.service.timezone.name(), "CET-1CEST,M3.5.0,M10.5.0/3");
+
+        let config = r#"
+            config_version = 1
+
+            [service]
+            timezone = "Mars/Olympus_Mons"
+        "#;
+
+        assert!(matches!(
+            parse_config(config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_panic_key() {
+        let config = r#"
+            config_version = 1
+
+            [service]
+            panic_key = "Mod4+Ctrl+BackSpace"
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        assert_eq!(policy.service.panic_key.as_deref(), Some("Mod4+Ctrl+BackSpace"));
+
+        let config = r#"
+            config_version = 1
+
+            [service]
+            panic_key = "Mod4+Ctrl+BackSpace exec foot"
+        "#;
+
+        assert!(matches!(
+            parse_config(config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_theme() {
+        let policy = parse_config("config_version = 1").unwrap();
+        assert_eq!(policy.service.theme, shepherd_api::Theme::Dark);
+
+        let config = r#"
+            config_version = 1
+
+            [service]
+            theme = "high-contrast"
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        assert_eq!(policy.service.theme, shepherd_api::Theme::HighContrast);
+
+        let config = r#"
+            config_version = 1
+
+            [service]
+            theme = "neon"
+        "#;
+
+        assert!(matches!(
+            parse_config(config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_time_exchange() {
+        let config = r#"
+            config_version = 1
+
+            [service.time_exchange]
+            ratio = 0.5
+            max_earned_minutes = 60
+
+            [[entries]]
+            id = "typing"
+            label = "Typing Tutor"
+            kind = { type = "process", command = "tuxtype" }
+            tags = ["education"]
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let exchange = policy.time_exchange.unwrap();
+        assert_eq!(exchange.earn_tag, "education");
+        assert_eq!(exchange.spend_tag, "games");
+        assert_eq!(
+            exchange.credit_for(std::time::Duration::from_secs(20 * 60)),
+            std::time::Duration::from_secs(10 * 60)
+        );
+        assert!(policy.entries[0].has_tag("education"));
+
+        let config = r#"
+            config_version = 1
+
+            [service.time_exchange]
+            ratio = 0
+        "#;
+
+        assert!(matches!(
+            parse_config(config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_maintenance() {
+        let config = r#"
+            config_version = 1
+
+            [service.maintenance]
+            entries = ["gcompris"]
+            interval_hours = 12
+
+            [[entries]]
+            id = "gcompris"
+            label = "GCompris"
+            kind = { type = "snap", snap_name = "gcompris" }
+
+            [[entries]]
+            id = "tuxmath"
+            label = "Tux Math"
+            kind = { type = "process", command = "tuxmath" }
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let maintenance = policy.maintenance.unwrap();
+        assert_eq!(maintenance.entries, vec![shepherd_util::EntryId::new("gcompris")]);
+        assert_eq!(maintenance.interval, std::time::Duration::from_secs(12 * 3600));
+        assert_eq!(maintenance.max_defer, std::time::Duration::from_secs(72 * 3600));
+
+        // Process entries have no package to update
+        let config = config.replace(r#"entries = ["gcompris"]"#, r#"entries = ["tuxmath"]"#);
+        assert!(matches!(
+            parse_config(&config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_self_update() {
+        let config = r#"
+            config_version = 1
+
+            [service.self_update]
+            feed = "https://example.org/shepherd/latest.json"
+            stage = true
+            public_key = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3"
+
+            [[entries]]
+            id = "tuxmath"
+            label = "Tux Math"
+            kind = { type = "process", command = "tuxmath" }
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let self_update = policy.service.self_update.unwrap();
+        assert_eq!(self_update.feed, "https://example.org/shepherd/latest.json");
+        assert_eq!(self_update.check_interval, std::time::Duration::from_secs(24 * 3600));
+        assert!(self_update.stage);
+        assert!(self_update.install_path.is_none());
+
+        let config = config.replace("https://example.org/", "example.org/");
+        assert!(matches!(
+            parse_config(&config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+
+        // Plain http could be tampered with on the way
+        let config = config.replace("example.org/", "http://example.org/");
+        assert!(matches!(
+            parse_config(&config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+
+        // Staging needs a key to check releases against
+        let config = config
+            .replace("http://", "https://")
+            .replace("public_key = \"RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3\"", "");
+        assert!(matches!(
+            parse_config(&config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_calendar() {
+        let config = r#"
+            config_version = 1
+
+            [service.calendar]
+            url = "https://example.org/family.ics"
+
+            [[entries]]
+            id = "tuxmath"
+            label = "Tux Math"
+            kind = { type = "process", command = "tuxmath" }
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let calendar = policy.calendar.unwrap();
+        assert_eq!(calendar.url, "https://example.org/family.ics");
+        assert_eq!(calendar.poll_interval, std::time::Duration::from_secs(15 * 60));
+
+        let config = config.replace("https://example.org/", "example.org/");
+        assert!(matches!(
+            parse_config(&config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_federation() {
+        let config = r#"
+            config_version = 1
+
+            [service.federation]
+            device = "living-room-pc"
+            secret = "correct horse battery staple"
+            listen = "0.0.0.0:7343"
+            peers = ["tablet.local:7343"]
+
+            [[entries]]
+            id = "tuxmath"
+            label = "Tux Math"
+            kind = { type = "process", command = "tuxmath" }
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let federation = policy.service.federation.unwrap();
+        assert_eq!(federation.device, "living-room-pc");
+        assert_eq!(federation.listen, Some("0.0.0.0:7343".parse().unwrap()));
+        assert_eq!(federation.peers, vec!["tablet.local:7343".to_string()]);
+        assert_eq!(federation.sync_interval, DEFAULT_FEDERATION_SYNC_INTERVAL);
+
+        // Private and VPN addresses are fine
+        let vpn = config
+            .replace("0.0.0.0:7343", "100.101.102.103:7343")
+            .replace("tablet.local:7343", "[fd7a:115c:a1e0::1]:7343");
+        assert!(parse_config(&vpn).is_ok());
+
+        for (from, to) in [
+            ("correct horse battery staple", "short"),
+            ("0.0.0.0:7343", "localhost"),
+            ("0.0.0.0:7343", "203.0.113.5:7343"),
+            ("tablet.local:7343", "tablet.local"),
+            ("tablet.local:7343", "[2001:db8::1]:7343"),
+        ] {
+            let config = config.replace(from, to);
+            assert!(matches!(
+                parse_config(&config),
+                Err(ConfigError::ValidationFailed { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_import_apps() {
+        let config = r#"
+            config_version = 1
+
+            [import.apps]
+            "Minecraft: Java Edition" = "minecraft"
+
+            [[entries]]
+            id = "minecraft"
+            label = "Minecraft"
+            kind = { type = "process", command = "minecraft-launcher" }
+
+            [[entries]]
+            id = "tuxmath"
+            label = "Tux Math"
+            kind = { type = "process", command = "tuxmath" }
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let minecraft = shepherd_util::EntryId::new("minecraft");
+        let tuxmath = shepherd_util::EntryId::new("tuxmath");
+        assert_eq!(policy.import_entry("minecraft: java edition"), Some(&minecraft));
+        // Unlisted apps match by ID or label
+        assert_eq!(policy.import_entry("TuxMath"), Some(&tuxmath));
+        assert_eq!(policy.import_entry(" tux math "), Some(&tuxmath));
+        assert_eq!(policy.import_entry("YouTube"), None);
+
+        let config = config.replace(r#"Edition" = "minecraft""#, r#"Edition" = "roblox""#);
+        assert!(matches!(
+            parse_config(&config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_prechecks() {
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "game"
+            label = "Game"
+            kind = { type = "process", command = "game" }
+
+            [entries.prechecks]
+            require_env = ["GAME_DATA"]
+            min_free_disk_mb = 500
+            require_display = true
+
+            [[entries]]
+            id = "other"
+            label = "Other"
+            kind = { type = "process", command = "other" }
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let prechecks = &policy.entries[0].prechecks;
+        assert!(prechecks.check_binary);
+        assert_eq!(prechecks.require_env, vec!["GAME_DATA".to_string()]);
+        assert_eq!(
+            prechecks.min_free_disk,
+            Some((std::path::PathBuf::from("~"), 500))
+        );
+        assert!(prechecks.require_display);
+        assert_eq!(policy.entries[1].prechecks, PrecheckPolicy::default());
+
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "game"
+            label = "Game"
+            kind = { type = "process", command = "game" }
+
+            [entries.prechecks]
+            disk_path = "/home"
+        "#;
+
+        assert!(matches!(
+            parse_config(config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_hooks() {
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "minecraft"
+            label = "Minecraft"
+            kind = { type = "process", command = "minecraft" }
+
+            [entries.hooks.pre_launch]
+            command = "mount-games"
+            on_failure = "continue"
+
+            [entries.hooks.post_exit]
+            command = "backup-world"
+            args = ["survival"]
+            timeout_seconds = 120
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let hooks = &policy.entries[0].hooks;
+        let pre_launch = hooks.pre_launch.as_ref().unwrap();
+        assert_eq!(pre_launch.command, "mount-games");
+        assert_eq!(pre_launch.timeout, std::time::Duration::from_secs(30));
+        assert_eq!(pre_launch.on_failure, HookFailure::Continue);
+        let post_exit = hooks.post_exit.as_ref().unwrap();
+        assert_eq!(post_exit.args, vec!["survival".to_string()]);
+        assert_eq!(post_exit.timeout, std::time::Duration::from_secs(120));
+        assert_eq!(post_exit.on_failure, HookFailure::Continue);
+
+        let config = config.replace("timeout_seconds = 120", "on_failure = \"abort\"");
+        assert!(matches!(
+            parse_config(&config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_confirm_launch() {
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "movie"
+            label = "Movie"
+            kind = { type = "process", command = "mpv" }
+            confirm_launch = true
+
+            [[entries]]
+            id = "game"
+            label = "Game"
+            kind = { type = "process", command = "game" }
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        assert!(policy.entries[0].confirm_launch);
+        assert!(!policy.entries[1].confirm_launch);
+    }
+
+    #[test]
+    fn parse_controller_only() {
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "kart"
+            label = "Kart"
+            kind = { type = "retro", core = "mupen64plus_next", rom_path = "~/roms/kart.z64" }
+            controller_only = true
+
+            [[entries]]
+            id = "paint"
+            label = "Paint"
+            kind = { type = "process", command = "tuxpaint" }
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        assert!(policy.entries[0].controller_only);
+        assert!(!policy.entries[1].controller_only);
+    }
+
+    #[test]
+    fn parse_autostart() {
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "white-noise"
+            label = "White Noise"
+            kind = { type = "process", command = "noise" }
+
+            [[entries.autostart]]
+            days = ["sun", "mon", "tue", "wed", "thu"]
+            at = "19:30"
+
+            [[entries.autostart]]
+            at = "13:00"
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let autostart = &policy.entries[0].autostart;
+        assert_eq!(autostart.len(), 2);
+        assert!(autostart[0].days.contains(chrono::Weekday::Sun));
+        assert!(!autostart[0].days.contains(chrono::Weekday::Fri));
+        assert_eq!(autostart[0].at, shepherd_util::WallClock::new(19, 30).unwrap());
+        assert_eq!(autostart[1].days, shepherd_util::DaysOfWeek::ALL_DAYS);
+
+        let config = config.replace("19:30", "7:30pm");
+        assert!(matches!(
+            parse_config(&config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_downtime() {
+        let config = r#"
+            config_version = 1
+
+            [[service.downtime]]
+            days = "weekdays"
+            start = "16:00"
+            end = "18:00"
+            label = "Homework"
+
+            [[service.downtime]]
+            start = "22:00"
+            end = "07:00"
+
+            [[entries]]
+            id = "game"
+            label = "Game"
+            kind = { type = "process", command = "game" }
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        assert_eq!(policy.downtime.len(), 2);
+        assert_eq!(policy.downtime[0].label.as_deref(), Some("Homework"));
+        assert_eq!(policy.downtime[0].window.days, shepherd_util::DaysOfWeek::WEEKDAYS);
+        assert_eq!(policy.downtime[1].window.days, shepherd_util::DaysOfWeek::ALL_DAYS);
+
+        let config = config.replace("07:00", "22:00");
+        assert!(matches!(
+            parse_config(&config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_limits() {
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "game"
+            label = "Game"
+            kind = { type = "process", command = "game" }
+
+            [entries.limits]
+            max_run_seconds = 1800
+            min_run_seconds = 600
+            refund_under_seconds = 30
+            refund_crash_under_seconds = 120
+        "#;
+
+        let limits = &parse_config(config).unwrap().entries[0].limits;
+        assert_eq!(limits.min_run, Some(std::time::Duration::from_secs(600)));
+        assert_eq!(limits.refund_under, Some(std::time::Duration::from_secs(30)));
+        assert_eq!(limits.refund_crash_under, Some(std::time::Duration::from_secs(120)));
+
+        // A window covering the whole session would refund everything
+        for (from, to) in [
+            ("refund_crash_under_seconds = 120", "refund_crash_under_seconds = 1800"),
+            ("min_run_seconds = 600", "min_run_seconds = 3600"),
+        ] {
+            assert!(matches!(
+                parse_config(&config.replace(from, to)),
+                Err(ConfigError::ValidationFailed { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_cooldown_scope() {
+        let config = r#"
+            config_version = 1
+
+            [[entries]]
+            id = "game"
+            label = "Game"
+            kind = { type = "process", command = "game" }
+            tags = ["games"]
+
+            [entries.limits]
+            cooldown_seconds = 3600
+            cooldown_scope = "tag:games"
+            cooldown_after_seconds = 7200
+            min_gap_between_sessions_seconds = 300
+        "#;
+
+        let limits = &parse_config(config).unwrap().entries[0].limits;
+        assert_eq!(
+            limits.cooldown_scope,
+            shepherd_api::CooldownScope::Tag { tag: "games".into() }
+        );
+        assert_eq!(limits.cooldown_after, Some(std::time::Duration::from_secs(7200)));
+        assert_eq!(limits.min_gap, Some(std::time::Duration::from_secs(300)));
+
+        for (from, to) in [
+            ("tag:games", "tag:books"),
+            ("tag:games", "everything"),
+            ("cooldown_seconds = 3600", ""),
+        ] {
+            let config = config.replace(from, to);
+            assert!(
+                matches!(parse_config(&config), Err(ConfigError::ValidationFailed { .. })),
+                "{} -> {} accepted",
+                from,
+                to
+            );
+        }
+    }
+
+    #[test]
+    fn parse_profiles() {
+        let config = r#"
+            config_version = 1
+
+            [service]
+            joint_usage = "split"
+
+            [[profiles]]
+            id = "alex"
+            label = "Alex"
+
+            [[profiles]]
+            id = "sam"
+            label = "Sam"
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        assert_eq!(policy.service.joint_usage, JointUsage::Split);
+        assert_eq!(
+            policy.get_profile(&shepherd_util::ProfileId::new("sam")).unwrap().label,
+            "Sam"
+        );
+
+        let config = r#"
+            config_version = 1
+
+            [[profiles]]
+            id = "alex"
+            label = "Alex"
+
+            [[profiles]]
+            id = "alex"
+            label = "Alex again"
+        "#;
+
+        assert!(matches!(
+            parse_config(config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_seats() {
+        let config = r#"
+            config_version = 1
+
+            [[seats]]
+            id = "seat1"
+            label = "Upstairs"
+            wayland_display = "wayland-1"
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let seat1 = shepherd_util::SeatId::new("seat1");
+        assert_eq!(policy.seat_ids(), vec![shepherd_util::SeatId::default(), seat1.clone()]);
+        assert_eq!(policy.get_seat(&seat1).unwrap().wayland_display.as_deref(), Some("wayland-1"));
+        assert!(policy.has_seat(&shepherd_util::SeatId::default()));
+        assert!(!policy.has_seat(&shepherd_util::SeatId::new("seat2")));
+
+        for seats in ["[[seats]]\nid = \"seat0\"", "[[seats]]\nid = \"a\"\n[[seats]]\nid = \"a\""] {
+            let config = format!("config_version = 1\n{}\n", seats);
+            assert!(
+                matches!(parse_config(&config), Err(ConfigError::ValidationFailed { .. })),
+                "{} accepted",
+                seats
+            );
+        }
+    }
+
+    #[test]
+    fn parse_budget_alerts() {
+        let config = r#"
+            config_version = 1
+
+            [[profiles]]
+            id = "alex"
+            label = "Alex"
+            weekly_budget_minutes = 600
+
+            [[profiles.budget_alerts]]
+            percent = 80
+            by = "wed"
+
+            [[profiles.budget_alerts]]
+            percent = 100
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let alex = policy.get_profile(&shepherd_util::ProfileId::new("alex")).unwrap();
+        assert_eq!(alex.weekly_budget, Some(std::time::Duration::from_secs(600 * 60)));
+        assert_eq!(
+            alex.budget_alerts,
+            vec![
+                BudgetAlert { percent: 80, by: Some(chrono::Weekday::Wed) },
+                BudgetAlert { percent: 100, by: None },
+            ]
+        );
+
+        // Alerts need a budget, a sane percentage and a real day
+        for profile in [
+            "budget_alerts = [{ percent = 80 }]",
+            "weekly_budget_minutes = 600\nbudget_alerts = [{ percent = 0 }]",
+            "weekly_budget_minutes = 600\nbudget_alerts = [{ percent = 80, by = \"someday\" }]",
+        ] {
+            let config = format!(
+                "config_version = 1\n[[profiles]]\nid = \"alex\"\nlabel = \"Alex\"\n{}\n",
+                profile
+            );
+            assert!(
+                matches!(parse_config(&config), Err(ConfigError::ValidationFailed { .. })),
+                "{} accepted",
+                profile
+            );
+        }
+    }
+
+    #[test]
+    fn parse_rewards() {
+        let config = r#"
+            config_version = 1
+
+            [[service.rewards]]
+            stars = 20
+            unlock = ["minecraft"]
+
+            [[service.rewards]]
+            stars = 5
+            bonus_minutes = 15
+
+            [[entries]]
+            id = "minecraft"
+            label = "Minecraft"
+            kind = { type = "process", command = "minecraft" }
+        "#;
+
+        let policy = parse_config(config).unwrap();
+        let minecraft = shepherd_util::EntryId::new("minecraft");
+        assert_eq!(policy.rewards.required_stars(&minecraft), Some(20));
+        assert_eq!(policy.rewards.bonus_quota(4), std::time::Duration::ZERO);
+        assert_eq!(
+            policy.rewards.bonus_quota(20),
+            std::time::Duration::from_secs(15 * 60)
+        );
+
+        let config = r#"
+            config_version = 1
+
+            [[service.rewards]]
+            stars = 10
+            unlock = ["missing"]
+        "#;
+
+        assert!(matches!(
+            parse_config(config),
+            Err(ConfigError::ValidationFailed { .. })
+        ));
+    }
 }
@@ -0,0 +1,444 @@
+//! Runtime policy edits
+//!
+//! Edits are applied to the raw config so that the result can be validated
+//! with the same rules as a hand-written file and saved back to disk.
+
+use crate::policy::{convert_time_window, convert_warning};
+use crate::schema::{RawAvailability, RawConfig, RawDays, RawEntry, RawLimits, RawTimeWindow, RawWarningThreshold};
+use crate::validation::{ValidationError, parse_days, parse_time, validate_config};
+use crate::{ConfigError, ConfigResult, Policy};
+use chrono::Weekday;
+use shepherd_api::{EntryPolicyView, PolicyPatch, PolicyView, WarningSeverity, WarningThreshold};
+use shepherd_util::{DaysOfWeek, EntryId, TimeWindow, WallClock};
+use std::time::Duration;
+
+/// A validated set of policy edits
+#[derive(Debug, Clone)]
+pub struct PatchedConfig {
+    pub raw: RawConfig,
+    pub policy: Policy,
+    /// Edits that changed something, in order
+    pub changes: Vec<PolicyChange>,
+}
+
+/// One effective edit, for the audit log
+#[derive(Debug, Clone)]
+pub struct PolicyChange {
+    /// Edit that restores the previous value
+    pub old: PolicyPatch,
+    /// The value after the edit
+    pub new: PolicyPatch,
+}
+
+/// Apply edits to a copy of the raw config and validate the result
+pub fn patch_config(raw: &RawConfig, ops: &[PolicyPatch]) -> ConfigResult<PatchedConfig> {
+    let invalid = |e| ConfigError::ValidationFailed { errors: vec![e] };
+
+    let mut patched = raw.clone();
+    let mut changes = Vec::new();
+    for op in ops {
+        let old = current_value(&patched, op).map_err(invalid)?;
+        apply_patch(&mut patched, op).map_err(invalid)?;
+        let new = current_value(&patched, op).map_err(invalid)?;
+
+        // Compare normalized values; editors resend fields that didn't change
+        if serde_json::to_value(&old).ok() != serde_json::to_value(&new).ok() {
+            changes.push(PolicyChange { old, new });
+        }
+    }
+
+    let errors = validate_config(&patched);
+    if !errors.is_empty() {
+        return Err(ConfigError::ValidationFailed { errors });
+    }
+
+    let policy = Policy::from_raw(patched.clone());
+    Ok(PatchedConfig {
+        raw: patched,
+        policy,
+        changes,
+    })
+}
+
+/// The value `op` would change, as an edit of the same kind
+fn current_value(raw: &RawConfig, op: &PolicyPatch) -> Result<PolicyPatch, ValidationError> {
+    let view = policy_view(raw);
+
+    let Some(entry_id) = op.entry_id() else {
+        return Ok(match op {
+            PolicyPatch::SetDefaultMaxRun { .. } => PolicyPatch::SetDefaultMaxRun {
+                max_run: view.default_max_run,
+            },
+            _ => PolicyPatch::SetDefaultWarnings {
+                warnings: view.default_warnings,
+            },
+        });
+    };
+
+    let entry = view
+        .entries
+        .into_iter()
+        .find(|e| &e.entry_id == entry_id)
+        .ok_or_else(|| ValidationError::EntryError {
+            entry_id: entry_id.to_string(),
+            message: "no such entry".into(),
+        })?;
+    let entry_id = entry.entry_id;
+
+    Ok(match op {
+        PolicyPatch::SetLabel { .. } => PolicyPatch::SetLabel {
+            entry_id,
+            label: entry.label,
+        },
+        PolicyPatch::SetWindows { .. } => PolicyPatch::SetWindows {
+            entry_id,
+            windows: entry.windows,
+        },
+        PolicyPatch::SetLimits { .. } => PolicyPatch::SetLimits {
+            entry_id,
+            max_run: entry.max_run,
+            daily_quota: entry.daily_quota,
+            cooldown: entry.cooldown,
+        },
+        PolicyPatch::SetWarnings { .. } => PolicyPatch::SetWarnings {
+            entry_id,
+            warnings: entry.warnings,
+        },
+        _ => PolicyPatch::SetDisabled {
+            entry_id,
+            disabled: entry.disabled,
+            reason: entry.disabled_reason,
+        },
+    })
+}
+
+/// Apply a single edit to the raw config
+pub fn apply_patch(raw: &mut RawConfig, op: &PolicyPatch) -> Result<(), ValidationError> {
+    if let Some(entry_id) = op.entry_id() {
+        let entry = raw
+            .entries
+            .iter_mut()
+            .find(|e| e.id == entry_id.as_str())
+            .ok_or_else(|| ValidationError::EntryError {
+                entry_id: entry_id.to_string(),
+                message: "no such entry".into(),
+            })?;
+        apply_entry_patch(entry, op);
+        return Ok(());
+    }
+
+    match op {
+        PolicyPatch::SetDefaultMaxRun { max_run } => {
+            raw.service.default_max_run_seconds = Some(max_run.map_or(0, |d| d.as_secs()));
+        }
+        PolicyPatch::SetDefaultWarnings { warnings } => {
+            raw.service.default_warnings = Some(warnings.iter().map(raw_warning).collect());
+        }
+        _ => unreachable!("entry patches are handled above"),
+    }
+    Ok(())
+}
+
+fn apply_entry_patch(entry: &mut RawEntry, op: &PolicyPatch) {
+    match op {
+        PolicyPatch::SetLabel { label, .. } => entry.label = label.clone(),
+        PolicyPatch::SetWindows { windows, .. } => {
+            entry.availability = (!windows.is_empty()).then(|| RawAvailability {
+                windows: windows.iter().map(raw_time_window).collect(),
+                always: false,
+            });
+        }
+        PolicyPatch::SetLimits {
+            max_run,
+            daily_quota,
+            cooldown,
+            ..
+        } => {
+            // Settings that aren't part of the patch are kept
+            let mut limits = RawLimits {
+                max_run_seconds: max_run.map(|d| d.as_secs()),
+                daily_quota_seconds: daily_quota.map(|d| d.as_secs()),
+                cooldown_seconds: cooldown.map(|d| d.as_secs()),
+                ..entry.limits.take().unwrap_or_default()
+            };
+            if limits.cooldown_seconds.is_none() {
+                limits.cooldown_scope = None;
+                limits.cooldown_after_seconds = None;
+            }
+            let is_empty = limits.max_run_seconds.is_none()
+                && limits.daily_quota_seconds.is_none()
+                && limits.cooldown_seconds.is_none()
+                && limits.cooldown_scope.is_none()
+                && limits.cooldown_after_seconds.is_none()
+                && limits.min_gap_between_sessions_seconds.is_none()
+                && limits.refund_under_seconds.is_none()
+                && limits.refund_crash_under_seconds.is_none();
+            entry.limits = (!is_empty).then_some(limits);
+        }
+        PolicyPatch::SetWarnings { warnings, .. } => {
+            entry.warnings = warnings
+                .as_ref()
+                .map(|w| w.iter().map(raw_warning).collect());
+        }
+        PolicyPatch::SetDisabled {
+            disabled, reason, ..
+        } => {
+            entry.disabled = *disabled;
+            entry.disabled_reason = if *disabled { reason.clone() } else { None };
+        }
+        PolicyPatch::SetDefaultMaxRun { .. } | PolicyPatch::SetDefaultWarnings { .. } => {}
+    }
+}
+
+/// Build the editable view of a validated raw config
+pub fn policy_view(raw: &RawConfig) -> PolicyView {
+    let policy = Policy::from_raw(raw.clone());
+
+    let entries = raw
+        .entries
+        .iter()
+        .zip(&policy.entries)
+        .map(|(entry, converted)| {
+            let availability = entry.availability.clone().unwrap_or_default();
+            let limits = entry.limits.clone().unwrap_or_default();
+            EntryPolicyView {
+                entry_id: EntryId::new(entry.id.clone()),
+                label: entry.label.clone(),
+                kind_tag: converted.kind.tag(),
+                disabled: entry.disabled,
+                disabled_reason: entry.disabled_reason.clone(),
+                windows: if availability.always {
+                    Vec::new()
+                } else {
+                    availability.windows.into_iter().map(convert_time_window).collect()
+                },
+                max_run: limits.max_run_seconds.map(Duration::from_secs),
+                daily_quota: limits
+                    .daily_quota_seconds
+                    .filter(|&s| s > 0)
+                    .map(Duration::from_secs),
+                cooldown: limits.cooldown_seconds.map(Duration::from_secs),
+                warnings: entry
+                    .warnings
+                    .clone()
+                    .map(|w| w.into_iter().map(convert_warning).collect()),
+            }
+        })
+        .collect();
+
+    PolicyView {
+        default_max_run: policy.default_max_run,
+        default_warnings: policy.default_warnings,
+        entries,
+    }
+}
+
+/// Format a window in the compact syntax used by editors, e.g.
+/// `weekdays 15:00-18:00` or `mon,wed 10:00-12:00`
+pub fn format_window(window: &TimeWindow) -> String {
+    let raw = raw_time_window(window);
+    let days = match raw.days {
+        RawDays::Preset(preset) => preset,
+        RawDays::List(list) => list.join(","),
+    };
+    format!("{} {}-{}", days, raw.start, raw.end)
+}
+
+/// Parse a window in the compact editor syntax
+pub fn parse_window(spec: &str) -> Result<TimeWindow, String> {
+    let usage = || format!("Expected '<days> HH:MM-HH:MM', got '{}'", spec.trim());
+    let (days, times) = spec.trim().split_once(char::is_whitespace).ok_or_else(usage)?;
+    let (start, end) = times.trim().split_once('-').ok_or_else(usage)?;
+
+    let mask = parse_days(&RawDays::Preset(days.to_string())).or_else(|_| {
+        parse_days(&RawDays::List(
+            days.split(',').map(|d| d.trim().to_string()).collect(),
+        ))
+    })?;
+    if mask == 0 {
+        return Err(usage());
+    }
+
+    let (start_h, start_m) = parse_time(start.trim())?;
+    let (end_h, end_m) = parse_time(end.trim())?;
+    Ok(TimeWindow::new(
+        DaysOfWeek::new(mask),
+        WallClock::new(start_h, start_m).ok_or_else(usage)?,
+        WallClock::new(end_h, end_m).ok_or_else(usage)?,
+    ))
+}
+
+fn raw_time_window(window: &TimeWindow) -> RawTimeWindow {
+    RawTimeWindow {
+        days: raw_days(window.days),
+        start: format!("{:02}:{:02}", window.start.hour, window.start.minute),
+        end: format!("{:02}:{:02}", window.end.hour, window.end.minute),
+    }
+}
+
+fn raw_days(days: DaysOfWeek) -> RawDays {
+    match days {
+        DaysOfWeek::ALL_DAYS => RawDays::Preset("all".into()),
+        DaysOfWeek::WEEKDAYS => RawDays::Preset("weekdays".into()),
+        DaysOfWeek::WEEKENDS => RawDays::Preset("weekends".into()),
+        _ => RawDays::List(
+            [
+                (Weekday::Mon, "mon"),
+                (Weekday::Tue, "tue"),
+                (Weekday::Wed, "wed"),
+                (Weekday::Thu, "thu"),
+                (Weekday::Fri, "fri"),
+                (Weekday::Sat, "sat"),
+                (Weekday::Sun, "sun"),
+            ]
+            .into_iter()
+            .filter(|(day, _)| days.contains(*day))
+            .map(|(_, name)| name.to_string())
+            .collect(),
+        ),
+    }
+}
+
+fn raw_warning(warning: &WarningThreshold) -> RawWarningThreshold {
+    RawWarningThreshold {
+        seconds_before: warning.seconds_before,
+        severity: match warning.severity {
+            WarningSeverity::Info => "info",
+            WarningSeverity::Warn => "warn",
+            WarningSeverity::Critical => "critical",
+        }
+        .to_string(),
+        message: warning.message_template.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_config() -> RawConfig {
+        toml::from_str(
+            r#"
+            config_version = 1
+
+            [[entries]]
+            id = "game"
+            label = "Game"
+            kind = { type = "process", command = "/bin/game" }
+
+            [entries.limits]
+            max_run_seconds = 1800
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_patch_config() {
+        let raw = raw_config();
+        let game = EntryId::new("game");
+        let window = TimeWindow::new(
+            DaysOfWeek::WEEKENDS,
+            WallClock::new(10, 0).unwrap(),
+            WallClock::new(12, 30).unwrap(),
+        );
+
+        let PatchedConfig {
+            raw: patched,
+            policy,
+            changes,
+        } = patch_config(
+            &raw,
+            &[
+                PolicyPatch::SetLabel {
+                    entry_id: game.clone(),
+                    label: "Fun Game".into(),
+                },
+                PolicyPatch::SetWindows {
+                    entry_id: game.clone(),
+                    windows: vec![window.clone()],
+                },
+                PolicyPatch::SetLimits {
+                    entry_id: game.clone(),
+                    max_run: Some(Duration::from_secs(3600)),
+                    daily_quota: Some(Duration::from_secs(7200)),
+                    cooldown: None,
+                },
+                PolicyPatch::SetDisabled {
+                    entry_id: game.clone(),
+                    disabled: false,
+                    reason: None,
+                },
+            ],
+        )
+        .unwrap();
+
+        // Re-enabling an enabled entry isn't a change
+        assert_eq!(changes.len(), 3);
+        assert!(matches!(
+            &changes[0].old,
+            PolicyPatch::SetLabel { label, .. } if label == "Game"
+        ));
+        assert!(matches!(
+            &changes[2].old,
+            PolicyPatch::SetLimits { max_run: Some(d), daily_quota: None, .. } if d.as_secs() == 1800
+        ));
+
+        let entry = policy.get_entry(&game).unwrap();
+        assert_eq!(entry.label, "Fun Game");
+        assert_eq!(entry.availability.windows, vec![window.clone()]);
+        assert_eq!(entry.limits.max_run, Some(Duration::from_secs(3600)));
+        assert_eq!(entry.limits.daily_quota, Some(Duration::from_secs(7200)));
+
+        // The patched raw config survives a save and reload
+        let saved = toml::to_string_pretty(&patched).unwrap();
+        let view = policy_view(&toml::from_str(&saved).unwrap());
+        assert_eq!(view.entries[0].windows, vec![window]);
+        assert_eq!(view.entries[0].max_run, Some(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_window_syntax() {
+        let window = parse_window("weekdays 15:00-18:30").unwrap();
+        assert_eq!(window.days, DaysOfWeek::WEEKDAYS);
+        assert_eq!(window.start, WallClock::new(15, 0).unwrap());
+        assert_eq!(window.end, WallClock::new(18, 30).unwrap());
+        assert_eq!(format_window(&window), "weekdays 15:00-18:30");
+
+        let err = parse_window("mon, wed 9:00-10:00").unwrap_err();
+        assert!(err.contains("Unknown day"));
+        let window = parse_window("mon,wed 9:00-10:00").unwrap();
+        assert_eq!(format_window(&window), "mon,wed 09:00-10:00");
+
+        assert!(parse_window("weekdays").is_err());
+        assert!(parse_window("weekdays 25:00-26:00").is_err());
+    }
+
+    #[test]
+    fn test_patch_config_rejects_invalid() {
+        let raw = raw_config();
+
+        let unknown = patch_config(
+            &raw,
+            &[PolicyPatch::SetLabel {
+                entry_id: EntryId::new("missing"),
+                label: "Missing".into(),
+            }],
+        );
+        assert!(matches!(unknown, Err(ConfigError::ValidationFailed { .. })));
+
+        // Warning longer than the max run
+        let invalid = patch_config(
+            &raw,
+            &[PolicyPatch::SetWarnings {
+                entry_id: EntryId::new("game"),
+                warnings: Some(vec![WarningThreshold {
+                    seconds_before: 3600,
+                    severity: WarningSeverity::Warn,
+                    message_template: None,
+                }]),
+            }],
+        );
+        assert!(matches!(invalid, Err(ConfigError::ValidationFailed { .. })));
+    }
+}
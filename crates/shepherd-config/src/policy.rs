@@ -1,9 +1,11 @@
 //! Validated policy structures
 
-use crate::schema::{RawConfig, RawEntry, RawEntryKind, RawVolumeConfig, RawServiceConfig, RawWarningThreshold};
-use crate::validation::{parse_days, parse_time};
-use shepherd_api::{EntryKind, WarningSeverity, WarningThreshold};
-use shepherd_util::{DaysOfWeek, EntryId, TimeWindow, WallClock, default_data_dir, default_log_dir, socket_path_without_env};
+use crate::schema::{RawAutostart, RawBatteryConfig, RawBudgetAlert, RawCalendarConfig, RawConfig, RawCurfewConfig, RawDays, RawDowntime, RawDisplayConfig, RawEntry, RawEntryKind, RawFederationConfig, RawGamescopeConfig, RawHook, RawHooks, RawMaintenanceConfig, RawMediaConfig, RawMinecraftConfig, RawPrechecks, RawProfile, RawRewardThreshold, RawSeat, RawSelfUpdateConfig, RawTimeExchangeConfig, RawVolumeConfig, RawServiceConfig, RawTimeWindow, RawWarningThreshold};
+use crate::validation::{lint_config, parse_cooldown_scope, parse_days, parse_hook_failure, parse_joint_usage, parse_power_action, parse_theme, parse_time, ValidationWarning};
+use shepherd_api::{CooldownScope, DeviceAccess, EntryKind, PowerAction, Theme, WarningSeverity, WarningThreshold};
+use shepherd_util::{DaysOfWeek, EntryId, PinHash, ProfileId, SeatId, TimeWindow, Timezone, WallClock, default_data_dir, default_log_dir, socket_path_without_env};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::time::Duration;
 
@@ -16,6 +18,12 @@ pub struct Policy {
     /// Validated entries
     pub entries: Vec<Entry>,
 
+    /// Child profiles
+    pub profiles: Vec<Profile>,
+
+    /// Seats besides `seat0`
+    pub seats: Vec<Seat>,
+
     /// Default warning thresholds
     pub default_warnings: Vec<WarningThreshold>,
 
@@ -24,11 +32,46 @@ pub struct Policy {
 
     /// Global volume restrictions
     pub volume: VolumePolicy,
+
+    /// Scheduled display brightness and night light
+    pub display: DisplayPolicy,
+
+    /// Power off or suspend when idle during curfew. None disables it.
+    pub curfew: Option<CurfewPolicy>,
+
+    /// Blocks during which every entry is unavailable
+    pub downtime: Vec<DowntimePolicy>,
+
+    /// Low-battery thresholds
+    pub battery: BatteryPolicy,
+
+    /// When the session's media players are paused
+    pub media: MediaPolicy,
+
+    /// Star thresholds and what they unlock
+    pub rewards: RewardsPolicy,
+
+    /// Learning time that earns game time. None disables it.
+    pub time_exchange: Option<TimeExchangePolicy>,
+
+    /// Automatic snap/flatpak updates. None disables them.
+    pub maintenance: Option<MaintenancePolicy>,
+
+    /// Calendar feed of one-off exceptions. None disables it.
+    pub calendar: Option<CalendarPolicy>,
+
+    /// App names in other parental controls' exports, mapped to entries
+    pub import_apps: BTreeMap<String, EntryId>,
+
+    /// Likely mistakes found while loading the config
+    pub warnings: Vec<ValidationWarning>,
 }
 
 impl Policy {
     /// Convert from raw config (after validation)
     pub fn from_raw(raw: RawConfig) -> Self {
+        let warnings = lint_config(&raw);
+
         let default_warnings = raw
             .service
             .default_warnings
@@ -48,29 +91,197 @@ impl Policy {
             .volume
             .as_ref()
             .map(convert_volume_config)
+            .unwrap_or_else(VolumePolicy::unrestricted);
+
+        let display = raw
+            .service
+            .display
+            .clone()
+            .map(convert_display_config)
+            .unwrap_or_default();
+
+        let curfew = raw.service.curfew.clone().map(convert_curfew_config);
+
+        let downtime = raw
+            .service
+            .downtime
+            .iter()
+            .cloned()
+            .map(convert_downtime)
+            .collect();
+
+        let battery = raw
+            .service
+            .battery
+            .as_ref()
+            .map(convert_battery_config)
+            .unwrap_or_default();
+
+        let media = raw
+            .service
+            .media
+            .as_ref()
+            .map(convert_media_config)
             .unwrap_or_default();
 
+        let rewards = convert_rewards(&raw.service.rewards);
+
+        let time_exchange = raw
+            .service
+            .time_exchange
+            .clone()
+            .map(convert_time_exchange_config);
+
+        let maintenance = raw
+            .service
+            .maintenance
+            .clone()
+            .map(convert_maintenance_config);
+        let calendar = raw.service.calendar.clone().map(convert_calendar_config);
+
         let entries = raw
             .entries
             .into_iter()
             .map(|e| Entry::from_raw(e, &default_warnings, default_max_run, &global_volume))
             .collect();
 
+        let import_apps = raw
+            .import
+            .apps
+            .into_iter()
+            .map(|(app, entry_id)| (app, EntryId::new(entry_id)))
+            .collect();
+
+        let profiles = raw.profiles.into_iter().map(convert_profile).collect();
+        let seats = raw.seats.into_iter().map(convert_seat).collect();
+
         Self {
             service: ServiceConfig::from_raw(raw.service),
             entries,
+            profiles,
+            seats,
             default_warnings,
             default_max_run,
             volume: global_volume,
+            display,
+            curfew,
+            downtime,
+            battery,
+            media,
+            rewards,
+            time_exchange,
+            maintenance,
+            calendar,
+            import_apps,
+            warnings,
         }
     }
 
+    /// Policy with no entries, used until first-run setup writes a config
+    pub fn unconfigured() -> Self {
+        Self::from_raw(RawConfig {
+            config_version: crate::CURRENT_CONFIG_VERSION,
+            service: RawServiceConfig::default(),
+            entries: Vec::new(),
+            profiles: Vec::new(),
+            seats: Vec::new(),
+            import: Default::default(),
+        })
+    }
+
     /// Get entry by ID
     pub fn get_entry(&self, id: &EntryId) -> Option<&Entry> {
         self.entries.iter().find(|e| &e.id == id)
     }
+
+    /// Entry an app in an imported export counts as: the one `[import.apps]`
+    /// maps it to, or else one with that ID or label. Case is ignored.
+    pub fn import_entry(&self, app: &str) -> Option<&EntryId> {
+        let app = app.trim();
+        self.import_apps
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(app))
+            .map(|(_, entry_id)| entry_id)
+            .or_else(|| {
+                self.entries
+                    .iter()
+                    .find(|e| e.id.as_str().eq_ignore_ascii_case(app) || e.label.eq_ignore_ascii_case(app))
+                    .map(|e| &e.id)
+            })
+    }
+
+    /// Get profile by ID
+    pub fn get_profile(&self, id: &ProfileId) -> Option<&Profile> {
+        self.profiles.iter().find(|p| &p.id == id)
+    }
+
+    /// Get an extra seat by ID. `seat0` is always there and has no entry.
+    pub fn get_seat(&self, id: &SeatId) -> Option<&Seat> {
+        self.seats.iter().find(|s| &s.id == id)
+    }
+
+    /// Every seat: `seat0` first, then the configured ones
+    pub fn seat_ids(&self) -> Vec<SeatId> {
+        std::iter::once(SeatId::default())
+            .chain(self.seats.iter().map(|s| s.id.clone()))
+            .collect()
+    }
+
+    /// Whether `id` is `seat0` or a configured seat
+    pub fn has_seat(&self, id: &SeatId) -> bool {
+        id.is_default() || self.get_seat(id).is_some()
+    }
+}
+
+/// Child profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub id: ProfileId,
+    pub label: String,
+    /// Expected use per week, Monday to Sunday. Reported on, not enforced.
+    pub weekly_budget: Option<Duration>,
+    pub budget_alerts: Vec<BudgetAlert>,
 }
 
+/// An extra seat
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Seat {
+    pub id: SeatId,
+    pub label: Option<String>,
+    /// Overrides WAYLAND_DISPLAY for sessions on this seat
+    pub wayland_display: Option<String>,
+}
+
+/// Alert parents once a week when a profile has used `percent` of its
+/// weekly budget, if that happens by the end of `by`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetAlert {
+    pub percent: u8,
+    pub by: Option<chrono::Weekday>,
+}
+
+/// How a joint session's time is charged to its participants
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JointUsage {
+    /// Every participant is charged the full session time
+    #[default]
+    Duplicate,
+    /// The session time is divided evenly between participants
+    Split,
+}
+
+/// Default interval between entry health probes
+pub const DEFAULT_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Default time the launcher shows a session's summary
+pub const DEFAULT_SESSION_SUMMARY: Duration = Duration::from_secs(15);
+
+/// Default longest wait between engine ticks
+pub const DEFAULT_MAX_TICK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Shortest `max_tick_interval` accepted; the old fixed tick rate
+pub const MIN_TICK_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Service configuration
 #[derive(Debug, Clone)]
 pub struct ServiceConfig {
@@ -81,6 +292,74 @@ pub struct ServiceConfig {
     pub capture_child_output: bool,
     /// Directory for child application logs
     pub child_log_dir: PathBuf,
+    /// Whether to show a feelings check-in after each session
+    pub session_check_in: bool,
+    /// How long the launcher shows a session's summary after it ends. Zero
+    /// skips it.
+    pub session_summary: Duration,
+    /// How joint sessions charge participants
+    pub joint_usage: JointUsage,
+    /// How often to probe entries for broken installs. None disables probing.
+    pub health_check_interval: Option<Duration>,
+    /// Longest wait between engine ticks. Ticks are otherwise scheduled for
+    /// the next warning, expiry or schedule boundary.
+    pub max_tick_interval: Duration,
+    /// Parent PIN for admin actions in the UI
+    pub admin_pin: Option<PinHash>,
+    /// Timezone that wall-clock windows are evaluated in
+    pub timezone: Timezone,
+    /// Look of the launcher and HUD
+    pub theme: Theme,
+    /// Executables trusted with the Shell role; empty trusts any non-admin client
+    pub shell_executables: Vec<PathBuf>,
+    /// Close kiosk escape routes found at startup rather than only report them
+    pub mitigate_escape_routes: bool,
+    /// Compositor key combination that force-stops every session. None
+    /// registers no key.
+    pub panic_key: Option<String>,
+    /// Release checks for shepherdd itself. None disables them.
+    pub self_update: Option<SelfUpdatePolicy>,
+    /// Usage sharing with other devices. None keeps usage local.
+    pub federation: Option<FederationPolicy>,
+}
+
+/// Where and how often to look for new shepherdd releases
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfUpdatePolicy {
+    /// https URL, file:// URL or absolute path of the release feed
+    pub feed: String,
+    pub check_interval: Duration,
+    /// Download new releases and install them between sessions
+    pub stage: bool,
+    /// Binary to replace; None means the running executable
+    pub install_path: Option<PathBuf>,
+    /// Minisign public key (base64) that staged releases must be signed with
+    pub public_key: Option<String>,
+}
+
+/// Default time between syncs with each federated peer
+pub const DEFAULT_FEDERATION_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Which devices share usage with this one, and how
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FederationPolicy {
+    /// This device's name in exchanged usage records
+    pub device: String,
+    /// Shared secret that messages are signed with
+    pub secret: String,
+    /// Where to accept peers. None only connects out.
+    pub listen: Option<SocketAddr>,
+    /// Peers to connect to, as "host:port"
+    pub peers: Vec<String>,
+    pub sync_interval: Duration,
+}
+
+/// Where to fetch the family calendar from, and how often
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarPolicy {
+    /// http(s) URL, file:// URL or absolute path of the .ics feed
+    pub url: String,
+    pub poll_interval: Duration,
 }
 
 impl ServiceConfig {
@@ -99,6 +378,40 @@ impl ServiceConfig {
             log_dir,
             capture_child_output: raw.capture_child_output,
             child_log_dir,
+            session_check_in: raw.session_check_in,
+            session_summary: raw
+                .session_summary_seconds
+                .map_or(DEFAULT_SESSION_SUMMARY, Duration::from_secs),
+            joint_usage: raw
+                .joint_usage
+                .as_deref()
+                .and_then(parse_joint_usage)
+                .unwrap_or_default(),
+            health_check_interval: match raw.health_check_interval_seconds {
+                Some(0) => None,
+                Some(secs) => Some(Duration::from_secs(secs)),
+                None => Some(DEFAULT_HEALTH_CHECK_INTERVAL),
+            },
+            max_tick_interval: raw
+                .max_tick_interval_ms
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_MAX_TICK_INTERVAL)
+                .max(MIN_TICK_INTERVAL),
+            admin_pin: raw.admin_pin.and_then(|pin| pin.parse().ok()),
+            shell_executables: raw.shell_executables,
+            mitigate_escape_routes: raw.mitigate_escape_routes,
+            panic_key: raw.panic_key,
+            self_update: raw.self_update.map(convert_self_update_config),
+            federation: raw.federation.map(convert_federation_config),
+            timezone: raw
+                .timezone
+                .and_then(|tz| tz.parse().ok())
+                .unwrap_or_default(),
+            theme: raw
+                .theme
+                .as_deref()
+                .and_then(parse_theme)
+                .unwrap_or_default(),
             data_dir: raw
                 .data_dir
                 .unwrap_or_else(default_data_dir),
@@ -115,6 +428,19 @@ impl Default for ServiceConfig {
             log_dir,
             data_dir: default_data_dir(),
             capture_child_output: false,
+            session_check_in: false,
+            session_summary: DEFAULT_SESSION_SUMMARY,
+            joint_usage: JointUsage::default(),
+            health_check_interval: Some(DEFAULT_HEALTH_CHECK_INTERVAL),
+            max_tick_interval: DEFAULT_MAX_TICK_INTERVAL,
+            admin_pin: None,
+            timezone: Timezone::Local,
+            theme: Theme::default(),
+            shell_executables: Vec::new(),
+            mitigate_escape_routes: false,
+            panic_key: None,
+            self_update: None,
+            federation: None,
         }
     }
 }
@@ -130,6 +456,23 @@ pub struct Entry {
     pub limits: LimitsPolicy,
     pub warnings: Vec<WarningThreshold>,
     pub volume: Option<VolumePolicy>,
+    pub gamescope: Option<GamescopePolicy>,
+    pub device_access: DeviceAccess,
+    pub tags: Vec<String>,
+    pub prechecks: PrecheckPolicy,
+    /// Launches wait for a ConfirmLaunch after showing the granted time
+    pub confirm_launch: bool,
+    /// Times the engine launches the entry by itself
+    pub autostart: Vec<AutostartRule>,
+    pub hooks: EntryHooks,
+    pub minecraft: Option<MinecraftPolicy>,
+    pub wine: Option<WinePolicy>,
+    /// Stop sessions by command name too, for apps that escape their
+    /// process group and have no cgroup of their own
+    pub kill_by_name: bool,
+    /// Block keyboard and pointer input for the session, so only game
+    /// controllers reach the app
+    pub controller_only: bool,
     pub disabled: bool,
     pub disabled_reason: Option<String>,
 }
@@ -151,14 +494,22 @@ impl Entry {
             .map(|l| convert_limits(l, default_max_run))
             .unwrap_or_else(|| LimitsPolicy {
                 max_run: default_max_run,
+                min_run: None,
                 daily_quota: None, // None means unlimited
                 cooldown: None,
+                cooldown_scope: CooldownScope::Entry,
+                cooldown_after: None,
+                min_gap: None,
+                refund_under: None,
+                refund_crash_under: None,
             });
         let warnings = raw
             .warnings
             .map(|w| w.into_iter().map(convert_warning).collect())
             .unwrap_or_else(|| default_warnings.to_vec());
         let volume = raw.volume.as_ref().map(convert_volume_config);
+        let gamescope = raw.gamescope.map(convert_gamescope_config);
+        let prechecks = raw.prechecks.map(convert_prechecks).unwrap_or_default();
 
         Self {
             id: EntryId::new(raw.id),
@@ -169,10 +520,32 @@ impl Entry {
             limits,
             warnings,
             volume,
+            gamescope,
+            device_access: DeviceAccess {
+                camera: raw.allow_camera,
+                microphone: raw.allow_microphone,
+            },
+            tags: raw.tags,
+            prechecks,
+            confirm_launch: raw.confirm_launch,
+            autostart: raw.autostart.into_iter().map(convert_autostart).collect(),
+            hooks: raw.hooks.map(convert_hooks).unwrap_or_default(),
+            minecraft: raw.minecraft.map(convert_minecraft),
+            wine: raw.wine.map(|wine| WinePolicy {
+                prefix: PathBuf::from(wine.prefix),
+                start_timeout: Duration::from_secs(wine.start_timeout_seconds),
+            }),
+            kill_by_name: raw.kill_by_name,
+            controller_only: raw.controller_only,
             disabled: raw.disabled,
             disabled_reason: raw.disabled_reason,
         }
     }
+
+    /// Check if the entry has a tag
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
 }
 
 /// When an entry is available
@@ -185,26 +558,27 @@ pub struct AvailabilityPolicy {
 }
 
 impl AvailabilityPolicy {
-    /// Check if available at given local time
-    pub fn is_available(&self, dt: &chrono::DateTime<chrono::Local>) -> bool {
+    /// Check if available at given time, with windows read in `tz`
+    pub fn is_available(&self, tz: &Timezone, dt: &chrono::DateTime<chrono::Local>) -> bool {
         if self.always {
             return true;
         }
         if self.windows.is_empty() {
             return true; // No windows = always available
         }
-        self.windows.iter().any(|w| w.contains(dt))
+        self.windows.iter().any(|w| w.contains_in(tz, dt))
     }
 
     /// Get remaining time in current window
     pub fn remaining_in_window(
         &self,
+        tz: &Timezone,
         dt: &chrono::DateTime<chrono::Local>,
     ) -> Option<Duration> {
         if self.always {
             return None; // No limit from windows
         }
-        self.windows.iter().find_map(|w| w.remaining_duration(dt))
+        self.windows.iter().find_map(|w| w.remaining_duration_in(tz, dt))
     }
 }
 
@@ -213,9 +587,24 @@ impl AvailabilityPolicy {
 pub struct LimitsPolicy {
     /// Maximum run duration. None means unlimited.
     pub max_run: Option<Duration>,
-    /// Daily quota. None means unlimited.
+    /// Don't start a session with less time than this left
+    pub min_run: Option<Duration>,
+    /// Daily quota. None means unlimited. Retro entries playing the same
+    /// ROM count their use together against it.
     pub daily_quota: Option<Duration>,
     pub cooldown: Option<Duration>,
+    /// What the cooldown pauses
+    pub cooldown_scope: CooldownScope,
+    /// Start the cooldown only after each this much use today in the scope.
+    /// None means after every session.
+    pub cooldown_after: Option<Duration>,
+    /// Time between the end of one session of the entry and the start of
+    /// the next, even when the session was refunded or set no cooldown
+    pub min_gap: Option<Duration>,
+    /// Sessions shorter than this aren't charged (unless they expired)
+    pub refund_under: Option<Duration>,
+    /// Sessions that exit with an error before this aren't charged
+    pub refund_crash_under: Option<Duration>,
 }
 
 /// Volume control policy
@@ -229,6 +618,15 @@ pub struct VolumePolicy {
     pub allow_mute: bool,
     /// Whether volume changes are allowed at all
     pub allow_change: bool,
+    /// Sound may only play through headphones. The global setting applies
+    /// on top of an entry's own policy.
+    pub require_headphones: bool,
+    /// Percentage of its level the session's sound is turned down to while
+    /// a warning is issued. An entry without one uses the global setting.
+    pub duck_warnings: Option<u8>,
+    /// Quiet hours; the first match wins. Only the global policy has them,
+    /// and they apply on top of an entry's own policy.
+    pub windows: Vec<VolumeWindow>,
 }
 
 impl VolumePolicy {
@@ -239,6 +637,9 @@ impl VolumePolicy {
             min_volume: None,
             allow_mute: true,
             allow_change: true,
+            require_headphones: false,
+            duck_warnings: None,
+            windows: Vec::new(),
         }
     }
 
@@ -248,6 +649,310 @@ impl VolumePolicy {
         let max = self.max_volume.unwrap_or(100);
         percent.clamp(min, max)
     }
+
+    /// The quiet hours in effect at the given time, with windows read in `tz`
+    pub fn quiet_hours_at(
+        &self,
+        tz: &Timezone,
+        dt: &chrono::DateTime<chrono::Local>,
+    ) -> QuietHours {
+        self.windows
+            .iter()
+            .find(|w| w.window.contains_in(tz, dt))
+            .map(|w| w.limits)
+            .unwrap_or_default()
+    }
+}
+
+/// Volume limits applied during a time window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeWindow {
+    pub window: TimeWindow,
+    pub limits: QuietHours,
+}
+
+/// What quiet hours add to the volume policy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuietHours {
+    /// Maximum volume percentage
+    pub max_volume: Option<u8>,
+    /// Sound may only play through headphones
+    pub require_headphones: bool,
+}
+
+/// Display brightness and night-light schedule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayPolicy {
+    /// Brightness outside of any window. None leaves the backlight alone.
+    pub default_brightness: Option<u8>,
+    /// Night-light color temperature in Kelvin
+    pub night_light_temperature: u32,
+    /// Windows with their own settings; the first match wins
+    pub windows: Vec<DisplayWindow>,
+}
+
+impl Default for DisplayPolicy {
+    fn default() -> Self {
+        Self {
+            default_brightness: None,
+            night_light_temperature: 4000,
+            windows: Vec::new(),
+        }
+    }
+}
+
+impl DisplayPolicy {
+    /// Whether the policy never changes the display
+    pub fn is_empty(&self) -> bool {
+        self.default_brightness.is_none() && self.windows.is_empty()
+    }
+
+    /// Get the display settings that apply at the given time, with windows read in `tz`
+    pub fn settings_at(
+        &self,
+        tz: &Timezone,
+        dt: &chrono::DateTime<chrono::Local>,
+    ) -> DisplaySettings {
+        match self.windows.iter().find(|w| w.window.contains_in(tz, dt)) {
+            Some(window) => DisplaySettings {
+                brightness: window.brightness.or(self.default_brightness),
+                night_light: window.night_light.then_some(self.night_light_temperature),
+            },
+            None => DisplaySettings {
+                brightness: self.default_brightness,
+                night_light: None,
+            },
+        }
+    }
+}
+
+/// Display settings applied during a time window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayWindow {
+    pub window: TimeWindow,
+    /// Brightness percentage. None falls back to the default brightness.
+    pub brightness: Option<u8>,
+    pub night_light: bool,
+}
+
+/// Display settings in effect at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplaySettings {
+    /// Backlight brightness percentage. None leaves the backlight alone.
+    pub brightness: Option<u8>,
+    /// Night-light color temperature in Kelvin, or None when disabled
+    pub night_light: Option<u32>,
+}
+
+/// Curfew power management
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurfewPolicy {
+    /// When the curfew is in effect
+    pub window: TimeWindow,
+    pub action: PowerAction,
+    /// Countdown before the action once the machine is idle
+    pub delay: Duration,
+}
+
+/// Recurring downtime that overrides every entry's availability
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DowntimePolicy {
+    pub window: TimeWindow,
+    pub label: Option<String>,
+}
+
+/// Low-battery thresholds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatteryPolicy {
+    /// Warn below this percentage while discharging
+    pub warn_below: u8,
+    /// End the current session below this percentage while discharging.
+    /// None means never.
+    pub stop_below: Option<u8>,
+}
+
+impl Default for BatteryPolicy {
+    fn default() -> Self {
+        Self {
+            warn_below: 20,
+            stop_below: None,
+        }
+    }
+}
+
+/// When the session's media players are paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MediaPolicy {
+    /// Pause while a warning is issued, then resume
+    pub pause_on_warnings: bool,
+    /// Pause before stopping an expired session
+    pub pause_on_expiry: bool,
+}
+
+impl Default for MediaPolicy {
+    fn default() -> Self {
+        Self {
+            pause_on_warnings: false,
+            pause_on_expiry: true,
+        }
+    }
+}
+
+/// Star thresholds and what they unlock
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RewardsPolicy {
+    /// Sorted by stars, lowest first
+    pub thresholds: Vec<RewardThreshold>,
+}
+
+/// What earning a number of stars unlocks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RewardThreshold {
+    pub stars: u32,
+    pub unlocks: Vec<EntryId>,
+    pub bonus: Duration,
+}
+
+impl RewardsPolicy {
+    /// Stars needed before an entry can be launched, if it is a reward
+    pub fn required_stars(&self, entry_id: &EntryId) -> Option<u32> {
+        self.thresholds
+            .iter()
+            .find(|t| t.unlocks.contains(entry_id))
+            .map(|t| t.stars)
+    }
+
+    /// Extra daily quota earned with the given star total
+    pub fn bonus_quota(&self, stars: u32) -> Duration {
+        self.thresholds
+            .iter()
+            .filter(|t| t.stars <= stars)
+            .map(|t| t.bonus)
+            .sum()
+    }
+}
+
+/// Exchange rate from learning time to game time
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeExchangePolicy {
+    /// Entries with this tag earn credit
+    pub earn_tag: String,
+    /// Entries with this tag get the credit added to their daily quota
+    pub spend_tag: String,
+    /// Credited time per unit of earning time
+    pub ratio: f64,
+    /// Cap on credit per day. None means unlimited.
+    pub max_earned: Option<Duration>,
+}
+
+impl TimeExchangePolicy {
+    /// Credit earned for a session, before the daily cap
+    pub fn credit_for(&self, spent: Duration) -> Duration {
+        // Whole seconds keep the store's accounting exact
+        Duration::from_secs((spent.as_secs_f64() * self.ratio) as u64)
+    }
+}
+
+/// Snap/Flatpak update scheduling
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenancePolicy {
+    /// Entries whose apps are updated
+    pub entries: Vec<EntryId>,
+    /// Minimum time between updates of an entry
+    pub interval: Duration,
+    /// How long an update waits for the entry's availability windows to close
+    pub max_defer: Duration,
+}
+
+/// Launch an entry at a fixed time, e.g. white noise at 19:30 on school nights
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutostartRule {
+    pub days: DaysOfWeek,
+    pub at: WallClock,
+}
+
+/// Commands run around an entry's sessions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryHooks {
+    pub pre_launch: Option<HookPolicy>,
+    pub post_exit: Option<HookPolicy>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookPolicy {
+    pub command: String,
+    pub args: Vec<String>,
+    pub timeout: Duration,
+    pub on_failure: HookFailure,
+}
+
+/// Minecraft settings written into the game directory before each launch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinecraftPolicy {
+    pub game_dir: PathBuf,
+    /// The only multiplayer servers listed. None leaves the list alone.
+    pub servers: Option<Vec<MinecraftServer>>,
+    pub disable_chat: bool,
+    /// Copies of replaced files to keep
+    pub backups: usize,
+}
+
+/// Proton/WINE games followed through their prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WinePolicy {
+    pub prefix: PathBuf,
+    /// Wait this long for the game once the launched command exits
+    pub start_timeout: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinecraftServer {
+    pub name: String,
+    pub address: String,
+}
+
+/// What a failing hook does to the launch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookFailure {
+    /// Refuse the launch
+    Abort,
+    /// Log and carry on
+    Continue,
+}
+
+/// Host checks run before a launch is approved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrecheckPolicy {
+    /// Check that the program exists and is executable
+    pub check_binary: bool,
+    /// Environment variables that must be set
+    pub require_env: Vec<String>,
+    /// Minimum free disk space: filesystem path and megabytes
+    pub min_free_disk: Option<(PathBuf, u64)>,
+    /// Require a reachable Wayland or X11 display
+    pub require_display: bool,
+}
+
+impl Default for PrecheckPolicy {
+    fn default() -> Self {
+        Self {
+            check_binary: true,
+            require_env: Vec::new(),
+            min_free_disk: None,
+            require_display: false,
+        }
+    }
+}
+
+/// Gamescope nested compositor settings for an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GamescopePolicy {
+    /// Fixed output resolution (width, height)
+    pub resolution: Option<(u32, u32)>,
+    /// Frame rate cap
+    pub fps_limit: Option<u32>,
+    pub fullscreen: bool,
+    pub extra_args: Vec<String>,
 }
 
 // Conversion helpers
@@ -259,6 +964,7 @@ fn convert_entry_kind(raw: RawEntryKind) -> EntryKind {
         RawEntryKind::Flatpak { app_id, args, env } => EntryKind::Flatpak { app_id, args, env },
         RawEntryKind::Vm { driver, args } => EntryKind::Vm { driver, args },
         RawEntryKind::Media { library_id, args } => EntryKind::Media { library_id, args },
+        RawEntryKind::Retro { core, rom_path } => EntryKind::Retro { core, rom_path },
         RawEntryKind::Custom { type_name, payload } => EntryKind::Custom {
             type_name,
             payload: payload.unwrap_or(serde_json::Value::Null),
@@ -280,10 +986,237 @@ fn convert_volume_config(raw: &RawVolumeConfig) -> VolumePolicy {
         min_volume: raw.min_volume,
         allow_mute: raw.allow_mute,
         allow_change: raw.allow_change,
+        require_headphones: raw.require_headphones,
+        duck_warnings: raw.duck_warnings,
+        windows: raw
+            .windows
+            .iter()
+            .map(|w| VolumeWindow {
+                window: convert_time_window(RawTimeWindow {
+                    days: w.days.clone(),
+                    start: w.start.clone(),
+                    end: w.end.clone(),
+                }),
+                limits: QuietHours {
+                    max_volume: w.max_volume,
+                    require_headphones: w.require_headphones,
+                },
+            })
+            .collect(),
+    }
+}
+
+fn convert_display_config(raw: RawDisplayConfig) -> DisplayPolicy {
+    let defaults = DisplayPolicy::default();
+    DisplayPolicy {
+        default_brightness: raw.default_brightness,
+        night_light_temperature: raw
+            .night_light_temperature
+            .unwrap_or(defaults.night_light_temperature),
+        windows: raw
+            .windows
+            .into_iter()
+            .map(|w| DisplayWindow {
+                window: convert_time_window(RawTimeWindow {
+                    days: w.days,
+                    start: w.start,
+                    end: w.end,
+                }),
+                brightness: w.brightness,
+                night_light: w.night_light,
+            })
+            .collect(),
+    }
+}
+
+fn convert_profile(raw: RawProfile) -> Profile {
+    Profile {
+        id: ProfileId::new(raw.id),
+        label: raw.label,
+        weekly_budget: raw.weekly_budget_minutes.map(|m| Duration::from_secs(m * 60)),
+        budget_alerts: raw.budget_alerts.into_iter().map(convert_budget_alert).collect(),
+    }
+}
+
+fn convert_seat(raw: RawSeat) -> Seat {
+    Seat {
+        id: SeatId::new(raw.id),
+        label: raw.label,
+        wayland_display: raw.wayland_display,
+    }
+}
+
+fn convert_budget_alert(raw: RawBudgetAlert) -> BudgetAlert {
+    BudgetAlert {
+        percent: raw.percent,
+        by: raw.by.and_then(|day| day.parse().ok()),
+    }
+}
+
+fn convert_time_exchange_config(raw: RawTimeExchangeConfig) -> TimeExchangePolicy {
+    TimeExchangePolicy {
+        earn_tag: raw.earn_tag,
+        spend_tag: raw.spend_tag,
+        ratio: raw.ratio,
+        max_earned: raw.max_earned_minutes.map(|m| Duration::from_secs(m * 60)),
+    }
+}
+
+fn convert_maintenance_config(raw: RawMaintenanceConfig) -> MaintenancePolicy {
+    MaintenancePolicy {
+        entries: raw.entries.into_iter().map(EntryId::new).collect(),
+        interval: Duration::from_secs(raw.interval_hours.unwrap_or(24) * 3600),
+        max_defer: Duration::from_secs(raw.max_defer_hours.unwrap_or(72) * 3600),
+    }
+}
+
+fn convert_calendar_config(raw: RawCalendarConfig) -> CalendarPolicy {
+    CalendarPolicy {
+        url: raw.url,
+        poll_interval: Duration::from_secs(raw.poll_interval_minutes.unwrap_or(15) * 60),
+    }
+}
+
+fn convert_self_update_config(raw: RawSelfUpdateConfig) -> SelfUpdatePolicy {
+    SelfUpdatePolicy {
+        feed: raw.feed,
+        check_interval: Duration::from_secs(raw.check_interval_hours.unwrap_or(24) * 3600),
+        stage: raw.stage,
+        install_path: raw.install_path,
+        public_key: raw.public_key,
+    }
+}
+
+fn convert_federation_config(raw: RawFederationConfig) -> FederationPolicy {
+    FederationPolicy {
+        device: raw.device,
+        secret: raw.secret,
+        listen: raw.listen.and_then(|addr| addr.parse().ok()),
+        peers: raw.peers,
+        sync_interval: raw
+            .sync_interval_seconds
+            .map_or(DEFAULT_FEDERATION_SYNC_INTERVAL, Duration::from_secs),
+    }
+}
+
+fn convert_rewards(raw: &[RawRewardThreshold]) -> RewardsPolicy {
+    let mut thresholds: Vec<_> = raw
+        .iter()
+        .map(|r| RewardThreshold {
+            stars: r.stars,
+            unlocks: r.unlock.iter().map(EntryId::new).collect(),
+            bonus: Duration::from_secs(r.bonus_minutes.unwrap_or(0) * 60),
+        })
+        .collect();
+    thresholds.sort_by_key(|t| t.stars);
+    RewardsPolicy { thresholds }
+}
+
+fn convert_battery_config(raw: &RawBatteryConfig) -> BatteryPolicy {
+    BatteryPolicy {
+        warn_below: raw
+            .warn_below_percent
+            .unwrap_or(BatteryPolicy::default().warn_below),
+        stop_below: raw.stop_below_percent,
+    }
+}
+
+fn convert_media_config(raw: &RawMediaConfig) -> MediaPolicy {
+    MediaPolicy {
+        pause_on_warnings: raw.pause_on_warnings,
+        pause_on_expiry: raw
+            .pause_on_expiry
+            .unwrap_or(MediaPolicy::default().pause_on_expiry),
+    }
+}
+
+fn convert_curfew_config(raw: RawCurfewConfig) -> CurfewPolicy {
+    CurfewPolicy {
+        window: convert_time_window(RawTimeWindow {
+            days: raw.days.unwrap_or_else(|| RawDays::Preset("all".into())),
+            start: raw.start,
+            end: raw.end,
+        }),
+        action: parse_power_action(&raw.action).unwrap_or(PowerAction::Poweroff),
+        delay: Duration::from_secs(raw.delay_minutes * 60),
+    }
+}
+
+fn convert_hooks(raw: RawHooks) -> EntryHooks {
+    let convert = |raw: RawHook, default_failure| HookPolicy {
+        command: raw.command,
+        args: raw.args,
+        timeout: Duration::from_secs(raw.timeout_seconds),
+        on_failure: raw
+            .on_failure
+            .as_deref()
+            .and_then(parse_hook_failure)
+            .unwrap_or(default_failure),
+    };
+    EntryHooks {
+        pre_launch: raw.pre_launch.map(|h| convert(h, HookFailure::Abort)),
+        post_exit: raw.post_exit.map(|h| convert(h, HookFailure::Continue)),
+    }
+}
+
+fn convert_autostart(raw: RawAutostart) -> AutostartRule {
+    let days = raw.days.as_ref().and_then(|d| parse_days(d).ok()).unwrap_or(0x7F);
+    let (hour, minute) = parse_time(&raw.at).unwrap_or((0, 0));
+    AutostartRule {
+        days: DaysOfWeek::new(days),
+        at: WallClock::new(hour, minute).unwrap(),
+    }
+}
+
+fn convert_downtime(raw: RawDowntime) -> DowntimePolicy {
+    DowntimePolicy {
+        window: convert_time_window(RawTimeWindow {
+            days: raw.days.unwrap_or_else(|| RawDays::Preset("all".into())),
+            start: raw.start,
+            end: raw.end,
+        }),
+        label: raw.label,
+    }
+}
+
+fn convert_minecraft(raw: RawMinecraftConfig) -> MinecraftPolicy {
+    MinecraftPolicy {
+        game_dir: PathBuf::from(raw.game_dir),
+        servers: raw.servers.map(|servers| {
+            servers
+                .into_iter()
+                .map(|s| MinecraftServer {
+                    name: s.name,
+                    address: s.address,
+                })
+                .collect()
+        }),
+        disable_chat: raw.disable_chat,
+        backups: raw.backups,
+    }
+}
+
+fn convert_prechecks(raw: RawPrechecks) -> PrecheckPolicy {
+    PrecheckPolicy {
+        check_binary: raw.check_binary,
+        require_env: raw.require_env,
+        min_free_disk: raw.min_free_disk_mb.map(|mb| {
+            (raw.disk_path.unwrap_or_else(|| PathBuf::from("~")), mb)
+        }),
+        require_display: raw.require_display,
+    }
+}
+
+fn convert_gamescope_config(raw: RawGamescopeConfig) -> GamescopePolicy {
+    GamescopePolicy {
+        resolution: raw.width.zip(raw.height),
+        fps_limit: raw.fps_limit,
+        fullscreen: raw.fullscreen,
+        extra_args: raw.extra_args,
     }
 }
 
-fn convert_time_window(raw: crate::schema::RawTimeWindow) -> TimeWindow {
+pub(crate) fn convert_time_window(raw: crate::schema::RawTimeWindow) -> TimeWindow {
     let days_mask = parse_days(&raw.days).unwrap_or(0x7F);
     let (start_h, start_m) = parse_time(&raw.start).unwrap_or((0, 0));
     let (end_h, end_m) = parse_time(&raw.end).unwrap_or((23, 59));
@@ -310,14 +1243,24 @@ fn convert_limits(raw: crate::schema::RawLimits, default_max_run: Option<Duratio
             .max_run_seconds
             .map(seconds_to_duration_or_unlimited)
             .unwrap_or(default_max_run),
+        min_run: raw.min_run_seconds.map(Duration::from_secs),
         daily_quota: raw
             .daily_quota_seconds
             .and_then(seconds_to_duration_or_unlimited),
         cooldown: raw.cooldown_seconds.map(Duration::from_secs),
+        cooldown_scope: raw
+            .cooldown_scope
+            .as_deref()
+            .and_then(parse_cooldown_scope)
+            .unwrap_or_default(),
+        cooldown_after: raw.cooldown_after_seconds.map(Duration::from_secs),
+        min_gap: raw.min_gap_between_sessions_seconds.map(Duration::from_secs),
+        refund_under: raw.refund_under_seconds.map(Duration::from_secs),
+        refund_crash_under: raw.refund_crash_under_seconds.map(Duration::from_secs),
     }
 }
 
-fn convert_warning(raw: RawWarningThreshold) -> WarningThreshold {
+pub(crate) fn convert_warning(raw: RawWarningThreshold) -> WarningThreshold {
     let severity = match raw.severity.to_lowercase().as_str() {
         "info" => WarningSeverity::Info,
         "critical" => WarningSeverity::Critical,
@@ -364,7 +1307,7 @@ mod tests {
         };
 
         let dt = shepherd_util::now();
-        assert!(policy.is_available(&dt));
+        assert!(policy.is_available(&Timezone::Local, &dt));
     }
 
     #[test]
@@ -380,10 +1323,10 @@ mod tests {
 
         // 3 PM should be available
         let dt = Local.with_ymd_and_hms(2025, 12, 26, 15, 0, 0).unwrap();
-        assert!(policy.is_available(&dt));
+        assert!(policy.is_available(&Timezone::Local, &dt));
 
         // 10 AM should not be available
         let dt = Local.with_ymd_and_hms(2025, 12, 26, 10, 0, 0).unwrap();
-        assert!(!policy.is_available(&dt));
+        assert!(!policy.is_available(&Timezone::Local, &dt));
     }
 }
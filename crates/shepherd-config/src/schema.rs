@@ -1,7 +1,7 @@
 //! Raw configuration schema (as parsed from TOML)
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 
 /// Raw configuration as parsed from TOML
@@ -17,6 +17,70 @@ pub struct RawConfig {
     /// List of allowed entries
     #[serde(default)]
     pub entries: Vec<RawEntry>,
+
+    /// Child profiles that sessions can be attributed to
+    #[serde(default)]
+    pub profiles: Vec<RawProfile>,
+
+    /// Seats besides the default `seat0`, each with its own display,
+    /// launcher and HUD, and its own session
+    #[serde(default)]
+    pub seats: Vec<RawSeat>,
+
+    /// How `shepherdctl import` reads exports from other parental controls
+    #[serde(default)]
+    pub import: RawImportConfig,
+}
+
+/// Importing usage history from other parental controls
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RawImportConfig {
+    /// App names as they appear in exports, mapped to entry IDs. Names not
+    /// listed match an entry with that ID or label, ignoring case.
+    #[serde(default)]
+    pub apps: BTreeMap<String, String>,
+}
+
+/// An extra seat
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawSeat {
+    /// Seat name, as given to its launcher and HUD with `--seat`
+    pub id: String,
+
+    /// Display name
+    pub label: Option<String>,
+
+    /// Wayland display sessions on this seat open on (e.g. "wayland-1").
+    /// Default: the service's own, like `seat0`
+    pub wayland_display: Option<String>,
+}
+
+/// Child profile
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawProfile {
+    /// Unique stable ID
+    pub id: String,
+
+    /// Display name
+    pub label: String,
+
+    /// Time this profile is expected to use in a week (Monday to Sunday),
+    /// in minutes. Only used for `budget_alerts`; nothing is blocked.
+    pub weekly_budget_minutes: Option<u64>,
+
+    /// Tell parents when the week's budget is being used up
+    #[serde(default)]
+    pub budget_alerts: Vec<RawBudgetAlert>,
+}
+
+/// Alert when a profile has used part of its weekly budget
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawBudgetAlert {
+    /// Share of `weekly_budget_minutes` used, 1-100
+    pub percent: u8,
+
+    /// Only alert if it happens by the end of this day ("wed"); any day if unset
+    pub by: Option<String>,
 }
 
 /// Service-level settings
@@ -39,6 +103,51 @@ pub struct RawServiceConfig {
     /// Directory for child application logs (default: log_dir/sessions)
     pub child_log_dir: Option<PathBuf>,
 
+    /// Ask "How do you feel?" in the launcher after each session
+    #[serde(default)]
+    pub session_check_in: bool,
+
+    /// How long the launcher shows what a session used after it ends, in
+    /// seconds (default: 15, 0 goes straight back to the grid)
+    pub session_summary_seconds: Option<u64>,
+
+    /// How joint sessions charge participants: "duplicate" (default) or "split"
+    pub joint_usage: Option<String>,
+
+    /// How often to probe entries for broken installs (default: 600, 0 disables)
+    pub health_check_interval_seconds: Option<u64>,
+
+    /// Longest wait between engine ticks when nothing is due sooner, in
+    /// milliseconds (default: 10000, at least 100)
+    pub max_tick_interval_ms: Option<u64>,
+
+    /// Hashed parent PIN ("sha256$<salt>$<digest>"), set by the setup wizard
+    pub admin_pin: Option<String>,
+
+    /// Programs allowed to connect as the launcher or HUD, by absolute path.
+    /// Other programs run by the child's user get read-only access.
+    /// Empty (default): any program of a non-admin user is trusted as a shell
+    #[serde(default)]
+    pub shell_executables: Vec<PathBuf>,
+
+    /// Close the kiosk escape routes found at startup where possible: turn
+    /// off login prompts on other VTs, unbind Sway keys that open terminals,
+    /// disable the GTK inspector shortcut (default: false, only report them)
+    #[serde(default)]
+    pub mitigate_escape_routes: bool,
+
+    /// Sway key combination that force-stops every running session and
+    /// returns to the launcher, e.g. "Mod4+Ctrl+BackSpace" (default: none)
+    pub panic_key: Option<String>,
+
+    /// Look of the launcher and HUD: "dark" (default), "light",
+    /// "high_contrast" or "seasonal"
+    pub theme: Option<String>,
+
+    /// Timezone for availability, display and curfew windows: an IANA name
+    /// ("Europe/Berlin") or POSIX TZ string (default: system timezone)
+    pub timezone: Option<String>,
+
     /// Default warning thresholds (can be overridden per entry)
     pub default_warnings: Option<Vec<RawWarningThreshold>>,
 
@@ -48,6 +157,260 @@ pub struct RawServiceConfig {
     /// Global volume restrictions
     #[serde(default)]
     pub volume: Option<RawVolumeConfig>,
+
+    /// Scheduled display brightness and night light
+    #[serde(default)]
+    pub display: Option<RawDisplayConfig>,
+
+    /// Power off or suspend the machine when idle during curfew
+    #[serde(default)]
+    pub curfew: Option<RawCurfewConfig>,
+
+    /// Recurring blocks (homework hours) when no entry can be launched
+    #[serde(default)]
+    pub downtime: Vec<RawDowntime>,
+
+    /// Low-battery warnings and session stop
+    #[serde(default)]
+    pub battery: Option<RawBatteryConfig>,
+
+    /// Pausing the session's media players (MPRIS)
+    #[serde(default)]
+    pub media: Option<RawMediaConfig>,
+
+    /// Star thresholds that unlock entries or bonus minutes
+    #[serde(default)]
+    pub rewards: Vec<RawRewardThreshold>,
+
+    /// Learning time that earns game time
+    #[serde(default)]
+    pub time_exchange: Option<RawTimeExchangeConfig>,
+
+    /// Automatic snap/flatpak updates between sessions
+    #[serde(default)]
+    pub maintenance: Option<RawMaintenanceConfig>,
+
+    /// Checking for new shepherdd releases
+    #[serde(default)]
+    pub self_update: Option<RawSelfUpdateConfig>,
+
+    /// Family calendar whose events open or close entries
+    #[serde(default)]
+    pub calendar: Option<RawCalendarConfig>,
+
+    /// Sharing usage with shepherdd on the family's other devices, so
+    /// quotas count time spent on all of them
+    #[serde(default)]
+    pub federation: Option<RawFederationConfig>,
+}
+
+/// Usage sharing with other devices running shepherdd
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawFederationConfig {
+    /// Name of this device, different on every federated device
+    pub device: String,
+
+    /// Shared secret that signs every message, the same on every device
+    /// (at least 16 characters)
+    pub secret: String,
+
+    /// Address to accept peers on, like "0.0.0.0:7343" (default: none,
+    /// only connect out)
+    pub listen: Option<String>,
+
+    /// Devices to sync with, as "host:port"
+    #[serde(default)]
+    pub peers: Vec<String>,
+
+    /// Seconds between syncs with each peer (default: 60)
+    pub sync_interval_seconds: Option<u64>,
+}
+
+/// iCalendar feed with one-off availability exceptions
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawCalendarConfig {
+    /// http(s) URL, file:// URL or absolute path of an .ics feed (for a
+    /// CalDAV calendar, its export URL)
+    pub url: String,
+
+    /// Minutes between fetches (default: 15)
+    pub poll_interval_minutes: Option<u64>,
+}
+
+/// Release feed for shepherdd itself
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawSelfUpdateConfig {
+    /// https URL, file:// URL or absolute path of the release feed
+    pub feed: String,
+
+    /// Hours between checks (default: 24)
+    pub check_interval_hours: Option<u64>,
+
+    /// Download and verify new releases, then install them when no session
+    /// is running (default: false, only report them)
+    #[serde(default)]
+    pub stage: bool,
+
+    /// Binary to replace when installing (default: the running executable)
+    pub install_path: Option<PathBuf>,
+
+    /// Minisign public key releases must be signed with. Required to stage.
+    pub public_key: Option<String>,
+}
+
+/// Snap/Flatpak update scheduling
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawMaintenanceConfig {
+    /// Snap or Flatpak entries to keep updated
+    pub entries: Vec<String>,
+
+    /// Minimum hours between updates of an entry (default: 24)
+    pub interval_hours: Option<u64>,
+
+    /// Hours an update may wait for the entry to be outside its availability
+    /// windows before it runs anyway between sessions (default: 72)
+    pub max_defer_hours: Option<u64>,
+}
+
+/// Exchange rate from time in one tag to extra quota in another
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawTimeExchangeConfig {
+    /// Tag whose session time earns credit (default: "education")
+    #[serde(default = "default_earn_tag")]
+    pub earn_tag: String,
+
+    /// Tag whose daily quota receives the credit (default: "games")
+    #[serde(default = "default_spend_tag")]
+    pub spend_tag: String,
+
+    /// Minutes credited per minute earned, e.g. 0.5
+    pub ratio: f64,
+
+    /// Cap on minutes credited per day (default: unlimited)
+    pub max_earned_minutes: Option<u64>,
+}
+
+fn default_earn_tag() -> String {
+    "education".to_string()
+}
+
+fn default_spend_tag() -> String {
+    "games".to_string()
+}
+
+/// What earning a number of stars unlocks
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawRewardThreshold {
+    /// Total stars needed
+    pub stars: u32,
+
+    /// Entry IDs that stay locked until this threshold is reached
+    #[serde(default)]
+    pub unlock: Vec<String>,
+
+    /// Extra minutes added to every daily quota once reached
+    pub bonus_minutes: Option<u64>,
+}
+
+/// Low-battery policy
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RawBatteryConfig {
+    /// Warn in the HUD below this percentage (default: 20)
+    pub warn_below_percent: Option<u8>,
+
+    /// End the current session gracefully below this percentage (default: never)
+    pub stop_below_percent: Option<u8>,
+}
+
+/// When to pause the session's media players
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RawMediaConfig {
+    /// Pause while a warning is issued and resume afterwards (default: false)
+    #[serde(default)]
+    pub pause_on_warnings: bool,
+
+    /// Pause before an expired session is stopped, so the sound stops at
+    /// once (default: true)
+    pub pause_on_expiry: Option<bool>,
+}
+
+/// Curfew power management
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawCurfewConfig {
+    /// Days of week the curfew applies (default: all)
+    pub days: Option<RawDays>,
+
+    /// Curfew start time (HH:MM format)
+    pub start: String,
+
+    /// Curfew end time (HH:MM format)
+    pub end: String,
+
+    /// Action: "poweroff" or "suspend"
+    #[serde(default = "default_power_action")]
+    pub action: String,
+
+    /// Minutes to count down once the machine is idle during curfew
+    #[serde(default = "default_curfew_delay_minutes")]
+    pub delay_minutes: u64,
+}
+
+/// Recurring downtime block
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawDowntime {
+    /// Days of week (default: all)
+    pub days: Option<RawDays>,
+
+    /// Start time (HH:MM format)
+    pub start: String,
+
+    /// End time (HH:MM format)
+    pub end: String,
+
+    /// Shown on the launcher tiles, e.g. "Homework"
+    pub label: Option<String>,
+}
+
+fn default_power_action() -> String {
+    "poweroff".to_string()
+}
+
+fn default_curfew_delay_minutes() -> u64 {
+    10
+}
+
+/// Display brightness and night-light schedule
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RawDisplayConfig {
+    /// Brightness percentage outside of any window (default: leave unchanged)
+    pub default_brightness: Option<u8>,
+
+    /// Night-light color temperature in Kelvin (default: 4000)
+    pub night_light_temperature: Option<u32>,
+
+    /// Time windows with their own display settings; the first match wins
+    #[serde(default)]
+    pub windows: Vec<RawDisplayWindow>,
+}
+
+/// Display settings applied during a time window
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawDisplayWindow {
+    /// Days of week: "weekdays", "weekends", "all", or list like ["mon", "tue", "wed"]
+    pub days: RawDays,
+
+    /// Start time (HH:MM format)
+    pub start: String,
+
+    /// End time (HH:MM format)
+    pub end: String,
+
+    /// Brightness percentage during this window
+    pub brightness: Option<u8>,
+
+    /// Enable the night-light color filter during this window
+    #[serde(default)]
+    pub night_light: bool,
 }
 
 /// Raw entry definition
@@ -81,6 +444,56 @@ pub struct RawEntry {
     #[serde(default)]
     pub volume: Option<RawVolumeConfig>,
 
+    /// Run this entry inside a gamescope nested compositor
+    #[serde(default)]
+    pub gamescope: Option<RawGamescopeConfig>,
+
+    /// Whether the entry may use the camera (default: true)
+    #[serde(default = "default_true")]
+    pub allow_camera: bool,
+
+    /// Whether the entry may use the microphone (default: true)
+    #[serde(default = "default_true")]
+    pub allow_microphone: bool,
+
+    /// Free-form tags, e.g. "education" or "games"
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Host checks run before a launch is approved
+    #[serde(default)]
+    pub prechecks: Option<RawPrechecks>,
+
+    /// Ask the user to confirm the granted time before launching
+    #[serde(default)]
+    pub confirm_launch: bool,
+
+    /// Times to launch the entry without anyone asking
+    #[serde(default)]
+    pub autostart: Vec<RawAutostart>,
+
+    /// Commands run before launch and after exit
+    #[serde(default)]
+    pub hooks: Option<RawHooks>,
+
+    /// Minecraft server list and chat settings enforced before each launch
+    #[serde(default)]
+    pub minecraft: Option<RawMinecraftConfig>,
+
+    /// Follow a Proton/WINE game through its prefix
+    #[serde(default)]
+    pub wine: Option<RawWineConfig>,
+
+    /// Also stop sessions by killing processes with the entry's command
+    /// name. Can kill unrelated processes with the same name (default: false)
+    #[serde(default)]
+    pub kill_by_name: bool,
+
+    /// Disable keyboard, mouse and touch input while the entry runs, leaving
+    /// only game controllers (default: false)
+    #[serde(default)]
+    pub controller_only: bool,
+
     /// Explicitly disabled
     #[serde(default)]
     pub disabled: bool,
@@ -137,6 +550,13 @@ pub enum RawEntryKind {
         #[serde(default)]
         args: HashMap<String, serde_json::Value>,
     },
+    /// Game played in RetroArch, fullscreen with a libretro core
+    Retro {
+        /// Core library path, or a core name like "snes9x"
+        core: String,
+        /// The game's ROM file
+        rom_path: PathBuf,
+    },
     Custom {
         type_name: String,
         #[serde(default)]
@@ -152,7 +572,7 @@ pub struct RawAvailability {
     pub windows: Vec<RawTimeWindow>,
 
     /// If true, entry is always available (ignores windows)
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub always: bool,
 }
 
@@ -183,11 +603,34 @@ pub struct RawLimits {
     /// Maximum run duration in seconds
     pub max_run_seconds: Option<u64>,
 
+    /// Refuse to launch when less than this many seconds are left in the
+    /// quota or time window
+    pub min_run_seconds: Option<u64>,
+
     /// Daily quota in seconds
     pub daily_quota_seconds: Option<u64>,
 
     /// Cooldown after session ends, in seconds
     pub cooldown_seconds: Option<u64>,
+
+    /// What the cooldown pauses: "entry" (default), "global", or
+    /// "tag:<name>" for every entry with that tag
+    pub cooldown_scope: Option<String>,
+
+    /// Only start the cooldown after each this many seconds of use today
+    /// across the scope, e.g. an hour's pause after every 2 hours of anything
+    pub cooldown_after_seconds: Option<u64>,
+
+    /// Seconds to wait after the entry's last session ended, however it
+    /// ended, before it can start again
+    pub min_gap_between_sessions_seconds: Option<u64>,
+
+    /// Don't charge sessions that end within this many seconds
+    pub refund_under_seconds: Option<u64>,
+
+    /// Don't charge sessions that exit with an error within this many
+    /// seconds of launch (the game crashed)
+    pub refund_crash_under_seconds: Option<u64>,
 }
 
 /// Warning threshold
@@ -224,6 +667,175 @@ pub struct RawVolumeConfig {
     /// Whether volume changes are allowed at all (default: true)
     #[serde(default = "default_true")]
     pub allow_change: bool,
+
+    /// Only play through headphones: launches are refused and the
+    /// speakers muted while sound would come out of them (default: false)
+    #[serde(default)]
+    pub require_headphones: bool,
+
+    /// Turn the running entry's own sound down to this percentage of its
+    /// level for a few seconds when a warning is issued (0-100)
+    pub duck_warnings: Option<u8>,
+
+    /// Quiet hours with a lower volume cap or headphones only; the first
+    /// match wins. Only read from `[service.volume]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<RawVolumeWindow>,
+}
+
+/// Volume limits applied during a time window
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawVolumeWindow {
+    /// Days of week: "weekdays", "weekends", "all", or list like ["mon", "tue", "wed"]
+    pub days: RawDays,
+
+    /// Start time (HH:MM format)
+    pub start: String,
+
+    /// End time (HH:MM format)
+    pub end: String,
+
+    /// Maximum volume percentage during this window (0-100)
+    pub max_volume: Option<u8>,
+
+    /// Only play through headphones during this window (default: false)
+    #[serde(default)]
+    pub require_headphones: bool,
+}
+
+/// Launch pre-checks
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawPrechecks {
+    /// Check that the program exists and is executable (default: true)
+    #[serde(default = "default_true")]
+    pub check_binary: bool,
+
+    /// Environment variables that must be set
+    #[serde(default)]
+    pub require_env: Vec<String>,
+
+    /// Minimum free disk space in megabytes
+    pub min_free_disk_mb: Option<u64>,
+
+    /// Filesystem to check free space on (default: home directory)
+    pub disk_path: Option<PathBuf>,
+
+    /// Require a reachable Wayland or X11 display
+    #[serde(default)]
+    pub require_display: bool,
+}
+
+/// Entry hooks
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RawHooks {
+    /// Run before the session starts
+    pub pre_launch: Option<RawHook>,
+
+    /// Run after the session exits
+    pub post_exit: Option<RawHook>,
+}
+
+/// A hook command
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawHook {
+    pub command: String,
+
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Kill the hook after this long (default: 30)
+    #[serde(default = "default_hook_timeout_seconds")]
+    pub timeout_seconds: u64,
+
+    /// "abort" or "continue" (default: "abort" before launch, "continue"
+    /// after exit)
+    pub on_failure: Option<String>,
+}
+
+fn default_hook_timeout_seconds() -> u64 {
+    30
+}
+
+/// Minecraft settings written into the game directory before each launch
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawMinecraftConfig {
+    /// Directory holding servers.dat and options.txt, e.g. "~/.minecraft"
+    /// or a Prism Launcher instance's ".minecraft"
+    pub game_dir: String,
+
+    /// The only multiplayer servers listed; others are removed at launch.
+    /// Unset leaves the server list alone.
+    pub servers: Option<Vec<RawMinecraftServer>>,
+
+    /// Hide chat (default: false)
+    #[serde(default)]
+    pub disable_chat: bool,
+
+    /// Copies of replaced files kept in "shepherd-backups" (default: 5)
+    #[serde(default = "default_minecraft_backups")]
+    pub backups: usize,
+}
+
+fn default_minecraft_backups() -> usize {
+    5
+}
+
+/// Proton/WINE supervision: the session lasts while any game process of
+/// the prefix runs, and stopping it stops everything in the prefix
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawWineConfig {
+    /// The WINE prefix, e.g. "~/.steam/steam/steamapps/compatdata/<app id>/pfx"
+    pub prefix: String,
+
+    /// How long to wait for the game to start after the launched command
+    /// exits, e.g. while Steam starts it (default: 60)
+    #[serde(default = "default_wine_start_timeout_seconds")]
+    pub start_timeout_seconds: u64,
+}
+
+fn default_wine_start_timeout_seconds() -> u64 {
+    60
+}
+
+/// An allowed Minecraft server
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawMinecraftServer {
+    /// Name shown in the server list
+    pub name: String,
+
+    /// Host, optionally with ":port"
+    pub address: String,
+}
+
+/// Scheduled automatic launch
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawAutostart {
+    /// Days of week (default: all)
+    pub days: Option<RawDays>,
+
+    /// Launch time (HH:MM format)
+    pub at: String,
+}
+
+/// Gamescope wrapper configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RawGamescopeConfig {
+    /// Output width in pixels (requires height)
+    pub width: Option<u32>,
+
+    /// Output height in pixels (requires width)
+    pub height: Option<u32>,
+
+    /// Frame rate cap
+    pub fps_limit: Option<u32>,
+
+    /// Start gamescope fullscreen (default: true)
+    #[serde(default = "default_true")]
+    pub fullscreen: bool,
+
+    /// Extra arguments passed to gamescope before the game command
+    #[serde(default)]
+    pub extra_args: Vec<String>,
 }
 
 fn default_true() -> bool {
@@ -0,0 +1,181 @@
+//! First-run setup
+//!
+//! Builds a starter config file from the choices made in the launcher's setup
+//! wizard. The output is commented TOML in the same layout as
+//! config.example.toml, so parents can keep editing it by hand.
+
+use shepherd_api::EntryKind;
+use shepherd_util::PinHash;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::CURRENT_CONFIG_VERSION;
+
+/// An app picked in the setup wizard
+#[derive(Debug, Clone)]
+pub struct SetupApp {
+    pub id: String,
+    pub label: String,
+    pub icon: Option<String>,
+    pub kind: EntryKind,
+}
+
+/// Choices made in the setup wizard
+#[derive(Debug, Clone, Default)]
+pub struct SetupChoices {
+    pub apps: Vec<SetupApp>,
+    /// Longest single session. None means unlimited.
+    pub max_run: Option<Duration>,
+    /// Daily time allowed per app. None means unlimited.
+    pub daily_quota: Option<Duration>,
+    pub admin_pin: Option<PinHash>,
+}
+
+impl SetupChoices {
+    /// Render the choices as a config file.
+    ///
+    /// Only the kinds app discovery produces (process, snap, flatpak) can be
+    /// written; other kinds are skipped.
+    pub fn to_toml(&self) -> String {
+        let mut out = String::from("# Shepherd configuration, created by first-run setup\n");
+        out.push_str("# See config.example.toml for everything that can be set here\n\n");
+        out.push_str(&format!("config_version = {}\n\n", CURRENT_CONFIG_VERSION));
+
+        out.push_str("[service]\n");
+        out.push_str("# Longest single session; 0 means unlimited\n");
+        out.push_str(&format!(
+            "default_max_run_seconds = {}\n",
+            self.max_run.map_or(0, |d| d.as_secs())
+        ));
+        if let Some(pin) = &self.admin_pin {
+            out.push_str(&format!("admin_pin = {}\n", toml::Value::from(pin.to_string())));
+        }
+
+        for app in &self.apps {
+            let Some(kind) = kind_fields(&app.kind) else {
+                warn!(id = %app.id, kind = ?app.kind.tag(), "Skipping app of unsupported kind");
+                continue;
+            };
+
+            out.push_str("\n[[entries]]\n");
+            out.push_str(&format!("id = {}\n", toml::Value::from(app.id.as_str())));
+            out.push_str(&format!("label = {}\n", toml::Value::from(app.label.as_str())));
+            if let Some(icon) = &app.icon {
+                out.push_str(&format!("icon = {}\n", toml::Value::from(icon.as_str())));
+            }
+
+            out.push_str("\n[entries.kind]\n");
+            for (key, value) in kind {
+                out.push_str(&format!("{} = {}\n", key, value));
+            }
+
+            if let Some(quota) = self.daily_quota {
+                out.push_str("\n[entries.limits]\n");
+                out.push_str(&format!("daily_quota_seconds = {}\n", quota.as_secs()));
+            }
+        }
+
+        out
+    }
+}
+
+/// Key/value pairs for an `[entries.kind]` table
+fn kind_fields(kind: &EntryKind) -> Option<Vec<(&'static str, toml::Value)>> {
+    let (mut fields, args) = match kind {
+        EntryKind::Process { command, args, .. } => (
+            vec![
+                ("type", toml::Value::from("process")),
+                ("command", toml::Value::from(command.as_str())),
+            ],
+            args,
+        ),
+        EntryKind::Snap {
+            snap_name,
+            command,
+            args,
+            ..
+        } => {
+            let mut fields = vec![
+                ("type", toml::Value::from("snap")),
+                ("snap_name", toml::Value::from(snap_name.as_str())),
+            ];
+            if let Some(command) = command {
+                fields.push(("command", toml::Value::from(command.as_str())));
+            }
+            (fields, args)
+        }
+        EntryKind::Flatpak { app_id, args, .. } => (
+            vec![
+                ("type", toml::Value::from("flatpak")),
+                ("app_id", toml::Value::from(app_id.as_str())),
+            ],
+            args,
+        ),
+        _ => return None,
+    };
+
+    if !args.is_empty() {
+        fields.push(("args", toml::Value::from(args.clone())));
+    }
+    Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_config;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_setup_config_roundtrip() {
+        let choices = SetupChoices {
+            apps: vec![
+                SetupApp {
+                    id: "tuxmath".into(),
+                    label: "Tux \"Math\"".into(),
+                    icon: Some("tuxmath".into()),
+                    kind: EntryKind::Process {
+                        command: "tuxmath".into(),
+                        args: vec!["--fullscreen".into()],
+                        env: HashMap::new(),
+                        cwd: None,
+                    },
+                },
+                SetupApp {
+                    id: "prism".into(),
+                    label: "Prism Launcher".into(),
+                    icon: None,
+                    kind: EntryKind::Flatpak {
+                        app_id: "org.prismlauncher.PrismLauncher".into(),
+                        args: vec![],
+                        env: HashMap::new(),
+                    },
+                },
+            ],
+            max_run: Some(Duration::from_secs(45 * 60)),
+            daily_quota: Some(Duration::from_secs(2 * 3600)),
+            admin_pin: Some(PinHash::new("2468")),
+        };
+
+        let policy = parse_config(&choices.to_toml()).unwrap();
+        assert_eq!(policy.entries.len(), 2);
+        assert_eq!(policy.entries[0].label, "Tux \"Math\"");
+        assert_eq!(policy.entries[0].kind, choices.apps[0].kind);
+        assert_eq!(policy.entries[1].kind, choices.apps[1].kind);
+        assert_eq!(policy.default_max_run, Some(Duration::from_secs(45 * 60)));
+        assert_eq!(
+            policy.entries[1].limits.daily_quota,
+            Some(Duration::from_secs(2 * 3600))
+        );
+        assert!(policy.service.admin_pin.unwrap().verify("2468"));
+
+        // No limits at all
+        let unlimited = SetupChoices {
+            apps: choices.apps,
+            ..Default::default()
+        };
+        let policy = parse_config(&unlimited.to_toml()).unwrap();
+        assert_eq!(policy.default_max_run, None);
+        assert_eq!(policy.entries[0].limits.daily_quota, None);
+    }
+}
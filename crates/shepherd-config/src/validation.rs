@@ -1,7 +1,10 @@
 //! Configuration validation
 
-use crate::schema::{RawConfig, RawDays, RawEntry, RawEntryKind, RawTimeWindow};
-use std::collections::HashSet;
+use crate::policy::{BatteryPolicy, HookFailure, JointUsage};
+use crate::schema::{RawBatteryConfig, RawCalendarConfig, RawConfig, RawCurfewConfig, RawDays, RawDisplayConfig, RawDowntime, RawEntry, RawEntryKind, RawFederationConfig, RawMaintenanceConfig, RawRewardThreshold, RawSelfUpdateConfig, RawTimeExchangeConfig, RawTimeWindow, RawVolumeConfig};
+use shepherd_api::{CooldownScope, PowerAction, Theme};
+use shepherd_util::{EntryId, PinHash, Timezone};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use thiserror::Error;
 
 /// Validation error
@@ -10,8 +13,12 @@ pub enum ValidationError {
     #[error("Entry '{entry_id}': {message}")]
     EntryError { entry_id: String, message: String },
 
-    #[error("Duplicate entry ID: {0}")]
-    DuplicateEntryId(String),
+    #[error("Duplicate entry ID '{id}': entries[{first}] and entries[{second}]")]
+    DuplicateEntryId {
+        id: String,
+        first: usize,
+        second: usize,
+    },
 
     #[error("Invalid time format '{value}': {message}")]
     InvalidTimeFormat { value: String, message: String },
@@ -30,29 +37,580 @@ pub enum ValidationError {
     GlobalError(String),
 }
 
+/// Something that doesn't stop the config from loading, but is probably
+/// not what was meant
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ValidationWarning {
+    #[error("Entry '{entry_id}': availability window {start}-{end} never opens")]
+    WindowNeverOpens {
+        entry_id: String,
+        start: String,
+        end: String,
+    },
+
+    #[error("Default warning at {seconds}s fires as soon as entry '{entry_id}' starts (max_run {max_run}s)")]
+    DefaultWarningExceedsMaxRun {
+        entry_id: String,
+        seconds: u64,
+        max_run: u64,
+    },
+
+    #[error("Entry '{entry_id}': min_run_seconds {min_run}s is longer than any availability window ({longest}s), so it can never launch")]
+    MinRunExceedsWindows {
+        entry_id: String,
+        min_run: u64,
+        longest: u64,
+    },
+
+    #[error("Entries {} share the label '{label}'", entry_ids.join(", "))]
+    DuplicateLabel { label: String, entry_ids: Vec<String> },
+
+    #[error("Entries {} all launch {target}", entry_ids.join(", "))]
+    DuplicateTarget { target: String, entry_ids: Vec<String> },
+
+    /// Two entries launch the same thing at the same times with different
+    /// limits, so the child can pick whichever tile is more generous
+    #[error("Entries '{}' (entries[{}]) and '{}' (entries[{}]) both launch {target} with overlapping availability but different limits: {differences}", entry_ids[0], indices[0], entry_ids[1], indices[1])]
+    ConflictingLimits {
+        entry_ids: [String; 2],
+        indices: [usize; 2],
+        target: String,
+        differences: String,
+    },
+}
+
 /// Validate a raw configuration
 pub fn validate_config(config: &RawConfig) -> Vec<ValidationError> {
     let mut errors = Vec::new();
 
     // Check for duplicate entry IDs
     let mut seen_ids = HashSet::new();
-    for entry in &config.entries {
-        if !seen_ids.insert(&entry.id) {
-            errors.push(ValidationError::DuplicateEntryId(entry.id.clone()));
+    let mut first_index: HashMap<&str, usize> = HashMap::new();
+    for (index, entry) in config.entries.iter().enumerate() {
+        seen_ids.insert(&entry.id);
+        if let Some(&first) = first_index.get(entry.id.as_str()) {
+            errors.push(ValidationError::DuplicateEntryId {
+                id: entry.id.clone(),
+                first,
+                second: index,
+            });
+        } else {
+            first_index.insert(&entry.id, index);
+        }
+    }
+
+    let mut seen_profiles = HashSet::new();
+    for profile in &config.profiles {
+        if !seen_profiles.insert(&profile.id) {
+            errors.push(ValidationError::GlobalError(format!(
+                "Duplicate profile ID: {}",
+                profile.id
+            )));
+        }
+        if !profile.budget_alerts.is_empty() && profile.weekly_budget_minutes.unwrap_or(0) == 0 {
+            errors.push(ValidationError::GlobalError(format!(
+                "Profile '{}': budget_alerts need a weekly_budget_minutes above 0",
+                profile.id
+            )));
+        }
+        for alert in &profile.budget_alerts {
+            if !(1..=100).contains(&alert.percent) {
+                errors.push(ValidationError::GlobalError(format!(
+                    "Profile '{}': budget alert percent must be 1-100, got {}",
+                    profile.id, alert.percent
+                )));
+            }
+            if let Some(by) = &alert.by
+                && by.parse::<chrono::Weekday>().is_err()
+            {
+                errors.push(ValidationError::InvalidDaySpec(format!(
+                    "Profile '{}': unknown budget alert day \"{}\"",
+                    profile.id, by
+                )));
+            }
         }
     }
 
+    let mut seen_seats = HashSet::new();
+    for seat in &config.seats {
+        if seat.id == "seat0" {
+            errors.push(ValidationError::GlobalError(
+                "seat0 is always there; [[seats]] lists the other seats".into(),
+            ));
+        } else if seat.id.is_empty() || !seen_seats.insert(&seat.id) {
+            errors.push(ValidationError::GlobalError(format!(
+                "Seat IDs must be unique and not empty: \"{}\"",
+                seat.id
+            )));
+        }
+    }
+
+    if let Some(joint_usage) = &config.service.joint_usage
+        && parse_joint_usage(joint_usage).is_none()
+    {
+        errors.push(ValidationError::GlobalError(format!(
+            "joint_usage must be \"duplicate\" or \"split\", got \"{}\"",
+            joint_usage
+        )));
+    }
+
+    if let Some(theme) = &config.service.theme
+        && parse_theme(theme).is_none()
+    {
+        errors.push(ValidationError::GlobalError(format!(
+            "theme must be one of {}, got \"{}\"",
+            Theme::ALL
+                .iter()
+                .map(|t| format!("\"{}\"", t.as_str()))
+                .collect::<Vec<_>>()
+                .join(", "),
+            theme
+        )));
+    }
+
+    if let Some(pin) = &config.service.admin_pin
+        && let Err(e) = pin.parse::<PinHash>()
+    {
+        errors.push(ValidationError::GlobalError(format!("admin_pin: {}", e)));
+    }
+
+    if let Some(tz) = &config.service.timezone
+        && let Err(e) = tz.parse::<Timezone>()
+    {
+        errors.push(ValidationError::GlobalError(format!("timezone: {}", e)));
+    }
+
+    // Matched against /proc/<pid>/exe, which is always absolute
+    for exe in &config.service.shell_executables {
+        if !exe.is_absolute() {
+            errors.push(ValidationError::GlobalError(format!(
+                "shell_executables: {:?} must be an absolute path",
+                exe
+            )));
+        }
+    }
+
+    if let Some(key) = &config.service.panic_key
+        && (key.is_empty() || key.contains(char::is_whitespace))
+    {
+        errors.push(ValidationError::GlobalError(format!(
+            "panic_key: {:?} must be a key combination like \"Mod4+Ctrl+BackSpace\"",
+            key
+        )));
+    }
+
     // Validate each entry
     for entry in &config.entries {
         errors.extend(validate_entry(entry, config));
     }
 
+    if let Some(volume) = &config.service.volume {
+        errors.extend(validate_volume_config(volume));
+    }
+
+    if let Some(display) = &config.service.display {
+        errors.extend(validate_display_config(display));
+    }
+
+    if let Some(curfew) = &config.service.curfew {
+        errors.extend(validate_curfew_config(curfew));
+    }
+
+    for downtime in &config.service.downtime {
+        errors.extend(validate_downtime(downtime));
+    }
+
+    if let Some(battery) = &config.service.battery {
+        errors.extend(validate_battery_config(battery));
+    }
+
+    errors.extend(validate_rewards(&config.service.rewards, &seen_ids));
+
+    if let Some(exchange) = &config.service.time_exchange {
+        errors.extend(validate_time_exchange(exchange));
+    }
+
+    if let Some(maintenance) = &config.service.maintenance {
+        errors.extend(validate_maintenance(maintenance, &config.entries));
+    }
+
+    if let Some(self_update) = &config.service.self_update {
+        errors.extend(validate_self_update(self_update));
+    }
+
+    if let Some(calendar) = &config.service.calendar {
+        errors.extend(validate_calendar(calendar));
+    }
+
+    if let Some(federation) = &config.service.federation {
+        errors.extend(validate_federation(federation));
+    }
+
+    for (app, entry_id) in &config.import.apps {
+        if !seen_ids.contains(entry_id) {
+            errors.push(ValidationError::GlobalError(format!(
+                "import app \"{}\" maps to unknown entry '{}'",
+                app, entry_id
+            )));
+        }
+    }
+
+    errors
+}
+
+/// Look for likely mistakes in a config that passed [`validate_config`]
+pub fn lint_config(config: &RawConfig) -> Vec<ValidationWarning> {
+    let mut warnings = Vec::new();
+
+    let mut labels: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for entry in &config.entries {
+        labels
+            .entry(entry.label.trim())
+            .or_default()
+            .push(entry.id.clone());
+    }
+    for (label, entry_ids) in labels {
+        if entry_ids.len() > 1 {
+            warnings.push(ValidationWarning::DuplicateLabel {
+                label: label.to_string(),
+                entry_ids,
+            });
+        }
+    }
+
+    for (target, indices) in entries_by_target(config) {
+        if indices.len() > 1 {
+            warnings.push(ValidationWarning::DuplicateTarget {
+                target: target.clone(),
+                entry_ids: indices.iter().map(|&i| config.entries[i].id.clone()).collect(),
+            });
+            warnings.extend(conflicting_limits(config, &target, &indices));
+        }
+    }
+
+    for entry in &config.entries {
+        warnings.extend(lint_entry(entry, config));
+    }
+
+    warnings
+}
+
+/// What an entry launches, for spotting two entries that wrap the same
+/// program. Entries with different arguments are different targets.
+fn entry_target(kind: &RawEntryKind) -> Option<String> {
+    let with_args = |target: String, args: &[String]| {
+        if args.is_empty() {
+            target
+        } else {
+            format!("{} {}", target, args.join(" "))
+        }
+    };
+    match kind {
+        RawEntryKind::Process { command, args, .. } => {
+            Some(with_args(format!("command '{}'", command), args))
+        }
+        RawEntryKind::Snap {
+            snap_name,
+            command,
+            args,
+            ..
+        } => {
+            let name = match command {
+                Some(command) if command != snap_name => format!("{}.{}", snap_name, command),
+                _ => snap_name.clone(),
+            };
+            Some(with_args(format!("snap '{}'", name), args))
+        }
+        RawEntryKind::Flatpak { app_id, args, .. } => {
+            Some(with_args(format!("flatpak '{}'", app_id), args))
+        }
+        // Entries playing the same ROM share its quota whatever the core
+        RawEntryKind::Retro { rom_path, .. } => Some(format!("ROM '{}'", rom_path.display())),
+        _ => None,
+    }
+}
+
+/// Indices of enabled entries grouped by what they launch, in config order
+fn entries_by_target(config: &RawConfig) -> BTreeMap<String, Vec<usize>> {
+    let mut targets: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (index, entry) in config.entries.iter().enumerate() {
+        if entry.disabled {
+            continue;
+        }
+        if let Some(target) = entry_target(&entry.kind) {
+            targets.entry(target).or_default().push(index);
+        }
+    }
+    targets
+}
+
+/// Pairs of entries launching `target` that are available at the same time
+/// but don't agree on limits
+fn conflicting_limits(config: &RawConfig, target: &str, indices: &[usize]) -> Vec<ValidationWarning> {
+    let mut warnings = Vec::new();
+    for (n, &a) in indices.iter().enumerate() {
+        for &b in &indices[n + 1..] {
+            let (first, second) = (&config.entries[a], &config.entries[b]);
+            let differences = limit_differences(first, second, config);
+            if differences.is_empty() || !availability_overlaps(first, second) {
+                continue;
+            }
+            warnings.push(ValidationWarning::ConflictingLimits {
+                entry_ids: [first.id.clone(), second.id.clone()],
+                indices: [a, b],
+                target: target.to_string(),
+                differences: differences.join(", "),
+            });
+        }
+    }
+    warnings
+}
+
+/// Limits two entries disagree on, e.g. "max_run 3600s vs 1800s"
+fn limit_differences(a: &RawEntry, b: &RawEntry, config: &RawConfig) -> Vec<String> {
+    // 0 and unset both mean unlimited, except max_run, which falls back to
+    // the service default
+    let max_run = |e: &RawEntry| {
+        e.limits
+            .as_ref()
+            .and_then(|l| l.max_run_seconds)
+            .or(config.service.default_max_run_seconds)
+            .unwrap_or(3600)
+    };
+    let quota = |e: &RawEntry| e.limits.as_ref().and_then(|l| l.daily_quota_seconds).unwrap_or(0);
+    let cooldown = |e: &RawEntry| e.limits.as_ref().and_then(|l| l.cooldown_seconds).unwrap_or(0);
+    let describe = |seconds: u64| {
+        if seconds == 0 {
+            "unlimited".to_string()
+        } else {
+            format!("{}s", seconds)
+        }
+    };
+
+    let mut differences = Vec::new();
+    if max_run(a) != max_run(b) {
+        differences.push(format!("max_run {} vs {}", describe(max_run(a)), describe(max_run(b))));
+    }
+    if quota(a) != quota(b) {
+        differences.push(format!("daily_quota {} vs {}", describe(quota(a)), describe(quota(b))));
+    }
+    if cooldown(a) != cooldown(b) {
+        differences.push(format!("cooldown {}s vs {}s", cooldown(a), cooldown(b)));
+    }
+    differences
+}
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+const MINUTES_PER_WEEK: u32 = 7 * MINUTES_PER_DAY;
+
+/// When an entry is available, as [start, end) minutes from Monday 00:00.
+/// None means always; windows that don't parse are left out.
+fn weekly_intervals(entry: &RawEntry) -> Option<Vec<(u32, u32)>> {
+    let availability = entry.availability.as_ref().filter(|a| !a.always && !a.windows.is_empty())?;
+    let mut intervals = Vec::new();
+    for window in &availability.windows {
+        let (Ok(days), Ok(start), Ok(end)) =
+            (parse_days(&window.days), parse_time(&window.start), parse_time(&window.end))
+        else {
+            continue;
+        };
+        let start = u32::from(start.0) * 60 + u32::from(start.1);
+        let mut end = u32::from(end.0) * 60 + u32::from(end.1);
+        if end < start {
+            end += MINUTES_PER_DAY;
+        }
+        for day in (0..7).filter(|day| days & (1 << day) != 0) {
+            let (from, to) = (day * MINUTES_PER_DAY + start, day * MINUTES_PER_DAY + end);
+            // Sunday night runs into Monday morning
+            if to > MINUTES_PER_WEEK {
+                intervals.push((from, MINUTES_PER_WEEK));
+                intervals.push((0, to - MINUTES_PER_WEEK));
+            } else {
+                intervals.push((from, to));
+            }
+        }
+    }
+    Some(intervals)
+}
+
+fn availability_overlaps(a: &RawEntry, b: &RawEntry) -> bool {
+    match (weekly_intervals(a), weekly_intervals(b)) {
+        (None, _) | (_, None) => true,
+        (Some(a), Some(b)) => a
+            .iter()
+            .any(|&(a_start, a_end)| b.iter().any(|&(b_start, b_end)| a_start < b_end && b_start < a_end)),
+    }
+}
+
+fn lint_entry(entry: &RawEntry, config: &RawConfig) -> Vec<ValidationWarning> {
+    let mut warnings = Vec::new();
+
+    let windows: Vec<&RawTimeWindow> = entry
+        .availability
+        .as_ref()
+        .filter(|a| !a.always)
+        .map(|a| a.windows.iter().collect())
+        .unwrap_or_default();
+    let mut longest_window = None;
+    for window in &windows {
+        let Some(seconds) = window_seconds(window) else {
+            continue;
+        };
+        if seconds == 0 {
+            warnings.push(ValidationWarning::WindowNeverOpens {
+                entry_id: entry.id.clone(),
+                start: window.start.clone(),
+                end: window.end.clone(),
+            });
+        }
+        longest_window = longest_window.max(Some(seconds));
+    }
+
+    let limits = entry.limits.as_ref();
+    if let (Some(min_run), Some(longest)) =
+        (limits.and_then(|l| l.min_run_seconds), longest_window)
+        && longest > 0
+        && min_run > longest
+    {
+        warnings.push(ValidationWarning::MinRunExceedsWindows {
+            entry_id: entry.id.clone(),
+            min_run,
+            longest,
+        });
+    }
+
+    // Entries with their own warnings are checked by validate_entry
+    let max_run = limits
+        .and_then(|l| l.max_run_seconds)
+        .or(config.service.default_max_run_seconds);
+    if entry.warnings.is_none()
+        && let (Some(defaults), Some(max_run)) = (&config.service.default_warnings, max_run)
+        && max_run > 0
+    {
+        for warning in defaults {
+            if warning.seconds_before >= max_run {
+                warnings.push(ValidationWarning::DefaultWarningExceedsMaxRun {
+                    entry_id: entry.id.clone(),
+                    seconds: warning.seconds_before,
+                    max_run,
+                });
+            }
+        }
+    }
+
+    warnings
+}
+
+/// How long a window stays open on the days it applies, or None if it
+/// doesn't parse. A window on no days never opens.
+fn window_seconds(window: &RawTimeWindow) -> Option<u64> {
+    if parse_days(&window.days).ok()? == 0 {
+        return Some(0);
+    }
+    let (start_h, start_m) = parse_time(&window.start).ok()?;
+    let (end_h, end_m) = parse_time(&window.end).ok()?;
+    let start = u64::from(start_h) * 3600 + u64::from(start_m) * 60;
+    let end = u64::from(end_h) * 3600 + u64::from(end_m) * 60;
+    // Windows that end before they start run past midnight
+    Some(if start <= end { end - start } else { 86_400 - start + end })
+}
+
+fn validate_volume_config(volume: &RawVolumeConfig) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    let caps = volume
+        .max_volume
+        .iter()
+        .chain(volume.min_volume.iter())
+        .chain(volume.duck_warnings.iter())
+        .chain(volume.windows.iter().filter_map(|w| w.max_volume.as_ref()));
+    for &percent in caps {
+        if percent > 100 {
+            errors.push(ValidationError::GlobalError(format!(
+                "volume must be 0-100, got {}",
+                percent
+            )));
+        }
+    }
+
+    for window in &volume.windows {
+        if window.max_volume.is_none() && !window.require_headphones {
+            errors.push(ValidationError::GlobalError(format!(
+                "volume window {}-{} sets neither max_volume nor require_headphones",
+                window.start, window.end
+            )));
+        }
+        if let Err(e) = parse_days(&window.days) {
+            errors.push(ValidationError::InvalidDaySpec(e));
+        }
+        for time in [&window.start, &window.end] {
+            if let Err(e) = parse_time(time) {
+                errors.push(ValidationError::InvalidTimeFormat {
+                    value: time.clone(),
+                    message: e,
+                });
+            }
+        }
+    }
+
+    errors
+}
+
+fn validate_display_config(display: &RawDisplayConfig) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    // 0% turns most backlights off entirely, which is never what's wanted
+    let brightness_values = display
+        .default_brightness
+        .iter()
+        .chain(display.windows.iter().filter_map(|w| w.brightness.as_ref()));
+    for &brightness in brightness_values {
+        if !(1..=100).contains(&brightness) {
+            errors.push(ValidationError::GlobalError(format!(
+                "display brightness must be 1-100, got {}",
+                brightness
+            )));
+        }
+    }
+
+    if let Some(temperature) = display.night_light_temperature
+        && !(1000..=25000).contains(&temperature)
+    {
+        errors.push(ValidationError::GlobalError(format!(
+            "night_light_temperature must be 1000-25000 K, got {}",
+            temperature
+        )));
+    }
+
+    for window in &display.windows {
+        if let Err(e) = parse_days(&window.days) {
+            errors.push(ValidationError::InvalidDaySpec(e));
+        }
+        for time in [&window.start, &window.end] {
+            if let Err(e) = parse_time(time) {
+                errors.push(ValidationError::InvalidTimeFormat {
+                    value: time.clone(),
+                    message: e,
+                });
+            }
+        }
+    }
+
     errors
 }
 
 fn validate_entry(entry: &RawEntry, config: &RawConfig) -> Vec<ValidationError> {
     let mut errors = Vec::new();
 
+    // IDs end up in log file names and audit records, so refuse anything
+    // that would need escaping there
+    if let Err(e) = EntryId::validate(&entry.id) {
+        errors.push(ValidationError::EntryError {
+            entry_id: entry.id.clone(),
+            message: format!("id {}", e.reason),
+        });
+    }
+
     // Validate kind
     match &entry.kind {
         RawEntryKind::Process { command, .. } => {
@@ -95,6 +653,20 @@ fn validate_entry(entry: &RawEntry, config: &RawConfig) -> Vec<ValidationError>
                 });
             }
         }
+        RawEntryKind::Retro { core, rom_path } => {
+            if core.is_empty() {
+                errors.push(ValidationError::EntryError {
+                    entry_id: entry.id.clone(),
+                    message: "core cannot be empty".into(),
+                });
+            }
+            if rom_path.as_os_str().is_empty() {
+                errors.push(ValidationError::EntryError {
+                    entry_id: entry.id.clone(),
+                    message: "rom_path cannot be empty".into(),
+                });
+            }
+        }
         RawEntryKind::Custom { type_name, .. } => {
             if type_name.is_empty() {
                 errors.push(ValidationError::EntryError {
@@ -135,6 +707,219 @@ fn validate_entry(entry: &RawEntry, config: &RawConfig) -> Vec<ValidationError>
         // Note: warnings are ignored for unlimited entries (max_run = 0)
     }
 
+    if let Some(limits) = &entry.limits {
+        if let Some(scope) = &limits.cooldown_scope {
+            match parse_cooldown_scope(scope) {
+                None => errors.push(ValidationError::EntryError {
+                    entry_id: entry.id.clone(),
+                    message: format!(
+                        "invalid cooldown_scope '{}': expected entry, global or tag:<name>",
+                        scope
+                    ),
+                }),
+                Some(CooldownScope::Tag { tag }) if !entry.tags.contains(&tag) => {
+                    errors.push(ValidationError::EntryError {
+                        entry_id: entry.id.clone(),
+                        message: format!("cooldown_scope tag '{}' isn't one of the entry's tags", tag),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+        if let Some(min_run) = limits.min_run_seconds {
+            let quota = limits.daily_quota_seconds.filter(|&q| q > 0);
+            if min_run == 0 {
+                errors.push(ValidationError::EntryError {
+                    entry_id: entry.id.clone(),
+                    message: "limits min_run_seconds must be non-zero".into(),
+                });
+            } else if max_run.is_some_and(|m| m > 0 && min_run > m)
+                || quota.is_some_and(|q| min_run > q)
+            {
+                errors.push(ValidationError::EntryError {
+                    entry_id: entry.id.clone(),
+                    message: "limits min_run_seconds exceeds max_run_seconds or daily_quota_seconds"
+                        .into(),
+                });
+            }
+        }
+        if limits.cooldown_after_seconds == Some(0) {
+            errors.push(ValidationError::EntryError {
+                entry_id: entry.id.clone(),
+                message: "limits cooldown_after_seconds must be non-zero".into(),
+            });
+        }
+        if (limits.cooldown_scope.is_some() || limits.cooldown_after_seconds.is_some())
+            && limits.cooldown_seconds.is_none()
+        {
+            errors.push(ValidationError::EntryError {
+                entry_id: entry.id.clone(),
+                message: "cooldown_scope and cooldown_after_seconds require cooldown_seconds".into(),
+            });
+        }
+    }
+
+    // A refund window as long as the session itself would refund everything
+    if let Some(limits) = &entry.limits {
+        for (name, seconds) in [
+            ("refund_under_seconds", limits.refund_under_seconds),
+            ("refund_crash_under_seconds", limits.refund_crash_under_seconds),
+        ] {
+            let Some(seconds) = seconds else { continue };
+            if seconds == 0 {
+                errors.push(ValidationError::EntryError {
+                    entry_id: entry.id.clone(),
+                    message: format!("limits {} must be non-zero", name),
+                });
+            } else if let Some(max_run) = max_run.filter(|&m| m > 0)
+                && seconds >= max_run
+            {
+                errors.push(ValidationError::EntryError {
+                    entry_id: entry.id.clone(),
+                    message: format!("limits {} must be less than max_run_seconds ({})", name, max_run),
+                });
+            }
+        }
+    }
+
+    // Validate hooks
+    if let Some(hooks) = &entry.hooks {
+        for (name, hook) in [("pre_launch", &hooks.pre_launch), ("post_exit", &hooks.post_exit)] {
+            let Some(hook) = hook else { continue };
+            if hook.command.trim().is_empty() {
+                errors.push(ValidationError::EntryError {
+                    entry_id: entry.id.clone(),
+                    message: format!("{} hook command must not be empty", name),
+                });
+            }
+            if hook.timeout_seconds == 0 {
+                errors.push(ValidationError::EntryError {
+                    entry_id: entry.id.clone(),
+                    message: format!("{} hook timeout_seconds must be non-zero", name),
+                });
+            }
+            match hook.on_failure.as_deref().map(parse_hook_failure) {
+                Some(None) => errors.push(ValidationError::EntryError {
+                    entry_id: entry.id.clone(),
+                    message: format!(
+                        "{} hook on_failure must be \"abort\" or \"continue\"",
+                        name
+                    ),
+                }),
+                Some(Some(HookFailure::Abort)) if name == "post_exit" => {
+                    errors.push(ValidationError::EntryError {
+                        entry_id: entry.id.clone(),
+                        message: "post_exit hooks run after the session and can't abort it".into(),
+                    })
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Validate Minecraft settings
+    if let Some(minecraft) = &entry.minecraft {
+        if minecraft.game_dir.trim().is_empty() {
+            errors.push(ValidationError::EntryError {
+                entry_id: entry.id.clone(),
+                message: "minecraft game_dir must not be empty".into(),
+            });
+        }
+        for server in minecraft.servers.iter().flatten() {
+            if server.name.trim().is_empty() || !valid_minecraft_address(&server.address) {
+                errors.push(ValidationError::EntryError {
+                    entry_id: entry.id.clone(),
+                    message: format!(
+                        "minecraft server {:?} needs a name and a host or host:port address",
+                        server.name
+                    ),
+                });
+            }
+        }
+    }
+
+    if entry.wine.as_ref().is_some_and(|wine| wine.prefix.trim().is_empty()) {
+        errors.push(ValidationError::EntryError {
+            entry_id: entry.id.clone(),
+            message: "wine prefix must not be empty".into(),
+        });
+    }
+
+    // Validate pre-checks
+    if let Some(prechecks) = &entry.prechecks {
+        if prechecks.min_free_disk_mb == Some(0) {
+            errors.push(ValidationError::EntryError {
+                entry_id: entry.id.clone(),
+                message: "prechecks min_free_disk_mb must be non-zero".into(),
+            });
+        }
+        if prechecks.disk_path.is_some() && prechecks.min_free_disk_mb.is_none() {
+            errors.push(ValidationError::EntryError {
+                entry_id: entry.id.clone(),
+                message: "prechecks disk_path requires min_free_disk_mb".into(),
+            });
+        }
+        if prechecks.require_env.iter().any(|name| name.is_empty() || name.contains('=')) {
+            errors.push(ValidationError::EntryError {
+                entry_id: entry.id.clone(),
+                message: "prechecks require_env contains an invalid variable name".into(),
+            });
+        }
+    }
+
+    // Validate autostart times
+    for autostart in &entry.autostart {
+        if let Some(days) = &autostart.days
+            && let Err(e) = parse_days(days)
+        {
+            errors.push(ValidationError::InvalidDaySpec(e));
+        }
+        if let Err(e) = parse_time(&autostart.at) {
+            errors.push(ValidationError::InvalidTimeFormat {
+                value: autostart.at.clone(),
+                message: e,
+            });
+        }
+    }
+
+    // Quiet hours are household-wide
+    if entry.volume.as_ref().is_some_and(|v| !v.windows.is_empty()) {
+        errors.push(ValidationError::EntryError {
+            entry_id: entry.id.clone(),
+            message: "volume windows can only be set in [service.volume]".into(),
+        });
+    }
+    if let Some(percent) = entry.volume.as_ref().and_then(|v| v.duck_warnings)
+        && percent > 100
+    {
+        errors.push(ValidationError::EntryError {
+            entry_id: entry.id.clone(),
+            message: format!("duck_warnings must be 0-100, got {}", percent),
+        });
+    }
+
+    // Validate gamescope settings
+    if let Some(gamescope) = &entry.gamescope {
+        if gamescope.width.is_some() != gamescope.height.is_some() {
+            errors.push(ValidationError::EntryError {
+                entry_id: entry.id.clone(),
+                message: "gamescope width and height must be set together".into(),
+            });
+        }
+        if gamescope.width == Some(0) || gamescope.height == Some(0) {
+            errors.push(ValidationError::EntryError {
+                entry_id: entry.id.clone(),
+                message: "gamescope resolution must be non-zero".into(),
+            });
+        }
+        if gamescope.fps_limit == Some(0) {
+            errors.push(ValidationError::EntryError {
+                entry_id: entry.id.clone(),
+                message: "gamescope fps_limit must be non-zero".into(),
+            });
+        }
+    }
+
     errors
 }
 
@@ -168,6 +953,390 @@ fn validate_time_window(window: &RawTimeWindow, entry_id: &str) -> Vec<Validatio
     errors
 }
 
+fn validate_downtime(downtime: &RawDowntime) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    if let Some(days) = &downtime.days
+        && let Err(e) = parse_days(days)
+    {
+        errors.push(ValidationError::InvalidDaySpec(e));
+    }
+    for time in [&downtime.start, &downtime.end] {
+        if let Err(e) = parse_time(time) {
+            errors.push(ValidationError::InvalidTimeFormat {
+                value: time.clone(),
+                message: e,
+            });
+        }
+    }
+    if downtime.start == downtime.end {
+        errors.push(ValidationError::GlobalError(format!(
+            "downtime start and end are both {}",
+            downtime.start
+        )));
+    }
+
+    errors
+}
+
+fn validate_curfew_config(curfew: &RawCurfewConfig) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    if let Some(days) = &curfew.days
+        && let Err(e) = parse_days(days)
+    {
+        errors.push(ValidationError::InvalidDaySpec(e));
+    }
+    for time in [&curfew.start, &curfew.end] {
+        if let Err(e) = parse_time(time) {
+            errors.push(ValidationError::InvalidTimeFormat {
+                value: time.clone(),
+                message: e,
+            });
+        }
+    }
+
+    if parse_power_action(&curfew.action).is_none() {
+        errors.push(ValidationError::GlobalError(format!(
+            "curfew action must be \"poweroff\" or \"suspend\", got \"{}\"",
+            curfew.action
+        )));
+    }
+
+    errors
+}
+
+fn validate_battery_config(battery: &RawBatteryConfig) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    for percent in [battery.warn_below_percent, battery.stop_below_percent]
+        .into_iter()
+        .flatten()
+    {
+        if !(1..=100).contains(&percent) {
+            errors.push(ValidationError::GlobalError(format!(
+                "battery thresholds must be 1-100, got {}",
+                percent
+            )));
+        }
+    }
+
+    // Stopping before warning would end sessions without notice
+    let warn = battery
+        .warn_below_percent
+        .unwrap_or(BatteryPolicy::default().warn_below);
+    if let Some(stop) = battery.stop_below_percent
+        && stop > warn
+    {
+        errors.push(ValidationError::GlobalError(format!(
+            "battery stop_below_percent ({}) must not exceed warn_below_percent ({})",
+            stop, warn
+        )));
+    }
+
+    errors
+}
+
+fn validate_rewards(
+    rewards: &[RawRewardThreshold],
+    entry_ids: &HashSet<&String>,
+) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    for reward in rewards {
+        if reward.stars == 0 {
+            errors.push(ValidationError::GlobalError(
+                "reward stars must be greater than 0".into(),
+            ));
+        }
+        if reward.unlock.is_empty() && reward.bonus_minutes.is_none() {
+            errors.push(ValidationError::GlobalError(format!(
+                "reward at {} stars unlocks nothing",
+                reward.stars
+            )));
+        }
+        for entry_id in &reward.unlock {
+            if !entry_ids.contains(entry_id) {
+                errors.push(ValidationError::GlobalError(format!(
+                    "reward at {} stars unlocks unknown entry '{}'",
+                    reward.stars, entry_id
+                )));
+            }
+        }
+    }
+
+    errors
+}
+
+fn validate_time_exchange(exchange: &RawTimeExchangeConfig) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    if !exchange.ratio.is_finite() || exchange.ratio <= 0.0 {
+        errors.push(ValidationError::GlobalError(format!(
+            "time_exchange ratio must be greater than 0, got {}",
+            exchange.ratio
+        )));
+    }
+
+    // Otherwise a session would keep extending its own quota
+    if exchange.earn_tag == exchange.spend_tag {
+        errors.push(ValidationError::GlobalError(format!(
+            "time_exchange earn_tag and spend_tag must differ, both are '{}'",
+            exchange.earn_tag
+        )));
+    }
+
+    errors
+}
+
+fn validate_self_update(self_update: &RawSelfUpdateConfig) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    let feed = self_update.feed.as_str();
+    if !(feed.starts_with("https://") || feed.starts_with("file:///") || feed.starts_with('/')) {
+        errors.push(ValidationError::GlobalError(format!(
+            "self_update feed \"{}\" must be an https URL, a file:// URL or an absolute path",
+            feed
+        )));
+    }
+
+    if self_update.check_interval_hours == Some(0) {
+        errors.push(ValidationError::GlobalError(
+            "self_update check_interval_hours must be greater than 0".into(),
+        ));
+    }
+
+    if let Some(path) = &self_update.install_path
+        && !path.is_absolute()
+    {
+        errors.push(ValidationError::GlobalError(format!(
+            "self_update install_path {:?} must be an absolute path",
+            path
+        )));
+    }
+
+    match &self_update.public_key {
+        Some(key) => {
+            if let Err(e) = minisign_verify::PublicKey::from_base64(key.trim()) {
+                errors.push(ValidationError::GlobalError(format!(
+                    "self_update public_key is not a minisign public key: {}",
+                    e
+                )));
+            }
+        }
+        // Nothing unsigned gets installed
+        None if self_update.stage => {
+            errors.push(ValidationError::GlobalError(
+                "self_update stage = true needs a public_key to check releases against".into(),
+            ));
+        }
+        None => {}
+    }
+
+    errors
+}
+
+fn validate_calendar(calendar: &RawCalendarConfig) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    let url = calendar.url.as_str();
+    if !(url.starts_with("https://")
+        || url.starts_with("http://")
+        || url.starts_with("file:///")
+        || url.starts_with('/'))
+    {
+        errors.push(ValidationError::GlobalError(format!(
+            "calendar url \"{}\" must be an http(s) URL, a file:// URL or an absolute path",
+            url
+        )));
+    }
+
+    if calendar.poll_interval_minutes == Some(0) {
+        errors.push(ValidationError::GlobalError(
+            "calendar poll_interval_minutes must be greater than 0".into(),
+        ));
+    }
+
+    errors
+}
+
+/// Shortest federation secret accepted
+const MIN_FEDERATION_SECRET_LEN: usize = 16;
+
+/// Whether `ip` is reachable from the internet: not loopback, link-local,
+/// unspecified, a private range, carrier-grade NAT (used by Tailscale) or
+/// an IPv6 unique local address
+fn is_public_ip(ip: std::net::IpAddr) -> bool {
+    match ip {
+        std::net::IpAddr::V4(ip) => {
+            let shared = ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64;
+            !(ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified() || shared)
+        }
+        std::net::IpAddr::V6(ip) => {
+            let unique_local = (ip.segments()[0] & 0xfe00) == 0xfc00;
+            let link_local = (ip.segments()[0] & 0xffc0) == 0xfe80;
+            !(ip.is_loopback() || ip.is_unspecified() || unique_local || link_local)
+        }
+    }
+}
+
+fn validate_federation(federation: &RawFederationConfig) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    let device = federation.device.as_str();
+    if device.is_empty() || device.chars().any(char::is_whitespace) {
+        errors.push(ValidationError::GlobalError(format!(
+            "federation device \"{}\" must be a non-empty name without spaces",
+            device
+        )));
+    }
+
+    if federation.secret.chars().count() < MIN_FEDERATION_SECRET_LEN {
+        errors.push(ValidationError::GlobalError(format!(
+            "federation secret must be at least {} characters",
+            MIN_FEDERATION_SECRET_LEN
+        )));
+    }
+
+    if let Some(listen) = &federation.listen {
+        match listen.parse::<std::net::SocketAddr>() {
+            Err(_) => errors.push(ValidationError::GlobalError(format!(
+                "federation listen \"{}\" must be an IP address and port, like \"0.0.0.0:7343\"",
+                listen
+            ))),
+            Ok(addr) if is_public_ip(addr.ip()) => errors.push(ValidationError::GlobalError(format!(
+                "federation listen \"{}\" is a public address; federation isn't encrypted, so keep it to the home network or a VPN",
+                listen
+            ))),
+            Ok(_) => {}
+        }
+    }
+
+    for peer in &federation.peers {
+        let port = peer.rsplit_once(':').map(|(host, port)| (host, port.parse::<u16>()));
+        if !matches!(port, Some((host, Ok(_))) if !host.is_empty()) {
+            errors.push(ValidationError::GlobalError(format!(
+                "federation peer \"{}\" must be a host and port, like \"tablet.local:7343\"",
+                peer
+            )));
+        }
+        if let Ok(addr) = peer.parse::<std::net::SocketAddr>()
+            && is_public_ip(addr.ip())
+        {
+            errors.push(ValidationError::GlobalError(format!(
+                "federation peer \"{}\" is a public address; federation isn't encrypted, so keep it to the home network or a VPN",
+                peer
+            )));
+        }
+    }
+
+    if federation.listen.is_none() && federation.peers.is_empty() {
+        errors.push(ValidationError::GlobalError(
+            "federation needs a listen address, peers, or both".into(),
+        ));
+    }
+
+    if federation.sync_interval_seconds == Some(0) {
+        errors.push(ValidationError::GlobalError(
+            "federation sync_interval_seconds must be greater than 0".into(),
+        ));
+    }
+
+    errors
+}
+
+fn validate_maintenance(
+    maintenance: &RawMaintenanceConfig,
+    entries: &[RawEntry],
+) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    if maintenance.interval_hours == Some(0) {
+        errors.push(ValidationError::GlobalError(
+            "maintenance interval_hours must be greater than 0".into(),
+        ));
+    }
+
+    for entry_id in &maintenance.entries {
+        match entries.iter().find(|e| &e.id == entry_id) {
+            None => errors.push(ValidationError::GlobalError(format!(
+                "maintenance lists unknown entry '{}'",
+                entry_id
+            ))),
+            Some(entry)
+                if !matches!(entry.kind, RawEntryKind::Snap { .. } | RawEntryKind::Flatpak { .. }) =>
+            {
+                errors.push(ValidationError::GlobalError(format!(
+                    "maintenance entry '{}' must be a snap or flatpak",
+                    entry_id
+                )));
+            }
+            Some(_) => {}
+        }
+    }
+
+    errors
+}
+
+/// Parse a joint session usage mode
+pub fn parse_joint_usage(s: &str) -> Option<JointUsage> {
+    match s.to_lowercase().as_str() {
+        "duplicate" => Some(JointUsage::Duplicate),
+        "split" => Some(JointUsage::Split),
+        _ => None,
+    }
+}
+
+/// Parse a cooldown scope: "entry", "global" or "tag:<name>"
+pub fn parse_cooldown_scope(s: &str) -> Option<CooldownScope> {
+    if let Some(tag) = s.strip_prefix("tag:") {
+        return (!tag.is_empty()).then(|| CooldownScope::Tag { tag: tag.to_string() });
+    }
+    match s.to_lowercase().as_str() {
+        "entry" => Some(CooldownScope::Entry),
+        "global" => Some(CooldownScope::Global),
+        _ => None,
+    }
+}
+
+/// Parse a hook failure policy: "abort" or "continue"
+/// "host" or "host:port", where a bare IPv6 address has no port
+fn valid_minecraft_address(address: &str) -> bool {
+    let address = address.trim();
+    if address.is_empty() || address.contains(char::is_whitespace) {
+        return false;
+    }
+    match address.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+            !host.is_empty() && port.parse::<u16>().is_ok()
+        }
+        _ => true,
+    }
+}
+
+pub fn parse_hook_failure(s: &str) -> Option<HookFailure> {
+    match s.to_lowercase().as_str() {
+        "abort" => Some(HookFailure::Abort),
+        "continue" => Some(HookFailure::Continue),
+        _ => None,
+    }
+}
+
+/// Parse a launcher/HUD theme name
+pub fn parse_theme(s: &str) -> Option<Theme> {
+    let s = s.to_lowercase().replace('-', "_");
+    Theme::ALL.into_iter().find(|t| t.as_str() == s)
+}
+
+/// Parse a curfew power action name
+pub fn parse_power_action(s: &str) -> Option<PowerAction> {
+    match s.to_lowercase().as_str() {
+        "poweroff" | "shutdown" => Some(PowerAction::Poweroff),
+        "suspend" => Some(PowerAction::Suspend),
+        _ => None,
+    }
+}
+
 /// Parse HH:MM time format
 pub fn parse_time(s: &str) -> Result<(u8, u8), String> {
     let parts: Vec<&str> = s.split(':').collect();
@@ -268,6 +1437,18 @@ mod tests {
                     limits: None,
                     warnings: None,
                     volume: None,
+                    gamescope: None,
+                    allow_camera: true,
+                    allow_microphone: true,
+                    tags: vec![],
+                    prechecks: None,
+                    confirm_launch: false,
+                    autostart: Vec::new(),
+                    hooks: None,
+                    minecraft: None,
+                    wine: None,
+                    kill_by_name: false,
+                    controller_only: false,
                     disabled: false,
                     disabled_reason: None,
                 },
@@ -285,13 +1466,31 @@ mod tests {
                     limits: None,
                     warnings: None,
                     volume: None,
+                    gamescope: None,
+                    allow_camera: true,
+                    allow_microphone: true,
+                    tags: vec![],
+                    prechecks: None,
+                    confirm_launch: false,
+                    autostart: Vec::new(),
+                    hooks: None,
+                    minecraft: None,
+                    wine: None,
+                    kill_by_name: false,
+                    controller_only: false,
                     disabled: false,
                     disabled_reason: None,
                 },
             ],
+            profiles: vec![],
+            seats: vec![],
+            import: Default::default(),
         };
 
         let errors = validate_config(&config);
-        assert!(errors.iter().any(|e| matches!(e, ValidationError::DuplicateEntryId(_))));
+        assert!(errors.iter().any(|e| matches!(
+            e,
+            ValidationError::DuplicateEntryId { first: 0, second: 1, .. }
+        )));
     }
 }
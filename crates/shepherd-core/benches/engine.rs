@@ -0,0 +1,129 @@
+//! How the engine copes with large entry catalogs. Every entry view needs
+//! its usage, cooldowns and last session; `list_entries_cold` reads them
+//! all from the store, the others mostly from the engine's memory.
+//!
+//! Run with `cargo bench -p shepherd-core`.
+
+use chrono::Duration as ChronoDuration;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use shepherd_core::CoreEngine;
+use shepherd_host_api::HostCapabilities;
+use shepherd_store::{CooldownKey, SqliteStore, Store};
+use shepherd_util::{EntryId, MonotonicInstant};
+use std::fmt::Write;
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const CATALOG_SIZES: [usize; 3] = [10, 100, 500];
+
+/// A catalog where every entry has a quota, cooldown, gap and tags
+fn config(entries: usize) -> String {
+    let mut toml = String::from("config_version = 1\n");
+    for i in 0..entries {
+        write!(
+            toml,
+            "\n[[entries]]\nid = \"e{i}\"\nlabel = \"Entry {i}\"\n\
+             kind = {{ type = \"process\", command = \"/usr/bin/game\" }}\n\
+             tags = [\"tag{}\", \"tag{}\"]\n\
+             limits = {{ max_run_seconds = 1800, daily_quota_seconds = 7200, \
+             cooldown_seconds = 600, cooldown_scope = \"tag:tag{}\", \
+             min_gap_between_sessions_seconds = 300 }}\n",
+            i % 7,
+            i % 11,
+            i % 7,
+        )
+        .unwrap();
+    }
+    toml
+}
+
+/// An engine over a store on disk, with usage recorded for every entry and
+/// running cooldowns for some of them
+fn engine(entries: usize, dir: &tempfile::TempDir) -> CoreEngine {
+    let policy = shepherd_config::parse_config(&config(entries)).unwrap();
+    let store = Arc::new(SqliteStore::open(dir.path().join("shepherdd.db")).unwrap());
+    let now = shepherd_util::now();
+    for i in 0..entries {
+        let entry_id = EntryId::new(format!("e{}", i));
+        store
+            .add_usage(&entry_id, now.date_naive(), Duration::from_secs(60 * (i as u64 % 90)))
+            .unwrap();
+        if i % 3 == 0 {
+            store
+                .set_cooldown_until(&CooldownKey::Entry(entry_id), now + ChronoDuration::minutes(5))
+                .unwrap();
+        }
+    }
+    CoreEngine::new(policy, store, HostCapabilities::minimal())
+}
+
+fn list_entries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("list_entries");
+    for size in CATALOG_SIZES {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine(size, &dir);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &engine, |b, engine| {
+            b.iter(|| black_box(engine.list_entries(shepherd_util::now())))
+        });
+    }
+    group.finish();
+}
+
+/// Listing with every store read going to sqlite, as on the first tick
+fn list_entries_cold(c: &mut Criterion) {
+    let mut group = c.benchmark_group("list_entries_cold");
+    for size in CATALOG_SIZES {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = engine(size, &dir);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &engine, |b, engine| {
+            b.iter(|| {
+                engine.reload_store();
+                black_box(engine.list_entries(shepherd_util::now()))
+            })
+        });
+    }
+    group.finish();
+}
+
+fn tick(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick");
+    for size in CATALOG_SIZES {
+        let dir = tempfile::tempdir().unwrap();
+        let mut engine = engine(size, &dir);
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| black_box(engine.tick(MonotonicInstant::now(), shepherd_util::now())))
+        });
+    }
+    group.finish();
+}
+
+/// Snapshots taken between ticks, as after every broadcast event
+fn get_state(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_state");
+    for size in CATALOG_SIZES {
+        let dir = tempfile::tempdir().unwrap();
+        let mut engine = engine(size, &dir);
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter_custom(|iters| {
+                let mut measured = Duration::ZERO;
+                let mut ticked: Option<Instant> = None;
+                for _ in 0..iters {
+                    // Tick as the service does, outside the measurement
+                    if ticked.is_none_or(|at| at.elapsed() >= Duration::from_millis(500)) {
+                        engine.tick(MonotonicInstant::now(), shepherd_util::now());
+                        ticked = Some(Instant::now());
+                    }
+                    let start = Instant::now();
+                    black_box(engine.get_state());
+                    measured += start.elapsed();
+                }
+                measured
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, list_entries, list_entries_cold, tick, get_state);
+criterion_main!(benches);
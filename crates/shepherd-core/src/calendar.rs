@@ -0,0 +1,337 @@
+//! One-off exceptions from a family calendar
+//!
+//! Parents add events such as "Movie night #open:movies" to a shared
+//! calendar. The service polls its ICS feed and each tagged event opens or
+//! closes entries for its duration, on top of the regular windows.
+//!
+//! Targets are entry IDs or tags, or `*` for every entry. They are written
+//! as `#open:<target>` / `#close:<target>` in the summary or description,
+//! or as `open:<target>` / `close:<target>` categories. Recurring events
+//! are ignored; exceptions are meant to be one-offs.
+
+use chrono::{DateTime, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use shepherd_config::Entry;
+use shepherd_util::Timezone;
+use std::fmt;
+
+/// What an exception does to its entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CalendarAction {
+    /// Available even outside the entry's windows
+    Open,
+    /// Unavailable, like downtime
+    Close,
+}
+
+impl fmt::Display for CalendarAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CalendarAction::Open => "open",
+            CalendarAction::Close => "close",
+        })
+    }
+}
+
+/// A calendar event that opens or closes entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarException {
+    /// The event's UID, so it's only audited once
+    pub uid: String,
+    /// Summary with the tags taken out, shown to the child
+    pub label: String,
+    pub action: CalendarAction,
+    /// Entry IDs or tags; "*" matches every entry
+    pub targets: Vec<String>,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+}
+
+impl CalendarException {
+    pub fn applies_to(&self, entry: &Entry) -> bool {
+        self.targets
+            .iter()
+            .any(|t| t == "*" || t == entry.id.as_str() || entry.has_tag(t))
+    }
+
+    pub fn is_active(&self, now: DateTime<Local>) -> bool {
+        self.start <= now && now < self.end
+    }
+}
+
+/// Read the exceptions from an ICS feed. Times without a zone are in `tz`,
+/// the service's timezone. Events that can't be read are skipped.
+pub fn parse_ics(text: &str, tz: &Timezone) -> Vec<CalendarException> {
+    let mut exceptions = Vec::new();
+    let mut event: Option<Vec<Property>> = None;
+
+    for line in unfold(text) {
+        let Some(property) = Property::parse(&line) else {
+            continue;
+        };
+        match (property.name.as_str(), property.value.as_str()) {
+            ("BEGIN", "VEVENT") => event = Some(Vec::new()),
+            ("END", "VEVENT") => {
+                if let Some(properties) = event.take() {
+                    exceptions.extend(exceptions_from(&properties, tz));
+                }
+            }
+            _ => {
+                if let Some(properties) = &mut event {
+                    properties.push(property);
+                }
+            }
+        }
+    }
+    exceptions
+}
+
+/// Join continuation lines (starting with a space or tab) onto the line before
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// One content line: `NAME;PARAM=VALUE:value`
+#[derive(Debug)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn parse(line: &str) -> Option<Self> {
+        let (head, value) = line.split_once(':')?;
+        let mut parts = head.split(';');
+        let name = parts.next()?.trim().to_ascii_uppercase();
+        let params = parts
+            .filter_map(|p| p.split_once('='))
+            .map(|(k, v)| (k.to_ascii_uppercase(), v.trim_matches('"').to_string()))
+            .collect();
+        Some(Self {
+            name,
+            params,
+            value: value.to_string(),
+        })
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn exceptions_from(properties: &[Property], tz: &Timezone) -> Vec<CalendarException> {
+    let get = |name: &str| properties.iter().find(|p| p.name == name);
+
+    if get("RRULE").is_some() || get("STATUS").is_some_and(|p| p.value.eq_ignore_ascii_case("CANCELLED")) {
+        return Vec::new();
+    }
+
+    let summary = get("SUMMARY").map(|p| unescape(&p.value)).unwrap_or_default();
+    let description = get("DESCRIPTION").map(|p| unescape(&p.value)).unwrap_or_default();
+
+    let mut tags: Vec<(CalendarAction, String)> = summary
+        .split_whitespace()
+        .chain(description.split_whitespace())
+        .filter_map(|word| word.strip_prefix('#'))
+        .filter_map(parse_tag)
+        .collect();
+    for categories in properties.iter().filter(|p| p.name == "CATEGORIES") {
+        tags.extend(unescape(&categories.value).split(',').filter_map(parse_tag));
+    }
+    if tags.is_empty() {
+        return Vec::new();
+    }
+
+    let Some((start, all_day)) = get("DTSTART").and_then(|p| parse_time(p, tz)) else {
+        return Vec::new();
+    };
+    let end = match get("DTEND").and_then(|p| parse_time(p, tz)) {
+        Some((end, _)) => end,
+        None if all_day => start + ChronoDuration::days(1),
+        None => return Vec::new(),
+    };
+    if end <= start {
+        return Vec::new();
+    }
+
+    let label = summary
+        .split_whitespace()
+        .filter(|word| word.strip_prefix('#').is_none_or(|t| parse_tag(t).is_none()))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let label = if label.is_empty() {
+        "Family calendar".to_string()
+    } else {
+        label
+    };
+    let uid = get("UID").map(|p| p.value.clone()).unwrap_or_else(|| {
+        format!("{}@{}", label, start.to_rfc3339())
+    });
+
+    [CalendarAction::Open, CalendarAction::Close]
+        .into_iter()
+        .filter_map(|action| {
+            let targets: Vec<String> = tags
+                .iter()
+                .filter(|(a, _)| *a == action)
+                .map(|(_, target)| target.clone())
+                .collect();
+            (!targets.is_empty()).then(|| CalendarException {
+                uid: uid.clone(),
+                label: label.clone(),
+                action,
+                targets,
+                start,
+                end,
+            })
+        })
+        .collect()
+}
+
+/// `open:<target>` or `close:<target>`
+fn parse_tag(tag: &str) -> Option<(CalendarAction, String)> {
+    let (action, target) = tag.trim().split_once(':')?;
+    let action = match action.to_ascii_lowercase().as_str() {
+        "open" => CalendarAction::Open,
+        "close" => CalendarAction::Close,
+        _ => return None,
+    };
+    let target = target.trim_end_matches(['.', ',', ';', '!', '?']);
+    (!target.is_empty()).then(|| (action, target.to_string()))
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+/// A DTSTART or DTEND value, and whether it's a whole day
+fn parse_time(property: &Property, tz: &Timezone) -> Option<(DateTime<Local>, bool)> {
+    let value = property.value.trim();
+    if property.param("VALUE") == Some("DATE") || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some((resolve(tz, date.and_hms_opt(0, 0, 0)?)?, true));
+    }
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        let wall = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some((Utc.from_utc_datetime(&wall).with_timezone(&Local), false));
+    }
+
+    let wall = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    let zone = property.param("TZID").and_then(|name| name.parse::<Timezone>().ok());
+    Some((resolve(zone.as_ref().unwrap_or(tz), wall)?, false))
+}
+
+/// The instant a wall-clock time in `tz` refers to, moving past a
+/// spring-forward gap
+fn resolve(tz: &Timezone, wall: NaiveDateTime) -> Option<DateTime<Local>> {
+    let before = Utc
+        .from_utc_datetime(&(wall - ChronoDuration::days(2)))
+        .with_timezone(&Local);
+    tz.next_instant(&wall, &before)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEED: &str = "BEGIN:VCALENDAR\r\n\
+        VERSION:2.0\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:movie-night\r\n\
+        DTSTART:20250607T190000\r\n\
+        DTEND:20250607T210000\r\n\
+        SUMMARY:Movie night #open:movies\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:dentist\r\n\
+        DTSTART:20250609T150000Z\r\n\
+        DTEND:20250609T160000Z\r\n\
+        SUMMARY:Dentist\r\n\
+        CATEGORIES:close:*,Family\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:weekly\r\n\
+        DTSTART:20250601T180000\r\n\
+        DTEND:20250601T190000\r\n\
+        RRULE:FREQ=WEEKLY\r\n\
+        SUMMARY:Game club #open:games\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:birthday\r\n\
+        DTSTART;VALUE=DATE:20250610\r\n\
+        SUMMARY:Birthday\r\n\
+        DESCRIPTION:No screens today\\, it's a party! #close:\r\n \
+        games\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:untagged\r\n\
+        DTSTART:20250611T100000\r\n\
+        DTEND:20250611T110000\r\n\
+        SUMMARY:Piano lesson\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+
+    #[test]
+    fn test_parse_ics() {
+        let exceptions = parse_ics(FEED, &Timezone::Local);
+        assert_eq!(exceptions.len(), 3);
+
+        let movie = &exceptions[0];
+        assert_eq!(movie.label, "Movie night");
+        assert_eq!(movie.action, CalendarAction::Open);
+        assert_eq!(movie.targets, vec!["movies".to_string()]);
+        assert_eq!(
+            movie.start,
+            Local.with_ymd_and_hms(2025, 6, 7, 19, 0, 0).unwrap()
+        );
+        assert_eq!(movie.end - movie.start, ChronoDuration::hours(2));
+
+        let dentist = &exceptions[1];
+        assert_eq!(dentist.action, CalendarAction::Close);
+        assert_eq!(dentist.targets, vec!["*".to_string()]);
+        assert_eq!(
+            dentist.start,
+            Utc.with_ymd_and_hms(2025, 6, 9, 15, 0, 0).unwrap()
+        );
+
+        // Folded description, whole day
+        let birthday = &exceptions[2];
+        assert_eq!(birthday.targets, vec!["games".to_string()]);
+        assert_eq!(birthday.end - birthday.start, ChronoDuration::days(1));
+    }
+
+    #[test]
+    fn test_parse_service_timezone() {
+        let tz: Timezone = "UTC0".parse().unwrap();
+        let exceptions = parse_ics(FEED, &tz);
+        assert_eq!(
+            exceptions[0].start,
+            Utc.with_ymd_and_hms(2025, 6, 7, 19, 0, 0).unwrap()
+        );
+    }
+}
@@ -1,25 +1,54 @@
 //! Core policy engine
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, Timelike};
 use shepherd_api::{
-    ServiceStateSnapshot, EntryView, ReasonCode, SessionEndReason,
-    WarningSeverity, API_VERSION,
+    AudioOutput, BatteryInfo, CheckInRequest, DayOverview, EntryKind, EscapeRoute, EntryUsage, Hold, ImportedUsage, Mood, ServiceStateSnapshot, EntryView,
+    PendingPowerAction, CooldownScope, ReasonCode, RefundReason, SessionEndReason, SessionSummary, UpcomingWindow,
+    UsageCategory, WarningSeverity, API_VERSION,
 };
-use shepherd_config::{Entry, Policy};
+use shepherd_config::{DisplaySettings, Entry, JointUsage, LimitsPolicy, Policy, QuietHours};
 use shepherd_host_api::{HostCapabilities, HostSessionHandle};
-use shepherd_store::{AuditEvent, AuditEventType, Store};
-use shepherd_util::{EntryId, MonotonicInstant, SessionId};
-use std::collections::HashSet;
-use std::sync::Arc;
+use shepherd_store::{
+    AuditEvent, AuditEventType, CooldownKey, RewardGrant, SessionCheckIn, Store, StoreResult, UsageRecord,
+};
+use shepherd_util::{EntryId, MonotonicInstant, ProfileId, SeatId, SessionId, Timezone};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tracing::{debug, info};
+use tracing::{debug, error, info, warn};
+
+use crate::lookups::Lookups;
+use crate::{ActiveSession, CalendarAction, CalendarException, CoreEvent, EnforcementStep, SessionPlan, StopResult};
+
+/// How long a launch waits for the user to confirm it
+pub const LAUNCH_CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Autostarts missed by more than this, e.g. while suspended, are skipped
+pub const AUTOSTART_GRACE: Duration = Duration::from_secs(5 * 60);
+
+/// How long an expired session gets to exit before the next, harsher stop
+pub const ENFORCEMENT_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Escalation steps after the initial graceful stop: one forced kill, then
+/// cgroup kills
+pub const MAX_ENFORCEMENT_ATTEMPTS: u32 = 3;
+
+/// How often clients are told the time left in a session
+pub const TIME_SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time left at which clients are told it every second instead
+pub const TIME_SYNC_FINAL: Duration = Duration::from_secs(5 * 60);
 
-use crate::{ActiveSession, CoreEvent, SessionPlan, StopResult};
+/// How long entry views from the last tick are reused by [`CoreEngine::get_state`]
+pub const ENTRY_CACHE_MAX_AGE: Duration = Duration::from_secs(1);
 
 /// Launch decision from the core engine
 #[derive(Debug)]
 pub enum LaunchDecision {
     Approved(SessionPlan),
+    /// The entry wants the user to confirm the granted time first; the plan
+    /// is held until [`CoreEngine::confirm_launch`] approves it
+    NeedsConfirmation(SessionPlan),
     Denied { reasons: Vec<ReasonCode> },
 }
 
@@ -33,11 +62,69 @@ pub enum StopDecision {
 /// The core policy engine
 pub struct CoreEngine {
     policy: Policy,
+    /// False while running without a config file, until first-run setup saves one
+    policy_loaded: bool,
     store: Arc<dyn Store>,
     capabilities: HostCapabilities,
-    current_session: Option<ActiveSession>,
+    /// Running session on each seat; one at most per seat
+    sessions: BTreeMap<SeatId, ActiveSession>,
     /// Tracks which entries were enabled on the last tick, to detect availability changes
     last_availability_set: HashSet<EntryId>,
+    /// Display settings applied on the last tick, to detect schedule boundaries
+    last_display_settings: Option<DisplaySettings>,
+    /// Quiet hours in effect as of the last tick
+    quiet_hours: QuietHours,
+    /// Where sound is going, as last reported by the volume backend
+    audio_output: AudioOutput,
+    /// Running curfew shutdown/suspend countdown
+    power_countdown: Option<PowerCountdown>,
+    /// Countdown was cancelled; don't restart it until the next session or curfew
+    power_cancelled: bool,
+    /// Latest battery reading from the host
+    battery: Option<BatteryInfo>,
+    /// Last ended session on each seat, until the feelings check-in is
+    /// answered or skipped
+    pending_check_in: BTreeMap<SeatId, CheckInRequest>,
+    /// Problems found by host health probes, for broken entries only
+    entry_health: HashMap<EntryId, String>,
+    /// Kiosk escape routes found by the host at startup
+    escape_routes: Vec<EscapeRoute>,
+    /// Entry whose app is being updated by maintenance
+    updating: Option<EntryId>,
+    /// Launch shown to the user on each seat and waiting for ConfirmLaunch
+    pending_launch: BTreeMap<SeatId, PendingLaunch>,
+    /// Time of the last autostart check; times up to here have been handled
+    last_autostart_check: Option<DateTime<Local>>,
+    /// Admin hold blocking all launches, kept in the store across restarts
+    hold: Option<Hold>,
+    /// One-off exceptions from the family calendar feed
+    calendar: Vec<CalendarException>,
+    /// Calendar events already audited as started, by UID and action
+    calendar_started: HashSet<(String, CalendarAction)>,
+    /// Entry views from the last tick or snapshot, cleared by anything
+    /// that changes them
+    entry_cache: Mutex<Option<EntryCache>>,
+    /// Usage, cooldowns and other store reads behind entry views
+    lookups: Lookups,
+}
+
+#[derive(Debug, Clone)]
+struct EntryCache {
+    at: DateTime<Local>,
+    entries: Vec<EntryView>,
+}
+
+#[derive(Debug, Clone)]
+struct PendingLaunch {
+    plan: SessionPlan,
+    requested_at: DateTime<Local>,
+}
+
+/// Curfew countdown tracked against monotonic time
+#[derive(Debug, Clone, Copy)]
+struct PowerCountdown {
+    pending: PendingPowerAction,
+    deadline_mono: MonotonicInstant,
 }
 
 impl CoreEngine {
@@ -57,12 +144,35 @@ impl CoreEngine {
             entry_count: policy.entries.len(),
         }));
 
+        let hold = store.get_hold().unwrap_or_else(|e| {
+            warn!(error = %e, "Failed to load launch hold");
+            None
+        });
+
         Self {
             policy,
             store,
             capabilities,
-            current_session: None,
+            policy_loaded: true,
+            sessions: BTreeMap::new(),
             last_availability_set: HashSet::new(),
+            last_display_settings: None,
+            quiet_hours: QuietHours::default(),
+            audio_output: AudioOutput::Unknown,
+            power_countdown: None,
+            power_cancelled: false,
+            battery: None,
+            pending_check_in: BTreeMap::new(),
+            entry_health: HashMap::new(),
+            escape_routes: Vec::new(),
+            updating: None,
+            pending_launch: BTreeMap::new(),
+            last_autostart_check: None,
+            hold,
+            calendar: Vec::new(),
+            calendar_started: HashSet::new(),
+            entry_cache: Mutex::new(None),
+            lookups: Lookups::default(),
         }
     }
 
@@ -71,10 +181,23 @@ impl CoreEngine {
         &self.policy
     }
 
+    /// Mark the engine as running without a config file, so clients offer
+    /// first-run setup
+    pub fn set_unconfigured(&mut self) {
+        self.policy_loaded = false;
+    }
+
     /// Reload policy
-    pub fn reload_policy(&mut self, policy: Policy) -> CoreEvent {
+    pub fn reload_policy(&mut self, policy: Policy) -> Vec<CoreEvent> {
         let entry_count = policy.entries.len();
+        let old_theme = self.policy.service.theme;
         self.policy = policy;
+        self.policy_loaded = true;
+        self.invalidate_entries();
+        self.entry_health
+            .retain(|entry_id, _| self.policy.get_entry(entry_id).is_some());
+        // Re-apply the display schedule from the new policy on the next tick
+        self.last_display_settings = None;
 
         let _ = self.store.append_audit(AuditEvent::new(AuditEventType::PolicyLoaded {
             entry_count,
@@ -82,20 +205,196 @@ impl CoreEngine {
 
         info!(entry_count, "Policy reloaded");
 
-        CoreEvent::PolicyReloaded { entry_count }
+        let mut events = vec![CoreEvent::PolicyReloaded { entry_count }];
+        let theme = self.policy.service.theme;
+        if theme != old_theme {
+            info!(theme = theme.as_str(), "Theme changed");
+            events.push(CoreEvent::ThemeChanged(theme));
+        }
+        events
     }
 
     /// List all entries with availability status
     pub fn list_entries(&self, now: DateTime<Local>) -> Vec<EntryView> {
+        self.list_entries_on(&SeatId::default(), now)
+    }
+
+    /// List entries as seen from `seat`
+    pub fn list_entries_on(&self, seat: &SeatId, now: DateTime<Local>) -> Vec<EntryView> {
         self.policy
             .entries
             .iter()
-            .map(|entry| self.evaluate_entry(entry, now))
+            .map(|entry| self.evaluate_entry(entry, seat, now))
             .collect()
     }
 
+    /// Today's use of every enabled entry, grouped by its first tag, and
+    /// the entries' time windows that haven't ended yet today
+    pub fn day_overview(&self, now: DateTime<Local>) -> DayOverview {
+        let today = now.date_naive();
+        let now_mono = MonotonicInstant::now();
+        let mut categories: Vec<UsageCategory> = Vec::new();
+        for entry in self.policy.entries.iter().filter(|e| !e.disabled) {
+            let mut used = self
+                .lookups
+                .usage(self.store.as_ref(), &entry.id, today)
+                .unwrap_or_default();
+            // Running sessions aren't charged until they end
+            used += self
+                .sessions
+                .values()
+                .filter(|session| session.plan.entry_id == entry.id)
+                .map(|session| session.charged(now_mono))
+                .sum::<Duration>();
+            let usage = EntryUsage {
+                entry_id: entry.id.clone(),
+                label: entry.label.clone(),
+                used,
+                quota: self.effective_quota(entry, now),
+            };
+
+            let name = entry.tags.first().cloned();
+            match categories.iter_mut().find(|c| c.name == name) {
+                Some(category) => category.entries.push(usage),
+                None => categories.push(UsageCategory {
+                    name,
+                    entries: vec![usage],
+                }),
+            }
+        }
+        // Stable, so tagged categories keep the config's order
+        categories.sort_by_key(|c| c.name.is_none());
+
+        DayOverview {
+            categories,
+            windows: self.upcoming_windows(now),
+        }
+    }
+
+    /// Today's occurrences of the enabled entries' time windows that end
+    /// after `now`, one per distinct window, earliest first
+    fn upcoming_windows(&self, now: DateTime<Local>) -> Vec<UpcomingWindow> {
+        let tz = &self.policy.service.timezone;
+        let today = tz.wall_time(&now).date();
+        // Before any wall time today, so windows already open keep their start
+        let since = now - chrono::Duration::days(1);
+        let mut windows: Vec<UpcomingWindow> = Vec::new();
+        for entry in self.policy.entries.iter().filter(|e| !e.disabled && !e.availability.always) {
+            for window in entry.availability.windows.iter().filter(|w| w.days.contains(today.weekday())) {
+                // A window crossing midnight ends tomorrow
+                let end_date = if window.start > window.end {
+                    today.succ_opt()
+                } else {
+                    Some(today)
+                };
+                let (Some(start), Some(end)) = (
+                    tz.next_instant(&today.and_time(window.start.to_naive_time()), &since),
+                    end_date.and_then(|date| tz.next_instant(&date.and_time(window.end.to_naive_time()), &since)),
+                ) else {
+                    continue;
+                };
+                if end <= now {
+                    continue;
+                }
+                match windows.iter_mut().find(|w| w.start == start && w.end == end) {
+                    Some(shared) => shared.labels.push(entry.label.clone()),
+                    None => windows.push(UpcomingWindow {
+                        start,
+                        end,
+                        labels: vec![entry.label.clone()],
+                    }),
+                }
+            }
+        }
+        windows.sort_by_key(|w| (w.start, w.end));
+        windows
+    }
+
+    /// List entries as of now, reusing the views from the last tick if
+    /// nothing has changed since and they are less than
+    /// [`ENTRY_CACHE_MAX_AGE`] old. Evaluating an entry reads the store, so
+    /// this keeps snapshots cheap with many entries.
+    pub fn current_entries(&self) -> Vec<EntryView> {
+        let now = shepherd_util::now();
+        let mut cache = self.entry_cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cached) = cache.as_ref()
+            && now >= cached.at
+            && (now - cached.at).to_std().is_ok_and(|age| age < ENTRY_CACHE_MAX_AGE)
+        {
+            return cached.entries.clone();
+        }
+        let entries = self.list_entries(now);
+        *cache = Some(EntryCache {
+            at: now,
+            entries: entries.clone(),
+        });
+        entries
+    }
+
+    /// List entries as of now as seen from `seat`. Only the default seat's
+    /// views are cached.
+    pub fn entries_on(&self, seat: &SeatId) -> Vec<EntryView> {
+        if seat.is_default() {
+            return self.current_entries();
+        }
+        self.list_entries_on(seat, shepherd_util::now())
+    }
+
+    /// Re-evaluate every entry and report whether the set of available ones
+    /// changed. The views are kept for snapshots taken before the next tick.
+    fn check_availability(&mut self, now: DateTime<Local>) -> Option<CoreEvent> {
+        let entries = self.list_entries(now);
+        let current_availability: HashSet<EntryId> = entries
+            .iter()
+            .filter(|view| view.enabled)
+            .map(|view| view.entry_id.clone())
+            .collect();
+        *self.entry_cache.lock().unwrap_or_else(|e| e.into_inner()) = Some(EntryCache { at: now, entries });
+
+        if current_availability == self.last_availability_set {
+            return None;
+        }
+        debug!(
+            previous = ?self.last_availability_set,
+            current = ?current_availability,
+            "Entry availability set changed"
+        );
+        self.last_availability_set = current_availability;
+        Some(CoreEvent::AvailabilitySetChanged)
+    }
+
+    /// Start a new day, or pick up a timezone change: re-read everything
+    /// cached from the store and re-evaluate availability
+    pub fn roll_over_day(&mut self, now: DateTime<Local>) -> Option<CoreEvent> {
+        info!(day = %now.date_naive(), "Day rolled over");
+        self.reload_store();
+        self.check_availability(now)
+    }
+
+    /// Drop cached entry views after a change that affects them
+    fn invalidate_entries(&self) {
+        *self.entry_cache.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Forget usage, cooldowns and rewards read from the store. The engine
+    /// keeps them in memory and updates them as it writes, so call this
+    /// after writing them to the store some other way.
+    pub fn reload_store(&self) {
+        self.lookups.clear();
+        self.invalidate_entries();
+    }
+
+    /// List entries as they would be under a candidate policy, without
+    /// applying it
+    pub fn simulate_entries(&mut self, policy: Policy, now: DateTime<Local>) -> Vec<EntryView> {
+        let current = std::mem::replace(&mut self.policy, policy);
+        let entries = self.list_entries(now);
+        self.policy = current;
+        entries
+    }
+
     /// Evaluate a single entry for availability
-    fn evaluate_entry(&self, entry: &Entry, now: DateTime<Local>) -> EntryView {
+    fn evaluate_entry(&self, entry: &Entry, seat: &SeatId, now: DateTime<Local>) -> EntryView {
         let mut reasons = Vec::new();
         let mut enabled = true;
 
@@ -107,6 +406,49 @@ impl CoreEngine {
             });
         }
 
+        // An admin hold blocks everything until it's lifted or runs out
+        if let Some(hold) = self.active_hold(now) {
+            enabled = false;
+            reasons.push(ReasonCode::OnHold {
+                message: hold.message.clone(),
+                until: hold.until,
+            });
+        }
+
+        // Downtime overrides every entry's own windows
+        if let Some((label, until)) = self.active_downtime(now) {
+            enabled = false;
+            reasons.push(ReasonCode::Downtime {
+                label: label.cloned(),
+                until,
+            });
+        }
+
+        // A calendar event closing the entry reads like downtime
+        if let Some(close) = self.calendar_exception(entry, CalendarAction::Close, now) {
+            enabled = false;
+            reasons.push(ReasonCode::Downtime {
+                label: Some(close.label.clone()),
+                until: close.end,
+            });
+        }
+
+        // Don't launch an app while its package is being replaced
+        if self.updating.as_ref() == Some(&entry.id) {
+            enabled = false;
+            reasons.push(ReasonCode::Disabled {
+                reason: Some("Updating".into()),
+            });
+        }
+
+        // Check if a health probe found the entry broken
+        if let Some(problem) = self.entry_health.get(&entry.id) {
+            enabled = false;
+            reasons.push(ReasonCode::Disabled {
+                reason: Some(problem.clone()),
+            });
+        }
+
         // Check host capabilities
         let kind_tag = entry.kind.tag();
         if !self.capabilities.supports_kind(kind_tag) {
@@ -114,40 +456,110 @@ impl CoreEngine {
             reasons.push(ReasonCode::UnsupportedKind { kind: kind_tag });
         }
 
-        // Check availability window
-        if !entry.availability.is_available(&now) {
+        // Check availability window, unless a calendar event opens the entry
+        if !entry.availability.is_available(&self.policy.service.timezone, &now)
+            && self.calendar_exception(entry, CalendarAction::Open, now).is_none()
+        {
             enabled = false;
             reasons.push(ReasonCode::OutsideTimeWindow {
                 next_window_start: None, // TODO: compute next window
             });
         }
 
-        // Check if another session is active
-        if let Some(session) = &self.current_session {
+        // Check if another session is active on this seat, or this entry
+        // is running on another one
+        if let Some(session) = self.sessions.get(seat) {
             enabled = false;
             reasons.push(ReasonCode::SessionActive {
                 entry_id: session.plan.entry_id.clone(),
                 remaining: session.time_remaining(MonotonicInstant::now()),
             });
+        } else if let Some(other) = self
+            .sessions
+            .iter()
+            .find_map(|(other, session)| (session.plan.entry_id == entry.id).then_some(other))
+        {
+            enabled = false;
+            reasons.push(ReasonCode::InUseOnOtherSeat { seat: other.clone() });
+        }
+
+        // Check headphones. An unknown output gets the benefit of the doubt,
+        // or hosts that can't tell would never launch anything.
+        if self.audio_output == AudioOutput::Speakers && self.requires_headphones(entry, &now) {
+            enabled = false;
+            reasons.push(ReasonCode::HeadphonesRequired);
+        }
+
+        // Check battery
+        if let Some(stop_below) = self.policy.battery.stop_below
+            && let Some(battery) = &self.battery
+            && battery.is_below(stop_below)
+        {
+            enabled = false;
+            reasons.push(ReasonCode::LowBattery {
+                percent: battery.percent.unwrap_or(0),
+            });
         }
 
-        // Check cooldown
-        if let Ok(Some(until)) = self.store.get_cooldown_until(&entry.id)
-            && until > now {
+        // Check reward unlock
+        if let Some(stars_required) = self.policy.rewards.required_stars(&entry.id) {
+            let stars = self.total_stars();
+            if stars < stars_required {
                 enabled = false;
-                reasons.push(ReasonCode::CooldownActive { available_at: until });
+                reasons.push(ReasonCode::RewardLocked { stars_required, stars });
             }
+        }
+
+        // Check cooldowns: the entry's own, its tags' and the global one,
+        // and the minimum gap since its last session, reporting whichever
+        // ends last
+        let mut keys = vec![CooldownKey::Entry(entry.id.clone()), CooldownKey::Global];
+        keys.extend(entry.tags.iter().cloned().map(CooldownKey::Tag));
+        let gap_until = entry.limits.min_gap.and_then(|gap| {
+            let ended = self.lookups.last_session_end(self.store.as_ref(), &entry.id).ok()??;
+            Some((ended + chrono::Duration::from_std(gap).ok()?, CooldownScope::Entry))
+        });
+        if let Some((until, scope)) = keys
+            .into_iter()
+            .filter_map(|key| {
+                let until = self.lookups.cooldown_until(self.store.as_ref(), &key).ok()??;
+                Some((until, key.scope()))
+            })
+            .chain(gap_until)
+            .filter(|(until, _)| *until > now)
+            .max_by_key(|(until, _)| *until)
+        {
+            enabled = false;
+            reasons.push(ReasonCode::CooldownActive {
+                available_at: until,
+                scope,
+            });
+        }
 
         // Check daily quota
-        if let Some(quota) = entry.limits.daily_quota {
+        if let Some(quota) = self.effective_quota(entry, now) {
             let today = now.date_naive();
-            if let Ok(used) = self.store.get_usage(&entry.id, today)
+            if let Ok(used) = self.quota_usage(entry, today)
                 && used >= quota {
                     enabled = false;
                     reasons.push(ReasonCode::QuotaExhausted { used, quota });
                 }
         }
 
+        // Don't start a session shorter than the entry's minimum
+        if enabled
+            && let Some(min_run) = entry.limits.min_run
+            && let Some(available) = self.compute_max_duration(entry, now)
+            && available < min_run
+        {
+            enabled = false;
+            reasons.push(ReasonCode::NotEnoughTime {
+                available,
+                min_run,
+                next_full_session: self.next_full_session(entry, min_run, now),
+            });
+        }
+
         // Calculate max run if enabled (None when disabled, Some(None) flattened for unlimited)
         let max_run_if_started_now = if enabled {
             self.compute_max_duration(entry, now)
@@ -163,6 +575,10 @@ impl CoreEngine {
             enabled,
             reasons,
             max_run_if_started_now,
+            time_bank: entry
+                .limits
+                .daily_quota
+                .and_then(|_| self.banked_time(entry, now)),
         }
     }
 
@@ -171,18 +587,45 @@ impl CoreEngine {
     fn compute_max_duration(&self, entry: &Entry, now: DateTime<Local>) -> Option<Duration> {
         let mut max = entry.limits.max_run;
 
-        // Limit by time window remaining
-        if let Some(window_remaining) = entry.availability.remaining_in_window(&now) {
+        // Limit by time window remaining, or by the end of a calendar event
+        // opening the entry if that's later
+        let mut window_remaining = entry.availability.remaining_in_window(&self.policy.service.timezone, &now);
+        if !entry.availability.always
+            && !entry.availability.windows.is_empty()
+            && let Some(open) = self.calendar_exception(entry, CalendarAction::Open, now)
+        {
+            let until_end = (open.end - now).to_std().unwrap_or(Duration::ZERO);
+            window_remaining = Some(window_remaining.map_or(until_end, |w| w.max(until_end)));
+        }
+        if let Some(window_remaining) = window_remaining {
             max = Some(match max {
                 Some(m) => m.min(window_remaining),
                 None => window_remaining,
             });
         }
 
+        // Limit by the next downtime
+        if let Some(start) = self.next_downtime_start(now) {
+            let until_downtime = (start - now).to_std().unwrap_or(Duration::ZERO);
+            max = Some(max.map_or(until_downtime, |m| m.min(until_downtime)));
+        }
+
+        // Limit by the next calendar event closing the entry
+        if let Some(start) = self
+            .calendar
+            .iter()
+            .filter(|e| e.action == CalendarAction::Close && e.start > now && e.applies_to(entry))
+            .map(|e| e.start)
+            .min()
+        {
+            let until_close = (start - now).to_std().unwrap_or(Duration::ZERO);
+            max = Some(max.map_or(until_close, |m| m.min(until_close)));
+        }
+
         // Limit by daily quota remaining
-        if let Some(quota) = entry.limits.daily_quota {
+        if let Some(quota) = self.effective_quota(entry, now) {
             let today = now.date_naive();
-            if let Ok(used) = self.store.get_usage(&entry.id, today) {
+            if let Ok(used) = self.quota_usage(entry, today) {
                 let remaining = quota.saturating_sub(used);
                 max = Some(match max {
                     Some(m) => m.min(remaining),
@@ -194,582 +637,3520 @@ impl CoreEngine {
         max
     }
 
-    /// Request to launch an entry
-    pub fn request_launch(
+    /// Earliest time in the next week when a session of at least `min_run`
+    /// could start: a window opening, or a new day's quota at midnight
+    fn next_full_session(
         &self,
-        entry_id: &EntryId,
+        entry: &Entry,
+        min_run: Duration,
         now: DateTime<Local>,
-    ) -> LaunchDecision {
-        // Find entry
-        let entry = match self.policy.get_entry(entry_id) {
-            Some(e) => e,
-            None => {
-                return LaunchDecision::Denied {
-                    reasons: vec![ReasonCode::Disabled {
-                        reason: Some("Entry not found".into()),
-                    }],
-                };
-            }
-        };
+    ) -> Option<DateTime<Local>> {
+        let tz = &self.policy.service.timezone;
+        let today = tz.wall_time(&now).date();
+        let mut candidates: Vec<DateTime<Local>> = (0..=7)
+            .filter_map(|days| today.checked_add_days(chrono::Days::new(days)))
+            .flat_map(|date| {
+                let midnight = date.and_time(NaiveTime::MIN);
+                // Usage is counted per local day, which may differ from `tz`
+                let mut starts = vec![
+                    tz.next_instant(&midnight, &now),
+                    Timezone::Local.next_instant(&midnight, &now),
+                ];
+                starts.extend(
+                    self.policy
+                        .downtime
+                        .iter()
+                        .filter(|d| d.window.days.contains(date.weekday()))
+                        .map(|d| tz.next_instant(&date.and_time(d.window.end.to_naive_time()), &now)),
+                );
+                if !entry.availability.always {
+                    starts.extend(
+                        entry
+                            .availability
+                            .windows
+                            .iter()
+                            .filter(|w| w.days.contains(date.weekday()))
+                            .map(|w| tz.next_instant(&date.and_time(w.start.to_naive_time()), &now)),
+                    );
+                }
+                starts
+            })
+            .flatten()
+            .filter(|start| *start > now)
+            .collect();
+        candidates.sort();
 
-        // Evaluate availability
-        let view = self.evaluate_entry(entry, now);
+        candidates.into_iter().find(|start| {
+            entry.availability.is_available(tz, start)
+                && self.active_downtime(*start).is_none()
+                && self
+                    .compute_max_duration(entry, *start)
+                    .is_none_or(|max| max >= min_run)
+        })
+    }
 
-        if !view.enabled {
-            // Log denial
-            let _ = self.store.append_audit(AuditEvent::new(AuditEventType::LaunchDenied {
-                entry_id: entry_id.clone(),
-                reasons: view.reasons.iter().map(|r| format!("{:?}", r)).collect(),
-            }));
+    /// The downtime block in effect at `now`: its label and when it ends.
+    /// Blocks that start as another ends read as one.
+    fn active_downtime(&self, now: DateTime<Local>) -> Option<(Option<&String>, DateTime<Local>)> {
+        let tz = &self.policy.service.timezone;
+        let current = self
+            .policy
+            .downtime
+            .iter()
+            .find(|d| d.window.contains_in(tz, &now))?;
 
-            return LaunchDecision::Denied {
-                reasons: view.reasons,
+        let mut until = now;
+        // Bounded in case blocks cover the whole week
+        for _ in 0..=7 * self.policy.downtime.len() {
+            let Some(remaining) = self
+                .policy
+                .downtime
+                .iter()
+                .filter_map(|d| d.window.remaining_duration_in(tz, &until))
+                .max()
+                .filter(|r| !r.is_zero())
+            else {
+                break;
             };
+            until += remaining;
         }
-
-        // Compute session plan
-        let max_duration = view.max_run_if_started_now;
-        let plan = SessionPlan {
-            session_id: SessionId::new(),
-            entry_id: entry_id.clone(),
-            label: entry.label.clone(),
-            max_duration,
-            warnings: entry.warnings.clone(),
-        };
-
-        if let Some(max_dur) = max_duration {
-            debug!(
-                entry_id = %entry_id,
-                max_duration_secs = max_dur.as_secs(),
-                "Launch approved"
-            );
-        } else {
-            debug!(
-                entry_id = %entry_id,
-                "Launch approved (unlimited)"
-            );
-        }
-
-        LaunchDecision::Approved(plan)
+        Some((current.label.as_ref(), until))
     }
 
-    /// Start a session from an approved plan
-    pub fn start_session(
-        &mut self,
-        plan: SessionPlan,
+    /// A calendar event with `action` covering the entry at `now`
+    fn calendar_exception(
+        &self,
+        entry: &Entry,
+        action: CalendarAction,
         now: DateTime<Local>,
-        now_mono: MonotonicInstant,
-    ) -> CoreEvent {
-        let session = ActiveSession::new(plan.clone(), now, now_mono);
-
-        let event = CoreEvent::SessionStarted {
-            session_id: session.plan.session_id.clone(),
-            entry_id: session.plan.entry_id.clone(),
-            label: session.plan.label.clone(),
-            deadline: session.deadline,
-        };
-
-        // Log to audit
-        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::SessionStarted {
-            session_id: session.plan.session_id.clone(),
-            entry_id: session.plan.entry_id.clone(),
-            label: session.plan.label.clone(),
-            deadline: session.deadline,
-        }));
-
-        if let Some(deadline) = session.deadline {
-            info!(
-                session_id = %session.plan.session_id,
-                entry_id = %session.plan.entry_id,
-                deadline = %deadline,
-                "Session started"
-            );
-        } else {
-            info!(
-                session_id = %session.plan.session_id,
-                entry_id = %session.plan.entry_id,
-                "Session started (unlimited)"
-            );
-        }
-
-        self.current_session = Some(session);
+    ) -> Option<&CalendarException> {
+        self.calendar
+            .iter()
+            .find(|e| e.action == action && e.is_active(now) && e.applies_to(entry))
+    }
 
-        event
+    /// Start of the next downtime block within a week
+    fn next_downtime_start(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        let tz = &self.policy.service.timezone;
+        let today = tz.wall_time(&now).date();
+        (0..=7)
+            .filter_map(|days| today.checked_add_days(chrono::Days::new(days)))
+            .flat_map(|date| {
+                self.policy
+                    .downtime
+                    .iter()
+                    .filter(move |d| d.window.days.contains(date.weekday()))
+                    .filter_map(move |d| {
+                        tz.next_instant(&date.and_time(d.window.start.to_naive_time()), &now)
+                    })
+            })
+            .filter(|start| *start > now)
+            .min()
     }
 
-    /// Attach host handle to current session
-    pub fn attach_host_handle(&mut self, handle: HostSessionHandle) {
-        if let Some(session) = &mut self.current_session {
-            session.attach_handle(handle);
+    /// Daily quota for an entry including bonus minutes earned with stars
+    /// and time banked today. Returns None if the entry has no quota (unlimited).
+    fn effective_quota(&self, entry: &Entry, now: DateTime<Local>) -> Option<Duration> {
+        let mut quota = entry.limits.daily_quota?;
+        if !self.policy.rewards.thresholds.is_empty() {
+            quota += self.policy.rewards.bonus_quota(self.total_stars());
+        }
+        if let Some(banked) = self.banked_time(entry, now) {
+            quota += banked;
         }
+        Some(quota)
     }
 
-    /// Tick the engine - check for warnings, expiry, and availability changes
-    pub fn tick(&mut self, now_mono: MonotonicInstant, now: DateTime<Local>) -> Vec<CoreEvent> {
-        let mut events = Vec::new();
-
-        // Check if the set of available entries has changed
-        let current_availability: HashSet<EntryId> = self
-            .policy
+    /// Use on `day` counted against an entry's daily quota. Retro entries
+    /// playing the same ROM share it, so another tile (or core) for the same
+    /// game doesn't reset the day.
+    fn quota_usage(&self, entry: &Entry, day: NaiveDate) -> StoreResult<Duration> {
+        let usage = |id: &EntryId| self.lookups.usage(self.store.as_ref(), id, day);
+        let EntryKind::Retro { rom_path, .. } = &entry.kind else {
+            return usage(&entry.id);
+        };
+        self.policy
             .entries
             .iter()
-            .filter(|e| self.evaluate_entry(e, now).enabled)
-            .map(|e| e.id.clone())
-            .collect();
+            .filter(|e| matches!(&e.kind, EntryKind::Retro { rom_path: other, .. } if other == rom_path))
+            .map(|e| usage(&e.id))
+            .sum()
+    }
 
-        if current_availability != self.last_availability_set {
-            debug!(
-                previous = ?self.last_availability_set,
-                current = ?current_availability,
-                "Entry availability set changed"
-            );
-            self.last_availability_set = current_availability;
-            events.push(CoreEvent::AvailabilitySetChanged);
+    /// Time banked today for an entry tagged to receive exchanged time
+    fn banked_time(&self, entry: &Entry, now: DateTime<Local>) -> Option<Duration> {
+        let exchange = self.policy.time_exchange.as_ref()?;
+        if !entry.has_tag(&exchange.spend_tag) {
+            return None;
         }
+        self.lookups
+            .time_bank(self.store.as_ref(), &exchange.spend_tag, now.date_naive())
+            .ok()
+            .filter(|banked| !banked.is_zero())
+    }
 
-        let session = match &mut self.current_session {
-            Some(s) => s,
-            None => return events,
-        };
-
-        // Check for pending warnings
-        for (threshold, remaining) in session.pending_warnings(now_mono) {
-            let severity = session
-                .plan
-                .warnings
-                .iter()
-                .find(|w| w.seconds_before == threshold)
-                .map(|w| w.severity)
-                .unwrap_or(WarningSeverity::Warn);
-
-            let message = session
-                .plan
-                .warnings
-                .iter()
-                .find(|w| w.seconds_before == threshold)
-                .and_then(|w| w.message_template.clone());
+    /// Charge a finished session to its entry's quota and set any cooldown,
+    /// unless the entry's refund policy covers it. Returns the time charged
+    /// to today.
+    fn record_usage(
+        &self,
+        session: &ActiveSession,
+        reason: &SessionEndReason,
+        now_mono: MonotonicInstant,
+        now: DateTime<Local>,
+    ) -> Duration {
+        let plan = &session.plan;
+        let duration = session.charged(now_mono);
+        self.lookups.session_ended(&plan.entry_id);
 
-            session.mark_warning_issued(threshold);
+        // The app never ran, so there's nothing to charge or cool down from
+        if matches!(reason, SessionEndReason::LaunchFailed { .. }) {
+            return Duration::ZERO;
+        }
 
-            // Log to audit
-            let _ = self.store.append_audit(AuditEvent::new(AuditEventType::WarningIssued {
-                session_id: session.plan.session_id.clone(),
-                threshold_seconds: threshold,
-            }));
+        let limits = self.policy.get_entry(&plan.entry_id).map(|e| &e.limits);
 
+        if let Some(refund) = limits.and_then(|l| refund_reason(l, reason, duration)) {
             info!(
-                session_id = %session.plan.session_id,
-                threshold_seconds = threshold,
-                remaining_secs = remaining.as_secs(),
-                "Warning issued"
+                session_id = %plan.session_id,
+                entry_id = %plan.entry_id,
+                duration_secs = duration.as_secs(),
+                reason = ?refund,
+                "Session time refunded"
             );
+            let _ = self.store.append_audit(AuditEvent::new(AuditEventType::UsageRefunded {
+                session_id: plan.session_id.clone(),
+                entry_id: plan.entry_id.clone(),
+                reason: refund,
+                duration,
+            }));
+            return Duration::ZERO;
+        }
 
-            events.push(CoreEvent::Warning {
-                session_id: session.plan.session_id.clone(),
-                threshold_seconds: threshold,
-                time_remaining: remaining,
-                severity,
-                message,
-            });
+        // A late-night session counts against each day it ran on
+        let today = now.date_naive();
+        let mut charged_today = Duration::ZERO;
+        for (day, part) in session_usage_by_day(session, now_mono, now) {
+            let _ = self.lookups.add_usage(self.store.as_ref(), &plan.entry_id, day, part);
+            self.charge_participants(plan, part, day);
+            if day == today {
+                charged_today = part;
+            }
         }
+        self.credit_time_bank(&plan.entry_id, duration, now);
 
-        // Check for expiry
-        if session.is_expired(now_mono)
-            && session.state != shepherd_api::SessionState::Expiring
-            && session.state != shepherd_api::SessionState::Ended
+        if let Some(limits) = limits
+            && let Some(cooldown) = limits.cooldown
+            && self.cooldown_due(limits, &plan.entry_id, charged_today, now)
         {
-            session.mark_expiring();
-
-            info!(
-                session_id = %session.plan.session_id,
-                "Session expiring"
-            );
-
-            events.push(CoreEvent::ExpireDue {
-                session_id: session.plan.session_id.clone(),
-            });
+            let key = CooldownKey::new(&limits.cooldown_scope, &plan.entry_id);
+            let until = now + chrono::Duration::from_std(cooldown).unwrap();
+            let _ = self.lookups.set_cooldown_until(self.store.as_ref(), &key, until);
         }
-
-        events
+        charged_today
     }
 
-    /// Notify that a session has exited
-    pub fn notify_session_exited(
-        &mut self,
-        exit_code: Option<i32>,
-        now_mono: MonotonicInstant,
+    /// Summary of a session that just ended and had `quota_used` charged
+    /// to today
+    fn summarize(
+        &self,
+        session: &ActiveSession,
+        reason: &SessionEndReason,
+        duration: Duration,
+        quota_used: Duration,
         now: DateTime<Local>,
-    ) -> Option<CoreEvent> {
-        let session = self.current_session.take()?;
+    ) -> SessionSummary {
+        let entry_id = &session.plan.entry_id;
+        let entry = self.policy.get_entry(entry_id);
+        let remaining_today = entry.and_then(|entry| {
+            let quota = self.effective_quota(entry, now)?;
+            let used = self.quota_usage(entry, now.date_naive()).unwrap_or_default();
+            Some(quota.saturating_sub(used))
+        });
+        SessionSummary {
+            session_id: session.plan.session_id.clone(),
+            entry_id: entry_id.clone(),
+            label: session.plan.label.clone(),
+            reason: reason.clone(),
+            duration,
+            quota_used,
+            remaining_today,
+            warnings_shown: session.warnings_issued.clone(),
+            next_available: entry.and_then(|entry| self.next_available(entry, &session.plan.seat, now)),
+            show_for: self.policy.service.session_summary,
+        }
+    }
 
-        let duration = session.duration_so_far(now_mono);
-        let reason = if session.state == shepherd_api::SessionState::Expiring {
-            SessionEndReason::Expired
-        } else {
-            SessionEndReason::ProcessExited { exit_code }
+    /// When an entry that can't be launched at `now` next can be: once
+    /// every reason it can't has passed. None if it can be launched now, or
+    /// a reason has no known end.
+    fn next_available(&self, entry: &Entry, seat: &SeatId, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        let view = self.evaluate_entry(entry, seat, now);
+        if view.enabled {
+            return None;
+        }
+        let ends = view
+            .reasons
+            .iter()
+            .map(|reason| match reason {
+                ReasonCode::CooldownActive { available_at, .. } => Some(*available_at),
+                ReasonCode::OutsideTimeWindow { next_window_start } => *next_window_start,
+                ReasonCode::Downtime { until, .. } => Some(*until),
+                ReasonCode::OnHold { until, .. } => *until,
+                ReasonCode::NotEnoughTime { next_full_session, .. } => *next_full_session,
+                ReasonCode::QuotaExhausted { .. } => {
+                    let min_run = entry.limits.min_run.unwrap_or(Duration::from_secs(60));
+                    self.next_full_session(entry, min_run, now)
+                }
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        ends.into_iter().max()
+    }
+
+    /// Whether a session of `duration`, already charged, starts a cooldown:
+    /// always, or when it takes today's use across the cooldown's scope past
+    /// another multiple of `cooldown_after`
+    fn cooldown_due(
+        &self,
+        limits: &LimitsPolicy,
+        entry_id: &EntryId,
+        duration: Duration,
+        now: DateTime<Local>,
+    ) -> bool {
+        let Some(after) = limits.cooldown_after.filter(|a| a.as_secs() > 0) else {
+            return true;
         };
 
-        // Update usage accounting
         let today = now.date_naive();
-        let _ = self.store.add_usage(&session.plan.entry_id, today, duration);
+        let usage = |id: &EntryId| {
+            self.lookups
+                .usage(self.store.as_ref(), id, today)
+                .unwrap_or_default()
+        };
+        let used: Duration = match &limits.cooldown_scope {
+            CooldownScope::Entry => usage(entry_id),
+            CooldownScope::Tag { tag } => self
+                .policy
+                .entries
+                .iter()
+                .filter(|e| e.has_tag(tag))
+                .map(|e| usage(&e.id))
+                .sum(),
+            CooldownScope::Global => self.policy.entries.iter().map(|e| usage(&e.id)).sum(),
+        };
+        let before = used.saturating_sub(duration);
+        used.as_secs() / after.as_secs() > before.as_secs() / after.as_secs()
+    }
 
-        // Set cooldown if configured
-        if let Some(entry) = self.policy.get_entry(&session.plan.entry_id)
-            && let Some(cooldown) = entry.limits.cooldown {
-                let until = now + chrono::Duration::from_std(cooldown).unwrap();
-                let _ = self.store.set_cooldown_until(&session.plan.entry_id, until);
-            }
+    /// Charge a joint session's time to each participant's profile
+    fn charge_participants(&self, plan: &SessionPlan, duration: Duration, day: NaiveDate) {
+        if plan.participants.is_empty() {
+            return;
+        }
 
-        // Log to audit
-        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::SessionEnded {
-            session_id: session.plan.session_id.clone(),
-            entry_id: session.plan.entry_id.clone(),
-            reason: reason.clone(),
-            duration,
-        }));
+        let charge = match self.policy.service.joint_usage {
+            JointUsage::Duplicate => duration,
+            JointUsage::Split => duration / plan.participants.len() as u32,
+        };
+        for profile_id in &plan.participants {
+            let _ = self
+                .store
+                .add_profile_usage(profile_id, &plan.entry_id, day, charge);
+        }
+    }
+
+    /// Credit the time bank for a session in an entry tagged to earn time
+    fn credit_time_bank(&self, entry_id: &EntryId, spent: Duration, now: DateTime<Local>) {
+        let Some(exchange) = &self.policy.time_exchange else {
+            return;
+        };
+        if !self
+            .policy
+            .get_entry(entry_id)
+            .is_some_and(|e| e.has_tag(&exchange.earn_tag))
+        {
+            return;
+        }
+
+        let today = now.date_naive();
+        let mut credit = exchange.credit_for(spent);
+        if let Some(max) = exchange.max_earned {
+            let already = self
+                .lookups
+                .time_bank(self.store.as_ref(), &exchange.spend_tag, today)
+                .unwrap_or_default();
+            credit = credit.min(max.saturating_sub(already));
+        }
+        if credit.is_zero() {
+            return;
+        }
 
+        if let Err(e) = self
+            .lookups
+            .add_time_bank(self.store.as_ref(), &exchange.spend_tag, today, credit)
+        {
+            warn!(error = %e, "Failed to credit time bank");
+            return;
+        }
         info!(
-            session_id = %session.plan.session_id,
-            entry_id = %session.plan.entry_id,
-            duration_secs = duration.as_secs(),
-            reason = ?reason,
-            "Session ended"
+            entry_id = %entry_id,
+            tag = %exchange.spend_tag,
+            credited_secs = credit.as_secs(),
+            "Time bank credited"
         );
+    }
 
-        Some(CoreEvent::SessionEnded {
-            session_id: session.plan.session_id,
-            entry_id: session.plan.entry_id,
-            reason,
-            duration,
-        })
+    /// Total reward stars earned
+    pub fn total_stars(&self) -> u32 {
+        self.lookups.total_stars(self.store.as_ref()).unwrap_or(0)
     }
 
-    /// Stop the current session
-    pub fn stop_current(
+    /// Grant reward stars (admin action). Returns the new total.
+    pub fn grant_reward(
         &mut self,
-        reason: SessionEndReason,
-        now_mono: MonotonicInstant,
+        stars: u32,
+        reason: Option<String>,
         now: DateTime<Local>,
-    ) -> StopDecision {
-        let session = match self.current_session.take() {
-            Some(s) => s,
-            None => return StopDecision::NoActiveSession,
+    ) -> u32 {
+        self.invalidate_entries();
+        let grant = RewardGrant {
+            stars,
+            reason: reason.clone(),
+            timestamp: now,
         };
+        if let Err(e) = self.store.add_reward(&grant) {
+            warn!(error = %e, "Failed to record reward");
+        }
+        self.lookups.rewards_changed();
 
-        let duration = session.duration_so_far(now_mono);
+        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::RewardGranted {
+            stars,
+            reason,
+        }));
 
-        // Update usage accounting
-        let today = now.date_naive();
-        let _ = self.store.add_usage(&session.plan.entry_id, today, duration);
+        let total = self.total_stars();
+        info!(stars, total_stars = total, "Reward granted");
+        total
+    }
 
-        // Set cooldown if configured
-        if let Some(entry) = self.policy.get_entry(&session.plan.entry_id)
-            && let Some(cooldown) = entry.limits.cooldown {
-                let until = now + chrono::Duration::from_std(cooldown).unwrap();
-                let _ = self.store.set_cooldown_until(&session.plan.entry_id, until);
-            }
+    /// Add usage history from another parental control (admin action). It is
+    /// kept as the totals of a device named after `source`, like usage
+    /// shared by a federated device, so importing the same export again
+    /// changes nothing. Returns how many daily totals changed.
+    pub fn import_usage(
+        &mut self,
+        source: &str,
+        profile_id: Option<&ProfileId>,
+        usage: &[ImportedUsage],
+    ) -> StoreResult<usize> {
+        let mut totals: HashMap<(EntryId, NaiveDate), Duration> = HashMap::new();
+        for item in usage {
+            *totals.entry((item.entry_id.clone(), item.day)).or_default() += item.duration;
+        }
 
-        // Log to audit
-        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::SessionEnded {
-            session_id: session.plan.session_id.clone(),
-            entry_id: session.plan.entry_id.clone(),
-            reason: reason.clone(),
-            duration,
+        let device = format!("import:{}", source);
+        let records: Vec<UsageRecord> = totals
+            .iter()
+            .flat_map(|((entry_id, day), duration)| {
+                let record = |profile_id: Option<&ProfileId>| UsageRecord {
+                    device: device.clone(),
+                    profile_id: profile_id.cloned(),
+                    entry_id: entry_id.clone(),
+                    day: *day,
+                    duration_secs: duration.as_secs(),
+                };
+                std::iter::once(record(None)).chain(profile_id.map(|p| record(Some(p))))
+            })
+            .collect();
+
+        let local = self
+            .policy
+            .service
+            .federation
+            .as_ref()
+            .map_or("", |federation| federation.device.as_str());
+        let changed = self.store.merge_usage_records(local, &records)?;
+        self.reload_store();
+
+        let total = totals.values().sum();
+        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::UsageImported {
+            source: source.to_string(),
+            changed,
+            total,
         }));
+        info!(source, changed, total_secs = total.as_secs(), "Usage imported");
+        Ok(changed)
+    }
 
-        info!(
-            session_id = %session.plan.session_id,
-            reason = ?reason,
-            "Session stopped"
-        );
+    /// Request to launch an entry on the default seat
+    pub fn request_launch(
+        &mut self,
+        entry_id: &EntryId,
+        now: DateTime<Local>,
+    ) -> LaunchDecision {
+        self.request_launch_on(&SeatId::default(), entry_id, now)
+    }
 
-        StopDecision::Stopped(StopResult {
-            session_id: session.plan.session_id,
-            entry_id: session.plan.entry_id,
-            reason,
-            duration,
+    /// Request to launch an entry on `seat`
+    pub fn request_launch_on(
+        &mut self,
+        seat: &SeatId,
+        entry_id: &EntryId,
+        now: DateTime<Local>,
+    ) -> LaunchDecision {
+        // Find entry
+        let entry = match self.policy.get_entry(entry_id) {
+            Some(e) => e,
+            None => {
+                return LaunchDecision::Denied {
+                    reasons: vec![ReasonCode::Disabled {
+                        reason: Some("Entry not found".into()),
+                    }],
+                };
+            }
+        };
+
+        // Evaluate availability
+        let view = self.evaluate_entry(entry, seat, now);
+
+        if !view.enabled {
+            return self.reject_launch(entry_id, view.reasons);
+        }
+
+        // Compute session plan
+        let max_duration = view.max_run_if_started_now;
+        let plan = SessionPlan {
+            session_id: SessionId::new(),
+            entry_id: entry_id.clone(),
+            label: entry.label.clone(),
+            max_duration,
+            warnings: entry.warnings.clone(),
+            device_access: entry.device_access,
+            participants: Vec::new(),
+            seat: seat.clone(),
+        };
+
+        if entry.confirm_launch {
+            debug!(
+                entry_id = %entry_id,
+                session_id = %plan.session_id,
+                "Launch awaiting confirmation"
+            );
+            self.pending_launch.insert(
+                seat.clone(),
+                PendingLaunch {
+                    plan: plan.clone(),
+                    requested_at: now,
+                },
+            );
+            return LaunchDecision::NeedsConfirmation(plan);
+        }
+
+        if let Some(max_dur) = max_duration {
+            debug!(
+                entry_id = %entry_id,
+                max_duration_secs = max_dur.as_secs(),
+                "Launch approved"
+            );
+        } else {
+            debug!(
+                entry_id = %entry_id,
+                "Launch approved (unlimited)"
+            );
+        }
+
+        LaunchDecision::Approved(plan)
+    }
+
+    /// Approve a launch held by [`LaunchDecision::NeedsConfirmation`].
+    ///
+    /// Returns None if no launch with that session ID is waiting, or it
+    /// waited longer than [`LAUNCH_CONFIRM_TIMEOUT`]. The entry is checked
+    /// again, and the session never gets more time than was shown.
+    pub fn confirm_launch(
+        &mut self,
+        session_id: &SessionId,
+        now: DateTime<Local>,
+    ) -> Option<LaunchDecision> {
+        let seat = self
+            .pending_launch
+            .iter()
+            .find_map(|(seat, p)| (p.plan.session_id == *session_id).then(|| seat.clone()))?;
+        let pending = self.pending_launch.remove(&seat)?;
+        let waited = (now - pending.requested_at).to_std().unwrap_or(Duration::ZERO);
+        if waited > LAUNCH_CONFIRM_TIMEOUT {
+            debug!(session_id = %session_id, "Launch confirmation expired");
+            return None;
+        }
+
+        let mut plan = pending.plan;
+        let Some(entry) = self.policy.get_entry(&plan.entry_id) else {
+            return Some(LaunchDecision::Denied {
+                reasons: vec![ReasonCode::Disabled {
+                    reason: Some("Entry not found".into()),
+                }],
+            });
+        };
+        let view = self.evaluate_entry(entry, &seat, now);
+        if !view.enabled {
+            return Some(self.reject_launch(&plan.entry_id, view.reasons));
+        }
+
+        plan.max_duration = match (plan.max_duration, view.max_run_if_started_now) {
+            (Some(shown), Some(now_max)) => Some(shown.min(now_max)),
+            (shown, now_max) => shown.or(now_max),
+        };
+        info!(entry_id = %plan.entry_id, session_id = %session_id, "Launch confirmed");
+        Some(LaunchDecision::Approved(plan))
+    }
+
+    /// Record the latest health probe result for an entry.
+    /// Returns an availability event if the entry became broken or recovered.
+    pub fn set_entry_health(
+        &mut self,
+        entry_id: &EntryId,
+        problem: Option<String>,
+        now: DateTime<Local>,
+    ) -> Option<CoreEvent> {
+        let previous = match &problem {
+            Some(problem) => self.entry_health.insert(entry_id.clone(), problem.clone()),
+            None => self.entry_health.remove(entry_id),
+        };
+        if previous == problem {
+            return None;
+        }
+        self.invalidate_entries();
+
+        match &problem {
+            Some(problem) => warn!(entry_id = %entry_id, problem = %problem, "Entry failed health check"),
+            None => info!(entry_id = %entry_id, "Entry passed health check"),
+        }
+
+        let entry = self.policy.get_entry(entry_id)?;
+        Some(CoreEvent::EntryAvailabilityChanged {
+            entry_id: entry_id.clone(),
+            enabled: self.evaluate_entry(entry, &SeatId::default(), now).enabled,
         })
     }
 
-    /// Get current service state snapshot
-    pub fn get_state(&self) -> ServiceStateSnapshot {
-        let current_session = self.current_session.as_ref().map(|s| {
-            s.to_session_info(MonotonicInstant::now())
-        });
+    /// Pick the next entry due for a maintenance update.
+    ///
+    /// Updates only run between sessions. Once due, an update waits for the
+    /// entry to be outside its availability windows, up to `max_defer`, so it
+    /// neither interrupts play nor gets skipped forever. Entries never updated
+    /// are updated at the first chance.
+    pub fn next_update(&self, now: DateTime<Local>) -> Option<EntryId> {
+        let maintenance = self.policy.maintenance.as_ref()?;
+        if !self.sessions.is_empty() || self.updating.is_some() {
+            return None;
+        }
 
-        // Build entry views for the snapshot
-        let entries = self.list_entries(shepherd_util::now());
+        maintenance
+            .entries
+            .iter()
+            .find(|entry_id| {
+                let Some(entry) = self.policy.get_entry(entry_id) else {
+                    return false;
+                };
+                let since_last = self
+                    .store
+                    .get_last_update(entry_id)
+                    .ok()
+                    .flatten()
+                    .map(|last| (now - last).to_std().unwrap_or(Duration::ZERO));
 
-        ServiceStateSnapshot {
-            api_version: API_VERSION,
-            policy_loaded: true,
-            current_session,
-            entry_count: self.policy.entries.len(),
-            entries,
+                match since_last {
+                    Some(since) if since < maintenance.interval => false,
+                    Some(since) if since < maintenance.interval + maintenance.max_defer => {
+                        !entry.availability.is_available(&self.policy.service.timezone, &now)
+                    }
+                    _ => true,
+                }
+            })
+            .cloned()
+    }
+
+    /// Mark an entry as updating; it can't be launched until the update finishes
+    pub fn begin_update(&mut self, entry_id: &EntryId) {
+        self.invalidate_entries();
+        info!(entry_id = %entry_id, "Starting app update");
+        self.updating = Some(entry_id.clone());
+    }
+
+    /// Record the outcome of a maintenance update
+    pub fn finish_update(
+        &mut self,
+        entry_id: &EntryId,
+        result: Result<(), String>,
+        now: DateTime<Local>,
+    ) {
+        self.invalidate_entries();
+        self.updating.take_if(|id| id == entry_id);
+
+        // Failed attempts count too, so a broken update isn't retried every tick
+        if let Err(e) = self.store.set_last_update(entry_id, now) {
+            warn!(entry_id = %entry_id, error = %e, "Failed to record update");
+        }
+
+        match &result {
+            Ok(()) => info!(entry_id = %entry_id, "App updated"),
+            Err(e) => warn!(entry_id = %entry_id, error = %e, "App update failed"),
         }
+
+        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::EntryUpdated {
+            entry_id: entry_id.clone(),
+            success: result.is_ok(),
+            error: result.err(),
+        }));
     }
 
-    /// Get current session reference
-    pub fn current_session(&self) -> Option<&ActiveSession> {
-        self.current_session.as_ref()
+    /// Deny a launch and record why, e.g. after a failed host pre-check
+    pub fn reject_launch(&self, entry_id: &EntryId, reasons: Vec<ReasonCode>) -> LaunchDecision {
+        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::LaunchDenied {
+            entry_id: entry_id.clone(),
+            reasons: reasons.iter().map(|r| format!("{:?}", r)).collect(),
+        }));
+
+        LaunchDecision::Denied { reasons }
+    }
+
+    /// Request a joint session on `seat` attributed to several profiles
+    pub fn request_joint_launch(
+        &mut self,
+        seat: &SeatId,
+        entry_id: &EntryId,
+        participants: Vec<ProfileId>,
+        now: DateTime<Local>,
+    ) -> LaunchDecision {
+        if let Some(unknown) = participants
+            .iter()
+            .find(|p| self.policy.get_profile(p).is_none())
+        {
+            return LaunchDecision::Denied {
+                reasons: vec![ReasonCode::UnknownProfile {
+                    profile_id: unknown.clone(),
+                }],
+            };
+        }
+
+        match self.request_launch_on(seat, entry_id, now) {
+            LaunchDecision::Approved(mut plan) => {
+                debug!(entry_id = %entry_id, participants = ?participants, "Joint launch approved");
+                plan.participants = participants;
+                LaunchDecision::Approved(plan)
+            }
+            LaunchDecision::NeedsConfirmation(mut plan) => {
+                if let Some(pending) = self.pending_launch.get_mut(seat) {
+                    pending.plan.participants = participants.clone();
+                }
+                plan.participants = participants;
+                LaunchDecision::NeedsConfirmation(plan)
+            }
+            denied => denied,
+        }
     }
 
-    /// Get mutable current session reference
-    pub fn current_session_mut(&mut self) -> Option<&mut ActiveSession> {
-        self.current_session.as_mut()
-    }
+    /// Start a session from an approved plan
+    pub fn start_session(
+        &mut self,
+        plan: SessionPlan,
+        now: DateTime<Local>,
+        now_mono: MonotonicInstant,
+    ) -> CoreEvent {
+        self.invalidate_entries();
+        let session = ActiveSession::new(plan.clone(), now, now_mono);
+
+        let event = CoreEvent::SessionStarted {
+            session_id: session.plan.session_id.clone(),
+            entry_id: session.plan.entry_id.clone(),
+            label: session.plan.label.clone(),
+            deadline: session.deadline,
+            device_access: session.plan.device_access,
+            seat: session.plan.seat.clone(),
+        };
+
+        // Log to audit
+        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::SessionStarted {
+            session_id: session.plan.session_id.clone(),
+            entry_id: session.plan.entry_id.clone(),
+            label: session.plan.label.clone(),
+            deadline: session.deadline,
+        }));
+
+        if let Some(deadline) = session.deadline {
+            info!(
+                session_id = %session.plan.session_id,
+                entry_id = %session.plan.entry_id,
+                seat = %session.plan.seat,
+                deadline = %deadline,
+                "Session started"
+            );
+        } else {
+            info!(
+                session_id = %session.plan.session_id,
+                entry_id = %session.plan.entry_id,
+                seat = %session.plan.seat,
+                "Session started (unlimited)"
+            );
+        }
+
+        let seat = session.plan.seat.clone();
+        self.pending_check_in.remove(&seat);
+        if let Some(replaced) = self.sessions.insert(seat, session) {
+            warn!(session_id = %replaced.plan.session_id, "Session replaced without ending");
+        }
+
+        event
+    }
+
+    /// Attach a host handle to the session it belongs to
+    pub fn attach_host_handle(&mut self, handle: HostSessionHandle) {
+        if let Some(session) = self
+            .sessions
+            .values_mut()
+            .find(|s| s.plan.session_id == handle.session_id)
+        {
+            session.attach_handle(handle);
+        }
+    }
+
+    /// Tick the engine - check for warnings, expiry, and availability changes
+    pub fn tick(&mut self, now_mono: MonotonicInstant, now: DateTime<Local>) -> Vec<CoreEvent> {
+        let mut events = Vec::new();
+
+        self.lookups.roll_over(now.date_naive());
+        events.extend(self.check_availability(now));
+
+        // Check if the scheduled display settings have changed
+        if !self.policy.display.is_empty() {
+            let settings = self.policy.display.settings_at(&self.policy.service.timezone, &now);
+            if self.last_display_settings != Some(settings) {
+                debug!(
+                    previous = ?self.last_display_settings,
+                    current = ?settings,
+                    "Display settings changed"
+                );
+                self.last_display_settings = Some(settings);
+                events.push(CoreEvent::DisplaySettingsChanged(settings));
+            }
+        }
+
+        // Check if quiet hours started or ended
+        let quiet_hours = self.policy.volume.quiet_hours_at(&self.policy.service.timezone, &now);
+        if quiet_hours != self.quiet_hours {
+            debug!(previous = ?self.quiet_hours, current = ?quiet_hours, "Quiet hours changed");
+            self.quiet_hours = quiet_hours;
+            events.push(CoreEvent::QuietHoursChanged(quiet_hours));
+        }
+
+        self.audit_calendar_starts(now);
+        events.extend(self.tick_curfew(now_mono, now));
+        events.extend(self.due_autostarts(now));
+
+        for session in self.sessions.values_mut() {
+            // Check for pending warnings
+            for (threshold, remaining) in session.pending_warnings(now_mono) {
+                let severity = session
+                    .plan
+                    .warnings
+                    .iter()
+                    .find(|w| w.seconds_before == threshold)
+                    .map(|w| w.severity)
+                    .unwrap_or(WarningSeverity::Warn);
+
+                let message = session
+                    .plan
+                    .warnings
+                    .iter()
+                    .find(|w| w.seconds_before == threshold)
+                    .and_then(|w| w.message_template.clone());
+
+                session.mark_warning_issued(threshold);
+
+                // Log to audit
+                let _ = self.store.append_audit(AuditEvent::new(AuditEventType::WarningIssued {
+                    session_id: session.plan.session_id.clone(),
+                    threshold_seconds: threshold,
+                }));
+
+                info!(
+                    session_id = %session.plan.session_id,
+                    threshold_seconds = threshold,
+                    remaining_secs = remaining.as_secs(),
+                    "Warning issued"
+                );
+
+                events.push(CoreEvent::Warning {
+                    session_id: session.plan.session_id.clone(),
+                    threshold_seconds: threshold,
+                    time_remaining: remaining,
+                    severity,
+                    message,
+                });
+            }
+
+            if next_time_sync(session, now_mono).is_some_and(|at| at <= now_mono) {
+                events.extend(time_remaining_event(session, now_mono));
+            }
+
+            // Check for expiry
+            if session.is_expired(now_mono)
+                && session.state != shepherd_api::SessionState::Expiring
+                && session.state != shepherd_api::SessionState::Ended
+            {
+                session.mark_expiring(now_mono);
+
+                info!(
+                    session_id = %session.plan.session_id,
+                    "Session expiring"
+                );
+
+                events.push(CoreEvent::ExpireDue {
+                    session_id: session.plan.session_id.clone(),
+                });
+            } else if session.state == shepherd_api::SessionState::Expiring
+                && session.enforcement_attempts <= MAX_ENFORCEMENT_ATTEMPTS
+                && session
+                    .last_enforcement
+                    .is_none_or(|last| now_mono.duration_since(last) >= ENFORCEMENT_RETRY_INTERVAL)
+            {
+                // Still running after the last stop; escalate
+                session.enforcement_attempts += 1;
+                session.last_enforcement = Some(now_mono);
+                let attempt = session.enforcement_attempts;
+
+                if attempt > MAX_ENFORCEMENT_ATTEMPTS {
+                    error!(
+                        session_id = %session.plan.session_id,
+                        entry_id = %session.plan.entry_id,
+                        "Expired session could not be stopped"
+                    );
+                    let _ = self.store.append_audit(AuditEvent::new(AuditEventType::EnforcementFailed {
+                        session_id: session.plan.session_id.clone(),
+                        entry_id: session.plan.entry_id.clone(),
+                        attempts: MAX_ENFORCEMENT_ATTEMPTS,
+                    }));
+                    events.push(CoreEvent::EnforcementFailed {
+                        session_id: session.plan.session_id.clone(),
+                        entry_id: session.plan.entry_id.clone(),
+                    });
+                } else {
+                    let step = if attempt == 1 {
+                        EnforcementStep::Force
+                    } else {
+                        EnforcementStep::KillCgroup
+                    };
+                    warn!(
+                        session_id = %session.plan.session_id,
+                        attempt,
+                        step = ?step,
+                        "Expired session still running, escalating"
+                    );
+                    events.push(CoreEvent::EnforcementRetry {
+                        session_id: session.plan.session_id.clone(),
+                        attempt,
+                        step,
+                    });
+                }
+            }
+        }
+
+        events
+    }
+
+    /// How long until the engine next needs a tick: the next warning,
+    /// expiry, enforcement retry, power action or end of a cooldown, or else
+    /// the start of the next wall-clock minute. Windows, schedules and
+    /// autostarts are set to the minute, so ticking on each minute catches
+    /// their boundaries.
+    pub fn next_tick(&self, now_mono: MonotonicInstant, now: DateTime<Local>) -> Duration {
+        let into_minute = Duration::new(u64::from(now.second()), now.nanosecond().min(999_999_999));
+        let mut deadlines = vec![Duration::from_secs(60).saturating_sub(into_minute)];
+
+        let until = |deadline: MonotonicInstant| deadline.saturating_duration_until(now_mono);
+        for session in self.sessions.values() {
+            match session.state {
+                shepherd_api::SessionState::Expiring => {
+                    if session.enforcement_attempts <= MAX_ENFORCEMENT_ATTEMPTS {
+                        let last = session.last_enforcement.unwrap_or(now_mono);
+                        deadlines.push(until(last + ENFORCEMENT_RETRY_INTERVAL));
+                    }
+                }
+                shepherd_api::SessionState::Ended => {}
+                _ => {
+                    deadlines.extend(session.deadline_mono().map(until));
+                    deadlines.extend(next_time_sync(session, now_mono).map(until));
+                    deadlines.extend(session.upcoming_warnings().into_iter().map(|(_, at)| until(at)));
+                }
+            }
+        }
+
+        if let Some(countdown) = &self.power_countdown {
+            deadlines.push(countdown.deadline_mono.saturating_duration_until(now_mono));
+        }
+
+        // Cooldowns end at any second; take them from the last tick's views
+        if let Some(cache) = self.entry_cache.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            deadlines.extend(
+                cache
+                    .entries
+                    .iter()
+                    .flat_map(|view| &view.reasons)
+                    .filter_map(|reason| match reason {
+                        ReasonCode::CooldownActive { available_at, .. } => {
+                            (*available_at - now).to_std().ok()
+                        }
+                        _ => None,
+                    }),
+            );
+        }
+
+        deadlines.into_iter().min().unwrap_or(Duration::ZERO)
+    }
+
+    /// Notify that the default seat's session has exited
+    pub fn notify_session_exited(
+        &mut self,
+        exit_code: Option<i32>,
+        now_mono: MonotonicInstant,
+        now: DateTime<Local>,
+    ) -> Option<CoreEvent> {
+        self.notify_exited_on(&SeatId::default(), exit_code, now_mono, now)
+    }
+
+    /// Notify that the session on `seat` has exited
+    pub fn notify_exited_on(
+        &mut self,
+        seat: &SeatId,
+        exit_code: Option<i32>,
+        now_mono: MonotonicInstant,
+        now: DateTime<Local>,
+    ) -> Option<CoreEvent> {
+        self.invalidate_entries();
+        let session = self.sessions.remove(seat)?;
+
+        let duration = session.charged(now_mono);
+        let reason = if session.state == shepherd_api::SessionState::Expiring {
+            SessionEndReason::Expired
+        } else {
+            SessionEndReason::ProcessExited { exit_code }
+        };
+
+        // Log to audit
+        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::SessionEnded {
+            session_id: session.plan.session_id.clone(),
+            entry_id: session.plan.entry_id.clone(),
+            reason: reason.clone(),
+            duration,
+        }));
+
+        let quota_used = self.record_usage(&session, &reason, now_mono, now);
+        let summary = Box::new(self.summarize(&session, &reason, duration, quota_used, now));
+
+        info!(
+            session_id = %session.plan.session_id,
+            entry_id = %session.plan.entry_id,
+            duration_secs = duration.as_secs(),
+            reason = ?reason,
+            "Session ended"
+        );
+
+        self.queue_check_in(&session, &reason);
+
+        Some(CoreEvent::SessionEnded {
+            session_id: session.plan.session_id,
+            entry_id: session.plan.entry_id,
+            seat: session.plan.seat,
+            reason,
+            duration,
+            summary,
+        })
+    }
+
+    /// Stop the default seat's session
+    pub fn stop_current(
+        &mut self,
+        reason: SessionEndReason,
+        now_mono: MonotonicInstant,
+        now: DateTime<Local>,
+    ) -> StopDecision {
+        self.stop_on(&SeatId::default(), reason, now_mono, now)
+    }
+
+    /// Stop the session on `seat`
+    pub fn stop_on(
+        &mut self,
+        seat: &SeatId,
+        reason: SessionEndReason,
+        now_mono: MonotonicInstant,
+        now: DateTime<Local>,
+    ) -> StopDecision {
+        self.invalidate_entries();
+        let session = match self.sessions.remove(seat) {
+            Some(s) => s,
+            None => return StopDecision::NoActiveSession,
+        };
+
+        let duration = session.charged(now_mono);
+
+        // Log to audit
+        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::SessionEnded {
+            session_id: session.plan.session_id.clone(),
+            entry_id: session.plan.entry_id.clone(),
+            reason: reason.clone(),
+            duration,
+        }));
+
+        let quota_used = self.record_usage(&session, &reason, now_mono, now);
+        let summary = Box::new(self.summarize(&session, &reason, duration, quota_used, now));
+
+        info!(
+            session_id = %session.plan.session_id,
+            reason = ?reason,
+            "Session stopped"
+        );
+
+        self.queue_check_in(&session, &reason);
+
+        StopDecision::Stopped(StopResult {
+            session_id: session.plan.session_id,
+            entry_id: session.plan.entry_id,
+            seat: session.plan.seat,
+            reason,
+            duration,
+            summary,
+        })
+    }
+
+    /// Get the default seat's service state snapshot
+    pub fn get_state(&self) -> ServiceStateSnapshot {
+        self.get_state_on(&SeatId::default())
+    }
+
+    /// Get the service state snapshot as seen from `seat`
+    pub fn get_state_on(&self, seat: &SeatId) -> ServiceStateSnapshot {
+        let current_session = self.sessions.get(seat).map(|s| {
+            s.to_session_info(MonotonicInstant::now())
+        });
+
+        // Build entry views for the snapshot
+        let entries = self.entries_on(seat);
+
+        ServiceStateSnapshot {
+            api_version: API_VERSION,
+            policy_loaded: self.policy_loaded,
+            seat: seat.clone(),
+            current_session,
+            entry_count: self.policy.entries.len(),
+            entries,
+            pending_power_action: self.power_countdown.map(|c| c.pending),
+            pending_check_in: self.pending_check_in.get(seat).cloned(),
+            total_stars: self.total_stars(),
+            hold: self.active_hold(shepherd_util::now()).cloned(),
+            theme: self.policy.service.theme,
+        }
+    }
+
+    /// The launch hold, unless its `until` has passed
+    pub fn active_hold(&self, now: DateTime<Local>) -> Option<&Hold> {
+        self.hold
+            .as_ref()
+            .filter(|hold| hold.until.is_none_or(|until| now < until))
+    }
+
+    /// Put all launches on hold, or lift the hold with None. A running
+    /// session is left alone.
+    pub fn set_hold(&mut self, hold: Option<Hold>) {
+        self.invalidate_entries();
+        if let Err(e) = self.store.set_hold(hold.as_ref()) {
+            warn!(error = %e, "Failed to save launch hold");
+        }
+        match &hold {
+            Some(h) => info!(message = ?h.message, until = ?h.until, "Launches on hold"),
+            None => info!("Launch hold lifted"),
+        }
+        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::HoldChanged {
+            hold: hold.clone(),
+        }));
+        self.hold = hold;
+    }
+
+    /// Replace the family calendar exceptions with a fresh copy of the feed.
+    /// Takes effect on the next tick; a running session keeps its deadline.
+    pub fn set_calendar(&mut self, exceptions: Vec<CalendarException>) {
+        self.invalidate_entries();
+        if exceptions == self.calendar {
+            return;
+        }
+        info!(count = exceptions.len(), "Calendar exceptions updated");
+        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::CalendarUpdated {
+            exception_count: exceptions.len(),
+        }));
+        self.calendar_started
+            .retain(|(uid, action)| exceptions.iter().any(|e| &e.uid == uid && e.action == *action));
+        self.calendar = exceptions;
+    }
+
+    /// Record the kiosk escape routes found by the host, auditing any
+    pub fn set_escape_routes(&mut self, routes: Vec<EscapeRoute>) {
+        if !routes.is_empty() {
+            let _ = self.store.append_audit(AuditEvent::new(AuditEventType::EscapeRoutesFound {
+                routes: routes.clone(),
+            }));
+        }
+        self.escape_routes = routes;
+    }
+
+    /// Kiosk escape routes found by the host at startup
+    pub fn escape_routes(&self) -> &[EscapeRoute] {
+        &self.escape_routes
+    }
+
+    /// Record each calendar event once, when it starts to apply
+    fn audit_calendar_starts(&mut self, now: DateTime<Local>) {
+        for exception in self.calendar.iter().filter(|e| e.is_active(now)) {
+            if !self
+                .calendar_started
+                .insert((exception.uid.clone(), exception.action))
+            {
+                continue;
+            }
+            info!(
+                label = %exception.label,
+                action = %exception.action,
+                targets = ?exception.targets,
+                "Calendar exception started"
+            );
+            let _ = self
+                .store
+                .append_audit(AuditEvent::new(AuditEventType::CalendarExceptionStarted {
+                    label: exception.label.clone(),
+                    action: exception.action.to_string(),
+                    targets: exception.targets.clone(),
+                    start: exception.start,
+                    end: exception.end,
+                }));
+        }
+    }
+
+    /// Ask for a feelings check-in about an ended session, if enabled
+    fn queue_check_in(&mut self, session: &ActiveSession, reason: &SessionEndReason) {
+        if !self.policy.service.session_check_in
+            || matches!(reason, SessionEndReason::LaunchFailed { .. })
+        {
+            return;
+        }
+
+        self.pending_check_in.insert(
+            session.plan.seat.clone(),
+            CheckInRequest {
+                session_id: session.plan.session_id.clone(),
+                entry_id: session.plan.entry_id.clone(),
+                label: session.plan.label.clone(),
+            },
+        );
+    }
+
+    /// Answer the pending feelings check-in, or skip it with `None`.
+    /// Returns false if there is no pending check-in for this session.
+    pub fn submit_check_in(
+        &mut self,
+        session_id: &SessionId,
+        mood: Option<Mood>,
+        now: DateTime<Local>,
+    ) -> bool {
+        let Some(seat) = self
+            .pending_check_in
+            .iter()
+            .find_map(|(seat, pending)| (&pending.session_id == session_id).then(|| seat.clone()))
+        else {
+            return false;
+        };
+        let Some(pending) = self.pending_check_in.remove(&seat) else {
+            return false;
+        };
+
+        if let Some(mood) = mood {
+            let check_in = SessionCheckIn {
+                session_id: pending.session_id,
+                entry_id: pending.entry_id,
+                mood,
+                timestamp: now,
+            };
+            if let Err(e) = self.store.record_check_in(&check_in) {
+                warn!(error = %e, "Failed to record check-in");
+            }
+        } else {
+            debug!(session_id = %session_id, "Check-in skipped");
+        }
+
+        true
+    }
+
+    /// Record a battery reading from the host.
+    /// Emits a status event when it changes, and a stop request when the
+    /// battery falls below the configured stop threshold during a session.
+    pub fn update_battery(&mut self, battery: BatteryInfo) -> Vec<CoreEvent> {
+        self.invalidate_entries();
+        let mut events = Vec::new();
+
+        if self.battery != Some(battery) {
+            debug!(battery = ?battery, "Battery status changed");
+            self.battery = Some(battery);
+            events.push(CoreEvent::BatteryStatus {
+                battery,
+                low: self.is_battery_low(&battery),
+            });
+        }
+
+        if let Some(stop_below) = self.policy.battery.stop_below
+            && battery.is_below(stop_below)
+        {
+            for session in self.sessions.values() {
+                info!(
+                    session_id = %session.plan.session_id,
+                    percent = ?battery.percent,
+                    stop_below,
+                    "Battery too low, ending session"
+                );
+                events.push(CoreEvent::LowBatteryStop {
+                    session_id: session.plan.session_id.clone(),
+                });
+            }
+        }
+
+        events
+    }
+
+    /// Quiet-hours limits in effect, on top of the volume policy
+    pub fn quiet_hours(&self) -> QuietHours {
+        self.quiet_hours
+    }
+
+    /// Record where sound is going. Entries that need headphones can't be
+    /// launched while it's the speakers.
+    pub fn set_audio_output(&mut self, output: AudioOutput) {
+        self.invalidate_entries();
+        if output != self.audio_output {
+            debug!(previous = ?self.audio_output, current = ?output, "Audio output changed");
+            self.audio_output = output;
+        }
+    }
+
+    /// Whether `entry` may only play through headphones at `now`
+    fn requires_headphones(&self, entry: &Entry, now: &DateTime<Local>) -> bool {
+        let volume = &self.policy.volume;
+        volume.require_headphones
+            || entry.volume.as_ref().is_some_and(|v| v.require_headphones)
+            || volume
+                .quiet_hours_at(&self.policy.service.timezone, now)
+                .require_headphones
+    }
+
+    /// Latest battery reading, if the host has a battery
+    pub fn battery(&self) -> Option<BatteryInfo> {
+        self.battery
+    }
+
+    /// Check if a battery reading is below the warning threshold
+    pub fn is_battery_low(&self, battery: &BatteryInfo) -> bool {
+        battery.is_below(self.policy.battery.warn_below)
+    }
+
+    /// Cancel the curfew countdown until the next session or curfew period.
+    /// Returns true if a countdown was running.
+    pub fn cancel_power_action(&mut self) -> bool {
+        self.power_cancelled = true;
+        let cancelled = self.power_countdown.take().is_some();
+        if cancelled {
+            info!("Curfew power action cancelled");
+        }
+        cancelled
+    }
+
+    /// Start, fire or cancel the curfew countdown
+    fn tick_curfew(&mut self, now_mono: MonotonicInstant, now: DateTime<Local>) -> Option<CoreEvent> {
+        let curfew = match &self.policy.curfew {
+            Some(c) if self.sessions.is_empty() && c.window.contains_in(&self.policy.service.timezone, &now) => c,
+            _ => {
+                // A session or the end of curfew re-arms a cancelled countdown
+                self.power_cancelled = false;
+                return self
+                    .power_countdown
+                    .take()
+                    .map(|_| CoreEvent::PowerActionCancelled);
+            }
+        };
+
+        match self.power_countdown {
+            None if !self.power_cancelled => {
+                let pending = PendingPowerAction {
+                    action: curfew.action,
+                    deadline: now + chrono::Duration::from_std(curfew.delay).unwrap_or_default(),
+                };
+                info!(
+                    action = ?pending.action,
+                    deadline = %pending.deadline,
+                    "Idle during curfew, power action scheduled"
+                );
+                self.power_countdown = Some(PowerCountdown {
+                    pending,
+                    deadline_mono: now_mono + curfew.delay,
+                });
+                Some(CoreEvent::PowerActionScheduled(pending))
+            }
+            Some(countdown) if now_mono >= countdown.deadline_mono => {
+                self.power_countdown = None;
+                Some(CoreEvent::PowerActionDue {
+                    action: countdown.pending.action,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Entries whose autostart time passed since the last check.
+    ///
+    /// The first check only sets the starting point, so restarting the
+    /// service late in the evening doesn't replay earlier autostarts.
+    fn due_autostarts(&mut self, now: DateTime<Local>) -> Vec<CoreEvent> {
+        let Some(last) = self.last_autostart_check.replace(now) else {
+            return Vec::new();
+        };
+        if now <= last {
+            return Vec::new();
+        }
+
+        let tz = &self.policy.service.timezone;
+        let mut dates = vec![tz.wall_time(&last).date()];
+        let today = tz.wall_time(&now).date();
+        if today != dates[0] {
+            dates.push(today);
+        }
+
+        let mut events = Vec::new();
+        for entry in &self.policy.entries {
+            let due = entry.autostart.iter().any(|rule| {
+                dates.iter().any(|date| {
+                    if !rule.days.contains(date.weekday()) {
+                        return false;
+                    }
+                    let Some(at) = tz.next_instant(&date.and_time(rule.at.to_naive_time()), &last)
+                    else {
+                        return false;
+                    };
+                    at > last
+                        && at <= now
+                        && (now - at).to_std().unwrap_or(Duration::ZERO) <= AUTOSTART_GRACE
+                })
+            });
+            if due {
+                info!(entry_id = %entry.id, "Autostart due");
+                events.push(CoreEvent::AutostartDue {
+                    entry_id: entry.id.clone(),
+                });
+            }
+        }
+        events
+    }
+
+    /// Launch an entry for its autostart schedule.
+    ///
+    /// Nobody is there to confirm, so `confirm_launch` is skipped. A running
+    /// session or any other denial reason skips the autostart.
+    pub fn request_autostart(&mut self, entry_id: &EntryId, now: DateTime<Local>) -> LaunchDecision {
+        let decision = match self.request_launch(entry_id, now) {
+            LaunchDecision::NeedsConfirmation(plan) => {
+                self.pending_launch.remove(&plan.seat);
+                LaunchDecision::Approved(plan)
+            }
+            other => other,
+        };
+
+        let launched = matches!(decision, LaunchDecision::Approved(_));
+        if !launched {
+            info!(entry_id = %entry_id, "Autostart skipped");
+        }
+        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::AutostartTriggered {
+            entry_id: entry_id.clone(),
+            launched,
+        }));
+
+        decision
+    }
+
+    /// Get the default seat's session
+    pub fn current_session(&self) -> Option<&ActiveSession> {
+        self.session_on(&SeatId::default())
+    }
+
+    /// Get mutable reference to the default seat's session
+    pub fn current_session_mut(&mut self) -> Option<&mut ActiveSession> {
+        self.invalidate_entries();
+        self.sessions.get_mut(&SeatId::default())
+    }
+
+    /// Get the session on `seat`
+    pub fn session_on(&self, seat: &SeatId) -> Option<&ActiveSession> {
+        self.sessions.get(seat)
+    }
+
+    /// Running sessions, by seat
+    pub fn sessions(&self) -> impl Iterator<Item = (&SeatId, &ActiveSession)> {
+        self.sessions.iter()
+    }
+
+    /// Get a running session by ID, whichever seat it's on
+    pub fn session_by_id(&self, session_id: &SessionId) -> Option<&ActiveSession> {
+        self.sessions.values().find(|s| s.plan.session_id == *session_id)
+    }
+
+    /// Seat a running session is on
+    pub fn seat_of(&self, session_id: &SessionId) -> Option<SeatId> {
+        self.session_by_id(session_id).map(|s| s.plan.seat.clone())
+    }
+
+    /// Check if a session is active on any seat
+    pub fn has_active_session(&self) -> bool {
+        !self.sessions.is_empty()
+    }
+
+    /// No session running on any seat and no app update in progress
+    pub fn is_idle(&self) -> bool {
+        self.sessions.is_empty() && self.updating.is_none()
+    }
+
+    /// Tell clients the time left in the default seat's session right away,
+    /// e.g. after it was extended. None without a session or for unlimited ones.
+    pub fn sync_time_remaining(&mut self, now_mono: MonotonicInstant) -> Option<CoreEvent> {
+        self.sync_time_remaining_on(&SeatId::default(), now_mono)
+    }
+
+    /// Tell clients the time left in the session on `seat` right away
+    pub fn sync_time_remaining_on(&mut self, seat: &SeatId, now_mono: MonotonicInstant) -> Option<CoreEvent> {
+        time_remaining_event(self.sessions.get_mut(seat)?, now_mono)
+    }
+
+    /// Extend the default seat's session (admin action)
+    /// Only works for sessions with a deadline (not unlimited sessions).
+    pub fn extend_current(
+        &mut self,
+        by: Duration,
+        now_mono: MonotonicInstant,
+        now: DateTime<Local>,
+    ) -> Option<DateTime<Local>> {
+        self.extend_on(&SeatId::default(), by, now_mono, now)
+    }
+
+    /// Extend the session on `seat` (admin action)
+    pub fn extend_on(
+        &mut self,
+        seat: &SeatId,
+        by: Duration,
+        _now_mono: MonotonicInstant,
+        _now: DateTime<Local>,
+    ) -> Option<DateTime<Local>> {
+        self.invalidate_entries();
+        let session = self.sessions.get_mut(seat)?;
+
+        // Can't extend unlimited sessions - they don't have a deadline
+        let new_deadline = session.extend(by)?;
+
+        // Log to audit
+        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::SessionExtended {
+            session_id: session.plan.session_id.clone(),
+            extended_by: by,
+            new_deadline,
+        }));
+
+        info!(
+            session_id = %session.plan.session_id,
+            extended_by_secs = by.as_secs(),
+            new_deadline = %new_deadline,
+            "Session extended"
+        );
+
+        Some(new_deadline)
+    }
+}
+
+/// When clients should next be told the time left in `session`: every
+/// [`TIME_SYNC_INTERVAL`], and every second as it nears [`TIME_SYNC_FINAL`]
+/// while running. None for unlimited and ending sessions.
+fn next_time_sync(session: &ActiveSession, now_mono: MonotonicInstant) -> Option<MonotonicInstant> {
+    if matches!(
+        session.state,
+        shepherd_api::SessionState::Expiring | shepherd_api::SessionState::Ended
+    ) {
+        return None;
+    }
+    let remaining = session.time_remaining(now_mono)?;
+    let Some(last) = session.last_time_sync else {
+        return Some(now_mono);
+    };
+    // Switch early enough that the final stretch isn't entered between syncs
+    let interval = if session.is_running() && remaining <= TIME_SYNC_FINAL + TIME_SYNC_INTERVAL {
+        Duration::from_secs(1)
+    } else {
+        TIME_SYNC_INTERVAL
+    };
+    Some(last + interval)
+}
+
+/// Tell clients the time left in `session` now
+fn time_remaining_event(session: &mut ActiveSession, now_mono: MonotonicInstant) -> Option<CoreEvent> {
+    let time_remaining = session.time_remaining(now_mono)?;
+    session.last_time_sync = Some(now_mono);
+    Some(CoreEvent::TimeRemaining {
+        session_id: session.plan.session_id.clone(),
+        time_remaining,
+        deadline: session.deadline,
+    })
+}
+
+/// Split a session of `duration` ending at `end` at each local midnight,
+/// oldest day first
+fn usage_by_day(end: DateTime<Local>, duration: Duration) -> Vec<(NaiveDate, Duration)> {
+    let mut parts = Vec::new();
+    let mut remaining = duration;
+    let mut cursor = end;
+    while !remaining.is_zero() {
+        let day = cursor.date_naive();
+        let midnight = day.and_time(NaiveTime::MIN).and_local_timezone(Local).earliest();
+        let on_day = midnight
+            .map_or(remaining, |m| (cursor - m).to_std().unwrap_or(Duration::ZERO))
+            .min(remaining);
+        if !on_day.is_zero() {
+            parts.push((day, on_day));
+            remaining -= on_day;
+        }
+        match midnight {
+            Some(midnight) => cursor = midnight - chrono::Duration::nanoseconds(1),
+            None => break,
+        }
+    }
+    parts.reverse();
+    parts
+}
+
+/// Split the time a session was charged at each local midnight, oldest day
+/// first. Each run segment counts on the days it ran, not time spent paused.
+fn session_usage_by_day(
+    session: &ActiveSession,
+    now_mono: MonotonicInstant,
+    now: DateTime<Local>,
+) -> Vec<(NaiveDate, Duration)> {
+    let mut parts: Vec<(NaiveDate, Duration)> = Vec::new();
+    for segment in &session.segments {
+        let ended = segment.ended.unwrap_or(now_mono);
+        let ago = chrono::Duration::from_std(now_mono.duration_since(ended)).unwrap_or_default();
+        for (day, part) in usage_by_day(now - ago, ended.duration_since(segment.started)) {
+            match parts.last_mut() {
+                Some((last, total)) if *last == day => *total += part,
+                _ => parts.push((day, part)),
+            }
+        }
+    }
+    parts
+}
+
+/// Whether a session ending this way shouldn't be charged. Expired sessions
+/// always are, so a nearly-used quota can't be replayed in short sessions.
+fn refund_reason(
+    limits: &LimitsPolicy,
+    reason: &SessionEndReason,
+    duration: Duration,
+) -> Option<RefundReason> {
+    if matches!(reason, SessionEndReason::Expired) {
+        return None;
+    }
+    if let SessionEndReason::ProcessExited { exit_code } = reason
+        && *exit_code != Some(0)
+        && limits.refund_crash_under.is_some_and(|within| duration < within)
+    {
+        return Some(RefundReason::Crashed {
+            exit_code: *exit_code,
+        });
+    }
+    if limits.refund_under.is_some_and(|within| duration < within) {
+        return Some(RefundReason::ShortSession);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shepherd_config::{AvailabilityPolicy, Entry, LimitsPolicy};
+    use shepherd_api::EntryKind;
+    use shepherd_store::SqliteStore;
+    use std::collections::HashMap;
+
+    fn make_test_policy() -> Policy {
+        Policy {
+            service: Default::default(),
+            entries: vec![Entry {
+                id: EntryId::new("test-game"),
+                label: "Test Game".into(),
+                icon_ref: None,
+                kind: EntryKind::Process {
+                    command: "game".into(),
+                    args: vec![],
+                    env: HashMap::new(),
+                    cwd: None,
+                },
+                availability: AvailabilityPolicy {
+                    windows: vec![],
+                    always: true,
+                },
+                limits: LimitsPolicy {
+                    max_run: Some(Duration::from_secs(300)),
+                    min_run: None,
+                    daily_quota: None,
+                    cooldown: None,
+                    cooldown_scope: Default::default(),
+                    cooldown_after: None,
+                    min_gap: None,
+                    refund_under: None,
+                    refund_crash_under: None,
+                },
+                warnings: vec![],
+                volume: None,
+                gamescope: None,
+                device_access: Default::default(),
+                tags: vec![],
+                prechecks: Default::default(),
+                confirm_launch: false,
+                autostart: Vec::new(),
+                hooks: Default::default(),
+                minecraft: None,
+                wine: None,
+                kill_by_name: false,
+                controller_only: false,
+                disabled: false,
+                disabled_reason: None,
+            }],
+            profiles: vec![],
+            seats: vec![],
+            default_warnings: vec![],
+            default_max_run: Some(Duration::from_secs(3600)),
+            volume: Default::default(),
+            display: Default::default(),
+            curfew: None,
+            downtime: Vec::new(),
+            battery: Default::default(),
+            media: Default::default(),
+            rewards: Default::default(),
+            time_exchange: None,
+            maintenance: None,
+            calendar: None,
+            import_apps: Default::default(),
+            warnings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_list_entries() {
+        let policy = make_test_policy();
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let caps = HostCapabilities::minimal();
+        let engine = CoreEngine::new(policy, store, caps);
+
+        let entries = engine.list_entries(shepherd_util::now());
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].enabled);
+    }
+
+    #[test]
+    fn test_reload_policy_theme_changed() {
+        let policy = make_test_policy();
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let caps = HostCapabilities::minimal();
+        let mut engine = CoreEngine::new(policy, store, caps);
+
+        let events = engine.reload_policy(make_test_policy());
+        assert!(matches!(events[..], [CoreEvent::PolicyReloaded { entry_count: 1 }]));
+
+        let mut policy = make_test_policy();
+        policy.service.theme = shepherd_api::Theme::Light;
+        let events = engine.reload_policy(policy);
+        assert!(matches!(
+            events[..],
+            [
+                CoreEvent::PolicyReloaded { .. },
+                CoreEvent::ThemeChanged(shepherd_api::Theme::Light)
+            ]
+        ));
+        assert_eq!(engine.get_state().theme, shepherd_api::Theme::Light);
+    }
+
+    #[test]
+    fn test_simulate_entries() {
+        let policy = make_test_policy();
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let caps = HostCapabilities::minimal();
+        let mut engine = CoreEngine::new(policy, store, caps);
+
+        let mut candidate = make_test_policy();
+        candidate.entries[0].disabled = true;
+
+        let now = shepherd_util::now();
+        let simulated = engine.simulate_entries(candidate, now);
+        assert!(!simulated[0].enabled);
+
+        // The live policy is untouched
+        assert!(engine.list_entries(now)[0].enabled);
+        assert!(!engine.policy().entries[0].disabled);
+    }
+
+    #[test]
+    fn test_launch_approval() {
+        let policy = make_test_policy();
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let caps = HostCapabilities::minimal();
+        let mut engine = CoreEngine::new(policy, store, caps);
+
+        let entry_id = EntryId::new("test-game");
+        let decision = engine.request_launch(&entry_id, shepherd_util::now());
+
+        assert!(matches!(decision, LaunchDecision::Approved(_)));
+    }
+
+    #[test]
+    fn test_session_blocks_new_launch() {
+        let policy = make_test_policy();
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let caps = HostCapabilities::minimal();
+        let mut engine = CoreEngine::new(policy, store, caps);
+
+        let entry_id = EntryId::new("test-game");
+        let now = shepherd_util::now();
+        let now_mono = MonotonicInstant::now();
+
+        // Launch first session
+        if let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) {
+            engine.start_session(plan, now, now_mono);
+        }
+
+        // Try to launch again - should be denied
+        let decision = engine.request_launch(&entry_id, now);
+        assert!(matches!(decision, LaunchDecision::Denied { .. }));
+    }
+
+    #[test]
+    fn test_seats_run_side_by_side() {
+        let mut policy = make_test_policy();
+        let mut other = policy.entries[0].clone();
+        other.id = EntryId::new("other-game");
+        policy.entries.push(other);
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
+
+        let game = EntryId::new("test-game");
+        let other = EntryId::new("other-game");
+        let den = SeatId::new("den");
+        let now = shepherd_util::now();
+        let now_mono = MonotonicInstant::now();
+
+        let LaunchDecision::Approved(plan) = engine.request_launch(&game, now) else {
+            panic!("Expected approval");
+        };
+        let first = plan.session_id.clone();
+        engine.start_session(plan, now, now_mono);
+
+        // The same entry can't run on two seats at once
+        let LaunchDecision::Denied { reasons } = engine.request_launch_on(&den, &game, now) else {
+            panic!("Expected denial");
+        };
+        assert_eq!(reasons, vec![ReasonCode::InUseOnOtherSeat { seat: SeatId::default() }]);
+
+        let LaunchDecision::Approved(plan) = engine.request_launch_on(&den, &other, now) else {
+            panic!("Expected approval");
+        };
+        assert_eq!(plan.seat, den);
+        let second = plan.session_id.clone();
+        engine.start_session(plan, now, now_mono);
+
+        assert_eq!(engine.seat_of(&first), Some(SeatId::default()));
+        assert_eq!(engine.seat_of(&second), Some(den.clone()));
+        let state = engine.get_state_on(&den);
+        assert_eq!(state.seat, den);
+        assert_eq!(state.current_session.unwrap().entry_id, other);
+        assert_eq!(engine.get_state().current_session.unwrap().entry_id, game);
+
+        // Stopping one seat leaves the other running
+        let StopDecision::Stopped(result) =
+            engine.stop_on(&den, SessionEndReason::UserStop, now_mono, now)
+        else {
+            panic!("Expected a stop");
+        };
+        assert_eq!(result.seat, den);
+        assert!(engine.session_on(&den).is_none());
+        assert_eq!(engine.current_session().unwrap().plan.session_id, first);
+    }
+
+    #[test]
+    fn test_state_entries_follow_changes() {
+        let policy = make_test_policy();
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let caps = HostCapabilities::minimal();
+        let mut engine = CoreEngine::new(policy, store, caps);
+
+        let entry_id = EntryId::new("test-game");
+        let now = shepherd_util::now();
+        let now_mono = MonotonicInstant::now();
+
+        // The tick's views are reused by the snapshot
+        engine.tick(now_mono, now);
+        assert!(engine.get_state().entries[0].enabled);
+
+        // Starting a session drops them
+        let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) else {
+            panic!("launch denied");
+        };
+        engine.start_session(plan, now, now_mono);
+        let entries = engine.get_state().entries;
+        assert!(!entries[0].enabled);
+        assert!(entries[0]
+            .reasons
+            .iter()
+            .any(|reason| matches!(reason, ReasonCode::SessionActive { .. })));
+
+        engine.stop_current(SessionEndReason::UserStop, now_mono, now);
+        assert!(engine.current_entries()[0].enabled);
+    }
+
+    #[test]
+    fn test_roll_over_day() {
+        use chrono::TimeZone;
+
+        let mut policy = make_test_policy();
+        policy.entries[0].limits.daily_quota = Some(Duration::from_secs(600));
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let caps = HostCapabilities::minimal();
+        let mut engine = CoreEngine::new(policy, store.clone(), caps);
+
+        let entry_id = EntryId::new("test-game");
+        let evening = Local.with_ymd_and_hms(2025, 1, 6, 23, 0, 0).unwrap();
+        let midnight = Local.with_ymd_and_hms(2025, 1, 7, 0, 0, 0).unwrap();
+        store
+            .add_usage(&entry_id, evening.date_naive(), Duration::from_secs(600))
+            .unwrap();
+
+        engine.tick(MonotonicInstant::now(), evening);
+        assert!(!engine.list_entries(evening)[0].enabled);
+        assert!(matches!(
+            engine.roll_over_day(midnight),
+            Some(CoreEvent::AvailabilitySetChanged)
+        ));
+        assert!(engine.list_entries(midnight)[0].enabled);
+
+        // Nothing else changes on a second roll over
+        assert!(engine.roll_over_day(midnight).is_none());
+    }
+
+    #[test]
+    fn test_next_tick() {
+        use chrono::TimeZone;
+
+        let mut policy = make_test_policy();
+        policy.entries[0].limits.max_run = Some(Duration::from_secs(90));
+        policy.entries[0].limits.cooldown = Some(Duration::from_secs(45));
+        policy.entries[0].warnings = vec![shepherd_api::WarningThreshold {
+            seconds_before: 60,
+            severity: WarningSeverity::Warn,
+            message_template: None,
+        }];
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let caps = HostCapabilities::minimal();
+        let mut engine = CoreEngine::new(policy, store, caps);
+
+        let entry_id = EntryId::new("test-game");
+        let wall = Local.with_ymd_and_hms(2025, 1, 6, 15, 0, 0).unwrap();
+        let mono = MonotonicInstant::now();
+        let at = |secs: u64| (mono + Duration::from_secs(secs), wall + chrono::Duration::seconds(secs as i64));
+
+        // Idle: the next minute
+        let (now_mono, now) = at(15);
+        assert_eq!(engine.next_tick(now_mono, now), Duration::from_secs(45));
+
+        // Time left is sent right away, then every second this close to the end
+        let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, wall) else {
+            panic!("launch denied");
+        };
+        engine.start_session(plan, wall, mono);
+        assert_eq!(engine.next_tick(mono, wall), Duration::ZERO);
+        engine.tick(mono, wall);
+        assert_eq!(engine.next_tick(mono, wall), Duration::from_secs(1));
+        let (now_mono, now) = at(70);
+        engine.tick(now_mono, now);
+        assert_eq!(engine.next_tick(now_mono, now), Duration::from_secs(1));
+
+        // Escalation retries while the expired app keeps running
+        let (now_mono, now) = at(90);
+        assert!(engine.tick(now_mono, now).iter().any(|e| matches!(e, CoreEvent::ExpireDue { .. })));
+        assert_eq!(engine.next_tick(now_mono, now), ENFORCEMENT_RETRY_INTERVAL);
+
+        // The cooldown ends 45s after the session
+        let (now_mono, now) = at(125);
+        engine.notify_session_exited(Some(0), now_mono, now);
+        engine.tick(now_mono, now);
+        assert_eq!(engine.next_tick(now_mono, now), Duration::from_secs(45));
+        let (now_mono, now) = at(150);
+        assert_eq!(engine.next_tick(now_mono, now), Duration::from_secs(20));
+    }
+
+    #[test]
+    fn test_time_sync() {
+        use chrono::TimeZone;
+
+        let mut policy = make_test_policy();
+        policy.entries[0].limits.max_run = Some(Duration::from_secs(10 * 60));
+        policy.entries[0].warnings.clear();
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
+
+        let entry_id = EntryId::new("test-game");
+        let now = Local.with_ymd_and_hms(2025, 1, 6, 15, 0, 0).unwrap();
+        let mono = MonotonicInstant::now();
+        let at = |secs: u64| (mono + Duration::from_secs(secs), now + chrono::Duration::seconds(secs as i64));
+        let synced = |events: &[CoreEvent]| {
+            events.iter().find_map(|e| match e {
+                CoreEvent::TimeRemaining { time_remaining, .. } => Some(*time_remaining),
+                _ => None,
+            })
+        };
+
+        let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) else {
+            panic!("launch denied");
+        };
+        engine.start_session(plan, now, mono);
+
+        // Sent on the first tick, then every 30s
+        assert_eq!(synced(&engine.tick(mono, now)), Some(Duration::from_secs(600)));
+        let (now_mono, now_wall) = at(10);
+        assert_eq!(synced(&engine.tick(now_mono, now_wall)), None);
+        assert_eq!(engine.next_tick(now_mono, now_wall), Duration::from_secs(20));
+        let (now_mono, now_wall) = at(30);
+        assert_eq!(synced(&engine.tick(now_mono, now_wall)), Some(Duration::from_secs(570)));
+
+        // Every second near the end
+        let (now_mono, now_wall) = at(300);
+        assert_eq!(synced(&engine.tick(now_mono, now_wall)), Some(Duration::from_secs(300)));
+        assert_eq!(engine.next_tick(now_mono, now_wall), Duration::from_secs(1));
+
+        // And right away after an extension
+        engine.extend_current(Duration::from_secs(600), now_mono, now_wall).unwrap();
+        let Some(CoreEvent::TimeRemaining { time_remaining, deadline, .. }) =
+            engine.sync_time_remaining(now_mono)
+        else {
+            panic!("no time sync");
+        };
+        assert_eq!(time_remaining, Duration::from_secs(900));
+        assert_eq!(deadline, Some(now_wall + chrono::Duration::seconds(900)));
+        assert_eq!(engine.next_tick(now_mono, now_wall), TIME_SYNC_INTERVAL);
+    }
+
+    #[test]
+    fn test_tick_warnings() {
+        let policy = Policy {
+            entries: vec![Entry {
+                id: EntryId::new("test"),
+                label: "Test".into(),
+                icon_ref: None,
+                kind: EntryKind::Process {
+                    command: "test".into(),
+                    args: vec![],
+                    env: HashMap::new(),
+                    cwd: None,
+                },
+                availability: AvailabilityPolicy {
+                    windows: vec![],
+                    always: true,
+                },
+                limits: LimitsPolicy {
+                    max_run: Some(Duration::from_secs(120)), // 2 minutes
+                    min_run: None,
+                    daily_quota: None,
+                    cooldown: None,
+                    cooldown_scope: Default::default(),
+                    cooldown_after: None,
+                    min_gap: None,
+                    refund_under: None,
+                    refund_crash_under: None,
+                },
+                warnings: vec![shepherd_api::WarningThreshold {
+                    seconds_before: 60,
+                    severity: WarningSeverity::Warn,
+                    message_template: Some("1 minute left".into()),
+                }],
+                volume: None,
+                gamescope: None,
+                device_access: Default::default(),
+                tags: vec![],
+                prechecks: Default::default(),
+                confirm_launch: false,
+                autostart: Vec::new(),
+                hooks: Default::default(),
+                minecraft: None,
+                wine: None,
+                kill_by_name: false,
+                controller_only: false,
+                disabled: false,
+                disabled_reason: None,
+            }],
+            service: Default::default(),
+            profiles: vec![],
+            seats: vec![],
+            default_warnings: vec![],
+            default_max_run: Some(Duration::from_secs(3600)),
+            volume: Default::default(),
+            display: Default::default(),
+            curfew: None,
+            downtime: Vec::new(),
+            battery: Default::default(),
+            media: Default::default(),
+            rewards: Default::default(),
+            time_exchange: None,
+            maintenance: None,
+            calendar: None,
+            import_apps: Default::default(),
+            warnings: Vec::new(),
+        };
+
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let caps = HostCapabilities::minimal();
+        let mut engine = CoreEngine::new(policy, store, caps);
+
+        let entry_id = EntryId::new("test");
+        let now = shepherd_util::now();
+        let now_mono = MonotonicInstant::now();
+
+        // Start session
+        if let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) {
+            engine.start_session(plan, now, now_mono);
+        }
+
+        // No warnings initially (first tick may emit AvailabilitySetChanged)
+        let events = engine.tick(now_mono, now);
+        // Filter to just warning events for this test
+        let warning_events: Vec<_> = events.iter().filter(|e| matches!(e, CoreEvent::Warning { .. })).collect();
+        assert!(warning_events.is_empty());
+
+        // At 70 seconds (10 seconds past warning threshold), warning should fire
+        let later = now_mono + Duration::from_secs(70);
+        let events = engine.tick(later, now);
+        let warning_events: Vec<_> = events.iter().filter(|e| matches!(e, CoreEvent::Warning { .. })).collect();
+        assert_eq!(warning_events.len(), 1);
+        assert!(matches!(warning_events[0], CoreEvent::Warning { threshold_seconds: 60, .. }));
+
+        // Warning shouldn't fire twice
+        let events = engine.tick(later, now);
+        let warning_events: Vec<_> = events.iter().filter(|e| matches!(e, CoreEvent::Warning { .. })).collect();
+        assert!(warning_events.is_empty());
+    }
+
+    #[test]
+    fn test_session_expiry() {
+        let policy = Policy {
+            entries: vec![Entry {
+                id: EntryId::new("test"),
+                label: "Test".into(),
+                icon_ref: None,
+                kind: EntryKind::Process {
+                    command: "test".into(),
+                    args: vec![],
+                    env: HashMap::new(),
+                    cwd: None,
+                },
+                availability: AvailabilityPolicy {
+                    windows: vec![],
+                    always: true,
+                },
+                limits: LimitsPolicy {
+                    max_run: Some(Duration::from_secs(60)),
+                    min_run: None,
+                    daily_quota: None,
+                    cooldown: None,
+                    cooldown_scope: Default::default(),
+                    cooldown_after: None,
+                    min_gap: None,
+                    refund_under: None,
+                    refund_crash_under: None,
+                },
+                warnings: vec![],
+                volume: None,
+                gamescope: None,
+                device_access: Default::default(),
+                tags: vec![],
+                prechecks: Default::default(),
+                confirm_launch: false,
+                autostart: Vec::new(),
+                hooks: Default::default(),
+                minecraft: None,
+                wine: None,
+                kill_by_name: false,
+                controller_only: false,
+                disabled: false,
+                disabled_reason: None,
+            }],
+            service: Default::default(),
+            profiles: vec![],
+            seats: vec![],
+            default_warnings: vec![],
+            default_max_run: Some(Duration::from_secs(3600)),
+            volume: Default::default(),
+            display: Default::default(),
+            curfew: None,
+            downtime: Vec::new(),
+            battery: Default::default(),
+            media: Default::default(),
+            rewards: Default::default(),
+            time_exchange: None,
+            maintenance: None,
+            calendar: None,
+            import_apps: Default::default(),
+            warnings: Vec::new(),
+        };
+
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let caps = HostCapabilities::minimal();
+        let mut engine = CoreEngine::new(policy, store, caps);
+
+        let entry_id = EntryId::new("test");
+        let now = shepherd_util::now();
+        let now_mono = MonotonicInstant::now();
+
+        // Start session
+        if let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) {
+            engine.start_session(plan, now, now_mono);
+        }
+
+        // At 61 seconds, should be expired
+        let later = now_mono + Duration::from_secs(61);
+        let events = engine.tick(later, now);
+        // Filter to just expiry events for this test
+        let expiry_events: Vec<_> = events.iter().filter(|e| matches!(e, CoreEvent::ExpireDue { .. })).collect();
+        assert_eq!(expiry_events.len(), 1);
+        assert!(matches!(expiry_events[0], CoreEvent::ExpireDue { .. }));
+    }
+
+    #[test]
+    fn test_enforcement_escalation() {
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(make_test_policy(), store.clone(), HostCapabilities::minimal());
+        let entry_id = EntryId::new("test-game");
+        let now = shepherd_util::now();
+        let start = MonotonicInstant::now();
+
+        let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) else {
+            panic!("launch should be approved");
+        };
+        engine.start_session(plan, now, start);
+
+        let mut enforcement_at = |secs| {
+            engine
+                .tick(start + Duration::from_secs(secs), now)
+                .into_iter()
+                .find_map(|e| match e {
+                    CoreEvent::ExpireDue { .. } => Some("expire".to_string()),
+                    CoreEvent::EnforcementRetry { attempt, step, .. } => {
+                        Some(format!("{attempt}:{step:?}"))
+                    }
+                    CoreEvent::EnforcementFailed { .. } => Some("failed".to_string()),
+                    _ => None,
+                })
+        };
+
+        assert_eq!(enforcement_at(300).as_deref(), Some("expire"));
+        assert_eq!(enforcement_at(305), None);
+        assert_eq!(enforcement_at(310).as_deref(), Some("1:Force"));
+        assert_eq!(enforcement_at(320).as_deref(), Some("2:KillCgroup"));
+        assert_eq!(enforcement_at(330).as_deref(), Some("3:KillCgroup"));
+        assert_eq!(enforcement_at(340).as_deref(), Some("failed"));
+        assert_eq!(enforcement_at(350), None);
+
+        let audit = store.get_recent_audits(10).unwrap();
+        assert!(audit
+            .iter()
+            .any(|a| matches!(a.event, AuditEventType::EnforcementFailed { attempts: 3, .. })));
+    }
+
+    #[test]
+    fn test_tick_display_schedule() {
+        use chrono::TimeZone;
+        use shepherd_config::{DisplayPolicy, DisplayWindow};
+        use shepherd_util::{DaysOfWeek, TimeWindow, WallClock};
+
+        let mut policy = make_test_policy();
+        policy.display = DisplayPolicy {
+            default_brightness: Some(100),
+            night_light_temperature: 3500,
+            windows: vec![DisplayWindow {
+                window: TimeWindow {
+                    days: DaysOfWeek::new(0x7F),
+                    start: WallClock::new(19, 0).unwrap(),
+                    end: WallClock::new(23, 0).unwrap(),
+                },
+                brightness: Some(40),
+                night_light: true,
+            }],
+        };
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
+        let now_mono = MonotonicInstant::now();
+
+        let display_events = |events: Vec<CoreEvent>| -> Vec<DisplaySettings> {
+            events
+                .into_iter()
+                .filter_map(|e| match e {
+                    CoreEvent::DisplaySettingsChanged(settings) => Some(settings),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // Initial settings are applied on the first tick only
+        let afternoon = Local.with_ymd_and_hms(2025, 1, 6, 18, 59, 0).unwrap();
+        assert_eq!(
+            display_events(engine.tick(now_mono, afternoon)),
+            vec![DisplaySettings { brightness: Some(100), night_light: None }]
+        );
+        assert!(display_events(engine.tick(now_mono, afternoon)).is_empty());
+
+        let evening = Local.with_ymd_and_hms(2025, 1, 6, 19, 0, 0).unwrap();
+        assert_eq!(
+            display_events(engine.tick(now_mono, evening)),
+            vec![DisplaySettings { brightness: Some(40), night_light: Some(3500) }]
+        );
+    }
+
+    #[test]
+    fn test_tick_volume_quiet_hours() {
+        use chrono::TimeZone;
+        use shepherd_config::VolumeWindow;
+        use shepherd_util::{DaysOfWeek, TimeWindow, WallClock};
+
+        let mut policy = make_test_policy();
+        policy.volume.windows = vec![VolumeWindow {
+            window: TimeWindow {
+                days: DaysOfWeek::new(0x7F),
+                start: WallClock::new(20, 0).unwrap(),
+                end: WallClock::new(7, 0).unwrap(),
+            },
+            limits: QuietHours {
+                max_volume: Some(40),
+                require_headphones: true,
+            },
+        }];
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
+        let now_mono = MonotonicInstant::now();
+
+        let caps = |events: Vec<CoreEvent>| -> Vec<Option<u8>> {
+            events
+                .into_iter()
+                .filter_map(|e| match e {
+                    CoreEvent::QuietHoursChanged(quiet) => Some(quiet.max_volume),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // Nothing to announce outside quiet hours
+        let evening = Local.with_ymd_and_hms(2025, 1, 6, 19, 59, 0).unwrap();
+        assert!(caps(engine.tick(now_mono, evening)).is_empty());
+        assert_eq!(engine.quiet_hours(), QuietHours::default());
+
+        let night = Local.with_ymd_and_hms(2025, 1, 6, 20, 0, 0).unwrap();
+        assert_eq!(caps(engine.tick(now_mono, night)), vec![Some(40)]);
+        assert!(caps(engine.tick(now_mono, night)).is_empty());
+        assert_eq!(engine.quiet_hours().max_volume, Some(40));
+
+        // Headphones only, once the speakers are known to be in use
+        let game = EntryId::new("test-game");
+        assert!(matches!(engine.request_launch(&game, night), LaunchDecision::Approved(_)));
+        engine.set_audio_output(AudioOutput::Speakers);
+        assert!(matches!(
+            engine.request_launch(&game, night),
+            LaunchDecision::Denied { ref reasons } if reasons.contains(&ReasonCode::HeadphonesRequired)
+        ));
+        engine.set_audio_output(AudioOutput::Headphones);
+        assert!(matches!(engine.request_launch(&game, night), LaunchDecision::Approved(_)));
+
+        let morning = Local.with_ymd_and_hms(2025, 1, 7, 7, 0, 0).unwrap();
+        assert_eq!(caps(engine.tick(now_mono, morning)), vec![None]);
+        engine.set_audio_output(AudioOutput::Speakers);
+        assert!(matches!(engine.request_launch(&game, morning), LaunchDecision::Approved(_)));
+    }
+
+    #[test]
+    fn test_tick_curfew_countdown() {
+        use chrono::TimeZone;
+        use shepherd_api::PowerAction;
+        use shepherd_config::CurfewPolicy;
+        use shepherd_util::{DaysOfWeek, TimeWindow, WallClock};
+
+        let mut policy = make_test_policy();
+        policy.curfew = Some(CurfewPolicy {
+            window: TimeWindow {
+                days: DaysOfWeek::new(0x7F),
+                start: WallClock::new(21, 0).unwrap(),
+                end: WallClock::new(7, 0).unwrap(),
+            },
+            action: PowerAction::Suspend,
+            delay: Duration::from_secs(600),
+        });
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
+
+        let power_events = |events: Vec<CoreEvent>| -> Vec<CoreEvent> {
+            events
+                .into_iter()
+                .filter(|e| {
+                    matches!(
+                        e,
+                        CoreEvent::PowerActionScheduled(_)
+                            | CoreEvent::PowerActionCancelled
+                            | CoreEvent::PowerActionDue { .. }
+                    )
+                })
+                .collect()
+        };
+
+        // Nothing happens before curfew
+        let start_mono = MonotonicInstant::now();
+        let evening = Local.with_ymd_and_hms(2025, 1, 6, 20, 0, 0).unwrap();
+        assert!(power_events(engine.tick(start_mono, evening)).is_empty());
+
+        // Idle during curfew starts the countdown
+        let night = Local.with_ymd_and_hms(2025, 1, 6, 21, 30, 0).unwrap();
+        let events = power_events(engine.tick(start_mono, night));
+        assert!(matches!(events.as_slice(), [CoreEvent::PowerActionScheduled(_)]));
+        assert!(engine.get_state().pending_power_action.is_some());
+
+        // Cancelling suppresses the countdown until re-armed
+        assert!(engine.cancel_power_action());
+        assert!(power_events(engine.tick(start_mono, night)).is_empty());
+
+        // A session re-arms it; once it ends, the countdown restarts and fires
+        let entry_id = EntryId::new("test-game");
+        if let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, night) {
+            engine.start_session(plan, night, start_mono);
+        }
+        assert!(power_events(engine.tick(start_mono, night)).is_empty());
+        engine.notify_session_exited(Some(0), start_mono, night);
+
+        assert!(matches!(
+            power_events(engine.tick(start_mono, night)).as_slice(),
+            [CoreEvent::PowerActionScheduled(_)]
+        ));
+        let later = start_mono + Duration::from_secs(601);
+        assert!(matches!(
+            power_events(engine.tick(later, night)).as_slice(),
+            [CoreEvent::PowerActionDue { action: PowerAction::Suspend }]
+        ));
+    }
+
+    #[test]
+    fn test_session_check_in() {
+        use shepherd_api::Mood;
+
+        let mut policy = make_test_policy();
+        policy.service.session_check_in = true;
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store.clone(), HostCapabilities::minimal());
+        let now = shepherd_util::now();
+        let now_mono = MonotonicInstant::now();
+
+        let entry_id = EntryId::new("test-game");
+        let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) else {
+            panic!("launch should be approved");
+        };
+        let session_id = plan.session_id.clone();
+        engine.start_session(plan, now, now_mono);
+        assert!(engine.get_state().pending_check_in.is_none());
+
+        engine.notify_session_exited(Some(0), now_mono, now);
+        let pending = engine.get_state().pending_check_in.unwrap();
+        assert_eq!(pending.session_id, session_id);
+        assert_eq!(pending.label, "Test Game");
+
+        // Unknown sessions are rejected
+        assert!(!engine.submit_check_in(&SessionId::new(), Some(Mood::Good), now));
+
+        assert!(engine.submit_check_in(&session_id, Some(Mood::Great), now));
+        assert!(engine.get_state().pending_check_in.is_none());
+        assert!(!engine.submit_check_in(&session_id, Some(Mood::Great), now));
+
+        let check_ins = store.get_recent_check_ins(10).unwrap();
+        assert_eq!(check_ins.len(), 1);
+        assert_eq!(check_ins[0].mood, Mood::Great);
+    }
+
+    #[test]
+    fn test_entry_health() {
+        let policy = make_test_policy();
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
+        let now = shepherd_util::now();
+        let entry_id = EntryId::new("test-game");
+
+        // Healthy entries don't produce events
+        assert!(engine.set_entry_health(&entry_id, None, now).is_none());
+
+        let event = engine.set_entry_health(&entry_id, Some("game is not installed".into()), now);
+        assert!(matches!(
+            event,
+            Some(CoreEvent::EntryAvailabilityChanged { enabled: false, .. })
+        ));
+        assert!(engine
+            .set_entry_health(&entry_id, Some("game is not installed".into()), now)
+            .is_none());
+
+        let view = &engine.list_entries(now)[0];
+        assert!(!view.enabled);
+        assert_eq!(
+            view.reasons,
+            vec![ReasonCode::Disabled {
+                reason: Some("game is not installed".into())
+            }]
+        );
+        assert!(matches!(
+            engine.request_launch(&entry_id, now),
+            LaunchDecision::Denied { .. }
+        ));
+
+        let event = engine.set_entry_health(&entry_id, None, now);
+        assert!(matches!(
+            event,
+            Some(CoreEvent::EntryAvailabilityChanged { enabled: true, .. })
+        ));
+    }
+
+    #[test]
+    fn test_maintenance_updates() {
+        use shepherd_config::MaintenancePolicy;
+
+        let mut policy = make_test_policy();
+        policy.maintenance = Some(MaintenancePolicy {
+            entries: vec![EntryId::new("test-game")],
+            interval: Duration::from_secs(24 * 3600),
+            max_defer: Duration::from_secs(72 * 3600),
+        });
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store.clone(), HostCapabilities::minimal());
+        let now = shepherd_util::now();
+        let now_mono = MonotonicInstant::now();
+        let entry_id = EntryId::new("test-game");
+
+        // Never updated, so due right away
+        assert_eq!(engine.next_update(now), Some(entry_id.clone()));
+
+        // Not while a session is running
+        let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) else {
+            panic!("launch should be approved");
+        };
+        engine.start_session(plan, now, now_mono);
+        assert_eq!(engine.next_update(now), None);
+        engine.notify_session_exited(Some(0), now_mono, now);
+
+        // The entry can't launch while updating
+        engine.begin_update(&entry_id);
+        assert_eq!(engine.next_update(now), None);
+        assert!(matches!(
+            engine.request_launch(&entry_id, now),
+            LaunchDecision::Denied { .. }
+        ));
+
+        engine.finish_update(&entry_id, Ok(()), now);
+        assert!(matches!(
+            engine.request_launch(&entry_id, now),
+            LaunchDecision::Approved(_)
+        ));
+
+        // Not due again until the interval passes. The entry is always
+        // available, so it then waits out max_defer.
+        assert_eq!(engine.next_update(now + chrono::Duration::hours(1)), None);
+        assert_eq!(engine.next_update(now + chrono::Duration::hours(48)), None);
+        assert_eq!(
+            engine.next_update(now + chrono::Duration::hours(97)),
+            Some(entry_id.clone())
+        );
+
+        // Failed updates are audited and also wait for the interval
+        engine.begin_update(&entry_id);
+        engine.finish_update(&entry_id, Err("no network".into()), now);
+        assert_eq!(engine.next_update(now + chrono::Duration::hours(1)), None);
+        let audit = store.get_recent_audits(5).unwrap();
+        assert!(audit.iter().any(|e| matches!(
+            &e.event,
+            AuditEventType::EntryUpdated { success: false, .. }
+        )));
+    }
+
+    #[test]
+    fn test_usage_split_at_midnight() {
+        use chrono::TimeZone;
+        use shepherd_config::Profile;
+
+        let mut policy = make_test_policy();
+        policy.entries[0].limits.max_run = None;
+        policy.profiles = vec![Profile {
+            id: ProfileId::new("alex"),
+            label: "Alex".into(),
+            weekly_budget: None,
+            budget_alerts: Vec::new(),
+        }];
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store.clone(), HostCapabilities::minimal());
+        let entry_id = EntryId::new("test-game");
+        let alex = ProfileId::new("alex");
+        let start = Local.with_ymd_and_hms(2025, 1, 6, 23, 40, 0).unwrap();
+        let end = Local.with_ymd_and_hms(2025, 1, 7, 0, 30, 0).unwrap();
+        let start_mono = MonotonicInstant::now();
+
+        let LaunchDecision::Approved(plan) =
+            engine.request_joint_launch(&SeatId::default(), &entry_id, vec![alex.clone()], start)
+        else {
+            panic!("launch should be approved");
+        };
+        engine.start_session(plan, start, start_mono);
+        engine.notify_session_exited(Some(0), start_mono + Duration::from_secs(50 * 60), end);
+
+        let (monday, tuesday) = (start.date_naive(), end.date_naive());
+        assert_eq!(store.get_usage(&entry_id, monday).unwrap(), Duration::from_secs(20 * 60));
+        assert_eq!(store.get_usage(&entry_id, tuesday).unwrap(), Duration::from_secs(30 * 60));
+        assert_eq!(store.get_profile_usage(&alex, monday).unwrap(), Duration::from_secs(20 * 60));
+        assert_eq!(store.get_profile_usage(&alex, tuesday).unwrap(), Duration::from_secs(30 * 60));
+
+        // Sessions that end exactly at midnight stay on the day they ran
+        assert_eq!(
+            usage_by_day(
+                Local.with_ymd_and_hms(2025, 1, 7, 0, 0, 0).unwrap(),
+                Duration::from_secs(600)
+            ),
+            vec![(monday, Duration::from_secs(600))]
+        );
+    }
+
+    #[test]
+    fn test_paused_time_is_not_used() {
+        use chrono::TimeZone;
+
+        let mut policy = make_test_policy();
+        policy.entries[0].limits.max_run = None;
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store.clone(), HostCapabilities::minimal());
+        let entry_id = EntryId::new("test-game");
+        let start = Local.with_ymd_and_hms(2025, 1, 6, 23, 0, 0).unwrap();
+        let start_mono = MonotonicInstant::now();
+        let mins = |m: u64| start_mono + Duration::from_secs(m * 60);
+
+        let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, start) else {
+            panic!("launch should be approved");
+        };
+        engine.start_session(plan, start, start_mono);
+
+        // 23:00-23:30 running, paused until 00:20, then 10 more minutes
+        let session = engine.current_session_mut().unwrap();
+        session.pause(mins(30));
+        session.resume(mins(80), start + chrono::Duration::minutes(80));
+        let info = engine.current_session().unwrap().to_session_info(mins(90));
+        assert_eq!(info.elapsed, Duration::from_secs(90 * 60));
+        assert_eq!(info.charged, Duration::from_secs(40 * 60));
+
+        let end = start + chrono::Duration::minutes(90);
+        let Some(CoreEvent::SessionEnded { duration, .. }) = engine.notify_session_exited(Some(0), mins(90), end)
+        else {
+            panic!("session should end");
+        };
+        assert_eq!(duration, Duration::from_secs(40 * 60));
+        assert_eq!(store.get_usage(&entry_id, start.date_naive()).unwrap(), Duration::from_secs(30 * 60));
+        assert_eq!(store.get_usage(&entry_id, end.date_naive()).unwrap(), Duration::from_secs(10 * 60));
+    }
+
+    #[test]
+    fn test_session_summary() {
+        use chrono::TimeZone;
+
+        let mut policy = make_test_policy();
+        policy.entries[0].limits.daily_quota = Some(Duration::from_secs(3600));
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
+        let entry_id = EntryId::new("test-game");
+        let now = Local.with_ymd_and_hms(2025, 1, 6, 15, 0, 0).unwrap();
+        let now_mono = MonotonicInstant::now();
+
+        let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) else {
+            panic!("launch should be approved");
+        };
+        engine.start_session(plan, now, now_mono);
+        engine.current_session_mut().unwrap().mark_warning_issued(60);
+
+        let StopDecision::Stopped(result) =
+            engine.stop_current(SessionEndReason::UserStop, now_mono + Duration::from_secs(20 * 60), now)
+        else {
+            panic!("session should stop");
+        };
+        let summary = result.summary;
+        assert_eq!(summary.entry_id, entry_id);
+        assert_eq!(summary.reason, SessionEndReason::UserStop);
+        assert_eq!(summary.duration, Duration::from_secs(20 * 60));
+        assert_eq!(summary.quota_used, Duration::from_secs(20 * 60));
+        assert_eq!(summary.remaining_today, Some(Duration::from_secs(40 * 60)));
+        assert_eq!(summary.warnings_shown, vec![60]);
+        assert_eq!(summary.next_available, None);
+        assert_eq!(summary.show_for, Duration::from_secs(15));
+
+        // The next session counts against what is left
+        let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) else {
+            panic!("launch should be approved");
+        };
+        engine.start_session(plan, now, now_mono);
+        let Some(CoreEvent::SessionEnded { summary, .. }) =
+            engine.notify_session_exited(Some(1), now_mono + Duration::from_secs(60), now)
+        else {
+            panic!("session should end");
+        };
+        assert_eq!(summary.duration, Duration::from_secs(60));
+        assert_eq!(summary.quota_used, Duration::from_secs(60));
+        assert_eq!(summary.remaining_today, Some(Duration::from_secs(39 * 60)));
+
+        // Using up the quota: back tomorrow
+        let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) else {
+            panic!("launch should be approved");
+        };
+        engine.start_session(plan, now, now_mono);
+        let StopDecision::Stopped(result) =
+            engine.stop_current(SessionEndReason::UserStop, now_mono + Duration::from_secs(39 * 60), now)
+        else {
+            panic!("session should stop");
+        };
+        assert_eq!(result.summary.remaining_today, Some(Duration::ZERO));
+        assert_eq!(
+            result.summary.next_available,
+            Some(Local.with_ymd_and_hms(2025, 1, 7, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_launch_failure_is_not_charged() {
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(make_test_policy(), store.clone(), HostCapabilities::minimal());
+        let entry_id = EntryId::new("test-game");
+        let now = shepherd_util::now();
+        let now_mono = MonotonicInstant::now();
+
+        let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) else {
+            panic!("launch should be approved");
+        };
+        engine.start_session(plan, now, now_mono);
+        let reason = SessionEndReason::LaunchFailed {
+            error: "exited with status 1".into(),
+            diagnostics: None,
+        };
+        assert!(matches!(
+            engine.stop_current(reason, now_mono + Duration::from_secs(2), now),
+            StopDecision::Stopped(_)
+        ));
+        assert_eq!(store.get_usage(&entry_id, now.date_naive()).unwrap(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_joint_session_usage() {
+        use shepherd_config::Profile;
+
+        let mut policy = make_test_policy();
+        policy.service.joint_usage = JointUsage::Split;
+        policy.profiles = ["alex", "sam"]
+            .into_iter()
+            .map(|id| Profile {
+                id: ProfileId::new(id),
+                label: id.into(),
+                weekly_budget: None,
+                budget_alerts: Vec::new(),
+            })
+            .collect();
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store.clone(), HostCapabilities::minimal());
+        let now = shepherd_util::now();
+        let start = MonotonicInstant::now();
+        let entry_id = EntryId::new("test-game");
+        let alex = ProfileId::new("alex");
+        let sam = ProfileId::new("sam");
+
+        let decision =
+            engine.request_joint_launch(&SeatId::default(), &entry_id, vec![alex.clone(), ProfileId::new("kim")], now);
+        assert!(matches!(
+            decision,
+            LaunchDecision::Denied { ref reasons }
+                if matches!(reasons.as_slice(), [ReasonCode::UnknownProfile { .. }])
+        ));
+
+        let LaunchDecision::Approved(plan) =
+            engine.request_joint_launch(&SeatId::default(), &entry_id, vec![alex.clone(), sam.clone()], now)
+        else {
+            panic!("joint launch should be approved");
+        };
+        engine.start_session(plan, now, start);
+        assert_eq!(
+            engine.get_state().current_session.unwrap().participants,
+            vec![alex.clone(), sam.clone()]
+        );
+        engine.notify_session_exited(Some(0), start + Duration::from_secs(600), now);
+
+        // Split evenly; the entry itself is charged once
+        let today = now.date_naive();
+        assert_eq!(store.get_profile_usage(&alex, today).unwrap(), Duration::from_secs(300));
+        assert_eq!(store.get_profile_usage(&sam, today).unwrap(), Duration::from_secs(300));
+        assert_eq!(store.get_usage(&entry_id, today).unwrap(), Duration::from_secs(600));
+    }
+
+    #[test]
+    fn test_usage_refunds() {
+        let mut policy = make_test_policy();
+        policy.entries[0].limits.cooldown = Some(Duration::from_secs(600));
+        policy.entries[0].limits.refund_under = Some(Duration::from_secs(30));
+        policy.entries[0].limits.refund_crash_under = Some(Duration::from_secs(120));
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store.clone(), HostCapabilities::minimal());
+        let now = shepherd_util::now();
+        let entry_id = EntryId::new("test-game");
+        let today = now.date_naive();
+
+        let run = |engine: &mut CoreEngine, secs: u64, exit_code: Option<i32>| {
+            let start = MonotonicInstant::now();
+            let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) else {
+                panic!("launch should be approved");
+            };
+            engine.start_session(plan, now, start);
+            engine.notify_session_exited(exit_code, start + Duration::from_secs(secs), now);
+        };
+
+        // Quit straight away, or crashed shortly after launch: not charged
+        run(&mut engine, 10, Some(0));
+        run(&mut engine, 90, Some(1));
+        run(&mut engine, 90, None);
+        assert_eq!(store.get_usage(&entry_id, today).unwrap(), Duration::ZERO);
+        assert_eq!(store.get_cooldown_until(&CooldownKey::Entry(entry_id.clone())).unwrap(), None);
+
+        let refunds: Vec<_> = store
+            .get_recent_audits(20)
+            .unwrap()
+            .into_iter()
+            .filter_map(|e| match e.event {
+                AuditEventType::UsageRefunded { reason, .. } => Some(reason),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(refunds.len(), 3);
+        assert!(refunds.contains(&RefundReason::ShortSession));
+        assert!(refunds.contains(&RefundReason::Crashed { exit_code: Some(1) }));
+
+        // A clean exit after the short-session window is charged
+        run(&mut engine, 90, Some(0));
+        assert_eq!(store.get_usage(&entry_id, today).unwrap(), Duration::from_secs(90));
+        assert!(store.get_cooldown_until(&CooldownKey::Entry(entry_id.clone())).unwrap().is_some());
+
+        // Expired sessions are always charged
+        let limits = LimitsPolicy {
+            max_run: None,
+            min_run: None,
+            daily_quota: None,
+            cooldown: None,
+            cooldown_scope: Default::default(),
+            cooldown_after: None,
+            min_gap: None,
+            refund_under: Some(Duration::from_secs(30)),
+            refund_crash_under: None,
+        };
+        assert_eq!(
+            refund_reason(&limits, &SessionEndReason::Expired, Duration::from_secs(5)),
+            None
+        );
+        assert_eq!(
+            refund_reason(&limits, &SessionEndReason::UserStop, Duration::from_secs(5)),
+            Some(RefundReason::ShortSession)
+        );
+    }
+
+    #[test]
+    fn test_min_run() {
+        use chrono::TimeZone;
+        use shepherd_util::{DaysOfWeek, TimeWindow, WallClock};
+
+        let mut policy = make_test_policy();
+        policy.entries[0].availability = AvailabilityPolicy {
+            windows: vec![TimeWindow::new(
+                DaysOfWeek::WEEKDAYS,
+                WallClock::new(15, 0).unwrap(),
+                WallClock::new(18, 0).unwrap(),
+            )],
+            always: false,
+        };
+        policy.entries[0].limits.max_run = None;
+        policy.entries[0].limits.daily_quota = Some(Duration::from_secs(3600));
+        policy.entries[0].limits.min_run = Some(Duration::from_secs(600));
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store.clone(), HostCapabilities::minimal());
+        let entry_id = EntryId::new("test-game");
+
+        let not_enough_time = |now| match engine.list_entries(now)[0].reasons.as_slice() {
+            [ReasonCode::NotEnoughTime { available, next_full_session, .. }] => {
+                Some((*available, *next_full_session))
+            }
+            _ => None,
+        };
+
+        // Monday: 15 minutes left in the window is enough, 5 isn't
+        let monday = Local.with_ymd_and_hms(2025, 1, 6, 17, 45, 0).unwrap();
+        assert!(engine.list_entries(monday)[0].enabled);
+        let monday = Local.with_ymd_and_hms(2025, 1, 6, 17, 55, 0).unwrap();
+        let tuesday = Local.with_ymd_and_hms(2025, 1, 7, 15, 0, 0).unwrap();
+        assert_eq!(
+            not_enough_time(monday),
+            Some((Duration::from_secs(300), Some(tuesday)))
+        );
+
+        // Friday evening waits for Monday's window
+        let friday = Local.with_ymd_and_hms(2025, 1, 10, 17, 55, 0).unwrap();
+        let next_monday = Local.with_ymd_and_hms(2025, 1, 13, 15, 0, 0).unwrap();
+        assert_eq!(not_enough_time(friday).unwrap().1, Some(next_monday));
+
+        // Nearly used-up quota waits for tomorrow's
+        let monday = Local.with_ymd_and_hms(2025, 1, 6, 15, 0, 0).unwrap();
+        store
+            .add_usage(&entry_id, monday.date_naive(), Duration::from_secs(3300))
+            .unwrap();
+        engine.reload_store();
+        assert_eq!(
+            not_enough_time(monday),
+            Some((Duration::from_secs(300), Some(tuesday)))
+        );
+        assert!(matches!(
+            engine.request_launch(&entry_id, monday),
+            LaunchDecision::Denied { .. }
+        ));
+    }
+
+    #[test]
+    fn test_launch_confirmation() {
+        let mut policy = make_test_policy();
+        policy.entries[0].confirm_launch = true;
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
+        let entry_id = EntryId::new("test-game");
+        let now = shepherd_util::now();
+
+        let plan = match engine.request_launch(&entry_id, now) {
+            LaunchDecision::NeedsConfirmation(plan) => plan,
+            other => panic!("expected confirmation, got {other:?}"),
+        };
+        assert_eq!(plan.max_duration, Some(Duration::from_secs(300)));
+
+        // Only the plan that was shown can be confirmed, and only once
+        assert!(engine.confirm_launch(&SessionId::new(), now).is_none());
+        let later = now + chrono::Duration::seconds(10);
+        let confirmed = engine.confirm_launch(&plan.session_id, later);
+        assert!(matches!(
+            confirmed,
+            Some(LaunchDecision::Approved(ref p)) if p.session_id == plan.session_id
+                && p.max_duration == Some(Duration::from_secs(300))
+        ));
+        assert!(engine.confirm_launch(&plan.session_id, later).is_none());
+
+        // An answer after the timeout is too late
+        let plan = match engine.request_launch(&entry_id, now) {
+            LaunchDecision::NeedsConfirmation(plan) => plan,
+            other => panic!("expected confirmation, got {other:?}"),
+        };
+        let late = now + chrono::Duration::seconds(LAUNCH_CONFIRM_TIMEOUT.as_secs() as i64 + 1);
+        assert!(engine.confirm_launch(&plan.session_id, late).is_none());
+    }
+
+    #[test]
+    fn test_autostart() {
+        use chrono::TimeZone;
+        use shepherd_config::AutostartRule;
+        use shepherd_util::{DaysOfWeek, WallClock};
+
+        let mut policy = make_test_policy();
+        policy.entries[0].confirm_launch = true;
+        policy.entries[0].autostart = vec![AutostartRule {
+            days: DaysOfWeek::WEEKDAYS,
+            at: WallClock::new(19, 30).unwrap(),
+        }];
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store.clone(), HostCapabilities::minimal());
+        let entry_id = EntryId::new("test-game");
+
+        let mut autostarts_at = |h, m, s, day| {
+            let now = Local.with_ymd_and_hms(2025, 1, day, h, m, s).unwrap();
+            engine
+                .tick(MonotonicInstant::now(), now)
+                .into_iter()
+                .filter(|e| matches!(e, CoreEvent::AutostartDue { .. }))
+                .count()
+        };
+
+        // Monday fires once, at the scheduled minute
+        assert_eq!(autostarts_at(19, 29, 0, 6), 0);
+        assert_eq!(autostarts_at(19, 29, 59, 6), 0);
+        assert_eq!(autostarts_at(19, 30, 0, 6), 1);
+        assert_eq!(autostarts_at(19, 30, 1, 6), 0);
+
+        // Saturday isn't a school night
+        assert_eq!(autostarts_at(19, 29, 0, 11), 0);
+        assert_eq!(autostarts_at(19, 31, 0, 11), 0);
+
+        // Asleep through the time: too late to start
+        assert_eq!(autostarts_at(19, 0, 0, 13), 0);
+        assert_eq!(autostarts_at(19, 40, 0, 13), 0);
+
+        // No one is asked to confirm, and a running session wins
+        let now = Local.with_ymd_and_hms(2025, 1, 14, 19, 30, 0).unwrap();
+        let plan = match engine.request_autostart(&entry_id, now) {
+            LaunchDecision::Approved(plan) => plan,
+            other => panic!("expected approval, got {other:?}"),
+        };
+        engine.start_session(plan, now, MonotonicInstant::now());
+        assert!(matches!(
+            engine.request_autostart(&entry_id, now),
+            LaunchDecision::Denied { .. }
+        ));
+        let launched: Vec<bool> = store
+            .get_recent_audits(10)
+            .unwrap()
+            .into_iter()
+            .filter_map(|a| match a.event {
+                AuditEventType::AutostartTriggered { launched, .. } => Some(launched),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(launched.len(), 2);
+        assert!(launched.contains(&true) && launched.contains(&false));
+    }
+
+    #[test]
+    fn test_launch_hold() {
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(make_test_policy(), store.clone(), HostCapabilities::minimal());
+        let entry_id = EntryId::new("test-game");
+        let now = shepherd_util::now();
+        let friday = now + chrono::Duration::days(3);
+
+        engine.set_hold(Some(Hold {
+            message: Some("Grounded until Friday".into()),
+            until: Some(friday),
+        }));
+        let view = &engine.list_entries(now)[0];
+        assert!(!view.enabled);
+        assert!(matches!(
+            view.reasons.as_slice(),
+            [ReasonCode::OnHold { message: Some(m), until: Some(u) }]
+                if m == "Grounded until Friday" && *u == friday
+        ));
+        assert!(matches!(engine.request_launch(&entry_id, now), LaunchDecision::Denied { .. }));
+        assert!(engine.get_state().hold.is_some());
+
+        // Survives a restart, and lifts by itself once `until` passes
+        let mut engine = CoreEngine::new(make_test_policy(), store, HostCapabilities::minimal());
+        assert!(!engine.list_entries(now)[0].enabled);
+        assert!(engine.list_entries(friday)[0].enabled);
+
+        engine.set_hold(None);
+        assert!(engine.list_entries(now)[0].enabled);
+        assert!(engine.get_state().hold.is_none());
+    }
+
+    #[test]
+    fn test_downtime() {
+        use chrono::TimeZone;
+        use shepherd_config::DowntimePolicy;
+        use shepherd_util::{DaysOfWeek, TimeWindow, WallClock};
+
+        let mut policy = make_test_policy();
+        policy.entries[0].limits.max_run = None;
+        policy.downtime = vec![
+            DowntimePolicy {
+                window: TimeWindow::new(
+                    DaysOfWeek::WEEKDAYS,
+                    WallClock::new(16, 0).unwrap(),
+                    WallClock::new(18, 0).unwrap(),
+                ),
+                label: Some("Homework".into()),
+            },
+            DowntimePolicy {
+                window: TimeWindow::new(
+                    DaysOfWeek::WEEKDAYS,
+                    WallClock::new(18, 0).unwrap(),
+                    WallClock::new(18, 30).unwrap(),
+                ),
+                label: None,
+            },
+        ];
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
+        let entry_id = EntryId::new("test-game");
+        let monday = |h, m| Local.with_ymd_and_hms(2025, 1, 6, h, m, 0).unwrap();
+
+        // Back-to-back blocks report a single end time
+        let view = &engine.list_entries(monday(16, 30))[0];
+        assert!(!view.enabled);
+        assert!(matches!(
+            view.reasons.as_slice(),
+            [ReasonCode::Downtime { label: Some(l), until }]
+                if l == "Homework" && *until == monday(18, 30)
+        ));
+        assert!(matches!(
+            engine.request_launch(&entry_id, monday(16, 30)),
+            LaunchDecision::Denied { .. }
+        ));
+        assert!(engine.list_entries(monday(18, 30))[0].enabled);
+
+        // Sessions started shortly before are cut off when downtime begins
+        assert_eq!(
+            engine.list_entries(monday(15, 50))[0].max_run_if_started_now,
+            Some(Duration::from_secs(600))
+        );
 
-    /// Check if a session is active
-    pub fn has_active_session(&self) -> bool {
-        self.current_session.is_some()
+        // Weekends are free
+        let saturday = Local.with_ymd_and_hms(2025, 1, 11, 16, 30, 0).unwrap();
+        assert!(engine.list_entries(saturday)[0].enabled);
     }
 
-    /// Extend current session (admin action)
-    /// Only works for sessions with a deadline (not unlimited sessions).
-    pub fn extend_current(
-        &mut self,
-        by: Duration,
-        _now_mono: MonotonicInstant,
-        _now: DateTime<Local>,
-    ) -> Option<DateTime<Local>> {
-        let session = self.current_session.as_mut()?;
-
-        // Can't extend unlimited sessions - they don't have a deadline
-        let deadline_mono = session.deadline_mono?;
-        let deadline = session.deadline?;
+    #[test]
+    fn test_calendar_exceptions() {
+        use chrono::TimeZone;
+        use shepherd_util::{DaysOfWeek, TimeWindow, WallClock};
 
-        let new_deadline_mono = deadline_mono + by;
-        let new_deadline = deadline + chrono::Duration::from_std(by).unwrap();
+        let mut policy = make_test_policy();
+        policy.entries[0].limits.max_run = None;
+        policy.entries[0].tags = vec!["movies".into()];
+        policy.entries[0].availability = AvailabilityPolicy {
+            windows: vec![TimeWindow::new(
+                DaysOfWeek::ALL_DAYS,
+                WallClock::new(16, 0).unwrap(),
+                WallClock::new(19, 30).unwrap(),
+            )],
+            always: false,
+        };
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store.clone(), HostCapabilities::minimal());
+        let saturday = |h, m| Local.with_ymd_and_hms(2025, 1, 11, h, m, 0).unwrap();
+        let exception = |uid: &str, action, target: &str, start, end| CalendarException {
+            uid: uid.into(),
+            label: "Movie night".into(),
+            action,
+            targets: vec![target.into()],
+            start,
+            end,
+        };
 
-        session.deadline_mono = Some(new_deadline_mono);
-        session.deadline = Some(new_deadline);
+        engine.set_calendar(vec![
+            exception("movie", CalendarAction::Open, "movies", saturday(19, 0), saturday(21, 0)),
+            exception("dentist", CalendarAction::Close, "*", saturday(17, 0), saturday(18, 0)),
+        ]);
 
-        // Log to audit
-        let _ = self.store.append_audit(AuditEvent::new(AuditEventType::SessionExtended {
-            session_id: session.plan.session_id.clone(),
-            extended_by: by,
-            new_deadline,
-        }));
+        // Open past the window, until the event ends
+        let view = &engine.list_entries(saturday(20, 0))[0];
+        assert!(view.enabled);
+        assert_eq!(view.max_run_if_started_now, Some(Duration::from_secs(3600)));
+        assert_eq!(
+            engine.list_entries(saturday(19, 0))[0].max_run_if_started_now,
+            Some(Duration::from_secs(2 * 3600))
+        );
+        assert!(!engine.list_entries(saturday(21, 0))[0].enabled);
 
-        info!(
-            session_id = %session.plan.session_id,
-            extended_by_secs = by.as_secs(),
-            new_deadline = %new_deadline,
-            "Session extended"
+        // Closed inside the window, and cut off when the close begins
+        let view = &engine.list_entries(saturday(17, 30))[0];
+        assert!(matches!(
+            view.reasons.as_slice(),
+            [ReasonCode::Downtime { label: Some(l), until }]
+                if l == "Movie night" && *until == saturday(18, 0)
+        ));
+        assert_eq!(
+            engine.list_entries(saturday(16, 30))[0].max_run_if_started_now,
+            Some(Duration::from_secs(1800))
         );
 
-        Some(new_deadline)
+        // Each event is audited once when it starts
+        engine.tick(MonotonicInstant::now(), saturday(19, 5));
+        engine.tick(MonotonicInstant::now(), saturday(19, 10));
+        let started = store
+            .get_recent_audits(10)
+            .unwrap()
+            .into_iter()
+            .filter(|a| matches!(a.event, AuditEventType::CalendarExceptionStarted { .. }))
+            .count();
+        assert_eq!(started, 1);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use shepherd_config::{AvailabilityPolicy, Entry, LimitsPolicy};
-    use shepherd_api::EntryKind;
-    use shepherd_store::SqliteStore;
-    use std::collections::HashMap;
+    #[test]
+    fn test_cooldown_scopes() {
+        let mut policy = make_test_policy();
+        policy.entries[0].tags = vec!["games".into()];
+        policy.entries[0].limits.max_run = None;
+        policy.entries[0].limits.cooldown = Some(Duration::from_secs(600));
+        policy.entries[0].limits.cooldown_scope = CooldownScope::Tag { tag: "games".into() };
+        let mut racing = policy.entries[0].clone();
+        racing.id = EntryId::new("racing");
+        racing.limits.cooldown_scope = CooldownScope::Global;
+        racing.limits.cooldown_after = Some(Duration::from_secs(3600));
+        let mut reading = policy.entries[0].clone();
+        reading.id = EntryId::new("reading");
+        reading.tags = vec!["education".into()];
+        reading.limits.cooldown = None;
+        policy.entries.extend([racing, reading]);
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
+        let mut now = shepherd_util::now();
 
-    fn make_test_policy() -> Policy {
-        Policy {
-            service: Default::default(),
-            entries: vec![Entry {
-                id: EntryId::new("test-game"),
-                label: "Test Game".into(),
-                icon_ref: None,
-                kind: EntryKind::Process {
-                    command: "game".into(),
-                    args: vec![],
-                    env: HashMap::new(),
-                    cwd: None,
-                },
-                availability: AvailabilityPolicy {
-                    windows: vec![],
-                    always: true,
-                },
-                limits: LimitsPolicy {
-                    max_run: Some(Duration::from_secs(300)),
-                    daily_quota: None,
-                    cooldown: None,
-                },
-                warnings: vec![],
-                volume: None,
-                disabled: false,
-                disabled_reason: None,
-            }],
-            default_warnings: vec![],
-            default_max_run: Some(Duration::from_secs(3600)),
-            volume: Default::default(),
+        let play = |engine: &mut CoreEngine, id: &str, secs: u64, now| {
+            let start = MonotonicInstant::now();
+            let LaunchDecision::Approved(plan) = engine.request_launch(&EntryId::new(id), now)
+            else {
+                panic!("{} should be available", id);
+            };
+            engine.start_session(plan, now, start);
+            engine.notify_session_exited(Some(0), start + Duration::from_secs(secs), now);
+        };
+        let cooldown_scope = |engine: &CoreEngine, index: usize, now| {
+            engine.list_entries(now)[index].reasons.iter().find_map(|r| match r {
+                ReasonCode::CooldownActive { scope, .. } => Some(scope.clone()),
+                _ => None,
+            })
+        };
+
+        // A tag cooldown pauses every game but not reading
+        play(&mut engine, "test-game", 300, now);
+        let games = CooldownScope::Tag { tag: "games".into() };
+        assert_eq!(cooldown_scope(&engine, 0, now), Some(games.clone()));
+        assert_eq!(cooldown_scope(&engine, 1, now), Some(games));
+        assert_eq!(cooldown_scope(&engine, 2, now), None);
+
+        // The global cooldown only starts once an hour of anything is used
+        now += chrono::Duration::minutes(11);
+        play(&mut engine, "reading", 1800, now);
+        play(&mut engine, "racing", 1200, now);
+        assert_eq!(cooldown_scope(&engine, 2, now), None);
+        play(&mut engine, "racing", 1200, now);
+        for index in 0..3 {
+            assert_eq!(cooldown_scope(&engine, index, now), Some(CooldownScope::Global));
         }
     }
 
     #[test]
-    fn test_list_entries() {
-        let policy = make_test_policy();
+    fn test_retro_quota_per_rom() {
+        use chrono::TimeZone;
+
+        let mut policy = make_test_policy();
+        let retro = |core: &str, rom: &str| EntryKind::Retro {
+            core: core.into(),
+            rom_path: rom.into(),
+        };
+        policy.entries[0].kind = retro("snes9x", "~/roms/mario-world.sfc");
+        policy.entries[0].limits.max_run = None;
+        policy.entries[0].limits.daily_quota = Some(Duration::from_secs(3600));
+        let mut other_core = policy.entries[0].clone();
+        other_core.id = EntryId::new("mario-bsnes");
+        other_core.kind = retro("bsnes", "~/roms/mario-world.sfc");
+        let mut other_rom = policy.entries[0].clone();
+        other_rom.id = EntryId::new("zelda");
+        other_rom.kind = retro("snes9x", "~/roms/zelda.sfc");
+        policy.entries.extend([other_core, other_rom]);
         let store = Arc::new(SqliteStore::in_memory().unwrap());
-        let caps = HostCapabilities::minimal();
-        let engine = CoreEngine::new(policy, store, caps);
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::linux_full());
+        let now = Local.with_ymd_and_hms(2025, 1, 6, 15, 0, 0).unwrap();
 
-        let entries = engine.list_entries(shepherd_util::now());
-        assert_eq!(entries.len(), 1);
-        assert!(entries[0].enabled);
+        let start = MonotonicInstant::now();
+        let LaunchDecision::Approved(plan) = engine.request_launch(&EntryId::new("test-game"), now) else {
+            panic!("Mario should be available");
+        };
+        engine.start_session(plan, now, start);
+        engine.notify_session_exited(Some(0), start + Duration::from_secs(3600), now);
+
+        // Another core for the same ROM doesn't get a fresh hour
+        let entries = engine.list_entries(now);
+        assert!(entries[1]
+            .reasons
+            .iter()
+            .any(|r| matches!(r, ReasonCode::QuotaExhausted { .. })));
+        assert_eq!(entries[2].max_run_if_started_now, Some(Duration::from_secs(3600)));
     }
 
     #[test]
-    fn test_launch_approval() {
-        let policy = make_test_policy();
+    fn test_min_gap_between_sessions() {
+        let mut policy = make_test_policy();
+        policy.entries[0].limits.min_gap = Some(Duration::from_secs(600));
+        policy.entries[0].limits.refund_under = Some(Duration::from_secs(30));
         let store = Arc::new(SqliteStore::in_memory().unwrap());
-        let caps = HostCapabilities::minimal();
-        let engine = CoreEngine::new(policy, store, caps);
-
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
+        let now = shepherd_util::now();
         let entry_id = EntryId::new("test-game");
-        let decision = engine.request_launch(&entry_id, shepherd_util::now());
 
-        assert!(matches!(decision, LaunchDecision::Approved(_)));
+        // A refunded session sets no cooldown but still starts the gap
+        let start = MonotonicInstant::now();
+        let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) else {
+            panic!("launch should be approved");
+        };
+        engine.start_session(plan, now, start);
+        engine.stop_current(SessionEndReason::UserStop, start + Duration::from_secs(10), now);
+
+        let LaunchDecision::Denied { reasons } = engine.request_launch(&entry_id, now) else {
+            panic!("relaunch should be denied");
+        };
+        assert!(matches!(
+            reasons.as_slice(),
+            [ReasonCode::CooldownActive { scope: CooldownScope::Entry, .. }]
+        ));
+
+        let later = now + chrono::Duration::minutes(11);
+        assert!(engine.list_entries(later)[0].enabled);
     }
 
     #[test]
-    fn test_session_blocks_new_launch() {
-        let policy = make_test_policy();
-        let store = Arc::new(SqliteStore::in_memory().unwrap());
-        let caps = HostCapabilities::minimal();
-        let mut engine = CoreEngine::new(policy, store, caps);
+    fn test_time_exchange() {
+        use shepherd_config::TimeExchangePolicy;
 
-        let entry_id = EntryId::new("test-game");
+        let mut policy = make_test_policy();
+        policy.entries[0].tags = vec!["games".into()];
+        policy.entries[0].limits.max_run = None;
+        policy.entries[0].limits.daily_quota = Some(Duration::from_secs(600));
+        let mut reading = policy.entries[0].clone();
+        reading.id = EntryId::new("reading");
+        reading.tags = vec!["education".into()];
+        reading.limits.daily_quota = None;
+        policy.entries.push(reading);
+        policy.time_exchange = Some(TimeExchangePolicy {
+            earn_tag: "education".into(),
+            spend_tag: "games".into(),
+            ratio: 0.5,
+            max_earned: Some(Duration::from_secs(900)),
+        });
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
         let now = shepherd_util::now();
-        let now_mono = MonotonicInstant::now();
+        let start = MonotonicInstant::now();
 
-        // Launch first session
-        if let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) {
-            engine.start_session(plan, now, now_mono);
-        }
+        let read_for = |engine: &mut CoreEngine, secs: u64| {
+            let LaunchDecision::Approved(plan) =
+                engine.request_launch(&EntryId::new("reading"), now)
+            else {
+                panic!("reading should be available");
+            };
+            engine.start_session(plan, now, start);
+            engine.notify_session_exited(Some(0), start + Duration::from_secs(secs), now);
+        };
 
-        // Try to launch again - should be denied
-        let decision = engine.request_launch(&entry_id, now);
-        assert!(matches!(decision, LaunchDecision::Denied { .. }));
+        // 20 minutes of reading earns 10 minutes of game time
+        read_for(&mut engine, 1200);
+        let game = &engine.list_entries(now)[0];
+        assert_eq!(game.time_bank, Some(Duration::from_secs(600)));
+        assert_eq!(game.max_run_if_started_now, Some(Duration::from_secs(1200)));
+
+        // The daily cap limits further credit
+        read_for(&mut engine, 3600);
+        let game = &engine.list_entries(now)[0];
+        assert_eq!(game.time_bank, Some(Duration::from_secs(900)));
+
+        // Reading itself earns nothing for its own tile
+        assert_eq!(engine.list_entries(now)[1].time_bank, None);
     }
 
     #[test]
-    fn test_tick_warnings() {
-        let policy = Policy {
-            entries: vec![Entry {
-                id: EntryId::new("test"),
-                label: "Test".into(),
-                icon_ref: None,
-                kind: EntryKind::Process {
-                    command: "test".into(),
-                    args: vec![],
-                    env: HashMap::new(),
-                    cwd: None,
-                },
-                availability: AvailabilityPolicy {
-                    windows: vec![],
-                    always: true,
-                },
-                limits: LimitsPolicy {
-                    max_run: Some(Duration::from_secs(120)), // 2 minutes
-                    daily_quota: None,
-                    cooldown: None,
-                },
-                warnings: vec![shepherd_api::WarningThreshold {
-                    seconds_before: 60,
-                    severity: WarningSeverity::Warn,
-                    message_template: Some("1 minute left".into()),
-                }],
-                volume: None,
-                disabled: false,
-                disabled_reason: None,
-            }],
-            service: Default::default(),
-            default_warnings: vec![],
-            default_max_run: Some(Duration::from_secs(3600)),
-            volume: Default::default(),
-        };
+    fn test_day_overview() {
+        use chrono::TimeZone;
+        use shepherd_util::{DaysOfWeek, TimeWindow, WallClock};
 
-        let store = Arc::new(SqliteStore::in_memory().unwrap());
-        let caps = HostCapabilities::minimal();
-        let mut engine = CoreEngine::new(policy, store, caps);
+        let window = |start: (u8, u8), end: (u8, u8)| TimeWindow {
+            days: DaysOfWeek::ALL_DAYS,
+            start: WallClock::new(start.0, start.1).unwrap(),
+            end: WallClock::new(end.0, end.1).unwrap(),
+        };
+        let entry = |id: &str, tags: &[&str], windows: Vec<TimeWindow>| {
+            let mut entry = make_test_policy().entries.remove(0);
+            entry.id = EntryId::new(id);
+            entry.label = id.to_uppercase();
+            entry.tags = tags.iter().map(|t| t.to_string()).collect();
+            entry.availability = AvailabilityPolicy {
+                always: windows.is_empty(),
+                windows,
+            };
+            entry
+        };
 
-        let entry_id = EntryId::new("test");
-        let now = shepherd_util::now();
-        let now_mono = MonotonicInstant::now();
+        let mut policy = make_test_policy();
+        policy.entries = vec![
+            entry("drawing", &[], vec![window((20, 0), (2, 0))]),
+            entry("game", &["games"], vec![window((8, 0), (9, 0)), window((15, 0), (18, 0))]),
+            entry("reading", &["education"], vec![]),
+            entry("lego", &["games", "building"], vec![window((15, 0), (18, 0))]),
+        ];
+        policy.entries[1].limits.daily_quota = Some(Duration::from_secs(3600));
+        let mut disabled = entry("broken", &["games"], vec![window((12, 0), (20, 0))]);
+        disabled.disabled = true;
+        policy.entries.push(disabled);
 
-        // Start session
-        if let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) {
-            engine.start_session(plan, now, now_mono);
-        }
+        let now = Local.with_ymd_and_hms(2025, 6, 2, 16, 0, 0).unwrap();
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        store
+            .add_usage(&EntryId::new("game"), now.date_naive(), Duration::from_secs(1200))
+            .unwrap();
+        let engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
 
-        // No warnings initially (first tick may emit AvailabilitySetChanged)
-        let events = engine.tick(now_mono, now);
-        // Filter to just warning events for this test
-        let warning_events: Vec<_> = events.iter().filter(|e| matches!(e, CoreEvent::Warning { .. })).collect();
-        assert!(warning_events.is_empty());
+        let overview = engine.day_overview(now);
+        let names: Vec<_> = overview.categories.iter().map(|c| c.name.as_deref()).collect();
+        assert_eq!(names, [Some("games"), Some("education"), None]);
 
-        // At 70 seconds (10 seconds past warning threshold), warning should fire
-        let later = now_mono + Duration::from_secs(70);
-        let events = engine.tick(later, now);
-        let warning_events: Vec<_> = events.iter().filter(|e| matches!(e, CoreEvent::Warning { .. })).collect();
-        assert_eq!(warning_events.len(), 1);
-        assert!(matches!(warning_events[0], CoreEvent::Warning { threshold_seconds: 60, .. }));
+        let games = &overview.categories[0].entries;
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].used, Duration::from_secs(1200));
+        assert_eq!(games[0].quota, Some(Duration::from_secs(3600)));
+        assert_eq!(games[0].remaining(), Some(Duration::from_secs(2400)));
+        assert_eq!(games[1].entry_id, EntryId::new("lego"));
+        assert_eq!(games[1].remaining(), None);
 
-        // Warning shouldn't fire twice
-        let events = engine.tick(later, now);
-        let warning_events: Vec<_> = events.iter().filter(|e| matches!(e, CoreEvent::Warning { .. })).collect();
-        assert!(warning_events.is_empty());
+        // The morning window is over; the shared afternoon one is listed
+        // once with its real start; the evening one runs past midnight
+        let at = |d, h| Local.with_ymd_and_hms(2025, 6, d, h, 0, 0).unwrap();
+        let windows: Vec<_> = overview
+            .windows
+            .iter()
+            .map(|w| (w.start, w.end, w.labels.clone()))
+            .collect();
+        assert_eq!(
+            windows,
+            [
+                (at(2, 15), at(2, 18), vec!["GAME".to_string(), "LEGO".to_string()]),
+                (at(2, 20), at(3, 2), vec!["DRAWING".to_string()]),
+            ]
+        );
     }
 
     #[test]
-    fn test_session_expiry() {
-        let policy = Policy {
-            entries: vec![Entry {
-                id: EntryId::new("test"),
-                label: "Test".into(),
-                icon_ref: None,
-                kind: EntryKind::Process {
-                    command: "test".into(),
-                    args: vec![],
-                    env: HashMap::new(),
-                    cwd: None,
-                },
-                availability: AvailabilityPolicy {
-                    windows: vec![],
-                    always: true,
+    fn test_reward_unlocks() {
+        use shepherd_config::{RewardThreshold, RewardsPolicy};
+
+        let mut policy = make_test_policy();
+        let entry_id = EntryId::new("test-game");
+        policy.entries[0].limits.max_run = None;
+        policy.entries[0].limits.daily_quota = Some(Duration::from_secs(600));
+        policy.rewards = RewardsPolicy {
+            thresholds: vec![
+                RewardThreshold {
+                    stars: 3,
+                    unlocks: vec![],
+                    bonus: Duration::from_secs(300),
                 },
-                limits: LimitsPolicy {
-                    max_run: Some(Duration::from_secs(60)),
-                    daily_quota: None,
-                    cooldown: None,
+                RewardThreshold {
+                    stars: 5,
+                    unlocks: vec![entry_id.clone()],
+                    bonus: Duration::ZERO,
                 },
-                warnings: vec![],
-                volume: None,
-                disabled: false,
-                disabled_reason: None,
-            }],
-            service: Default::default(),
-            default_warnings: vec![],
-            default_max_run: Some(Duration::from_secs(3600)),
-            volume: Default::default(),
+            ],
         };
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
+        let now = shepherd_util::now();
+
+        let view = &engine.list_entries(now)[0];
+        assert!(!view.enabled);
+        assert!(view.reasons.contains(&ReasonCode::RewardLocked {
+            stars_required: 5,
+            stars: 0,
+        }));
 
+        assert_eq!(engine.grant_reward(3, Some("Homework".into()), now), 3);
+        assert!(!engine.list_entries(now)[0].enabled);
+
+        // Unlocked, with the bonus minutes from the first threshold
+        assert_eq!(engine.grant_reward(2, None, now), 5);
+        let view = &engine.list_entries(now)[0];
+        assert!(view.enabled);
+        assert_eq!(view.max_run_if_started_now, Some(Duration::from_secs(900)));
+        assert_eq!(engine.get_state().total_stars, 5);
+    }
+
+    #[test]
+    fn test_import_usage() {
+        let mut policy = make_test_policy();
+        policy.entries[0].limits.max_run = None;
+        policy.entries[0].limits.daily_quota = Some(Duration::from_secs(3600));
         let store = Arc::new(SqliteStore::in_memory().unwrap());
-        let caps = HostCapabilities::minimal();
-        let mut engine = CoreEngine::new(policy, store, caps);
+        let mut engine = CoreEngine::new(policy, store.clone(), HostCapabilities::minimal());
+        let now = shepherd_util::now();
+        let today = now.date_naive();
+        let entry_id = EntryId::new("test-game");
+        let alex = ProfileId::new("alex");
 
-        let entry_id = EntryId::new("test");
+        // Two rows for the same day add up
+        let usage = [
+            ImportedUsage { entry_id: entry_id.clone(), day: today, duration: Duration::from_secs(1200) },
+            ImportedUsage { entry_id: entry_id.clone(), day: today, duration: Duration::from_secs(600) },
+        ];
+        assert_eq!(engine.import_usage("family-link", Some(&alex), &usage).unwrap(), 2);
+        assert_eq!(store.get_usage(&entry_id, today).unwrap(), Duration::from_secs(1800));
+        assert_eq!(store.get_profile_usage(&alex, today).unwrap(), Duration::from_secs(1800));
+        assert_eq!(
+            engine.list_entries(now)[0].max_run_if_started_now,
+            Some(Duration::from_secs(1800))
+        );
+
+        // The same export again is already there
+        assert_eq!(engine.import_usage("family-link", Some(&alex), &usage).unwrap(), 0);
+        assert_eq!(store.get_usage(&entry_id, today).unwrap(), Duration::from_secs(1800));
+    }
+
+    #[test]
+    fn test_low_battery_policy() {
+        use shepherd_api::BatteryInfo;
+        use shepherd_config::BatteryPolicy;
+
+        let mut policy = make_test_policy();
+        policy.battery = BatteryPolicy {
+            warn_below: 20,
+            stop_below: Some(10),
+        };
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
         let now = shepherd_util::now();
-        let now_mono = MonotonicInstant::now();
+        let entry_id = EntryId::new("test-game");
+
+        let discharging = |percent| BatteryInfo {
+            percent: Some(percent),
+            charging: false,
+            ac_connected: false,
+        };
+
+        // Low but above the stop threshold: warn only
+        let events = engine.update_battery(discharging(15));
+        assert!(matches!(events.as_slice(), [CoreEvent::BatteryStatus { low: true, .. }]));
+        assert!(engine.update_battery(discharging(15)).is_empty());
 
-        // Start session
         if let LaunchDecision::Approved(plan) = engine.request_launch(&entry_id, now) {
-            engine.start_session(plan, now, now_mono);
+            engine.start_session(plan, now, MonotonicInstant::now());
         }
 
-        // At 61 seconds, should be expired
-        let later = now_mono + Duration::from_secs(61);
-        let events = engine.tick(later, now);
-        // Filter to just expiry events for this test
-        let expiry_events: Vec<_> = events.iter().filter(|e| matches!(e, CoreEvent::ExpireDue { .. })).collect();
-        assert_eq!(expiry_events.len(), 1);
-        assert!(matches!(expiry_events[0], CoreEvent::ExpireDue { .. }));
+        // Below the stop threshold: end the session and refuse new launches
+        let events = engine.update_battery(discharging(9));
+        assert!(events.iter().any(|e| matches!(e, CoreEvent::LowBatteryStop { .. })));
+        engine.stop_current(SessionEndReason::LowBattery, MonotonicInstant::now(), now);
+
+        let decision = engine.request_launch(&entry_id, now);
+        assert!(matches!(
+            decision,
+            LaunchDecision::Denied { ref reasons } if reasons.contains(&ReasonCode::LowBattery { percent: 9 })
+        ));
+
+        // Plugging in lifts the restriction
+        engine.update_battery(BatteryInfo {
+            percent: Some(9),
+            charging: true,
+            ac_connected: true,
+        });
+        assert!(matches!(engine.request_launch(&entry_id, now), LaunchDecision::Approved(_)));
     }
 }
@@ -1,8 +1,9 @@
 //! Core events emitted by the engine
 
 use chrono::{DateTime, Local};
-use shepherd_api::{SessionEndReason, WarningSeverity};
-use shepherd_util::{EntryId, SessionId};
+use shepherd_api::{BatteryInfo, DeviceAccess, PendingPowerAction, PowerAction, SessionEndReason, SessionSummary, Theme, WarningSeverity};
+use shepherd_config::{DisplaySettings, QuietHours};
+use shepherd_util::{EntryId, SeatId, SessionId};
 use std::time::Duration;
 
 /// Events emitted by the core engine
@@ -15,6 +16,8 @@ pub enum CoreEvent {
         label: String,
         /// Deadline for session. None means unlimited.
         deadline: Option<DateTime<Local>>,
+        device_access: DeviceAccess,
+        seat: SeatId,
     },
 
     /// The set of available entries has changed (e.g., due to time window boundaries)
@@ -29,17 +32,40 @@ pub enum CoreEvent {
         message: Option<String>,
     },
 
+    /// Time left in the current session, so clients needn't count down on
+    /// their own clocks
+    TimeRemaining {
+        session_id: SessionId,
+        time_remaining: Duration,
+        deadline: Option<DateTime<Local>>,
+    },
+
     /// Session is expiring (termination initiated)
     ExpireDue {
         session_id: SessionId,
     },
 
+    /// An expired session is still running; the host should escalate
+    EnforcementRetry {
+        session_id: SessionId,
+        attempt: u32,
+        step: EnforcementStep,
+    },
+
+    /// An expired session survived every escalation step
+    EnforcementFailed {
+        session_id: SessionId,
+        entry_id: EntryId,
+    },
+
     /// Session has ended
     SessionEnded {
         session_id: SessionId,
         entry_id: EntryId,
+        seat: SeatId,
         reason: SessionEndReason,
         duration: Duration,
+        summary: Box<SessionSummary>,
     },
 
     /// Entry availability changed
@@ -48,8 +74,54 @@ pub enum CoreEvent {
         enabled: bool,
     },
 
+    /// The scheduled display settings changed (e.g., a night-light window started)
+    DisplaySettingsChanged(DisplaySettings),
+
+    /// Quiet hours started or ended
+    QuietHoursChanged(QuietHours),
+
+    /// Host battery status changed
+    BatteryStatus {
+        battery: BatteryInfo,
+        /// Below the warning threshold and discharging
+        low: bool,
+    },
+
+    /// Battery fell below the stop threshold; the session should end
+    LowBatteryStop {
+        session_id: SessionId,
+    },
+
+    /// Idle during curfew; shutdown/suspend countdown started
+    PowerActionScheduled(PendingPowerAction),
+
+    /// Curfew countdown cancelled because a session started or curfew ended
+    PowerActionCancelled,
+
+    /// Curfew countdown elapsed; the host should power off or suspend now
+    PowerActionDue {
+        action: PowerAction,
+    },
+
+    /// An entry's autostart time was reached; the daemon should launch it
+    AutostartDue {
+        entry_id: EntryId,
+    },
+
     /// Policy was reloaded
     PolicyReloaded {
         entry_count: usize,
     },
+
+    /// The reloaded policy picks a different launcher/HUD theme
+    ThemeChanged(Theme),
+}
+
+/// How hard to try stopping an expired session that hasn't exited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnforcementStep {
+    /// SIGKILL the session's process group
+    Force,
+    /// Kill everything in the session's cgroup
+    KillCgroup,
 }
@@ -6,10 +6,13 @@
 //! - Warning and expiry scheduling
 //! - Time enforcement using monotonic time
 
+mod calendar;
 mod engine;
 mod events;
+mod lookups;
 mod session;
 
+pub use calendar::*;
 pub use engine::*;
 pub use events::*;
 pub use session::*;
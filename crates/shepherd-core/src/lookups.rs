@@ -0,0 +1,250 @@
+//! Store reads made while evaluating entries, kept in memory
+//!
+//! Every entry view needs the entry's usage today, its cooldowns and when it
+//! last ended, and each of those is a sqlite query (the last session end
+//! scans the audit log). The engine is the only writer of these values, so
+//! it reads them once and updates them as it writes. Usage and banked time
+//! are kept per day; days other than today are dropped at midnight.
+//!
+//! Failed reads aren't cached, and a failed write drops the value so it is
+//! read again.
+
+use chrono::{DateTime, Local, NaiveDate};
+use shepherd_store::{CooldownKey, Store, StoreResult};
+use shepherd_util::EntryId;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Debug, Default)]
+struct Cached {
+    usage: HashMap<(EntryId, NaiveDate), Duration>,
+    time_bank: HashMap<(String, NaiveDate), Duration>,
+    cooldowns: HashMap<CooldownKey, Option<DateTime<Local>>>,
+    last_session_end: HashMap<EntryId, Option<DateTime<Local>>>,
+    total_stars: Option<u32>,
+    /// Day of the last roll over
+    today: Option<NaiveDate>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Lookups {
+    cached: Mutex<Cached>,
+}
+
+/// Return the cached value for `key`, or read and cache it
+fn read_through<K: Hash + Eq, V: Clone>(
+    map: &mut HashMap<K, V>,
+    key: K,
+    read: impl FnOnce() -> StoreResult<V>,
+) -> StoreResult<V> {
+    if let Some(value) = map.get(&key) {
+        return Ok(value.clone());
+    }
+    let value = read()?;
+    map.insert(key, value.clone());
+    Ok(value)
+}
+
+impl Lookups {
+    fn cached(&self) -> std::sync::MutexGuard<'_, Cached> {
+        self.cached.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Drop everything, after the store was written around the engine
+    pub fn clear(&self) {
+        *self.cached() = Cached::default();
+    }
+
+    /// Drop usage and banked time of days other than `today`, once the day
+    /// changes
+    pub fn roll_over(&self, today: NaiveDate) {
+        let mut cached = self.cached();
+        if cached.today.replace(today) == Some(today) {
+            return;
+        }
+        cached.usage.retain(|(_, day), _| *day == today);
+        cached.time_bank.retain(|(_, day), _| *day == today);
+    }
+
+    pub fn usage(&self, store: &dyn Store, entry_id: &EntryId, day: NaiveDate) -> StoreResult<Duration> {
+        read_through(&mut self.cached().usage, (entry_id.clone(), day), || {
+            store.get_usage(entry_id, day)
+        })
+    }
+
+    pub fn add_usage(
+        &self,
+        store: &dyn Store,
+        entry_id: &EntryId,
+        day: NaiveDate,
+        duration: Duration,
+    ) -> StoreResult<()> {
+        let mut cached = self.cached();
+        let key = (entry_id.clone(), day);
+        match store.add_usage(entry_id, day, duration) {
+            Ok(()) => {
+                if let Some(used) = cached.usage.get_mut(&key) {
+                    *used += duration;
+                }
+                Ok(())
+            }
+            Err(e) => {
+                cached.usage.remove(&key);
+                Err(e)
+            }
+        }
+    }
+
+    pub fn time_bank(&self, store: &dyn Store, tag: &str, day: NaiveDate) -> StoreResult<Duration> {
+        read_through(&mut self.cached().time_bank, (tag.to_string(), day), || {
+            store.get_time_bank(tag, day)
+        })
+    }
+
+    pub fn add_time_bank(
+        &self,
+        store: &dyn Store,
+        tag: &str,
+        day: NaiveDate,
+        duration: Duration,
+    ) -> StoreResult<()> {
+        let mut cached = self.cached();
+        let key = (tag.to_string(), day);
+        match store.add_time_bank(tag, day, duration) {
+            Ok(()) => {
+                if let Some(banked) = cached.time_bank.get_mut(&key) {
+                    *banked += duration;
+                }
+                Ok(())
+            }
+            Err(e) => {
+                cached.time_bank.remove(&key);
+                Err(e)
+            }
+        }
+    }
+
+    pub fn cooldown_until(&self, store: &dyn Store, key: &CooldownKey) -> StoreResult<Option<DateTime<Local>>> {
+        read_through(&mut self.cached().cooldowns, key.clone(), || {
+            store.get_cooldown_until(key)
+        })
+    }
+
+    pub fn set_cooldown_until(
+        &self,
+        store: &dyn Store,
+        key: &CooldownKey,
+        until: DateTime<Local>,
+    ) -> StoreResult<()> {
+        let mut cached = self.cached();
+        let result = store.set_cooldown_until(key, until);
+        match result {
+            Ok(()) => cached.cooldowns.insert(key.clone(), Some(until)),
+            Err(_) => cached.cooldowns.remove(key),
+        };
+        result
+    }
+
+    pub fn last_session_end(&self, store: &dyn Store, entry_id: &EntryId) -> StoreResult<Option<DateTime<Local>>> {
+        read_through(&mut self.cached().last_session_end, entry_id.clone(), || {
+            store.get_last_session_end(entry_id)
+        })
+    }
+
+    /// A session of `entry_id` ended; its end is read from the audit log
+    /// next time
+    pub fn session_ended(&self, entry_id: &EntryId) {
+        self.cached().last_session_end.remove(entry_id);
+    }
+
+    pub fn total_stars(&self, store: &dyn Store) -> StoreResult<u32> {
+        let mut cached = self.cached();
+        if let Some(stars) = cached.total_stars {
+            return Ok(stars);
+        }
+        let stars = store.get_total_stars()?;
+        cached.total_stars = Some(stars);
+        Ok(stars)
+    }
+
+    /// Stars were granted
+    pub fn rewards_changed(&self) {
+        self.cached().total_stars = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shepherd_store::SqliteStore;
+
+    fn day() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 6, 2).unwrap()
+    }
+
+    #[test]
+    fn test_writes_update_cached_usage() {
+        let store = SqliteStore::in_memory().unwrap();
+        let lookups = Lookups::default();
+        let entry_id = EntryId::new("game");
+
+        assert_eq!(lookups.usage(&store, &entry_id, day()).unwrap(), Duration::ZERO);
+        lookups
+            .add_usage(&store, &entry_id, day(), Duration::from_secs(90))
+            .unwrap();
+        assert_eq!(lookups.usage(&store, &entry_id, day()).unwrap(), Duration::from_secs(90));
+
+        // Served from memory: a write around the cache isn't seen until cleared
+        store.add_usage(&entry_id, day(), Duration::from_secs(10)).unwrap();
+        assert_eq!(lookups.usage(&store, &entry_id, day()).unwrap(), Duration::from_secs(90));
+        lookups.clear();
+        assert_eq!(lookups.usage(&store, &entry_id, day()).unwrap(), Duration::from_secs(100));
+    }
+
+    #[test]
+    fn test_roll_over_drops_other_days() {
+        let store = SqliteStore::in_memory().unwrap();
+        let lookups = Lookups::default();
+        let entry_id = EntryId::new("game");
+        let tomorrow = day().succ_opt().unwrap();
+
+        lookups.usage(&store, &entry_id, day()).unwrap();
+        lookups.usage(&store, &entry_id, tomorrow).unwrap();
+        store.add_usage(&entry_id, day(), Duration::from_secs(10)).unwrap();
+        store.add_usage(&entry_id, tomorrow, Duration::from_secs(10)).unwrap();
+
+        lookups.roll_over(tomorrow);
+        assert_eq!(lookups.usage(&store, &entry_id, day()).unwrap(), Duration::from_secs(10));
+        assert_eq!(lookups.usage(&store, &entry_id, tomorrow).unwrap(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_cooldowns_and_session_ends() {
+        let store = SqliteStore::in_memory().unwrap();
+        let lookups = Lookups::default();
+        let key = CooldownKey::Tag("games".into());
+        let until = shepherd_util::now() + chrono::Duration::minutes(5);
+
+        assert_eq!(lookups.cooldown_until(&store, &key).unwrap(), None);
+        lookups.set_cooldown_until(&store, &key, until).unwrap();
+        assert_eq!(lookups.cooldown_until(&store, &key).unwrap(), Some(until));
+        assert_eq!(store.get_cooldown_until(&key).unwrap().map(|t| t.timestamp()), Some(until.timestamp()));
+
+        let entry_id = EntryId::new("game");
+        assert_eq!(lookups.last_session_end(&store, &entry_id).unwrap(), None);
+        store
+            .append_audit(shepherd_store::AuditEvent::new(
+                shepherd_store::AuditEventType::SessionEnded {
+                    session_id: shepherd_util::SessionId::new(),
+                    entry_id: entry_id.clone(),
+                    reason: shepherd_api::SessionEndReason::UserStop,
+                    duration: Duration::from_secs(60),
+                },
+            ))
+            .unwrap();
+        lookups.session_ended(&entry_id);
+        assert!(lookups.last_session_end(&store, &entry_id).unwrap().is_some());
+    }
+}
@@ -1,9 +1,9 @@
 //! Session state machine
 
 use chrono::{DateTime, Local};
-use shepherd_api::{SessionEndReason, SessionState, WarningThreshold};
+use shepherd_api::{DeviceAccess, SessionEndReason, SessionState, SessionSummary, WarningThreshold};
 use shepherd_host_api::HostSessionHandle;
-use shepherd_util::{EntryId, MonotonicInstant, SessionId};
+use shepherd_util::{EntryId, MonotonicInstant, ProfileId, SeatId, SessionId};
 use std::time::Duration;
 
 /// Session plan computed at launch approval
@@ -15,29 +15,56 @@ pub struct SessionPlan {
     /// Maximum duration for this session. None means unlimited.
     pub max_duration: Option<Duration>,
     pub warnings: Vec<WarningThreshold>,
+    /// Camera/microphone access granted to the session
+    pub device_access: DeviceAccess,
+    /// Profiles sharing a joint session. Empty for a normal session.
+    pub participants: Vec<ProfileId>,
+    /// Seat the session runs on
+    pub seat: SeatId,
 }
 
 impl SessionPlan {
     /// Compute warning times (as durations after start)
     /// Returns empty vec for unlimited sessions.
     pub fn warning_times(&self) -> Vec<(u64, Duration)> {
-        let max_duration = match self.max_duration {
-            Some(d) => d,
-            None => return Vec::new(), // No warnings for unlimited sessions
-        };
+        match self.max_duration {
+            Some(max_duration) => self.warning_times_within(max_duration),
+            None => Vec::new(), // No warnings for unlimited sessions
+        }
+    }
+
+    /// Warning times for a session allowed to run `allowance`
+    fn warning_times_within(&self, allowance: Duration) -> Vec<(u64, Duration)> {
         self.warnings
             .iter()
-            .filter(|w| Duration::from_secs(w.seconds_before) < max_duration)
+            .filter(|w| Duration::from_secs(w.seconds_before) < allowance)
             .map(|w| {
-                let trigger_after =
-                    max_duration - Duration::from_secs(w.seconds_before);
+                let trigger_after = allowance - Duration::from_secs(w.seconds_before);
                 (w.seconds_before, trigger_after)
             })
             .collect()
     }
 }
 
+/// A stretch of time the session ran, and was charged for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunSegment {
+    pub started: MonotonicInstant,
+    /// None while the segment is still running
+    pub ended: Option<MonotonicInstant>,
+}
+
+impl RunSegment {
+    fn length(&self, now_mono: MonotonicInstant) -> Duration {
+        self.ended.unwrap_or(now_mono).duration_since(self.started)
+    }
+}
+
 /// Active session tracking
+///
+/// A session is charged only for its run segments. Pausing it ends the
+/// current segment and resuming starts a new one, so time spent paused
+/// (or idle, or suspended) counts toward neither its limit nor usage.
 #[derive(Debug)]
 pub struct ActiveSession {
     /// Session plan
@@ -52,17 +79,32 @@ pub struct ActiveSession {
     /// Monotonic start time (for enforcement)
     pub started_at_mono: MonotonicInstant,
 
-    /// Wall-clock deadline (for display). None means unlimited.
+    /// Wall-clock deadline (for display), as of the last resume or
+    /// extension. None means unlimited.
     pub deadline: Option<DateTime<Local>>,
 
-    /// Monotonic deadline (for enforcement). None means unlimited.
-    pub deadline_mono: Option<MonotonicInstant>,
+    /// Time the session may be charged for: the plan's maximum plus any
+    /// extensions. None means unlimited.
+    pub allowance: Option<Duration>,
+
+    /// Segments the session has run, oldest first. Only the last can be
+    /// open.
+    pub segments: Vec<RunSegment>,
 
     /// Warning thresholds already issued (seconds before expiry)
     pub warnings_issued: Vec<u64>,
 
     /// Host session handle (for stopping)
     pub host_handle: Option<HostSessionHandle>,
+
+    /// When the last attempt to stop the expired session was made
+    pub last_enforcement: Option<MonotonicInstant>,
+
+    /// Escalation steps taken since the session expired
+    pub enforcement_attempts: u32,
+
+    /// When clients were last told the time remaining
+    pub last_time_sync: Option<MonotonicInstant>,
 }
 
 impl ActiveSession {
@@ -72,24 +114,26 @@ impl ActiveSession {
         now: DateTime<Local>,
         now_mono: MonotonicInstant,
     ) -> Self {
-        let (deadline, deadline_mono) = match plan.max_duration {
-            Some(max_dur) => {
-                let deadline = now + chrono::Duration::from_std(max_dur).unwrap();
-                let deadline_mono = now_mono + max_dur;
-                (Some(deadline), Some(deadline_mono))
-            }
-            None => (None, None), // Unlimited session
-        };
+        let deadline = plan
+            .max_duration
+            .map(|max_dur| now + chrono::Duration::from_std(max_dur).unwrap());
 
         Self {
+            allowance: plan.max_duration,
             plan,
             state: SessionState::Launching,
             started_at: now,
             started_at_mono: now_mono,
             deadline,
-            deadline_mono,
+            segments: vec![RunSegment {
+                started: now_mono,
+                ended: None,
+            }],
             warnings_issued: Vec::new(),
             host_handle: None,
+            last_enforcement: None,
+            enforcement_attempts: 0,
+            last_time_sync: None,
         }
     }
 
@@ -99,19 +143,104 @@ impl ActiveSession {
         self.state = SessionState::Running;
     }
 
+    /// Whether the session is in a run segment, rather than paused
+    pub fn is_running(&self) -> bool {
+        self.segments.last().is_some_and(|s| s.ended.is_none())
+    }
+
+    /// Stop charging the session. Returns false if it was already paused.
+    pub fn pause(&mut self, now_mono: MonotonicInstant) -> bool {
+        match self.segments.last_mut() {
+            Some(segment) if segment.ended.is_none() => {
+                segment.ended = Some(now_mono);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Start charging the session again, moving the displayed deadline out
+    /// by the time spent paused. Returns false if it was already running.
+    pub fn resume(&mut self, now_mono: MonotonicInstant, now: DateTime<Local>) -> bool {
+        if self.is_running() {
+            return false;
+        }
+        self.segments.push(RunSegment {
+            started: now_mono,
+            ended: None,
+        });
+        self.deadline = self
+            .time_remaining(now_mono)
+            .map(|remaining| now + chrono::Duration::from_std(remaining).unwrap());
+        true
+    }
+
+    /// Give the session `by` more time. Returns the new deadline, or None
+    /// for unlimited sessions.
+    pub fn extend(&mut self, by: Duration) -> Option<DateTime<Local>> {
+        let allowance = self.allowance.as_mut()?;
+        let deadline = self.deadline.as_mut()?;
+        *allowance += by;
+        *deadline += chrono::Duration::from_std(by).unwrap();
+        Some(*deadline)
+    }
+
+    /// Time since the session started, including time spent paused
+    pub fn elapsed(&self, now_mono: MonotonicInstant) -> Duration {
+        now_mono.duration_since(self.started_at_mono)
+    }
+
+    /// Time the session has been charged for: the sum of its run segments.
+    /// This is what counts against its limit and toward usage.
+    pub fn charged(&self, now_mono: MonotonicInstant) -> Duration {
+        self.segments.iter().map(|s| s.length(now_mono)).sum()
+    }
+
+    /// When the running session will have been charged `target` in total.
+    /// None while paused.
+    fn when_charged(&self, target: Duration) -> Option<MonotonicInstant> {
+        let (open, closed) = self.segments.split_last()?;
+        if open.ended.is_some() {
+            return None;
+        }
+        let charged: Duration = closed.iter().map(|s| s.length(open.started)).sum();
+        Some(open.started + target.saturating_sub(charged))
+    }
+
+    /// Monotonic deadline (for enforcement). None for unlimited sessions
+    /// and while paused.
+    pub fn deadline_mono(&self) -> Option<MonotonicInstant> {
+        self.when_charged(self.allowance?)
+    }
+
     /// Get time remaining using monotonic time. None means unlimited.
     pub fn time_remaining(&self, now_mono: MonotonicInstant) -> Option<Duration> {
-        self.deadline_mono.map(|deadline| deadline.saturating_duration_until(now_mono))
+        self.allowance
+            .map(|allowance| allowance.saturating_sub(self.charged(now_mono)))
     }
 
     /// Check if session is expired (never true for unlimited sessions)
     pub fn is_expired(&self, now_mono: MonotonicInstant) -> bool {
-        match self.deadline_mono {
-            Some(deadline) => now_mono >= deadline,
-            None => false, // Unlimited sessions never expire
+        self.time_remaining(now_mono).is_some_and(|r| r.is_zero())
+    }
+
+    /// Warning thresholds with the charged time each fires at
+    pub fn warning_times(&self) -> Vec<(u64, Duration)> {
+        match self.allowance {
+            Some(allowance) => self.plan.warning_times_within(allowance),
+            None => Vec::new(),
         }
     }
 
+    /// When each warning not yet issued is due. Empty while paused.
+    pub fn upcoming_warnings(&self) -> Vec<(u64, MonotonicInstant)> {
+        self.warning_times()
+            .into_iter()
+            .filter(|(threshold, _)| !self.warnings_issued.contains(threshold))
+            .filter_map(|(threshold, after)| Some((threshold, self.when_charged(after)?)))
+            .collect()
+    }
+
     /// Get pending warnings (not yet issued) that should fire now.
     /// Returns empty vec for unlimited sessions (no warnings to issue).
     pub fn pending_warnings(&self, now_mono: MonotonicInstant) -> Vec<(u64, Duration)> {
@@ -121,14 +250,13 @@ impl ActiveSession {
             None => return Vec::new(),
         };
 
-        let elapsed = now_mono.duration_since(self.started_at_mono);
+        let charged = self.charged(now_mono);
 
-        self.plan
-            .warning_times()
+        self.warning_times()
             .into_iter()
             .filter(|(threshold, trigger_after)| {
-                // Should trigger if elapsed >= trigger_after and not already issued
-                elapsed >= *trigger_after && !self.warnings_issued.contains(threshold)
+                // Should trigger if charged >= trigger_after and not already issued
+                charged >= *trigger_after && !self.warnings_issued.contains(threshold)
             })
             .map(|(threshold, _)| (threshold, remaining))
             .collect()
@@ -146,8 +274,9 @@ impl ActiveSession {
     }
 
     /// Mark session as expiring
-    pub fn mark_expiring(&mut self) {
+    pub fn mark_expiring(&mut self, now_mono: MonotonicInstant) {
         self.state = SessionState::Expiring;
+        self.last_enforcement = Some(now_mono);
     }
 
     /// Mark session as ended
@@ -155,11 +284,6 @@ impl ActiveSession {
         self.state = SessionState::Ended;
     }
 
-    /// Get session duration so far
-    pub fn duration_so_far(&self, now_mono: MonotonicInstant) -> Duration {
-        now_mono.duration_since(self.started_at_mono)
-    }
-
     /// Get session info for API
     pub fn to_session_info(&self, now_mono: MonotonicInstant) -> shepherd_api::SessionInfo {
         shepherd_api::SessionInfo {
@@ -170,7 +294,12 @@ impl ActiveSession {
             started_at: self.started_at,
             deadline: self.deadline,
             time_remaining: self.time_remaining(now_mono),
+            elapsed: self.elapsed(now_mono),
+            charged: self.charged(now_mono),
             warnings_issued: self.warnings_issued.clone(),
+            device_access: self.plan.device_access,
+            participants: self.plan.participants.clone(),
+            seat: self.plan.seat.clone(),
         }
     }
 }
@@ -180,8 +309,10 @@ impl ActiveSession {
 pub struct StopResult {
     pub session_id: SessionId,
     pub entry_id: EntryId,
+    pub seat: SeatId,
     pub reason: SessionEndReason,
     pub duration: Duration,
+    pub summary: Box<SessionSummary>,
 }
 
 #[cfg(test)]
@@ -194,6 +325,9 @@ mod tests {
             session_id: SessionId::new(),
             entry_id: EntryId::new("test"),
             label: "Test".into(),
+            device_access: DeviceAccess::default(),
+            participants: vec![],
+            seat: SeatId::default(),
             max_duration: Some(Duration::from_secs(duration_secs)),
             warnings: vec![
                 WarningThreshold {
@@ -246,6 +380,9 @@ mod tests {
             session_id: SessionId::new(),
             entry_id: EntryId::new("test"),
             label: "Test".into(),
+            device_access: DeviceAccess::default(),
+            participants: vec![],
+            seat: SeatId::default(),
             max_duration: Some(Duration::from_secs(30)), // 30 seconds
             warnings: vec![WarningThreshold {
                 seconds_before: 60, // 60 second warning - longer than session!
@@ -281,4 +418,57 @@ mod tests {
         let pending = session.pending_warnings(later);
         assert!(pending.is_empty());
     }
+
+    #[test]
+    fn test_paused_time_is_not_charged() {
+        let plan = make_test_plan(300);
+        let now = shepherd_util::now();
+        let start = MonotonicInstant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        let mut session = ActiveSession::new(plan, now, start);
+        assert_eq!(session.deadline_mono(), Some(at(300)));
+
+        // Run 100s, pause for 1000s, then run again
+        assert!(session.pause(at(100)));
+        assert!(!session.pause(at(200)));
+        assert!(session.deadline_mono().is_none());
+        assert!(session.upcoming_warnings().is_empty());
+        assert!(!session.is_expired(at(1100)));
+        assert_eq!(session.time_remaining(at(1100)), Some(Duration::from_secs(200)));
+
+        assert!(session.resume(at(1100), now + chrono::Duration::seconds(1100)));
+        assert!(!session.resume(at(1150), now));
+        assert_eq!(session.deadline_mono(), Some(at(1300)));
+        assert_eq!(session.deadline, Some(now + chrono::Duration::seconds(1300)));
+        assert_eq!(session.upcoming_warnings(), vec![(60, at(1240)), (10, at(1290))]);
+
+        assert_eq!(session.elapsed(at(1200)), Duration::from_secs(1200));
+        assert_eq!(session.charged(at(1200)), Duration::from_secs(200));
+        assert!(session.pending_warnings(at(1200)).is_empty());
+        assert_eq!(session.pending_warnings(at(1250))[0].0, 60);
+        assert!(session.is_expired(at(1300)));
+    }
+
+    #[test]
+    fn test_extend_moves_warnings() {
+        let plan = make_test_plan(300);
+        let now = shepherd_util::now();
+        let start = MonotonicInstant::now();
+
+        let mut session = ActiveSession::new(plan, now, start);
+        let deadline = session.extend(Duration::from_secs(120));
+        assert_eq!(deadline, Some(now + chrono::Duration::seconds(420)));
+        assert_eq!(session.deadline_mono(), Some(start + Duration::from_secs(420)));
+
+        // The 60s warning follows the new deadline
+        assert!(session.pending_warnings(start + Duration::from_secs(250)).is_empty());
+        assert_eq!(session.pending_warnings(start + Duration::from_secs(360)).len(), 1);
+
+        let mut plan = make_test_plan(300);
+        plan.max_duration = None;
+        let mut unlimited = ActiveSession::new(plan, now, start);
+        assert!(unlimited.extend(Duration::from_secs(120)).is_none());
+        assert_eq!(unlimited.time_remaining(start + Duration::from_secs(60)), None);
+    }
 }
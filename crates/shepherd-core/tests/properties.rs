@@ -0,0 +1,354 @@
+//! Property tests: random policies driven through random sequences of
+//! launches, stops, ticks, wall-clock jumps and suspends. After every step
+//! the engine must hold to what the daemon relies on:
+//!
+//! - each warning of a session fires exactly once, never before its time and
+//!   no later than the first tick after it
+//! - `ExpireDue` fires once, never before the monotonic deadline and no
+//!   later than the first tick after it; unlimited sessions never expire
+//! - an approved launch matches what `list_entries` showed at that moment
+//! - the usage charged for a session is its monotonic run time
+//! - `next_tick` never sleeps past a warning or the expiry
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use proptest::prelude::*;
+use shepherd_api::SessionEndReason;
+use shepherd_core::{CoreEngine, CoreEvent, LaunchDecision, StopDecision};
+use shepherd_host_api::{HostCapabilities, HostHandlePayload, HostSessionHandle};
+use shepherd_store::{SqliteStore, Store};
+use shepherd_util::{EntryId, MonotonicInstant};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+struct EntrySpec {
+    /// Zero is unlimited
+    max_run: u64,
+    daily_quota: Option<u64>,
+    warnings: BTreeSet<u64>,
+    /// Start and end, in minutes after midnight
+    windows: Vec<(u32, u32)>,
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    /// Time passes on both clocks
+    Tick(u64),
+    /// The wall clock is set forward or back by this many minutes
+    JumpWall(i64),
+    /// The machine sleeps: the wall clock moves on, monotonic time doesn't
+    Suspend(u64),
+    Launch(usize),
+    Stop,
+}
+
+fn entry_spec() -> impl Strategy<Value = EntrySpec> {
+    let max_run = prop_oneof![1 => Just(0u64), 4 => 60u64..=3600];
+    max_run
+        .prop_flat_map(|max_run| {
+            let warnings = if max_run == 0 {
+                Just(BTreeSet::new()).boxed()
+            } else {
+                proptest::collection::btree_set(1..max_run, 0..4).boxed()
+            };
+            let window = (0u32..1380).prop_flat_map(|start| (Just(start), start + 1..=1439));
+            (
+                Just(max_run),
+                proptest::option::of(60u64..=14_400),
+                warnings,
+                proptest::collection::vec(window, 0..3),
+            )
+        })
+        .prop_map(|(max_run, daily_quota, warnings, windows)| EntrySpec {
+            max_run,
+            daily_quota,
+            warnings,
+            windows,
+        })
+}
+
+fn step(entries: usize) -> impl Strategy<Value = Step> {
+    prop_oneof![
+        6 => (1u64..=30).prop_map(Step::Tick),
+        4 => (31u64..=900).prop_map(Step::Tick),
+        1 => (-180i64..=180).prop_map(Step::JumpWall),
+        1 => (1u64..=600).prop_map(Step::Suspend),
+        3 => (0..entries).prop_map(Step::Launch),
+        1 => Just(Step::Stop),
+    ]
+}
+
+fn scenario() -> impl Strategy<Value = (Vec<EntrySpec>, DateTime<Local>, Vec<Step>)> {
+    proptest::collection::vec(entry_spec(), 1..4).prop_flat_map(|entries| {
+        let count = entries.len();
+        (
+            Just(entries),
+            // Any minute of 2025
+            (0i64..525_600).prop_map(|minute| {
+                Local.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap() + chrono::Duration::minutes(minute)
+            }),
+            proptest::collection::vec(step(count), 1..120),
+        )
+    })
+}
+
+fn hhmm(minutes: u32) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+fn config(entries: &[EntrySpec]) -> String {
+    let mut toml = String::from("config_version = 1\n");
+    for (i, entry) in entries.iter().enumerate() {
+        let warnings: Vec<String> = entry
+            .warnings
+            .iter()
+            .map(|seconds| format!("{{ seconds_before = {} }}", seconds))
+            .collect();
+        let quota = entry
+            .daily_quota
+            .map(|quota| format!(", daily_quota_seconds = {}", quota))
+            .unwrap_or_default();
+        write!(
+            toml,
+            "\n[[entries]]\nid = \"e{i}\"\nlabel = \"Entry {i}\"\n\
+             kind = {{ type = \"process\", command = \"/usr/bin/game\" }}\n\
+             warnings = [{}]\nlimits = {{ max_run_seconds = {}{} }}\n",
+            warnings.join(", "),
+            entry.max_run,
+            quota,
+        )
+        .unwrap();
+        if !entry.windows.is_empty() {
+            let windows: Vec<String> = entry
+                .windows
+                .iter()
+                .map(|(start, end)| {
+                    format!("{{ days = \"all\", start = \"{}\", end = \"{}\" }}", hhmm(*start), hhmm(*end))
+                })
+                .collect();
+            writeln!(toml, "availability = {{ windows = [{}] }}", windows.join(", ")).unwrap();
+        }
+    }
+    toml
+}
+
+/// The running session as the test saw it start
+struct Running {
+    entry_id: EntryId,
+    started_mono: MonotonicInstant,
+    deadline_mono: Option<MonotonicInstant>,
+    /// (threshold, seconds after start it's due)
+    warnings: Vec<(u64, Duration)>,
+    fired: Vec<u64>,
+    expire_due: bool,
+}
+
+struct Harness {
+    engine: CoreEngine,
+    store: Arc<SqliteStore>,
+    wall: DateTime<Local>,
+    mono: MonotonicInstant,
+    /// Days the wall clock has shown, for summing usage
+    first_day: NaiveDate,
+    last_day: NaiveDate,
+    running: Option<Running>,
+}
+
+impl Harness {
+    fn new(entries: &[EntrySpec], start: DateTime<Local>) -> Self {
+        let policy = shepherd_config::parse_config(&config(entries)).unwrap();
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let engine = CoreEngine::new(policy, store.clone(), HostCapabilities::minimal());
+        Self {
+            engine,
+            store,
+            wall: start,
+            mono: MonotonicInstant::now(),
+            first_day: start.date_naive(),
+            last_day: start.date_naive(),
+            running: None,
+        }
+    }
+
+    fn set_wall(&mut self, wall: DateTime<Local>) {
+        self.wall = wall;
+        self.first_day = self.first_day.min(wall.date_naive());
+        self.last_day = self.last_day.max(wall.date_naive());
+    }
+
+    fn total_usage(&self, entry_id: &EntryId) -> u64 {
+        // Usage of a session is split over the days before it ended
+        let first = self.first_day - chrono::Days::new(1);
+        first
+            .iter_days()
+            .take_while(|day| *day <= self.last_day)
+            .map(|day| self.store.get_usage(entry_id, day).unwrap().as_secs())
+            .sum()
+    }
+
+    fn run(&mut self, step: &Step) -> Result<(), TestCaseError> {
+        match *step {
+            Step::Tick(secs) => {
+                self.mono = self.mono + Duration::from_secs(secs);
+                self.set_wall(self.wall + chrono::Duration::seconds(secs as i64));
+                self.tick()?;
+            }
+            Step::JumpWall(minutes) => {
+                self.set_wall(self.wall + chrono::Duration::minutes(minutes));
+                self.tick()?;
+            }
+            Step::Suspend(minutes) => {
+                self.set_wall(self.wall + chrono::Duration::minutes(minutes as i64));
+                self.tick()?;
+            }
+            Step::Launch(entry) => self.launch(entry)?,
+            Step::Stop => {
+                if self.running.is_some() {
+                    self.end(false)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn launch(&mut self, entry: usize) -> Result<(), TestCaseError> {
+        if self.running.is_some() {
+            return Ok(());
+        }
+        let entry_id = EntryId::new(format!("e{}", entry));
+        let view = self
+            .engine
+            .list_entries(self.wall)
+            .into_iter()
+            .find(|view| view.entry_id == entry_id)
+            .unwrap();
+
+        let plan = match self.engine.request_launch(&entry_id, self.wall) {
+            LaunchDecision::Approved(plan) => plan,
+            LaunchDecision::Denied { .. } => {
+                prop_assert!(!view.enabled, "{} denied while listed as enabled", entry_id);
+                return Ok(());
+            }
+            LaunchDecision::NeedsConfirmation(_) => unreachable!("no entry asks for confirmation"),
+        };
+        prop_assert!(view.enabled, "{} approved while listed as disabled", entry_id);
+        prop_assert_eq!(plan.max_duration, view.max_run_if_started_now);
+        if let Some(max) = plan.max_duration {
+            prop_assert!(!max.is_zero(), "{} approved with no time", entry_id);
+        }
+
+        let session_id = plan.session_id.clone();
+        let warnings = plan.warning_times();
+        let max_duration = plan.max_duration;
+        self.engine.start_session(plan, self.wall, self.mono);
+        self.engine
+            .attach_host_handle(HostSessionHandle::new(session_id, HostHandlePayload::Mock { id: 1 }));
+        self.running = Some(Running {
+            entry_id,
+            started_mono: self.mono,
+            deadline_mono: max_duration.map(|max| self.mono + max),
+            warnings,
+            fired: Vec::new(),
+            expire_due: false,
+        });
+        Ok(())
+    }
+
+    fn tick(&mut self) -> Result<(), TestCaseError> {
+        let events = self.engine.tick(self.mono, self.wall);
+        let Some(running) = &mut self.running else {
+            return Ok(());
+        };
+        let elapsed = self.mono.duration_since(running.started_mono);
+
+        for event in &events {
+            match event {
+                CoreEvent::Warning { threshold_seconds, .. } => {
+                    let due = running
+                        .warnings
+                        .iter()
+                        .find(|(threshold, _)| threshold == threshold_seconds)
+                        .map(|(_, due)| *due);
+                    prop_assert!(due.is_some(), "unplanned warning {}", threshold_seconds);
+                    prop_assert!(elapsed >= due.unwrap(), "warning {} fired early", threshold_seconds);
+                    prop_assert!(
+                        !running.fired.contains(threshold_seconds),
+                        "warning {} fired twice",
+                        threshold_seconds
+                    );
+                    running.fired.push(*threshold_seconds);
+                }
+                CoreEvent::ExpireDue { .. } => {
+                    prop_assert!(!running.expire_due, "ExpireDue fired twice");
+                    let deadline = running.deadline_mono;
+                    prop_assert!(deadline.is_some(), "unlimited session expired");
+                    prop_assert!(self.mono >= deadline.unwrap(), "expired before the deadline");
+                    running.expire_due = true;
+                }
+                _ => {}
+            }
+        }
+
+        for (threshold, due) in &running.warnings {
+            prop_assert!(
+                elapsed < *due || running.fired.contains(threshold),
+                "warning {} missed",
+                threshold
+            );
+        }
+        if let Some(deadline) = running.deadline_mono {
+            prop_assert!(self.mono < deadline || running.expire_due, "expiry missed");
+        }
+
+        let next = self.engine.next_tick(self.mono, self.wall);
+        for (threshold, due) in &running.warnings {
+            if !running.fired.contains(threshold) {
+                prop_assert!(elapsed + next <= *due, "next tick is after warning {}", threshold);
+            }
+        }
+        if let Some(deadline) = running.deadline_mono
+            && !running.expire_due
+        {
+            prop_assert!(self.mono + next <= deadline, "next tick is after the expiry");
+        }
+
+        if running.expire_due {
+            // The daemon stops the session; here it exits straight away
+            self.end(true)?;
+        }
+        Ok(())
+    }
+
+    fn end(&mut self, expired: bool) -> Result<(), TestCaseError> {
+        let running = self.running.take().unwrap();
+        let before = self.total_usage(&running.entry_id);
+        let duration = if expired {
+            match self.engine.notify_session_exited(Some(0), self.mono, self.wall) {
+                Some(CoreEvent::SessionEnded { duration, .. }) => duration,
+                other => return Err(TestCaseError::fail(format!("session didn't end: {:?}", other))),
+            }
+        } else {
+            match self.engine.stop_current(SessionEndReason::UserStop, self.mono, self.wall) {
+                StopDecision::Stopped(result) => result.duration,
+                StopDecision::NoActiveSession => return Err(TestCaseError::fail("no session to stop")),
+            }
+        };
+
+        let ran = self.mono.duration_since(running.started_mono);
+        prop_assert_eq!(duration, ran);
+        prop_assert_eq!(self.total_usage(&running.entry_id) - before, ran.as_secs());
+        Ok(())
+    }
+}
+
+proptest! {
+    #[test]
+    fn engine_invariants((entries, start, steps) in scenario()) {
+        let mut harness = Harness::new(&entries, start);
+        for step in &steps {
+            harness.run(step)?;
+        }
+    }
+}
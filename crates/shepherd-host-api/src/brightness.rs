@@ -0,0 +1,36 @@
+//! Display brightness trait interfaces
+//!
+//! Defines the capability-based interface for backlight and night-light
+//! control between the shepherdd service and platform-specific implementations.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::HostResult;
+
+/// Brightness capabilities
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BrightnessCapabilities {
+    /// Whether backlight control is available
+    pub available: bool,
+    /// The detected backend (e.g., "brightnessctl", "sysfs")
+    pub backend: Option<String>,
+    /// Whether a color temperature (night light) filter can be applied
+    pub can_night_light: bool,
+}
+
+/// Brightness controller trait - implemented by platform-specific adapters
+#[async_trait]
+pub trait BrightnessController: Send + Sync {
+    /// Get the capabilities of this brightness controller
+    fn capabilities(&self) -> &BrightnessCapabilities;
+
+    /// Get current backlight brightness as a percentage
+    async fn get_brightness(&self) -> HostResult<u8>;
+
+    /// Set backlight brightness to a specific percentage
+    async fn set_brightness(&self, percent: u8) -> HostResult<()>;
+
+    /// Apply a night-light color temperature in Kelvin, or remove it with `None`
+    async fn set_night_light(&self, temperature: Option<u32>) -> HostResult<()>;
+}
@@ -33,6 +33,21 @@ pub struct HostCapabilities {
 
     /// Can lock to single app (MDM/kiosk mode, optional)
     pub can_lock_to_single_app: bool,
+
+    /// Can temporarily block keyboard/pointer input (optional)
+    pub can_inhibit_input: bool,
+
+    /// Can report battery status (optional, laptops/tablets only)
+    pub can_read_battery: bool,
+
+    /// Can freeze and thaw a running session (optional)
+    pub can_freeze_session: bool,
+
+    /// Can tell how long since the last keyboard or pointer input (optional)
+    pub can_detect_idle: bool,
+
+    /// Can read and pause the session's media players (optional)
+    pub can_control_media: bool,
 }
 
 impl HostCapabilities {
@@ -51,6 +66,11 @@ impl HostCapabilities {
             can_force_foreground: false,
             can_force_fullscreen: false,
             can_lock_to_single_app: false,
+            can_inhibit_input: false,
+            can_read_battery: false,
+            can_freeze_session: false,
+            can_detect_idle: false,
+            can_control_media: false,
         }
     }
 
@@ -62,6 +82,7 @@ impl HostCapabilities {
         spawn_kinds.insert(EntryKindTag::Flatpak);
         spawn_kinds.insert(EntryKindTag::Vm);
         spawn_kinds.insert(EntryKindTag::Media);
+        spawn_kinds.insert(EntryKindTag::Retro);
 
         Self {
             spawn_kinds_supported: spawn_kinds,
@@ -73,6 +94,11 @@ impl HostCapabilities {
             can_force_foreground: true,
             can_force_fullscreen: true,
             can_lock_to_single_app: false, // Would need additional setup
+            can_inhibit_input: true,
+            can_read_battery: true,
+            can_freeze_session: true,
+            can_detect_idle: false,
+            can_control_media: true,
         }
     }
 
@@ -98,6 +124,7 @@ mod tests {
         assert!(caps.supports_kind(EntryKindTag::Process));
         assert!(!caps.supports_kind(EntryKindTag::Vm));
         assert!(caps.can_kill_forcefully);
+        assert!(!caps.can_inhibit_input);
     }
 
     #[test]
@@ -0,0 +1,19 @@
+//! Input device monitoring trait interfaces
+//!
+//! Lets the service report paired controllers and their battery levels
+//! without knowing which platform stack (BlueZ, etc.) provides them.
+
+use async_trait::async_trait;
+use shepherd_api::InputDeviceInfo;
+
+use crate::HostResult;
+
+/// Input device monitor trait - implemented by platform-specific adapters
+#[async_trait]
+pub trait InputDeviceMonitor: Send + Sync {
+    /// Whether device information can be queried on this host
+    fn is_available(&self) -> bool;
+
+    /// List paired and connected input devices
+    async fn list_devices(&self) -> HostResult<Vec<InputDeviceInfo>>;
+}
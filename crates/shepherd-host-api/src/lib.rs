@@ -3,14 +3,18 @@
 //! This crate defines the capability-based interface between the shepherdd service
 //! and platform-specific implementations. It contains no platform code itself.
 
+mod brightness;
 mod capabilities;
 mod handle;
+mod input_devices;
 mod mock;
 mod traits;
 mod volume;
 
+pub use brightness::*;
 pub use capabilities::*;
 pub use handle::*;
+pub use input_devices::*;
 pub use mock::*;
 pub use traits::*;
 pub use volume::*;
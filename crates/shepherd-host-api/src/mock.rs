@@ -1,7 +1,7 @@
 //! Mock host adapter for testing
 
 use async_trait::async_trait;
-use shepherd_api::EntryKind;
+use shepherd_api::{EntryKind, MediaInfo, PlaybackStatus};
 use shepherd_util::SessionId;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -10,8 +10,9 @@ use std::time::Duration;
 use tokio::sync::mpsc;
 
 use crate::{
-    ExitStatus, HostAdapter, HostCapabilities, HostError, HostEvent, HostHandlePayload,
-    HostResult, HostSessionHandle, SpawnOptions, StopMode,
+    BrightnessCapabilities, BrightnessController, ExitStatus, HostAdapter, HostCapabilities,
+    HostError, HostEvent, HostHandlePayload, HostResult, HostSessionHandle, SpawnOptions,
+    StopMode, VolumeCapabilities, VolumeController, VolumeError, VolumeResult, VolumeStatus,
 };
 
 /// Mock session state for testing
@@ -39,6 +40,22 @@ pub struct MockHost {
 
     /// Auto-exit delay (simulates process exiting on its own)
     pub auto_exit_delay: Arc<Mutex<Option<Duration>>>,
+
+    /// What the sessions' media player is playing, if they have one
+    pub media: Arc<Mutex<Option<MediaInfo>>>,
+
+    /// Whether keyboard and pointer input is blocked
+    pub input_blocked: Arc<Mutex<bool>>,
+
+    /// Times `inhibit_input` and `release_input` were called
+    pub input_calls: Arc<Mutex<InputCalls>>,
+}
+
+/// Input blocking calls made to a [`MockHost`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputCalls {
+    pub inhibit: u32,
+    pub release: u32,
 }
 
 impl MockHost {
@@ -54,6 +71,9 @@ impl MockHost {
             fail_spawn: Arc::new(Mutex::new(false)),
             fail_stop: Arc::new(Mutex::new(false)),
             auto_exit_delay: Arc::new(Mutex::new(None)),
+            media: Arc::new(Mutex::new(None)),
+            input_blocked: Arc::new(Mutex::new(false)),
+            input_calls: Arc::new(Mutex::new(InputCalls::default())),
         }
     }
 
@@ -75,8 +95,9 @@ impl MockHost {
 
     /// Simulate process exit
     pub fn simulate_exit(&self, session_id: &SessionId, status: ExitStatus) {
-        let sessions = self.sessions.lock().unwrap();
-        if let Some(session) = sessions.values().find(|s| &s.session_id == session_id) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(session) = sessions.values_mut().find(|s| &s.session_id == session_id) {
+            session.running = false;
             let handle = HostSessionHandle::new(
                 session.session_id.clone(),
                 HostHandlePayload::Mock { id: session.mock_id },
@@ -168,6 +189,33 @@ impl HostAdapter for MockHost {
         }
     }
 
+    async fn media_status(&self, _handle: &HostSessionHandle) -> HostResult<Option<MediaInfo>> {
+        Ok(self.media.lock().unwrap().clone())
+    }
+
+    async fn set_media_playing(&self, _handle: &HostSessionHandle, playing: bool) -> HostResult<()> {
+        if let Some(media) = self.media.lock().unwrap().as_mut() {
+            media.status = match (media.status, playing) {
+                (PlaybackStatus::Playing, false) => PlaybackStatus::Paused,
+                (PlaybackStatus::Paused, true) => PlaybackStatus::Playing,
+                (status, _) => status,
+            };
+        }
+        Ok(())
+    }
+
+    async fn inhibit_input(&self) -> HostResult<()> {
+        self.input_calls.lock().unwrap().inhibit += 1;
+        *self.input_blocked.lock().unwrap() = true;
+        Ok(())
+    }
+
+    async fn release_input(&self) -> HostResult<()> {
+        self.input_calls.lock().unwrap().release += 1;
+        *self.input_blocked.lock().unwrap() = false;
+        Ok(())
+    }
+
     fn subscribe(&self) -> mpsc::UnboundedReceiver<HostEvent> {
         self.event_rx
             .lock()
@@ -177,6 +225,137 @@ impl HostAdapter for MockHost {
     }
 }
 
+/// Mock volume controller: remembers the volume without touching any device
+pub struct MockVolumeController {
+    capabilities: VolumeCapabilities,
+    status: Mutex<VolumeStatus>,
+    /// How far sessions are ducked, if they are
+    pub ducked: Mutex<Option<u8>>,
+}
+
+impl MockVolumeController {
+    pub fn new() -> Self {
+        Self {
+            capabilities: VolumeCapabilities {
+                available: true,
+                backend: Some("mock".into()),
+                can_mute: true,
+                max_volume: 100,
+                can_detect_output: false,
+                can_duck: true,
+            },
+            status: Mutex::new(VolumeStatus {
+                percent: 50,
+                muted: false,
+                output: Default::default(),
+            }),
+            ducked: Mutex::new(None),
+        }
+    }
+}
+
+impl Default for MockVolumeController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl VolumeController for MockVolumeController {
+    fn capabilities(&self) -> &VolumeCapabilities {
+        &self.capabilities
+    }
+
+    async fn get_status(&self) -> VolumeResult<VolumeStatus> {
+        Ok(self.status.lock().unwrap().clone())
+    }
+
+    async fn set_volume(&self, percent: u8) -> VolumeResult<()> {
+        if percent > self.capabilities.max_volume {
+            return Err(VolumeError::OutOfRange(percent));
+        }
+        self.status.lock().unwrap().percent = percent;
+        Ok(())
+    }
+
+    async fn volume_up(&self, step: u8) -> VolumeResult<()> {
+        let mut status = self.status.lock().unwrap();
+        status.percent = status.percent.saturating_add(step).min(self.capabilities.max_volume);
+        Ok(())
+    }
+
+    async fn volume_down(&self, step: u8) -> VolumeResult<()> {
+        let mut status = self.status.lock().unwrap();
+        status.percent = status.percent.saturating_sub(step);
+        Ok(())
+    }
+
+    async fn toggle_mute(&self) -> VolumeResult<()> {
+        let mut status = self.status.lock().unwrap();
+        status.muted = !status.muted;
+        Ok(())
+    }
+
+    async fn set_mute(&self, muted: bool) -> VolumeResult<()> {
+        self.status.lock().unwrap().muted = muted;
+        Ok(())
+    }
+
+    async fn duck_session(&self, _session: &HostSessionHandle, percent: Option<u8>) -> VolumeResult<()> {
+        *self.ducked.lock().unwrap() = percent;
+        Ok(())
+    }
+}
+
+/// Mock brightness controller: remembers brightness and night light
+pub struct MockBrightnessController {
+    capabilities: BrightnessCapabilities,
+    brightness: Mutex<u8>,
+    /// Current night-light temperature, if one is applied
+    pub night_light: Mutex<Option<u32>>,
+}
+
+impl MockBrightnessController {
+    pub fn new() -> Self {
+        Self {
+            capabilities: BrightnessCapabilities {
+                available: true,
+                backend: Some("mock".into()),
+                can_night_light: true,
+            },
+            brightness: Mutex::new(100),
+            night_light: Mutex::new(None),
+        }
+    }
+}
+
+impl Default for MockBrightnessController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl BrightnessController for MockBrightnessController {
+    fn capabilities(&self) -> &BrightnessCapabilities {
+        &self.capabilities
+    }
+
+    async fn get_brightness(&self) -> HostResult<u8> {
+        Ok(*self.brightness.lock().unwrap())
+    }
+
+    async fn set_brightness(&self, percent: u8) -> HostResult<()> {
+        *self.brightness.lock().unwrap() = percent.min(100);
+        Ok(())
+    }
+
+    async fn set_night_light(&self, temperature: Option<u32>) -> HostResult<()> {
+        *self.night_light.lock().unwrap() = temperature;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -230,4 +409,16 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn mock_volume() {
+        let volume = MockVolumeController::new();
+        volume.set_volume(95).await.unwrap();
+        volume.volume_up(10).await.unwrap();
+        assert_eq!(volume.get_status().await.unwrap().percent, 100);
+        assert!(volume.set_volume(101).await.is_err());
+
+        volume.toggle_mute().await.unwrap();
+        assert!(volume.get_status().await.unwrap().muted);
+    }
 }
@@ -1,9 +1,13 @@
 //! Host adapter traits
 
 use async_trait::async_trait;
-use shepherd_api::EntryKind;
+use serde::{Deserialize, Serialize};
+use shepherd_api::{
+    BatteryInfo, DeviceAccess, DiscoveredApp, EntryKind, ErrorKind, EscapeRoute, MediaInfo, PowerAction,
+    ReasonCode, SpawnDiagnostics, SpawnFailureCause,
+};
 use shepherd_util::SessionId;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tokio::sync::mpsc;
 
@@ -15,6 +19,10 @@ pub enum HostError {
     #[error("Spawn failed: {0}")]
     SpawnFailed(String),
 
+    /// Spawn failed for a known reason the launcher can show
+    #[error("{0}")]
+    SpawnDiagnosed(SpawnDiagnostics),
+
     #[error("Stop failed: {0}")]
     StopFailed(String),
 
@@ -30,14 +38,75 @@ pub enum HostError {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
-    #[error("Internal error: {0}")]
-    Internal(String),
+    /// Any other failure, classified by kind
+    #[error("{context}")]
+    Failed { kind: ErrorKind, context: String },
+}
+
+impl HostError {
+    pub fn new(kind: ErrorKind, context: impl Into<String>) -> Self {
+        Self::Failed {
+            kind,
+            context: context.into(),
+        }
+    }
+
+    /// The host can't do this at all
+    pub fn unsupported(context: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Unsupported, context)
+    }
+
+    /// An external tool ran and failed
+    pub fn command_failed(context: impl Into<String>) -> Self {
+        Self::new(ErrorKind::CommandFailed, context)
+    }
+
+    pub fn internal(context: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Internal, context)
+    }
+
+    /// An external tool couldn't be started; a missing tool means the
+    /// feature is unsupported here
+    pub fn exec(program: &str, error: &std::io::Error) -> Self {
+        let kind = match error.kind() {
+            std::io::ErrorKind::NotFound => ErrorKind::Unsupported,
+            _ => ErrorKind::from_io(error),
+        };
+        Self::new(kind, format!("Failed to run {}: {}", program, error))
+    }
+
+    /// Why a spawn failed, if the host could tell
+    pub fn spawn_diagnostics(&self) -> Option<SpawnDiagnostics> {
+        match self {
+            HostError::SpawnDiagnosed(d) => Some(d.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            HostError::SpawnFailed(_) | HostError::StopFailed(_) => ErrorKind::CommandFailed,
+            HostError::SpawnDiagnosed(d) => match d.cause {
+                SpawnFailureCause::NotFound
+                | SpawnFailureCause::SnapNotInstalled
+                | SpawnFailureCause::FlatpakNotInstalled => ErrorKind::NotFound,
+                SpawnFailureCause::PermissionDenied => ErrorKind::PermissionDenied,
+                SpawnFailureCause::NoDisplay => ErrorKind::Unavailable,
+                _ => ErrorKind::CommandFailed,
+            },
+            HostError::UnsupportedKind => ErrorKind::Unsupported,
+            HostError::SessionNotFound => ErrorKind::NotFound,
+            HostError::PermissionDenied(_) => ErrorKind::PermissionDenied,
+            HostError::Io(e) => ErrorKind::from_io(e),
+            HostError::Failed { kind, .. } => *kind,
+        }
+    }
 }
 
 pub type HostResult<T> = Result<T, HostError>;
 
 /// Stop mode for session termination
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum StopMode {
     /// Try graceful stop with timeout, then force
     Graceful { timeout: Duration },
@@ -54,7 +123,7 @@ impl Default for StopMode {
 }
 
 /// Options for spawning a session
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SpawnOptions {
     /// Capture stdout to log file
     pub capture_stdout: bool,
@@ -70,10 +139,140 @@ pub struct SpawnOptions {
 
     /// Request foreground focus (if supported)
     pub foreground: bool,
+
+    /// Run the session inside a gamescope nested compositor
+    pub gamescope: Option<GamescopeOptions>,
+
+    /// Camera/microphone access granted to the session
+    pub device_access: DeviceAccess,
+
+    /// Command run before the session starts
+    pub pre_launch: Option<SessionHook>,
+
+    /// Command run after the session exits
+    pub post_exit: Option<SessionHook>,
+
+    /// Minecraft settings written before the session starts
+    pub minecraft: Option<MinecraftOptions>,
+
+    /// Supervise the session through its Proton/WINE prefix
+    pub wine: Option<WineOptions>,
+
+    /// Also stop the session by killing processes with its command name.
+    /// Can hit unrelated processes, so only for entries that opt in.
+    pub kill_by_name: bool,
+
+    /// Wayland display of the session's seat, when it isn't the service's own
+    pub wayland_display: Option<String>,
+}
+
+/// A command run around a session, with the session's environment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionHook {
+    /// Program and arguments
+    pub argv: Vec<String>,
+
+    /// Kill the hook if it runs longer than this
+    pub timeout: Duration,
+
+    /// Fail the launch if the hook fails (pre-launch hooks only)
+    pub abort_on_failure: bool,
+}
+
+/// Minecraft settings enforced before each launch, see
+/// `shepherd_host_linux::apply_minecraft`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MinecraftOptions {
+    /// Game directory holding servers.dat and options.txt
+    pub game_dir: std::path::PathBuf,
+
+    /// The only multiplayer servers listed. None leaves the list alone.
+    pub servers: Option<Vec<MinecraftServer>>,
+
+    /// Hide chat
+    pub disable_chat: bool,
+
+    /// Copies of replaced files to keep
+    pub backups: usize,
+}
+
+/// How a Proton or WINE game is supervised. The session lasts while any
+/// game process of the prefix runs, and stopping it tears the prefix down.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WineOptions {
+    /// The WINE prefix (WINEPREFIX), e.g. a Steam game's compatdata/<id>/pfx
+    pub prefix: std::path::PathBuf,
+
+    /// How long to wait for the game after the launched process exits,
+    /// e.g. while Steam hands the launch to its running client
+    pub start_timeout: Duration,
+}
+
+/// A multiplayer server in Minecraft's server list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MinecraftServer {
+    pub name: String,
+    pub address: String,
+}
+
+/// Host checks run before a launch is approved
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchPrechecks {
+    /// Check that the program exists and is executable
+    pub check_binary: bool,
+
+    /// Environment variables that must be set, in the entry or the service
+    pub require_env: Vec<String>,
+
+    /// Minimum free disk space: filesystem path and megabytes
+    pub min_free_disk: Option<(std::path::PathBuf, u64)>,
+
+    /// Require a reachable Wayland or X11 display
+    pub require_display: bool,
+}
+
+/// Settings for wrapping a session in gamescope
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GamescopeOptions {
+    /// Fixed output resolution (width, height)
+    pub resolution: Option<(u32, u32)>,
+
+    /// Frame rate cap
+    pub fps_limit: Option<u32>,
+
+    /// Start gamescope fullscreen
+    pub fullscreen: bool,
+
+    /// Extra arguments passed to gamescope before the session command
+    pub extra_args: Vec<String>,
+}
+
+impl GamescopeOptions {
+    /// Build the gamescope argv that should precede the session command
+    pub fn wrapper_argv(&self) -> Vec<String> {
+        let mut argv = vec!["gamescope".to_string()];
+        if let Some((width, height)) = self.resolution {
+            argv.extend([
+                "-W".to_string(),
+                width.to_string(),
+                "-H".to_string(),
+                height.to_string(),
+            ]);
+        }
+        if let Some(fps) = self.fps_limit {
+            argv.extend(["-r".to_string(), fps.to_string()]);
+        }
+        if self.fullscreen {
+            argv.push("-f".to_string());
+        }
+        argv.extend(self.extra_args.iter().cloned());
+        argv.push("--".to_string());
+        argv
+    }
 }
 
 /// Events from the host adapter
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum HostEvent {
     /// Process/session has exited
     Exited {
@@ -86,11 +285,15 @@ pub enum HostEvent {
         handle: HostSessionHandle,
     },
 
-    /// Spawn failed after handle was created
+    /// Spawn failed after handle was created, e.g. the program exited
+    /// right after starting. Sent before the matching `Exited`.
     SpawnFailed {
         session_id: SessionId,
-        error: String,
+        diagnostics: SpawnDiagnostics,
     },
+
+    /// The key registered with `register_panic_key` was pressed
+    PanicKeyPressed,
 }
 
 /// Host adapter trait - implemented by platform-specific adapters
@@ -110,17 +313,112 @@ pub trait HostAdapter: Send + Sync {
     /// Stop a running session
     async fn stop(&self, handle: &HostSessionHandle, mode: StopMode) -> HostResult<()>;
 
-    /// Subscribe to host events
+    /// Subscribe to host events. The channel closes only if the host is
+    /// gone for good, e.g. a remote host's connection dropped.
     fn subscribe(&self) -> mpsc::UnboundedReceiver<HostEvent>;
 
+    /// Optional: kill every process in the session's cgroup, for sessions
+    /// that survive a forced stop (if supported)
+    async fn kill_cgroup(&self, _handle: &HostSessionHandle) -> HostResult<()> {
+        Err(HostError::unsupported("Not supported"))
+    }
+
+    /// Optional: freeze or thaw everything in a session (if supported)
+    async fn freeze_session(&self, _handle: &HostSessionHandle, _frozen: bool) -> HostResult<()> {
+        Err(HostError::unsupported("Not supported"))
+    }
+
+    /// Optional: whether the session's process is still running. None if
+    /// the host can't tell.
+    fn is_session_alive(&self, _handle: &HostSessionHandle) -> Option<bool> {
+        None
+    }
+
+    /// Optional: check that an entry can be launched on this host.
+    /// Returns the reasons it can't; empty means all checks passed.
+    async fn precheck(
+        &self,
+        _entry_kind: &EntryKind,
+        _checks: &LaunchPrechecks,
+    ) -> Vec<ReasonCode> {
+        Vec::new()
+    }
+
+    /// Optional: probe whether an entry is still runnable (e.g. its app is
+    /// still installed). Returns a description of the problem if not.
+    async fn check_entry_health(&self, _entry_kind: &EntryKind) -> Option<String> {
+        None
+    }
+
+    /// Optional: list launchable apps installed on the host
+    async fn discover_apps(&self) -> Vec<DiscoveredApp> {
+        Vec::new()
+    }
+
+    /// Optional: look for ways out of the kiosk (other VTs, key bindings
+    /// that open a terminal, ...), closing them where possible when
+    /// `mitigate` is set
+    async fn audit_escape_routes(&self, _mitigate: bool) -> Vec<EscapeRoute> {
+        Vec::new()
+    }
+
+    /// Optional: update the app behind an entry (e.g. `snap refresh`)
+    async fn update_entry(&self, _entry_kind: &EntryKind) -> HostResult<()> {
+        Err(HostError::UnsupportedKind)
+    }
+
     /// Optional: set foreground focus (if supported)
     async fn set_foreground(&self, _handle: &HostSessionHandle) -> HostResult<()> {
-        Err(HostError::Internal("Not supported".into()))
+        Err(HostError::unsupported("Not supported"))
     }
 
     /// Optional: set fullscreen mode (if supported)
     async fn set_fullscreen(&self, _handle: &HostSessionHandle) -> HostResult<()> {
-        Err(HostError::Internal("Not supported".into()))
+        Err(HostError::unsupported("Not supported"))
+    }
+
+    /// Optional: time since the last keyboard or pointer input (if supported)
+    async fn idle_time(&self) -> HostResult<Duration> {
+        Err(HostError::unsupported("Not supported"))
+    }
+
+    /// Optional: block keyboard/pointer input to running applications (if supported)
+    async fn inhibit_input(&self) -> HostResult<()> {
+        Err(HostError::unsupported("Not supported"))
+    }
+
+    /// Optional: restore input blocked by `inhibit_input` (if supported)
+    async fn release_input(&self) -> HostResult<()> {
+        Err(HostError::unsupported("Not supported"))
+    }
+
+    /// Optional: register a global key combination, handled even over
+    /// fullscreen apps, that sends [`HostEvent::PanicKeyPressed`] (if
+    /// supported)
+    async fn register_panic_key(&self, _combo: &str) -> HostResult<()> {
+        Err(HostError::unsupported("Not supported"))
+    }
+
+    /// Optional: read the host battery status (if supported)
+    async fn battery_status(&self) -> HostResult<BatteryInfo> {
+        Err(HostError::unsupported("Not supported"))
+    }
+
+    /// Optional: what the session's media player is playing; None if it
+    /// has none (if supported)
+    async fn media_status(&self, _handle: &HostSessionHandle) -> HostResult<Option<MediaInfo>> {
+        Err(HostError::unsupported("Not supported"))
+    }
+
+    /// Optional: pause the session's media players, or resume the paused
+    /// ones (if supported)
+    async fn set_media_playing(&self, _handle: &HostSessionHandle, _playing: bool) -> HostResult<()> {
+        Err(HostError::unsupported("Not supported"))
+    }
+
+    /// Optional: power off or suspend the machine (if supported)
+    async fn power_action(&self, _action: PowerAction) -> HostResult<()> {
+        Err(HostError::unsupported("Not supported"))
     }
 
     /// Optional: ensure the shell/launcher is visible
@@ -132,6 +430,23 @@ pub trait HostAdapter: Send + Sync {
     fn is_healthy(&self) -> bool {
         true
     }
+
+    /// Optional: whether host operations run in another process
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    /// Optional: when the process monitor last checked for exited sessions
+    fn last_monitor_tick(&self) -> Option<Instant> {
+        None
+    }
+
+    /// Optional: force-kill every session right away, from a synchronous
+    /// crash path where the async runtime can't be relied on. Returns how
+    /// many sessions were signalled.
+    fn kill_all_now(&self) -> usize {
+        0
+    }
 }
 
 #[cfg(test)]
@@ -143,4 +458,18 @@ mod tests {
         let mode = StopMode::default();
         assert!(matches!(mode, StopMode::Graceful { timeout } if timeout == Duration::from_secs(5)));
     }
+
+    #[test]
+    fn gamescope_wrapper_argv() {
+        let opts = GamescopeOptions {
+            resolution: Some((1280, 720)),
+            fps_limit: Some(60),
+            fullscreen: true,
+            extra_args: vec!["--adaptive-sync".into()],
+        };
+        assert_eq!(
+            opts.wrapper_argv(),
+            vec!["gamescope", "-W", "1280", "-H", "720", "-r", "60", "-f", "--adaptive-sync", "--"]
+        );
+    }
 }
@@ -5,7 +5,11 @@
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use shepherd_api::{AudioOutput, ErrorKind};
+
+use crate::HostSessionHandle;
 use thiserror::Error;
+use tokio::sync::mpsc;
 
 /// Errors from volume control operations
 #[derive(Debug, Error)]
@@ -23,6 +27,17 @@ pub enum VolumeError {
     Io(#[from] std::io::Error),
 }
 
+impl VolumeError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            VolumeError::NotAvailable(_) => ErrorKind::Unsupported,
+            VolumeError::Backend(_) => ErrorKind::CommandFailed,
+            VolumeError::OutOfRange(_) => ErrorKind::InvalidInput,
+            VolumeError::Io(e) => ErrorKind::from_io(e),
+        }
+    }
+}
+
 pub type VolumeResult<T> = Result<T, VolumeError>;
 
 /// Volume status
@@ -32,6 +47,9 @@ pub struct VolumeStatus {
     pub percent: u8,
     /// Whether audio is muted
     pub muted: bool,
+    /// What the default output plays through
+    #[serde(default)]
+    pub output: AudioOutput,
 }
 
 impl VolumeStatus {
@@ -60,6 +78,13 @@ pub struct VolumeCapabilities {
     pub can_mute: bool,
     /// Maximum volume percentage allowed (for systems that allow >100%)
     pub max_volume: u8,
+    /// Whether [`VolumeStatus::output`] tells headphones from speakers
+    #[serde(default)]
+    pub can_detect_output: bool,
+    /// Whether [`VolumeController::duck_session`] can turn a session's own
+    /// streams down
+    #[serde(default)]
+    pub can_duck: bool,
 }
 
 /// Volume restrictions that can be enforced by policy
@@ -117,6 +142,21 @@ pub trait VolumeController: Send + Sync {
 
     /// Set mute state explicitly
     async fn set_mute(&self, muted: bool) -> VolumeResult<()>;
+
+    /// Volume changes from anywhere (this service, other apps, hardware
+    /// keys), pushed as they happen. None if the backend can't report
+    /// changes, so callers have to ask. Can only be called once.
+    fn watch(&self) -> Option<mpsc::UnboundedReceiver<VolumeStatus>> {
+        None
+    }
+
+    /// Turn the audio streams played by a session down to `percent` of
+    /// their level, leaving the output volume alone; `None` puts them back.
+    /// Ducking again while ducked scales from the original level.
+    async fn duck_session(&self, session: &HostSessionHandle, percent: Option<u8>) -> VolumeResult<()> {
+        let _ = (session, percent);
+        Err(VolumeError::NotAvailable("per-stream volume".into()))
+    }
 }
 
 #[cfg(test)]
@@ -128,18 +168,21 @@ mod tests {
         let status = VolumeStatus {
             percent: 0,
             muted: false,
+            ..Default::default()
         };
         assert_eq!(status.icon_name(), "audio-volume-muted-symbolic");
 
         let status = VolumeStatus {
             percent: 50,
             muted: false,
+            ..Default::default()
         };
         assert_eq!(status.icon_name(), "audio-volume-medium-symbolic");
 
         let status = VolumeStatus {
             percent: 100,
             muted: true,
+            ..Default::default()
         };
         assert_eq!(status.icon_name(), "audio-volume-muted-symbolic");
     }
@@ -0,0 +1,61 @@
+//! shepherd-host-helper - session-side half of a privilege-separated shepherdd
+//!
+//! Runs inside the user's session and performs the host operations
+//! shepherdd asks for over a Unix socket: spawning and stopping sessions,
+//! input lockout, volume and power actions. shepherdd itself can then run
+//! as a separate, locked-down user. See `shepherd_host_linux::HelperClient`
+//! for the protocol.
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use shepherd_util::{default_host_helper_socket_path, SHEPHERD_HOST_HELPER_ENV};
+use std::path::PathBuf;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+
+/// shepherd-host-helper - host operations for a privilege-separated shepherdd
+#[derive(Parser, Debug)]
+#[command(name = "shepherd-host-helper")]
+#[command(about = "Host operations for a privilege-separated shepherdd", long_about = None)]
+struct Args {
+    /// Socket path (or set SHEPHERD_HOST_HELPER env var)
+    #[arg(short, long, env = SHEPHERD_HOST_HELPER_ENV, default_value_os_t = default_host_helper_socket_path())]
+    socket: PathBuf,
+
+    /// UID allowed to connect, i.e. the user shepherdd runs as. Required;
+    /// may be repeated.
+    #[arg(long = "allow-uid", required = true)]
+    allow_uids: Vec<u32>,
+
+    /// Group the socket belongs to, i.e. shepherdd's group. The session user
+    /// must be a member.
+    #[arg(long, default_value = "shepherd")]
+    socket_group: String,
+
+    /// Log level
+    #[arg(short, long, default_value = "info")]
+    log_level: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(&args.log_level));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_target(true)
+        .init();
+
+    info!(
+        version = env!("CARGO_PKG_VERSION"),
+        allow_uids = ?args.allow_uids,
+        socket_group = %args.socket_group,
+        "shepherd-host-helper starting"
+    );
+
+    shepherd_host_linux::run_helper(&args.socket, &args.allow_uids, &args.socket_group)
+        .await
+        .with_context(|| format!("Host helper failed on {:?}", args.socket))
+}
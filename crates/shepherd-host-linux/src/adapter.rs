@@ -1,22 +1,51 @@
 //! Linux host adapter implementation
 
 use async_trait::async_trait;
-use shepherd_api::EntryKind;
+use shepherd_api::{
+    BatteryInfo, DiscoveredApp, EntryKind, ErrorKind, EscapeRoute, MediaInfo, PowerAction,
+    ReasonCode, SpawnDiagnostics, SpawnFailureCause,
+};
 use shepherd_host_api::{
-    HostAdapter, HostCapabilities, HostError, HostEvent, HostHandlePayload,
-    HostResult, HostSessionHandle, SpawnOptions, StopMode,
+    ExitStatus, HostAdapter, HostCapabilities, HostError, HostEvent, HostHandlePayload,
+    HostResult, HostSessionHandle, LaunchPrechecks, SessionHook, SpawnOptions, StopMode,
 };
 use shepherd_util::SessionId;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use tokio::sync::mpsc;
-use tracing::{info, warn};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Notify};
+use tracing::{debug, info, warn};
 
-use crate::process::{init, kill_by_command, kill_flatpak_cgroup, kill_snap_cgroup, ManagedProcess};
+use crate::diagnostics::{early_exit, log_tail, StderrTail, EARLY_EXIT_WINDOW};
+use crate::devices::{
+    default_capture_muted, deny_flatpak_camera, set_default_capture_muted, CameraRestore, CaptureRestore,
+};
+use crate::input::{is_sway_available, set_input_events_enabled};
+use crate::battery::{has_battery, read_battery_status};
+use crate::power::logind_power_action;
+use crate::panic_key::{bind_panic_key, watch_panic_key};
+use crate::mpris::{is_mpris_available, session_media, set_session_playing};
+use crate::discovery::discover_apps;
+use crate::hardening::audit_escape_routes;
+use crate::health::probe_entry_health;
+use crate::hooks::run_hook_with_policy;
+use crate::minecraft::apply_minecraft;
+use crate::pidfd::{pidfd_supported, PidFd};
+use crate::precheck::run_prechecks;
+use crate::retro::{backup_retro_saves, retroarch_argv};
+use crate::update::update_app;
+use crate::wine::{expand_prefix, game_running, prefix_processes, signal_prefix};
+use crate::x11::{
+    activate_window, fullscreen_window, is_x11_available, is_x11_session, windows_for_pids,
+    x11_idle_time, X11Window,
+};
+use crate::SoundBackend;
+use crate::process::{cgroup_freeze, cgroup_kill, descendant_pids, init, kill_by_command, kill_cgroups, kill_flatpak_cgroup, kill_snap_cgroup, process_alive, session_cgroups, signal_cgroup, ManagedProcess};
 
 /// Expand `~` at the beginning of a path to the user's home directory
-fn expand_tilde(path: &str) -> String {
+pub(crate) fn expand_tilde(path: &str) -> String {
     if path.starts_with("~/") {
         if let Some(home) = dirs::home_dir() {
             return path.replacen("~", &home.to_string_lossy(), 1);
@@ -34,12 +63,62 @@ fn expand_args(args: &[String]) -> Vec<String> {
     args.iter().map(|arg| expand_tilde(arg)).collect()
 }
 
+/// Where a session is in its life, as far as the adapter knows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TrackedState {
+    Running,
+    /// `stop()` was called; the exit is expected
+    Stopping,
+    /// The process exited and was reaped, and `Exited` was sent
+    Exited,
+}
+
 /// Information tracked for each session for cleanup purposes
 #[derive(Clone, Debug)]
 struct SessionInfo {
+    state: TrackedState,
+    pid: u32,
     command_name: String,
+    /// The entry opted in to name-based killing
+    kill_by_name: bool,
     snap_name: Option<String>,
     flatpak_app_id: Option<String>,
+    /// The session lasts as long as its Proton/WINE prefix
+    wine: Option<WineWatch>,
+}
+
+/// A Proton/WINE session's prefix, see [`crate::wine`]
+#[derive(Clone, Debug)]
+struct WineWatch {
+    prefix: PathBuf,
+    start_timeout: Duration,
+    /// The session's own cgroup, found at spawn since the launched process
+    /// may be gone long before the game
+    cgroup: Option<PathBuf>,
+}
+
+/// Output that may explain a failed launch: stderr, or the log file
+type EarlyExit = (Option<StderrTail>, Option<PathBuf>);
+
+/// A Proton/WINE session whose launched process exited while the game may
+/// still be running
+struct Lingering {
+    pid: u32,
+    pgid: u32,
+    status: ExitStatus,
+    early: Option<EarlyExit>,
+    wine: WineWatch,
+    since: Instant,
+    /// A game process has been seen since
+    seen: bool,
+}
+
+/// A post-exit hook waiting for its session's process to exit
+struct PendingHook {
+    session_id: SessionId,
+    hook: SessionHook,
+    env: HashMap<String, String>,
+    cwd: Option<std::path::PathBuf>,
 }
 
 /// Linux host adapter
@@ -50,6 +129,20 @@ pub struct LinuxHost {
     session_info: Arc<Mutex<HashMap<SessionId, SessionInfo>>>,
     event_tx: mpsc::UnboundedSender<HostEvent>,
     event_rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<HostEvent>>>>,
+    /// Whether input is currently blocked by `inhibit_input`
+    input_inhibited: AtomicBool,
+    /// Whether Sway's binding events are followed for the panic key
+    panic_key_watched: AtomicBool,
+    /// Microphone state to restore when the current session exits
+    capture_restore: Arc<Mutex<Option<CaptureRestore>>>,
+    /// Camera permission to restore when the current session exits
+    camera_restore: Arc<Mutex<Option<CameraRestore>>>,
+    /// When the process monitor last ran, for health checks
+    monitor_tick: Arc<Mutex<Option<Instant>>>,
+    /// Tells the monitor a process was spawned and needs watching
+    monitor_wake: Arc<Notify>,
+    /// Post-exit hooks by session pid
+    post_exit_hooks: Arc<Mutex<HashMap<u32, PendingHook>>>,
 }
 
 impl LinuxHost {
@@ -59,23 +152,100 @@ impl LinuxHost {
         // Initialize process management
         init();
 
+        let mut capabilities = HostCapabilities::linux_full();
+        capabilities.can_inhibit_input = is_sway_available();
+        // Window management and idle time are only available on X11
+        let x11 = is_x11_available();
+        capabilities.can_observe_window_ready = x11;
+        capabilities.can_force_foreground = x11;
+        capabilities.can_force_fullscreen = x11;
+        capabilities.can_detect_idle = is_x11_session() && x11_idle_time().is_ok();
+        capabilities.can_read_battery = has_battery();
+        capabilities.can_control_media = is_mpris_available();
+        if !pidfd_supported() {
+            info!("No pidfd support in this kernel, polling for process exits");
+        }
+
         Self {
-            capabilities: HostCapabilities::linux_full(),
+            capabilities,
             processes: Arc::new(Mutex::new(HashMap::new())),
             session_info: Arc::new(Mutex::new(HashMap::new())),
             event_tx: tx,
             event_rx: Arc::new(Mutex::new(Some(rx))),
+            input_inhibited: AtomicBool::new(false),
+            panic_key_watched: AtomicBool::new(false),
+            capture_restore: Arc::new(Mutex::new(None)),
+            camera_restore: Arc::new(Mutex::new(None)),
+            monitor_tick: Arc::new(Mutex::new(None)),
+            monitor_wake: Arc::new(Notify::new()),
+            post_exit_hooks: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    /// Start the background process monitor
+    /// Start the background process monitor.
+    ///
+    /// Each process is watched through a pidfd, so the monitor sleeps until
+    /// one exits. Processes without a pidfd (kernels before 5.3) are polled
+    /// every [`MONITOR_POLL_INTERVAL`]. Every process is also checked every
+    /// [`MONITOR_HEARTBEAT`] in case an exit was missed. Once a Proton/WINE
+    /// session's launched process exits, its prefix is checked every
+    /// [`WINE_POLL_INTERVAL`] until no game process is left.
     pub fn start_monitor(&self) -> tokio::task::JoinHandle<()> {
         let processes = self.processes.clone();
+        let capture_restore = self.capture_restore.clone();
+        let camera_restore = self.camera_restore.clone();
         let event_tx = self.event_tx.clone();
+        let monitor_tick = self.monitor_tick.clone();
+        let monitor_wake = self.monitor_wake.clone();
+        let post_exit_hooks = self.post_exit_hooks.clone();
+        let session_info = self.session_info.clone();
 
         tokio::spawn(async move {
+            let (exit_tx, mut exit_rx) = mpsc::unbounded_channel();
+            // Processes with a pidfd waiter
+            let mut watched = HashSet::new();
+            let mut lingering: Vec<Lingering> = Vec::new();
             loop {
-                tokio::time::sleep(Duration::from_millis(100)).await;
+                let mut polling = false;
+                {
+                    let procs = processes.lock().unwrap();
+                    watched.retain(|pid| procs.contains_key(pid));
+                    for &pid in procs.keys() {
+                        if watched.contains(&pid) {
+                            continue;
+                        }
+                        match PidFd::open(pid) {
+                            Ok(pidfd) => {
+                                watched.insert(pid);
+                                let exit_tx = exit_tx.clone();
+                                tokio::spawn(async move {
+                                    if let Err(e) = pidfd.exited().await {
+                                        debug!(pid = pid, error = %e, "Failed to wait on pidfd");
+                                    }
+                                    let _ = exit_tx.send(pid);
+                                });
+                            }
+                            Err(e) => {
+                                debug!(pid = pid, error = %e, "No pidfd, polling for exit");
+                                polling = true;
+                            }
+                        }
+                    }
+                }
+
+                let wait = if polling {
+                    MONITOR_POLL_INTERVAL
+                } else if !lingering.is_empty() {
+                    WINE_POLL_INTERVAL
+                } else {
+                    MONITOR_HEARTBEAT
+                };
+                tokio::select! {
+                    Some(_) = exit_rx.recv() => {}
+                    _ = monitor_wake.notified() => {}
+                    _ = tokio::time::sleep(wait) => {}
+                }
+                *monitor_tick.lock().unwrap() = Some(Instant::now());
 
                 let mut exited = Vec::new();
 
@@ -84,7 +254,10 @@ impl LinuxHost {
                     for (pid, proc) in procs.iter_mut() {
                         match proc.try_wait() {
                             Ok(Some(status)) => {
-                                exited.push((*pid, proc.pgid, status));
+                                // Output that may explain a failed launch
+                                let early = (proc.started.elapsed() < EARLY_EXIT_WINDOW)
+                                    .then(|| (proc.stderr_tail.clone(), proc.log_path.clone()));
+                                exited.push((*pid, proc.pgid, status, early));
                             }
                             Ok(None) => {}
                             Err(e) => {
@@ -93,18 +266,151 @@ impl LinuxHost {
                         }
                     }
 
-                    for (pid, _, _) in &exited {
+                    for (pid, _, _, _) in &exited {
                         procs.remove(pid);
                     }
                 }
 
-                for (pid, pgid, status) in exited {
+                // A Proton/WINE game outlives the process that launched it
+                let mut exited: Vec<_> = exited
+                    .into_iter()
+                    .filter_map(|(pid, pgid, status, early)| {
+                        let wine = session_info
+                            .lock()
+                            .unwrap()
+                            .values()
+                            .find(|info| info.pid == pid)
+                            .and_then(|info| info.wine.clone());
+                        let Some(wine) = wine else {
+                            return Some((pid, pgid, status, early));
+                        };
+                        info!(pid = pid, prefix = %wine.prefix.display(), "Launched process exited, following the WINE prefix");
+                        lingering.push(Lingering {
+                            pid,
+                            pgid,
+                            status,
+                            early,
+                            wine,
+                            since: Instant::now(),
+                            seen: false,
+                        });
+                        None
+                    })
+                    .collect();
+                lingering.retain_mut(|session| {
+                    if game_running(&session.wine.prefix, session.wine.cgroup.as_deref()) {
+                        session.seen = true;
+                        return true;
+                    }
+                    // Steam may take a while to start the game, unless the
+                    // session is being stopped anyway
+                    let stopping = session_info
+                        .lock()
+                        .unwrap()
+                        .values()
+                        .any(|info| info.pid == session.pid && info.state == TrackedState::Stopping);
+                    if !session.seen && !stopping && session.since.elapsed() < session.wine.start_timeout {
+                        return true;
+                    }
+                    info!(pid = session.pid, prefix = %session.wine.prefix.display(), "WINE prefix has no game running");
+                    // A launcher failing after the game ran isn't a failed launch
+                    let early = session.early.take().filter(|_| !session.seen);
+                    exited.push((session.pid, session.pgid, session.status.clone(), early));
+                    false
+                });
+
+                for (pid, pgid, status, early) in exited {
                     info!(pid = pid, pgid = pgid, status = ?status, "Process exited - sending HostEvent::Exited");
 
-                    // We don't have the session_id here, so we use a placeholder
-                    // The service should track the mapping
+                    // Undo any per-session microphone block
+                    let restore = {
+                        let mut guard = capture_restore.lock().unwrap();
+                        if guard.is_some_and(|r| r.pid == pid) {
+                            guard.take()
+                        } else {
+                            None
+                        }
+                    };
+                    if let Some(restore) = restore {
+                        restore.restore();
+                    }
+                    let restore = {
+                        let mut guard = camera_restore.lock().unwrap();
+                        if guard.as_ref().is_some_and(|r| r.pid == pid) {
+                            guard.take()
+                        } else {
+                            None
+                        }
+                    };
+                    if let Some(restore) = restore {
+                        restore.restore();
+                    }
+
+                    let pending = post_exit_hooks.lock().unwrap().remove(&pid);
+                    if let Some(mut pending) = pending {
+                        if let Some(code) = status.code {
+                            pending.env.insert("SHEPHERD_EXIT_CODE".into(), code.to_string());
+                        }
+                        tokio::spawn(async move {
+                            let _ = run_hook_with_policy(
+                                "post_exit",
+                                &pending.hook,
+                                &pending.session_id,
+                                &pending.env,
+                                pending.cwd.as_ref(),
+                            )
+                            .await;
+                        });
+                    }
+
+                    // Kept (until the next spawn) so a late stop() can tell
+                    // the session already ended
+                    let found = session_info
+                        .lock()
+                        .unwrap()
+                        .iter_mut()
+                        .find(|(_, info)| info.pid == pid)
+                        .map(|(id, info)| {
+                            let previous = info.clone();
+                            info.state = TrackedState::Exited;
+                            (id.clone(), previous)
+                        });
+                    let Some((session_id, info)) = found else {
+                        warn!(pid = pid, "Exited process has no session");
+                        continue;
+                    };
+
+                    // Failing right after starting, without being stopped,
+                    // is a failed launch
+                    if let Some((stderr_tail, log_path)) = early
+                        && info.state == TrackedState::Running
+                        && status.code != Some(0)
+                    {
+                        let status = status.clone();
+                        let diagnosed = tokio::task::spawn_blocking(move || {
+                            let stderr = stderr_tail
+                                .and_then(|tail| tail.text())
+                                .or_else(|| log_path.as_deref().and_then(log_tail));
+                            early_exit(
+                                &info.command_name,
+                                info.snap_name.as_deref(),
+                                info.flatpak_app_id.as_deref(),
+                                &status,
+                                stderr,
+                            )
+                        })
+                        .await;
+                        if let Ok(diagnostics) = diagnosed {
+                            warn!(session_id = %session_id, diagnostics = %diagnostics, "Session failed to launch");
+                            let _ = event_tx.send(HostEvent::SpawnFailed {
+                                session_id: session_id.clone(),
+                                diagnostics,
+                            });
+                        }
+                    }
+
                     let handle = HostSessionHandle::new(
-                        SessionId::new(), // This will be matched by PID
+                        session_id,
                         HostHandlePayload::Linux { pid, pgid },
                     );
 
@@ -115,6 +421,113 @@ impl LinuxHost {
     }
 }
 
+/// How often processes without a pidfd are checked for exit
+const MONITOR_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often every process is checked, even with pidfds
+const MONITOR_HEARTBEAT: Duration = Duration::from_secs(5);
+
+/// How often a Proton/WINE prefix is checked once its launched process exited
+const WINE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long to wait for a session's first X11 window
+const WINDOW_READY_TIMEOUT: Duration = Duration::from_secs(60);
+const WINDOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// X11 windows owned by a session's process or its descendants
+fn session_windows(pid: u32) -> HostResult<Vec<X11Window>> {
+    let mut pids = vec![pid];
+    pids.extend(descendant_pids(pid as i32).into_iter().map(|p| p as u32));
+    windows_for_pids(&pids)
+}
+
+impl LinuxHost {
+    fn first_window(handle: &HostSessionHandle) -> HostResult<X11Window> {
+        let HostHandlePayload::Linux { pid, .. } = handle.payload() else {
+            return Err(HostError::SessionNotFound);
+        };
+        session_windows(*pid)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| HostError::new(ErrorKind::NotFound, "Session has no window"))
+    }
+
+    /// Wait for a session's first X11 window, then send `WindowReady` and
+    /// apply the requested fullscreen and focus. Sway does this through its
+    /// own config; X11 window managers need to be told.
+    fn watch_x11_window(&self, handle: HostSessionHandle, fullscreen: bool, foreground: bool) {
+        let processes = self.processes.clone();
+        let event_tx = self.event_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let HostHandlePayload::Linux { pid, .. } = *handle.payload() else {
+                return;
+            };
+            let deadline = Instant::now() + WINDOW_READY_TIMEOUT;
+            while Instant::now() < deadline && processes.lock().unwrap().contains_key(&pid) {
+                let window = session_windows(pid).ok().and_then(|w| w.into_iter().next());
+                if let Some(window) = window {
+                    info!(session_id = %handle.session_id, window = %window.id, title = %window.title, "Session window ready");
+                    if fullscreen && let Err(e) = fullscreen_window(&window.id) {
+                        warn!(error = %e, "Failed to make session window fullscreen");
+                    }
+                    if foreground && let Err(e) = activate_window(&window.id) {
+                        warn!(error = %e, "Failed to focus session window");
+                    }
+                    let _ = event_tx.send(HostEvent::WindowReady { handle });
+                    return;
+                }
+                std::thread::sleep(WINDOW_POLL_INTERVAL);
+            }
+            debug!(session_id = %handle.session_id, "No session window found");
+        });
+    }
+
+    /// SIGKILL a session: at once through `cgroup.kill` when it has a cgroup
+    /// of its own, otherwise through systemd scopes, or the command name if
+    /// the entry opted in
+    fn force_kill(pid: u32, info: Option<&SessionInfo>) {
+        if let Some(wine) = info.and_then(|i| i.wine.as_ref()) {
+            signal_prefix(&wine.prefix, nix::sys::signal::Signal::SIGKILL);
+        }
+        let snap = info.and_then(|i| i.snap_name.as_deref());
+        let flatpak = info.and_then(|i| i.flatpak_app_id.as_deref());
+        if kill_cgroups(&session_cgroups(pid, snap, flatpak)) {
+            return;
+        }
+
+        if let Some(snap) = snap {
+            kill_snap_cgroup(snap, nix::sys::signal::Signal::SIGKILL);
+            info!(snap = %snap, "Sent SIGKILL via snap cgroup");
+        } else if let Some(app_id) = flatpak {
+            kill_flatpak_cgroup(app_id, nix::sys::signal::Signal::SIGKILL);
+            info!(flatpak = %app_id, "Sent SIGKILL via flatpak cgroup");
+        } else if let Some(info) = info.filter(|i| i.kill_by_name) {
+            kill_by_command(&info.command_name, nix::sys::signal::Signal::SIGKILL);
+            info!(command = %info.command_name, "Sent SIGKILL via command name");
+        }
+    }
+
+    /// Mute the default capture source, returning the backend used and
+    /// whether the source was already muted
+    fn block_microphone() -> Option<(SoundBackend, bool)> {
+        let Some(backend) = SoundBackend::detect() else {
+            warn!("No sound backend detected, cannot block microphone");
+            return None;
+        };
+        let was_muted = default_capture_muted(backend).unwrap_or(false);
+        match set_default_capture_muted(backend, true) {
+            Ok(()) => {
+                info!("Blocked microphone for session");
+                Some((backend, was_muted))
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to block microphone");
+                None
+            }
+        }
+    }
+}
+
 impl Default for LinuxHost {
     fn default() -> Self {
         Self::new()
@@ -181,11 +594,25 @@ impl HostAdapter for LinuxHost {
                 let argv = vec!["xdg-open".to_string(), expand_tilde(library_id)];
                 (argv, HashMap::new(), None, None, None)
             }
+            EntryKind::Retro { core, rom_path } => {
+                // A failed backup shouldn't keep the game from starting
+                if let Err(e) = backup_retro_saves(rom_path) {
+                    warn!(rom = %rom_path.display(), error = %e, "Failed to back up RetroArch saves");
+                }
+                (retroarch_argv(core, rom_path), HashMap::new(), None, None, None)
+            }
             EntryKind::Custom { type_name: _, payload: _ } => {
                 return Err(HostError::UnsupportedKind);
             }
         };
 
+        // Sessions on another seat show up on that seat's display, unless
+        // the entry picks one itself
+        let mut env = env;
+        if let Some(display) = &options.wayland_display {
+            env.entry("WAYLAND_DISPLAY".into()).or_insert_with(|| display.clone());
+        }
+
         // Get the command name for fallback killing
         // For snap/flatpak apps, use the app name (not "snap"/"flatpak") to avoid killing unrelated processes
         let command_name = if let Some(ref snap) = snap_name {
@@ -198,6 +625,75 @@ impl HostAdapter for LinuxHost {
 
         // Determine if this is a sandboxed app (snap or flatpak)
         let sandboxed_app_name = snap_name.clone().or_else(|| flatpak_app_id.clone());
+
+        // Reset the server list and chat before the game reads them
+        if let Some(minecraft) = &options.minecraft {
+            apply_minecraft(minecraft).map_err(|e| {
+                HostError::SpawnDiagnosed(SpawnDiagnostics {
+                    cause: SpawnFailureCause::HookFailed,
+                    program: command_name.clone(),
+                    detail: e.to_string(),
+                    stderr: None,
+                })
+            })?;
+        }
+
+        // e.g. mount a drive or restore saves before the app sees them
+        if let Some(hook) = &options.pre_launch {
+            run_hook_with_policy("pre_launch", hook, &session_id, &env, cwd.as_ref())
+                .await
+                .map_err(|e| {
+                    HostError::SpawnDiagnosed(SpawnDiagnostics {
+                        cause: SpawnFailureCause::HookFailed,
+                        program: command_name.clone(),
+                        detail: e.to_string(),
+                        stderr: None,
+                    })
+                })?;
+        }
+
+        // Camera access can only be revoked for Flatpak apps
+        let mut camera_restore = if options.device_access.camera {
+            None
+        } else {
+            match &flatpak_app_id {
+                Some(app_id) => match deny_flatpak_camera(app_id) {
+                    // The pid is filled in once the session has one
+                    Ok(previous) => Some(CameraRestore {
+                        pid: 0,
+                        app_id: app_id.clone(),
+                        previous,
+                    }),
+                    Err(e) => {
+                        warn!(error = %e, "Failed to block camera");
+                        None
+                    }
+                },
+                None => {
+                    warn!(command = %command_name, "Camera blocking is only supported for Flatpak entries");
+                    None
+                }
+            }
+        };
+
+        // Block the microphone before the app starts so it never sees an open source
+        let capture_backend = if options.device_access.microphone {
+            None
+        } else {
+            Self::block_microphone()
+        };
+
+        // Optionally nest the session inside gamescope. The wrapper shares the
+        // session's process group, so stopping the session tears down both.
+        let argv = match &options.gamescope {
+            Some(gamescope) => {
+                let mut wrapped = gamescope.wrapper_argv();
+                wrapped.extend(argv);
+                info!(argv = ?wrapped, "Wrapping session in gamescope");
+                wrapped
+            }
+            None => argv,
+        };
         
         let proc = ManagedProcess::spawn(
             &argv,
@@ -205,18 +701,54 @@ impl HostAdapter for LinuxHost {
             cwd.as_ref(),
             options.log_path.clone(),
             sandboxed_app_name,
-        )?;
+        )
+        .inspect_err(|_| {
+            if let Some((backend, false)) = capture_backend {
+                let _ = set_default_capture_muted(backend, false);
+            }
+            if let Some(restore) = &camera_restore {
+                restore.restore();
+            }
+        })?;
 
         let pid = proc.pid;
         let pgid = proc.pgid;
         
         // Store the session info so we can use it for killing even after process exits
         let session_info_entry = SessionInfo {
+            state: TrackedState::Running,
+            pid,
             command_name: command_name.clone(),
+            kill_by_name: options.kill_by_name,
             snap_name: snap_name.clone(),
             flatpak_app_id: flatpak_app_id.clone(),
+            wine: options.wine.as_ref().map(|wine| WineWatch {
+                prefix: expand_prefix(&wine.prefix),
+                start_timeout: wine.start_timeout,
+                // Snap and Flatpak scopes also hold the Steam client itself
+                cgroup: if snap_name.is_some() || flatpak_app_id.is_some() {
+                    None
+                } else {
+                    session_cgroups(pid, None, None).into_iter().next()
+                },
+            }),
         };
-        self.session_info.lock().unwrap().insert(session_id.clone(), session_info_entry);
+        {
+            let mut sessions = self.session_info.lock().unwrap();
+            sessions.retain(|_, info| info.state != TrackedState::Exited);
+            sessions.insert(session_id.clone(), session_info_entry);
+        }
+        if let Some((backend, was_muted)) = capture_backend {
+            *self.capture_restore.lock().unwrap() = Some(CaptureRestore {
+                pid,
+                backend,
+                was_muted,
+            });
+        }
+        if let Some(mut restore) = camera_restore.take() {
+            restore.pid = pid;
+            *self.camera_restore.lock().unwrap() = Some(restore);
+        }
         info!(session_id = %session_id, command = %command_name, snap = ?snap_name, flatpak = ?flatpak_app_id, "Tracking session info");
 
         let handle = HostSessionHandle::new(
@@ -225,6 +757,21 @@ impl HostAdapter for LinuxHost {
         );
 
         self.processes.lock().unwrap().insert(pid, proc);
+        self.monitor_wake.notify_one();
+        if self.capabilities.can_observe_window_ready {
+            self.watch_x11_window(handle.clone(), options.fullscreen, options.foreground);
+        }
+        if let Some(hook) = options.post_exit {
+            self.post_exit_hooks.lock().unwrap().insert(
+                pid,
+                PendingHook {
+                    session_id: handle.session_id.clone(),
+                    hook,
+                    env,
+                    cwd,
+                },
+            );
+        }
 
         info!(pid = pid, pgid = pgid, "Spawned process");
 
@@ -238,21 +785,42 @@ impl HostAdapter for LinuxHost {
             _ => return Err(HostError::SessionNotFound),
         };
 
-        // Get the session's info for killing
-        let session_info = self.session_info.lock().unwrap().get(&session_id).cloned();
+        // Get the session's info for killing, and mark it as stopping
+        let session_info = self
+            .session_info
+            .lock()
+            .unwrap()
+            .get_mut(&session_id)
+            .map(|info| {
+                if info.state == TrackedState::Running {
+                    info.state = TrackedState::Stopping;
+                }
+                info.clone()
+            });
         
         // Check if we have session info OR a tracked process
         let has_process = self.processes.lock().unwrap().contains_key(&pid);
-        
-        if session_info.is_none() && !has_process {
-            warn!(session_id = %session_id, pid = pid, "No session info or tracked process found");
-            return Err(HostError::SessionNotFound);
+
+        match session_info.as_ref().map(|info| info.state) {
+            // The monitor reaped it first; stopping is already done
+            Some(TrackedState::Exited) => {
+                debug!(session_id = %session_id, pid = pid, "Session already exited");
+                return Ok(());
+            }
+            None if !has_process => {
+                warn!(session_id = %session_id, pid = pid, "No session info or tracked process found");
+                return Err(HostError::SessionNotFound);
+            }
+            _ => {}
         }
 
         match mode {
             StopMode::Graceful { timeout } => {
                 // If this is a snap or flatpak app, use cgroup-based killing (most reliable)
                 if let Some(ref info) = session_info {
+                    if let Some(ref wine) = info.wine {
+                        signal_prefix(&wine.prefix, nix::sys::signal::Signal::SIGTERM);
+                    }
                     if let Some(ref snap) = info.snap_name {
                         kill_snap_cgroup(snap, nix::sys::signal::Signal::SIGTERM);
                         info!(snap = %snap, "Sent SIGTERM via snap cgroup");
@@ -260,9 +828,15 @@ impl HostAdapter for LinuxHost {
                         kill_flatpak_cgroup(app_id, nix::sys::signal::Signal::SIGTERM);
                         info!(flatpak = %app_id, "Sent SIGTERM via flatpak cgroup");
                     } else {
-                        // Fall back to command name for non-sandboxed apps
-                        kill_by_command(&info.command_name, nix::sys::signal::Signal::SIGTERM);
-                        info!(command = %info.command_name, "Sent SIGTERM via command name");
+                        // Processes that escaped the process group are still
+                        // in the session's cgroup, if it has one
+                        for dir in session_cgroups(pid, None, None) {
+                            signal_cgroup(&dir, nix::sys::signal::Signal::SIGTERM);
+                        }
+                        if info.kill_by_name {
+                            kill_by_command(&info.command_name, nix::sys::signal::Signal::SIGTERM);
+                            info!(command = %info.command_name, "Sent SIGTERM via command name");
+                        }
                     }
                 }
                 
@@ -278,19 +852,9 @@ impl HostAdapter for LinuxHost {
                 let start = std::time::Instant::now();
                 loop {
                     if start.elapsed() >= timeout {
-                        // Force kill after timeout using snap/flatpak cgroup or command name
-                        if let Some(ref info) = session_info {
-                            if let Some(ref snap) = info.snap_name {
-                                kill_snap_cgroup(snap, nix::sys::signal::Signal::SIGKILL);
-                                info!(snap = %snap, "Sent SIGKILL via snap cgroup (timeout)");
-                            } else if let Some(ref app_id) = info.flatpak_app_id {
-                                kill_flatpak_cgroup(app_id, nix::sys::signal::Signal::SIGKILL);
-                                info!(flatpak = %app_id, "Sent SIGKILL via flatpak cgroup (timeout)");
-                            } else {
-                                kill_by_command(&info.command_name, nix::sys::signal::Signal::SIGKILL);
-                                info!(command = %info.command_name, "Sent SIGKILL via command name (timeout)");
-                            }
-                        }
+                        // Force kill after timeout
+                        info!(pid = pid, "Graceful stop timed out, forcing");
+                        Self::force_kill(pid, session_info.as_ref());
                         
                         // Also force kill via process handle
                         let procs = self.processes.lock().unwrap();
@@ -300,8 +864,12 @@ impl HostAdapter for LinuxHost {
                         break;
                     }
 
-                    // Check if process is still running
-                    let still_running = self.processes.lock().unwrap().contains_key(&pid);
+                    // Check if process is still running, or the game it left
+                    let still_running = self.processes.lock().unwrap().contains_key(&pid)
+                        || session_info
+                            .as_ref()
+                            .and_then(|info| info.wine.as_ref())
+                            .is_some_and(|wine| !prefix_processes(&wine.prefix).is_empty());
                     
                     if !still_running {
                         break;
@@ -311,19 +879,7 @@ impl HostAdapter for LinuxHost {
                 }
             }
             StopMode::Force => {
-                // Force kill via snap/flatpak cgroup or command name
-                if let Some(ref info) = session_info {
-                    if let Some(ref snap) = info.snap_name {
-                        kill_snap_cgroup(snap, nix::sys::signal::Signal::SIGKILL);
-                        info!(snap = %snap, "Sent SIGKILL via snap cgroup");
-                    } else if let Some(ref app_id) = info.flatpak_app_id {
-                        kill_flatpak_cgroup(app_id, nix::sys::signal::Signal::SIGKILL);
-                        info!(flatpak = %app_id, "Sent SIGKILL via flatpak cgroup");
-                    } else {
-                        kill_by_command(&info.command_name, nix::sys::signal::Signal::SIGKILL);
-                        info!(command = %info.command_name, "Sent SIGKILL via command name");
-                    }
-                }
+                Self::force_kill(pid, session_info.as_ref());
                 
                 // Also force kill via process handle
                 let procs = self.processes.lock().unwrap();
@@ -332,9 +888,6 @@ impl HostAdapter for LinuxHost {
                 }
             }
         }
-        
-        // Clean up the session info tracking
-        self.session_info.lock().unwrap().remove(&session_id);
 
         Ok(())
     }
@@ -347,14 +900,230 @@ impl HostAdapter for LinuxHost {
             .expect("subscribe() can only be called once")
     }
 
+    async fn set_foreground(&self, handle: &HostSessionHandle) -> HostResult<()> {
+        if !self.capabilities.can_force_foreground {
+            return Err(HostError::unsupported("Not supported"));
+        }
+        let window = Self::first_window(handle)?;
+        activate_window(&window.id)
+    }
+
+    async fn set_fullscreen(&self, handle: &HostSessionHandle) -> HostResult<()> {
+        if !self.capabilities.can_force_fullscreen {
+            return Err(HostError::unsupported("Not supported"));
+        }
+        let window = Self::first_window(handle)?;
+        fullscreen_window(&window.id)
+    }
+
+    async fn idle_time(&self) -> HostResult<Duration> {
+        if !self.capabilities.can_detect_idle {
+            return Err(HostError::unsupported("Not supported"));
+        }
+        x11_idle_time()
+    }
+
+    async fn inhibit_input(&self) -> HostResult<()> {
+        if !self.capabilities.can_inhibit_input {
+            return Err(HostError::unsupported("Not supported"));
+        }
+        if self.input_inhibited.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        info!("Inhibiting input");
+        set_input_events_enabled(false).inspect_err(|_| {
+            self.input_inhibited.store(false, Ordering::SeqCst);
+        })
+    }
+
+    async fn release_input(&self) -> HostResult<()> {
+        if !self.input_inhibited.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        info!("Releasing input");
+        set_input_events_enabled(true).inspect_err(|_| {
+            self.input_inhibited.store(true, Ordering::SeqCst);
+        })
+    }
+
+    async fn register_panic_key(&self, combo: &str) -> HostResult<()> {
+        if !is_sway_available() {
+            return Err(HostError::unsupported("The panic key needs Sway"));
+        }
+        bind_panic_key(combo)?;
+        if !self.panic_key_watched.swap(true, Ordering::SeqCst) {
+            tokio::spawn(watch_panic_key(self.event_tx.clone()));
+        }
+        Ok(())
+    }
+
+    async fn kill_cgroup(&self, handle: &HostSessionHandle) -> HostResult<()> {
+        let HostHandlePayload::Linux { pid, .. } = handle.payload() else {
+            return Err(HostError::SessionNotFound);
+        };
+        let info = self.session_info.lock().unwrap().get(&handle.session_id).cloned();
+        if info.as_ref().is_some_and(|i| i.state == TrackedState::Exited) {
+            return Ok(());
+        }
+        if let Some(wine) = info.as_ref().and_then(|i| i.wine.as_ref()) {
+            signal_prefix(&wine.prefix, nix::sys::signal::Signal::SIGKILL);
+            if let Some(cgroup) = &wine.cgroup {
+                let _ = cgroup_kill(cgroup);
+            }
+        }
+        let snap = info.as_ref().and_then(|i| i.snap_name.as_deref());
+        let flatpak = info.as_ref().and_then(|i| i.flatpak_app_id.as_deref());
+        if kill_cgroups(&session_cgroups(*pid, snap, flatpak)) {
+            return Ok(());
+        }
+
+        // No cgroup.kill (kernel before 5.14): have systemd signal the scopes
+        let killed = match (snap, flatpak) {
+            (Some(snap), _) => kill_snap_cgroup(snap, nix::sys::signal::Signal::SIGKILL),
+            (_, Some(app_id)) => kill_flatpak_cgroup(app_id, nix::sys::signal::Signal::SIGKILL),
+            _ => false,
+        };
+        if killed {
+            Ok(())
+        } else {
+            Err(HostError::StopFailed("Session has no cgroup of its own".into()))
+        }
+    }
+
+    async fn freeze_session(&self, handle: &HostSessionHandle, frozen: bool) -> HostResult<()> {
+        let HostHandlePayload::Linux { pid, pgid } = handle.payload() else {
+            return Err(HostError::SessionNotFound);
+        };
+        let info = self.session_info.lock().unwrap().get(&handle.session_id).cloned();
+        if info.as_ref().is_some_and(|i| i.state == TrackedState::Exited) {
+            return Err(HostError::SessionNotFound);
+        }
+        let snap = info.as_ref().and_then(|i| i.snap_name.as_deref());
+        let flatpak = info.as_ref().and_then(|i| i.flatpak_app_id.as_deref());
+        let cgroups = session_cgroups(*pid, snap, flatpak);
+        if !cgroups.is_empty() && cgroups.iter().all(|dir| cgroup_freeze(dir, frozen).is_ok()) {
+            return Ok(());
+        }
+
+        // No cgroup or freezer: stop or continue the process group instead
+        let signal = if frozen {
+            nix::sys::signal::Signal::SIGSTOP
+        } else {
+            nix::sys::signal::Signal::SIGCONT
+        };
+        nix::sys::signal::killpg(nix::unistd::Pid::from_raw(*pgid as i32), signal)
+            .map_err(|e| HostError::StopFailed(format!("Failed to send {}: {}", signal, e)))?;
+        info!(pgid = pgid, frozen, "Froze session via process group signal");
+        Ok(())
+    }
+
+    fn is_session_alive(&self, handle: &HostSessionHandle) -> Option<bool> {
+        match self.session_info.lock().unwrap().get(&handle.session_id) {
+            Some(info) if info.state == TrackedState::Exited => return Some(false),
+            // Running until the monitor finds the prefix empty
+            Some(info) if info.wine.is_some() => return Some(true),
+            _ => {}
+        }
+        match handle.payload() {
+            HostHandlePayload::Linux { pid, .. } => Some(process_alive(*pid)),
+            _ => None,
+        }
+    }
+
+    async fn precheck(&self, entry_kind: &EntryKind, checks: &LaunchPrechecks) -> Vec<ReasonCode> {
+        run_prechecks(entry_kind, checks)
+    }
+
+    async fn check_entry_health(&self, entry_kind: &EntryKind) -> Option<String> {
+        probe_entry_health(entry_kind)
+    }
+
+    async fn discover_apps(&self) -> Vec<DiscoveredApp> {
+        discover_apps()
+    }
+
+    async fn audit_escape_routes(&self, mitigate: bool) -> Vec<EscapeRoute> {
+        audit_escape_routes(mitigate)
+    }
+
+    async fn update_entry(&self, entry_kind: &EntryKind) -> HostResult<()> {
+        update_app(entry_kind).await
+    }
+
+    async fn battery_status(&self) -> HostResult<BatteryInfo> {
+        if !self.capabilities.can_read_battery {
+            return Err(HostError::unsupported("No battery present"));
+        }
+        Ok(read_battery_status())
+    }
+
+    async fn media_status(&self, handle: &HostSessionHandle) -> HostResult<Option<MediaInfo>> {
+        let Some(pid) = handle.payload().pid() else {
+            return Err(HostError::SessionNotFound);
+        };
+        session_media(pid)
+    }
+
+    async fn set_media_playing(&self, handle: &HostSessionHandle, playing: bool) -> HostResult<()> {
+        let Some(pid) = handle.payload().pid() else {
+            return Err(HostError::SessionNotFound);
+        };
+        set_session_playing(pid, playing)
+    }
+
+    async fn power_action(&self, action: PowerAction) -> HostResult<()> {
+        logind_power_action(action)
+    }
+
     fn is_healthy(&self) -> bool {
         true
     }
+
+    fn last_monitor_tick(&self) -> Option<Instant> {
+        *self.monitor_tick.lock().unwrap()
+    }
+
+    /// Tracking maps locked by another thread are skipped rather than
+    /// waited on, since that thread may be the one that panicked.
+    fn kill_all_now(&self) -> usize {
+        let mut killed = 0;
+
+        if let Ok(sessions) = self.session_info.try_lock() {
+            for info in sessions.values().filter(|i| i.state != TrackedState::Exited) {
+                if let Some(ref wine) = info.wine {
+                    signal_prefix(&wine.prefix, nix::sys::signal::Signal::SIGKILL);
+                }
+                if let Some(ref snap) = info.snap_name {
+                    kill_snap_cgroup(snap, nix::sys::signal::Signal::SIGKILL);
+                } else if let Some(ref app_id) = info.flatpak_app_id {
+                    kill_flatpak_cgroup(app_id, nix::sys::signal::Signal::SIGKILL);
+                } else if !kill_cgroups(&session_cgroups(info.pid, None, None)) && info.kill_by_name {
+                    kill_by_command(&info.command_name, nix::sys::signal::Signal::SIGKILL);
+                }
+                killed += 1;
+            }
+        }
+
+        if let Ok(procs) = self.processes.try_lock() {
+            for proc in procs.values() {
+                let _ = proc.kill();
+            }
+            killed = killed.max(procs.len());
+        }
+
+        // Don't leave the keyboard and mouse disabled behind a dead daemon
+        if self.input_inhibited.swap(false, Ordering::SeqCst) {
+            let _ = set_input_events_enabled(true);
+        }
+
+        killed
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use shepherd_host_api::WineOptions;
 
     #[tokio::test]
     async fn test_spawn_and_exit() {
@@ -417,4 +1186,218 @@ mod tests {
         .await
         .unwrap();
     }
+
+    #[tokio::test]
+    async fn test_stop_spares_same_named_processes() {
+        let host = LinuxHost::new();
+        let _rx = host.subscribe();
+
+        let mut bystander = std::process::Command::new("sleep").arg("61").spawn().unwrap();
+        let entry = EntryKind::Process {
+            command: "sleep".into(),
+            args: vec!["61".into()],
+            env: HashMap::new(),
+            cwd: None,
+        };
+        let handle = host
+            .spawn(SessionId::new(), &entry, SpawnOptions::default())
+            .await
+            .unwrap();
+
+        host.stop(&handle, StopMode::Force).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(bystander.try_wait().unwrap().is_none());
+
+        bystander.kill().unwrap();
+        bystander.wait().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_freeze_session() {
+        let host = LinuxHost::new();
+        let _rx = host.subscribe();
+
+        let entry = EntryKind::Process {
+            command: "sleep".into(),
+            args: vec!["60".into()],
+            env: HashMap::new(),
+            cwd: None,
+        };
+        let handle = host
+            .spawn(SessionId::new(), &entry, SpawnOptions::default())
+            .await
+            .unwrap();
+        let HostHandlePayload::Linux { pid, .. } = *handle.payload() else {
+            panic!("Expected Linux handle");
+        };
+        let state = || {
+            let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap();
+            stat.rsplit_once(')').unwrap().1.trim_start().chars().next().unwrap()
+        };
+
+        // The child shares the test's cgroup, so this falls back to SIGSTOP
+        host.freeze_session(&handle, true).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(state(), 'T');
+        host.freeze_session(&handle, false).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(state(), 'S');
+
+        host.stop(&handle, StopMode::Force).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_exit_noticed_between_heartbeats() {
+        let host = LinuxHost::new();
+        let mut rx = host.subscribe();
+        let _monitor = host.start_monitor();
+
+        let entry = EntryKind::Process {
+            command: "sleep".into(),
+            args: vec!["0.5".into()],
+            env: HashMap::new(),
+            cwd: None,
+        };
+        let started = Instant::now();
+        host.spawn(SessionId::new(), &entry, SpawnOptions::default())
+            .await
+            .unwrap();
+
+        // Long before the next heartbeat: the pidfd (or polling) saw it
+        let event = tokio::time::timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .expect("exit not noticed")
+            .unwrap();
+        assert!(matches!(event, HostEvent::Exited { .. }));
+        assert!(started.elapsed() < MONITOR_HEARTBEAT);
+    }
+
+    #[tokio::test]
+    async fn test_wine_session_follows_prefix() {
+        let host = LinuxHost::new();
+        let mut rx = host.subscribe();
+        let _monitor = host.start_monitor();
+        let prefix = tempfile::tempdir().unwrap();
+        let options = || SpawnOptions {
+            wine: Some(WineOptions {
+                prefix: prefix.path().to_path_buf(),
+                start_timeout: Duration::from_secs(5),
+            }),
+            ..Default::default()
+        };
+        // Like Steam: the launched process hands the game off and exits
+        let entry = |game_secs: &str| EntryKind::Process {
+            command: "sh".into(),
+            args: vec!["-c".into(), format!("sleep {} & exit 0", game_secs)],
+            env: HashMap::from([("WINEPREFIX".to_string(), prefix.path().display().to_string())]),
+            cwd: None,
+        };
+
+        let started = Instant::now();
+        host.spawn(SessionId::new(), &entry("1.5"), options()).await.unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("game exit not noticed")
+            .unwrap();
+        assert!(matches!(event, HostEvent::Exited { .. }));
+        assert!(started.elapsed() >= Duration::from_millis(1500));
+
+        // Stopping tears the prefix down
+        let handle = host.spawn(SessionId::new(), &entry("30"), options()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(host.is_session_alive(&handle), Some(true));
+        host.stop(&handle, StopMode::Graceful { timeout: Duration::from_secs(2) })
+            .await
+            .unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("stopped session not noticed")
+            .unwrap();
+        assert!(matches!(event, HostEvent::Exited { .. }));
+        assert!(prefix_processes(prefix.path()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stop_after_exit() {
+        let host = LinuxHost::new();
+        let mut rx = host.subscribe();
+        let _monitor = host.start_monitor();
+
+        let session_id = SessionId::new();
+        let entry = EntryKind::Process {
+            command: "true".into(),
+            args: vec![],
+            env: HashMap::new(),
+            cwd: None,
+        };
+        let handle = host
+            .spawn(session_id.clone(), &entry, SpawnOptions::default())
+            .await
+            .unwrap();
+
+        // The exit event names the session that exited
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let HostEvent::Exited { handle: exited, .. } = event else {
+            panic!("Expected Exited event");
+        };
+        assert_eq!(exited.session_id, session_id);
+
+        // The monitor reaped it first; stopping it is not an error
+        assert_eq!(host.is_session_alive(&handle), Some(false));
+        host.stop(&handle, StopMode::Force).await.unwrap();
+        host.stop(
+            &handle,
+            StopMode::Graceful {
+                timeout: Duration::from_secs(1),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_spawn_diagnostics() {
+        let host = LinuxHost::new();
+        let mut rx = host.subscribe();
+        let _monitor = host.start_monitor();
+
+        let missing = EntryKind::Process {
+            command: "shepherd-no-such-program".into(),
+            args: vec![],
+            env: HashMap::new(),
+            cwd: None,
+        };
+        let err = host
+            .spawn(SessionId::new(), &missing, SpawnOptions::default())
+            .await
+            .unwrap_err();
+        let diagnostics = err.spawn_diagnostics().unwrap();
+        assert_eq!(diagnostics.cause, SpawnFailureCause::NotFound);
+
+        // A program that fails right away reports what it printed
+        let session_id = SessionId::new();
+        let failing = EntryKind::Process {
+            command: "sh".into(),
+            args: vec!["-c".into(), "echo 'Error: cannot open display: :9' >&2; exit 1".into()],
+            env: HashMap::new(),
+            cwd: None,
+        };
+        host.spawn(session_id.clone(), &failing, SpawnOptions::default())
+            .await
+            .unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let HostEvent::SpawnFailed { session_id: failed, diagnostics } = event else {
+            panic!("Expected SpawnFailed event");
+        };
+        assert_eq!(failed, session_id);
+        assert_eq!(diagnostics.cause, SpawnFailureCause::NoDisplay);
+        assert_eq!(diagnostics.stderr.as_deref(), Some("Error: cannot open display: :9"));
+        assert!(matches!(rx.recv().await, Some(HostEvent::Exited { .. })));
+    }
 }
@@ -0,0 +1,87 @@
+//! Battery monitoring via sysfs
+//!
+//! Reads `/sys/class/power_supply` for battery charge and AC adapter state.
+
+use shepherd_api::BatteryInfo;
+use std::fs;
+use std::path::Path;
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// Check whether the host has a battery
+pub fn has_battery() -> bool {
+    fs::read_dir(POWER_SUPPLY_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|e| e.file_name().to_string_lossy().starts_with("BAT"))
+        })
+        .unwrap_or(false)
+}
+
+/// Read battery status from sysfs
+pub fn read_battery_status() -> BatteryInfo {
+    let mut status = BatteryInfo::default();
+
+    let Ok(entries) = fs::read_dir(POWER_SUPPLY_DIR) else {
+        return status;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name_str = name.to_string_lossy();
+
+        // Check for battery
+        if name_str.starts_with("BAT")
+            && let Some((percent, charging)) = read_battery_info(&path) {
+                status.percent = Some(percent);
+                status.charging = charging;
+            }
+
+        // Check for AC adapter
+        if (name_str.starts_with("AC") || name_str.contains("ADP"))
+            && let Some(online) = read_ac_status(&path) {
+                status.ac_connected = online;
+            }
+    }
+
+    status
+}
+
+fn read_battery_info(path: &Path) -> Option<(u8, bool)> {
+    // Read capacity
+    let capacity: u8 = fs::read_to_string(path.join("capacity"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+
+    // Read status
+    let status = fs::read_to_string(path.join("status")).ok()?;
+    let charging = status.trim().eq_ignore_ascii_case("charging")
+        || status.trim().eq_ignore_ascii_case("full");
+
+    Some((capacity.min(100), charging))
+}
+
+fn read_ac_status(path: &Path) -> Option<bool> {
+    let online = fs::read_to_string(path.join("online")).ok()?;
+    Some(online.trim() == "1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_battery_info() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("capacity"), "42\n").unwrap();
+        fs::write(dir.path().join("status"), "Discharging\n").unwrap();
+        assert_eq!(read_battery_info(dir.path()), Some((42, false)));
+
+        fs::write(dir.path().join("status"), "Full\n").unwrap();
+        assert_eq!(read_battery_info(dir.path()), Some((42, true)));
+    }
+}
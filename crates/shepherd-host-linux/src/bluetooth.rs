@@ -0,0 +1,193 @@
+//! Bluetooth input device monitoring
+//!
+//! Reports paired controllers, keyboards and mice along with their battery
+//! levels by querying BlueZ through `bluetoothctl`.
+
+use async_trait::async_trait;
+use shepherd_api::{InputDeviceInfo, InputDeviceKind};
+use shepherd_host_api::{HostError, HostResult, InputDeviceMonitor};
+use std::process::Command;
+use tracing::{debug, info, warn};
+
+/// Input device monitor backed by BlueZ
+pub struct BluetoothInputMonitor {
+    available: bool,
+}
+
+impl BluetoothInputMonitor {
+    pub fn new() -> Self {
+        let available = Command::new("bluetoothctl")
+            .arg("--version")
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false);
+
+        if available {
+            info!("Bluetooth input device monitoring available");
+        } else {
+            warn!("bluetoothctl not found, input device monitoring unavailable");
+        }
+
+        Self { available }
+    }
+
+    fn run_bluetoothctl(args: &[&str]) -> HostResult<String> {
+        let output = Command::new("bluetoothctl")
+            .args(args)
+            .output()
+            .map_err(|e| HostError::exec("bluetoothctl", &e))?;
+
+        if !output.status.success() {
+            return Err(HostError::command_failed(format!(
+                "bluetoothctl {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn paired_addresses() -> HostResult<Vec<String>> {
+        // `devices Paired` needs BlueZ 5.65+; older versions use `paired-devices`
+        let output = Self::run_bluetoothctl(&["devices", "Paired"])
+            .or_else(|_| Self::run_bluetoothctl(&["paired-devices"]))?;
+        Ok(parse_device_list(&output))
+    }
+}
+
+impl Default for BluetoothInputMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl InputDeviceMonitor for BluetoothInputMonitor {
+    fn is_available(&self) -> bool {
+        self.available
+    }
+
+    async fn list_devices(&self) -> HostResult<Vec<InputDeviceInfo>> {
+        if !self.available {
+            return Err(HostError::unsupported("bluetoothctl not available"));
+        }
+
+        let mut devices = Vec::new();
+        for address in Self::paired_addresses()? {
+            match Self::run_bluetoothctl(&["info", &address]) {
+                Ok(output) => {
+                    if let Some(device) = parse_device_info(&address, &output) {
+                        devices.push(device);
+                    }
+                }
+                Err(e) => {
+                    debug!(address = %address, error = %e, "Failed to query device info");
+                }
+            }
+        }
+
+        Ok(devices)
+    }
+}
+
+/// Parse `bluetoothctl devices` output into a list of addresses
+fn parse_device_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next()) {
+                (Some("Device"), Some(address)) => Some(address.to_string()),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Parse `bluetoothctl info <address>` output.
+///
+/// Returns `None` for devices that aren't input devices (headsets, phones, etc).
+fn parse_device_info(address: &str, output: &str) -> Option<InputDeviceInfo> {
+    let mut name = None;
+    let mut icon = None;
+    let mut paired = false;
+    let mut connected = false;
+    let mut battery_percent = None;
+
+    for line in output.lines() {
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "Name" => name = Some(value.to_string()),
+            "Alias" if name.is_none() => name = Some(value.to_string()),
+            "Icon" => icon = Some(value.to_string()),
+            "Paired" => paired = value == "yes",
+            "Connected" => connected = value == "yes",
+            // Format: "0x64 (100)"
+            "Battery Percentage" => {
+                battery_percent = value
+                    .split_once('(')
+                    .and_then(|(_, rest)| rest.trim_end_matches(')').parse::<u8>().ok())
+                    .map(|p| p.min(100));
+            }
+            _ => {}
+        }
+    }
+
+    let kind = match icon.as_deref()? {
+        "input-gaming" => InputDeviceKind::Gamepad,
+        "input-keyboard" => InputDeviceKind::Keyboard,
+        "input-mouse" | "input-tablet" => InputDeviceKind::Mouse,
+        other if other.starts_with("input-") => InputDeviceKind::Other,
+        _ => return None,
+    };
+
+    Some(InputDeviceInfo {
+        name: name.unwrap_or_else(|| address.to_string()),
+        address: address.to_string(),
+        kind,
+        paired,
+        connected,
+        battery_percent,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_device_list() {
+        let output = "Device AA:BB:CC:DD:EE:FF Xbox Wireless Controller\n\
+                      Device 11:22:33:44:55:66 Headphones\n";
+        assert_eq!(
+            parse_device_list(output),
+            vec!["AA:BB:CC:DD:EE:FF", "11:22:33:44:55:66"]
+        );
+    }
+
+    #[test]
+    fn test_parse_device_info() {
+        let output = "Device AA:BB:CC:DD:EE:FF (public)\n\
+                      \tName: Xbox Wireless Controller\n\
+                      \tAlias: Xbox Wireless Controller\n\
+                      \tIcon: input-gaming\n\
+                      \tPaired: yes\n\
+                      \tConnected: yes\n\
+                      \tBattery Percentage: 0x4b (75)\n";
+        let device = parse_device_info("AA:BB:CC:DD:EE:FF", output).unwrap();
+        assert_eq!(device.name, "Xbox Wireless Controller");
+        assert_eq!(device.kind, InputDeviceKind::Gamepad);
+        assert!(device.paired);
+        assert!(device.connected);
+        assert_eq!(device.battery_percent, Some(75));
+
+        let headphones = "Device 11:22:33:44:55:66 (public)\n\
+                          \tName: Headphones\n\
+                          \tIcon: audio-headphones\n";
+        assert!(parse_device_info("11:22:33:44:55:66", headphones).is_none());
+    }
+}
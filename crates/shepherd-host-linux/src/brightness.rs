@@ -0,0 +1,188 @@
+//! Linux display brightness implementation
+//!
+//! Backlight control goes through `brightnessctl`, which works without root
+//! for users in the `video` group. Night light is applied by running
+//! `gammastep` in one-shot mode; on Wayland the gamma ramp is only held while
+//! the client is connected, so the child is kept alive until night light is
+//! turned off again.
+
+use async_trait::async_trait;
+use shepherd_host_api::{BrightnessCapabilities, BrightnessController, HostError, HostResult};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// Linux brightness controller
+pub struct LinuxBrightnessController {
+    capabilities: BrightnessCapabilities,
+    /// Running gammastep process holding the night-light gamma ramp
+    night_light: Mutex<Option<(u32, Child)>>,
+}
+
+impl LinuxBrightnessController {
+    pub fn new() -> Self {
+        let available = Self::is_brightnessctl_available();
+        let can_night_light = Self::is_gammastep_available();
+
+        if available {
+            info!("Detected brightnessctl backlight control");
+        } else {
+            warn!("brightnessctl not found or no backlight device, brightness control unavailable");
+        }
+        if !can_night_light {
+            debug!("gammastep not found, night light unavailable");
+        }
+
+        Self {
+            capabilities: BrightnessCapabilities {
+                available,
+                backend: available.then(|| "brightnessctl".to_string()),
+                can_night_light,
+            },
+            night_light: Mutex::new(None),
+        }
+    }
+
+    fn is_brightnessctl_available() -> bool {
+        // Fails when there is no backlight device (e.g. desktops with external monitors)
+        Command::new("brightnessctl")
+            .args(["--class=backlight", "-m", "info"])
+            .output()
+            .map(|o| o.status.success() && !o.stdout.is_empty())
+            .unwrap_or(false)
+    }
+
+    fn is_gammastep_available() -> bool {
+        Command::new("gammastep")
+            .arg("-V")
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+
+    fn stop_night_light(slot: &mut Option<(u32, Child)>) {
+        if let Some((temperature, mut child)) = slot.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+            info!(temperature = temperature, "Night light disabled");
+        }
+    }
+}
+
+impl Default for LinuxBrightnessController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for LinuxBrightnessController {
+    fn drop(&mut self) {
+        if let Ok(mut slot) = self.night_light.lock() {
+            Self::stop_night_light(&mut slot);
+        }
+    }
+}
+
+#[async_trait]
+impl BrightnessController for LinuxBrightnessController {
+    fn capabilities(&self) -> &BrightnessCapabilities {
+        &self.capabilities
+    }
+
+    async fn get_brightness(&self) -> HostResult<u8> {
+        if !self.capabilities.available {
+            return Err(HostError::unsupported("Brightness control not available"));
+        }
+
+        let output = Command::new("brightnessctl")
+            .args(["--class=backlight", "-m", "info"])
+            .output()
+            .map_err(|e| HostError::exec("brightnessctl", &e))?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        parse_brightnessctl_percent(&stdout).ok_or_else(|| {
+            HostError::command_failed(format!("Unexpected brightnessctl output: {}", stdout.trim()))
+        })
+    }
+
+    async fn set_brightness(&self, percent: u8) -> HostResult<()> {
+        if !self.capabilities.available {
+            return Err(HostError::unsupported("Brightness control not available"));
+        }
+
+        let value = format!("{}%", percent.min(100));
+        let status = Command::new("brightnessctl")
+            .args(["--class=backlight", "-q", "set", &value])
+            .status()
+            .map_err(|e| HostError::exec("brightnessctl", &e))?;
+
+        if !status.success() {
+            return Err(HostError::command_failed(format!("brightnessctl set {} failed", value)));
+        }
+
+        debug!(percent = percent, "Set backlight brightness");
+        Ok(())
+    }
+
+    async fn set_night_light(&self, temperature: Option<u32>) -> HostResult<()> {
+        if !self.capabilities.can_night_light {
+            return Err(HostError::unsupported("Night light not available"));
+        }
+
+        let mut slot = self
+            .night_light
+            .lock()
+            .map_err(|_| HostError::internal("Night light state poisoned"))?;
+
+        if let Some((current, _)) = slot.as_ref()
+            && Some(*current) == temperature
+        {
+            return Ok(());
+        }
+
+        Self::stop_night_light(&mut slot);
+
+        if let Some(temperature) = temperature {
+            let child = Command::new("gammastep")
+                .args(["-P", "-O", &temperature.to_string()])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .map_err(|e| HostError::exec("gammastep", &e))?;
+            info!(temperature = temperature, "Night light enabled");
+            *slot = Some((temperature, child));
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse the current percentage from `brightnessctl -m info` output.
+///
+/// Format: `intel_backlight,backlight,512,50%,1024`
+fn parse_brightnessctl_percent(output: &str) -> Option<u8> {
+    output
+        .lines()
+        .next()?
+        .split(',')
+        .nth(3)?
+        .trim_end_matches('%')
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_brightnessctl_percent() {
+        assert_eq!(
+            parse_brightnessctl_percent("intel_backlight,backlight,512,50%,1024\n"),
+            Some(50)
+        );
+        assert_eq!(parse_brightnessctl_percent(""), None);
+        assert_eq!(parse_brightnessctl_percent("garbage"), None);
+    }
+}
@@ -0,0 +1,214 @@
+//! Camera and microphone access control
+//!
+//! The microphone is blocked by muting the default capture source for the
+//! duration of a session. Camera access can only be revoked for Flatpak apps,
+//! through the portal permission store; other kinds open `/dev/video*`
+//! directly and cannot be restricted without elevated privileges.
+
+use shepherd_host_api::{HostError, HostResult};
+use std::process::Command;
+use tracing::{debug, info, warn};
+
+use crate::SoundBackend;
+
+/// Capture source state to restore when a session ends
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CaptureRestore {
+    /// PID of the session whose exit triggers the restore
+    pub pid: u32,
+    pub backend: SoundBackend,
+    /// Whether the source was already muted before the session
+    pub was_muted: bool,
+}
+
+impl CaptureRestore {
+    /// Unmute the capture source unless it was muted before the session
+    pub fn restore(&self) {
+        if self.was_muted {
+            return;
+        }
+        info!(pid = self.pid, "Restoring microphone after session");
+        if let Err(e) = set_default_capture_muted(self.backend, false) {
+            warn!(error = %e, "Failed to restore microphone");
+        }
+    }
+}
+
+/// Check whether the default capture source is muted
+pub fn default_capture_muted(backend: SoundBackend) -> HostResult<bool> {
+    let (program, args): (&str, &[&str]) = match backend {
+        // Output: "Volume: 1.00" or "Volume: 1.00 [MUTED]"
+        SoundBackend::PipeWire => ("wpctl", &["get-volume", "@DEFAULT_AUDIO_SOURCE@"]),
+        // Output: "Mute: yes"
+        SoundBackend::PulseAudio => ("pactl", &["get-source-mute", "@DEFAULT_SOURCE@"]),
+        // Output includes: "Front Left: Capture 40 [63%] [0.00dB] [off]"
+        SoundBackend::Alsa => ("amixer", &["sget", "Capture"]),
+    };
+
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| HostError::exec(program, &e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    debug!(program = program, output = %stdout.trim(), "Capture source state");
+
+    Ok(match backend {
+        SoundBackend::PipeWire => stdout.contains("[MUTED]"),
+        SoundBackend::PulseAudio => stdout.contains("yes"),
+        SoundBackend::Alsa => stdout.contains("[off]"),
+    })
+}
+
+/// Mute or unmute the default capture source
+pub fn set_default_capture_muted(backend: SoundBackend, muted: bool) -> HostResult<()> {
+    let (program, args): (&str, Vec<&str>) = match backend {
+        SoundBackend::PipeWire => (
+            "wpctl",
+            vec!["set-mute", "@DEFAULT_AUDIO_SOURCE@", if muted { "1" } else { "0" }],
+        ),
+        SoundBackend::PulseAudio => (
+            "pactl",
+            vec!["set-source-mute", "@DEFAULT_SOURCE@", if muted { "1" } else { "0" }],
+        ),
+        SoundBackend::Alsa => (
+            "amixer",
+            vec!["sset", "Capture", if muted { "nocap" } else { "cap" }],
+        ),
+    };
+
+    let status = Command::new(program)
+        .args(&args)
+        .status()
+        .map_err(|e| HostError::exec(program, &e))?;
+
+    if !status.success() {
+        return Err(HostError::command_failed(format!(
+            "{} {} failed",
+            program,
+            args.join(" ")
+        )));
+    }
+    Ok(())
+}
+
+/// Flatpak camera permission to restore when a session ends
+#[derive(Debug, Clone)]
+pub(crate) struct CameraRestore {
+    /// PID of the session whose exit triggers the restore
+    pub pid: u32,
+    pub app_id: String,
+    /// The app's permission before the session, `None` if it had none
+    pub previous: Option<String>,
+}
+
+impl CameraRestore {
+    /// Put the app's camera permission back the way it was
+    pub fn restore(&self) {
+        info!(pid = self.pid, app_id = %self.app_id, "Restoring camera permission after session");
+        let args = self.restore_args();
+        match Command::new("flatpak").args(&args).status() {
+            Ok(status) if status.success() => {}
+            Ok(_) => warn!(app_id = %self.app_id, "Failed to restore camera permission"),
+            Err(e) => warn!(error = %e, "Failed to restore camera permission"),
+        }
+    }
+
+    /// `flatpak` arguments that undo the denial
+    fn restore_args(&self) -> Vec<&str> {
+        match &self.previous {
+            Some(previous) => vec!["permission-set", "devices", "camera", &self.app_id, previous],
+            None => vec!["permission-remove", "devices", "camera", &self.app_id],
+        }
+    }
+}
+
+/// A Flatpak app's camera permission in the portal permission store
+pub fn flatpak_camera_permission(app_id: &str) -> HostResult<Option<String>> {
+    let output = Command::new("flatpak")
+        .args(["permission-show", app_id])
+        .output()
+        .map_err(|e| HostError::exec("flatpak", &e))?;
+
+    if !output.status.success() {
+        return Err(HostError::command_failed(format!(
+            "Failed to read camera permission for {}",
+            app_id
+        )));
+    }
+    Ok(parse_camera_permission(&String::from_utf8_lossy(&output.stdout), app_id))
+}
+
+/// Find the camera row in `flatpak permission-show` output:
+/// "Table Object App Permissions Data", one row per line
+fn parse_camera_permission(output: &str, app_id: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["devices", "camera", app, permission, ..] if *app == app_id => {
+                Some(permission.to_string())
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Deny camera access to a Flatpak app via the portal permission store.
+///
+/// The store outlives the session, so this returns the app's permission
+/// before, for a [`CameraRestore`] to put back when the session ends.
+pub fn deny_flatpak_camera(app_id: &str) -> HostResult<Option<String>> {
+    let previous = flatpak_camera_permission(app_id)?;
+    let status = Command::new("flatpak")
+        .args(["permission-set", "devices", "camera", app_id, "no"])
+        .status()
+        .map_err(|e| HostError::exec("flatpak", &e))?;
+
+    if !status.success() {
+        return Err(HostError::command_failed(format!(
+            "Failed to deny camera permission for {}",
+            app_id
+        )));
+    }
+    info!(app_id = %app_id, previous = ?previous, "Denied camera permission");
+    Ok(previous)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_camera_permission() {
+        let output = "\
+Table   Object App                Permissions Data
+devices camera org.example.Other  no          0x00
+devices camera org.example.App    yes         0x00
+";
+        assert_eq!(
+            parse_camera_permission(output, "org.example.App").as_deref(),
+            Some("yes")
+        );
+        assert_eq!(parse_camera_permission(output, "org.example.Missing"), None);
+        assert_eq!(parse_camera_permission("", "org.example.App"), None);
+    }
+
+    #[test]
+    fn test_camera_restore_args() {
+        let mut restore = CameraRestore {
+            pid: 1,
+            app_id: "org.example.App".into(),
+            previous: Some("yes".into()),
+        };
+        assert_eq!(
+            restore.restore_args(),
+            ["permission-set", "devices", "camera", "org.example.App", "yes"]
+        );
+
+        // An app the store knew nothing about goes back to asking
+        restore.previous = None;
+        assert_eq!(
+            restore.restore_args(),
+            ["permission-remove", "devices", "camera", "org.example.App"]
+        );
+    }
+}
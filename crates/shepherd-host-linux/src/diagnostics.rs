@@ -0,0 +1,205 @@
+//! Spawn diagnostics
+//!
+//! Explains a failed launch: the OS error when the program can't be
+//! executed, or, when it exits with an error right after starting, what it
+//! printed to stderr. Common causes are classified so the launcher can say
+//! "Snap foo is not installed" instead of "exit status 1".
+
+use shepherd_api::{SpawnDiagnostics, SpawnFailureCause};
+use shepherd_host_api::ExitStatus;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::ChildStderr;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use crate::health::{flatpak_installed, snap_installed};
+use crate::precheck::display_reachable;
+
+/// A session that fails within this long of starting failed to launch
+pub const EARLY_EXIT_WINDOW: Duration = Duration::from_secs(3);
+
+/// How many lines of stderr to keep
+const TAIL_LINES: usize = 20;
+
+/// How long to wait for the last stderr lines after the program exits
+const TAIL_DRAIN_TIMEOUT: Duration = Duration::from_millis(200);
+
+#[derive(Default)]
+struct Tail {
+    lines: VecDeque<String>,
+    closed: bool,
+}
+
+/// The last lines a child wrote to stderr. Everything is still copied
+/// through to the service's own stderr.
+#[derive(Clone, Default)]
+pub struct StderrTail(Arc<(Mutex<Tail>, Condvar)>);
+
+impl StderrTail {
+    pub fn capture(stderr: ChildStderr) -> Self {
+        let tail = Self::default();
+        let shared = tail.clone();
+        std::thread::spawn(move || shared.read_from(stderr));
+        tail
+    }
+
+    fn read_from(&self, stderr: impl Read) {
+        let (tail, closed) = &*self.0;
+        for line in BufReader::new(stderr).lines() {
+            let Ok(line) = line else { break };
+            eprintln!("{}", line);
+            let mut tail = tail.lock().unwrap();
+            if tail.lines.len() == TAIL_LINES {
+                tail.lines.pop_front();
+            }
+            tail.lines.push_back(line);
+        }
+        tail.lock().unwrap().closed = true;
+        closed.notify_all();
+    }
+
+    /// The captured lines, waiting briefly for the pipe to drain
+    pub fn text(&self) -> Option<String> {
+        let (tail, closed) = &*self.0;
+        let tail = closed
+            .wait_timeout_while(tail.lock().unwrap(), TAIL_DRAIN_TIMEOUT, |t| !t.closed)
+            .unwrap()
+            .0;
+        let lines: Vec<&str> = tail.lines.iter().map(String::as_str).collect();
+        non_empty(lines.join("\n"))
+    }
+}
+
+/// The last lines of a session's log file
+pub fn log_tail(path: &Path) -> Option<String> {
+    let log = std::fs::read_to_string(path).ok()?;
+    let lines: Vec<&str> = log.lines().collect();
+    non_empty(lines[lines.len().saturating_sub(TAIL_LINES)..].join("\n"))
+}
+
+fn non_empty(text: String) -> Option<String> {
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// Diagnose a program that couldn't be executed at all
+pub fn exec_failure(program: &str, error: &std::io::Error) -> SpawnDiagnostics {
+    let cause = match error.kind() {
+        std::io::ErrorKind::NotFound => SpawnFailureCause::NotFound,
+        std::io::ErrorKind::PermissionDenied => SpawnFailureCause::PermissionDenied,
+        _ => SpawnFailureCause::Other,
+    };
+    SpawnDiagnostics {
+        cause,
+        program: program.to_string(),
+        detail: error.to_string(),
+        stderr: None,
+    }
+}
+
+/// Diagnose a session that exited with an error right after starting.
+/// `program` is the snap name or Flatpak app ID for sandboxed apps.
+/// Blocking: may ask snap or flatpak whether the app is installed.
+pub fn early_exit(
+    program: &str,
+    snap_name: Option<&str>,
+    flatpak_app_id: Option<&str>,
+    status: &ExitStatus,
+    stderr: Option<String>,
+) -> SpawnDiagnostics {
+    let cause = stderr
+        .as_deref()
+        .and_then(classify_stderr)
+        .or_else(|| sandbox_missing(snap_name, flatpak_app_id))
+        .or_else(|| (!display_reachable()).then_some(SpawnFailureCause::NoDisplay))
+        .unwrap_or(SpawnFailureCause::ExitedEarly);
+    let detail = match (status.code, status.signal) {
+        (_, Some(signal)) => format!("killed by signal {}", signal),
+        (Some(code), None) => format!("exited with status {}", code),
+        (None, None) => "exited".to_string(),
+    };
+    SpawnDiagnostics {
+        cause,
+        program: program.to_string(),
+        detail,
+        stderr,
+    }
+}
+
+/// Recognize common failures from what the program printed
+fn classify_stderr(stderr: &str) -> Option<SpawnFailureCause> {
+    let stderr = stderr.to_lowercase();
+    if stderr.contains("snap \"") && stderr.contains("not installed") {
+        Some(SpawnFailureCause::SnapNotInstalled)
+    } else if stderr.contains("app/") && stderr.contains("not installed") {
+        Some(SpawnFailureCause::FlatpakNotInstalled)
+    } else if [
+        "cannot open display",
+        "can't open display",
+        "could not connect to display",
+        "failed to connect to wayland",
+        "cannot connect to wayland",
+    ]
+    .iter()
+    .any(|pattern| stderr.contains(pattern))
+    {
+        Some(SpawnFailureCause::NoDisplay)
+    } else if stderr.contains("command not found") {
+        Some(SpawnFailureCause::NotFound)
+    } else {
+        None
+    }
+}
+
+fn sandbox_missing(snap_name: Option<&str>, flatpak_app_id: Option<&str>) -> Option<SpawnFailureCause> {
+    match (snap_name, flatpak_app_id) {
+        (Some(snap_name), _) if !snap_installed(snap_name) => {
+            Some(SpawnFailureCause::SnapNotInstalled)
+        }
+        (None, Some(app_id)) if !flatpak_installed(app_id) => {
+            Some(SpawnFailureCause::FlatpakNotInstalled)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_stderr() {
+        assert_eq!(
+            classify_stderr("error: snap \"minecraft\" is not installed"),
+            Some(SpawnFailureCause::SnapNotInstalled)
+        );
+        assert_eq!(
+            classify_stderr("error: app/org.example.Game/x86_64/master not installed"),
+            Some(SpawnFailureCause::FlatpakNotInstalled)
+        );
+        assert_eq!(
+            classify_stderr("Error: cannot open display: :0"),
+            Some(SpawnFailureCause::NoDisplay)
+        );
+        assert_eq!(classify_stderr("Segmentation fault"), None);
+    }
+
+    #[test]
+    fn test_exec_failure() {
+        let error = std::io::Error::from(std::io::ErrorKind::NotFound);
+        let diagnostics = exec_failure("supertux2", &error);
+        assert_eq!(diagnostics.cause, SpawnFailureCause::NotFound);
+        assert_eq!(diagnostics.summary(), "supertux2 is not installed");
+    }
+
+    #[test]
+    fn test_stderr_tail() {
+        let tail = StderrTail::default();
+        let output: String = (1..=30).map(|i| format!("line {}\n", i)).collect();
+        tail.read_from(output.as_bytes());
+        let text = tail.text().unwrap();
+        assert!(text.starts_with("line 11\n"));
+        assert!(text.ends_with("line 30"));
+    }
+}
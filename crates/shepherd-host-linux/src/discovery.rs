@@ -0,0 +1,311 @@
+//! Installed app discovery
+//!
+//! Finds launchable apps through their XDG desktop entries, including the ones
+//! Flatpak and Snap export, and maps each to an entry kind so first-run setup
+//! can offer them as tiles.
+
+use shepherd_api::{DiscoveredApp, EntryKind};
+use shepherd_util::{EntryId, MAX_ENTRY_ID_LEN};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use tracing::debug;
+
+/// List apps with desktop entries, sorted by label
+pub fn discover_apps() -> Vec<DiscoveredApp> {
+    let mut apps = Vec::new();
+    // Desktop file IDs already seen; earlier directories take precedence,
+    // even when the earlier file hides the app
+    let mut seen = HashSet::new();
+    // Entry IDs handed out, since different file IDs can map to the same one
+    let mut taken = HashSet::new();
+
+    for dir in application_dirs() {
+        let Ok(read_dir) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut paths: Vec<_> = read_dir
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "desktop"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let Some(file_id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if !seen.insert(file_id.to_string()) {
+                continue;
+            }
+            let Ok(contents) = fs::read_to_string(&path) else {
+                continue;
+            };
+            if let Some(mut app) = parse_desktop_entry(file_id, &contents) {
+                app.id = unique_entry_id(&app.id, &mut taken);
+                apps.push(app);
+            }
+        }
+    }
+
+    apps.sort_by_key(|app| app.label.to_lowercase());
+    debug!(count = apps.len(), "Discovered installed apps");
+    apps
+}
+
+/// `applications` directories in XDG precedence order, plus the Snap export
+/// directory, which isn't always on XDG_DATA_DIRS
+fn application_dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string());
+
+    let mut app_dirs: Vec<PathBuf> = data_home
+        .into_iter()
+        .chain(data_dirs.split(':').filter(|d| !d.is_empty()).map(PathBuf::from))
+        .map(|d| d.join("applications"))
+        .collect();
+    app_dirs.push(PathBuf::from("/var/lib/snapd/desktop/applications"));
+    app_dirs.dedup();
+    app_dirs
+}
+
+/// Parse a desktop entry, returning `None` for hidden, terminal, or
+/// non-application entries
+fn parse_desktop_entry(file_id: &str, contents: &str) -> Option<DiscoveredApp> {
+    let mut fields = HashMap::new();
+    let mut in_main_section = false;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_main_section = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_main_section || line.starts_with('#') {
+            continue;
+        }
+        // Localized keys like Name[fr] are skipped by the exact-key lookups below
+        if let Some((key, value)) = line.split_once('=') {
+            fields.insert(key.trim(), value.trim());
+        }
+    }
+
+    let is_true = |key: &str| fields.get(key).is_some_and(|v| *v == "true");
+    if fields.get("Type") != Some(&"Application")
+        || is_true("NoDisplay")
+        || is_true("Hidden")
+        || is_true("Terminal")
+    {
+        return None;
+    }
+
+    let label = fields.get("Name")?.to_string();
+    let exec = fields.get("Exec")?;
+
+    let kind = if let Some(app_id) = fields.get("X-Flatpak") {
+        EntryKind::Flatpak {
+            app_id: app_id.to_string(),
+            args: Vec::new(),
+            env: HashMap::new(),
+        }
+    } else if let Some(snap_name) = fields.get("X-SnapInstanceName") {
+        EntryKind::Snap {
+            snap_name: snap_name.to_string(),
+            command: None,
+            args: Vec::new(),
+            env: HashMap::new(),
+        }
+    } else {
+        let mut words = split_exec(exec).into_iter();
+        EntryKind::Process {
+            command: words.next()?,
+            args: words.collect(),
+            env: HashMap::new(),
+            cwd: None,
+        }
+    };
+
+    Some(DiscoveredApp {
+        id: entry_id_for(file_id)?,
+        label,
+        icon: fields.get("Icon").map(|s| s.to_string()),
+        kind,
+    })
+}
+
+/// Split an Exec value into words, dropping field codes like `%U`
+fn split_exec(exec: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '\\' if in_quotes => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    words.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+        .into_iter()
+        .filter(|w| !(w.len() == 2 && w.starts_with('%')))
+        .collect()
+}
+
+/// Turn a desktop file ID like `org.example.Game` into an entry ID, or
+/// `None` if nothing usable is left of it
+fn entry_id_for(file_id: &str) -> Option<String> {
+    let id: String = file_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let id = truncate_id(id.trim_matches('-'), MAX_ENTRY_ID_LEN);
+    EntryId::validate(&id).ok()?;
+    Some(id)
+}
+
+/// At most `max` characters of an ASCII ID, without a trailing separator
+fn truncate_id(id: &str, max: usize) -> String {
+    id[..id.len().min(max)].trim_end_matches('-').to_string()
+}
+
+/// `id`, or `id` with a number appended if another app already has it
+fn unique_entry_id(id: &str, taken: &mut HashSet<String>) -> String {
+    let mut unique = id.to_string();
+    let mut n = 2;
+    while !taken.insert(unique.clone()) {
+        let suffix = format!("-{}", n);
+        unique = format!("{}{}", truncate_id(id, MAX_ENTRY_ID_LEN - suffix.len()), suffix);
+        n += 1;
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_process_entry() {
+        let contents = "[Desktop Entry]\n\
+                        Type=Application\n\
+                        Name=Tux Math\n\
+                        Name[fr]=Tux Maths\n\
+                        Exec=tuxmath --fullscreen %F\n\
+                        Icon=tuxmath\n\
+                        \n\
+                        [Desktop Action Window]\n\
+                        Exec=tuxmath --windowed\n";
+        let app = parse_desktop_entry("tuxmath", contents).unwrap();
+        assert_eq!(app.id, "tuxmath");
+        assert_eq!(app.label, "Tux Math");
+        assert_eq!(app.icon.as_deref(), Some("tuxmath"));
+        assert_eq!(
+            app.kind,
+            EntryKind::Process {
+                command: "tuxmath".into(),
+                args: vec!["--fullscreen".into()],
+                env: HashMap::new(),
+                cwd: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_sandboxed_entries() {
+        let flatpak = "[Desktop Entry]\n\
+                       Type=Application\n\
+                       Name=Prism Launcher\n\
+                       Exec=/usr/bin/flatpak run --branch=stable org.prismlauncher.PrismLauncher\n\
+                       X-Flatpak=org.prismlauncher.PrismLauncher\n";
+        let app = parse_desktop_entry("org.prismlauncher.PrismLauncher", flatpak).unwrap();
+        assert_eq!(app.id, "org-prismlauncher-prismlauncher");
+        assert!(matches!(
+            app.kind,
+            EntryKind::Flatpak { ref app_id, .. } if app_id == "org.prismlauncher.PrismLauncher"
+        ));
+
+        let snap = "[Desktop Entry]\n\
+                    Type=Application\n\
+                    Name=GCompris\n\
+                    Exec=env BAMF_DESKTOP_FILE_HINT=/var/lib/snapd/desktop/applications/gcompris_gcompris.desktop /snap/bin/gcompris\n\
+                    X-SnapInstanceName=gcompris\n";
+        let app = parse_desktop_entry("gcompris_gcompris", snap).unwrap();
+        assert!(matches!(
+            app.kind,
+            EntryKind::Snap { ref snap_name, .. } if snap_name == "gcompris"
+        ));
+    }
+
+    #[test]
+    fn test_skip_hidden_entries() {
+        let hidden = "[Desktop Entry]\nType=Application\nName=Settings\nExec=settings\nNoDisplay=true\n";
+        assert!(parse_desktop_entry("settings", hidden).is_none());
+
+        let terminal = "[Desktop Entry]\nType=Application\nName=Vim\nExec=vim %F\nTerminal=true\n";
+        assert!(parse_desktop_entry("vim", terminal).is_none());
+
+        let link = "[Desktop Entry]\nType=Link\nName=Docs\nURL=https://example.com\n";
+        assert!(parse_desktop_entry("docs", link).is_none());
+    }
+
+    #[test]
+    fn test_entry_id_for() {
+        assert_eq!(entry_id_for("org.example.Game").as_deref(), Some("org-example-game"));
+        assert_eq!(entry_id_for("-foo").as_deref(), Some("foo"));
+        assert_eq!(entry_id_for("[beta] game").as_deref(), Some("beta--game"));
+        assert_eq!(entry_id_for("[]"), None);
+
+        // Cut to length without leaving a separator at the end
+        let long = format!("{}.{}", "a".repeat(MAX_ENTRY_ID_LEN - 1), "b".repeat(10));
+        let id = entry_id_for(&long).unwrap();
+        assert_eq!(id, "a".repeat(MAX_ENTRY_ID_LEN - 1));
+        assert!(EntryId::validate(&id).is_ok());
+    }
+
+    #[test]
+    fn test_unique_entry_id() {
+        let mut taken = HashSet::new();
+        assert_eq!(unique_entry_id("game", &mut taken), "game");
+        assert_eq!(unique_entry_id("game", &mut taken), "game-2");
+        assert_eq!(unique_entry_id("game", &mut taken), "game-3");
+
+        // Long IDs that collide after truncation still fit
+        let long = "a".repeat(MAX_ENTRY_ID_LEN);
+        assert_eq!(unique_entry_id(&long, &mut taken), long);
+        let second = unique_entry_id(&long, &mut taken);
+        assert_eq!(second, format!("{}-2", "a".repeat(MAX_ENTRY_ID_LEN - 2)));
+        assert!(EntryId::validate(&second).is_ok());
+    }
+
+    #[test]
+    fn test_split_exec() {
+        assert_eq!(
+            split_exec(r#""/opt/My Game/run" --level "a \"b\"" %u"#),
+            vec!["/opt/My Game/run", "--level", "a \"b\""]
+        );
+    }
+}
@@ -0,0 +1,474 @@
+//! Kiosk escape route audit
+//!
+//! Looks for the common ways out of a kiosk session that shepherdd cannot
+//! police once they are open:
+//!
+//! - Login prompts on other virtual terminals, reachable with Ctrl+Alt+Fn,
+//!   from logind's `NAutoVTs`/`ReserveVT` settings
+//! - Sway key bindings that exec a terminal or an app launcher
+//! - The GTK inspector (`GTK_DEBUG=interactive` or its Ctrl+Shift+D
+//!   keybinding), which can run arbitrary code inside the launcher
+//!
+//! With mitigation on, VT gettys are turned off with a logind drop-in
+//! (effective once logind restarts), offending bindings are removed over
+//! Sway IPC and the inspector keybinding is switched off with `gsettings`.
+
+use shepherd_api::{EscapeRoute, EscapeRouteKind};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, info, warn};
+
+use crate::input::is_sway_available;
+
+/// logind's main config file
+const LOGIND_CONF: &str = "/etc/systemd/logind.conf";
+
+/// logind drop-in directories, lowest precedence first
+const LOGIND_DROPIN_DIRS: &[&str] = &[
+    "/usr/lib/systemd/logind.conf.d",
+    "/run/systemd/logind.conf.d",
+    "/etc/systemd/logind.conf.d",
+];
+
+/// Drop-in written to turn VT gettys off
+const LOGIND_KIOSK_DROPIN: &str = "/etc/systemd/logind.conf.d/90-shepherd-kiosk.conf";
+
+/// Programs that give a way out when bound to a key
+const ESCAPE_PROGRAMS: &[&str] = &[
+    "foot",
+    "footclient",
+    "alacritty",
+    "kitty",
+    "xterm",
+    "gnome-terminal",
+    "konsole",
+    "wezterm",
+    "urxvt",
+    "st",
+    "terminator",
+    "tilix",
+    "wofi",
+    "rofi",
+    "dmenu",
+    "dmenu_run",
+    "bemenu",
+    "bemenu-run",
+    "fuzzel",
+    "tofi",
+];
+
+/// GSettings schemas holding the inspector keybinding, GTK 3 then GTK 4
+const GTK_DEBUG_SCHEMAS: &[&str] = &["org.gtk.Settings.Debug", "org.gtk.gtk4.Settings.Debug"];
+
+/// GTK clients whose environment decides whether the inspector opens
+const GTK_CLIENTS: &[&str] = &["shepherd-launcher", "shepherd-hud"];
+
+/// Check every escape route, closing them where possible when `mitigate`
+/// is set
+pub fn audit_escape_routes(mitigate: bool) -> Vec<EscapeRoute> {
+    let mut routes = Vec::new();
+    routes.extend(check_vt_switching(mitigate));
+    if is_sway_available() {
+        routes.extend(check_sway_bindings(mitigate));
+    }
+    routes.extend(check_gtk_inspector(mitigate));
+
+    for route in &routes {
+        warn!(kind = ?route.kind, detail = %route.detail, mitigated = route.mitigated, "Kiosk escape route found");
+    }
+    routes
+}
+
+/// `NAutoVTs` and `ReserveVT` after applying `files` in order
+fn logind_vts(files: &[String]) -> (u32, u32) {
+    // logind's defaults
+    let (mut auto_vts, mut reserve_vt) = (6, 6);
+    for contents in files {
+        let mut in_login = false;
+        for line in contents.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_login = line == "[Login]";
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            if !in_login || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let Ok(value) = value.trim().parse() else {
+                continue;
+            };
+            match key.trim() {
+                "NAutoVTs" => auto_vts = value,
+                "ReserveVT" => reserve_vt = value,
+                _ => {}
+            }
+        }
+    }
+    (auto_vts, reserve_vt)
+}
+
+/// logind's config files in the order they apply: the main file, then
+/// drop-ins sorted by name, with later directories shadowing earlier ones
+fn logind_files() -> Vec<PathBuf> {
+    let mut dropins: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for dir in LOGIND_DROPIN_DIRS {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().is_some_and(|ext| ext == "conf")
+                && let Some(name) = path.file_name().and_then(|n| n.to_str())
+            {
+                dropins.insert(name.to_string(), path);
+            }
+        }
+    }
+    std::iter::once(PathBuf::from(LOGIND_CONF))
+        .chain(dropins.into_values())
+        .collect()
+}
+
+fn check_vt_switching(mitigate: bool) -> Option<EscapeRoute> {
+    let files: Vec<String> = logind_files()
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .collect();
+    let (auto_vts, reserve_vt) = logind_vts(&files);
+    if auto_vts == 0 && reserve_vt == 0 {
+        return None;
+    }
+
+    let mut detail = format!(
+        "logind starts login prompts on other VTs (NAutoVTs={}, ReserveVT={})",
+        auto_vts, reserve_vt
+    );
+    let mut mitigated = false;
+    if mitigate {
+        match write_logind_dropin(Path::new(LOGIND_KIOSK_DROPIN)) {
+            Ok(()) => {
+                info!(path = LOGIND_KIOSK_DROPIN, "Disabled VT login prompts");
+                detail.push_str("; disabled once logind restarts");
+                mitigated = true;
+            }
+            Err(e) => {
+                warn!(path = LOGIND_KIOSK_DROPIN, error = %e, "Failed to disable VT login prompts");
+                detail.push_str(&format!("; writing {} needs root", LOGIND_KIOSK_DROPIN));
+            }
+        }
+    }
+    Some(EscapeRoute {
+        kind: EscapeRouteKind::VtSwitching,
+        detail,
+        mitigated,
+    })
+}
+
+fn write_logind_dropin(path: &Path) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, "[Login]\nNAutoVTs=0\nReserveVT=0\n")
+}
+
+/// A Sway key binding that execs an escape program
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EscapeBinding {
+    /// `bindsym` or `bindcode`
+    bind: String,
+    flags: Vec<String>,
+    combo: String,
+    command: String,
+    /// The binding mode, when not the default one
+    mode: Option<String>,
+}
+
+/// Whether a (variable-expanded) command execs one of [`ESCAPE_PROGRAMS`]
+fn execs_escape_program(command: &str) -> bool {
+    let mut words = command.split_whitespace();
+    if !words.next().is_some_and(|w| w == "exec" || w == "exec_always") {
+        return false;
+    }
+    words
+        .map(|w| w.trim_matches(|c| c == '"' || c == '\''))
+        .filter_map(|w| Path::new(w).file_name().and_then(|n| n.to_str()))
+        .any(|name| ESCAPE_PROGRAMS.contains(&name))
+}
+
+/// Replace `$name` variables in `text`, longest names first so `$term`
+/// doesn't clobber `$terminal`
+fn expand_vars(text: &str, vars: &HashMap<String, String>) -> String {
+    let mut names: Vec<&String> = vars.keys().collect();
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    names
+        .into_iter()
+        .fold(text.to_string(), |text, name| text.replace(name.as_str(), &vars[name]))
+}
+
+/// Key bindings in a Sway config that exec an escape program
+fn escape_bindings(config: &str) -> Vec<EscapeBinding> {
+    let mut vars: HashMap<String, String> = HashMap::new();
+    let mut bindings = Vec::new();
+    let mut mode: Option<String> = None;
+    let mut depth = 0usize;
+
+    let joined = config.replace("\\\n", " ");
+    for line in joined.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("set ") {
+            // The name itself is never expanded
+            if let Some((name, value)) = rest.trim().split_once(char::is_whitespace)
+                && name.starts_with('$')
+            {
+                let value = expand_vars(value.trim(), &vars);
+                vars.insert(name.to_string(), value);
+            }
+            continue;
+        }
+        let line = expand_vars(line, &vars);
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("mode") if line.ends_with('{') => {
+                let name = words
+                    .filter(|w| !w.starts_with("--") && *w != "{")
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                mode = Some(name.trim_matches('"').to_string());
+                depth += 1;
+            }
+            Some(bind @ ("bindsym" | "bindcode")) => {
+                let mut flags = Vec::new();
+                let mut combo = None;
+                for word in words.by_ref() {
+                    if word.starts_with("--") {
+                        flags.push(word.to_string());
+                    } else {
+                        combo = Some(word.to_string());
+                        break;
+                    }
+                }
+                let command = words.collect::<Vec<_>>().join(" ");
+                if let Some(combo) = combo
+                    && execs_escape_program(&command)
+                {
+                    bindings.push(EscapeBinding {
+                        bind: bind.to_string(),
+                        flags,
+                        combo,
+                        command,
+                        mode: mode.clone(),
+                    });
+                }
+            }
+            Some("}") => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    mode = None;
+                }
+            }
+            _ if line.ends_with('{') => depth += 1,
+            _ => {}
+        }
+    }
+    bindings
+}
+
+/// The running Sway config, including any files it includes
+fn sway_config() -> Option<String> {
+    let output = Command::new("swaymsg").args(["-t", "get_config"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let reply: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    let mut config = reply.get("config")?.as_str()?.to_string();
+    for included in reply
+        .get("included_configs")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+    {
+        if let Some(contents) = included.get("raw_contents").and_then(|v| v.as_str()) {
+            config.push('\n');
+            config.push_str(contents);
+        }
+    }
+    Some(config)
+}
+
+fn check_sway_bindings(mitigate: bool) -> Vec<EscapeRoute> {
+    let Some(config) = sway_config() else {
+        debug!("Could not read the Sway config");
+        return Vec::new();
+    };
+
+    escape_bindings(&config)
+        .into_iter()
+        .map(|binding| {
+            let mut detail = format!("{} {} {}", binding.bind, binding.combo, binding.command);
+            if let Some(mode) = &binding.mode {
+                detail.push_str(&format!(" (mode \"{}\")", mode));
+            }
+            // Unbinding at runtime only reaches the current (default) mode
+            let mitigated = mitigate && binding.mode.is_none() && unbind(&binding);
+            EscapeRoute {
+                kind: EscapeRouteKind::TerminalBinding,
+                detail,
+                mitigated,
+            }
+        })
+        .collect()
+}
+
+fn unbind(binding: &EscapeBinding) -> bool {
+    let unbind = format!("un{}", binding.bind);
+    let mut args = vec![unbind.as_str()];
+    args.extend(binding.flags.iter().map(String::as_str));
+    args.push(&binding.combo);
+    match Command::new("swaymsg").args(&args).output() {
+        Ok(output) if output.status.success() => {
+            info!(combo = %binding.combo, "Removed Sway binding");
+            true
+        }
+        Ok(output) => {
+            warn!(combo = %binding.combo, stderr = %String::from_utf8_lossy(&output.stderr).trim(), "Failed to remove Sway binding");
+            false
+        }
+        Err(e) => {
+            warn!(combo = %binding.combo, error = %e, "Failed to run swaymsg");
+            false
+        }
+    }
+}
+
+/// The `GTK_DEBUG` value in `vars` if it opens the inspector
+fn inspector_gtk_debug<'a>(mut vars: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    vars.find_map(|var| var.strip_prefix("GTK_DEBUG="))
+        .filter(|debug| debug.split([',', ':', ' ']).any(|flag| flag == "interactive" || flag == "all"))
+}
+
+/// Where the launcher and HUD get their environment: each running one's
+/// own, and the systemd user manager's that their units start with
+fn gtk_client_environments() -> Vec<(String, String)> {
+    let mut environments = Vec::new();
+    if let Ok(entries) = std::fs::read_dir("/proc") {
+        for dir in entries.flatten().map(|e| e.path()) {
+            let Ok(comm) = std::fs::read_to_string(dir.join("comm")) else {
+                continue;
+            };
+            let comm = comm.trim();
+            if GTK_CLIENTS.contains(&comm)
+                && let Ok(environ) = std::fs::read(dir.join("environ"))
+            {
+                let environ = String::from_utf8_lossy(&environ).replace('\0', "\n");
+                environments.push((format!("{}'s environment", comm), environ));
+            }
+        }
+    }
+    match Command::new("systemctl").args(["--user", "show-environment"]).output() {
+        Ok(output) if output.status.success() => environments.push((
+            "the systemd user environment".to_string(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        )),
+        _ => debug!("No systemd user environment to check for GTK_DEBUG"),
+    }
+    environments
+}
+
+fn check_gtk_inspector(mitigate: bool) -> Vec<EscapeRoute> {
+    let mut routes = Vec::new();
+
+    // shepherdd's own environment says nothing about the launcher's
+    for (source, environment) in gtk_client_environments() {
+        if let Some(debug) = inspector_gtk_debug(environment.lines()) {
+            routes.push(EscapeRoute {
+                kind: EscapeRouteKind::GtkInspector,
+                detail: format!("GTK_DEBUG={} in {} opens the inspector at startup", debug, source),
+                mitigated: false,
+            });
+        }
+    }
+
+    for schema in GTK_DEBUG_SCHEMAS {
+        let enabled = Command::new("gsettings")
+            .args(["get", schema, "enable-inspector-keybinding"])
+            .output()
+            .is_ok_and(|o| o.status.success() && String::from_utf8_lossy(&o.stdout).trim() == "true");
+        if !enabled {
+            continue;
+        }
+        let mitigated = mitigate
+            && Command::new("gsettings")
+                .args(["set", schema, "enable-inspector-keybinding", "false"])
+                .output()
+                .is_ok_and(|o| o.status.success());
+        if mitigated {
+            info!(schema = %schema, "Disabled the GTK inspector keybinding");
+        }
+        routes.push(EscapeRoute {
+            kind: EscapeRouteKind::GtkInspector,
+            detail: format!("{} enable-inspector-keybinding is on", schema),
+            mitigated,
+        });
+    }
+    routes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logind_vts() {
+        assert_eq!(logind_vts(&[]), (6, 6));
+
+        let main = "[Login]\n#NAutoVTs=6\nReserveVT=2\n".to_string();
+        assert_eq!(logind_vts(std::slice::from_ref(&main)), (6, 2));
+
+        let dropin = "[Login]\nNAutoVTs=0\nReserveVT=0\n[Other]\nNAutoVTs=3\n".to_string();
+        assert_eq!(logind_vts(&[main, dropin]), (0, 0));
+    }
+
+    #[test]
+    fn test_inspector_gtk_debug() {
+        let environ = "PATH=/usr/bin\nGTK_DEBUG=actions,interactive\nHOME=/home/alex";
+        assert_eq!(inspector_gtk_debug(environ.lines()), Some("actions,interactive"));
+        assert_eq!(inspector_gtk_debug(["GTK_DEBUG=all"].into_iter()), Some("all"));
+        assert_eq!(inspector_gtk_debug(["GTK_DEBUG=actions"].into_iter()), None);
+        assert_eq!(inspector_gtk_debug(["MY_GTK_DEBUG=interactive"].into_iter()), None);
+    }
+
+    #[test]
+    fn test_escape_bindings() {
+        let config = "\
+set $mod Mod4
+set $term foot
+set $terminal alacritty
+bindsym $mod+Return exec $term
+bindsym --release $mod+t exec $terminal
+bindsym $mod+d exec wofi --show drun
+bindcode 133+36 exec /usr/bin/kitty
+bindsym $mod+l exec shepherd-launcher
+bindsym $mod+f fullscreen
+mode \"resize\" {
+    bindsym Return exec \\
+        xterm
+    bindsym Escape mode \"default\"
+}
+bindsym $mod+s exec sh -c 'st'
+";
+        let bindings = escape_bindings(config);
+        let combos: Vec<&str> = bindings.iter().map(|b| b.combo.as_str()).collect();
+        assert_eq!(combos, ["Mod4+Return", "Mod4+t", "Mod4+d", "133+36", "Return", "Mod4+s"]);
+
+        assert_eq!(bindings[0].command, "exec foot");
+        assert_eq!(bindings[1].command, "exec alacritty");
+        assert_eq!(bindings[1].flags, ["--release"]);
+        assert_eq!(bindings[3].bind, "bindcode");
+        assert_eq!(bindings[4].mode.as_deref(), Some("resize"));
+        assert_eq!(bindings[5].mode, None);
+    }
+}
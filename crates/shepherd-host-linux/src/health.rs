@@ -0,0 +1,76 @@
+//! Entry health probes
+//!
+//! Checks that an entry's app is still installed, so broken tiles can be
+//! disabled up front instead of failing on every launch. Probes never run the
+//! entry itself; snaps and flatpaks are looked up through their package tools.
+
+use shepherd_api::EntryKind;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::adapter::expand_tilde;
+use crate::precheck::{find_executable, program_for};
+
+/// Probe an entry, returning a description of the problem if it can't run
+pub fn probe_entry_health(entry_kind: &EntryKind) -> Option<String> {
+    match entry_kind {
+        EntryKind::Snap { snap_name, .. } => (!snap_installed(snap_name))
+            .then(|| format!("Snap {} is not installed", snap_name)),
+        EntryKind::Flatpak { app_id, .. } => (!flatpak_installed(app_id))
+            .then(|| format!("Flatpak {} is not installed", app_id)),
+        EntryKind::Retro { rom_path, .. }
+            if !Path::new(&expand_tilde(&rom_path.to_string_lossy())).is_file() =>
+        {
+            Some(format!("ROM {} is missing", rom_path.display()))
+        }
+        other => {
+            let program = program_for(other)?;
+            find_executable(&program, std::env::var_os("PATH").as_deref())
+                .is_none()
+                .then(|| format!("{} is not installed", program))
+        }
+    }
+}
+
+pub(crate) fn snap_installed(snap_name: &str) -> bool {
+    command_succeeds("snap", &["list", snap_name])
+}
+
+pub(crate) fn flatpak_installed(app_id: &str) -> bool {
+    command_succeeds("flatpak", &["info", app_id])
+}
+
+fn command_succeeds(program: &str, args: &[&str]) -> bool {
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn process(command: &str) -> EntryKind {
+        EntryKind::Process {
+            command: command.into(),
+            args: vec![],
+            env: HashMap::new(),
+            cwd: None,
+        }
+    }
+
+    #[test]
+    fn test_probe_process() {
+        assert_eq!(probe_entry_health(&process("/bin/sh")), None);
+        assert_eq!(
+            probe_entry_health(&process("/nonexistent/game")),
+            Some("/nonexistent/game is not installed".into())
+        );
+    }
+}
@@ -0,0 +1,1070 @@
+//! Host helper for privilege separation
+//!
+//! By default shepherdd runs [`LinuxHost`] in-process. With privilege
+//! separation, everything that touches the user's session (spawning and
+//! killing processes, cgroups, input lockout, volume, power actions) runs
+//! in `shepherd-host-helper`, started inside that session, while shepherdd
+//! runs as its own locked-down user and reaches the helper over a Unix
+//! socket through [`HelperClient`], which implements both [`HostAdapter`]
+//! and [`VolumeController`].
+//!
+//! The protocol is newline-delimited JSON. The helper greets each
+//! connection with [`HelperMessage::Hello`], answers every
+//! [`HelperEnvelope`] with a [`HelperMessage::Reply`] carrying the same id,
+//! and pushes [`HostEvent`]s and volume changes as they happen. It serves one daemon at a time
+//! and kills all sessions when that connection drops, so a daemon that
+//! crashed can't leave a game running unmanaged.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use shepherd_api::{
+    BatteryInfo, DiscoveredApp, EntryKind, ErrorKind, EscapeRoute, MediaInfo, PowerAction, ReasonCode,
+    SpawnDiagnostics,
+};
+use shepherd_host_api::{
+    HostAdapter, HostCapabilities, HostError, HostEvent, HostResult, HostSessionHandle,
+    LaunchPrechecks, SpawnOptions, StopMode, VolumeCapabilities, VolumeController, VolumeError,
+    VolumeResult, VolumeStatus,
+};
+use shepherd_util::SessionId;
+use std::collections::HashMap;
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error, info, warn};
+
+use crate::{LinuxHost, LinuxVolumeController};
+
+/// Operations shepherdd may ask the helper to perform
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum HelperRequest {
+    Spawn {
+        session_id: SessionId,
+        entry_kind: EntryKind,
+        options: Box<SpawnOptions>,
+    },
+    Stop {
+        handle: HostSessionHandle,
+        mode: StopMode,
+    },
+    KillCgroup {
+        handle: HostSessionHandle,
+    },
+    FreezeSession {
+        handle: HostSessionHandle,
+        frozen: bool,
+    },
+    Precheck {
+        entry_kind: EntryKind,
+        checks: LaunchPrechecks,
+    },
+    CheckEntryHealth {
+        entry_kind: EntryKind,
+    },
+    DiscoverApps,
+    AuditEscapeRoutes {
+        mitigate: bool,
+    },
+    UpdateEntry {
+        entry_kind: EntryKind,
+    },
+    SetForeground {
+        handle: HostSessionHandle,
+    },
+    SetFullscreen {
+        handle: HostSessionHandle,
+    },
+    IdleTime,
+    InhibitInput,
+    ReleaseInput,
+    RegisterPanicKey {
+        combo: String,
+    },
+    BatteryStatus,
+    PowerAction {
+        action: PowerAction,
+    },
+    MediaStatus {
+        handle: HostSessionHandle,
+    },
+    SetMediaPlaying {
+        handle: HostSessionHandle,
+        playing: bool,
+    },
+    EnsureShellVisible,
+    GetVolume,
+    SetVolume {
+        percent: u8,
+    },
+    VolumeUp {
+        step: u8,
+    },
+    VolumeDown {
+        step: u8,
+    },
+    ToggleMute,
+    SetMute {
+        muted: bool,
+    },
+    DuckSession {
+        handle: HostSessionHandle,
+        percent: Option<u8>,
+    },
+}
+
+/// A request with the id its reply will carry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HelperEnvelope {
+    pub id: u64,
+    pub request: HelperRequest,
+}
+
+/// Successful result of a [`HelperRequest`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HelperReply {
+    Done,
+    Spawned { handle: HostSessionHandle },
+    Reasons { reasons: Vec<ReasonCode> },
+    Health { problem: Option<String> },
+    Apps { apps: Vec<DiscoveredApp> },
+    EscapeRoutes { routes: Vec<EscapeRoute> },
+    Battery { battery: BatteryInfo },
+    Idle { idle: Duration },
+    Volume { status: VolumeStatus },
+    Media { media: Option<MediaInfo> },
+}
+
+/// Failed result of a [`HelperRequest`], keeping the error's kind across
+/// the socket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HelperError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// Why a spawn failed, if the host could tell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn: Option<SpawnDiagnostics>,
+}
+
+impl HelperError {
+    fn unavailable(message: impl Into<String>) -> Self {
+        Self {
+            kind: ErrorKind::Unavailable,
+            message: message.into(),
+            spawn: None,
+        }
+    }
+}
+
+impl std::fmt::Display for HelperError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<HostError> for HelperError {
+    fn from(e: HostError) -> Self {
+        Self {
+            kind: e.kind(),
+            message: e.to_string(),
+            spawn: e.spawn_diagnostics(),
+        }
+    }
+}
+
+impl From<VolumeError> for HelperError {
+    fn from(e: VolumeError) -> Self {
+        Self {
+            kind: e.kind(),
+            message: e.to_string(),
+            spawn: None,
+        }
+    }
+}
+
+impl From<HelperError> for HostError {
+    fn from(e: HelperError) -> Self {
+        match e.spawn {
+            Some(diagnostics) => HostError::SpawnDiagnosed(diagnostics),
+            None => HostError::new(e.kind, e.message),
+        }
+    }
+}
+
+impl From<HelperError> for VolumeError {
+    fn from(e: HelperError) -> Self {
+        match e.kind {
+            ErrorKind::Unsupported => VolumeError::NotAvailable(e.message),
+            _ => VolumeError::Backend(e.message),
+        }
+    }
+}
+
+/// Messages from the helper to shepherdd
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HelperMessage {
+    /// Sent once when shepherdd connects
+    Hello {
+        capabilities: HostCapabilities,
+        volume: VolumeCapabilities,
+        /// Whether [`HelperMessage::Volume`] will report every volume change
+        #[serde(default)]
+        volume_watched: bool,
+    },
+    Reply {
+        id: u64,
+        result: Result<HelperReply, HelperError>,
+    },
+    Event {
+        event: HostEvent,
+    },
+    Volume {
+        status: VolumeStatus,
+    },
+}
+
+/// Serve the helper protocol on `socket_path` until the listener fails.
+///
+/// Only `allowed_uids`, the user shepherdd runs as, may connect; not even
+/// the helper's own user, since everything the child launches runs as that
+/// user too. The socket belongs to `socket_group`, shepherdd's group, and
+/// nobody else can open it.
+pub async fn run_helper(
+    socket_path: &Path,
+    allowed_uids: &[u32],
+    socket_group: &str,
+) -> std::io::Result<()> {
+    if allowed_uids.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "no UID is allowed to connect",
+        ));
+    }
+    let group = nix::unistd::Group::from_name(socket_group)?.ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("no group named {:?}", socket_group),
+        )
+    })?;
+
+    let host = Arc::new(LinuxHost::new());
+    let _monitor = host.start_monitor();
+    let mut events = host.subscribe();
+    let volume = Arc::new(LinuxVolumeController::new());
+    let mut volume_changes = volume.watch();
+
+    if socket_path.exists() {
+        std::fs::remove_file(socket_path)?;
+    }
+    if let Some(parent) = socket_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(socket_path)?;
+    nix::unistd::chown(socket_path, None, Some(group.gid))?;
+    std::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(0o660))?;
+    info!(path = %socket_path.display(), group = socket_group, "Host helper listening");
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let uid = peer_uid(&stream);
+        if !is_allowed_peer(uid, allowed_uids) {
+            warn!(uid = ?uid, "Rejected host helper connection");
+            continue;
+        }
+
+        info!(uid = ?uid, "shepherdd connected");
+        // Exits from before this connection belong to the previous daemon
+        while events.try_recv().is_ok() {}
+
+        if let Err(e) = serve_connection(stream, &host, &volume, &mut events, &mut volume_changes).await {
+            warn!(error = %e, "Host helper connection failed");
+        }
+
+        // Nothing enforces limits until shepherdd comes back
+        let killed = host.kill_all_now();
+        warn!(killed, "shepherdd disconnected, sessions stopped");
+    }
+}
+
+async fn serve_connection(
+    stream: UnixStream,
+    host: &Arc<LinuxHost>,
+    volume: &Arc<LinuxVolumeController>,
+    events: &mut mpsc::UnboundedReceiver<HostEvent>,
+    volume_changes: &mut Option<mpsc::UnboundedReceiver<VolumeStatus>>,
+) -> std::io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+
+    let (out_tx, mut out_rx) = mpsc::unbounded_channel::<HelperMessage>();
+    let writer = tokio::spawn(async move {
+        while let Some(message) = out_rx.recv().await {
+            if let Err(e) = write_line(&mut write, &message).await {
+                debug!(error = %e, "Failed to write to shepherdd");
+                break;
+            }
+        }
+    });
+
+    let _ = out_tx.send(HelperMessage::Hello {
+        capabilities: host.capabilities().clone(),
+        volume: volume.capabilities().clone(),
+        volume_watched: volume_changes.is_some(),
+    });
+    // The daemon starts from nothing; give it the current volume
+    if volume_changes.is_some()
+        && let Ok(status) = volume.get_status().await
+    {
+        let _ = out_tx.send(HelperMessage::Volume { status });
+    }
+
+    let result = loop {
+        tokio::select! {
+            line = lines.next_line() => match line {
+                Ok(Some(line)) => {
+                    let envelope: HelperEnvelope = match serde_json::from_str(&line) {
+                        Ok(envelope) => envelope,
+                        Err(e) => {
+                            warn!(error = %e, "Invalid host helper request");
+                            continue;
+                        }
+                    };
+                    debug!(id = envelope.id, request = ?envelope.request, "Host helper request");
+
+                    // Graceful stops take seconds; don't hold up other requests
+                    let host = host.clone();
+                    let volume = volume.clone();
+                    let out_tx = out_tx.clone();
+                    tokio::spawn(async move {
+                        let result = handle_request(&host, &volume, envelope.request).await;
+                        let _ = out_tx.send(HelperMessage::Reply { id: envelope.id, result });
+                    });
+                }
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            },
+            Some(event) = events.recv() => {
+                let _ = out_tx.send(HelperMessage::Event { event });
+            }
+            Some(status) = next_volume_change(volume_changes) => {
+                let _ = out_tx.send(HelperMessage::Volume { status });
+            }
+        }
+    };
+
+    writer.abort();
+    result
+}
+
+/// The next volume change, or never if changes aren't watched
+async fn next_volume_change(
+    changes: &mut Option<mpsc::UnboundedReceiver<VolumeStatus>>,
+) -> Option<VolumeStatus> {
+    let Some(rx) = changes else {
+        return std::future::pending().await;
+    };
+    let status = rx.recv().await;
+    if status.is_none() {
+        *changes = None;
+    }
+    status
+}
+
+async fn handle_request(
+    host: &LinuxHost,
+    volume: &LinuxVolumeController,
+    request: HelperRequest,
+) -> Result<HelperReply, HelperError> {
+    fn done<E: Into<HelperError>>(result: Result<(), E>) -> Result<HelperReply, HelperError> {
+        result.map(|()| HelperReply::Done).map_err(Into::into)
+    }
+
+    match request {
+        HelperRequest::Spawn {
+            session_id,
+            entry_kind,
+            options,
+        } => host
+            .spawn(session_id, &entry_kind, *options)
+            .await
+            .map(|handle| HelperReply::Spawned { handle })
+            .map_err(HelperError::from),
+        HelperRequest::Stop { handle, mode } => done(host.stop(&handle, mode).await),
+        HelperRequest::KillCgroup { handle } => done(host.kill_cgroup(&handle).await),
+        HelperRequest::FreezeSession { handle, frozen } => {
+            done(host.freeze_session(&handle, frozen).await)
+        }
+        HelperRequest::Precheck { entry_kind, checks } => Ok(HelperReply::Reasons {
+            reasons: host.precheck(&entry_kind, &checks).await,
+        }),
+        HelperRequest::CheckEntryHealth { entry_kind } => Ok(HelperReply::Health {
+            problem: host.check_entry_health(&entry_kind).await,
+        }),
+        HelperRequest::DiscoverApps => Ok(HelperReply::Apps {
+            apps: host.discover_apps().await,
+        }),
+        HelperRequest::AuditEscapeRoutes { mitigate } => Ok(HelperReply::EscapeRoutes {
+            routes: host.audit_escape_routes(mitigate).await,
+        }),
+        HelperRequest::UpdateEntry { entry_kind } => done(host.update_entry(&entry_kind).await),
+        HelperRequest::SetForeground { handle } => done(host.set_foreground(&handle).await),
+        HelperRequest::SetFullscreen { handle } => done(host.set_fullscreen(&handle).await),
+        HelperRequest::IdleTime => host
+            .idle_time()
+            .await
+            .map(|idle| HelperReply::Idle { idle })
+            .map_err(HelperError::from),
+        HelperRequest::InhibitInput => done(host.inhibit_input().await),
+        HelperRequest::ReleaseInput => done(host.release_input().await),
+        HelperRequest::RegisterPanicKey { combo } => done(host.register_panic_key(&combo).await),
+        HelperRequest::BatteryStatus => host
+            .battery_status()
+            .await
+            .map(|battery| HelperReply::Battery { battery })
+            .map_err(HelperError::from),
+        HelperRequest::PowerAction { action } => done(host.power_action(action).await),
+        HelperRequest::MediaStatus { handle } => host
+            .media_status(&handle)
+            .await
+            .map(|media| HelperReply::Media { media })
+            .map_err(HelperError::from),
+        HelperRequest::SetMediaPlaying { handle, playing } => {
+            done(host.set_media_playing(&handle, playing).await)
+        }
+        HelperRequest::EnsureShellVisible => done(host.ensure_shell_visible().await),
+        HelperRequest::GetVolume => volume
+            .get_status()
+            .await
+            .map(|status| HelperReply::Volume { status })
+            .map_err(HelperError::from),
+        HelperRequest::SetVolume { percent } => done(volume.set_volume(percent).await),
+        HelperRequest::VolumeUp { step } => done(volume.volume_up(step).await),
+        HelperRequest::VolumeDown { step } => done(volume.volume_down(step).await),
+        HelperRequest::ToggleMute => done(volume.toggle_mute().await),
+        HelperRequest::SetMute { muted } => done(volume.set_mute(muted).await),
+        HelperRequest::DuckSession { handle, percent } => {
+            done(volume.duck_session(&handle, percent).await)
+        }
+    }
+}
+
+type PendingReplies = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<HelperReply, HelperError>>>>>;
+
+/// shepherdd's end of the helper connection
+pub struct HelperClient {
+    capabilities: HostCapabilities,
+    volume_capabilities: VolumeCapabilities,
+    writer: tokio::sync::Mutex<OwnedWriteHalf>,
+    /// Raw socket, for shutting the connection down from a crash path
+    fd: RawFd,
+    pending: PendingReplies,
+    next_id: AtomicU64,
+    connected: Arc<AtomicBool>,
+    event_rx: Mutex<Option<mpsc::UnboundedReceiver<HostEvent>>>,
+    /// Set if the helper reports volume changes
+    volume_rx: Mutex<Option<mpsc::UnboundedReceiver<VolumeStatus>>>,
+}
+
+impl HelperClient {
+    /// Connect to the helper, retrying for up to `timeout` since both are
+    /// usually started at the same time
+    pub async fn connect(socket_path: &Path, timeout: Duration) -> std::io::Result<Self> {
+        let deadline = Instant::now() + timeout;
+        let stream = loop {
+            match UnixStream::connect(socket_path).await {
+                Ok(stream) => break stream,
+                Err(e) if Instant::now() < deadline => {
+                    debug!(error = %e, "Host helper not ready, retrying");
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+
+        let fd = stream.as_raw_fd();
+        let (read, write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+
+        let hello = lines.next_line().await?.ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "host helper closed the connection")
+        })?;
+        let HelperMessage::Hello {
+            capabilities,
+            volume: volume_capabilities,
+            volume_watched,
+        } = serde_json::from_str(&hello)?
+        else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "expected hello from host helper",
+            ));
+        };
+
+        let pending: PendingReplies = Arc::default();
+        let connected = Arc::new(AtomicBool::new(true));
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let (volume_tx, volume_rx) = mpsc::unbounded_channel();
+
+        let reader_pending = pending.clone();
+        let reader_connected = connected.clone();
+        tokio::spawn(async move {
+            loop {
+                let line = match lines.next_line().await {
+                    Ok(Some(line)) => line,
+                    Ok(None) => break,
+                    Err(e) => {
+                        warn!(error = %e, "Failed to read from host helper");
+                        break;
+                    }
+                };
+                match serde_json::from_str(&line) {
+                    Ok(HelperMessage::Reply { id, result }) => {
+                        if let Some(tx) = reader_pending.lock().unwrap().remove(&id) {
+                            let _ = tx.send(result);
+                        }
+                    }
+                    Ok(HelperMessage::Event { event }) => {
+                        let _ = event_tx.send(event);
+                    }
+                    Ok(HelperMessage::Volume { status }) => {
+                        let _ = volume_tx.send(status);
+                    }
+                    Ok(HelperMessage::Hello { .. }) => {}
+                    Err(e) => warn!(error = %e, "Invalid message from host helper"),
+                }
+            }
+
+            error!("Lost connection to host helper");
+            reader_connected.store(false, Ordering::SeqCst);
+            // Dropping the senders fails every call still waiting
+            reader_pending.lock().unwrap().clear();
+        });
+
+        info!(path = %socket_path.display(), "Connected to host helper");
+
+        Ok(Self {
+            capabilities,
+            volume_capabilities,
+            writer: tokio::sync::Mutex::new(write),
+            fd,
+            pending,
+            next_id: AtomicU64::new(1),
+            connected,
+            event_rx: Mutex::new(Some(event_rx)),
+            volume_rx: Mutex::new(volume_watched.then_some(volume_rx)),
+        })
+    }
+
+    async fn call(&self, request: HelperRequest) -> Result<HelperReply, HelperError> {
+        if !self.connected.load(Ordering::SeqCst) {
+            return Err(HelperError::unavailable("host helper not connected"));
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+
+        let written = {
+            let mut writer = self.writer.lock().await;
+            write_line(&mut *writer, &HelperEnvelope { id, request }).await
+        };
+        if let Err(e) = written {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(HelperError::unavailable(format!(
+                "failed to reach host helper: {}",
+                e
+            )));
+        }
+
+        rx.await
+            .map_err(|_| HelperError::unavailable("lost connection to host helper"))?
+    }
+
+    async fn host_call(&self, request: HelperRequest) -> HostResult<HelperReply> {
+        self.call(request).await.map_err(HostError::from)
+    }
+
+    async fn volume_call(&self, request: HelperRequest) -> VolumeResult<HelperReply> {
+        self.call(request).await.map_err(VolumeError::from)
+    }
+}
+
+fn unexpected(reply: HelperReply) -> String {
+    format!("unexpected host helper reply: {:?}", reply)
+}
+
+#[async_trait]
+impl HostAdapter for HelperClient {
+    fn capabilities(&self) -> &HostCapabilities {
+        &self.capabilities
+    }
+
+    async fn spawn(
+        &self,
+        session_id: SessionId,
+        entry_kind: &EntryKind,
+        options: SpawnOptions,
+    ) -> HostResult<HostSessionHandle> {
+        match self
+            .host_call(HelperRequest::Spawn {
+                session_id,
+                entry_kind: entry_kind.clone(),
+                options: Box::new(options),
+            })
+            .await?
+        {
+            HelperReply::Spawned { handle } => Ok(handle),
+            reply => Err(HostError::SpawnFailed(unexpected(reply))),
+        }
+    }
+
+    async fn stop(&self, handle: &HostSessionHandle, mode: StopMode) -> HostResult<()> {
+        self.host_call(HelperRequest::Stop {
+            handle: handle.clone(),
+            mode,
+        })
+        .await
+        .map(drop)
+    }
+
+    async fn kill_cgroup(&self, handle: &HostSessionHandle) -> HostResult<()> {
+        self.host_call(HelperRequest::KillCgroup {
+            handle: handle.clone(),
+        })
+        .await
+        .map(drop)
+    }
+
+    async fn freeze_session(&self, handle: &HostSessionHandle, frozen: bool) -> HostResult<()> {
+        self.host_call(HelperRequest::FreezeSession {
+            handle: handle.clone(),
+            frozen,
+        })
+        .await
+        .map(drop)
+    }
+
+    fn subscribe(&self) -> mpsc::UnboundedReceiver<HostEvent> {
+        self.event_rx
+            .lock()
+            .unwrap()
+            .take()
+            .expect("subscribe() can only be called once")
+    }
+
+    async fn precheck(&self, entry_kind: &EntryKind, checks: &LaunchPrechecks) -> Vec<ReasonCode> {
+        match self
+            .call(HelperRequest::Precheck {
+                entry_kind: entry_kind.clone(),
+                checks: checks.clone(),
+            })
+            .await
+        {
+            Ok(HelperReply::Reasons { reasons }) => reasons,
+            // Let the spawn report the failure
+            Ok(reply) => {
+                warn!(error = %unexpected(reply), "Launch pre-checks skipped");
+                Vec::new()
+            }
+            Err(e) => {
+                warn!(error = %e, "Launch pre-checks skipped");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn check_entry_health(&self, entry_kind: &EntryKind) -> Option<String> {
+        match self
+            .call(HelperRequest::CheckEntryHealth {
+                entry_kind: entry_kind.clone(),
+            })
+            .await
+        {
+            Ok(HelperReply::Health { problem }) => problem,
+            // An unreachable helper says nothing about the entry itself
+            Ok(reply) => {
+                warn!(error = %unexpected(reply), "Entry health probe skipped");
+                None
+            }
+            Err(e) => {
+                warn!(error = %e, "Entry health probe skipped");
+                None
+            }
+        }
+    }
+
+    async fn discover_apps(&self) -> Vec<DiscoveredApp> {
+        match self.call(HelperRequest::DiscoverApps).await {
+            Ok(HelperReply::Apps { apps }) => apps,
+            Ok(reply) => {
+                warn!(error = %unexpected(reply), "App discovery failed");
+                Vec::new()
+            }
+            Err(e) => {
+                warn!(error = %e, "App discovery failed");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn audit_escape_routes(&self, mitigate: bool) -> Vec<EscapeRoute> {
+        match self.call(HelperRequest::AuditEscapeRoutes { mitigate }).await {
+            Ok(HelperReply::EscapeRoutes { routes }) => routes,
+            Ok(reply) => {
+                warn!(error = %unexpected(reply), "Escape route audit failed");
+                Vec::new()
+            }
+            Err(e) => {
+                warn!(error = %e, "Escape route audit failed");
+                Vec::new()
+            }
+        }
+    }
+
+    async fn update_entry(&self, entry_kind: &EntryKind) -> HostResult<()> {
+        self.host_call(HelperRequest::UpdateEntry {
+            entry_kind: entry_kind.clone(),
+        })
+        .await
+        .map(drop)
+    }
+
+    async fn set_foreground(&self, handle: &HostSessionHandle) -> HostResult<()> {
+        self.host_call(HelperRequest::SetForeground {
+            handle: handle.clone(),
+        })
+        .await
+        .map(drop)
+    }
+
+    async fn set_fullscreen(&self, handle: &HostSessionHandle) -> HostResult<()> {
+        self.host_call(HelperRequest::SetFullscreen {
+            handle: handle.clone(),
+        })
+        .await
+        .map(drop)
+    }
+
+    async fn idle_time(&self) -> HostResult<Duration> {
+        match self.host_call(HelperRequest::IdleTime).await? {
+            HelperReply::Idle { idle } => Ok(idle),
+            reply => Err(HostError::internal(unexpected(reply))),
+        }
+    }
+
+    async fn inhibit_input(&self) -> HostResult<()> {
+        self.host_call(HelperRequest::InhibitInput).await.map(drop)
+    }
+
+    async fn release_input(&self) -> HostResult<()> {
+        self.host_call(HelperRequest::ReleaseInput).await.map(drop)
+    }
+
+    async fn register_panic_key(&self, combo: &str) -> HostResult<()> {
+        self.host_call(HelperRequest::RegisterPanicKey {
+            combo: combo.to_string(),
+        })
+        .await
+        .map(drop)
+    }
+
+    async fn battery_status(&self) -> HostResult<BatteryInfo> {
+        match self.host_call(HelperRequest::BatteryStatus).await? {
+            HelperReply::Battery { battery } => Ok(battery),
+            reply => Err(HostError::internal(unexpected(reply))),
+        }
+    }
+
+    async fn media_status(&self, handle: &HostSessionHandle) -> HostResult<Option<MediaInfo>> {
+        match self
+            .host_call(HelperRequest::MediaStatus {
+                handle: handle.clone(),
+            })
+            .await?
+        {
+            HelperReply::Media { media } => Ok(media),
+            reply => Err(HostError::internal(unexpected(reply))),
+        }
+    }
+
+    async fn set_media_playing(&self, handle: &HostSessionHandle, playing: bool) -> HostResult<()> {
+        self.host_call(HelperRequest::SetMediaPlaying {
+            handle: handle.clone(),
+            playing,
+        })
+        .await
+        .map(drop)
+    }
+
+    async fn power_action(&self, action: PowerAction) -> HostResult<()> {
+        self.host_call(HelperRequest::PowerAction { action })
+            .await
+            .map(drop)
+    }
+
+    async fn ensure_shell_visible(&self) -> HostResult<()> {
+        self.host_call(HelperRequest::EnsureShellVisible)
+            .await
+            .map(drop)
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    /// Closes the connection; the helper then kills its sessions itself.
+    /// The count isn't known here, so this reports none.
+    fn kill_all_now(&self) -> usize {
+        let _ = nix::sys::socket::shutdown(self.fd, nix::sys::socket::Shutdown::Both);
+        0
+    }
+}
+
+#[async_trait]
+impl VolumeController for HelperClient {
+    fn capabilities(&self) -> &VolumeCapabilities {
+        &self.volume_capabilities
+    }
+
+    async fn get_status(&self) -> VolumeResult<VolumeStatus> {
+        match self.volume_call(HelperRequest::GetVolume).await? {
+            HelperReply::Volume { status } => Ok(status),
+            reply => Err(VolumeError::Backend(unexpected(reply))),
+        }
+    }
+
+    async fn set_volume(&self, percent: u8) -> VolumeResult<()> {
+        self.volume_call(HelperRequest::SetVolume { percent })
+            .await
+            .map(drop)
+    }
+
+    async fn volume_up(&self, step: u8) -> VolumeResult<()> {
+        self.volume_call(HelperRequest::VolumeUp { step })
+            .await
+            .map(drop)
+    }
+
+    async fn volume_down(&self, step: u8) -> VolumeResult<()> {
+        self.volume_call(HelperRequest::VolumeDown { step })
+            .await
+            .map(drop)
+    }
+
+    async fn toggle_mute(&self) -> VolumeResult<()> {
+        self.volume_call(HelperRequest::ToggleMute).await.map(drop)
+    }
+
+    async fn set_mute(&self, muted: bool) -> VolumeResult<()> {
+        self.volume_call(HelperRequest::SetMute { muted })
+            .await
+            .map(drop)
+    }
+
+    async fn duck_session(&self, session: &HostSessionHandle, percent: Option<u8>) -> VolumeResult<()> {
+        self.volume_call(HelperRequest::DuckSession {
+            handle: session.clone(),
+            percent,
+        })
+        .await
+        .map(drop)
+    }
+
+    /// Changes pushed by the helper; the channel closes with the connection
+    fn watch(&self) -> Option<mpsc::UnboundedReceiver<VolumeStatus>> {
+        self.volume_rx.lock().unwrap().take()
+    }
+}
+
+async fn write_line<W, T>(writer: &mut W, message: &T) -> std::io::Result<()>
+where
+    W: AsyncWriteExt + Unpin,
+    T: Serialize,
+{
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line).await
+}
+
+/// Whether a peer with `uid` may connect
+fn is_allowed_peer(uid: Option<u32>, allowed_uids: &[u32]) -> bool {
+    uid.is_some_and(|uid| allowed_uids.contains(&uid))
+}
+
+fn peer_uid(stream: &UnixStream) -> Option<u32> {
+    use std::os::unix::io::AsFd;
+
+    nix::sys::socket::getsockopt(&stream.as_fd(), nix::sys::socket::sockopt::PeerCredentials)
+        .ok()
+        .map(|cred| cred.uid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shepherd_host_api::HostHandlePayload;
+
+    #[test]
+    fn test_protocol_round_trip() {
+        let envelope = HelperEnvelope {
+            id: 7,
+            request: HelperRequest::Stop {
+                handle: HostSessionHandle::new(
+                    SessionId::new(),
+                    HostHandlePayload::Linux { pid: 42, pgid: 42 },
+                ),
+                mode: StopMode::Force,
+            },
+        };
+        let line = serde_json::to_string(&envelope).unwrap();
+        assert!(line.contains(r#""op":"stop""#));
+        let parsed: HelperEnvelope = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed.id, 7);
+        assert!(matches!(
+            parsed.request,
+            HelperRequest::Stop {
+                mode: StopMode::Force,
+                ..
+            }
+        ));
+
+        let reply = HelperMessage::Reply {
+            id: 7,
+            result: Err(HostError::SessionNotFound.into()),
+        };
+        let parsed: HelperMessage =
+            serde_json::from_str(&serde_json::to_string(&reply).unwrap()).unwrap();
+        assert!(matches!(
+            parsed,
+            HelperMessage::Reply { id: 7, result: Err(e) }
+                if e.kind == ErrorKind::NotFound && e.message == "Session not found"
+        ));
+
+        // Spawn diagnostics survive the trip
+        let diagnostics = crate::diagnostics::exec_failure(
+            "supertux2",
+            &std::io::Error::from(std::io::ErrorKind::NotFound),
+        );
+        let error: HelperError = HostError::SpawnDiagnosed(diagnostics.clone()).into();
+        let parsed: HelperError =
+            serde_json::from_str(&serde_json::to_string(&error).unwrap()).unwrap();
+        assert_eq!(HostError::from(parsed).spawn_diagnostics(), Some(diagnostics));
+    }
+
+    #[test]
+    fn test_is_allowed_peer() {
+        let own_uid = nix::unistd::getuid().as_raw();
+        assert!(is_allowed_peer(Some(990), &[990]));
+        // The session user's own programs can't drive the helper
+        assert!(!is_allowed_peer(Some(own_uid), &[990]));
+        assert!(!is_allowed_peer(None, &[990]));
+    }
+
+    #[tokio::test]
+    async fn test_client_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("helper.sock");
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        // A stand-in helper that answers volume requests from a fixed status
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let hello = HelperMessage::Hello {
+                capabilities: HostCapabilities::minimal(),
+                volume: VolumeCapabilities::default(),
+                volume_watched: true,
+            };
+            write_line(&mut write, &hello).await.unwrap();
+            let change = HelperMessage::Volume {
+                status: VolumeStatus {
+                    percent: 40,
+                    muted: false,
+                    ..Default::default()
+                },
+            };
+            write_line(&mut write, &change).await.unwrap();
+
+            let mut lines = BufReader::new(read).lines();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let envelope: HelperEnvelope = serde_json::from_str(&line).unwrap();
+                let result = match envelope.request {
+                    HelperRequest::GetVolume => Ok(HelperReply::Volume {
+                        status: VolumeStatus {
+                            percent: 35,
+                            muted: true,
+                            ..Default::default()
+                        },
+                    }),
+                    _ => Err(HostError::unsupported("unsupported").into()),
+                };
+                let reply = HelperMessage::Reply {
+                    id: envelope.id,
+                    result,
+                };
+                write_line(&mut write, &reply).await.unwrap();
+            }
+        });
+
+        let client = HelperClient::connect(&socket_path, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert!(client.is_healthy());
+
+        let status = client.get_status().await.unwrap();
+        assert_eq!(status.percent, 35);
+        assert!(status.muted);
+
+        // Pushed changes arrive without asking
+        let mut changes = client.watch().unwrap();
+        assert_eq!(changes.recv().await.unwrap().percent, 40);
+        assert!(client.watch().is_none());
+
+        // The error's kind survives the socket
+        let err = client.inhibit_input().await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+
+    #[tokio::test]
+    async fn test_client_sees_helper_exit() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("helper.sock");
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        // A stand-in helper that says hello and goes away
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (_read, mut write) = stream.into_split();
+            let hello = HelperMessage::Hello {
+                capabilities: HostCapabilities::minimal(),
+                volume: VolumeCapabilities::default(),
+                volume_watched: false,
+            };
+            write_line(&mut write, &hello).await.unwrap();
+        });
+
+        let client = HelperClient::connect(&socket_path, Duration::from_secs(1))
+            .await
+            .unwrap();
+        let mut events = client.subscribe();
+
+        // shepherdd exits when the event stream ends
+        assert!(events.recv().await.is_none());
+        assert!(!client.is_healthy());
+        let err = client.release_input().await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unavailable);
+    }
+}
@@ -0,0 +1,128 @@
+//! Entry hooks
+//!
+//! Commands configured to run before a session starts and after it exits,
+//! e.g. mounting a game drive or backing up saves. Hooks see the session's
+//! environment plus `SHEPHERD_SESSION_ID` and `SHEPHERD_HOOK`, and are
+//! killed if they overrun their timeout.
+
+use shepherd_host_api::{HostError, HostResult, SessionHook};
+use shepherd_util::SessionId;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+use crate::adapter::expand_tilde;
+
+/// Run a hook to completion, failing if it exits non-zero or times out
+pub async fn run_hook(
+    name: &str,
+    hook: &SessionHook,
+    session_id: &SessionId,
+    env: &HashMap<String, String>,
+    cwd: Option<&PathBuf>,
+) -> HostResult<()> {
+    let argv: Vec<String> = hook.argv.iter().map(|arg| expand_tilde(arg)).collect();
+    let Some((program, args)) = argv.split_first() else {
+        return Err(HostError::internal(format!("{} hook has no command", name)));
+    };
+
+    info!(hook = name, argv = ?argv, session_id = %session_id, "Running hook");
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .envs(env)
+        .env("SHEPHERD_SESSION_ID", session_id.to_string())
+        .env("SHEPHERD_HOOK", name)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+
+    let child = command.spawn().map_err(|e| HostError::exec(program, &e))?;
+    let output = tokio::time::timeout(hook.timeout, child.wait_with_output())
+        .await
+        .map_err(|_| {
+            HostError::command_failed(format!(
+                "{} hook timed out after {}s",
+                name,
+                hook.timeout.as_secs()
+            ))
+        })?
+        .map_err(|e| HostError::exec(program, &e))?;
+
+    if !output.status.success() {
+        return Err(HostError::command_failed(format!(
+            "{} hook failed ({}): {}",
+            name,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Run a hook, applying its failure policy. Only returns an error when the
+/// hook fails and `abort_on_failure` is set.
+pub async fn run_hook_with_policy(
+    name: &str,
+    hook: &SessionHook,
+    session_id: &SessionId,
+    env: &HashMap<String, String>,
+    cwd: Option<&PathBuf>,
+) -> HostResult<()> {
+    match run_hook(name, hook, session_id, env, cwd).await {
+        Ok(()) => Ok(()),
+        Err(e) if hook.abort_on_failure => Err(e),
+        Err(e) => {
+            warn!(hook = name, error = %e, "Hook failed, continuing");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn hook(argv: &[&str], abort_on_failure: bool) -> SessionHook {
+        SessionHook {
+            argv: argv.iter().map(|s| s.to_string()).collect(),
+            timeout: Duration::from_secs(5),
+            abort_on_failure,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_hook() {
+        let session_id = SessionId::new();
+        let env = HashMap::from([("SAVE_DIR".to_string(), "/saves".to_string())]);
+
+        let check_env = hook(
+            &["sh", "-c", "test \"$SAVE_DIR\" = /saves && test \"$SHEPHERD_HOOK\" = pre_launch"],
+            true,
+        );
+        assert!(run_hook("pre_launch", &check_env, &session_id, &env, None).await.is_ok());
+
+        let failing = hook(&["false"], true);
+        assert!(run_hook("pre_launch", &failing, &session_id, &env, None).await.is_err());
+        assert!(run_hook_with_policy("pre_launch", &failing, &session_id, &env, None).await.is_err());
+        let ignored = hook(&["false"], false);
+        assert!(run_hook_with_policy("post_exit", &ignored, &session_id, &env, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_hook_timeout() {
+        let mut slow = hook(&["sleep", "10"], true);
+        slow.timeout = Duration::from_millis(100);
+        let err = run_hook("pre_launch", &slow, &SessionId::new(), &HashMap::new(), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+    }
+}
@@ -0,0 +1,59 @@
+//! Input lockout via the Sway compositor
+//!
+//! Disables keyboard, pointer and touch input while an expiring session is
+//! being stopped, so the child cannot keep interacting with the application
+//! during the graceful stop window. Uses `swaymsg input type:<kind> events`.
+
+use shepherd_host_api::{HostError, HostResult};
+use std::process::Command;
+use tracing::{debug, warn};
+
+/// Sway input device types affected by the lockout
+const LOCKED_INPUT_TYPES: &[&str] = &["keyboard", "pointer", "touchpad", "touch", "tablet_tool"];
+
+/// Check whether a Sway IPC socket is reachable
+pub fn is_sway_available() -> bool {
+    std::env::var_os("SWAYSOCK").is_some()
+        && Command::new("swaymsg")
+            .args(["-t", "get_version"])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+}
+
+/// Enable or disable events from all lockable input device types
+pub fn set_input_events_enabled(enabled: bool) -> HostResult<()> {
+    let state = if enabled { "enabled" } else { "disabled" };
+    let mut failures = Vec::new();
+
+    for input_type in LOCKED_INPUT_TYPES {
+        let selector = format!("type:{}", input_type);
+        match Command::new("swaymsg")
+            .args(["input", &selector, "events", state])
+            .output()
+        {
+            Ok(output) if output.status.success() => {
+                debug!(input_type = %input_type, state = %state, "Updated input events");
+            }
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                warn!(input_type = %input_type, stderr = %stderr, "swaymsg input command failed");
+                failures.push(input_type.to_string());
+            }
+            Err(e) => {
+                return Err(HostError::exec("swaymsg", &e));
+            }
+        }
+    }
+
+    // Not every device type is present on every machine, so only treat it
+    // as an error if every command failed
+    if failures.len() == LOCKED_INPUT_TYPES.len() {
+        return Err(HostError::command_failed(format!(
+            "Failed to set input events {} for all device types",
+            state
+        )));
+    }
+
+    Ok(())
+}
@@ -5,12 +5,77 @@
 //! - Graceful (SIGTERM) and forceful (SIGKILL) termination
 //! - Exit observation
 //! - stdout/stderr capture
-//! - Volume control with auto-detection of sound systems
+//! - Volume control with auto-detection of sound systems (natively on
+//!   PipeWire with the `pipewire` feature)
+//! - Input lockout while sessions expire (Sway)
+//! - Panic key force-stopping every session (Sway)
+//! - X11 fallback: EWMH window management and idle time (XScreenSaver)
+//! - Bluetooth controller and battery reporting (BlueZ)
+//! - Per-session camera/microphone blocking
+//! - Scheduled backlight brightness and night light
+//! - Curfew power off/suspend (logind)
+//! - Media player status and pause/resume (MPRIS)
+//! - Battery status (sysfs)
+//! - Launch pre-checks (binary, environment, disk space, display)
+//! - Spawn failure diagnostics (exec errors, stderr of early exits)
+//! - Per-entry pre-launch and post-exit hooks
+//! - Minecraft server allow-list and chat settings
+//! - Proton/WINE sessions followed through their prefix
+//! - RetroArch launches with save file backups
+//! - Kiosk escape route audit (VT switching, terminal bindings, GTK
+//!   inspector)
+//! - Entry health probes (installed binaries, snaps, flatpaks)
+//! - Installed app discovery (XDG desktop entries)
+//! - Snap/Flatpak app updates
+//! - Host helper process for privilege separation
 
 mod adapter;
+mod battery;
+mod bluetooth;
+mod brightness;
+mod devices;
+mod diagnostics;
+mod input;
+mod minecraft;
+mod discovery;
+mod hardening;
+mod health;
+mod helper;
+mod hooks;
+mod mpris;
+mod panic_key;
+mod pidfd;
+#[cfg(feature = "pipewire")]
+mod pipewire_volume;
+mod power;
+mod precheck;
+mod retro;
+mod update;
 mod process;
 mod volume;
+mod wine;
+mod x11;
 
 pub use adapter::*;
+pub use battery::*;
+pub use bluetooth::*;
+pub use brightness::*;
+pub use devices::*;
+pub use diagnostics::*;
+pub use input::*;
+pub use minecraft::*;
+pub use discovery::*;
+pub use hardening::*;
+pub use health::*;
+pub use helper::*;
+pub use hooks::*;
+pub use mpris::*;
+pub use panic_key::*;
+pub use power::*;
+pub use precheck::*;
+pub use retro::*;
+pub use update::*;
 pub use process::*;
 pub use volume::*;
+pub use wine::*;
+pub use x11::*;
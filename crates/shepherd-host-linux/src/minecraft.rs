@@ -0,0 +1,287 @@
+//! Minecraft server allow-list and chat settings
+//!
+//! Before a Minecraft entry launches, its game directory gets the server
+//! list and chat setting its config asks for, so whatever was added in-game
+//! since the last launch is gone again:
+//!
+//! - `servers.dat` (uncompressed NBT) is rewritten to list exactly the
+//!   allowed servers
+//! - `options.txt` gets `chatVisibility:2` (hidden) and `chatLinks:false`,
+//!   keeping every other option
+//!
+//! A file about to change is first copied to `shepherd-backups/` in the game
+//! directory, keeping the newest few. Direct Connect still reaches unlisted
+//! servers; block those at the network if that matters.
+
+use shepherd_host_api::{HostError, HostResult, MinecraftOptions, MinecraftServer};
+use std::path::Path;
+use tracing::{debug, info};
+
+use crate::adapter::expand_tilde;
+
+/// Where replaced files are copied, inside the game directory
+const BACKUP_DIR: &str = "shepherd-backups";
+
+/// Options written with `disable_chat`
+const CHAT_OPTIONS: &[(&str, &str)] = &[("chatVisibility", "2"), ("chatLinks", "false")];
+
+// NBT tag types
+const TAG_END: u8 = 0;
+const TAG_BYTE: u8 = 1;
+const TAG_STRING: u8 = 8;
+const TAG_LIST: u8 = 9;
+const TAG_COMPOUND: u8 = 10;
+
+/// Append a string in Java's modified UTF-8, as NBT stores them
+fn put_string(out: &mut Vec<u8>, text: &str) {
+    let mut bytes = Vec::new();
+    for unit in text.encode_utf16() {
+        match unit {
+            0x0001..=0x007f => bytes.push(unit as u8),
+            0x0000 | 0x0080..=0x07ff => {
+                bytes.push(0xc0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+            // Surrogates are encoded one by one
+            _ => {
+                bytes.push(0xe0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3f) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+        }
+    }
+    let len = u16::try_from(bytes.len()).unwrap_or(u16::MAX);
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(&bytes[..usize::from(len)]);
+}
+
+fn put_tag(out: &mut Vec<u8>, tag: u8, name: &str) {
+    out.push(tag);
+    put_string(out, name);
+}
+
+/// servers.dat listing `servers`, in order
+pub fn servers_dat(servers: &[MinecraftServer]) -> Vec<u8> {
+    let mut out = Vec::new();
+    put_tag(&mut out, TAG_COMPOUND, "");
+    put_tag(&mut out, TAG_LIST, "servers");
+    out.push(TAG_COMPOUND);
+    let count = i32::try_from(servers.len()).unwrap_or(i32::MAX);
+    out.extend_from_slice(&count.to_be_bytes());
+    for server in servers {
+        put_tag(&mut out, TAG_STRING, "name");
+        put_string(&mut out, &server.name);
+        put_tag(&mut out, TAG_STRING, "ip");
+        put_string(&mut out, &server.address);
+        // Ask before taking a server's resource pack
+        put_tag(&mut out, TAG_BYTE, "acceptTextures");
+        out.push(0);
+        out.push(TAG_END);
+    }
+    out.push(TAG_END);
+    out
+}
+
+/// options.txt with chat hidden, keeping the other options
+pub fn options_without_chat(existing: &str) -> String {
+    let mut lines: Vec<String> = existing
+        .lines()
+        .filter(|line| {
+            let key = line.split(':').next().unwrap_or_default();
+            !CHAT_OPTIONS.iter().any(|(option, _)| *option == key)
+        })
+        .map(str::to_string)
+        .collect();
+    lines.extend(CHAT_OPTIONS.iter().map(|(option, value)| format!("{}:{}", option, value)));
+    lines.join("\n") + "\n"
+}
+
+/// Replace `name` in `game_dir` with `contents`, backing up what was there.
+/// Returns false when it already had them.
+fn replace_file(game_dir: &Path, name: &str, contents: &[u8], backups: usize) -> std::io::Result<bool> {
+    let path = game_dir.join(name);
+    let existing = match std::fs::read(&path) {
+        Ok(existing) => Some(existing),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    if existing.as_deref() == Some(contents) {
+        return Ok(false);
+    }
+
+    if let Some(existing) = existing
+        && backups > 0
+    {
+        let backup_dir = game_dir.join(BACKUP_DIR);
+        std::fs::create_dir_all(&backup_dir)?;
+        let stamp = shepherd_util::now().format("%Y%m%d-%H%M%S");
+        std::fs::write(backup_dir.join(format!("{}.{}", name, stamp)), existing)?;
+        prune_backups(&backup_dir, name, backups)?;
+    }
+
+    // Write beside the file and rename, so the game never reads half of it
+    let temp = game_dir.join(format!(".{}.shepherd-tmp", name));
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, &path)?;
+    Ok(true)
+}
+
+/// Delete all but the newest `keep` backups of `name`
+pub(crate) fn prune_backups(backup_dir: &Path, name: &str, keep: usize) -> std::io::Result<()> {
+    let prefix = format!("{}.", name);
+    let mut backups: Vec<_> = std::fs::read_dir(backup_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(&prefix))
+        })
+        .collect();
+    // Timestamps sort by name
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for old in &backups[..excess] {
+        debug!(path = %old.display(), "Removing old backup");
+        std::fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// Write the server list and chat setting into the game directory
+pub fn apply_minecraft(options: &MinecraftOptions) -> HostResult<()> {
+    let game_dir = expand_tilde(&options.game_dir.to_string_lossy());
+    let game_dir = Path::new(&game_dir);
+    let failed = |what: &str, e: std::io::Error| {
+        HostError::command_failed(format!(
+            "Failed to update Minecraft {} in {}: {}",
+            what,
+            game_dir.display(),
+            e
+        ))
+    };
+    std::fs::create_dir_all(game_dir).map_err(|e| failed("game directory", e))?;
+
+    if let Some(servers) = &options.servers {
+        let changed = replace_file(game_dir, "servers.dat", &servers_dat(servers), options.backups)
+            .map_err(|e| failed("server list", e))?;
+        if changed {
+            info!(game_dir = %game_dir.display(), servers = servers.len(), "Reset Minecraft server list");
+        }
+    }
+
+    if options.disable_chat {
+        let path = game_dir.join("options.txt");
+        let existing = match std::fs::read_to_string(&path) {
+            Ok(existing) => existing,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(failed("options", e)),
+        };
+        let changed = replace_file(
+            game_dir,
+            "options.txt",
+            options_without_chat(&existing).as_bytes(),
+            options.backups,
+        )
+        .map_err(|e| failed("options", e))?;
+        if changed {
+            info!(game_dir = %game_dir.display(), "Hid Minecraft chat");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(name: &str, address: &str) -> MinecraftServer {
+        MinecraftServer {
+            name: name.into(),
+            address: address.into(),
+        }
+    }
+
+    #[test]
+    fn test_servers_dat() {
+        let dat = servers_dat(&[server("Home", "192.168.1.5")]);
+        let mut expected = vec![TAG_COMPOUND, 0, 0, TAG_LIST, 0, 7];
+        expected.extend_from_slice(b"servers");
+        expected.extend_from_slice(&[TAG_COMPOUND, 0, 0, 0, 1]);
+        expected.extend_from_slice(&[TAG_STRING, 0, 4]);
+        expected.extend_from_slice(b"name");
+        expected.extend_from_slice(&[0, 4]);
+        expected.extend_from_slice(b"Home");
+        expected.extend_from_slice(&[TAG_STRING, 0, 2]);
+        expected.extend_from_slice(b"ip");
+        expected.extend_from_slice(&[0, 11]);
+        expected.extend_from_slice(b"192.168.1.5");
+        expected.extend_from_slice(&[TAG_BYTE, 0, 14]);
+        expected.extend_from_slice(b"acceptTextures");
+        expected.extend_from_slice(&[0, TAG_END, TAG_END]);
+        assert_eq!(dat, expected);
+
+        // Modified UTF-8: NUL takes two bytes, emoji are two surrogates
+        let mut out = Vec::new();
+        put_string(&mut out, "é\0🙂");
+        assert_eq!(out, [0, 10, 0xc3, 0xa9, 0xc0, 0x80, 0xed, 0xa0, 0xbd, 0xed, 0xb9, 0x82]);
+    }
+
+    #[test]
+    fn test_options_without_chat() {
+        let options = "version:3465\nchatVisibility:0\nlang:en_us\nchatLinks:true\n";
+        assert_eq!(
+            options_without_chat(options),
+            "version:3465\nlang:en_us\nchatVisibility:2\nchatLinks:false\n"
+        );
+        assert_eq!(options_without_chat(""), "chatVisibility:2\nchatLinks:false\n");
+    }
+
+    #[test]
+    fn test_apply_minecraft() {
+        let dir = tempfile::tempdir().unwrap();
+        let game_dir = dir.path().join(".minecraft");
+        let options = MinecraftOptions {
+            game_dir: game_dir.clone(),
+            servers: Some(vec![server("Family", "mc.example.net")]),
+            disable_chat: true,
+            backups: 2,
+        };
+        let backups = || std::fs::read_dir(game_dir.join(BACKUP_DIR)).map_or(0, |d| d.count());
+
+        // A fresh game directory has nothing to back up
+        apply_minecraft(&options).unwrap();
+        assert_eq!(std::fs::read(game_dir.join("servers.dat")).unwrap(), servers_dat(&options.servers.clone().unwrap()));
+        assert_eq!(backups(), 0);
+
+        // Unchanged files aren't backed up again
+        apply_minecraft(&options).unwrap();
+        assert_eq!(backups(), 0);
+
+        // Servers added in-game are backed up and removed
+        std::fs::write(game_dir.join("servers.dat"), servers_dat(&[server("Anything", "example.com")])).unwrap();
+        std::fs::write(game_dir.join("options.txt"), "chatVisibility:0\nfov:0.5\n").unwrap();
+        apply_minecraft(&options).unwrap();
+        assert_eq!(backups(), 2);
+        assert_eq!(
+            std::fs::read_to_string(game_dir.join("options.txt")).unwrap(),
+            "fov:0.5\nchatVisibility:2\nchatLinks:false\n"
+        );
+
+        // Only the newest backups are kept
+        let backup_dir = game_dir.join(BACKUP_DIR);
+        for stamp in ["20240101-000000", "20240102-000000"] {
+            std::fs::write(backup_dir.join(format!("servers.dat.{}", stamp)), b"old").unwrap();
+        }
+        std::fs::write(game_dir.join("servers.dat"), b"changed").unwrap();
+        apply_minecraft(&options).unwrap();
+        let mut names: Vec<String> = std::fs::read_dir(&backup_dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|n| n.starts_with("servers.dat."))
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[0], "servers.dat.20240102-000000");
+    }
+}
@@ -0,0 +1,218 @@
+//! Media players over MPRIS
+//!
+//! Players own an `org.mpris.MediaPlayer2.*` name on the session bus. A
+//! player belongs to a session when the process owning its name is the
+//! session's process or one of its descendants. The bus is called with
+//! `busctl --user`, the same way `power` calls logind.
+
+use serde_json::Value;
+use shepherd_api::{MediaInfo, PlaybackStatus};
+use shepherd_host_api::{HostError, HostResult};
+use std::process::Command;
+use tracing::debug;
+
+use crate::process::descendant_pids;
+
+/// Bus name prefix every MPRIS player registers under
+const PREFIX: &str = "org.mpris.MediaPlayer2.";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// Run `busctl --user --json=short` and parse what it prints
+fn busctl(args: &[&str]) -> HostResult<Value> {
+    let output = Command::new("busctl")
+        .args(["--user", "--json=short"])
+        .args(args)
+        .output()
+        .map_err(|e| HostError::exec("busctl", &e))?;
+    if !output.status.success() {
+        return Err(HostError::command_failed(format!(
+            "busctl {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| HostError::command_failed(format!("Unexpected busctl output: {}", e)))
+}
+
+/// Whether `busctl` can reach a session bus
+pub fn is_mpris_available() -> bool {
+    Command::new("busctl")
+        .args(["--user", "status"])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Bus names of the players belonging to the process `pid` or its
+/// descendants
+pub fn session_players(pid: u32) -> HostResult<Vec<String>> {
+    let names = busctl(&[
+        "call",
+        "org.freedesktop.DBus",
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus",
+        "ListNames",
+    ])?;
+    let mut pids: Vec<u32> = descendant_pids(pid as i32)
+        .into_iter()
+        .map(|pid| pid as u32)
+        .collect();
+    pids.push(pid);
+
+    Ok(player_names(&names)
+        .into_iter()
+        .filter(|name| owner_pid(name).is_some_and(|owner| pids.contains(&owner)))
+        .collect())
+}
+
+/// The `org.mpris.MediaPlayer2.*` names in a `ListNames` reply
+fn player_names(reply: &Value) -> Vec<String> {
+    reply["data"][0]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .filter(|name| name.starts_with(PREFIX))
+        .map(String::from)
+        .collect()
+}
+
+/// The process owning a bus name
+fn owner_pid(name: &str) -> Option<u32> {
+    let reply = busctl(&[
+        "call",
+        "org.freedesktop.DBus",
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus",
+        "GetConnectionUnixProcessID",
+        "s",
+        name,
+    ])
+    .ok()?;
+    reply["data"][0].as_u64().and_then(|pid| u32::try_from(pid).ok())
+}
+
+fn get_property(name: &str, property: &str) -> HostResult<Value> {
+    busctl(&["get-property", name, OBJECT_PATH, PLAYER_INTERFACE, property])
+}
+
+/// What the player owning `name` is playing
+pub fn player_status(name: &str) -> HostResult<MediaInfo> {
+    let status = get_property(name, "PlaybackStatus")?;
+    // Some players have no metadata while stopped
+    let metadata = get_property(name, "Metadata").unwrap_or(Value::Null);
+    Ok(media_info(name, &status, &metadata))
+}
+
+fn media_info(name: &str, status: &Value, metadata: &Value) -> MediaInfo {
+    let status = match status["data"].as_str() {
+        Some("Playing") => PlaybackStatus::Playing,
+        Some("Paused") => PlaybackStatus::Paused,
+        _ => PlaybackStatus::Stopped,
+    };
+    let entries = &metadata["data"];
+    let title = entries["xesam:title"]["data"]
+        .as_str()
+        .filter(|title| !title.is_empty())
+        .map(String::from);
+    let artist = entries["xesam:artist"]["data"]
+        .as_array()
+        .map(|artists| {
+            artists
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .filter(|artist| !artist.is_empty());
+
+    MediaInfo {
+        player: player_label(name),
+        status,
+        title,
+        artist,
+    }
+}
+
+/// "vlc" for "org.mpris.MediaPlayer2.vlc", "firefox" for
+/// "org.mpris.MediaPlayer2.firefox.instance_1_42"
+fn player_label(name: &str) -> String {
+    let name = name.strip_prefix(PREFIX).unwrap_or(name);
+    name.split('.').next().unwrap_or(name).to_string()
+}
+
+/// What the session's players are playing, preferring one that plays
+pub fn session_media(pid: u32) -> HostResult<Option<MediaInfo>> {
+    let mut players = Vec::new();
+    for name in session_players(pid)? {
+        match player_status(&name) {
+            Ok(media) => players.push(media),
+            Err(e) => debug!(player = %name, error = %e, "Failed to read player status"),
+        }
+    }
+    let playing = players
+        .iter()
+        .position(|media| media.status == PlaybackStatus::Playing)
+        .unwrap_or(0);
+    Ok((!players.is_empty()).then(|| players.swap_remove(playing)))
+}
+
+/// Pause the session's playing players, or resume its paused ones
+pub fn set_session_playing(pid: u32, playing: bool) -> HostResult<()> {
+    let (from, method) = if playing {
+        (PlaybackStatus::Paused, "Play")
+    } else {
+        (PlaybackStatus::Playing, "Pause")
+    };
+    for name in session_players(pid)? {
+        if player_status(&name).is_ok_and(|media| media.status == from) {
+            debug!(player = %name, method, "Controlling media player");
+            busctl(&["call", &name, OBJECT_PATH, PLAYER_INTERFACE, method])?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_player_names() {
+        let reply = json!({
+            "type": "as",
+            "data": [["org.freedesktop.DBus", ":1.42", "org.mpris.MediaPlayer2.vlc", "org.mpris.MediaPlayer2.firefox.instance_1_7"]]
+        });
+        assert_eq!(
+            player_names(&reply),
+            vec!["org.mpris.MediaPlayer2.vlc", "org.mpris.MediaPlayer2.firefox.instance_1_7"]
+        );
+        assert!(player_names(&json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_media_info() {
+        let status = json!({"type": "s", "data": "Playing"});
+        let metadata = json!({
+            "type": "a{sv}",
+            "data": {
+                "xesam:title": {"type": "s", "data": "Lofi Beats"},
+                "xesam:artist": {"type": "as", "data": ["Chillhop", "Friends"]},
+                "mpris:length": {"type": "x", "data": 180000000}
+            }
+        });
+        let media = media_info("org.mpris.MediaPlayer2.firefox.instance_1_7", &status, &metadata);
+        assert_eq!(media.player, "firefox");
+        assert_eq!(media.status, PlaybackStatus::Playing);
+        assert_eq!(media.title.as_deref(), Some("Lofi Beats"));
+        assert_eq!(media.artist.as_deref(), Some("Chillhop, Friends"));
+
+        let media = media_info("org.mpris.MediaPlayer2.vlc", &json!({"data": "Stopped"}), &Value::Null);
+        assert_eq!(media.status, PlaybackStatus::Stopped);
+        assert_eq!(media.title, None);
+        assert_eq!(media.artist, None);
+    }
+}
@@ -0,0 +1,103 @@
+//! Panic key via the Sway compositor
+//!
+//! Binds a key combination to a `nop` command with `swaymsg bindsym
+//! --locked`, so Sway handles it even over a fullscreen game that grabbed
+//! the keyboard, then follows Sway's binding events and reports each press
+//! as [`HostEvent::PanicKeyPressed`]. Like every key, it does nothing while
+//! keyboard events are disabled by the input lockout.
+
+use serde_json::Value;
+use shepherd_host_api::{HostError, HostEvent, HostResult};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// Command the panic key runs; Sway echoes it in binding events
+const PANIC_COMMAND: &str = "nop shepherd-panic";
+
+/// Wait before restarting a failed binding subscription
+const WATCH_RETRY: Duration = Duration::from_secs(5);
+
+/// Bind `combo` to the panic command in the running Sway
+pub fn bind_panic_key(combo: &str) -> HostResult<()> {
+    let output = Command::new("swaymsg")
+        .args(["bindsym", "--locked", combo, PANIC_COMMAND])
+        .output()
+        .map_err(|e| HostError::exec("swaymsg", &e))?;
+    if !output.status.success() {
+        return Err(HostError::command_failed(format!(
+            "Failed to bind panic key {}: {}",
+            combo,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    info!(combo = %combo, "Bound panic key");
+    Ok(())
+}
+
+/// Whether a line from `swaymsg -t subscribe -m '["binding"]'` is a press
+/// of the panic key
+fn is_panic_binding(line: &str) -> bool {
+    serde_json::from_str::<Value>(line).is_ok_and(|event| {
+        event.get("change").and_then(Value::as_str) == Some("run")
+            && event
+                .pointer("/binding/command")
+                .and_then(Value::as_str)
+                .is_some_and(|command| command.trim() == PANIC_COMMAND)
+    })
+}
+
+/// Follow Sway's binding events and send [`HostEvent::PanicKeyPressed`]
+/// for each panic key press, until the receiver is dropped
+pub async fn watch_panic_key(tx: mpsc::UnboundedSender<HostEvent>) {
+    while !tx.is_closed() {
+        let mut child = match tokio::process::Command::new("swaymsg")
+            .args(["-t", "subscribe", "-m", r#"["binding"]"#])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                warn!(error = %e, "Failed to watch the panic key, retrying");
+                tokio::time::sleep(WATCH_RETRY).await;
+                continue;
+            }
+        };
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let mut lines = BufReader::new(stdout).lines();
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            if !is_panic_binding(&line) {
+                continue;
+            }
+            warn!("Panic key pressed");
+            if tx.send(HostEvent::PanicKeyPressed).is_err() {
+                return;
+            }
+        }
+
+        warn!("Sway binding subscription ended, restarting");
+        tokio::time::sleep(WATCH_RETRY).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_binding() {
+        assert!(is_panic_binding(
+            r#"{"change": "run", "binding": {"command": "nop shepherd-panic", "event_state_mask": ["Mod4", "Ctrl"], "symbol": "BackSpace", "input_type": "keyboard"}}"#
+        ));
+        assert!(!is_panic_binding(
+            r#"{"change": "run", "binding": {"command": "exec pkill -TERM shepherdd && swaymsg exit"}}"#
+        ));
+        assert!(!is_panic_binding(r#"{"success": true}"#));
+        assert!(!is_panic_binding("not json"));
+    }
+}
@@ -0,0 +1,80 @@
+//! Waiting for process exits with pidfds
+//!
+//! A pidfd (`pidfd_open(2)`, Linux 5.3+) becomes readable when its process
+//! exits, so the monitor can sleep until a session ends instead of polling
+//! every process. Opening one doesn't reap the process; the monitor still
+//! collects the exit status with `try_wait()`. Older kernels return
+//! `ENOSYS`, and the monitor falls back to polling.
+
+use nix::libc;
+use std::io;
+use std::os::fd::{FromRawFd, OwnedFd};
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+
+/// A file descriptor referring to one process
+#[derive(Debug)]
+pub struct PidFd(OwnedFd);
+
+impl PidFd {
+    /// Open a pidfd for `pid`. The process must be our unreaped child, so
+    /// the PID can't have been reused.
+    pub fn open(pid: u32) -> io::Result<Self> {
+        // SAFETY: pidfd_open takes a PID and flags and returns a new fd or -1
+        let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the fd was just created and nothing else owns it
+        Ok(Self(unsafe { OwnedFd::from_raw_fd(fd as i32) }))
+    }
+
+    /// Wait until the process exits
+    pub async fn exited(self) -> io::Result<()> {
+        let fd = AsyncFd::with_interest(self.0, Interest::READABLE)?;
+        let _ready = fd.readable().await?;
+        Ok(())
+    }
+}
+
+/// Whether the running kernel has `pidfd_open`
+pub fn pidfd_supported() -> bool {
+    PidFd::open(std::process::id()).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_exit_wakes_the_waiter() {
+        if !pidfd_supported() {
+            return;
+        }
+        let mut child = Command::new("sleep").arg("0.2").spawn().unwrap();
+        let pidfd = PidFd::open(child.id()).unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), pidfd.exited())
+            .await
+            .expect("exit not noticed")
+            .unwrap();
+        // Still ours to reap
+        assert!(child.try_wait().unwrap().unwrap().success());
+    }
+
+    #[tokio::test]
+    async fn test_running_process_keeps_waiting() {
+        if !pidfd_supported() {
+            return;
+        }
+        let mut child = Command::new("sleep").arg("5").spawn().unwrap();
+        let pidfd = PidFd::open(child.id()).unwrap();
+
+        let waited = tokio::time::timeout(Duration::from_millis(100), pidfd.exited()).await;
+        assert!(waited.is_err());
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
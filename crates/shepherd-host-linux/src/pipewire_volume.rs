@@ -0,0 +1,610 @@
+//! Native PipeWire volume backend
+//!
+//! Talks to PipeWire directly instead of spawning `wpctl` for every read
+//! and parsing its output. The default sink is followed through the
+//! `default` metadata, its `Props` param is subscribed to, and volume and
+//! mute are written back with `set_param`. Whether it plays through
+//! headphones comes from the node's form factor and the active `Route` of
+//! the card behind it. PipeWire objects can't leave
+//! the thread that owns their loop, so a dedicated thread runs it and
+//! takes commands through a channel.
+//!
+//! Built with the `pipewire` feature, which needs `libpipewire-0.3` and
+//! libclang at build time. [`LinuxVolumeController`](crate::LinuxVolumeController)
+//! falls back to `wpctl` when the server can't be reached.
+
+use pipewire as pw;
+use pw::device::{Device, DeviceListener};
+use pw::metadata::{Metadata, MetadataListener};
+use pw::node::{Node, NodeListener};
+use pw::spa::param::ParamType;
+use pw::spa::pod::deserialize::PodDeserializer;
+use pw::spa::pod::serialize::PodSerializer;
+use pw::spa::pod::{Object, Pod, Property, Value, ValueArray};
+use pw::spa::utils::SpaTypes;
+use pw::types::ObjectType;
+use shepherd_api::AudioOutput;
+use shepherd_host_api::{VolumeError, VolumeResult, VolumeStatus};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info, warn};
+
+/// How long to wait for the PipeWire thread to connect
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long to wait for the loop thread to take a command
+const CALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Metadata key naming the default output
+const DEFAULT_SINK_KEY: &str = "default.audio.sink";
+
+enum Request {
+    SetVolume(u8),
+    SetMute(bool),
+    ToggleMute,
+}
+
+struct Command {
+    request: Request,
+    reply: oneshot::Sender<VolumeResult<()>>,
+}
+
+/// Volume and mute of a sink, as PipeWire reports them
+#[derive(Debug, Clone, PartialEq)]
+struct SinkProps {
+    /// Linear per-channel volumes
+    channel_volumes: Vec<f32>,
+    mute: bool,
+}
+
+impl SinkProps {
+    /// On the cubic scale `wpctl` and `pactl` show, so 50% sounds like
+    /// half as loud rather than a barely audible change
+    fn status(&self) -> VolumeStatus {
+        let mean = if self.channel_volumes.is_empty() {
+            0.0
+        } else {
+            self.channel_volumes.iter().sum::<f32>() / self.channel_volumes.len() as f32
+        };
+        VolumeStatus {
+            percent: (mean.cbrt() * 100.0).round().clamp(0.0, 255.0) as u8,
+            muted: self.mute,
+            ..Default::default()
+        }
+    }
+
+    fn from_pod(pod: &Pod) -> Option<Self> {
+        let Ok((_, Value::Object(object))) = PodDeserializer::deserialize_any_from(pod.as_bytes())
+        else {
+            return None;
+        };
+        let mut channel_volumes = None;
+        let mut mute = None;
+        for property in object.properties {
+            match (property.key, property.value) {
+                (pw::spa::sys::SPA_PROP_channelVolumes, Value::ValueArray(ValueArray::Float(v))) => {
+                    channel_volumes = Some(v)
+                }
+                (pw::spa::sys::SPA_PROP_mute, Value::Bool(b)) => mute = Some(b),
+                _ => {}
+            }
+        }
+        // Props also arrive for unrelated changes, without these
+        Some(Self {
+            channel_volumes: channel_volumes?,
+            mute: mute.unwrap_or(false),
+        })
+    }
+}
+
+/// A `Props` param setting some of volume and mute
+fn props_pod(channel_volumes: Option<Vec<f32>>, mute: Option<bool>) -> Vec<u8> {
+    let mut properties = Vec::new();
+    if let Some(volumes) = channel_volumes {
+        properties.push(Property::new(
+            pw::spa::sys::SPA_PROP_channelVolumes,
+            Value::ValueArray(ValueArray::Float(volumes)),
+        ));
+    }
+    if let Some(mute) = mute {
+        properties.push(Property::new(pw::spa::sys::SPA_PROP_mute, Value::Bool(mute)));
+    }
+    let object = Value::Object(Object {
+        type_: SpaTypes::ObjectParamProps.as_raw(),
+        id: ParamType::Props.as_raw(),
+        properties,
+    });
+    PodSerializer::serialize(std::io::Cursor::new(Vec::new()), &object)
+        .expect("serializing to memory can't fail")
+        .0
+        .into_inner()
+}
+
+struct Sink {
+    node: Node,
+    _listener: NodeListener,
+    props: Option<SinkProps>,
+    /// `device.form-factor`, e.g. "headset" for Bluetooth headsets
+    form_factor: Option<String>,
+    /// The card (`device.id`) and its device index (`card.profile.device`)
+    card: Option<(u32, i32)>,
+}
+
+/// A sound card, watched for the port each of its outputs uses
+struct Card {
+    _device: Device,
+    _listener: DeviceListener,
+    /// Active output port name per device index
+    routes: HashMap<i32, String>,
+}
+
+/// Everything the loop thread knows, shared between its callbacks
+#[derive(Default)]
+struct State {
+    sinks: HashMap<u32, Sink>,
+    cards: HashMap<u32, Card>,
+    /// Node name to global id
+    names: HashMap<String, u32>,
+    default_sink: Option<String>,
+    metadata: Option<(Metadata, MetadataListener)>,
+    last: Option<VolumeStatus>,
+}
+
+impl State {
+    fn default_sink(&self) -> Option<&Sink> {
+        let id = self.names.get(self.default_sink.as_ref()?)?;
+        self.sinks.get(id)
+    }
+
+    fn output(&self, sink: &Sink) -> AudioOutput {
+        let port = sink
+            .card
+            .and_then(|(card, index)| self.cards.get(&card)?.routes.get(&index));
+        crate::volume::classify_output(sink.form_factor.as_deref(), port.map(String::as_str))
+    }
+
+    fn status(&self) -> Option<VolumeStatus> {
+        let sink = self.default_sink()?;
+        Some(VolumeStatus {
+            output: self.output(sink),
+            ..sink.props.as_ref()?.status()
+        })
+    }
+
+    fn handle(&self, request: Request) -> VolumeResult<()> {
+        let sink = self
+            .default_sink()
+            .ok_or_else(|| VolumeError::NotAvailable("No default audio sink".into()))?;
+        let props = sink
+            .props
+            .as_ref()
+            .ok_or_else(|| VolumeError::Backend("Default sink has not reported its volume".into()))?;
+        let pod = match request {
+            Request::SetVolume(percent) => {
+                let linear = (f32::from(percent) / 100.0).powi(3);
+                props_pod(Some(vec![linear; props.channel_volumes.len()]), None)
+            }
+            Request::SetMute(muted) => props_pod(None, Some(muted)),
+            Request::ToggleMute => props_pod(None, Some(!props.mute)),
+        };
+        let pod = Pod::from_bytes(&pod).expect("serialized pod is valid");
+        sink.node.set_param(ParamType::Props, 0, pod);
+        Ok(())
+    }
+}
+
+/// Connection to PipeWire on its own thread
+pub struct PipeWireVolume {
+    commands: pw::channel::Sender<Command>,
+    status: Arc<Mutex<Option<VolumeStatus>>>,
+    changes: Mutex<Option<mpsc::UnboundedReceiver<VolumeStatus>>>,
+    connected: Arc<AtomicBool>,
+}
+
+impl PipeWireVolume {
+    /// Connect to the session's PipeWire server, or None if it can't be reached
+    pub fn connect() -> Option<Self> {
+        let (command_tx, command_rx) = pw::channel::channel::<Command>();
+        let (changes_tx, changes_rx) = mpsc::unbounded_channel();
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        let status = Arc::new(Mutex::new(None));
+        let connected = Arc::new(AtomicBool::new(true));
+
+        let thread_status = status.clone();
+        let thread_connected = connected.clone();
+        std::thread::Builder::new()
+            .name("pipewire-volume".into())
+            .spawn(move || {
+                if let Err(e) = run(command_rx, thread_status.clone(), changes_tx, &ready_tx) {
+                    let _ = ready_tx.send(Err(e.to_string()));
+                }
+                // Let the controller fall back to wpctl
+                thread_connected.store(false, Ordering::SeqCst);
+                *thread_status.lock().unwrap() = None;
+            })
+            .ok()?;
+
+        match ready_rx.recv_timeout(CONNECT_TIMEOUT) {
+            Ok(Ok(())) => {
+                info!("Connected to PipeWire for volume control");
+                Some(Self {
+                    commands: command_tx,
+                    status,
+                    changes: Mutex::new(Some(changes_rx)),
+                    connected,
+                })
+            }
+            Ok(Err(e)) => {
+                warn!(error = %e, "Cannot connect to PipeWire, using wpctl");
+                None
+            }
+            Err(_) => {
+                warn!("Timed out connecting to PipeWire, using wpctl");
+                None
+            }
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    /// The default sink's volume, once it has been reported
+    pub fn status(&self) -> Option<VolumeStatus> {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub async fn set_volume(&self, percent: u8) -> VolumeResult<()> {
+        self.call(Request::SetVolume(percent)).await
+    }
+
+    pub async fn set_mute(&self, muted: bool) -> VolumeResult<()> {
+        self.call(Request::SetMute(muted)).await
+    }
+
+    pub async fn toggle_mute(&self) -> VolumeResult<()> {
+        self.call(Request::ToggleMute).await
+    }
+
+    /// Changes of the default sink's volume. Can only be taken once.
+    pub fn take_changes(&self) -> Option<mpsc::UnboundedReceiver<VolumeStatus>> {
+        self.changes.lock().unwrap().take()
+    }
+
+    async fn call(&self, request: Request) -> VolumeResult<()> {
+        let (reply, rx) = oneshot::channel();
+        let lost = || VolumeError::Backend("PipeWire connection lost".into());
+        if !self.is_connected() {
+            return Err(lost());
+        }
+        self.commands
+            .send(Command { request, reply })
+            .map_err(|_| lost())?;
+        match tokio::time::timeout(CALL_TIMEOUT, rx).await {
+            Ok(Ok(result)) => result,
+            _ => Err(lost()),
+        }
+    }
+}
+
+/// Run the PipeWire loop until the server goes away
+fn run(
+    commands: pw::channel::Receiver<Command>,
+    status: Arc<Mutex<Option<VolumeStatus>>>,
+    changes: mpsc::UnboundedSender<VolumeStatus>,
+    ready: &std::sync::mpsc::Sender<Result<(), String>>,
+) -> Result<(), pw::Error> {
+    pw::init();
+    let main_loop = pw::main_loop::MainLoopRc::new(None)?;
+    let context = pw::context::ContextRc::new(&main_loop, None)?;
+    let core = context.connect_rc(None)?;
+    let registry = core.get_registry_rc()?;
+
+    let state = Rc::new(RefCell::new(State::default()));
+
+    // Publish the default sink's volume whenever it or the default changes
+    let publish: Rc<dyn Fn(&mut State)> = Rc::new(move |state: &mut State| {
+        let current = state.status();
+        if current.is_none() || current == state.last {
+            return;
+        }
+        state.last = current.clone();
+        *status.lock().unwrap() = current.clone();
+        if let Some(current) = current {
+            let _ = changes.send(current);
+        }
+    });
+
+    let main_loop_weak = main_loop.downgrade();
+    let _core_listener = core
+        .add_listener_local()
+        .error(move |id, _seq, res, message| {
+            warn!(id, res, error = message, "PipeWire error");
+            if id == pw::core::PW_ID_CORE
+                && let Some(main_loop) = main_loop_weak.upgrade()
+            {
+                main_loop.quit();
+            }
+        })
+        .register();
+
+    let registry_weak = registry.downgrade();
+    let global_state = Rc::downgrade(&state);
+    let global_publish = publish.clone();
+    let remove_state = Rc::downgrade(&state);
+    let remove_publish = publish.clone();
+    let _registry_listener = registry
+        .add_listener_local()
+        .global(move |global| {
+            let (Some(registry), Some(state)) = (registry_weak.upgrade(), global_state.upgrade())
+            else {
+                return;
+            };
+            let Some(props) = global.props else {
+                return;
+            };
+            match global.type_ {
+                ObjectType::Node if props.get(*pw::keys::MEDIA_CLASS) == Some("Audio/Sink") => {
+                    let Some(name) = props.get(*pw::keys::NODE_NAME).map(str::to_owned) else {
+                        return;
+                    };
+                    let node: Node = match registry.bind(global) {
+                        Ok(node) => node,
+                        Err(e) => {
+                            warn!(error = %e, name = %name, "Failed to bind audio sink");
+                            return;
+                        }
+                    };
+                    let id = global.id;
+                    let info_state = Rc::downgrade(&state);
+                    let info_publish = global_publish.clone();
+                    let param_state = Rc::downgrade(&state);
+                    let param_publish = global_publish.clone();
+                    let listener = node
+                        .add_listener_local()
+                        .info(move |info| {
+                            let (Some(state), Some(props)) = (info_state.upgrade(), info.props())
+                            else {
+                                return;
+                            };
+                            let mut state = state.borrow_mut();
+                            if let Some(sink) = state.sinks.get_mut(&id) {
+                                sink.form_factor = props.get("device.form-factor").map(str::to_owned);
+                                sink.card = props
+                                    .get("device.id")
+                                    .and_then(|card| card.parse().ok())
+                                    .zip(props.get("card.profile.device").and_then(|i| i.parse().ok()));
+                            }
+                            info_publish(&mut state);
+                        })
+                        .param(move |_seq, param_id, _index, _next, param| {
+                            if param_id != ParamType::Props {
+                                return;
+                            }
+                            let (Some(state), Some(props)) =
+                                (param_state.upgrade(), param.and_then(SinkProps::from_pod))
+                            else {
+                                return;
+                            };
+                            let mut state = state.borrow_mut();
+                            if let Some(sink) = state.sinks.get_mut(&id) {
+                                sink.props = Some(props);
+                            }
+                            param_publish(&mut state);
+                        })
+                        .register();
+                    node.subscribe_params(&[ParamType::Props]);
+                    debug!(id, name = %name, "Watching audio sink");
+
+                    let mut state = state.borrow_mut();
+                    state.names.insert(name, id);
+                    state.sinks.insert(
+                        id,
+                        Sink {
+                            node,
+                            _listener: listener,
+                            props: None,
+                            form_factor: None,
+                            card: None,
+                        },
+                    );
+                }
+                ObjectType::Device if props.get(*pw::keys::MEDIA_CLASS) == Some("Audio/Device") => {
+                    let device: Device = match registry.bind(global) {
+                        Ok(device) => device,
+                        Err(e) => {
+                            warn!(error = %e, "Failed to bind sound card");
+                            return;
+                        }
+                    };
+                    let id = global.id;
+                    let route_state = Rc::downgrade(&state);
+                    let route_publish = global_publish.clone();
+                    let listener = device
+                        .add_listener_local()
+                        .param(move |_seq, param_id, _index, _next, param| {
+                            if param_id != ParamType::Route {
+                                return;
+                            }
+                            let (Some(state), Some((index, port))) =
+                                (route_state.upgrade(), param.and_then(output_route))
+                            else {
+                                return;
+                            };
+                            let mut state = state.borrow_mut();
+                            if let Some(card) = state.cards.get_mut(&id) {
+                                debug!(card = id, index, port = %port, "Output port changed");
+                                card.routes.insert(index, port);
+                            }
+                            route_publish(&mut state);
+                        })
+                        .register();
+                    device.subscribe_params(&[ParamType::Route]);
+                    state.borrow_mut().cards.insert(
+                        id,
+                        Card {
+                            _device: device,
+                            _listener: listener,
+                            routes: HashMap::new(),
+                        },
+                    );
+                }
+                ObjectType::Metadata if props.get("metadata.name") == Some("default") => {
+                    let metadata: Metadata = match registry.bind(global) {
+                        Ok(metadata) => metadata,
+                        Err(e) => {
+                            warn!(error = %e, "Failed to bind default metadata");
+                            return;
+                        }
+                    };
+                    let property_state = Rc::downgrade(&state);
+                    let property_publish = global_publish.clone();
+                    let listener = metadata
+                        .add_listener_local()
+                        .property(move |_subject, key, _type, value| {
+                            if key.is_some_and(|key| key != DEFAULT_SINK_KEY) {
+                                return 0;
+                            }
+                            let Some(state) = property_state.upgrade() else {
+                                return 0;
+                            };
+                            let mut state = state.borrow_mut();
+                            state.default_sink = value.and_then(default_sink_name);
+                            debug!(sink = ?state.default_sink, "Default audio sink changed");
+                            property_publish(&mut state);
+                            0
+                        })
+                        .register();
+                    state.borrow_mut().metadata = Some((metadata, listener));
+                }
+                _ => {}
+            }
+        })
+        .global_remove(move |id| {
+            let Some(state) = remove_state.upgrade() else {
+                return;
+            };
+            let mut state = state.borrow_mut();
+            if state.sinks.remove(&id).is_some() {
+                state.names.retain(|_, sink| *sink != id);
+                remove_publish(&mut state);
+            } else if state.cards.remove(&id).is_some() {
+                remove_publish(&mut state);
+            }
+        })
+        .register();
+
+    let command_state = Rc::downgrade(&state);
+    let _commands = commands.attach(main_loop.loop_(), move |command: Command| {
+        let result = match command_state.upgrade() {
+            Some(state) => state.borrow().handle(command.request),
+            None => Err(VolumeError::Backend("PipeWire connection lost".into())),
+        };
+        let _ = command.reply.send(result);
+    });
+
+    let _ = ready.send(Ok(()));
+    main_loop.run();
+    warn!("PipeWire connection closed");
+    Ok(())
+}
+
+/// The sink name in a `default.audio.sink` value, `{"name": "..."}`
+fn default_sink_name(value: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(value)
+        .ok()?
+        .get("name")?
+        .as_str()
+        .map(str::to_owned)
+}
+
+/// The card device index and port name of an active output route
+fn output_route(pod: &Pod) -> Option<(i32, String)> {
+    let Ok((_, Value::Object(object))) = PodDeserializer::deserialize_any_from(pod.as_bytes())
+    else {
+        return None;
+    };
+    let mut direction = None;
+    let mut device = None;
+    let mut name = None;
+    for property in object.properties {
+        match (property.key, property.value) {
+            (pw::spa::sys::SPA_PARAM_ROUTE_direction, Value::Id(id)) => direction = Some(id.0),
+            (pw::spa::sys::SPA_PARAM_ROUTE_device, Value::Int(index)) => device = Some(index),
+            (pw::spa::sys::SPA_PARAM_ROUTE_name, Value::String(port)) => name = Some(port),
+            _ => {}
+        }
+    }
+    (direction? == pw::spa::sys::SPA_DIRECTION_OUTPUT).then_some((device?, name?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_props_round_trip() {
+        let bytes = props_pod(Some(vec![0.125, 0.125]), Some(true));
+        let props = SinkProps::from_pod(Pod::from_bytes(&bytes).unwrap()).unwrap();
+        assert_eq!(props.channel_volumes, vec![0.125, 0.125]);
+        // 0.5 on the cubic scale
+        assert_eq!(
+            props.status(),
+            VolumeStatus {
+                percent: 50,
+                muted: true,
+                output: AudioOutput::Unknown,
+            }
+        );
+
+        // Props without volumes don't say anything about the volume
+        let bytes = props_pod(None, Some(false));
+        assert!(SinkProps::from_pod(Pod::from_bytes(&bytes).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_output_route() {
+        let route = |direction| {
+            let object = Value::Object(Object {
+                type_: SpaTypes::ObjectParamRoute.as_raw(),
+                id: ParamType::Route.as_raw(),
+                properties: vec![
+                    Property::new(
+                        pw::spa::sys::SPA_PARAM_ROUTE_direction,
+                        Value::Id(pw::spa::utils::Id(direction)),
+                    ),
+                    Property::new(pw::spa::sys::SPA_PARAM_ROUTE_device, Value::Int(1)),
+                    Property::new(
+                        pw::spa::sys::SPA_PARAM_ROUTE_name,
+                        Value::String("analog-output-headphones".into()),
+                    ),
+                ],
+            });
+            PodSerializer::serialize(std::io::Cursor::new(Vec::new()), &object)
+                .unwrap()
+                .0
+                .into_inner()
+        };
+        let output = route(pw::spa::sys::SPA_DIRECTION_OUTPUT);
+        assert_eq!(
+            output_route(Pod::from_bytes(&output).unwrap()),
+            Some((1, "analog-output-headphones".into()))
+        );
+        let input = route(pw::spa::sys::SPA_DIRECTION_INPUT);
+        assert_eq!(output_route(Pod::from_bytes(&input).unwrap()), None);
+    }
+
+    #[test]
+    fn test_default_sink_name() {
+        assert_eq!(
+            default_sink_name(r#"{ "name": "alsa_output.pci-0000_00_1f.3.analog-stereo" }"#).as_deref(),
+            Some("alsa_output.pci-0000_00_1f.3.analog-stereo")
+        );
+        assert_eq!(default_sink_name("garbage"), None);
+    }
+}
@@ -0,0 +1,44 @@
+//! Power management via systemd-logind
+//!
+//! Calls the login1 manager over D-Bus with `busctl`. logind decides whether
+//! the calling user may power off or suspend (normally allowed for the active
+//! local session by polkit), so shepherdd needs no extra privileges.
+
+use shepherd_api::PowerAction;
+use shepherd_host_api::{HostError, HostResult};
+use std::process::Command;
+use tracing::info;
+
+/// Ask logind to power off or suspend the machine
+pub fn logind_power_action(action: PowerAction) -> HostResult<()> {
+    let method = match action {
+        PowerAction::Poweroff => "PowerOff",
+        PowerAction::Suspend => "Suspend",
+    };
+
+    info!(method = method, "Requesting power action from logind");
+
+    // Trailing "b false": non-interactive, never prompt for authentication
+    let output = Command::new("busctl")
+        .args([
+            "call",
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1",
+            "org.freedesktop.login1.Manager",
+            method,
+            "b",
+            "false",
+        ])
+        .output()
+        .map_err(|e| HostError::exec("busctl", &e))?;
+
+    if !output.status.success() {
+        return Err(HostError::PermissionDenied(format!(
+            "logind {} failed: {}",
+            method,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
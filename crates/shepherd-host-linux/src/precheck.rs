@@ -0,0 +1,193 @@
+//! Launch pre-checks
+//!
+//! Catches the common reasons a launch would fail (missing program, unset
+//! environment, full disk, no display) before the session starts, so the
+//! launcher can show a specific reason instead of a spawn error.
+
+use nix::sys::statvfs::statvfs;
+use shepherd_api::{EntryKind, ReasonCode};
+use shepherd_host_api::LaunchPrechecks;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::adapter::expand_tilde;
+
+/// Run the configured checks for an entry, returning why it can't launch
+pub fn run_prechecks(entry_kind: &EntryKind, checks: &LaunchPrechecks) -> Vec<ReasonCode> {
+    let mut reasons = Vec::new();
+
+    if checks.check_binary
+        && let Some(program) = program_for(entry_kind)
+        && find_executable(&program, std::env::var_os("PATH").as_deref()).is_none()
+    {
+        reasons.push(ReasonCode::MissingBinary { command: program });
+    }
+
+    let entry_env = entry_env(entry_kind);
+    for name in &checks.require_env {
+        let in_entry = entry_env.is_some_and(|env| env.contains_key(name));
+        let in_service = std::env::var_os(name).is_some_and(|v| !v.is_empty());
+        if !in_entry && !in_service {
+            reasons.push(ReasonCode::MissingEnv { name: name.clone() });
+        }
+    }
+
+    if let Some((path, required_mb)) = &checks.min_free_disk {
+        let path = PathBuf::from(expand_tilde(&path.to_string_lossy()));
+        match free_disk_mb(&path) {
+            Ok(available_mb) if available_mb < *required_mb => {
+                reasons.push(ReasonCode::LowDiskSpace {
+                    path,
+                    available_mb,
+                    required_mb: *required_mb,
+                });
+            }
+            Ok(_) => {}
+            Err(e) => {
+                // Don't block launches on a misconfigured path
+                warn!(path = %path.display(), error = %e, "Failed to check free disk space");
+            }
+        }
+    }
+
+    if checks.require_display && !display_reachable() {
+        reasons.push(ReasonCode::DisplayUnavailable);
+    }
+
+    if !reasons.is_empty() {
+        debug!(reasons = ?reasons, "Launch pre-checks failed");
+    }
+    reasons
+}
+
+/// The program the adapter will execute for an entry
+pub(crate) fn program_for(entry_kind: &EntryKind) -> Option<String> {
+    match entry_kind {
+        EntryKind::Process { command, .. } => Some(expand_tilde(command)),
+        EntryKind::Snap { .. } => Some("snap".into()),
+        EntryKind::Flatpak { .. } => Some("flatpak".into()),
+        EntryKind::Vm { driver, .. } => Some(driver.clone()),
+        EntryKind::Media { .. } => Some("xdg-open".into()),
+        EntryKind::Retro { .. } => Some("retroarch".into()),
+        EntryKind::Custom { .. } => None,
+    }
+}
+
+fn entry_env(entry_kind: &EntryKind) -> Option<&HashMap<String, String>> {
+    match entry_kind {
+        EntryKind::Process { env, .. }
+        | EntryKind::Snap { env, .. }
+        | EntryKind::Flatpak { env, .. } => Some(env),
+        _ => None,
+    }
+}
+
+/// Resolve a program the way the shell would
+pub(crate) fn find_executable(program: &str, path_var: Option<&OsStr>) -> Option<PathBuf> {
+    if program.contains('/') {
+        let path = PathBuf::from(program);
+        return is_executable(&path).then_some(path);
+    }
+    std::env::split_paths(path_var?)
+        .map(|dir| dir.join(program))
+        .find(|path| is_executable(path))
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[allow(clippy::unnecessary_cast)] // statvfs field widths vary by platform
+fn free_disk_mb(path: &Path) -> nix::Result<u64> {
+    let stat = statvfs(path)?;
+    Ok(stat.blocks_available() as u64 * stat.fragment_size() as u64 / (1024 * 1024))
+}
+
+/// Check for a Wayland compositor socket or a local X11 display socket
+pub(crate) fn display_reachable() -> bool {
+    if let Some(socket) = std::env::var_os("WAYLAND_DISPLAY") {
+        let socket = PathBuf::from(socket);
+        let socket = if socket.is_absolute() {
+            socket
+        } else {
+            match std::env::var_os("XDG_RUNTIME_DIR") {
+                Some(dir) => PathBuf::from(dir).join(socket),
+                None => socket,
+            }
+        };
+        if socket.exists() {
+            return true;
+        }
+    }
+
+    match std::env::var("DISPLAY") {
+        Ok(display) => x11_socket_path(&display).is_none_or(|socket| socket.exists()),
+        Err(_) => false,
+    }
+}
+
+/// Socket path for a local X11 display like ":0" or ":1.0".
+/// Returns None for remote displays, which can't be checked.
+fn x11_socket_path(display: &str) -> Option<PathBuf> {
+    let number = display.strip_prefix(':')?;
+    let number = number.split('.').next()?;
+    number.parse::<u32>().ok()?;
+    Some(PathBuf::from(format!("/tmp/.X11-unix/X{}", number)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_find_executable() {
+        let dir = tempfile::tempdir().unwrap();
+        let program = dir.path().join("game");
+        fs::write(&program, "#!/bin/sh\n").unwrap();
+        fs::write(dir.path().join("notes"), "").unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let path_var = dir.path().as_os_str();
+        assert_eq!(find_executable("game", Some(path_var)), Some(program.clone()));
+        assert_eq!(find_executable("notes", Some(path_var)), None);
+        assert_eq!(find_executable("missing", Some(path_var)), None);
+        assert_eq!(
+            find_executable(&program.to_string_lossy(), None),
+            Some(program)
+        );
+    }
+
+    #[test]
+    fn test_x11_socket_path() {
+        assert_eq!(x11_socket_path(":0"), Some(PathBuf::from("/tmp/.X11-unix/X0")));
+        assert_eq!(x11_socket_path(":1.0"), Some(PathBuf::from("/tmp/.X11-unix/X1")));
+        assert_eq!(x11_socket_path("remote:0"), None);
+    }
+
+    #[test]
+    fn test_missing_env() {
+        let kind = EntryKind::Process {
+            command: "true".into(),
+            args: vec![],
+            env: HashMap::from([("GAME_DATA".to_string(), "/data".to_string())]),
+            cwd: None,
+        };
+        let checks = LaunchPrechecks {
+            require_env: vec!["GAME_DATA".into(), "SHEPHERD_TEST_UNSET_VAR".into()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            run_prechecks(&kind, &checks),
+            vec![ReasonCode::MissingEnv {
+                name: "SHEPHERD_TEST_UNSET_VAR".into()
+            }]
+        );
+    }
+}
@@ -5,12 +5,15 @@ use nix::unistd::Pid;
 use std::collections::HashMap;
 use std::fs::File;
 use std::os::unix::process::CommandExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
+use std::time::Instant;
 use tracing::{debug, info, warn};
 
 use shepherd_host_api::{ExitStatus, HostError, HostResult};
 
+use crate::diagnostics::{exec_failure, StderrTail};
+
 /// Managed child process with process group tracking
 pub struct ManagedProcess {
     pub child: Child,
@@ -20,6 +23,12 @@ pub struct ManagedProcess {
     pub command_name: String,
     /// The snap name if this is a snap app (for cgroup-based killing)
     pub snap_name: Option<String>,
+    /// When the process was spawned
+    pub started: Instant,
+    /// Recent stderr, when it isn't going to a log file
+    pub stderr_tail: Option<StderrTail>,
+    /// Where stdout and stderr are logged, if anywhere
+    pub log_path: Option<PathBuf>,
 }
 
 /// Initialize process management (called once at startup)
@@ -155,7 +164,109 @@ pub fn kill_flatpak_cgroup(app_id: &str, _signal: Signal) -> bool {
     stopped_any
 }
 
-/// Kill processes by command name using pkill
+/// The cgroup v2 path a process belongs to, e.g. "/user.slice/..."
+fn cgroup_of(pid: &str) -> Option<String> {
+    std::fs::read_to_string(format!("/proc/{}/cgroup", pid))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(str::to_string)
+}
+
+/// Scope cgroups under the user's app.slice whose names start with `prefix`
+fn app_scopes(prefix: &str) -> Vec<PathBuf> {
+    let uid = nix::unistd::getuid().as_raw();
+    let base = PathBuf::from(format!(
+        "/sys/fs/cgroup/user.slice/user-{}.slice/user@{}.service/app.slice",
+        uid, uid
+    ));
+    let Ok(entries) = std::fs::read_dir(&base) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with(prefix) && name.ends_with(".scope")
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+/// The cgroup v2 directories holding a session's processes, if it has its
+/// own: the snap or flatpak scopes, or `pid`'s cgroup when it isn't shared
+/// with this service (killing that would take the service down too)
+pub fn session_cgroups(pid: u32, snap_name: Option<&str>, flatpak_app_id: Option<&str>) -> Vec<PathBuf> {
+    if let Some(snap) = snap_name {
+        return app_scopes(&format!("snap.{}.{}-", snap, snap));
+    }
+    if let Some(app_id) = flatpak_app_id {
+        return app_scopes(&format!("app-flatpak-{}-", app_id));
+    }
+    match cgroup_of(&pid.to_string()) {
+        Some(cgroup) if cgroup_of("self").as_ref() != Some(&cgroup) => {
+            vec![PathBuf::from("/sys/fs/cgroup").join(cgroup.trim_start_matches('/'))]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// SIGKILL everything in a cgroup at once through `cgroup.kill`, which
+/// can't race with forking children. Fails on kernels before 5.14, which
+/// lack the file.
+pub fn cgroup_kill(dir: &Path) -> std::io::Result<()> {
+    std::fs::write(dir.join("cgroup.kill"), "1")?;
+    info!(cgroup = %dir.display(), "Killed cgroup");
+    Ok(())
+}
+
+/// Freeze or thaw everything in a cgroup through `cgroup.freeze`
+pub fn cgroup_freeze(dir: &Path, frozen: bool) -> std::io::Result<()> {
+    std::fs::write(dir.join("cgroup.freeze"), if frozen { "1" } else { "0" })?;
+    info!(cgroup = %dir.display(), frozen, "Set cgroup freeze");
+    Ok(())
+}
+
+/// Kill every cgroup in `cgroups`, returning false if there were none or
+/// any couldn't be killed
+pub fn kill_cgroups(cgroups: &[PathBuf]) -> bool {
+    !cgroups.is_empty()
+        && cgroups.iter().all(|dir| {
+            cgroup_kill(dir)
+                .inspect_err(|e| debug!(cgroup = %dir.display(), error = %e, "cgroup.kill unavailable"))
+                .is_ok()
+        })
+}
+
+/// Whether a process is running; zombies waiting to be reaped don't count
+pub fn process_alive(pid: u32) -> bool {
+    std::fs::read_to_string(format!("/proc/{}/stat", pid))
+        .ok()
+        .and_then(|stat| {
+            // The state follows the parenthesised command name
+            let (_, rest) = stat.rsplit_once(')')?;
+            rest.trim_start().chars().next()
+        })
+        .is_some_and(|state| !matches!(state, 'Z' | 'X'))
+}
+
+/// Signal every process in a cgroup, e.g. SIGTERM before `cgroup.kill`
+pub fn signal_cgroup(dir: &Path, signal: Signal) -> bool {
+    let Ok(procs) = std::fs::read_to_string(dir.join("cgroup.procs")) else {
+        return false;
+    };
+    let pids: Vec<i32> = procs.lines().filter_map(|line| line.parse().ok()).collect();
+    for pid in &pids {
+        let _ = signal::kill(Pid::from_raw(*pid), signal);
+    }
+    debug!(cgroup = %dir.display(), count = pids.len(), signal = %signal, "Signalled cgroup");
+    !pids.is_empty()
+}
+
+/// Kill this user's processes by command name using pkill. This can hit
+/// unrelated processes with the same name (e.g. "java"), so it's only used
+/// for entries that opt in with `kill_by_name`.
 pub fn kill_by_command(command_name: &str, signal: Signal) -> bool {
     let signal_name = match signal {
         Signal::SIGTERM => "TERM",
@@ -165,7 +276,13 @@ pub fn kill_by_command(command_name: &str, signal: Signal) -> bool {
     
     // Use pkill to find and kill processes by command name
     let result = Command::new("pkill")
-        .args([&format!("-{}", signal_name), "-f", command_name])
+        .args([
+            &format!("-{}", signal_name),
+            "-U",
+            &nix::unistd::getuid().to_string(),
+            "-f",
+            command_name,
+        ])
         .output();
     
     match result {
@@ -187,6 +304,44 @@ pub fn kill_by_command(command_name: &str, signal: Signal) -> bool {
     }
 }
 
+/// All descendants of a process, found by walking /proc
+pub fn descendant_pids(pid: i32) -> Vec<i32> {
+    let mut descendants = Vec::new();
+    let mut to_check = vec![pid];
+    
+    while let Some(parent_pid) = to_check.pop() {
+        // Read /proc to find children of this PID
+        if let Ok(entries) = std::fs::read_dir("/proc") {
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+                let name_str = name.to_string_lossy();
+                
+                // Skip non-numeric entries (not PIDs)
+                if let Ok(pid) = name_str.parse::<i32>() {
+                    // Read the stat file to get parent PID
+                    let stat_path = format!("/proc/{}/stat", pid);
+                    if let Ok(stat) = std::fs::read_to_string(&stat_path) {
+                        // Format: pid (comm) state ppid ...
+                        // Find the closing paren to handle comm with spaces/parens
+                        if let Some(paren_end) = stat.rfind(')') {
+                            let after_comm = &stat[paren_end + 2..];
+                            let fields: Vec<&str> = after_comm.split_whitespace().collect();
+                            if fields.len() >= 2
+                                && let Ok(ppid) = fields[1].parse::<i32>()
+                                && ppid == parent_pid {
+                                    descendants.push(pid);
+                                    to_check.push(pid);
+                                }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    descendants
+}
+
 impl ManagedProcess {
     /// Spawn a new process in its own process group
     /// 
@@ -206,6 +361,7 @@ impl ManagedProcess {
         if argv.is_empty() {
             return Err(HostError::SpawnFailed("Empty argv".into()));
         }
+        let session_log = log_path.clone();
 
         // For snap apps with log capture, wrap with `script` to capture all child output
         // via a pseudo-terminal. Snap child processes don't inherit file descriptors,
@@ -374,7 +530,7 @@ impl ManagedProcess {
                             cmd.stderr(Stdio::inherit());
                             cmd.stdin(Stdio::null());
                             // Skip to spawn
-                            return Self::spawn_with_cmd(cmd, program, snap_name);
+                            return Self::spawn_with_cmd(cmd, program, snap_name, None);
                         }
                     };
                     cmd.stdout(Stdio::from(file));
@@ -388,14 +544,20 @@ impl ManagedProcess {
                 }
             }
         } else {
-            // Inherit from parent so we can see child output for debugging
+            // Inherit stdout so we can see child output for debugging. stderr
+            // is copied through, keeping the tail to explain failed launches
+            // (snaps log through `script` instead).
             cmd.stdout(Stdio::inherit());
-            cmd.stderr(Stdio::inherit());
+            if session_log.is_none() {
+                cmd.stderr(Stdio::piped());
+            } else {
+                cmd.stderr(Stdio::inherit());
+            }
         }
 
         cmd.stdin(Stdio::null());
 
-        Self::spawn_with_cmd(cmd, program, snap_name)
+        Self::spawn_with_cmd(cmd, program, snap_name, session_log)
     }
 
     /// Complete the spawn process with the configured command
@@ -403,6 +565,7 @@ impl ManagedProcess {
         mut cmd: Command,
         program: &str,
         snap_name: Option<String>,
+        log_path: Option<PathBuf>,
     ) -> HostResult<Self> {
         // Store the command name for later use in killing
         let command_name = program.to_string();
@@ -418,65 +581,36 @@ impl ManagedProcess {
             });
         }
 
-        let child = cmd.spawn().map_err(|e| {
-            HostError::SpawnFailed(format!("Failed to spawn {}: {}", program, e))
-        })?;
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| HostError::SpawnDiagnosed(exec_failure(program, &e)))?;
+        let stderr_tail = child.stderr.take().map(StderrTail::capture);
 
         let pid = child.id();
         let pgid = pid; // After setsid, pid == pgid
         
         info!(pid = pid, pgid = pgid, program = %program, snap = ?snap_name, "Process spawned");
 
-        Ok(Self { child, pid, pgid, command_name, snap_name })
+        Ok(Self {
+            child,
+            pid,
+            pgid,
+            command_name,
+            snap_name,
+            started: Instant::now(),
+            stderr_tail,
+            log_path,
+        })
     }
 
     /// Get all descendant PIDs of this process using /proc
     fn get_descendant_pids(&self) -> Vec<i32> {
-        let mut descendants = Vec::new();
-        let mut to_check = vec![self.pid as i32];
-        
-        while let Some(parent_pid) = to_check.pop() {
-            // Read /proc to find children of this PID
-            if let Ok(entries) = std::fs::read_dir("/proc") {
-                for entry in entries.flatten() {
-                    let name = entry.file_name();
-                    let name_str = name.to_string_lossy();
-                    
-                    // Skip non-numeric entries (not PIDs)
-                    if let Ok(pid) = name_str.parse::<i32>() {
-                        // Read the stat file to get parent PID
-                        let stat_path = format!("/proc/{}/stat", pid);
-                        if let Ok(stat) = std::fs::read_to_string(&stat_path) {
-                            // Format: pid (comm) state ppid ...
-                            // Find the closing paren to handle comm with spaces/parens
-                            if let Some(paren_end) = stat.rfind(')') {
-                                let after_comm = &stat[paren_end + 2..];
-                                let fields: Vec<&str> = after_comm.split_whitespace().collect();
-                                if fields.len() >= 2
-                                    && let Ok(ppid) = fields[1].parse::<i32>()
-                                    && ppid == parent_pid {
-                                        descendants.push(pid);
-                                        to_check.push(pid);
-                                    }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        
-        descendants
+        descendant_pids(self.pid as i32)
     }
 
     /// Send SIGTERM to all processes in this session
     pub fn terminate(&self) -> HostResult<()> {
-        // For snap apps, we rely on cgroup-based killing in the adapter, not pkill
-        // Using pkill with broad patterns like "snap" would kill unrelated processes
-        if self.snap_name.is_none() {
-            kill_by_command(&self.command_name, Signal::SIGTERM);
-        }
-        
-        // Also try to kill the process group
+        // Kill the process group
         let pgid = Pid::from_raw(-(self.pgid as i32)); // Negative for process group
 
         match signal::kill(pgid, Signal::SIGTERM) {
@@ -505,13 +639,7 @@ impl ManagedProcess {
 
     /// Send SIGKILL to all processes in this session
     pub fn kill(&self) -> HostResult<()> {
-        // For snap apps, we rely on cgroup-based killing in the adapter, not pkill
-        // Using pkill with broad patterns like "snap" would kill unrelated processes
-        if self.snap_name.is_none() {
-            kill_by_command(&self.command_name, Signal::SIGKILL);
-        }
-        
-        // Also try to kill the process group
+        // Kill the process group
         let pgid = Pid::from_raw(-(self.pgid as i32));
 
         match signal::kill(pgid, Signal::SIGKILL) {
@@ -563,7 +691,7 @@ impl ManagedProcess {
                 Ok(Some(exit_status))
             }
             Ok(None) => Ok(None), // Still running
-            Err(e) => Err(HostError::Internal(format!("Wait failed: {}", e))),
+            Err(e) => Err(HostError::internal(format!("Wait failed: {}", e))),
         }
     }
 
@@ -590,7 +718,7 @@ impl ManagedProcess {
                 };
                 Ok(exit_status)
             }
-            Err(e) => Err(HostError::Internal(format!("Wait failed: {}", e))),
+            Err(e) => Err(HostError::internal(format!("Wait failed: {}", e))),
         }
     }
     
@@ -602,7 +730,9 @@ impl ManagedProcess {
 
 impl Drop for ManagedProcess {
     fn drop(&mut self) {
-        // Nothing special to do for systemd scopes - systemd cleans up automatically
+        // Nothing special to do for systemd scopes - systemd cleans up automatically.
+        // Reap the child if it already exited so it doesn't linger as a zombie.
+        let _ = self.child.try_wait();
     }
 }
 
@@ -622,6 +752,25 @@ mod tests {
         assert!(status.is_success());
     }
 
+    #[test]
+    fn process_alive_ignores_zombies() {
+        let argv = vec!["true".to_string()];
+        let mut proc = ManagedProcess::spawn(&argv, &HashMap::new(), None, None, None).unwrap();
+        assert!(process_alive(std::process::id()));
+
+        // Exited but not yet reaped
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert!(!process_alive(proc.pid));
+        proc.wait().unwrap();
+        assert!(!process_alive(proc.pid));
+    }
+
+    #[test]
+    fn shared_cgroup_is_not_a_session_cgroup() {
+        // Killing our own cgroup would take the test runner down with it
+        assert!(session_cgroups(std::process::id(), None, None).is_empty());
+    }
+
     #[test]
     fn spawn_with_args() {
         let argv = vec!["echo".to_string(), "hello".to_string()];
@@ -0,0 +1,212 @@
+//! RetroArch entries
+//!
+//! A retro entry runs `retroarch --fullscreen -L <core> <rom>`. The core is
+//! a library path, or a name like `snes9x` looked up as `snes9x_libretro.so`
+//! in RetroArch's core directories and the distribution's libretro
+//! directories.
+//!
+//! Before each launch the ROM's save files (`.srm` battery saves and
+//! `.state*` save states) are copied to `retro-saves/<rom>/` in the data
+//! directory, keeping the newest few versions of each, so a bad save state
+//! written over a good one can be undone. Saves are looked for next to the
+//! ROM and in RetroArch's `saves/` and `states/` directories, including the
+//! per-core subdirectories RetroArch sorts them into.
+
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+use crate::adapter::expand_tilde;
+use crate::minecraft::prune_backups;
+
+/// Backups kept of each save file
+const SAVE_BACKUPS: usize = 5;
+
+/// RetroArch's config directories, native and Flatpak
+const RETROARCH_DIRS: &[&str] = &[
+    "~/.config/retroarch",
+    "~/.var/app/org.libretro.RetroArch/config/retroarch",
+];
+
+/// Where distributions install libretro cores
+const SYSTEM_CORE_DIRS: &[&str] = &[
+    "/usr/lib/libretro",
+    "/usr/lib/x86_64-linux-gnu/libretro",
+    "/usr/lib/aarch64-linux-gnu/libretro",
+    "/usr/lib64/libretro",
+    "/usr/local/lib/libretro",
+];
+
+fn retroarch_dirs(sub: &str) -> impl Iterator<Item = PathBuf> + '_ {
+    RETROARCH_DIRS
+        .iter()
+        .map(move |dir| PathBuf::from(expand_tilde(dir)).join(sub))
+}
+
+/// The core library for `core` among `dirs`. Paths are used as given; names
+/// that aren't found are passed through for RetroArch to resolve.
+fn find_core(core: &str, dirs: &[PathBuf]) -> String {
+    if core.contains('/') || core.ends_with(".so") {
+        return expand_tilde(core);
+    }
+    let name = core.strip_suffix("_libretro").unwrap_or(core);
+    let file = format!("{}_libretro.so", name);
+    dirs.iter()
+        .map(|dir| dir.join(&file))
+        .find(|path| path.is_file())
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|| core.to_string())
+}
+
+/// Command line running `rom` fullscreen in RetroArch with `core`
+pub fn retroarch_argv(core: &str, rom: &Path) -> Vec<String> {
+    let dirs: Vec<PathBuf> = retroarch_dirs("cores")
+        .chain(SYSTEM_CORE_DIRS.iter().map(PathBuf::from))
+        .collect();
+    vec![
+        "retroarch".into(),
+        "--fullscreen".into(),
+        "-L".into(),
+        find_core(core, &dirs),
+        expand_tilde(&rom.to_string_lossy()),
+    ]
+}
+
+/// Whether `name` is a save of the ROM named `stem`: `<stem>.srm`,
+/// `<stem>.state`, `<stem>.state3`, `<stem>.state.auto`, ...
+fn is_save_of(name: &str, stem: &str) -> bool {
+    name.strip_prefix(stem)
+        .and_then(|rest| rest.strip_prefix('.'))
+        .is_some_and(|ext| ext == "srm" || ext.starts_with("state"))
+}
+
+/// Save files of `rom` in `dirs` and their immediate subdirectories
+fn save_files(rom: &Path, dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let Some(stem) = rom.file_stem().and_then(|s| s.to_str()) else {
+        return Vec::new();
+    };
+    let mut searched: Vec<PathBuf> = Vec::new();
+    for dir in dirs {
+        searched.push(dir.clone());
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            searched.extend(entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()));
+        }
+    }
+    let mut saves: Vec<PathBuf> = searched
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| is_save_of(n, stem))
+        })
+        .collect();
+    saves.sort();
+    saves.dedup();
+    saves
+}
+
+/// Copy each save of `rom` found in `dirs` into `backup_dir` unless its
+/// newest backup already matches, returning how many were copied
+fn backup_saves_in(rom: &Path, dirs: &[PathBuf], backup_dir: &Path, keep: usize) -> std::io::Result<usize> {
+    let mut copied = 0;
+    for save in save_files(rom, dirs) {
+        let Some(name) = save.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let contents = std::fs::read(&save)?;
+        std::fs::create_dir_all(backup_dir)?;
+
+        let prefix = format!("{}.", name);
+        let newest = std::fs::read_dir(backup_dir)?
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with(&prefix)))
+            .max();
+        if newest.is_some_and(|newest| std::fs::read(newest).is_ok_and(|old| old == contents)) {
+            continue;
+        }
+
+        let stamp = shepherd_util::now().format("%Y%m%d-%H%M%S");
+        std::fs::write(backup_dir.join(format!("{}{}", prefix, stamp)), &contents)?;
+        prune_backups(backup_dir, name, keep)?;
+        debug!(save = %save.display(), "Backed up RetroArch save");
+        copied += 1;
+    }
+    Ok(copied)
+}
+
+/// Back up the save files of `rom` before a session can overwrite them,
+/// returning how many changed since the last backup
+pub fn backup_retro_saves(rom: &Path) -> std::io::Result<usize> {
+    let rom = PathBuf::from(expand_tilde(&rom.to_string_lossy()));
+    let mut dirs: Vec<PathBuf> = rom.parent().map(Path::to_path_buf).into_iter().collect();
+    dirs.extend(retroarch_dirs("saves"));
+    dirs.extend(retroarch_dirs("states"));
+
+    let stem = rom.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let backup_dir = shepherd_util::default_data_dir().join("retro-saves").join(&stem);
+    let copied = backup_saves_in(&rom, &dirs, &backup_dir, SAVE_BACKUPS)?;
+    if copied > 0 {
+        info!(rom = %rom.display(), copied, backup_dir = %backup_dir.display(), "Backed up RetroArch saves");
+    }
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_core() {
+        let dir = tempfile::tempdir().unwrap();
+        let cores = vec![PathBuf::from("/nonexistent"), dir.path().to_path_buf()];
+        let snes9x = dir.path().join("snes9x_libretro.so");
+        std::fs::write(&snes9x, b"").unwrap();
+
+        let found = snes9x.to_string_lossy().into_owned();
+        assert_eq!(find_core("snes9x", &cores), found);
+        assert_eq!(find_core("snes9x_libretro", &cores), found);
+        assert_eq!(find_core("/opt/cores/mgba_libretro.so", &cores), "/opt/cores/mgba_libretro.so");
+        assert_eq!(find_core("mgba", &cores), "mgba");
+    }
+
+    #[test]
+    fn test_is_save_of() {
+        assert!(is_save_of("Super Mario World.srm", "Super Mario World"));
+        assert!(is_save_of("Super Mario World.state", "Super Mario World"));
+        assert!(is_save_of("Super Mario World.state3", "Super Mario World"));
+        assert!(is_save_of("Super Mario World.state.auto", "Super Mario World"));
+        assert!(!is_save_of("Super Mario World.sfc", "Super Mario World"));
+        assert!(!is_save_of("Super Mario World 2.srm", "Super Mario World"));
+    }
+
+    #[test]
+    fn test_backup_saves() {
+        let roms = tempfile::tempdir().unwrap();
+        let states = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let rom = roms.path().join("zelda.sfc");
+        std::fs::write(&rom, b"rom").unwrap();
+        std::fs::write(roms.path().join("zelda.srm"), b"battery").unwrap();
+        std::fs::create_dir(states.path().join("Snes9x")).unwrap();
+        std::fs::write(states.path().join("Snes9x/zelda.state1"), b"dungeon").unwrap();
+        std::fs::write(states.path().join("Snes9x/mario.state1"), b"other").unwrap();
+        let dirs = vec![roms.path().to_path_buf(), states.path().to_path_buf()];
+
+        assert_eq!(backup_saves_in(&rom, &dirs, backups.path(), 5).unwrap(), 2);
+        // Nothing changed, so nothing new to keep
+        assert_eq!(backup_saves_in(&rom, &dirs, backups.path(), 5).unwrap(), 0);
+
+        let mut names: Vec<String> = std::fs::read_dir(backups.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 2);
+        assert!(names[0].starts_with("zelda.srm."));
+        assert!(names[1].starts_with("zelda.state1."));
+    }
+}
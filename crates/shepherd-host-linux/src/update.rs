@@ -0,0 +1,40 @@
+//! Snap and Flatpak app updates
+//!
+//! Updates run between sessions during maintenance. They can take minutes
+//! to download, so the package tools are run as async processes instead of
+//! blocking the service.
+
+use shepherd_api::EntryKind;
+use shepherd_host_api::{HostError, HostResult};
+use std::process::Stdio;
+use tokio::process::Command;
+use tracing::info;
+
+/// Update the app behind a snap or flatpak entry
+pub async fn update_app(entry_kind: &EntryKind) -> HostResult<()> {
+    let (program, args): (&str, Vec<&str>) = match entry_kind {
+        EntryKind::Snap { snap_name, .. } => ("snap", vec!["refresh", snap_name]),
+        EntryKind::Flatpak { app_id, .. } => {
+            ("flatpak", vec!["update", "--noninteractive", "-y", app_id])
+        }
+        _ => return Err(HostError::UnsupportedKind),
+    };
+
+    info!(program = program, args = ?args, "Updating app");
+    let output = Command::new(program)
+        .args(&args)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| HostError::exec(program, &e))?;
+
+    if !output.status.success() {
+        return Err(HostError::command_failed(format!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
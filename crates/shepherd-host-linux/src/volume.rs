@@ -4,14 +4,40 @@
 //! - PipeWire (via `wpctl`)
 //! - PulseAudio (via `pactl`)
 //! - ALSA (via `amixer`)
+//!
+//! With the `pipewire` feature, PipeWire is driven natively (see
+//! `pipewire_volume`) and the tools above are only a fallback.
+//!
+//! Otherwise changes are watched through `pactl subscribe`, which both PulseAudio and
+//! PipeWire (through pipewire-pulse) serve: one long-lived process instead
+//! of polling. ALSA has no such stream, so its volume is only read on
+//! request.
+//!
+//! Whether sound goes to headphones or speakers is read from the default
+//! sink's form factor and active port, through `pactl` or natively. ALSA
+//! can't tell, so its output is always [`AudioOutput::Unknown`].
+//!
+//! Sessions are ducked per stream through `pactl`: the sink inputs whose
+//! `application.process.id` belongs to the session are turned down, and
+//! their original levels kept until they are put back.
 
 use async_trait::async_trait;
+use shepherd_api::AudioOutput;
 use shepherd_host_api::{
-    VolumeCapabilities, VolumeController, VolumeError, VolumeResult, VolumeStatus,
+    HostSessionHandle, VolumeCapabilities, VolumeController, VolumeError, VolumeResult,
+    VolumeStatus,
 };
-use std::process::Command;
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc;
 use tracing::{debug, info, warn};
 
+/// Wait before restarting `pactl subscribe`, e.g. while the sound server restarts
+const WATCH_RETRY: Duration = Duration::from_secs(5);
+
 /// Detected sound backend
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SoundBackend {
@@ -88,24 +114,106 @@ impl SoundBackend {
 pub struct LinuxVolumeController {
     capabilities: VolumeCapabilities,
     backend: Option<SoundBackend>,
+    /// `pactl` answers, so the output kind can be read and streams ducked
+    /// through it
+    pactl: bool,
+    /// Levels of the ducked sink inputs before ducking, by index
+    ducked: Mutex<HashMap<u32, u32>>,
+    #[cfg(feature = "pipewire")]
+    native: Option<crate::pipewire_volume::PipeWireVolume>,
 }
 
 impl LinuxVolumeController {
     /// Create a new volume controller with auto-detection
     pub fn new() -> Self {
         let backend = SoundBackend::detect();
+        let pactl = match backend {
+            Some(SoundBackend::PulseAudio) => true,
+            Some(SoundBackend::PipeWire) => SoundBackend::is_pulseaudio_available(),
+            Some(SoundBackend::Alsa) | None => false,
+        };
+        #[cfg(feature = "pipewire")]
+        let native = (backend == Some(SoundBackend::PipeWire))
+            .then(crate::pipewire_volume::PipeWireVolume::connect)
+            .flatten();
+        #[cfg(feature = "pipewire")]
+        let native_output = native.is_some();
+        #[cfg(not(feature = "pipewire"))]
+        let native_output = false;
 
         let capabilities = VolumeCapabilities {
             available: backend.is_some(),
             backend: backend.map(|b| b.name().to_string()),
             can_mute: backend.is_some(),
             max_volume: 100,
+            can_detect_output: pactl || native_output,
+            can_duck: pactl,
         };
 
         Self {
+            #[cfg(feature = "pipewire")]
+            native,
             capabilities,
             backend,
+            pactl,
+            ducked: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The native PipeWire connection, while it's up
+    #[cfg(feature = "pipewire")]
+    fn native(&self) -> Option<&crate::pipewire_volume::PipeWireVolume> {
+        self.native.as_ref().filter(|native| native.is_connected())
+    }
+
+    fn read_status(backend: Option<SoundBackend>, pactl: bool) -> VolumeResult<VolumeStatus> {
+        let mut status = match backend {
+            Some(SoundBackend::PipeWire) => Self::get_status_pipewire(),
+            Some(SoundBackend::PulseAudio) => Self::get_status_pulseaudio(),
+            Some(SoundBackend::Alsa) => Self::get_status_alsa(),
+            None => Err(VolumeError::NotAvailable(
+                "No sound backend available".into(),
+            )),
+        }?;
+        if pactl {
+            status.output = Self::get_output_pactl();
         }
+        Ok(status)
+    }
+
+    /// What the default sink plays through, via `pactl` (PulseAudio or
+    /// pipewire-pulse)
+    fn get_output_pactl() -> AudioOutput {
+        let default = Command::new("pactl").arg("get-default-sink").output();
+        let sinks = Command::new("pactl").args(["list", "sinks"]).output();
+        match (default, sinks) {
+            (Ok(default), Ok(sinks)) => sink_output(
+                &String::from_utf8_lossy(&sinks.stdout),
+                String::from_utf8_lossy(&default.stdout).trim(),
+            ),
+            (Err(e), _) | (_, Err(e)) => {
+                debug!(error = %e, "Failed to read the default sink");
+                AudioOutput::Unknown
+            }
+        }
+    }
+
+    /// Sink inputs (application streams), via `pactl`
+    fn sink_inputs_pactl() -> VolumeResult<Vec<SinkInput>> {
+        let output = Command::new("pactl")
+            .args(["list", "sink-inputs"])
+            .output()
+            .map_err(|e| VolumeError::Backend(e.to_string()))?;
+        Ok(sink_inputs(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Set a sink input's volume via `pactl`
+    fn set_sink_input_volume(index: u32, percent: u32) -> VolumeResult<()> {
+        Command::new("pactl")
+            .args(["set-sink-input-volume", &index.to_string(), &format!("{}%", percent)])
+            .status()
+            .map_err(|e| VolumeError::Backend(e.to_string()))?;
+        Ok(())
     }
 
     /// Get volume status via PipeWire
@@ -131,7 +239,11 @@ impl LinuxVolumeController {
             .map(|v| (v * 100.0).round() as u8)
             .unwrap_or(0);
 
-        Ok(VolumeStatus { percent, muted })
+        Ok(VolumeStatus {
+            percent,
+            muted,
+            ..Default::default()
+        })
     }
 
     /// Get volume status via PulseAudio
@@ -296,14 +408,11 @@ impl VolumeController for LinuxVolumeController {
     }
 
     async fn get_status(&self) -> VolumeResult<VolumeStatus> {
-        match self.backend {
-            Some(SoundBackend::PipeWire) => Self::get_status_pipewire(),
-            Some(SoundBackend::PulseAudio) => Self::get_status_pulseaudio(),
-            Some(SoundBackend::Alsa) => Self::get_status_alsa(),
-            None => Err(VolumeError::NotAvailable(
-                "No sound backend available".into(),
-            )),
+        #[cfg(feature = "pipewire")]
+        if let Some(status) = self.native().and_then(|native| native.status()) {
+            return Ok(status);
         }
+        Self::read_status(self.backend, self.pactl)
     }
 
     async fn set_volume(&self, percent: u8) -> VolumeResult<()> {
@@ -311,6 +420,11 @@ impl VolumeController for LinuxVolumeController {
             return Err(VolumeError::OutOfRange(percent));
         }
 
+        #[cfg(feature = "pipewire")]
+        if let Some(native) = self.native() {
+            return native.set_volume(percent).await;
+        }
+
         match self.backend {
             Some(SoundBackend::PipeWire) => Self::set_volume_pipewire(percent),
             Some(SoundBackend::PulseAudio) => Self::set_volume_pulseaudio(percent),
@@ -334,6 +448,11 @@ impl VolumeController for LinuxVolumeController {
     }
 
     async fn toggle_mute(&self) -> VolumeResult<()> {
+        #[cfg(feature = "pipewire")]
+        if let Some(native) = self.native() {
+            return native.toggle_mute().await;
+        }
+
         match self.backend {
             Some(SoundBackend::PipeWire) => Self::toggle_mute_pipewire(),
             Some(SoundBackend::PulseAudio) => Self::toggle_mute_pulseaudio(),
@@ -345,6 +464,11 @@ impl VolumeController for LinuxVolumeController {
     }
 
     async fn set_mute(&self, muted: bool) -> VolumeResult<()> {
+        #[cfg(feature = "pipewire")]
+        if let Some(native) = self.native() {
+            return native.set_mute(muted).await;
+        }
+
         match self.backend {
             Some(SoundBackend::PipeWire) => Self::set_mute_pipewire(muted),
             Some(SoundBackend::PulseAudio) => Self::set_mute_pulseaudio(muted),
@@ -354,6 +478,195 @@ impl VolumeController for LinuxVolumeController {
             )),
         }
     }
+
+    fn watch(&self) -> Option<mpsc::UnboundedReceiver<VolumeStatus>> {
+        #[cfg(feature = "pipewire")]
+        if let Some(native) = self.native() {
+            return native.take_changes();
+        }
+
+        let backend = match self.backend {
+            Some(SoundBackend::PipeWire | SoundBackend::PulseAudio) => self.backend,
+            Some(SoundBackend::Alsa) | None => return None,
+        };
+        // PipeWire without pipewire-pulse has no pactl to listen with
+        if !SoundBackend::is_pulseaudio_available() {
+            info!("pactl unavailable, volume changes are read on request");
+            return None;
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(watch_sound_server(backend, tx));
+        Some(rx)
+    }
+
+    async fn duck_session(&self, session: &HostSessionHandle, percent: Option<u8>) -> VolumeResult<()> {
+        if !self.pactl {
+            return Err(VolumeError::NotAvailable("per-stream volume needs pactl".into()));
+        }
+        let streams = Self::sink_inputs_pactl()?;
+
+        let Some(percent) = percent else {
+            // Streams that ended meanwhile have nothing to put back
+            let ducked = std::mem::take(&mut *self.ducked.lock().unwrap());
+            for (index, original) in ducked {
+                if streams.iter().any(|stream| stream.index == index) {
+                    Self::set_sink_input_volume(index, original)?;
+                }
+            }
+            return Ok(());
+        };
+
+        let Some(pid) = session.payload().pid() else {
+            return Err(VolumeError::NotAvailable("session has no process".into()));
+        };
+        let mut pids: Vec<u32> = crate::process::descendant_pids(pid as i32)
+            .into_iter()
+            .map(|pid| pid as u32)
+            .collect();
+        pids.push(pid);
+
+        let mut ducked = self.ducked.lock().unwrap();
+        for stream in streams.iter().filter(|stream| stream.pid.is_some_and(|pid| pids.contains(&pid))) {
+            let original = *ducked.entry(stream.index).or_insert(stream.percent);
+            debug!(index = stream.index, original, percent, "Ducking stream");
+            Self::set_sink_input_volume(stream.index, original * u32::from(percent) / 100)?;
+        }
+        Ok(())
+    }
+}
+
+/// An application's audio stream in `pactl list sink-inputs`
+#[derive(Debug, PartialEq, Eq)]
+struct SinkInput {
+    index: u32,
+    /// Process playing it, if the client said
+    pid: Option<u32>,
+    /// Volume of the first channel
+    percent: u32,
+}
+
+/// The streams in `pactl list sink-inputs` output
+fn sink_inputs(listing: &str) -> Vec<SinkInput> {
+    listing
+        .split("Sink Input #")
+        .skip(1)
+        .filter_map(|block| {
+            let mut lines = block.lines();
+            let index = lines.next()?.trim().parse().ok()?;
+            let mut pid = None;
+            let mut percent = 100;
+            for line in lines.map(str::trim) {
+                if let Some(value) = line.strip_prefix("application.process.id = ") {
+                    pid = value.trim_matches('"').parse().ok();
+                } else if let Some(volume) = line.strip_prefix("Volume: ") {
+                    // "front-left: 65536 / 100% / 0.00 dB,   front-right: ..."
+                    if let Some(value) = volume.split('/').nth(1) {
+                        percent = value.trim().trim_end_matches('%').parse().unwrap_or(100);
+                    }
+                }
+            }
+            Some(SinkInput { index, pid, percent })
+        })
+        .collect()
+}
+
+/// What a sink plays through, from its `device.form_factor` property and
+/// active port name. Anything that says something but not "headphones"
+/// counts as speakers, so an unrecognized device doesn't get a pass.
+pub(crate) fn classify_output(form_factor: Option<&str>, port: Option<&str>) -> AudioOutput {
+    let says_headphones = |value: &str| {
+        let value = value.to_ascii_lowercase();
+        value.contains("headphone") || value.contains("headset")
+    };
+    if form_factor.is_some_and(says_headphones) || port.is_some_and(says_headphones) {
+        AudioOutput::Headphones
+    } else if form_factor.is_some() || port.is_some() {
+        AudioOutput::Speakers
+    } else {
+        AudioOutput::Unknown
+    }
+}
+
+/// The output kind of the sink called `name` in `pactl list sinks` output
+fn sink_output(listing: &str, name: &str) -> AudioOutput {
+    let Some(sink) = listing
+        .split("Sink #")
+        .find(|sink| sink.lines().any(|line| line.trim() == format!("Name: {}", name)))
+    else {
+        return AudioOutput::Unknown;
+    };
+    let mut form_factor = None;
+    let mut port = None;
+    for line in sink.lines().map(str::trim) {
+        if let Some(value) = line.strip_prefix("device.form_factor = ") {
+            form_factor = Some(value.trim_matches('"'));
+        } else if let Some(value) = line.strip_prefix("Active Port: ") {
+            port = Some(value);
+        }
+    }
+    classify_output(form_factor, port)
+}
+
+/// Follow `pactl subscribe` and send the volume whenever it differs from
+/// the last one sent, until the receiver is dropped
+async fn watch_sound_server(backend: Option<SoundBackend>, tx: mpsc::UnboundedSender<VolumeStatus>) {
+    let mut last: Option<VolumeStatus> = None;
+    let mut send_if_changed = |status: VolumeStatus| {
+        if last.as_ref() == Some(&status) {
+            return true;
+        }
+        last = Some(status.clone());
+        tx.send(status).is_ok()
+    };
+
+    while !tx.is_closed() {
+        let mut child = match tokio::process::Command::new("pactl")
+            .arg("subscribe")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                warn!(error = %e, "Failed to watch volume changes, retrying");
+                tokio::time::sleep(WATCH_RETRY).await;
+                continue;
+            }
+        };
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let mut lines = BufReader::new(stdout).lines();
+
+        // It may have changed while nothing was listening
+        if let Ok(status) = LinuxVolumeController::read_status(backend, true)
+            && !send_if_changed(status)
+        {
+            return;
+        }
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            if !is_volume_event(&line) {
+                continue;
+            }
+            match LinuxVolumeController::read_status(backend, true) {
+                Ok(status) => {
+                    if !send_if_changed(status) {
+                        return;
+                    }
+                }
+                Err(e) => debug!(error = %e, "Failed to read volume after change"),
+            }
+        }
+
+        warn!("pactl subscribe exited, restarting");
+        tokio::time::sleep(WATCH_RETRY).await;
+    }
+}
+
+/// Whether a `pactl subscribe` line can mean the default output's volume
+/// changed: a sink changed, or the server's default sink did
+fn is_volume_event(line: &str) -> bool {
+    line.starts_with("Event 'change'") && (line.contains(" on sink #") || line.contains(" on server"))
 }
 
 #[cfg(test)]
@@ -366,4 +679,76 @@ mod tests {
         assert_eq!(SoundBackend::PulseAudio.name(), "pulseaudio");
         assert_eq!(SoundBackend::Alsa.name(), "alsa");
     }
+
+    #[test]
+    fn test_volume_events() {
+        assert!(is_volume_event("Event 'change' on sink #57"));
+        assert!(is_volume_event("Event 'change' on server #-1"));
+        // Per-stream volumes and new sinks don't move the output volume
+        assert!(!is_volume_event("Event 'change' on sink-input #112"));
+        assert!(!is_volume_event("Event 'new' on sink #58"));
+        assert!(!is_volume_event("Event 'change' on source #3"));
+    }
+
+    #[test]
+    fn test_sink_output() {
+        let listing = "\
+Sink #56
+\tState: RUNNING
+\tName: alsa_output.pci-0000_00_1f.3.analog-stereo
+\tProperties:
+\t\tdevice.form_factor = \"internal\"
+\tActive Port: analog-output-headphones
+Sink #71
+\tName: bluez_output.00_11_22_33_44_55.1
+\tProperties:
+\t\tdevice.form_factor = \"headset\"
+Sink #80
+\tName: alsa_output.pci-0000_01_00.1.hdmi-stereo
+\tActive Port: hdmi-output-0
+Sink #81
+\tName: null-sink
+";
+        let output = |name| sink_output(listing, name);
+        // The jack wins over the card's built-in form factor
+        assert_eq!(output("alsa_output.pci-0000_00_1f.3.analog-stereo"), AudioOutput::Headphones);
+        assert_eq!(output("bluez_output.00_11_22_33_44_55.1"), AudioOutput::Headphones);
+        assert_eq!(output("alsa_output.pci-0000_01_00.1.hdmi-stereo"), AudioOutput::Speakers);
+        assert_eq!(output("null-sink"), AudioOutput::Unknown);
+        assert_eq!(output("gone"), AudioOutput::Unknown);
+
+        assert_eq!(classify_output(None, Some("[Out] Speaker")), AudioOutput::Speakers);
+        assert_eq!(classify_output(Some("headphone"), None), AudioOutput::Headphones);
+    }
+
+    #[test]
+    fn test_sink_inputs() {
+        let listing = "\
+Sink Input #112
+\tDriver: protocol-native.c
+\tVolume: front-left: 52429 /  80% / -5.81 dB,   front-right: 52429 /  80% / -5.81 dB
+\t        balance 0.00
+\tProperties:
+\t\tapplication.name = \"Minecraft\"
+\t\tapplication.process.id = \"4242\"
+Sink Input #113
+\tVolume: mono: 65536 / 100% / 0.00 dB
+";
+        assert_eq!(
+            sink_inputs(listing),
+            vec![
+                SinkInput {
+                    index: 112,
+                    pid: Some(4242),
+                    percent: 80
+                },
+                SinkInput {
+                    index: 113,
+                    pid: None,
+                    percent: 100
+                },
+            ]
+        );
+        assert!(sink_inputs("").is_empty());
+    }
 }
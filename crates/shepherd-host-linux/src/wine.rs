@@ -0,0 +1,184 @@
+//! Proton and WINE session supervision
+//!
+//! A game under Proton or WINE doesn't stay a child of what launched it:
+//! `steam steam://rungameid/...` hands the game to the running Steam client
+//! and exits, and WINE starts the game's processes through `wineserver`.
+//! For entries with a prefix configured, the session lasts as long as any
+//! game process of that prefix runs, rather than the launched process.
+//!
+//! Processes belong to the prefix when their environment says so:
+//! `WINEPREFIX` is the prefix, or Steam's `STEAM_COMPAT_DATA_PATH` is the
+//! prefix or holds it as `pfx`. WINE's own services (`wineserver`,
+//! `services.exe`, `winedevice.exe`, ...) don't keep a session alive, since
+//! they linger for a while after the game exits. When the session has a
+//! cgroup of its own, anything still in it counts too.
+//!
+//! Stopping signals every process of the prefix, `wineserver` included, so
+//! nothing of the game is left behind to hold the prefix.
+
+use nix::sys::signal::{self, Signal};
+use nix::unistd::Pid;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+/// WINE's own processes, by executable name
+const WINE_SERVICES: &[&str] = &[
+    "wineserver",
+    "wineserver64",
+    "services.exe",
+    "winedevice.exe",
+    "plugplay.exe",
+    "svchost.exe",
+    "rpcss.exe",
+    "explorer.exe",
+    "conhost.exe",
+    "tabtip.exe",
+];
+
+/// A process running in a WINE prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixProcess {
+    pub pid: i32,
+    /// One of WINE's own services rather than part of the game
+    pub service: bool,
+}
+
+/// Whether a NUL-separated environment puts its process in `prefix`
+fn environ_in_prefix(environ: &[u8], prefix: &Path) -> bool {
+    environ.split(|b| *b == 0).any(|var| {
+        let var = String::from_utf8_lossy(var);
+        let Some((name, value)) = var.split_once('=') else {
+            return false;
+        };
+        let value = Path::new(value.trim_end_matches('/'));
+        match name {
+            "WINEPREFIX" => value == prefix,
+            "STEAM_COMPAT_DATA_PATH" => value == prefix || value.join("pfx") == prefix,
+            _ => false,
+        }
+    })
+}
+
+/// Whether a command line (NUL-separated) is one of WINE's services.
+/// Windows programs show up with paths like `C:\windows\system32\...`.
+fn is_wine_service(cmdline: &[u8]) -> bool {
+    let argv0 = cmdline.split(|b| *b == 0).next().unwrap_or_default();
+    let argv0 = String::from_utf8_lossy(argv0).to_lowercase();
+    let name = argv0.rsplit(['/', '\\']).next().unwrap_or_default();
+    WINE_SERVICES.contains(&name)
+}
+
+/// This user's processes running in `prefix`
+pub fn prefix_processes(prefix: &Path) -> Vec<PrefixProcess> {
+    let prefix = Path::new(prefix.to_str().unwrap_or_default().trim_end_matches('/'));
+    let uid = nix::unistd::getuid().as_raw();
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid: i32 = entry.file_name().to_str()?.parse().ok()?;
+            if entry.metadata().ok()?.uid() != uid || pid == std::process::id() as i32 {
+                return None;
+            }
+            let dir = entry.path();
+            let environ = std::fs::read(dir.join("environ")).ok()?;
+            if !environ_in_prefix(&environ, prefix) || !crate::process_alive(pid as u32) {
+                return None;
+            }
+            let cmdline = std::fs::read(dir.join("cmdline")).unwrap_or_default();
+            Some(PrefixProcess {
+                pid,
+                service: is_wine_service(&cmdline),
+            })
+        })
+        .collect()
+}
+
+/// Whether any of the game is still running: a process of `prefix` other
+/// than WINE's services, or anything left in the session's own `cgroup`
+pub fn game_running(prefix: &Path, cgroup: Option<&Path>) -> bool {
+    let in_cgroup = cgroup
+        .and_then(|dir| std::fs::read_to_string(dir.join("cgroup.procs")).ok())
+        .is_some_and(|procs| procs.lines().any(|line| !line.trim().is_empty()));
+    in_cgroup || prefix_processes(prefix).iter().any(|p| !p.service)
+}
+
+/// Signal every process of `prefix`, WINE's services included, returning
+/// how many were signalled
+pub fn signal_prefix(prefix: &Path, signal: Signal) -> usize {
+    let processes = prefix_processes(prefix);
+    for process in &processes {
+        let _ = signal::kill(Pid::from_raw(process.pid), signal);
+    }
+    if processes.is_empty() {
+        debug!(prefix = %prefix.display(), "No processes left in WINE prefix");
+    } else {
+        info!(prefix = %prefix.display(), count = processes.len(), signal = %signal, "Signalled WINE prefix");
+    }
+    processes.len()
+}
+
+/// The prefix path from the config, with `~` expanded
+pub fn expand_prefix(prefix: &Path) -> PathBuf {
+    PathBuf::from(crate::adapter::expand_tilde(&prefix.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use std::time::Duration;
+
+    #[test]
+    fn test_environ_in_prefix() {
+        let prefix = Path::new("/home/kid/.steam/steam/steamapps/compatdata/504230/pfx");
+        assert!(environ_in_prefix(
+            b"HOME=/home/kid\0WINEPREFIX=/home/kid/.steam/steam/steamapps/compatdata/504230/pfx/\0",
+            prefix
+        ));
+        assert!(environ_in_prefix(
+            b"STEAM_COMPAT_DATA_PATH=/home/kid/.steam/steam/steamapps/compatdata/504230\0",
+            prefix
+        ));
+        assert!(!environ_in_prefix(
+            b"WINEPREFIX=/home/kid/.steam/steam/steamapps/compatdata/1091500/pfx\0",
+            prefix
+        ));
+        assert!(!environ_in_prefix(b"PATH=/usr/bin\0", prefix));
+    }
+
+    #[test]
+    fn test_is_wine_service() {
+        assert!(is_wine_service(b"/opt/proton/files/bin/wineserver\0"));
+        assert!(is_wine_service(b"C:\\windows\\system32\\winedevice.exe\0"));
+        assert!(is_wine_service(b"C:\\windows\\system32\\Services.exe\0"));
+        assert!(!is_wine_service(b"Z:\\home\\kid\\Games\\Celeste\\Celeste.exe\0-fullscreen\0"));
+        assert!(!is_wine_service(b""));
+    }
+
+    #[test]
+    fn test_prefix_processes() {
+        let prefix = tempfile::tempdir().unwrap();
+        let mut game = Command::new("sleep")
+            .arg("30")
+            .env("WINEPREFIX", prefix.path())
+            .spawn()
+            .unwrap();
+        let mut other = Command::new("sleep").arg("30").env("WINEPREFIX", "/elsewhere").spawn().unwrap();
+
+        let found = prefix_processes(prefix.path());
+        assert_eq!(found, vec![PrefixProcess { pid: game.id() as i32, service: false }]);
+        assert!(game_running(prefix.path(), None));
+
+        assert_eq!(signal_prefix(prefix.path(), Signal::SIGKILL), 1);
+        game.wait().unwrap();
+        // Give /proc a moment to drop the reaped process
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!game_running(prefix.path(), None));
+        other.kill().unwrap();
+        other.wait().unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+//! X11 fallback
+//!
+//! For kiosks running an X11 session instead of Sway. Windows are managed
+//! through EWMH hints with `wmctrl`, matched to sessions by `_NET_WM_PID`,
+//! and idle time comes from the XScreenSaver extension via `xprintidle`.
+//! Sandboxed apps report PIDs from their own namespace, so Flatpak windows
+//! may not be found.
+
+use shepherd_api::ErrorKind;
+use shepherd_host_api::{HostError, HostResult};
+use std::process::Command;
+use std::time::Duration;
+use tracing::debug;
+
+/// A top-level window, as listed by `wmctrl -l -p`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct X11Window {
+    pub id: String,
+    pub pid: u32,
+    pub title: String,
+}
+
+/// Whether the service is running in an X11 session
+pub fn is_x11_session() -> bool {
+    match std::env::var("XDG_SESSION_TYPE") {
+        Ok(session_type) if !session_type.is_empty() => session_type == "x11",
+        _ => {
+            std::env::var_os("DISPLAY").is_some_and(|d| !d.is_empty())
+                && std::env::var_os("WAYLAND_DISPLAY").is_none()
+        }
+    }
+}
+
+/// Check for an X11 session with an EWMH window manager that `wmctrl` can talk to
+pub fn is_x11_available() -> bool {
+    is_x11_session()
+        && Command::new("wmctrl")
+            .arg("-m")
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+}
+
+fn wmctrl(args: &[&str]) -> HostResult<String> {
+    let output = Command::new("wmctrl")
+        .args(args)
+        .output()
+        .map_err(|e| HostError::exec("wmctrl", &e))?;
+    if !output.status.success() {
+        return Err(HostError::command_failed(format!(
+            "wmctrl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// All top-level windows
+pub fn list_windows() -> HostResult<Vec<X11Window>> {
+    Ok(parse_window_list(&wmctrl(&["-l", "-p"])?))
+}
+
+/// Windows owned by any of the given processes
+pub fn windows_for_pids(pids: &[u32]) -> HostResult<Vec<X11Window>> {
+    Ok(list_windows()?
+        .into_iter()
+        .filter(|w| pids.contains(&w.pid))
+        .collect())
+}
+
+/// Raise and focus a window (`_NET_ACTIVE_WINDOW`)
+pub fn activate_window(id: &str) -> HostResult<()> {
+    debug!(window = id, "Activating X11 window");
+    wmctrl(&["-i", "-a", id]).map(|_| ())
+}
+
+/// Make a window fullscreen (`_NET_WM_STATE_FULLSCREEN`)
+pub fn fullscreen_window(id: &str) -> HostResult<()> {
+    debug!(window = id, "Making X11 window fullscreen");
+    wmctrl(&["-i", "-r", id, "-b", "add,fullscreen"]).map(|_| ())
+}
+
+/// How long since the last keyboard or pointer input
+pub fn x11_idle_time() -> HostResult<Duration> {
+    let output = Command::new("xprintidle")
+        .output()
+        .map_err(|e| HostError::exec("xprintidle", &e))?;
+    if !output.status.success() {
+        return Err(HostError::command_failed(format!(
+            "xprintidle failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<u64>()
+        .map(Duration::from_millis)
+        .map_err(|e| HostError::new(ErrorKind::Internal, format!("Bad xprintidle output: {}", e)))
+}
+
+/// Parse `wmctrl -l -p`: window id, desktop, pid, host, then the title
+fn parse_window_list(output: &str) -> Vec<X11Window> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let id = fields.next()?.to_string();
+            let _desktop = fields.next()?;
+            let pid = fields.next()?.parse().ok()?;
+            let _host = fields.next()?;
+            let title = fields.collect::<Vec<_>>().join(" ");
+            Some(X11Window { id, pid, title })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_window_list() {
+        let output = "\
+0x01e00003 -1 812    kiosk shepherd-hud
+0x03a00007  0 20431  kiosk SuperTux 0.6.3
+0x04000001  0 0      kiosk N/A
+";
+        let windows = parse_window_list(output);
+        assert_eq!(windows.len(), 3);
+        assert_eq!(
+            windows[1],
+            X11Window {
+                id: "0x03a00007".into(),
+                pid: 20431,
+                title: "SuperTux 0.6.3".into(),
+            }
+        );
+        assert_eq!(windows[2].pid, 0);
+    }
+}
@@ -1,17 +1,20 @@
 //! HUD Application
 //!
 //! The main GTK4 application for the HUD overlay.
-//! Uses gtk4-layer-shell to create an always-visible overlay.
+//! Uses gtk4-layer-shell to create an always-visible overlay, or EWMH
+//! hints on X11. Compositors with neither (e.g. GNOME on Wayland) get a
+//! plain undecorated window.
 
-use crate::battery::BatteryStatus;
+use crate::controllers::ControllerSummary;
 use crate::state::{SessionState, SharedState};
 use crate::time_display::TimeDisplay;
 use gtk4::glib;
 use gtk4::prelude::*;
 use gtk4_layer_shell::{Edge, Layer, LayerShell};
-use shepherd_api::Command;
-use shepherd_ipc::IpcClient;
-use shepherd_util::default_socket_path;
+use shepherd_api::{ClientKind, Command, Event, ResponseResult};
+use shepherd_ui_theme::{Component, Theme, ThemeProvider};
+use shepherd_ipc::{CommandSender, EventStream, IpcClient, IpcError, StreamMessage};
+use shepherd_util::SeatId;
 use std::path::PathBuf;
 use std::sync::mpsc;
 use std::time::Duration;
@@ -21,12 +24,13 @@ use tokio::runtime::Runtime;
 pub struct HudApp {
     app: gtk4::Application,
     socket_path: PathBuf,
+    seat: Option<SeatId>,
     anchor: String,
     height: i32,
 }
 
 impl HudApp {
-    pub fn new(socket_path: PathBuf, anchor: String, height: i32) -> Self {
+    pub fn new(socket_path: PathBuf, seat: Option<SeatId>, anchor: String, height: i32) -> Self {
         let app = gtk4::Application::builder()
             .application_id("org.shepherd.hud")
             .build();
@@ -34,6 +38,7 @@ impl HudApp {
         Self {
             app,
             socket_path,
+            seat,
             anchor,
             height,
         }
@@ -41,39 +46,97 @@ impl HudApp {
 
     pub fn run(&self) -> i32 {
         let socket_path = self.socket_path.clone();
+        let seat = self.seat.clone();
         let anchor = self.anchor.clone();
         let height = self.height;
 
         self.app.connect_activate(move |app| {
             let state = SharedState::new();
-            let window = build_hud_window(app, &anchor, height, state.clone());
+            build_hud(app, &anchor, height, state.clone());
 
             // Start the IPC event listener
-            let state_clone = state.clone();
             let socket_clone = socket_path.clone();
+            let seat = seat.clone();
             std::thread::spawn(move || {
-                if let Err(e) = run_event_loop(socket_clone, state_clone) {
+                if let Err(e) = run_event_loop(socket_clone, seat, state) {
                     tracing::error!("Event loop error: {}", e);
                 }
             });
-
-            // Subscribe to state changes
-            let window_clone = window.clone();
-            let state_clone = state.clone();
-            glib::timeout_add_local(Duration::from_millis(100), move || {
-                let session_state = state_clone.session_state();
-                let visible = session_state.is_visible();
-                window_clone.set_visible(visible);
-                glib::ControlFlow::Continue
-            });
-
-            window.present();
         });
 
         self.app.run().into()
     }
 }
 
+/// Build and show the HUD bar. `state` is fed by whoever owns the event
+/// connection: the HUD's own event loop, or the combined shell.
+pub fn build_hud(
+    app: &gtk4::Application,
+    anchor: &str,
+    height: i32,
+    state: SharedState,
+) -> gtk4::ApplicationWindow {
+    let theme_provider = ThemeProvider::install(Component::Hud, Theme::default());
+    let window = build_hud_window(app, anchor, height, state.clone());
+
+    // Follow the theme picked in the service config
+    let mut theme_rx = state.subscribe_theme();
+    glib::spawn_future_local(async move {
+        loop {
+            theme_provider.set_theme(*theme_rx.borrow_and_update());
+            if theme_rx.changed().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // Poll paired controllers (BlueZ doesn't push battery changes to us)
+    let state_clone = state.clone();
+    std::thread::spawn(move || {
+        loop {
+            if let Some(devices) = crate::controllers::get_input_devices() {
+                state_clone.set_input_devices(devices);
+            }
+            std::thread::sleep(Duration::from_secs(15));
+        }
+    });
+
+    // Subscribe to state changes
+    let window_clone = window.clone();
+    glib::timeout_add_local(Duration::from_millis(100), move || {
+        let session_state = state.session_state();
+        let visible = session_state.is_visible();
+        window_clone.set_visible(visible);
+        glib::ControlFlow::Continue
+    });
+
+    window.present();
+    window
+}
+
+/// How the HUD window is kept on screen, picked at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WindowMode {
+    /// wlr-layer-shell overlay (Sway, Hyprland, ...)
+    LayerShell,
+    /// Undecorated window pinned with EWMH hints
+    X11,
+    /// Plain window; the compositor decides where it goes
+    Fallback,
+}
+
+impl WindowMode {
+    fn detect() -> Self {
+        if gtk4_layer_shell::is_supported() {
+            Self::LayerShell
+        } else if crate::x11::is_x11_display() {
+            Self::X11
+        } else {
+            Self::Fallback
+        }
+    }
+}
+
 fn build_hud_window(
     app: &gtk4::Application,
     anchor: &str,
@@ -86,6 +149,37 @@ fn build_hud_window(
         .decorated(false)
         .build();
 
+    let mode = WindowMode::detect();
+    tracing::info!(?mode, "Creating HUD window");
+
+    match mode {
+        WindowMode::LayerShell => {}
+        WindowMode::X11 => {
+            // No layer-shell on X11; keep the window above others once it's mapped
+            window.set_title(Some(crate::x11::WINDOW_TITLE));
+            let anchor = anchor.to_string();
+            window.connect_map(move |window| {
+                crate::x11::place_window(window, &anchor, height);
+            });
+            window.set_child(Some(&build_hud_content(state)));
+            return window;
+        }
+        WindowMode::Fallback => {
+            // Wayland without layer-shell gives clients no say over stacking
+            // or position, so the best we can do is a full-width bar
+            tracing::warn!(
+                "Compositor has no layer-shell support; the HUD may be covered by other windows"
+            );
+            window.set_title(Some(crate::x11::WINDOW_TITLE));
+            window.set_resizable(false);
+            if let Some(width) = first_monitor_width() {
+                window.set_default_size(width, height);
+            }
+            window.set_child(Some(&build_hud_content(state)));
+            return window;
+        }
+    }
+
     // Initialize layer shell
     window.init_layer_shell();
     window.set_layer(Layer::Overlay);
@@ -115,9 +209,6 @@ fn build_hud_window(
     // Set exclusive zone so other windows don't overlap
     window.set_exclusive_zone(height);
 
-    // Load CSS
-    load_css();
-
     // Build the HUD content
     let content = build_hud_content(state);
     window.set_child(Some(&content));
@@ -125,6 +216,16 @@ fn build_hud_window(
     window
 }
 
+fn first_monitor_width() -> Option<i32> {
+    let display = gtk4::gdk::Display::default()?;
+    let monitor = display
+        .monitors()
+        .item(0)?
+        .downcast::<gtk4::gdk::Monitor>()
+        .ok()?;
+    Some(monitor.geometry().width())
+}
+
 fn build_hud_content(state: SharedState) -> gtk4::Box {
     let container = gtk4::Box::builder()
         .orientation(gtk4::Orientation::Horizontal)
@@ -149,6 +250,30 @@ fn build_hud_content(state: SharedState) -> gtk4::Box {
     let time_display = TimeDisplay::new();
     left_box.append(&time_display);
 
+    // Camera/microphone access indicators for the current session
+    let camera_icon = gtk4::Image::from_icon_name("camera-web-symbolic");
+    camera_icon.set_pixel_size(16);
+    camera_icon.set_tooltip_text(Some("This app can use the camera"));
+    camera_icon.add_css_class("device-access-icon");
+    camera_icon.set_visible(false);
+    left_box.append(&camera_icon);
+
+    let microphone_icon = gtk4::Image::from_icon_name("audio-input-microphone-symbolic");
+    microphone_icon.set_pixel_size(16);
+    microphone_icon.set_tooltip_text(Some("This app can use the microphone"));
+    microphone_icon.add_css_class("device-access-icon");
+    microphone_icon.set_visible(false);
+    left_box.append(&microphone_icon);
+
+    // What the session's media player is playing (hidden when nothing is)
+    let media_label = gtk4::Label::builder()
+        .ellipsize(gtk4::pango::EllipsizeMode::End)
+        .max_width_chars(32)
+        .visible(false)
+        .build();
+    media_label.add_css_class("media-label");
+    left_box.append(&media_label);
+
     container.append(&left_box);
 
     // Center section: Warning banner (hidden by default)
@@ -304,6 +429,7 @@ fn build_hud_content(state: SharedState) -> gtk4::Box {
     let battery_box = gtk4::Box::builder()
         .orientation(gtk4::Orientation::Horizontal)
         .spacing(4)
+        .visible(false)
         .build();
 
     let battery_icon = gtk4::Image::from_icon_name("battery-good-symbolic");
@@ -316,6 +442,23 @@ fn build_hud_content(state: SharedState) -> gtk4::Box {
 
     right_box.append(&battery_box);
 
+    // Controller indicator (hidden when no gamepad is connected)
+    let controller_box = gtk4::Box::builder()
+        .orientation(gtk4::Orientation::Horizontal)
+        .spacing(4)
+        .visible(false)
+        .build();
+
+    let controller_icon = gtk4::Image::from_icon_name("input-gaming-symbolic");
+    controller_icon.set_pixel_size(20);
+    controller_box.append(&controller_icon);
+
+    let controller_label = gtk4::Label::new(None);
+    controller_label.add_css_class("controller-label");
+    controller_box.append(&controller_label);
+
+    right_box.append(&controller_box);
+
     // Close button
     let close_button = gtk4::Button::builder()
         .icon_name("window-close-symbolic")
@@ -329,25 +472,10 @@ fn build_hud_content(state: SharedState) -> gtk4::Box {
         let session_state = state_for_close.session_state();
         if let Some(session_id) = session_state.session_id() {
             tracing::info!("Requesting end session for {}", session_id);
-            // Send StopCurrent command to shepherdd
-            let socket_path = default_socket_path();
-            std::thread::spawn(move || {
-                let rt = Runtime::new().expect("Failed to create runtime");
-                rt.block_on(async {
-                    match IpcClient::connect(&socket_path).await {
-                        Ok(mut client) => {
-                            let cmd = Command::StopCurrent {
-                                mode: shepherd_api::StopMode::Graceful,
-                            };
-                            if let Err(e) = client.send(cmd).await {
-                                tracing::error!("Failed to send StopCurrent: {}", e);
-                            }
-                        }
-                        Err(e) => {
-                            tracing::error!("Failed to connect to shepherdd: {}", e);
-                        }
-                    }
-                });
+            // Sent on the event connection
+            state_for_close.send_command(Command::StopCurrent {
+                mode: shepherd_api::StopMode::Graceful,
+                seat: None,
             });
         }
     });
@@ -360,8 +488,14 @@ fn build_hud_content(state: SharedState) -> gtk4::Box {
     let time_display_clone = time_display.clone();
     let warning_box_clone = warning_box.clone();
     let warning_label_clone = warning_label.clone();
+    let battery_box_clone = battery_box.clone();
     let battery_icon_clone = battery_icon.clone();
     let battery_label_clone = battery_label.clone();
+    let controller_box_clone = controller_box.clone();
+    let camera_icon_clone = camera_icon.clone();
+    let microphone_icon_clone = microphone_icon.clone();
+    let controller_label_clone = controller_label.clone();
+    let media_label_clone = media_label.clone();
     let volume_button_clone = volume_button.clone();
     let volume_slider_clone = volume_slider.clone();
     let volume_label_clone = volume_label.clone();
@@ -385,33 +519,20 @@ fn build_hud_content(state: SharedState) -> gtk4::Box {
                 time_display_clone.set_remaining(None);
                 warning_box_clone.set_visible(false);
             }
-            SessionState::Active {
-                entry_name,
-                started_at,
-                time_limit_secs,
-                ..
-            } => {
+            SessionState::Active { entry_name, .. } => {
                 app_label_clone.set_text(entry_name);
-                // Calculate remaining time based on elapsed time since session start
-                let remaining = time_limit_secs.map(|limit| {
-                    let elapsed = started_at.elapsed().as_secs();
-                    limit.saturating_sub(elapsed)
-                });
-                time_display_clone.set_remaining(remaining);
+                // Counted down locally between the service's syncs
+                time_display_clone.set_remaining(session_state.time_remaining_secs());
                 warning_box_clone.set_visible(false);
             }
             SessionState::Warning {
                 entry_name,
-                warning_issued_at,
-                time_remaining_at_warning,
                 message,
                 severity,
                 ..
             } => {
                 app_label_clone.set_text(entry_name);
-                // Calculate remaining time based on elapsed time since warning was issued
-                let elapsed = warning_issued_at.elapsed().as_secs();
-                let remaining = time_remaining_at_warning.saturating_sub(elapsed);
+                let remaining = session_state.time_remaining_secs().unwrap_or(0);
                 time_display_clone.set_remaining(Some(remaining));
                 // Use configuration-defined message if present, otherwise show time-based message
                 let warning_text = message.clone().unwrap_or_else(|| {
@@ -444,13 +565,58 @@ fn build_hud_content(state: SharedState) -> gtk4::Box {
             }
         }
 
-        // Update battery
-        let battery = BatteryStatus::read();
-        battery_icon_clone.set_icon_name(Some(battery.icon_name()));
-        if let Some(percent) = battery.percent {
-            battery_label_clone.set_text(&format!("{}%", percent));
-        } else {
-            battery_label_clone.set_text("--%");
+        // Update battery (hidden on hosts without one)
+        match state.battery() {
+            Some(status) => {
+                battery_icon_clone.set_icon_name(Some(status.battery.icon_name()));
+                match status.battery.percent {
+                    Some(percent) => battery_label_clone.set_text(&format!("{}%", percent)),
+                    None => battery_label_clone.set_text("--%"),
+                }
+                if status.low {
+                    battery_label_clone.add_css_class("battery-low");
+                    battery_box_clone.set_tooltip_text(Some("Battery low - plug in the charger"));
+                } else {
+                    battery_label_clone.remove_css_class("battery-low");
+                    battery_box_clone.set_tooltip_text(None);
+                }
+                battery_box_clone.set_visible(true);
+            }
+            None => battery_box_clone.set_visible(false),
+        }
+
+        // Update camera/microphone indicators
+        let device_access = session_state.device_access();
+        camera_icon_clone.set_visible(device_access.is_some_and(|a| a.camera));
+        microphone_icon_clone.set_visible(device_access.is_some_and(|a| a.microphone));
+
+        // Update the playing track
+        match state.media().filter(|media| media.status == shepherd_api::PlaybackStatus::Playing) {
+            Some(media) => {
+                let track = media.track().unwrap_or(media.player);
+                media_label_clone.set_text(&format!("♪ {}", track));
+                media_label_clone.set_visible(true);
+            }
+            None => media_label_clone.set_visible(false),
+        }
+
+        // Update controller indicator
+        match ControllerSummary::from_devices(&state.input_devices()) {
+            Some(summary) => {
+                let text = match summary.lowest_battery {
+                    Some(percent) => format!("{}%", percent),
+                    None if summary.count > 1 => format!("×{}", summary.count),
+                    None => String::new(),
+                };
+                controller_label_clone.set_text(&text);
+                if summary.is_low() {
+                    controller_label_clone.add_css_class("controller-low");
+                } else {
+                    controller_label_clone.remove_css_class("controller-low");
+                }
+                controller_box_clone.set_visible(true);
+            }
+            None => controller_box_clone.set_visible(false),
         }
 
         // Update volume from cached state (updated via events, no polling needed)
@@ -485,190 +651,86 @@ fn build_hud_content(state: SharedState) -> gtk4::Box {
     container
 }
 
-fn load_css() {
-    let css = r#"
-        :root {
-            --hud-bg: rgba(30, 30, 30, 0.95);
-            --text-primary: white;
-            --text-secondary: #d8dee9;
-            --color-info: #88c0d0;
-            --color-warning: #ebcb8b;
-            --color-critical: #ff6b6b;
-            --color-success: #a3be8c;
-            --hover-bg: rgba(255, 255, 255, 0.1);
-        }
-
-        .hud-bar {
-            background-color: var(--hud-bg);
-            border: none;
-            margin: 0;
-            padding: 6px 12px;
-        }
-
-        .app-name {
-            font-weight: bold;
-            font-size: 14px;
-            color: var(--text-primary);
-        }
-
-        .time-display {
-            font-family: monospace;
-            font-size: 14px;
-            color: var(--color-info);
-        }
-
-        .time-display.time-warning {
-            color: var(--color-warning);
-        }
-
-        .time-display.time-critical {
-            color: var(--color-critical);
-            animation: blink 1s infinite;
-        }
-
-        @keyframes blink {
-            50% { opacity: 0.5; }
-        }
-
-        .warning-banner {
-            background-color: rgba(235, 203, 139, 0.2);
-            border-radius: 4px;
-            padding: 4px 12px;
-        }
-
-        .warning-banner.warning-info {
-            background-color: rgba(136, 192, 208, 0.2);
-        }
-
-        .warning-banner.warning-info .warning-text {
-            color: var(--color-info);
-        }
-
-        .warning-banner.warning-warn {
-            background-color: rgba(235, 203, 139, 0.2);
-        }
-
-        .warning-banner.warning-warn .warning-text {
-            color: var(--color-warning);
-        }
-
-        .warning-banner.warning-critical {
-            background-color: rgba(255, 107, 107, 0.2);
-            animation: blink 1s infinite;
-        }
-
-        .warning-banner.warning-critical .warning-text {
-            color: var(--color-critical);
-        }
-
-        .warning-text {
-            color: var(--color-warning);
-            font-weight: bold;
-        }
-
-        image {
-            color: var(--text-primary);
-        }
-
-        .indicator-button,
-        .control-button {
-            min-width: 32px;
-            min-height: 32px;
-            padding: 4px;
-            border-radius: 4px;
-            color: var(--text-primary);
-        }
-
-        .indicator-button:hover,
-        .control-button:hover {
-            background-color: var(--hover-bg);
-        }
-
-        .close-button {
-            min-width: 32px;
-            min-height: 32px;
-            padding: 4px;
-            border-radius: 4px;
-            color: var(--color-critical);
-        }
-
-        .close-button:hover {
-            background-color: rgba(191, 97, 106, 0.3);
-        }
-
-        .battery-label {
-            font-size: 12px;
-            color: var(--text-primary);
-        }
-
-        .volume-control {
-            padding: 0 4px;
-        }
-
-        .volume-slider {
-            min-width: 80px;
-        }
-
-        .volume-slider trough {
-            min-height: 4px;
-            border-radius: 2px;
-            background-color: rgba(255, 255, 255, 0.2);
-        }
-
-        .volume-slider highlight {
-            min-height: 4px;
-            border-radius: 2px;
-            background-color: var(--color-info);
-        }
-
-        .volume-slider slider {
-            min-width: 12px;
-            min-height: 12px;
-            border-radius: 50%;
-            background-color: var(--text-primary);
+/// Volume, battery and media aren't in state snapshots; ask for them before
+/// subscribing (no commands can be sent after)
+pub async fn fetch_initial_status(client: &mut IpcClient, state: &SharedState) {
+    match client.send(Command::GetVolume).await {
+        Ok(response) => {
+            if let shepherd_api::ResponseResult::Ok(shepherd_api::ResponsePayload::Volume(info)) =
+                response.result
+            {
+                tracing::debug!("Got initial volume: {}%", info.percent);
+                state.set_initial_volume(info);
+            }
         }
-
-        .volume-slider:disabled trough {
-            background-color: rgba(255, 255, 255, 0.1);
+        Err(e) => {
+            tracing::warn!("Failed to get initial volume: {}", e);
         }
+    }
 
-        .volume-slider:disabled highlight {
-            background-color: rgba(136, 192, 208, 0.5);
-        }
+    // Fails on hosts without a battery
+    if let Ok(response) = client.send(Command::GetBattery).await
+        && let shepherd_api::ResponseResult::Ok(shepherd_api::ResponsePayload::Battery {
+            battery,
+            low,
+        }) = response.result
+    {
+        state.set_battery(battery, low);
+    }
 
-        .volume-label {
-            font-size: 12px;
-            color: var(--text-secondary);
-            min-width: 3em;
-            text-align: right;
-        }
+    if let Ok(response) = client.send(Command::GetMedia).await
+        && let shepherd_api::ResponseResult::Ok(shepherd_api::ResponsePayload::Media { media }) = response.result
+    {
+        state.set_media(media);
+    }
+}
 
-        .clock-label {
-            font-family: monospace;
-            font-size: 14px;
-            color: var(--text-primary);
-        }
+/// Handle the events of a subscribed connection, sending the HUD's queued
+/// commands on the same connection. Runs until the connection fails.
+pub async fn run_connection(
+    mut events: EventStream,
+    mut sender: CommandSender,
+    commands: &mut tokio::sync::mpsc::UnboundedReceiver<Command>,
+    mut on_event: impl FnMut(Event),
+) -> IpcError {
+    // A stop queued while disconnected could end a session started since
+    while let Ok(command) = commands.try_recv() {
+        tracing::warn!(?command, "Dropping command queued while disconnected");
+    }
 
-        .mock-time-indicator {
-            font-size: 10px;
-            font-weight: bold;
-            color: var(--color-warning);
-            margin-left: 4px;
+    loop {
+        tokio::select! {
+            message = events.next_message() => match message {
+                Ok(StreamMessage::Event(event)) => {
+                    tracing::debug!("Received event: {:?}", event);
+                    if shepherd_ipc::replaced_this_process(&event) {
+                        tracing::warn!("A newer instance took over, exiting");
+                        std::process::exit(0);
+                    }
+                    on_event(event);
+                }
+                Ok(StreamMessage::Response(response)) => {
+                    if let ResponseResult::Err(e) = response.result {
+                        tracing::error!(request_id = response.request_id, "Command failed: {}", e.message);
+                    }
+                }
+                Err(e) => return e,
+            },
+            Some(command) = commands.recv() => {
+                match sender.send(command).await {
+                    Ok(request_id) => tracing::debug!(request_id, "Sent command"),
+                    Err(e) => return e,
+                }
+            }
         }
-    "#;
-
-    let provider = gtk4::CssProvider::new();
-    provider.load_from_data(css);
-
-    gtk4::style_context_add_provider_for_display(
-        &gtk4::gdk::Display::default().expect("Could not get display"),
-        &provider,
-        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
-    );
+    }
 }
 
-fn run_event_loop(socket_path: PathBuf, state: SharedState) -> anyhow::Result<()> {
+fn run_event_loop(socket_path: PathBuf, seat: Option<SeatId>, state: SharedState) -> anyhow::Result<()> {
     let rt = Runtime::new()?;
+    let mut commands = state
+        .take_commands()
+        .unwrap_or_else(|| tokio::sync::mpsc::unbounded_channel().1);
 
     rt.block_on(async {
         loop {
@@ -677,25 +739,15 @@ fn run_event_loop(socket_path: PathBuf, state: SharedState) -> anyhow::Result<()
             match IpcClient::connect(&socket_path).await {
                 Ok(mut client) => {
                     tracing::info!("Connected to shepherdd");
-
-                    // Get initial volume before subscribing (can't send commands after subscribe)
-                    match client.send(Command::GetVolume).await {
-                        Ok(response) => {
-                            if let shepherd_api::ResponseResult::Ok(
-                                shepherd_api::ResponsePayload::Volume(info),
-                            ) = response.result
-                            {
-                                tracing::debug!("Got initial volume: {}%", info.percent);
-                                state.set_initial_volume(info);
-                            }
-                        }
-                        Err(e) => {
-                            tracing::warn!("Failed to get initial volume: {}", e);
-                        }
+                    // Take over from a HUD left behind by a crash
+                    match client.identify(ClientKind::Hud, seat.clone()).await {
+                        Ok(role) => tracing::info!("Identified as HUD with role {:?}", role),
+                        Err(e) => tracing::warn!("Failed to identify: {}", e),
                     }
+                    fetch_initial_status(&mut client, &state).await;
 
-                    let mut stream = match client.subscribe().await {
-                        Ok(stream) => stream,
+                    let (stream, sender) = match client.subscribe_with_commands().await {
+                        Ok(subscribed) => subscribed,
                         Err(e) => {
                             tracing::error!("Failed to subscribe: {}", e);
                             tokio::time::sleep(Duration::from_secs(1)).await;
@@ -703,18 +755,8 @@ fn run_event_loop(socket_path: PathBuf, state: SharedState) -> anyhow::Result<()
                         }
                     };
 
-                    loop {
-                        match stream.next().await {
-                            Ok(event) => {
-                                tracing::debug!("Received event: {:?}", event);
-                                state.handle_event(&event);
-                            }
-                            Err(e) => {
-                                tracing::error!("Event stream error: {}", e);
-                                break;
-                            }
-                        }
-                    }
+                    let e = run_connection(stream, sender, &mut commands, |event| state.handle_event(&event)).await;
+                    tracing::error!("Event stream error: {}", e);
                 }
                 Err(e) => {
                     tracing::warn!("Failed to connect to shepherdd: {}", e);
@@ -0,0 +1,112 @@
+//! Game controller status module
+//!
+//! Fetches paired input devices from shepherdd and summarizes connected
+//! gamepads for the HUD indicator.
+
+use shepherd_api::{Command, InputDeviceInfo, InputDeviceKind, ResponsePayload};
+use shepherd_ipc::IpcClient;
+use shepherd_util::default_socket_path;
+use tokio::runtime::Runtime;
+
+/// Summary of connected gamepads
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControllerSummary {
+    /// Number of connected gamepads
+    pub count: usize,
+    /// Lowest reported battery percentage among connected gamepads
+    pub lowest_battery: Option<u8>,
+}
+
+impl ControllerSummary {
+    /// Summarize connected gamepads, or `None` if there are none
+    pub fn from_devices(devices: &[InputDeviceInfo]) -> Option<Self> {
+        let gamepads: Vec<_> = devices
+            .iter()
+            .filter(|d| d.connected && d.kind == InputDeviceKind::Gamepad)
+            .collect();
+
+        if gamepads.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            count: gamepads.len(),
+            lowest_battery: gamepads.iter().filter_map(|d| d.battery_percent).min(),
+        })
+    }
+
+    /// Check if any controller battery is low
+    pub fn is_low(&self) -> bool {
+        matches!(self.lowest_battery, Some(p) if p < 20)
+    }
+}
+
+/// Get paired input devices from shepherdd
+pub fn get_input_devices() -> Option<Vec<InputDeviceInfo>> {
+    let socket_path = default_socket_path();
+
+    let rt = match Runtime::new() {
+        Ok(rt) => rt,
+        Err(e) => {
+            tracing::error!("Failed to create runtime: {}", e);
+            return None;
+        }
+    };
+
+    rt.block_on(async {
+        match IpcClient::connect(&socket_path).await {
+            Ok(mut client) => match client.send(Command::GetInputDevices).await {
+                Ok(response) => {
+                    if let shepherd_api::ResponseResult::Ok(ResponsePayload::InputDevices {
+                        devices,
+                    }) = response.result
+                    {
+                        Some(devices)
+                    } else {
+                        None
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to get input devices: {}", e);
+                    None
+                }
+            },
+            Err(e) => {
+                tracing::debug!("Failed to connect to shepherdd for input devices: {}", e);
+                None
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gamepad(connected: bool, battery_percent: Option<u8>) -> InputDeviceInfo {
+        InputDeviceInfo {
+            name: "Controller".into(),
+            address: "AA:BB:CC:DD:EE:FF".into(),
+            kind: InputDeviceKind::Gamepad,
+            paired: true,
+            connected,
+            battery_percent,
+        }
+    }
+
+    #[test]
+    fn test_controller_summary() {
+        assert_eq!(ControllerSummary::from_devices(&[]), None);
+        assert_eq!(ControllerSummary::from_devices(&[gamepad(false, Some(50))]), None);
+
+        let summary = ControllerSummary::from_devices(&[
+            gamepad(true, Some(80)),
+            gamepad(true, Some(15)),
+            gamepad(true, None),
+        ])
+        .unwrap();
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.lowest_battery, Some(15));
+        assert!(summary.is_low());
+    }
+}
@@ -0,0 +1,15 @@
+//! Shepherd HUD
+//!
+//! The HUD bar as a library, so `shepherd-shell` can run it in the same
+//! process as the launcher. The `shepherd-hud` binary is a thin wrapper
+//! around [`HudApp`].
+
+pub mod app;
+mod controllers;
+pub mod state;
+mod time_display;
+mod volume;
+mod x11;
+
+pub use app::{HudApp, build_hud, fetch_initial_status, run_connection};
+pub use state::SharedState;
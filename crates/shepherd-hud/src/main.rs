@@ -3,15 +3,10 @@
 //! This is the heads-up display that remains visible during active sessions.
 //! It shows time remaining, battery, volume, and provides session controls.
 
-mod app;
-mod battery;
-mod state;
-mod time_display;
-mod volume;
-
 use anyhow::Result;
 use clap::Parser;
-use shepherd_util::default_socket_path;
+use shepherd_hud::HudApp;
+use shepherd_util::{default_socket_path, SeatId};
 use std::path::PathBuf;
 use tracing_subscriber::EnvFilter;
 
@@ -24,6 +19,10 @@ struct Args {
     #[arg(short, long, env = "SHEPHERD_SOCKET")]
     socket: Option<PathBuf>,
 
+    /// Seat this HUD runs on, for multi-seat setups (or set SHEPHERD_SEAT env var)
+    #[arg(long, env = "SHEPHERD_SEAT")]
+    seat: Option<SeatId>,
+
     /// Log level
     #[arg(short, long, default_value = "info")]
     log_level: String,
@@ -54,7 +53,7 @@ fn main() -> Result<()> {
     let socket_path = args.socket.unwrap_or_else(default_socket_path);
 
     // Run GTK application
-    let application = app::HudApp::new(socket_path, args.anchor, args.height);
+    let application = HudApp::new(socket_path, args.seat, args.anchor, args.height);
     let exit_code = application.run();
 
     std::process::exit(exit_code);
@@ -2,10 +2,15 @@
 //!
 //! The HUD subscribes to events from shepherdd and tracks session state.
 
-use shepherd_api::{Event, EventPayload, VolumeInfo, VolumeRestrictions, WarningSeverity};
+use shepherd_api::{
+    BatteryInfo, Command, DeviceAccess, Event, EventPayload, InputDeviceInfo, Locale, MediaInfo, SessionEndReason, Theme,
+    VolumeInfo,
+    VolumeRestrictions, WarningSeverity,
+};
 use shepherd_util::{EntryId, SessionId};
-use std::sync::Arc;
-use tokio::sync::watch;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
 
 /// The current state of the session as seen by the HUD
 #[derive(Debug, Clone)]
@@ -18,10 +23,12 @@ pub enum SessionState {
         session_id: SessionId,
         entry_id: EntryId,
         entry_name: String,
-        started_at: std::time::Instant,
-        time_limit_secs: Option<u64>,
-        #[allow(dead_code)]
+        /// Time left as of `synced_at`, by the service's count. None means
+        /// unlimited.
         time_remaining_secs: Option<u64>,
+        synced_at: Instant,
+        /// Camera/microphone access granted to the session
+        device_access: DeviceAccess,
     },
 
     /// Warning shown - time running low
@@ -29,12 +36,17 @@ pub enum SessionState {
         session_id: SessionId,
         entry_id: EntryId,
         entry_name: String,
-        warning_issued_at: std::time::Instant,
-        time_remaining_at_warning: u64,
+        /// Time left as of `synced_at`, by the service's count
+        time_remaining_secs: u64,
+        synced_at: Instant,
+        /// Time left the warning was for; an extension past it clears the
+        /// warning
+        threshold_secs: u64,
         /// Optional custom message from configuration
         message: Option<String>,
         /// Severity level of the warning
         severity: WarningSeverity,
+        device_access: DeviceAccess,
     },
 
     /// Session is ending
@@ -62,6 +74,42 @@ impl SessionState {
             SessionState::Ending { session_id, .. } => Some(session_id),
         }
     }
+
+    /// Get the camera/microphone access of the current session, if any
+    pub fn device_access(&self) -> Option<DeviceAccess> {
+        match self {
+            SessionState::Active { device_access, .. } => Some(*device_access),
+            SessionState::Warning { device_access, .. } => Some(*device_access),
+            SessionState::NoSession | SessionState::Ending { .. } => None,
+        }
+    }
+
+    /// Seconds left in the session now, counted down from the last sync.
+    /// None without a session or for unlimited ones.
+    pub fn time_remaining_secs(&self) -> Option<u64> {
+        let (remaining, synced_at) = match self {
+            SessionState::Active {
+                time_remaining_secs,
+                synced_at,
+                ..
+            } => ((*time_remaining_secs)?, synced_at),
+            SessionState::Warning {
+                time_remaining_secs,
+                synced_at,
+                ..
+            } => (*time_remaining_secs, synced_at),
+            SessionState::NoSession | SessionState::Ending { .. } => return None,
+        };
+        Some(remaining.saturating_sub(synced_at.elapsed().as_secs()))
+    }
+}
+
+/// Host battery status as reported by shepherdd
+#[derive(Debug, Clone, Copy)]
+pub struct BatteryState {
+    pub battery: BatteryInfo,
+    /// Below the configured warning threshold and discharging
+    pub low: bool,
 }
 
 /// Shared state for the HUD
@@ -75,18 +123,53 @@ pub struct SharedState {
     volume_tx: Arc<watch::Sender<Option<VolumeInfo>>>,
     /// Volume info receiver
     volume_rx: watch::Receiver<Option<VolumeInfo>>,
+    /// Paired input devices sender (updated by polling)
+    input_devices_tx: Arc<watch::Sender<Vec<InputDeviceInfo>>>,
+    /// Paired input devices receiver
+    input_devices_rx: watch::Receiver<Vec<InputDeviceInfo>>,
+    /// Battery status sender (updated via events; None if the host has no battery)
+    battery_tx: Arc<watch::Sender<Option<BatteryState>>>,
+    /// Battery status receiver
+    battery_rx: watch::Receiver<Option<BatteryState>>,
+    /// Session media sender (updated via events; None when nothing plays)
+    media_tx: Arc<watch::Sender<Option<MediaInfo>>>,
+    /// Session media receiver
+    media_rx: watch::Receiver<Option<MediaInfo>>,
+    /// Theme sender (from snapshots and ThemeChanged events)
+    theme_tx: Arc<watch::Sender<Theme>>,
+    /// Theme receiver
+    theme_rx: watch::Receiver<Theme>,
+    /// Commands for shepherdd, sent on the event connection
+    command_tx: mpsc::UnboundedSender<Command>,
+    /// Command receiver, taken by whoever owns the event connection
+    command_rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<Command>>>>,
 }
 
 impl SharedState {
     pub fn new() -> Self {
         let (session_tx, session_rx) = watch::channel(SessionState::NoSession);
         let (volume_tx, volume_rx) = watch::channel(None);
+        let (input_devices_tx, input_devices_rx) = watch::channel(Vec::new());
+        let (battery_tx, battery_rx) = watch::channel(None);
+        let (media_tx, media_rx) = watch::channel(None);
+        let (theme_tx, theme_rx) = watch::channel(Theme::default());
+        let (command_tx, command_rx) = mpsc::unbounded_channel();
 
         Self {
             session_tx: Arc::new(session_tx),
             session_rx,
             volume_tx: Arc::new(volume_tx),
             volume_rx,
+            input_devices_tx: Arc::new(input_devices_tx),
+            input_devices_rx,
+            battery_tx: Arc::new(battery_tx),
+            battery_rx,
+            media_tx: Arc::new(media_tx),
+            media_rx,
+            theme_tx: Arc::new(theme_tx),
+            theme_rx,
+            command_tx,
+            command_rx: Arc::new(Mutex::new(Some(command_rx))),
         }
     }
 
@@ -116,6 +199,60 @@ impl SharedState {
         let _ = self.volume_tx.send(Some(info));
     }
 
+    /// Get the most recently polled input devices
+    pub fn input_devices(&self) -> Vec<InputDeviceInfo> {
+        self.input_devices_rx.borrow().clone()
+    }
+
+    /// Replace the list of input devices
+    pub fn set_input_devices(&self, devices: Vec<InputDeviceInfo>) {
+        let _ = self.input_devices_tx.send(devices);
+    }
+
+    /// Get the latest battery status
+    pub fn battery(&self) -> Option<BatteryState> {
+        *self.battery_rx.borrow()
+    }
+
+    /// Update the battery status (initial fetch and BatteryStatus events)
+    pub fn set_battery(&self, battery: BatteryInfo, low: bool) {
+        let _ = self.battery_tx.send(Some(BatteryState { battery, low }));
+    }
+
+    /// Get what the session's media player is playing
+    pub fn media(&self) -> Option<MediaInfo> {
+        self.media_rx.borrow().clone()
+    }
+
+    /// Update the session's media (initial fetch and MediaChanged events)
+    pub fn set_media(&self, media: Option<MediaInfo>) {
+        let _ = self.media_tx.send(media);
+    }
+
+    /// Queue a command for shepherdd, e.g. from a button
+    pub fn send_command(&self, command: Command) {
+        let _ = self.command_tx.send(command);
+    }
+
+    /// Take the queued commands, to send them on the event connection.
+    /// Only the first caller gets them.
+    pub fn take_commands(&self) -> Option<mpsc::UnboundedReceiver<Command>> {
+        self.command_rx.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+
+    /// Subscribe to theme changes
+    pub fn subscribe_theme(&self) -> watch::Receiver<Theme> {
+        self.theme_rx.clone()
+    }
+
+    fn set_theme(&self, theme: Theme) {
+        self.theme_tx.send_if_modified(|current| {
+            let changed = *current != theme;
+            *current = theme;
+            changed
+        });
+    }
+
     /// Update volume from VolumeChanged event (preserves restrictions from initial fetch)
     fn update_volume(&self, percent: u8, muted: bool) {
         self.volume_tx.send_modify(|vol| {
@@ -130,22 +267,63 @@ impl SharedState {
                     available: true,
                     backend: None,
                     restrictions: VolumeRestrictions::unrestricted(),
+                    output: Default::default(),
                 });
             }
         });
     }
 
-    /// Update time remaining for current session
-    #[allow(dead_code)]
-    pub fn update_time_remaining(&self, remaining_secs: u64) {
-        self.session_tx.send_modify(|state| {
-            if let SessionState::Active {
+    /// Update the slider range and allowed actions, e.g. when quiet hours start
+    fn update_volume_restrictions(&self, restrictions: VolumeRestrictions) {
+        self.volume_tx.send_modify(|vol| {
+            if let Some(v) = vol {
+                v.restrictions = restrictions;
+            }
+        });
+    }
+
+    /// Take the time left in `session_id` from the service, replacing the
+    /// local countdown. A warning is cleared once an extension brings the
+    /// time back above its threshold.
+    fn update_time_remaining(&self, session_id: &SessionId, remaining: Duration) {
+        let remaining_secs = remaining.as_secs();
+        self.session_tx.send_modify(|state| match state {
+            SessionState::Active {
+                session_id: sid,
                 time_remaining_secs,
+                synced_at,
                 ..
-            } = state
-            {
+            } if sid == session_id => {
                 *time_remaining_secs = Some(remaining_secs);
+                *synced_at = Instant::now();
             }
+            SessionState::Warning {
+                session_id: sid,
+                entry_id,
+                entry_name,
+                threshold_secs,
+                device_access,
+                ..
+            } if sid == session_id && remaining_secs > *threshold_secs => {
+                *state = SessionState::Active {
+                    session_id: session_id.clone(),
+                    entry_id: entry_id.clone(),
+                    entry_name: entry_name.clone(),
+                    time_remaining_secs: Some(remaining_secs),
+                    synced_at: Instant::now(),
+                    device_access: *device_access,
+                };
+            }
+            SessionState::Warning {
+                session_id: sid,
+                time_remaining_secs,
+                synced_at,
+                ..
+            } if sid == session_id => {
+                *time_remaining_secs = remaining_secs;
+                *synced_at = Instant::now();
+            }
+            _ => {}
         });
     }
 
@@ -157,6 +335,7 @@ impl SharedState {
                 entry_id,
                 label,
                 deadline,
+                device_access,
             } => {
                 let now = shepherd_util::now();
                 // For unlimited sessions (deadline=None), time_remaining is None
@@ -171,12 +350,20 @@ impl SharedState {
                     session_id: session_id.clone(),
                     entry_id: entry_id.clone(),
                     entry_name: label.clone(),
-                    started_at: std::time::Instant::now(),
-                    time_limit_secs: time_remaining,
                     time_remaining_secs: time_remaining,
+                    synced_at: Instant::now(),
+                    device_access: *device_access,
                 });
             }
 
+            EventPayload::TimeRemaining {
+                session_id,
+                time_remaining,
+                ..
+            } => {
+                self.update_time_remaining(session_id, *time_remaining);
+            }
+
             EventPayload::SessionEnded { session_id, .. } => {
                 if self.session_state().session_id() == Some(session_id) {
                     self.set_session_state(SessionState::NoSession);
@@ -185,10 +372,10 @@ impl SharedState {
 
             EventPayload::WarningIssued {
                 session_id,
+                threshold_seconds,
                 time_remaining,
                 message,
                 severity,
-                ..
             } => {
                 self.session_tx.send_modify(|state| {
                     // Handle transition from Active state
@@ -196,6 +383,7 @@ impl SharedState {
                         session_id: sid,
                         entry_id,
                         entry_name,
+                        device_access,
                         ..
                     } = state
                     {
@@ -204,10 +392,12 @@ impl SharedState {
                                 session_id: session_id.clone(),
                                 entry_id: entry_id.clone(),
                                 entry_name: entry_name.clone(),
-                                warning_issued_at: std::time::Instant::now(),
-                                time_remaining_at_warning: time_remaining.as_secs(),
+                                time_remaining_secs: time_remaining.as_secs(),
+                                synced_at: Instant::now(),
+                                threshold_secs: *threshold_seconds,
                                 message: message.clone(),
                                 severity: *severity,
+                                device_access: *device_access,
                             };
                         }
                     }
@@ -216,6 +406,7 @@ impl SharedState {
                         session_id: sid,
                         entry_id,
                         entry_name,
+                        device_access,
                         ..
                     } = state
                         && sid == session_id {
@@ -223,10 +414,12 @@ impl SharedState {
                                 session_id: session_id.clone(),
                                 entry_id: entry_id.clone(),
                                 entry_name: entry_name.clone(),
-                                warning_issued_at: std::time::Instant::now(),
-                                time_remaining_at_warning: time_remaining.as_secs(),
+                                time_remaining_secs: time_remaining.as_secs(),
+                                synced_at: Instant::now(),
+                                threshold_secs: *threshold_seconds,
                                 message: message.clone(),
                                 severity: *severity,
+                                device_access: *device_access,
                             };
                         }
                 });
@@ -236,29 +429,22 @@ impl SharedState {
                 if self.session_state().session_id() == Some(session_id) {
                     self.set_session_state(SessionState::Ending {
                         session_id: session_id.clone(),
-                        reason: "Time expired".to_string(),
+                        reason: SessionEndReason::Expired.message(Locale::from_env()),
                     });
                 }
             }
 
             EventPayload::StateChanged(snapshot) => {
+                self.set_theme(snapshot.theme);
                 if let Some(session) = &snapshot.current_session {
-                    let now = shepherd_util::now();
-                    // For unlimited sessions (deadline=None), time_remaining is None
-                    let time_remaining = session.deadline.map(|d| {
-                        if d > now {
-                            (d - now).num_seconds().max(0) as u64
-                        } else {
-                            0
-                        }
-                    });
+                    // The service's count, not the deadline against our clock
                     self.set_session_state(SessionState::Active {
                         session_id: session.session_id.clone(),
                         entry_id: session.entry_id.clone(),
                         entry_name: session.label.clone(),
-                        started_at: std::time::Instant::now(),
-                        time_limit_secs: time_remaining,
-                        time_remaining_secs: time_remaining,
+                        time_remaining_secs: session.time_remaining.map(|t| t.as_secs()),
+                        synced_at: Instant::now(),
+                        device_access: session.device_access,
                     });
                 } else {
                     self.set_session_state(SessionState::NoSession);
@@ -269,6 +455,22 @@ impl SharedState {
                 self.update_volume(*percent, *muted);
             }
 
+            EventPayload::VolumeRestrictionsChanged { restrictions } => {
+                self.update_volume_restrictions(restrictions.clone());
+            }
+
+            EventPayload::BatteryStatus { battery, low } => {
+                self.set_battery(*battery, *low);
+            }
+
+            EventPayload::MediaChanged { media } => {
+                self.set_media(media.clone());
+            }
+
+            EventPayload::ThemeChanged { theme } => {
+                self.set_theme(*theme);
+            }
+
             _ => {}
         }
     }
@@ -104,6 +104,7 @@ mod tests {
             available: true,
             backend: Some("test".into()),
             restrictions: VolumeRestrictions::unrestricted(),
+            output: Default::default(),
         };
         assert_eq!(info.icon_name(), "audio-volume-muted-symbolic");
 
@@ -113,6 +114,7 @@ mod tests {
             available: true,
             backend: Some("test".into()),
             restrictions: VolumeRestrictions::unrestricted(),
+            output: Default::default(),
         };
         assert_eq!(info.icon_name(), "audio-volume-medium-symbolic");
 
@@ -122,6 +124,7 @@ mod tests {
             available: true,
             backend: Some("test".into()),
             restrictions: VolumeRestrictions::unrestricted(),
+            output: Default::default(),
         };
         assert_eq!(info.icon_name(), "audio-volume-muted-symbolic");
     }
@@ -0,0 +1,115 @@
+//! X11 placement
+//!
+//! X11 has no layer-shell, so on an X11 display the HUD is an undecorated
+//! window that `wmctrl` keeps above other windows on every workspace,
+//! stretched along the anchored screen edge. A `_NET_WM_STRUT_PARTIAL`
+//! reserves its space so maximized windows stay clear of it, like the
+//! layer-shell exclusive zone.
+
+use gtk4::gdk;
+use gtk4::prelude::*;
+use std::process::Command;
+
+/// Window title the placement commands look the HUD up by
+pub const WINDOW_TITLE: &str = "shepherd-hud";
+
+/// Whether GTK is running on an X11 display
+pub fn is_x11_display() -> bool {
+    gdk::Display::default().is_some_and(|display| display.type_().name() == "GdkX11Display")
+}
+
+/// Pin the mapped HUD window to the top or bottom edge of its monitor
+pub fn place_window(window: &gtk4::ApplicationWindow, anchor: &str, height: i32) {
+    let Some(geometry) = monitor_geometry(window) else {
+        tracing::warn!("No monitor found for the HUD window");
+        return;
+    };
+    let bottom = anchor == "bottom";
+    let y = if bottom {
+        geometry.y() + geometry.height() - height
+    } else {
+        geometry.y()
+    };
+
+    run(
+        "wmctrl",
+        &["-r", WINDOW_TITLE, "-b", "add,above,sticky,skip_taskbar,skip_pager"],
+    );
+    run(
+        "wmctrl",
+        &[
+            "-r",
+            WINDOW_TITLE,
+            "-e",
+            &format!("0,{},{},{},{}", geometry.x(), y, geometry.width(), height),
+        ],
+    );
+    run(
+        "xprop",
+        &[
+            "-name",
+            WINDOW_TITLE,
+            "-f",
+            "_NET_WM_STRUT_PARTIAL",
+            "32c",
+            "-set",
+            "_NET_WM_STRUT_PARTIAL",
+            &strut_partial(bottom, geometry.x(), geometry.width(), height),
+        ],
+    );
+}
+
+fn monitor_geometry(window: &gtk4::ApplicationWindow) -> Option<gdk::Rectangle> {
+    let display = WidgetExt::display(window);
+    let monitor = window
+        .surface()
+        .and_then(|surface| display.monitor_at_surface(&surface))
+        .or_else(|| {
+            display
+                .monitors()
+                .item(0)
+                .and_then(|item| item.downcast::<gdk::Monitor>().ok())
+        })?;
+    Some(monitor.geometry())
+}
+
+/// `_NET_WM_STRUT_PARTIAL`: left, right, top, bottom, then the start and
+/// end of each side's span
+fn strut_partial(bottom: bool, x: i32, width: i32, height: i32) -> String {
+    let (top, bottom_size) = if bottom { (0, height) } else { (height, 0) };
+    let (top_span, bottom_span) = if bottom {
+        ((0, 0), (x, x + width - 1))
+    } else {
+        ((x, x + width - 1), (0, 0))
+    };
+    format!(
+        "0, 0, {}, {}, 0, 0, 0, 0, {}, {}, {}, {}",
+        top, bottom_size, top_span.0, top_span.1, bottom_span.0, bottom_span.1
+    )
+}
+
+fn run(program: &str, args: &[&str]) {
+    match Command::new(program).args(args).output() {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => tracing::warn!(
+            program,
+            stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+            "HUD placement command failed"
+        ),
+        Err(e) => tracing::warn!(program, error = %e, "Failed to run HUD placement command"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strut_partial() {
+        assert_eq!(strut_partial(false, 0, 1920, 48), "0, 0, 48, 0, 0, 0, 0, 0, 0, 1919, 0, 0");
+        assert_eq!(
+            strut_partial(true, 1920, 1280, 40),
+            "0, 0, 0, 40, 0, 0, 0, 0, 0, 0, 1920, 3199"
+        );
+    }
+}
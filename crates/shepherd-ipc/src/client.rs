@@ -1,9 +1,11 @@
 //! IPC client implementation
 
-use shepherd_api::{Command, Event, Request, Response, ResponseResult};
+use shepherd_api::{ClientKind, ClientRole, Command, Event, EventPayload, Request, Response, ResponsePayload, ResponseResult};
+use shepherd_util::SeatId;
 use std::path::Path;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::UnixStream;
+use tokio::net::unix::OwnedWriteHalf;
 
 use crate::{IpcError, IpcResult};
 
@@ -18,13 +20,19 @@ impl IpcClient {
     /// Connect to shepherdd
     pub async fn connect(socket_path: impl AsRef<Path>) -> IpcResult<Self> {
         let stream = UnixStream::connect(socket_path).await?;
+        Ok(Self::from_stream(stream))
+    }
+
+    /// Talk over an already connected stream, e.g. one from
+    /// [`IpcServer::connect_local`](crate::IpcServer::connect_local)
+    pub fn from_stream(stream: UnixStream) -> Self {
         let (read_half, write_half) = stream.into_split();
 
-        Ok(Self {
+        Self {
             reader: BufReader::new(read_half),
             writer: write_half,
             next_request_id: 1,
-        })
+        }
     }
 
     /// Send a command and wait for response
@@ -50,8 +58,25 @@ impl IpcClient {
         Ok(response)
     }
 
+    /// Tell shepherdd which program this is, taking over from an older
+    /// instance still connected on the same seat. Returns the connection's
+    /// role from now on.
+    pub async fn identify(&mut self, kind: ClientKind, seat: Option<SeatId>) -> IpcResult<ClientRole> {
+        match self.send(Command::Identify { kind, seat }).await?.result {
+            ResponseResult::Ok(ResponsePayload::Identified { role }) => Ok(role),
+            ResponseResult::Ok(other) => Err(IpcError::InvalidMessage(format!("Unexpected Identify reply: {:?}", other))),
+            ResponseResult::Err(e) => Err(IpcError::ServerError(e.message)),
+        }
+    }
+
     /// Subscribe to events and consume this client to return an event stream
-    pub async fn subscribe(mut self) -> IpcResult<EventStream> {
+    pub async fn subscribe(self) -> IpcResult<EventStream> {
+        Ok(self.subscribe_with_commands().await?.0)
+    }
+
+    /// Subscribe to events, keeping the connection open for commands. Their
+    /// responses arrive on the event stream, in between events.
+    pub async fn subscribe_with_commands(mut self) -> IpcResult<(EventStream, CommandSender)> {
         let response = self.send(Command::SubscribeEvents).await?;
 
         match response.result {
@@ -61,33 +86,162 @@ impl IpcClient {
             }
         }
 
-        Ok(EventStream {
+        let stream = EventStream {
             reader: self.reader,
-        })
+            line: Vec::new(),
+        };
+        let sender = CommandSender {
+            writer: self.writer,
+            next_request_id: self.next_request_id,
+        };
+        Ok((stream, sender))
     }
 }
 
+/// Whether the event says a newer instance took over from this process,
+/// which should then exit
+pub fn replaced_this_process(event: &Event) -> bool {
+    matches!(event.payload, EventPayload::ClientReplaced { pid, .. } if pid == std::process::id())
+}
+
+/// Sends commands on a subscribed connection
+pub struct CommandSender {
+    writer: OwnedWriteHalf,
+    next_request_id: u64,
+}
+
+impl CommandSender {
+    /// Send a command without waiting for its response, which comes as a
+    /// [`StreamMessage::Response`] with the returned request ID
+    pub async fn send(&mut self, command: Command) -> IpcResult<u64> {
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+
+        let mut json = serde_json::to_string(&Request::new(request_id, command))?;
+        json.push('\n');
+        self.writer.write_all(json.as_bytes()).await?;
+        Ok(request_id)
+    }
+}
+
+/// A message on a subscribed connection
+#[derive(Debug)]
+pub enum StreamMessage {
+    Event(Event),
+    /// Response to a command sent with [`CommandSender`]
+    Response(Response),
+}
+
 /// Stream of events from shepherdd
 pub struct EventStream {
     reader: BufReader<tokio::net::unix::OwnedReadHalf>,
+    /// The line being read, kept across calls so a read cancelled by
+    /// `select!` doesn't lose what it had read
+    line: Vec<u8>,
 }
 
 impl EventStream {
-    /// Wait for the next event
+    /// Wait for the next event, skipping command responses
     pub async fn next(&mut self) -> IpcResult<Event> {
-        let mut line = String::new();
-        let n = self.reader.read_line(&mut line).await?;
-        if n == 0 {
-            return Err(IpcError::ConnectionClosed);
+        loop {
+            if let StreamMessage::Event(event) = self.next_message().await? {
+                return Ok(event);
+            }
         }
+    }
 
-        let event: Event = serde_json::from_str(line.trim())?;
-        Ok(event)
+    /// Wait for the next event or command response. Cancel safe.
+    pub async fn next_message(&mut self) -> IpcResult<StreamMessage> {
+        let n = self.reader.read_until(b'\n', &mut self.line).await?;
+        if n == 0 && !self.line.ends_with(b"\n") {
+            return Err(IpcError::ConnectionClosed);
+        }
+        let line = std::mem::take(&mut self.line);
+
+        // Only responses carry a request ID at the top level
+        let value: serde_json::Value = serde_json::from_slice(&line)?;
+        if value.get("request_id").is_some() {
+            Ok(StreamMessage::Response(serde_json::from_value(value)?))
+        } else {
+            Ok(StreamMessage::Event(serde_json::from_value(value)?))
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    // Client tests would require a running server
-    // See integration tests
+    use super::*;
+    use tokio::net::UnixListener;
+
+    fn line(value: &impl serde::Serialize) -> Vec<u8> {
+        let mut json = serde_json::to_vec(value).unwrap();
+        json.push(b'\n');
+        json
+    }
+
+    #[test]
+    fn test_replaced_this_process() {
+        let replaced = |pid| {
+            Event::new(EventPayload::ClientReplaced {
+                kind: ClientKind::Launcher,
+                pid,
+                by_pid: 1,
+            })
+        };
+        assert!(replaced_this_process(&replaced(std::process::id())));
+        assert!(!replaced_this_process(&replaced(std::process::id() + 1)));
+        assert!(!replaced_this_process(&Event::new(EventPayload::PowerActionCancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_commands_on_subscribed_connection() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("test.sock");
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        // A server that acknowledges the subscription, then answers one
+        // command after sending an event
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read_half, mut write_half) = stream.into_split();
+            let mut requests = BufReader::new(read_half).lines();
+
+            let mut next_request = async || -> Request {
+                serde_json::from_str(&requests.next_line().await.unwrap().unwrap()).unwrap()
+            };
+
+            let subscribe = next_request().await;
+            let client_id = shepherd_util::ClientId::new();
+            let ack = Response::success(subscribe.request_id, ResponsePayload::Subscribed { client_id });
+            write_half.write_all(&line(&ack)).await.unwrap();
+
+            let request = next_request().await;
+            assert!(matches!(request.command, Command::StopCurrent { .. }));
+            let event = Event::new(EventPayload::PowerActionCancelled);
+            write_half.write_all(&line(&event)).await.unwrap();
+            let stopped = Response::success(request.request_id, ResponsePayload::Stopped);
+            write_half.write_all(&line(&stopped)).await.unwrap();
+        });
+
+        let client = IpcClient::connect(&socket_path).await.unwrap();
+        let (mut stream, mut sender) = client.subscribe_with_commands().await.unwrap();
+        let request_id = sender
+            .send(Command::StopCurrent {
+                mode: shepherd_api::StopMode::Graceful,
+                seat: None,
+            })
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            stream.next_message().await.unwrap(),
+            StreamMessage::Event(Event { payload: EventPayload::PowerActionCancelled, .. })
+        ));
+        match stream.next_message().await.unwrap() {
+            StreamMessage::Response(response) => assert_eq!(response.request_id, request_id),
+            other => panic!("expected a response, got {:?}", other),
+        }
+        server.await.unwrap();
+        assert!(matches!(stream.next_message().await, Err(IpcError::ConnectionClosed)));
+    }
 }
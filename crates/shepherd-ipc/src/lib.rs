@@ -4,7 +4,7 @@
 //! - Unix domain socket server
 //! - NDJSON (newline-delimited JSON) protocol
 //! - Client connection management
-//! - Peer UID authentication
+//! - Peer UID and executable authentication
 
 mod client;
 mod server;
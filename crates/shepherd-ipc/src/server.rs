@@ -1,8 +1,8 @@
 //! IPC server implementation
 
-use shepherd_api::{ClientInfo, ClientRole, Event, Request, Response};
-use shepherd_util::ClientId;
-use std::collections::HashMap;
+use shepherd_api::{ClientInfo, ClientKind, ClientRole, Event, Request, Response};
+use shepherd_util::{ClientId, SeatId};
+use std::collections::{HashMap, HashSet};
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -34,8 +34,21 @@ pub struct IpcServer {
     listener: Option<UnixListener>,
     clients: Arc<RwLock<HashMap<ClientId, ClientHandle>>>,
     event_tx: broadcast::Sender<Event>,
+    /// Sequence number of the last broadcast event
+    last_seq: std::sync::Mutex<u64>,
     message_tx: mpsc::UnboundedSender<ServerMessage>,
     message_rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<ServerMessage>>>>,
+    shell_executables: Vec<PathBuf>,
+    /// Processes a newer instance took over from; they only observe
+    replaced_pids: std::sync::Mutex<HashSet<u32>>,
+}
+
+/// An older client instance that a newer one took over from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientReplaced {
+    pub kind: ClientKind,
+    pub pid: u32,
+    pub by_pid: u32,
 }
 
 struct ClientHandle {
@@ -55,11 +68,28 @@ impl IpcServer {
             listener: None,
             clients: Arc::new(RwLock::new(HashMap::new())),
             event_tx,
+            last_seq: std::sync::Mutex::new(0),
             message_tx,
             message_rx: Arc::new(Mutex::new(Some(message_rx))),
+            shell_executables: Vec::new(),
+            replaced_pids: std::sync::Mutex::new(HashSet::new()),
         }
     }
 
+    /// Only grant the Shell role to clients running one of these programs.
+    ///
+    /// Other clients of the child's UID connect as read-only observers, so
+    /// an arbitrary program can't pose as the launcher. An empty list
+    /// trusts every non-admin UID as a shell.
+    pub fn with_shell_executables(mut self, paths: Vec<PathBuf>) -> Self {
+        // /proc/<pid>/exe is always canonical, so compare against the real path
+        self.shell_executables = paths
+            .into_iter()
+            .map(|path| std::fs::canonicalize(&path).unwrap_or(path))
+            .collect();
+        self
+    }
+
     /// Start listening
     pub async fn start(&mut self) -> IpcResult<()> {
         // Remove existing socket if present
@@ -102,23 +132,42 @@ impl IpcServer {
                     let client_id = ClientId::new();
 
                     // Get peer credentials
-                    let uid = get_peer_uid(&stream);
-
-                    // Determine role based on UID
-                    let role = match uid {
-                        Some(0) => ClientRole::Admin, // root
-                        Some(u) if u == nix::unistd::getuid().as_raw() => ClientRole::Admin,
-                        _ => ClientRole::Shell,
+                    let (uid, pid, exe) = match get_peer_credentials(&stream) {
+                        Some((uid, pid)) => (Some(uid), u32::try_from(pid).ok(), peer_executable(pid)),
+                        None => (None, None, None),
                     };
 
-                    let info = ClientInfo::new(role);
-                    let info = if let Some(u) = uid {
-                        info.with_uid(u)
-                    } else {
-                        info
-                    };
+                    let mut role = peer_role(
+                        uid,
+                        exe.as_deref(),
+                        nix::unistd::getuid().as_raw(),
+                        &self.shell_executables,
+                    );
+                    if let Some(pid) = pid
+                        && self.was_replaced(pid)
+                    {
+                        warn!(client_id = %client_id, pid, "Client was replaced by a newer instance, connecting as observer");
+                        role = ClientRole::Observer;
+                    } else if role == ClientRole::Observer {
+                        warn!(
+                            client_id = %client_id,
+                            uid = ?uid,
+                            exe = ?exe,
+                            "Client is not an allowed shell executable, connecting as observer"
+                        );
+                    }
+
+                    let mut info = ClientInfo::new(role);
+                    if let Some(u) = uid {
+                        info = info.with_uid(u);
+                    }
+                    info.pid = pid;
+                    info.process_name = exe
+                        .as_deref()
+                        .and_then(Path::file_name)
+                        .map(|name| name.to_string_lossy().into_owned());
 
-                    info!(client_id = %client_id, uid = ?uid, role = ?role, "Client connected");
+                    info!(client_id = %client_id, uid = ?uid, exe = ?exe, role = ?role, "Client connected");
 
                     self.handle_client(stream, client_id, info).await;
                 }
@@ -230,12 +279,14 @@ impl IpcServer {
 
                     // Handle events (for subscribed clients)
                     Ok(event) = event_rx.recv() => {
-                        let is_subscribed = {
+                        let wanted = {
                             let clients = clients_writer.read().await;
-                            clients.get(&client_id_writer).map(|h| h.subscribed).unwrap_or(false)
+                            clients
+                                .get(&client_id_writer)
+                                .is_some_and(|h| h.subscribed && receives(&h.info, &event))
                         };
 
-                        if is_subscribed
+                        if wanted
                             && let Ok(json) = serde_json::to_string(&event) {
                                 let mut msg = json;
                                 msg.push('\n');
@@ -274,8 +325,13 @@ impl IpcServer {
         Ok(())
     }
 
-    /// Broadcast an event to all subscribed clients
-    pub fn broadcast_event(&self, event: Event) {
+    /// Number an event and broadcast it to all subscribed clients
+    pub fn broadcast_event(&self, mut event: Event) {
+        // Held until sent, so clients receive events in sequence order
+        let mut last_seq = self.last_seq.lock().unwrap_or_else(|e| e.into_inner());
+        *last_seq += 1;
+        event.seq = *last_seq;
+        debug!(seq = event.seq, correlation = ?event.correlation, "Broadcasting event");
         let _ = self.event_tx.send(event);
     }
 
@@ -285,6 +341,79 @@ impl IpcServer {
         clients.get(client_id).map(|h| h.info.clone())
     }
 
+    /// Record which program a client is. A newer instance takes over: every
+    /// connection of an older process doing an overlapping job becomes an
+    /// observer, and so does any connection it opens later.
+    ///
+    /// Observers can't take over, so neither an impostor nor a replaced
+    /// instance that reconnects demotes the current one. Instances on
+    /// different seats don't overlap.
+    pub async fn identify(
+        &self,
+        client_id: &ClientId,
+        kind: ClientKind,
+        seat: Option<SeatId>,
+    ) -> Vec<ClientReplaced> {
+        let mut clients = self.clients.write().await;
+        let Some(handle) = clients.get_mut(client_id) else {
+            return Vec::new();
+        };
+        handle.info.kind = Some(kind);
+        handle.info.seat = seat;
+        let seat = handle.info.seat.clone().unwrap_or_default();
+        let (Some(by_pid), true) = (handle.info.pid, handle.info.role != ClientRole::Observer) else {
+            return Vec::new();
+        };
+
+        let mut replaced: Vec<ClientReplaced> = Vec::new();
+        for handle in clients.values() {
+            if let (Some(pid), Some(other)) = (handle.info.pid, handle.info.kind)
+                && pid != by_pid
+                && other.overlaps(kind)
+                && handle.info.seat.clone().unwrap_or_default() == seat
+                && handle.info.role != ClientRole::Observer
+                && !replaced.iter().any(|r| r.pid == pid)
+            {
+                replaced.push(ClientReplaced { kind: other, pid, by_pid });
+            }
+        }
+        if replaced.is_empty() {
+            return replaced;
+        }
+
+        for handle in clients.values_mut() {
+            if handle.info.pid.is_some_and(|pid| replaced.iter().any(|r| r.pid == pid)) {
+                handle.info.role = ClientRole::Observer;
+            }
+        }
+        let mut replaced_pids = self.replaced_pids.lock().unwrap_or_else(|e| e.into_inner());
+        replaced_pids.extend(replaced.iter().map(|r| r.pid));
+        replaced
+    }
+
+    /// Whether a newer instance took over from this process. Exited
+    /// processes are forgotten, so a reused PID isn't held against the
+    /// next program.
+    fn was_replaced(&self, pid: u32) -> bool {
+        let mut replaced_pids = self.replaced_pids.lock().unwrap_or_else(|e| e.into_inner());
+        replaced_pids.retain(|pid| Path::new(&format!("/proc/{}", pid)).exists());
+        replaced_pids.contains(&pid)
+    }
+
+    /// Connect a client from inside this process. It gets `info` as its
+    /// identity instead of one worked out from socket credentials.
+    pub async fn connect_local(&self, info: ClientInfo) -> IpcResult<(ClientId, UnixStream)> {
+        let (ours, theirs) = UnixStream::pair()?;
+        let client_id = info.client_id.clone();
+        self.handle_client(ours, client_id.clone(), info).await;
+        Ok((client_id, theirs))
+    }
+
+    /// Info on every connected client
+    pub async fn clients(&self) -> Vec<ClientInfo> {
+        self.clients.read().await.values().map(|h| h.info.clone()).collect()
+    }
+
     /// Get connected client count
     pub async fn client_count(&self) -> usize {
         self.clients.read().await.len()
@@ -304,19 +433,69 @@ impl Drop for IpcServer {
     }
 }
 
-/// Get peer UID from Unix socket
-fn get_peer_uid(stream: &UnixStream) -> Option<u32> {
+/// Whether an event is for this client. Events about one seat go to the
+/// clients on it; admins that didn't pick a seat see every seat.
+fn receives(info: &ClientInfo, event: &Event) -> bool {
+    match (&event.seat, &info.seat) {
+        (None, _) => true,
+        (Some(seat), Some(own)) => seat == own,
+        (Some(seat), None) => info.role == ClientRole::Admin || seat.is_default(),
+    }
+}
+
+/// Get peer UID and PID from Unix socket
+fn get_peer_credentials(stream: &UnixStream) -> Option<(u32, i32)> {
     use std::os::unix::io::AsFd;
 
     // Get the borrowed file descriptor from the stream
     let fd = stream.as_fd();
 
     match nix::sys::socket::getsockopt(&fd, nix::sys::socket::sockopt::PeerCredentials) {
-        Ok(cred) => Some(cred.uid()),
+        Ok(cred) => Some((cred.uid(), cred.pid())),
         Err(_) => None,
     }
 }
 
+/// Executable of the process that connected.
+///
+/// The PID is the one recorded at connect time, so the process must still
+/// be alive; a client that exits straight away reads as unknown.
+fn peer_executable(pid: i32) -> Option<PathBuf> {
+    let exe = std::fs::read_link(format!("/proc/{}/exe", pid)).ok()?;
+    Some(strip_deleted(exe))
+}
+
+/// The kernel marks a binary replaced on disk (e.g. by a package upgrade
+/// while the launcher runs) with a " (deleted)" suffix
+fn strip_deleted(exe: PathBuf) -> PathBuf {
+    match exe.to_str().and_then(|s| s.strip_suffix(" (deleted)")) {
+        Some(path) => PathBuf::from(path),
+        None => exe,
+    }
+}
+
+/// Role for a peer: root and the service's own user are admins; anyone
+/// else is a shell if it runs an allowed executable, or when no allow-list
+/// is configured, and an observer otherwise
+fn peer_role(
+    uid: Option<u32>,
+    exe: Option<&Path>,
+    service_uid: u32,
+    shell_executables: &[PathBuf],
+) -> ClientRole {
+    match uid {
+        Some(0) => ClientRole::Admin,
+        Some(u) if u == service_uid => ClientRole::Admin,
+        _ if shell_executables.is_empty() => ClientRole::Shell,
+        _ => match exe {
+            Some(exe) if shell_executables.iter().any(|allowed| allowed == exe) => {
+                ClientRole::Shell
+            }
+            _ => ClientRole::Observer,
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -332,4 +511,151 @@ mod tests {
 
         assert!(socket_path.exists());
     }
+
+    #[tokio::test]
+    async fn test_broadcast_numbers_events() {
+        let server = IpcServer::new("/nonexistent/test.sock");
+        let mut rx = server.event_tx.subscribe();
+
+        server.broadcast_event(Event::new(shepherd_api::EventPayload::PowerActionCancelled));
+        server.broadcast_event(Event::new(shepherd_api::EventPayload::PowerActionCancelled));
+
+        assert_eq!(rx.recv().await.unwrap().seq, 1);
+        assert_eq!(rx.recv().await.unwrap().seq, 2);
+    }
+
+    /// Register a client of process `pid` on one end of a socket pair
+    async fn connect(server: &IpcServer, pid: u32, role: ClientRole) -> (ClientId, UnixStream) {
+        let mut info = ClientInfo::new(role);
+        info.pid = Some(pid);
+        server.connect_local(info).await.unwrap()
+    }
+
+    async fn role(server: &IpcServer, client_id: &ClientId) -> ClientRole {
+        server.get_client_info(client_id).await.unwrap().role
+    }
+
+    #[tokio::test]
+    async fn test_newer_instance_takes_over() {
+        let server = IpcServer::new("/nonexistent/test.sock");
+        let (old_events, _a) = connect(&server, 100, ClientRole::Shell).await;
+        let (old_launch, _b) = connect(&server, 100, ClientRole::Shell).await;
+        let (hud, _c) = connect(&server, 200, ClientRole::Shell).await;
+        let (new, _d) = connect(&server, 300, ClientRole::Shell).await;
+
+        assert!(server.identify(&old_events, ClientKind::Launcher, None).await.is_empty());
+        assert!(server.identify(&hud, ClientKind::Hud, None).await.is_empty());
+
+        let replaced = server.identify(&new, ClientKind::Launcher, None).await;
+        assert_eq!(
+            replaced,
+            vec![ClientReplaced { kind: ClientKind::Launcher, pid: 100, by_pid: 300 }]
+        );
+        // Every connection of the old process, identified or not
+        assert_eq!(role(&server, &old_events).await, ClientRole::Observer);
+        assert_eq!(role(&server, &old_launch).await, ClientRole::Observer);
+        assert_eq!(role(&server, &hud).await, ClientRole::Shell);
+        assert_eq!(role(&server, &new).await, ClientRole::Shell);
+
+        // The replaced instance can't take over again
+        assert!(server.identify(&old_events, ClientKind::Launcher, None).await.is_empty());
+        assert_eq!(role(&server, &new).await, ClientRole::Shell);
+
+        // A shell replaces the separate HUD too
+        let (shell, _e) = connect(&server, 400, ClientRole::Shell).await;
+        let replaced = server.identify(&shell, ClientKind::Shell, None).await;
+        let pids: Vec<u32> = replaced.iter().map(|r| r.pid).collect();
+        assert_eq!(pids.len(), 2);
+        assert!(pids.contains(&200) && pids.contains(&300));
+    }
+
+    #[tokio::test]
+    async fn test_observer_cannot_take_over() {
+        let server = IpcServer::new("/nonexistent/test.sock");
+        let (launcher, _a) = connect(&server, 100, ClientRole::Shell).await;
+        let (impostor, _b) = connect(&server, 200, ClientRole::Observer).await;
+
+        server.identify(&launcher, ClientKind::Launcher, None).await;
+        assert!(server.identify(&impostor, ClientKind::Launcher, None).await.is_empty());
+        assert_eq!(role(&server, &launcher).await, ClientRole::Shell);
+    }
+
+    #[tokio::test]
+    async fn test_seats_dont_overlap() {
+        let server = IpcServer::new("/nonexistent/test.sock");
+        let (first, _a) = connect(&server, 100, ClientRole::Shell).await;
+        let (second, _b) = connect(&server, 200, ClientRole::Shell).await;
+
+        server.identify(&first, ClientKind::Launcher, None).await;
+        let den = Some(SeatId::new("den"));
+        assert!(server.identify(&second, ClientKind::Launcher, den).await.is_empty());
+        assert_eq!(role(&server, &first).await, ClientRole::Shell);
+
+        // Naming the default seat is the same as not naming one
+        let (third, _c) = connect(&server, 300, ClientRole::Shell).await;
+        let replaced = server.identify(&third, ClientKind::Launcher, Some(SeatId::default())).await;
+        assert_eq!(replaced.len(), 1);
+        assert_eq!(replaced[0].pid, 100);
+    }
+
+    #[test]
+    fn test_receives_own_seat() {
+        let den = SeatId::new("den");
+        let for_den = Event::new(shepherd_api::EventPayload::PowerActionCancelled)
+            .for_seat(Some(den.clone()));
+        let for_default = Event::new(shepherd_api::EventPayload::PowerActionCancelled)
+            .for_seat(Some(SeatId::default()));
+        let everyone = Event::new(shepherd_api::EventPayload::PowerActionCancelled);
+
+        let mut on_den = ClientInfo::new(ClientRole::Shell);
+        on_den.seat = Some(den);
+        assert!(receives(&on_den, &for_den));
+        assert!(!receives(&on_den, &for_default));
+        assert!(receives(&on_den, &everyone));
+
+        let unseated = ClientInfo::new(ClientRole::Shell);
+        assert!(!receives(&unseated, &for_den));
+        assert!(receives(&unseated, &for_default));
+
+        let admin = ClientInfo::new(ClientRole::Admin);
+        assert!(receives(&admin, &for_den));
+        assert!(receives(&admin, &for_default));
+    }
+
+    #[test]
+    fn test_peer_role() {
+        let launcher = PathBuf::from("/usr/bin/shepherd-launcher");
+        let allowed = vec![launcher.clone(), PathBuf::from("/usr/bin/shepherd-hud")];
+        let other = Path::new("/home/kid/impostor");
+
+        // Admins don't need to be on the list
+        assert_eq!(peer_role(Some(0), Some(other), 1000, &allowed), ClientRole::Admin);
+        assert_eq!(peer_role(Some(1000), Some(other), 1000, &allowed), ClientRole::Admin);
+
+        assert_eq!(peer_role(Some(1001), Some(&launcher), 1000, &allowed), ClientRole::Shell);
+        assert_eq!(peer_role(Some(1001), Some(other), 1000, &allowed), ClientRole::Observer);
+        assert_eq!(peer_role(Some(1001), None, 1000, &allowed), ClientRole::Observer);
+        assert_eq!(peer_role(None, None, 1000, &allowed), ClientRole::Observer);
+
+        // No list: UID only
+        assert_eq!(peer_role(Some(1001), Some(other), 1000, &[]), ClientRole::Shell);
+    }
+
+    #[test]
+    fn test_strip_deleted() {
+        assert_eq!(
+            strip_deleted(PathBuf::from("/usr/bin/shepherd-launcher (deleted)")),
+            PathBuf::from("/usr/bin/shepherd-launcher")
+        );
+        assert_eq!(
+            strip_deleted(PathBuf::from("/usr/bin/shepherd-launcher")),
+            PathBuf::from("/usr/bin/shepherd-launcher")
+        );
+    }
+
+    #[test]
+    fn test_peer_executable_of_self() {
+        let pid = std::process::id() as i32;
+        assert_eq!(peer_executable(pid), std::env::current_exe().ok());
+    }
 }
@@ -1,104 +1,40 @@
 //! Main GTK4 application for the launcher
 
+use chrono::{DateTime, Local, NaiveTime};
 use gtk4::glib;
 use gtk4::prelude::*;
-use std::path::PathBuf;
+use shepherd_api::{
+    DayOverview, EntryUsage, Mood, PowerAction, ResponseResult, SessionState, SessionSummary,
+};
+use shepherd_ui_theme::{Component, Theme, ThemeProvider};
+use shepherd_util::SeatId;
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
 use tokio::sync::mpsc;
 use tracing::{debug, error, info};
 
 use crate::client::{CommandClient, ServiceClient};
 use crate::grid::LauncherGrid;
+use crate::setup::SetupWizard;
 use crate::state::{LauncherState, SharedState};
 
-/// CSS styling for the launcher
-const LAUNCHER_CSS: &str = r#"
-window {
-    background-color: #1a1a2e;
-}
-
-.launcher-grid {
-    padding: 48px;
-}
-
-.launcher-tile {
-    background: #16213e;
-    background-color: #16213e;
-    border-radius: 16px;
-    padding: 16px;
-    min-width: 140px;
-    min-height: 140px;
-    border: 2px solid transparent;
-    transition: all 200ms ease;
-    color: #e0e0e0;
-    box-shadow: none;
-}
-
-.launcher-tile:hover {
-    background: #1f3460;
-    background-color: #1f3460;
-    border-color: #4a90d9;
-}
-
-.launcher-tile:active {
-    background: #0f3460;
-    background-color: #0f3460;
-}
-
-.launcher-tile:disabled {
-    opacity: 0.4;
-}
-
-.tile-label {
-    color: #e0e0e0;
-    font-size: 14px;
-    font-weight: 500;
-}
-
-.launcher-tile image {
-    -gtk-icon-style: regular;
-    color: #e0e0e0;
-}
-
-.status-label {
-    color: #888888;
-    font-size: 18px;
-}
-
-.error-label {
-    color: #ff6b6b;
-    font-size: 16px;
-}
-
-.launching-spinner {
-    min-width: 64px;
-    min-height: 64px;
-}
-
-.session-active-box {
-    padding: 48px;
-}
-
-.session-label {
-    color: #ffffff;
-    font-size: 24px;
-    font-weight: 600;
-}
-
-.session-sublabel {
-    color: #888888;
-    font-size: 16px;
-}
-"#;
+/// How long the grid sits untouched before "Your time today" comes up
+const TODAY_IDLE_AFTER: Duration = Duration::from_secs(120);
+/// How often the open "Your time today" view is refreshed
+const TODAY_REFRESH_SECONDS: u32 = 30;
 
 pub struct LauncherApp {
     socket_path: PathBuf,
+    seat: Option<SeatId>,
 }
 
 impl LauncherApp {
-    pub fn new(socket_path: PathBuf) -> Self {
-        Self { socket_path }
+    pub fn new(socket_path: PathBuf, seat: Option<SeatId>) -> Self {
+        Self { socket_path, seat }
     }
 
     pub fn run(&self) -> i32 {
@@ -107,23 +43,42 @@ impl LauncherApp {
             .build();
 
         let socket_path = self.socket_path.clone();
+        let seat = self.seat.clone();
 
         app.connect_activate(move |app| {
-            Self::build_ui(app, socket_path.clone());
+            Self::build_ui(app, socket_path.clone(), seat.clone());
         });
 
         app.run().into()
     }
 
-    fn build_ui(app: &gtk4::Application, socket_path: PathBuf) {
-        // Load CSS
-        let provider = gtk4::CssProvider::new();
-        provider.load_from_data(LAUNCHER_CSS);
-        gtk4::style_context_add_provider_for_display(
-            &gtk4::gdk::Display::default().expect("Could not get default display"),
-            &provider,
-            gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
-        );
+    fn build_ui(app: &gtk4::Application, socket_path: PathBuf, seat: Option<SeatId>) {
+        let state = SharedState::new();
+        state.load_entry_cache();
+        Self::build_window(app, &socket_path, seat.clone(), state.clone());
+
+        // Start shepherdd client in background thread (separate from GTK main loop)
+        // This ensures the tokio runtime is properly driven for event reception
+        let (_command_tx, command_rx) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime for event loop");
+            rt.block_on(async move {
+                let client = ServiceClient::new(socket_path, seat, state, command_rx);
+                client.run().await;
+            });
+        });
+    }
+
+    /// Build and show the launcher window. `state` is fed by whoever owns the
+    /// event connection: [`ServiceClient`] here, or the combined shell.
+    pub fn build_window(
+        app: &gtk4::Application,
+        socket_path: &Path,
+        seat: Option<SeatId>,
+        state: SharedState,
+    ) -> gtk4::ApplicationWindow {
+        // Styled in the default theme until the service says otherwise
+        let theme_provider = ThemeProvider::install(Component::Launcher, Theme::default());
 
         // Create main window
         let window = gtk4::ApplicationWindow::builder()
@@ -146,28 +101,52 @@ impl LauncherApp {
         let loading_view = Self::create_loading_view();
         let error_view = Self::create_error_view();
         let session_view = Self::create_session_view();
+        let confirm_view = Self::create_confirm_view();
         let disconnected_view = Self::create_disconnected_view();
+        let setup_wizard = SetupWizard::new();
 
         stack.add_named(&grid, Some("grid"));
         stack.add_named(&loading_view, Some("loading"));
         stack.add_named(&error_view.0, Some("error"));
         stack.add_named(&session_view.0, Some("session"));
+        stack.add_named(&confirm_view.0, Some("confirm"));
         stack.add_named(&disconnected_view.0, Some("disconnected"));
+        stack.add_named(&setup_wizard, Some("setup"));
+
+        // Curfew countdown floats above whichever view is showing
+        let power_view = Self::create_power_view();
+        let overlay = gtk4::Overlay::new();
+        overlay.set_child(Some(&stack));
+        overlay.add_overlay(&power_view.0);
+        let check_in_view = Self::create_check_in_view();
+        overlay.add_overlay(&check_in_view.0);
+        let profile_header = Self::create_profile_header();
+        overlay.add_overlay(&profile_header.0);
+        let hold_banner = Self::create_hold_banner();
+        overlay.add_overlay(&hold_banner.0);
+        let launch_error_banner = Self::create_hold_banner();
+        launch_error_banner.0.set_valign(gtk4::Align::End);
+        launch_error_banner.0.set_margin_bottom(24);
+        overlay.add_overlay(&launch_error_banner.0);
+        let summary_view = Self::create_summary_view();
+        overlay.add_overlay(&summary_view.0);
+        let offline_banner = Self::create_hold_banner();
+        offline_banner.1.set_text("Connecting...");
+        overlay.add_overlay(&offline_banner.0);
+        let today_button = Self::create_today_button();
+        overlay.add_overlay(&today_button);
+        let today_view = Self::create_today_view();
+        overlay.add_overlay(&today_view.0);
+
+        window.set_child(Some(&overlay));
 
-        window.set_child(Some(&stack));
-
-        // Create shared state
-        let state = SharedState::new();
         let state_receiver = state.subscribe();
 
         // Create tokio runtime for async operations
         let runtime = Arc::new(Runtime::new().expect("Failed to create tokio runtime"));
 
-        // Create command channel
-        let (_command_tx, command_rx) = mpsc::unbounded_channel();
-
         // Create command client for sending commands
-        let command_client = Arc::new(CommandClient::new(&socket_path));
+        let command_client = Arc::new(CommandClient::new(socket_path, seat));
 
         // Connect grid launch callback
         let cmd_client = command_client.clone();
@@ -175,6 +154,8 @@ impl LauncherApp {
         let rt = runtime.clone();
         grid.connect_launch(move |entry_id| {
             info!(entry_id = %entry_id, "Launch requested");
+            state_clone.set_launch_error(None);
+            state_clone.set_session_summary(None);
             state_clone.set(LauncherState::Launching {
                 entry_id: entry_id.to_string(),
             });
@@ -183,86 +164,55 @@ impl LauncherApp {
             let state = state_clone.clone();
             let entry_id = entry_id.clone();
             rt.spawn(async move {
-                match client.launch(&entry_id).await {
+                let response = client.launch(&entry_id).await;
+                Self::handle_launch_response(&client, &state, entry_id.to_string(), response).await;
+            });
+        });
+
+        // Confirm a held launch; the session id is read at click time so
+        // the answer always goes to the launch being shown
+        let cmd_client = command_client.clone();
+        let state_clone = state.clone();
+        let rt = runtime.clone();
+        confirm_view.3.connect_clicked(move |_| {
+            let LauncherState::ConfirmLaunch { session_id, entry_label, .. } = state_clone.get() else {
+                return;
+            };
+            info!(session_id = %session_id, "Launch confirmed");
+            state_clone.set(LauncherState::Launching {
+                entry_id: entry_label.clone(),
+            });
+
+            let client = cmd_client.clone();
+            let state = state_clone.clone();
+            rt.spawn(async move {
+                let response = client.confirm_launch(session_id).await;
+                Self::handle_launch_response(&client, &state, entry_label, response).await;
+            });
+        });
+
+        // Cancelling just drops the held launch; the daemon forgets it on its own
+        let cmd_client = command_client.clone();
+        let state_clone = state.clone();
+        let rt = runtime.clone();
+        confirm_view.4.connect_clicked(move |_| {
+            info!("Launch cancelled");
+            let client = cmd_client.clone();
+            let state = state_clone.clone();
+            rt.spawn(async move {
+                match client.get_state().await {
                     Ok(response) => {
-                        debug!(response = ?response, "Launch response");
-                        // Handle error responses from shepherdd
-                        match response.result {
-                            shepherd_api::ResponseResult::Ok(payload) => {
-                                // Check what kind of success response we got
-                                match payload {
-                                    shepherd_api::ResponsePayload::LaunchApproved { session_id, deadline } => {
-                                        info!(session_id = %session_id, "Launch approved, setting SessionActive");
-                                        let now = shepherd_util::now();
-                                        // For unlimited sessions (deadline=None), time_remaining is None
-                                        let time_remaining = deadline.and_then(|d| {
-                                            if d > now {
-                                                (d - now).to_std().ok()
-                                            } else {
-                                                Some(std::time::Duration::ZERO)
-                                            }
-                                        });
-                                        state.set(LauncherState::SessionActive {
-                                            session_id,
-                                            entry_label: entry_id.to_string(),
-                                            time_remaining,
-                                        });
-                                    }
-                                    shepherd_api::ResponsePayload::LaunchDenied { reasons } => {
-                                        let message = reasons
-                                            .iter()
-                                            .map(|r| format!("{:?}", r))
-                                            .collect::<Vec<_>>()
-                                            .join(", ");
-                                        error!(message = %message, "Launch denied");
-                                        state.set(LauncherState::Error { message });
-                                    }
-                                    _ => {
-                                        // Other OK responses - events will update state
-                                    }
-                                }
-                            }
-                            shepherd_api::ResponseResult::Err(err) => {
-                                // Launch failed on server side - refresh state to recover
-                                error!(error = %err.message, "Launch failed on server");
-                                // Request fresh state from shepherdd to get back to correct state
-                                match client.get_state().await {
-                                    Ok(state_resp) => {
-                                        if let shepherd_api::ResponseResult::Ok(
-                                            shepherd_api::ResponsePayload::State(snapshot)
-                                        ) = state_resp.result {
-                                            if snapshot.current_session.is_some() {
-                                                // Session is still active somehow
-                                                debug!("Session still active after spawn failure");
-                                            } else {
-                                                // No session - return to idle with entries
-                                                state.set(LauncherState::Idle {
-                                                    entries: snapshot.entries,
-                                                });
-                                            }
-                                        } else {
-                                            // Unexpected response, show error
-                                            state.set(LauncherState::Error {
-                                                message: format!("Launch failed: {}", err.message),
-                                            });
-                                        }
-                                    }
-                                    Err(e) => {
-                                        // Can't get state, show error
-                                        error!(error = %e, "Failed to get state after launch failure");
-                                        state.set(LauncherState::Error {
-                                            message: format!("Launch failed: {}", err.message),
-                                        });
-                                    }
-                                }
-                            }
+                        if let ResponseResult::Ok(shepherd_api::ResponsePayload::State(snapshot)) =
+                            response.result
+                        {
+                            state.set(LauncherState::Idle {
+                                entries: snapshot.entries,
+                            });
                         }
                     }
                     Err(e) => {
-                        error!(error = %e, "Launch failed");
-                        state.set(LauncherState::Error {
-                            message: format!("Launch failed: {}", e),
-                        });
+                        error!(error = %e, "Failed to get state after cancelling launch");
+                        state.set(LauncherState::Disconnected);
                     }
                 }
             });
@@ -291,17 +241,280 @@ impl LauncherApp {
             });
         });
 
-        // Start shepherdd client in background thread (separate from GTK main loop)
-        // This ensures the tokio runtime is properly driven for event reception
-        let state_for_client = state.clone();
-        let socket_for_client = socket_path.clone();
-        std::thread::spawn(move || {
-            let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime for event loop");
-            rt.block_on(async move {
-                let client = ServiceClient::new(socket_for_client, state_for_client, command_rx);
-                client.run().await;
+        // Save the first-run setup as the service config. On success the
+        // daemon reloads and the state update moves us to the grid.
+        let cmd_client = command_client.clone();
+        let rt = runtime.clone();
+        let wizard_weak = setup_wizard.downgrade();
+        setup_wizard.connect_finish(move |choices| {
+            let Some(wizard) = wizard_weak.upgrade() else {
+                return;
+            };
+            info!(apps = choices.apps.len(), "First-run setup finished");
+            wizard.set_busy(true);
+            wizard.set_status("Saving...");
+
+            let client = cmd_client.clone();
+            let config = choices.to_toml();
+            let handle = rt.spawn(async move { client.save_config(config).await });
+            glib::spawn_future_local(async move {
+                let error = match handle.await {
+                    Ok(Ok(response)) => match response.result {
+                        ResponseResult::Ok(_) => None,
+                        ResponseResult::Err(e) => Some(e.message),
+                    },
+                    Ok(Err(e)) => Some(e.to_string()),
+                    Err(e) => Some(e.to_string()),
+                };
+                if let Some(message) = error {
+                    error!(error = %message, "Failed to save setup config");
+                    wizard.set_status(&format!("Could not save: {}", message));
+                    wizard.set_busy(false);
+                }
+            });
+        });
+
+        // Connect curfew cancel button
+        let cmd_client = command_client.clone();
+        let rt = runtime.clone();
+        power_view.3.connect_clicked(move |_| {
+            info!("Curfew power action cancel requested");
+            let client = cmd_client.clone();
+            rt.spawn(async move {
+                if let Err(e) = client.cancel_power_action().await {
+                    error!(error = %e, "Failed to cancel power action");
+                }
+            });
+        });
+
+        // Refresh the curfew countdown once per second
+        let power_receiver = state.subscribe_power_action();
+        let (power_box, power_title, power_countdown) =
+            (power_view.0.clone(), power_view.1.clone(), power_view.2.clone());
+        glib::timeout_add_seconds_local(1, move || {
+            match *power_receiver.borrow() {
+                Some(pending) => {
+                    let remaining = (pending.deadline - shepherd_util::now())
+                        .num_seconds()
+                        .max(0);
+                    power_title.set_text(match pending.action {
+                        PowerAction::Poweroff => "Bedtime! Turning off in",
+                        PowerAction::Suspend => "Bedtime! Going to sleep in",
+                    });
+                    power_countdown.set_text(&format!("{}:{:02}", remaining / 60, remaining % 60));
+                    power_box.set_visible(true);
+                }
+                None => power_box.set_visible(false),
+            }
+            glib::ControlFlow::Continue
+        });
+
+        // Post-session "How do you feel?" check-in; the session id is read
+        // at click time so the answer always goes to the pending session
+        let check_in_receiver = state.subscribe_check_in();
+        let (check_in_box, check_in_title, mood_buttons, skip_button) = check_in_view;
+        let answers = mood_buttons
+            .into_iter()
+            .map(|(mood, button)| (Some(mood), button))
+            .chain(std::iter::once((None, skip_button)));
+        for (mood, button) in answers {
+            let cmd_client = command_client.clone();
+            let rt = runtime.clone();
+            let receiver = check_in_receiver.clone();
+            let state = state.clone();
+            button.connect_clicked(move |_| {
+                let Some(pending) = receiver.borrow().clone() else {
+                    return;
+                };
+                info!(session_id = %pending.session_id, mood = ?mood, "Check-in answered");
+                // Hide right away; the daemon confirms with a state update
+                state.set_check_in(None);
+                let client = cmd_client.clone();
+                rt.spawn(async move {
+                    if let Err(e) = client.submit_check_in(pending.session_id, mood).await {
+                        error!(error = %e, "Failed to submit check-in");
+                    }
+                });
+            });
+        }
+
+        glib::spawn_future_local(async move {
+            let mut receiver = check_in_receiver;
+            loop {
+                match receiver.borrow_and_update().clone() {
+                    Some(pending) => {
+                        check_in_title.set_text(&format!(
+                            "How do you feel after {}?",
+                            pending.label
+                        ));
+                        check_in_box.set_visible(true);
+                    }
+                    None => check_in_box.set_visible(false),
+                }
+                if receiver.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Show earned reward stars on the profile header
+        let (header_box, stars_label) = profile_header;
+        let mut stars_receiver = state.subscribe_stars();
+        glib::spawn_future_local(async move {
+            loop {
+                let stars = *stars_receiver.borrow_and_update();
+                stars_label.set_text(&format!("⭐ {}", stars));
+                header_box.set_visible(stars > 0);
+                if stars_receiver.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Show why every tile is greyed out while launches are on hold
+        let (hold_box, hold_label) = hold_banner;
+        let mut hold_receiver = state.subscribe_hold();
+        glib::spawn_future_local(async move {
+            loop {
+                match hold_receiver.borrow_and_update().clone() {
+                    Some(hold) => {
+                        let mut text = hold.message.unwrap_or_else(|| "No games right now".into());
+                        if let Some(until) = hold.until {
+                            text.push_str(&format!(" (until {})", until.format("%a %H:%M")));
+                        }
+                        hold_label.set_text(&text);
+                        hold_box.set_visible(true);
+                    }
+                    None => hold_box.set_visible(false),
+                }
+                if hold_receiver.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Follow the theme picked in the service config
+        let mut theme_receiver = state.subscribe_theme();
+        glib::spawn_future_local(async move {
+            loop {
+                theme_provider.set_theme(*theme_receiver.borrow_and_update());
+                if theme_receiver.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Say why the last launch failed until the next one
+        let (launch_error_box, launch_error_label) = launch_error_banner;
+        let mut launch_error_receiver = state.subscribe_launch_error();
+        glib::spawn_future_local(async move {
+            loop {
+                match launch_error_receiver.borrow_and_update().clone() {
+                    Some(message) => {
+                        launch_error_label.set_text(&message);
+                        launch_error_box.set_visible(true);
+                    }
+                    None => launch_error_box.set_visible(false),
+                }
+                if launch_error_receiver.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // "You played 43 minutes" card after a session, until dismissed or
+        // the time set in the service config runs out
+        let (summary_box, summary_title, summary_detail, summary_button) = summary_view;
+        let state_clone = state.clone();
+        summary_button.connect_clicked(move |_| state_clone.set_session_summary(None));
+        let mut summary_receiver = state.subscribe_session_summary();
+        let state_clone = state.clone();
+        glib::spawn_future_local(async move {
+            loop {
+                match summary_receiver.borrow_and_update().clone() {
+                    Some(summary) => {
+                        let (title, detail) = summary_text(&summary);
+                        summary_title.set_text(&title);
+                        summary_detail.set_text(detail.as_deref().unwrap_or_default());
+                        summary_detail.set_visible(detail.is_some());
+                        summary_box.set_visible(true);
+
+                        let state = state_clone.clone();
+                        glib::timeout_add_local_once(summary.show_for, move || {
+                            let shown = state.subscribe_session_summary().borrow().clone();
+                            if shown.is_some_and(|s| s.session_id == summary.session_id) {
+                                state.set_session_summary(None);
+                            }
+                        });
+                    }
+                    None => summary_box.set_visible(false),
+                }
+                if summary_receiver.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // "Your time today": today's usage as bars and the windows still to
+        // come, opened from the grid's "My time" button or after the grid
+        // has sat untouched for a while
+        let (today_box, today_categories, today_windows, today_back) = today_view;
+        let cmd_client = command_client.clone();
+        let rt = runtime.clone();
+        let today_box_clone = today_box.clone();
+        let open_today = Rc::new(move || {
+            let client = cmd_client.clone();
+            let handle = rt.spawn(async move { client.day_overview().await });
+            let (today_box, categories, windows) =
+                (today_box_clone.clone(), today_categories.clone(), today_windows.clone());
+            glib::spawn_future_local(async move {
+                match handle.await {
+                    Ok(Ok(overview)) => {
+                        fill_today_view(&categories, &windows, &overview, shepherd_util::now());
+                        today_box.set_visible(true);
+                    }
+                    Ok(Err(e)) => error!(error = %e, "Failed to get day overview"),
+                    Err(e) => error!(error = %e, "Day overview task failed"),
+                }
             });
         });
+        let open = open_today.clone();
+        today_button.connect_clicked(move |_| open());
+        let today_box_clone = today_box.clone();
+        today_back.connect_clicked(move |_| today_box_clone.set_visible(false));
+
+        let last_input = Rc::new(Cell::new(Instant::now()));
+        let key_controller = gtk4::EventControllerKey::new();
+        key_controller.set_propagation_phase(gtk4::PropagationPhase::Capture);
+        let input = last_input.clone();
+        key_controller.connect_key_pressed(move |_, _, _, _| {
+            input.set(Instant::now());
+            glib::Propagation::Proceed
+        });
+        window.add_controller(key_controller);
+        let click_controller = gtk4::GestureClick::new();
+        click_controller.set_propagation_phase(gtk4::PropagationPhase::Capture);
+        let input = last_input.clone();
+        click_controller.connect_pressed(move |_, _, _, _| input.set(Instant::now()));
+        window.add_controller(click_controller);
+        let motion_controller = gtk4::EventControllerMotion::new();
+        let input = last_input.clone();
+        motion_controller.connect_motion(move |_, _, _| input.set(Instant::now()));
+        window.add_controller(motion_controller);
+
+        let today_state = state.clone();
+        let today_box_clone = today_box.clone();
+        glib::timeout_add_seconds_local(TODAY_REFRESH_SECONDS, move || {
+            if today_box_clone.is_visible() {
+                open_today();
+            } else if last_input.get().elapsed() >= TODAY_IDLE_AFTER
+                && matches!(today_state.get(), LauncherState::Idle { .. })
+            {
+                info!("Launcher idle, showing today's time");
+                open_today();
+            }
+            glib::ControlFlow::Continue
+        });
 
         // Set up state change handler
         let stack_weak = stack.downgrade();
@@ -309,6 +522,11 @@ impl LauncherApp {
         let window_weak = window.downgrade();
         let error_label = error_view.1.clone();
         let session_label = session_view.1.clone();
+        let (confirm_title, confirm_detail) = (confirm_view.1.clone(), confirm_view.2.clone());
+        let wizard_weak = setup_wizard.downgrade();
+        let offline_box = offline_banner.0.clone();
+        let cmd_client = command_client.clone();
+        let rt = runtime.clone();
 
         glib::spawn_future_local(async move {
             let mut receiver = state_receiver;
@@ -324,6 +542,12 @@ impl LauncherApp {
 
                 let grid = grid_weak.upgrade();
                 let window = window_weak.upgrade();
+                offline_box.set_visible(matches!(state, LauncherState::Offline { .. }));
+                let idle = matches!(state, LauncherState::Idle { .. });
+                today_button.set_visible(idle);
+                if !idle {
+                    today_box.set_visible(false);
+                }
 
                 match state {
                     LauncherState::Disconnected => {
@@ -348,18 +572,57 @@ impl LauncherApp {
                         }
                         stack.set_visible_child_name("grid");
                     }
+                    LauncherState::Offline { entries } => {
+                        // Last-known grid, read-only until shepherdd answers
+                        if let Some(grid) = grid {
+                            grid.set_entries(entries);
+                            grid.set_tiles_sensitive(false);
+                        }
+                        if let Some(ref win) = window {
+                            win.set_visible(true);
+                        }
+                        stack.set_visible_child_name("grid");
+                    }
                     LauncherState::Launching { entry_id: _ } => {
                         if let Some(grid) = grid {
                             grid.set_tiles_sensitive(false);
                         }
                         stack.set_visible_child_name("loading");
                     }
+                    LauncherState::ConfirmLaunch {
+                        session_id: _,
+                        entry_label,
+                        max_duration,
+                        deadline,
+                    } => {
+                        confirm_title.set_text(&format!("Start {}?", entry_label));
+                        confirm_detail.set_text(&match (max_duration, deadline) {
+                            (Some(duration), Some(deadline)) => format!(
+                                "You can play for {} minutes, until {}",
+                                duration.as_secs().div_ceil(60),
+                                deadline.format("%H:%M")
+                            ),
+                            (Some(duration), None) => {
+                                format!("You can play for {} minutes", duration.as_secs().div_ceil(60))
+                            }
+                            _ => "There is no time limit".to_string(),
+                        });
+                        if let Some(ref win) = window {
+                            win.set_visible(true);
+                        }
+                        stack.set_visible_child_name("confirm");
+                    }
                     LauncherState::SessionActive {
                         session_id: _,
                         entry_label,
+                        state: session_state,
                         time_remaining: _,
                     } => {
-                        session_label.set_text(&format!("Loading: {}", entry_label));
+                        session_label.set_text(&match session_state {
+                            SessionState::Launching => format!("Loading: {}", entry_label),
+                            SessionState::Expiring => format!("Time's up: {}", entry_label),
+                            _ => format!("Playing: {}", entry_label),
+                        });
                         // Show the session view as a loading screen behind the game
                         // The game window will appear on top when it launches
                         if let Some(ref win) = window {
@@ -374,11 +637,148 @@ impl LauncherApp {
                         error_label.set_text(&message);
                         stack.set_visible_child_name("error");
                     }
+                    LauncherState::Setup => {
+                        if let Some(ref win) = window {
+                            win.set_visible(true);
+                        }
+                        if let Some(wizard) = wizard_weak.upgrade()
+                            && wizard.request_apps_once()
+                        {
+                            let client = cmd_client.clone();
+                            let handle = rt.spawn(async move { client.discover_apps().await });
+                            glib::spawn_future_local(async move {
+                                let apps = match handle.await {
+                                    Ok(Ok(apps)) => apps,
+                                    Ok(Err(e)) => {
+                                        error!(error = %e, "Failed to discover apps");
+                                        Vec::new()
+                                    }
+                                    Err(e) => {
+                                        error!(error = %e, "App discovery task failed");
+                                        Vec::new()
+                                    }
+                                };
+                                wizard.set_apps(apps);
+                            });
+                        }
+                        stack.set_visible_child_name("setup");
+                    }
                 }
             }
         });
 
         window.present();
+        window
+    }
+
+    /// Move the launcher to whatever a Launch or ConfirmLaunch answer calls for
+    async fn handle_launch_response(
+        client: &CommandClient,
+        state: &SharedState,
+        entry_label: String,
+        response: anyhow::Result<shepherd_api::Response>,
+    ) {
+        match response {
+            Ok(response) => {
+                debug!(response = ?response, "Launch response");
+                // Handle error responses from shepherdd
+                match response.result {
+                    shepherd_api::ResponseResult::Ok(payload) => {
+                        // Check what kind of success response we got
+                        match payload {
+                            shepherd_api::ResponsePayload::LaunchApproved { session_id, deadline } => {
+                                info!(session_id = %session_id, "Launch approved, setting SessionActive");
+                                let now = shepherd_util::now();
+                                // For unlimited sessions (deadline=None), time_remaining is None
+                                let time_remaining = deadline.and_then(|d| {
+                                    if d > now {
+                                        (d - now).to_std().ok()
+                                    } else {
+                                        Some(std::time::Duration::ZERO)
+                                    }
+                                });
+                                state.set(LauncherState::SessionActive {
+                                    session_id,
+                                    entry_label,
+                                    state: SessionState::Launching,
+                                    time_remaining,
+                                });
+                            }
+                            shepherd_api::ResponsePayload::LaunchDenied { reasons } => {
+                                let message = reasons
+                                    .iter()
+                                    .map(|r| format!("{:?}", r))
+                                    .collect::<Vec<_>>()
+                                    .join(", ");
+                                error!(message = %message, "Launch denied");
+                                state.set(LauncherState::Error { message });
+                            }
+                            shepherd_api::ResponsePayload::LaunchNeedsConfirmation {
+                                session_id,
+                                label,
+                                max_duration,
+                                deadline,
+                                ..
+                            } => {
+                                info!(session_id = %session_id, "Launch needs confirmation");
+                                state.set(LauncherState::ConfirmLaunch {
+                                    session_id,
+                                    entry_label: label,
+                                    max_duration,
+                                    deadline,
+                                });
+                            }
+                            _ => {
+                                // Other OK responses - events will update state
+                            }
+                        }
+                    }
+                    shepherd_api::ResponseResult::Err(err) => {
+                        // Launch failed on server side - refresh state to recover
+                        error!(error = %err.message, "Launch failed on server");
+                        if let Some(diagnostics) = &err.spawn {
+                            state.set_launch_error(Some(diagnostics.summary()));
+                        }
+                        // Request fresh state from shepherdd to get back to correct state
+                        match client.get_state().await {
+                            Ok(state_resp) => {
+                                if let shepherd_api::ResponseResult::Ok(
+                                    shepherd_api::ResponsePayload::State(snapshot)
+                                ) = state_resp.result {
+                                    if snapshot.current_session.is_some() {
+                                        // Session is still active somehow
+                                        debug!("Session still active after spawn failure");
+                                    } else {
+                                        // No session - return to idle with entries
+                                        state.set(LauncherState::Idle {
+                                            entries: snapshot.entries,
+                                        });
+                                    }
+                                } else {
+                                    // Unexpected response, show error
+                                    state.set(LauncherState::Error {
+                                        message: format!("Launch failed: {}", err.message),
+                                    });
+                                }
+                            }
+                            Err(e) => {
+                                // Can't get state, show error
+                                error!(error = %e, "Failed to get state after launch failure");
+                                state.set(LauncherState::Error {
+                                    message: format!("Launch failed: {}", err.message),
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                error!(error = %e, "Launch failed");
+                state.set(LauncherState::Error {
+                    message: format!("Launch failed: {}", e),
+                });
+            }
+        }
     }
 
     fn create_loading_view() -> gtk4::Box {
@@ -438,6 +838,187 @@ impl LauncherApp {
         (container, label)
     }
 
+    fn create_confirm_view() -> (gtk4::Box, gtk4::Label, gtk4::Label, gtk4::Button, gtk4::Button) {
+        let container = gtk4::Box::new(gtk4::Orientation::Vertical, 24);
+        container.set_halign(gtk4::Align::Center);
+        container.set_valign(gtk4::Align::Center);
+        container.add_css_class("session-active-box");
+
+        let title = gtk4::Label::new(None);
+        title.add_css_class("session-label");
+        container.append(&title);
+
+        let detail = gtk4::Label::new(None);
+        detail.add_css_class("session-sublabel");
+        detail.set_wrap(true);
+        detail.set_max_width_chars(40);
+        container.append(&detail);
+
+        let buttons = gtk4::Box::new(gtk4::Orientation::Horizontal, 24);
+        buttons.set_halign(gtk4::Align::Center);
+        let start = gtk4::Button::with_label("Start");
+        start.add_css_class("confirm-button");
+        start.add_css_class("suggested-action");
+        let cancel = gtk4::Button::with_label("Not now");
+        cancel.add_css_class("confirm-button");
+        buttons.append(&cancel);
+        buttons.append(&start);
+        container.append(&buttons);
+
+        (container, title, detail, start, cancel)
+    }
+
+    fn create_power_view() -> (gtk4::Box, gtk4::Label, gtk4::Label, gtk4::Button) {
+        let container = gtk4::Box::new(gtk4::Orientation::Vertical, 16);
+        container.set_halign(gtk4::Align::Center);
+        container.set_valign(gtk4::Align::Center);
+        container.add_css_class("power-overlay");
+        container.set_visible(false);
+
+        let title = gtk4::Label::new(None);
+        title.add_css_class("power-title");
+        container.append(&title);
+
+        let countdown = gtk4::Label::new(None);
+        countdown.add_css_class("power-countdown");
+        container.append(&countdown);
+
+        let cancel_button = gtk4::Button::with_label("Not yet");
+        cancel_button.add_css_class("launcher-tile");
+        container.append(&cancel_button);
+
+        (container, title, countdown, cancel_button)
+    }
+
+    fn create_profile_header() -> (gtk4::Box, gtk4::Label) {
+        let container = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+        container.set_halign(gtk4::Align::End);
+        container.set_valign(gtk4::Align::Start);
+        container.add_css_class("profile-header");
+        container.set_visible(false);
+
+        let stars = gtk4::Label::new(None);
+        stars.add_css_class("profile-stars");
+        stars.set_tooltip_text(Some("Stars earned"));
+        container.append(&stars);
+
+        (container, stars)
+    }
+
+    fn create_hold_banner() -> (gtk4::Box, gtk4::Label) {
+        let container = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+        container.set_halign(gtk4::Align::Center);
+        container.set_valign(gtk4::Align::Start);
+        container.add_css_class("hold-banner");
+        container.set_visible(false);
+
+        let message = gtk4::Label::new(None);
+        message.add_css_class("hold-message");
+        message.set_wrap(true);
+        message.set_max_width_chars(60);
+        container.append(&message);
+
+        (container, message)
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn create_check_in_view() -> (gtk4::Box, gtk4::Label, Vec<(Mood, gtk4::Button)>, gtk4::Button) {
+        let container = gtk4::Box::new(gtk4::Orientation::Vertical, 24);
+        container.set_halign(gtk4::Align::Center);
+        container.set_valign(gtk4::Align::Center);
+        container.add_css_class("check-in-overlay");
+        container.set_visible(false);
+
+        let title = gtk4::Label::new(Some("How do you feel?"));
+        title.add_css_class("check-in-title");
+        container.append(&title);
+
+        let moods = gtk4::Box::new(gtk4::Orientation::Horizontal, 16);
+        moods.set_halign(gtk4::Align::Center);
+        let mood_buttons: Vec<_> = Mood::ALL
+            .into_iter()
+            .map(|mood| {
+                let button = gtk4::Button::with_label(mood.emoji());
+                button.add_css_class("launcher-tile");
+                button.add_css_class("check-in-mood");
+                moods.append(&button);
+                (mood, button)
+            })
+            .collect();
+        container.append(&moods);
+
+        let skip_button = gtk4::Button::with_label("Skip");
+        skip_button.add_css_class("launcher-tile");
+        container.append(&skip_button);
+
+        (container, title, mood_buttons, skip_button)
+    }
+
+    fn create_summary_view() -> (gtk4::Box, gtk4::Label, gtk4::Label, gtk4::Button) {
+        let container = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+        container.set_halign(gtk4::Align::Center);
+        container.set_valign(gtk4::Align::End);
+        container.set_margin_bottom(24);
+        container.add_css_class("check-in-overlay");
+        container.set_visible(false);
+
+        let title = gtk4::Label::new(None);
+        title.add_css_class("check-in-title");
+        title.set_wrap(true);
+        container.append(&title);
+
+        let detail = gtk4::Label::new(None);
+        detail.add_css_class("hold-message");
+        container.append(&detail);
+
+        let ok_button = gtk4::Button::with_label("OK");
+        ok_button.add_css_class("launcher-tile");
+        container.append(&ok_button);
+
+        (container, title, detail, ok_button)
+    }
+
+    fn create_today_button() -> gtk4::Button {
+        let button = gtk4::Button::with_label("My time");
+        button.set_halign(gtk4::Align::Start);
+        button.set_valign(gtk4::Align::Start);
+        button.set_margin_start(24);
+        button.set_margin_top(24);
+        button.add_css_class("launcher-tile");
+        button.add_css_class("today-button");
+        button.set_visible(false);
+        button
+    }
+
+    fn create_today_view() -> (gtk4::Box, gtk4::Box, gtk4::Box, gtk4::Button) {
+        let container = gtk4::Box::new(gtk4::Orientation::Vertical, 16);
+        container.set_halign(gtk4::Align::Center);
+        container.set_valign(gtk4::Align::Center);
+        container.add_css_class("check-in-overlay");
+        container.set_visible(false);
+
+        let title = gtk4::Label::new(Some("Your time today"));
+        title.add_css_class("check-in-title");
+        container.append(&title);
+
+        let categories = gtk4::Box::new(gtk4::Orientation::Vertical, 8);
+        container.append(&categories);
+
+        let coming_up = gtk4::Label::new(Some("Coming up"));
+        coming_up.add_css_class("today-heading");
+        coming_up.set_halign(gtk4::Align::Start);
+        container.append(&coming_up);
+
+        let windows = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
+        container.append(&windows);
+
+        let back_button = gtk4::Button::with_label("Back");
+        back_button.add_css_class("launcher-tile");
+        container.append(&back_button);
+
+        (container, categories, windows, back_button)
+    }
+
     fn create_disconnected_view() -> (gtk4::Box, gtk4::Button) {
         let container = gtk4::Box::new(gtk4::Orientation::Vertical, 24);
         container.set_halign(gtk4::Align::Center);
@@ -458,3 +1039,132 @@ impl LauncherApp {
         (container, retry_button)
     }
 }
+
+/// "You played 43 minutes", plus what's left today for entries with a
+/// quota and when the entry can be played again
+fn summary_text(summary: &SessionSummary) -> (String, Option<String>) {
+    let title = format!("You played {} for {}", summary.label, minutes_phrase(summary.duration));
+    let mut lines = Vec::new();
+    if let Some(left) = summary.remaining_today {
+        lines.push(if left.as_secs() < 60 {
+            "No time left today".to_string()
+        } else {
+            format!("{} left today", minutes_phrase(left))
+        });
+    }
+    if let Some(at) = summary.next_available {
+        lines.push(format!("You can play again {}", when_text(at, shepherd_util::now())));
+    }
+    (title, (!lines.is_empty()).then(|| lines.join("\n")))
+}
+
+/// Rebuild the "Your time today" lists from a fresh overview
+fn fill_today_view(
+    categories: &gtk4::Box,
+    windows: &gtk4::Box,
+    overview: &DayOverview,
+    now: DateTime<Local>,
+) {
+    for list in [categories, windows] {
+        while let Some(child) = list.first_child() {
+            list.remove(&child);
+        }
+    }
+
+    // Only name the groups when there is more than one
+    let named = overview.categories.len() > 1;
+    for category in &overview.categories {
+        if named {
+            let heading = gtk4::Label::new(Some(category.name.as_deref().unwrap_or("Everything else")));
+            heading.add_css_class("today-heading");
+            heading.set_halign(gtk4::Align::Start);
+            categories.append(&heading);
+        }
+        for entry in &category.entries {
+            let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 16);
+            let label = gtk4::Label::new(Some(&entry.label));
+            label.add_css_class("today-entry");
+            label.set_halign(gtk4::Align::Start);
+            label.set_width_chars(16);
+            label.set_xalign(0.0);
+            row.append(&label);
+
+            let bar = gtk4::ProgressBar::new();
+            bar.set_hexpand(true);
+            bar.set_valign(gtk4::Align::Center);
+            bar.set_width_request(320);
+            bar.set_show_text(true);
+            bar.set_text(Some(&usage_text(entry)));
+            match usage_fraction(entry) {
+                Some(fraction) => bar.set_fraction(fraction),
+                None => bar.add_css_class("today-unlimited"),
+            }
+            row.append(&bar);
+            categories.append(&row);
+        }
+    }
+
+    if overview.windows.is_empty() {
+        let label = gtk4::Label::new(Some("Nothing else today"));
+        label.add_css_class("hold-message");
+        label.set_halign(gtk4::Align::Start);
+        windows.append(&label);
+    }
+    for window in &overview.windows {
+        let start = if window.start <= now {
+            "Now".to_string()
+        } else {
+            window.start.format("%H:%M").to_string()
+        };
+        let label = gtk4::Label::new(Some(&format!(
+            "{}–{}  {}",
+            start,
+            window.end.format("%H:%M"),
+            window.labels.join(", ")
+        )));
+        label.add_css_class("hold-message");
+        label.set_halign(gtk4::Align::Start);
+        label.set_wrap(true);
+        label.set_max_width_chars(60);
+        windows.append(&label);
+    }
+}
+
+/// "20 minutes left", "No time left" or, without a quota, "40 minutes played"
+fn usage_text(entry: &EntryUsage) -> String {
+    match entry.remaining() {
+        Some(left) if left.as_secs() < 60 => "No time left".to_string(),
+        Some(left) => format!("{} left", minutes_phrase(left)),
+        None if entry.used.is_zero() => "Not played yet".to_string(),
+        None => format!("{} played", minutes_phrase(entry.used)),
+    }
+}
+
+/// How full an entry's bar is. None for entries without a quota.
+fn usage_fraction(entry: &EntryUsage) -> Option<f64> {
+    let quota = entry.quota?;
+    if quota.is_zero() {
+        return Some(1.0);
+    }
+    Some((entry.used.as_secs_f64() / quota.as_secs_f64()).min(1.0))
+}
+
+/// "at 18:00", "tomorrow", "tomorrow at 08:00" or "on Saturday at 10:00"
+fn when_text(at: DateTime<Local>, now: DateTime<Local>) -> String {
+    let days = (at.date_naive() - now.date_naive()).num_days();
+    let time = at.format("%H:%M");
+    match days {
+        ..=0 => format!("at {}", time),
+        1 if at.time() == NaiveTime::MIN => "tomorrow".to_string(),
+        1 => format!("tomorrow at {}", time),
+        _ => format!("on {} at {}", at.format("%A"), time),
+    }
+}
+
+fn minutes_phrase(duration: Duration) -> String {
+    match duration.as_secs() / 60 {
+        0 => "less than a minute".to_string(),
+        1 => "1 minute".to_string(),
+        minutes => format!("{} minutes", minutes),
+    }
+}
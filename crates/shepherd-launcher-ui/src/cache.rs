@@ -0,0 +1,79 @@
+//! Last-known entries, cached on disk
+//!
+//! The entries from each state snapshot are saved so the launcher can show
+//! the grid (read-only) while shepherdd is restarting, instead of the
+//! disconnected screen.
+
+use shepherd_api::EntryView;
+use std::path::PathBuf;
+use tracing::{debug, warn};
+
+fn cache_path() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("shepherd")
+        .join("launcher-entries.json")
+}
+
+#[derive(Default)]
+pub struct EntryCache {
+    entries: Option<Vec<EntryView>>,
+    /// What's on disk, so unchanged snapshots aren't rewritten
+    saved: Option<String>,
+}
+
+impl EntryCache {
+    /// Read the entries a previous launcher run saved
+    pub fn load() -> Self {
+        let path = cache_path();
+        let Ok(saved) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        match serde_json::from_str(&saved) {
+            Ok(entries) => Self {
+                entries: Some(entries),
+                saved: Some(saved),
+            },
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Ignoring unreadable entry cache");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn entries(&self) -> Option<&[EntryView]> {
+        self.entries.as_deref()
+    }
+
+    pub fn update(&mut self, entries: &[EntryView]) {
+        let json = match serde_json::to_string(entries) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!(error = %e, "Failed to serialize entry cache");
+                return;
+            }
+        };
+        self.entries = Some(entries.to_vec());
+        if self.saved.as_ref() == Some(&json) {
+            return;
+        }
+
+        let path = cache_path();
+        let result = (|| -> std::io::Result<()> {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            // Write then rename so a crash never leaves half a file behind
+            let tmp = path.with_extension("json.tmp");
+            std::fs::write(&tmp, &json)?;
+            std::fs::rename(&tmp, &path)
+        })();
+        match result {
+            Ok(()) => {
+                debug!(path = %path.display(), entries = entries.len(), "Saved entry cache");
+                self.saved = Some(json);
+            }
+            Err(e) => warn!(path = %path.display(), error = %e, "Failed to save entry cache"),
+        }
+    }
+}
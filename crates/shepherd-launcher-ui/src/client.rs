@@ -1,9 +1,11 @@
 //! IPC client wrapper for the launcher UI
 
 use anyhow::{Context, Result};
-use shepherd_api::{Command, ReasonCode, Response, ResponsePayload, ResponseResult};
-use shepherd_ipc::IpcClient;
-use shepherd_util::EntryId;
+use shepherd_api::{
+    ClientKind, Command, DayOverview, DiscoveredApp, Locale, Mood, Response, ResponsePayload, ResponseResult, SessionState,
+};
+use shepherd_ipc::{IpcClient, StreamMessage};
+use shepherd_util::{EntryId, SeatId, SessionId};
 use std::path::Path;
 use std::time::Duration;
 use tokio::sync::mpsc;
@@ -12,6 +14,12 @@ use tracing::{error, info, warn};
 
 use crate::state::{LauncherState, SharedState};
 
+/// How long to wait for shepherdd to answer a launch request
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(15);
+/// Tries before a launch request is reported as failed
+const LAUNCH_ATTEMPTS: u32 = 3;
+const LAUNCH_RETRY_DELAY: Duration = Duration::from_millis(500);
+
 /// Messages from UI to client task
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -29,6 +37,7 @@ pub enum ClientCommand {
 /// Client connection manager
 pub struct ServiceClient {
     socket_path: std::path::PathBuf,
+    seat: Option<SeatId>,
     state: SharedState,
     command_rx: mpsc::UnboundedReceiver<ClientCommand>,
 }
@@ -36,11 +45,13 @@ pub struct ServiceClient {
 impl ServiceClient {
     pub fn new(
         socket_path: impl AsRef<Path>,
+        seat: Option<SeatId>,
         state: SharedState,
         command_rx: mpsc::UnboundedReceiver<ClientCommand>,
     ) -> Self {
         Self {
             socket_path: socket_path.as_ref().to_path_buf(),
+            seat,
             state,
             command_rx,
         }
@@ -56,7 +67,7 @@ impl ServiceClient {
                 }
                 Err(e) => {
                     error!(error = %e, "Connection error");
-                    self.state.set(LauncherState::Disconnected);
+                    self.state.set_disconnected();
                     
                     // Wait before reconnecting
                     sleep(Duration::from_secs(2)).await;
@@ -66,7 +77,10 @@ impl ServiceClient {
     }
 
     async fn connect_and_run(&mut self) -> Result<()> {
-        self.state.set(LauncherState::Connecting);
+        // Keep the cached grid up rather than flashing the spinner on every retry
+        if !self.state.is_offline() {
+            self.state.set(LauncherState::Connecting);
+        }
 
         info!(path = %self.socket_path.display(), "Connecting to shepherdd");
         
@@ -76,17 +90,19 @@ impl ServiceClient {
 
         info!("Connected to shepherdd");
 
-        // Get initial state (includes entries)
-        info!("Sending GetState command");
-        let response = client.send(Command::GetState).await?;
-        info!("Got GetState response");
-        self.handle_response(response)?;
-
-        // Note: ListEntries is not needed since GetState includes entries in the snapshot
+        // Take over from a launcher left behind by a crash
+        let role = client.identify(ClientKind::Launcher, self.seat.clone()).await?;
+        info!(role = ?role, "Identified as launcher");
 
-        // Now consume client for event stream (this will send SubscribeEvents internally)
+        // Subscribe before asking for the state (which includes entries), so
+        // nothing that happens in between is missed. Until the snapshot
+        // arrives we show the spinner or the read-only cached grid: after a
+        // restart mid-session, the tiles mustn't be offered before we know
+        // whether a session is running.
         info!("Subscribing to events");
-        let mut events = client.subscribe().await?;
+        let (mut events, mut sender) = client.subscribe_with_commands().await?;
+        info!("Sending GetState command");
+        let mut state_request = Some(sender.send(Command::GetState { seat: None }).await?);
         info!("Subscribed to events, entering event loop");
 
         // Main event loop
@@ -111,17 +127,27 @@ impl ServiceClient {
                             return Ok(());
                         }
                         ClientCommand::RefreshState => {
-                            // Trigger reconnect to refresh
-                            return Ok(());
+                            state_request = Some(sender.send(Command::GetState { seat: None }).await?);
                         }
                     }
                 }
 
                 // Handle events from shepherdd
-                event_result = events.next() => {
-                    match event_result {
-                        Ok(event) => {
+                message = events.next_message() => {
+                    match message {
+                        Ok(StreamMessage::Response(response)) => {
+                            if state_request == Some(response.request_id) {
+                                info!("Got GetState response");
+                                state_request = None;
+                                self.handle_response(response)?;
+                            }
+                        }
+                        Ok(StreamMessage::Event(event)) => {
                             info!(event = ?event, "Received event from shepherdd (client.rs)");
+                            if shepherd_ipc::replaced_this_process(&event) {
+                                warn!("A newer launcher took over, exiting");
+                                std::process::exit(0);
+                            }
                             self.state.handle_event(event);
                         }
                         Err(e) => {
@@ -139,30 +165,14 @@ impl ServiceClient {
             ResponseResult::Ok(payload) => {
                 match payload {
                     ResponsePayload::State(snapshot) => {
-                        if let Some(session) = snapshot.current_session {
-                            let now = shepherd_util::now();
-                            // For unlimited sessions (deadline=None), time_remaining is None
-                            let time_remaining = session.deadline.and_then(|d| {
-                                if d > now {
-                                    (d - now).to_std().ok()
-                                } else {
-                                    Some(Duration::ZERO)
-                                }
-                            });
-                            self.state.set(LauncherState::SessionActive {
-                                session_id: session.session_id,
-                                entry_label: session.label,
-                                time_remaining,
-                            });
-                        } else {
-                            self.state.set(LauncherState::Idle {
-                                entries: snapshot.entries,
-                            });
-                        }
+                        self.state.apply_snapshot(snapshot);
                     }
-                    ResponsePayload::Entries(entries) => {
+                    ResponsePayload::Entries { entries } => {
                         // Only update if we're in idle state
-                        if matches!(self.state.get(), LauncherState::Idle { .. } | LauncherState::Connecting) {
+                        if matches!(
+                            self.state.get(),
+                            LauncherState::Idle { .. } | LauncherState::Offline { .. } | LauncherState::Connecting
+                        ) {
                             self.state.set(LauncherState::Idle { entries });
                         }
                     }
@@ -179,13 +189,15 @@ impl ServiceClient {
                         self.state.set(LauncherState::SessionActive {
                             session_id,
                             entry_label: "Starting...".into(),
+                            state: SessionState::Launching,
                             time_remaining,
                         });
                     }
                     ResponsePayload::LaunchDenied { reasons } => {
+                        let locale = Locale::from_env();
                         let message = reasons
                             .iter()
-                            .map(|r| reason_to_message(r))
+                            .map(|r| r.message(locale))
                             .collect::<Vec<_>>()
                             .join(", ");
                         self.state.set(LauncherState::Error { message });
@@ -207,50 +219,117 @@ impl ServiceClient {
 /// Separate command client for sending commands (not subscribed)
 pub struct CommandClient {
     socket_path: std::path::PathBuf,
+    seat: Option<SeatId>,
 }
 
 impl CommandClient {
-    pub fn new(socket_path: impl AsRef<Path>) -> Self {
+    pub fn new(socket_path: impl AsRef<Path>, seat: Option<SeatId>) -> Self {
         Self {
             socket_path: socket_path.as_ref().to_path_buf(),
+            seat,
         }
     }
 
-    pub async fn launch(&self, entry_id: &EntryId) -> Result<Response> {
+    /// Connect for one command. On a seat other than the default, the
+    /// connection says which, so launches and state are for this seat.
+    async fn connect(&self) -> Result<IpcClient> {
         let mut client = IpcClient::connect(&self.socket_path).await?;
-        client.send(Command::Launch {
-            entry_id: entry_id.clone(),
-        }).await.map_err(Into::into)
+        if let Some(seat) = &self.seat {
+            client.identify(ClientKind::Launcher, Some(seat.clone())).await?;
+        }
+        Ok(client)
+    }
+
+    /// Launch an entry. If shepherdd doesn't answer, the request is sent
+    /// again with the same idempotency key, so a launch that did go through
+    /// isn't started (and charged) twice.
+    pub async fn launch(&self, entry_id: &EntryId) -> Result<Response> {
+        let idempotency_key = uuid::Uuid::new_v4().to_string();
+        let mut attempt = 1;
+        loop {
+            let command = Command::Launch {
+                entry_id: entry_id.clone(),
+                participants: Vec::new(),
+                idempotency_key: Some(idempotency_key.clone()),
+            };
+            let result = tokio::time::timeout(LAUNCH_TIMEOUT, async {
+                let mut client = self.connect().await?;
+                client.send(command).await.map_err(anyhow::Error::from)
+            })
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("No response from shepherdd")));
+
+            match result {
+                Err(e) if attempt < LAUNCH_ATTEMPTS => {
+                    warn!(error = %e, attempt, "Launch request failed, retrying");
+                    sleep(LAUNCH_RETRY_DELAY).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub async fn confirm_launch(&self, session_id: SessionId) -> Result<Response> {
+        let mut client = self.connect().await?;
+        client.send(Command::ConfirmLaunch { session_id }).await.map_err(Into::into)
     }
 
     #[allow(dead_code)]
     pub async fn stop_current(&self) -> Result<Response> {
-        let mut client = IpcClient::connect(&self.socket_path).await?;
+        let mut client = self.connect().await?;
         client.send(Command::StopCurrent {
             mode: shepherd_api::StopMode::Graceful,
+            seat: None,
         }).await.map_err(Into::into)
     }
 
     pub async fn get_state(&self) -> Result<Response> {
-        let mut client = IpcClient::connect(&self.socket_path).await?;
-        client.send(Command::GetState).await.map_err(Into::into)
+        let mut client = self.connect().await?;
+        client.send(Command::GetState { seat: None }).await.map_err(Into::into)
+    }
+
+    pub async fn cancel_power_action(&self) -> Result<Response> {
+        let mut client = self.connect().await?;
+        client.send(Command::CancelPowerAction).await.map_err(Into::into)
+    }
+
+    pub async fn submit_check_in(
+        &self,
+        session_id: SessionId,
+        mood: Option<Mood>,
+    ) -> Result<Response> {
+        let mut client = self.connect().await?;
+        client.send(Command::SubmitCheckIn { session_id, mood }).await.map_err(Into::into)
+    }
+
+    pub async fn discover_apps(&self) -> Result<Vec<DiscoveredApp>> {
+        let mut client = self.connect().await?;
+        match client.send(Command::DiscoverApps).await?.result {
+            ResponseResult::Ok(ResponsePayload::DiscoveredApps { apps }) => Ok(apps),
+            ResponseResult::Ok(other) => anyhow::bail!("Unexpected response: {:?}", other),
+            ResponseResult::Err(e) => anyhow::bail!("{}", e.message),
+        }
+    }
+
+    /// Today's usage per entry and the windows still to come
+    pub async fn day_overview(&self) -> Result<DayOverview> {
+        let mut client = self.connect().await?;
+        match client.send(Command::GetDayOverview).await?.result {
+            ResponseResult::Ok(ResponsePayload::DayOverview(overview)) => Ok(overview),
+            ResponseResult::Ok(other) => anyhow::bail!("Unexpected response: {:?}", other),
+            ResponseResult::Err(e) => anyhow::bail!("{}", e.message),
+        }
+    }
+
+    pub async fn save_config(&self, config: String) -> Result<Response> {
+        let mut client = self.connect().await?;
+        client.send(Command::SaveConfig { config }).await.map_err(Into::into)
     }
 
     #[allow(dead_code)]
     pub async fn list_entries(&self) -> Result<Response> {
-        let mut client = IpcClient::connect(&self.socket_path).await?;
+        let mut client = self.connect().await?;
         client.send(Command::ListEntries { at_time: None }).await.map_err(Into::into)
     }
 }
-
-/// Convert a ReasonCode enum variant to a human-readable message
-fn reason_to_message(reason: &ReasonCode) -> &'static str {
-    match reason {
-        ReasonCode::OutsideTimeWindow { .. } => "Outside allowed time window",
-        ReasonCode::QuotaExhausted { .. } => "Daily quota exhausted",
-        ReasonCode::CooldownActive { .. } => "Cooldown period active",
-        ReasonCode::SessionActive { .. } => "Another session is active",
-        ReasonCode::UnsupportedKind { .. } => "Entry type not supported",
-        ReasonCode::Disabled { .. } => "Entry disabled",
-    }
-}
@@ -0,0 +1,16 @@
+//! Shepherd Launcher UI
+//!
+//! The launcher grid as a library, so `shepherd-shell` can run it in the
+//! same process as the HUD. The `shepherd-launcher` binary is a thin
+//! wrapper around [`LauncherApp`].
+
+pub mod app;
+mod cache;
+mod client;
+mod grid;
+mod setup;
+pub mod state;
+mod tile;
+
+pub use app::LauncherApp;
+pub use state::SharedState;
@@ -3,15 +3,10 @@
 //! This is the primary user-facing shell for the kiosk-style environment.
 //! It displays available entries from shepherdd and allows launching them.
 
-mod app;
-mod client;
-mod grid;
-mod state;
-mod tile;
-
 use anyhow::Result;
 use clap::Parser;
-use shepherd_util::default_socket_path;
+use shepherd_launcher_ui::LauncherApp;
+use shepherd_util::{default_socket_path, SeatId};
 use std::path::PathBuf;
 use tracing_subscriber::EnvFilter;
 
@@ -24,6 +19,10 @@ struct Args {
     #[arg(short, long, env = "SHEPHERD_SOCKET")]
     socket: Option<PathBuf>,
 
+    /// Seat this launcher runs on, for multi-seat setups (or set SHEPHERD_SEAT env var)
+    #[arg(long, env = "SHEPHERD_SEAT")]
+    seat: Option<SeatId>,
+
     /// Log level
     #[arg(short, long, default_value = "info")]
     log_level: String,
@@ -46,7 +45,7 @@ fn main() -> Result<()> {
     let socket_path = args.socket.unwrap_or_else(default_socket_path);
 
     // Run GTK application
-    let application = app::LauncherApp::new(socket_path);
+    let application = LauncherApp::new(socket_path, args.seat);
     let exit_code = application.run();
 
     std::process::exit(exit_code);
@@ -0,0 +1,361 @@
+//! First-run setup wizard
+//!
+//! Shown when shepherdd has no config file. A parent picks installed apps,
+//! sets simple time limits and chooses a PIN; the result is saved as the
+//! service config through SaveConfig.
+
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use shepherd_api::DiscoveredApp;
+use shepherd_config::{SetupApp, SetupChoices};
+use shepherd_util::PinHash;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Wizard pages, in order
+const PAGES: [&str; 3] = ["apps", "limits", "pin"];
+
+mod imp {
+    use super::*;
+
+    type FinishCallback = Rc<RefCell<Option<Box<dyn Fn(SetupChoices) + 'static>>>>;
+
+    pub struct SetupWizard {
+        pub pages: gtk4::Stack,
+        pub app_list: gtk4::Box,
+        pub apps: RefCell<Vec<(DiscoveredApp, gtk4::CheckButton)>>,
+        pub apps_requested: Cell<bool>,
+        pub max_run: gtk4::Scale,
+        pub max_run_label: gtk4::Label,
+        pub daily_quota: gtk4::Scale,
+        pub daily_quota_label: gtk4::Label,
+        pub pin: gtk4::PasswordEntry,
+        pub pin_confirm: gtk4::PasswordEntry,
+        pub status: gtk4::Label,
+        pub back_button: gtk4::Button,
+        pub next_button: gtk4::Button,
+        pub on_finish: FinishCallback,
+    }
+
+    impl Default for SetupWizard {
+        fn default() -> Self {
+            Self {
+                pages: gtk4::Stack::new(),
+                app_list: gtk4::Box::new(gtk4::Orientation::Vertical, 8),
+                apps: RefCell::new(Vec::new()),
+                apps_requested: Cell::new(false),
+                max_run: gtk4::Scale::with_range(gtk4::Orientation::Horizontal, 15.0, 180.0, 15.0),
+                max_run_label: gtk4::Label::new(None),
+                daily_quota: gtk4::Scale::with_range(gtk4::Orientation::Horizontal, 0.0, 300.0, 15.0),
+                daily_quota_label: gtk4::Label::new(None),
+                pin: gtk4::PasswordEntry::new(),
+                pin_confirm: gtk4::PasswordEntry::new(),
+                status: gtk4::Label::new(None),
+                back_button: gtk4::Button::with_label("Back"),
+                next_button: gtk4::Button::with_label("Next"),
+                on_finish: Rc::new(RefCell::new(None)),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SetupWizard {
+        const NAME: &'static str = "ShepherdSetupWizard";
+        type Type = super::SetupWizard;
+        type ParentType = gtk4::Box;
+    }
+
+    impl ObjectImpl for SetupWizard {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.set_orientation(gtk4::Orientation::Vertical);
+            obj.set_spacing(24);
+            obj.set_halign(gtk4::Align::Center);
+            obj.set_valign(gtk4::Align::Center);
+            obj.set_width_request(560);
+            obj.add_css_class("setup-box");
+
+            let title = gtk4::Label::new(Some("Welcome! Let's set things up"));
+            title.add_css_class("setup-title");
+            obj.append(&title);
+
+            self.pages.set_transition_type(gtk4::StackTransitionType::SlideLeftRight);
+            self.pages.add_named(&self.build_apps_page(), Some(PAGES[0]));
+            self.pages.add_named(&self.build_limits_page(), Some(PAGES[1]));
+            self.pages.add_named(&self.build_pin_page(), Some(PAGES[2]));
+            obj.append(&self.pages);
+
+            self.status.add_css_class("error-label");
+            self.status.set_wrap(true);
+            obj.append(&self.status);
+
+            let buttons = gtk4::Box::new(gtk4::Orientation::Horizontal, 16);
+            buttons.set_halign(gtk4::Align::End);
+            self.back_button.add_css_class("launcher-tile");
+            self.back_button.set_sensitive(false);
+            self.next_button.add_css_class("launcher-tile");
+            buttons.append(&self.back_button);
+            buttons.append(&self.next_button);
+            obj.append(&buttons);
+
+            let wizard = obj.downgrade();
+            self.next_button.connect_clicked(move |_| {
+                if let Some(wizard) = wizard.upgrade() {
+                    wizard.advance();
+                }
+            });
+            let wizard = obj.downgrade();
+            self.back_button.connect_clicked(move |_| {
+                if let Some(wizard) = wizard.upgrade() {
+                    wizard.go_back();
+                }
+            });
+        }
+    }
+
+    impl SetupWizard {
+        fn build_apps_page(&self) -> gtk4::Box {
+            let page = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+
+            let hint = gtk4::Label::new(Some("Which apps should appear on the home screen?"));
+            hint.add_css_class("setup-hint");
+            page.append(&hint);
+
+            let loading = gtk4::Label::new(Some("Looking for installed apps..."));
+            loading.add_css_class("status-label");
+            self.app_list.append(&loading);
+
+            let scrolled = gtk4::ScrolledWindow::new();
+            scrolled.set_policy(gtk4::PolicyType::Never, gtk4::PolicyType::Automatic);
+            scrolled.set_min_content_height(320);
+            scrolled.set_child(Some(&self.app_list));
+            page.append(&scrolled);
+
+            page
+        }
+
+        fn build_limits_page(&self) -> gtk4::Box {
+            let page = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+
+            let hint = gtk4::Label::new(Some("Longest time in an app at once"));
+            hint.add_css_class("setup-hint");
+            page.append(&hint);
+            self.max_run.set_value(60.0);
+            page.append(&self.max_run);
+            self.max_run_label.add_css_class("status-label");
+            page.append(&self.max_run_label);
+
+            let hint = gtk4::Label::new(Some("Daily time per app"));
+            hint.add_css_class("setup-hint");
+            page.append(&hint);
+            self.daily_quota.set_value(120.0);
+            page.append(&self.daily_quota);
+            self.daily_quota_label.add_css_class("status-label");
+            page.append(&self.daily_quota_label);
+
+            for (scale, label) in [
+                (&self.max_run, &self.max_run_label),
+                (&self.daily_quota, &self.daily_quota_label),
+            ] {
+                scale.set_draw_value(false);
+                label.set_text(&minutes_text(scale_minutes(scale)));
+                let label = label.clone();
+                scale.connect_value_changed(move |scale| {
+                    label.set_text(&minutes_text(scale_minutes(scale)));
+                });
+            }
+
+            page
+        }
+
+        fn build_pin_page(&self) -> gtk4::Box {
+            let page = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+
+            let hint = gtk4::Label::new(Some("Choose a parent PIN (4 to 8 digits)"));
+            hint.add_css_class("setup-hint");
+            page.append(&hint);
+
+            self.pin.set_show_peek_icon(true);
+            self.pin.set_placeholder_text(Some("PIN"));
+            page.append(&self.pin);
+
+            self.pin_confirm.set_show_peek_icon(true);
+            self.pin_confirm.set_placeholder_text(Some("Repeat PIN"));
+            page.append(&self.pin_confirm);
+
+            page
+        }
+    }
+
+    impl WidgetImpl for SetupWizard {}
+    impl BoxImpl for SetupWizard {}
+}
+
+glib::wrapper! {
+    pub struct SetupWizard(ObjectSubclass<imp::SetupWizard>)
+        @extends gtk4::Box, gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable, gtk4::ConstraintTarget, gtk4::Orientable;
+}
+
+impl SetupWizard {
+    pub fn new() -> Self {
+        glib::Object::builder().build()
+    }
+
+    /// Set the callback for when the parent finishes the wizard
+    pub fn connect_finish<F: Fn(SetupChoices) + 'static>(&self, callback: F) {
+        *self.imp().on_finish.borrow_mut() = Some(Box::new(callback));
+    }
+
+    /// Returns true the first time it's called, so apps are only fetched once
+    pub fn request_apps_once(&self) -> bool {
+        !self.imp().apps_requested.replace(true)
+    }
+
+    /// Show the discovered apps as checkboxes
+    pub fn set_apps(&self, apps: Vec<DiscoveredApp>) {
+        let imp = self.imp();
+
+        while let Some(child) = imp.app_list.first_child() {
+            imp.app_list.remove(&child);
+        }
+
+        if apps.is_empty() {
+            let label = gtk4::Label::new(Some(
+                "No apps found. You can add entries to the config file later.",
+            ));
+            label.add_css_class("status-label");
+            label.set_wrap(true);
+            imp.app_list.append(&label);
+        }
+
+        let mut rows = imp.apps.borrow_mut();
+        rows.clear();
+        for app in apps {
+            let check = gtk4::CheckButton::with_label(&app.label);
+            check.add_css_class("setup-app");
+            imp.app_list.append(&check);
+            rows.push((app, check));
+        }
+    }
+
+    /// Show a problem, or clear it with an empty message
+    pub fn set_status(&self, message: &str) {
+        self.imp().status.set_text(message);
+    }
+
+    /// Block navigation while the config is being saved
+    pub fn set_busy(&self, busy: bool) {
+        let imp = self.imp();
+        imp.next_button.set_sensitive(!busy);
+        imp.back_button.set_sensitive(!busy && self.current_page() > 0);
+    }
+
+    fn current_page(&self) -> usize {
+        let name = self.imp().pages.visible_child_name();
+        PAGES
+            .iter()
+            .position(|p| Some(*p) == name.as_deref())
+            .unwrap_or(0)
+    }
+
+    fn show_page(&self, index: usize) {
+        let imp = self.imp();
+        imp.pages.set_visible_child_name(PAGES[index]);
+        imp.back_button.set_sensitive(index > 0);
+        imp.next_button
+            .set_label(if index == PAGES.len() - 1 { "Finish" } else { "Next" });
+    }
+
+    fn advance(&self) {
+        self.set_status("");
+        let page = self.current_page();
+
+        if PAGES[page] == "apps" && !self.imp().apps.borrow().iter().any(|(_, c)| c.is_active()) {
+            self.set_status("Pick at least one app");
+            return;
+        }
+
+        if page + 1 < PAGES.len() {
+            self.show_page(page + 1);
+            return;
+        }
+
+        match self.choices() {
+            Ok(choices) => {
+                if let Some(callback) = self.imp().on_finish.borrow().as_ref() {
+                    callback(choices);
+                }
+            }
+            Err(message) => self.set_status(message),
+        }
+    }
+
+    fn go_back(&self) {
+        self.set_status("");
+        let page = self.current_page();
+        if page > 0 {
+            self.show_page(page - 1);
+        }
+    }
+
+    fn choices(&self) -> Result<SetupChoices, &'static str> {
+        let imp = self.imp();
+
+        let pin = imp.pin.text();
+        if !PinHash::is_valid_pin(&pin) {
+            return Err("The PIN must be 4 to 8 digits");
+        }
+        if pin != imp.pin_confirm.text() {
+            return Err("The PINs don't match");
+        }
+
+        // Distinct desktop files can map to the same entry ID
+        let mut seen = HashSet::new();
+        let apps = imp
+            .apps
+            .borrow()
+            .iter()
+            .filter(|(app, check)| check.is_active() && seen.insert(app.id.clone()))
+            .map(|(app, _)| SetupApp {
+                id: app.id.clone(),
+                label: app.label.clone(),
+                icon: app.icon.clone(),
+                kind: app.kind.clone(),
+            })
+            .collect();
+
+        let daily_minutes = scale_minutes(&imp.daily_quota);
+        Ok(SetupChoices {
+            apps,
+            max_run: Some(Duration::from_secs(scale_minutes(&imp.max_run) * 60)),
+            daily_quota: (daily_minutes > 0).then(|| Duration::from_secs(daily_minutes * 60)),
+            admin_pin: Some(PinHash::new(&pin)),
+        })
+    }
+}
+
+impl Default for SetupWizard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn scale_minutes(scale: &gtk4::Scale) -> u64 {
+    scale.value().round().max(0.0) as u64
+}
+
+/// Slider caption; 0 means no limit
+fn minutes_text(minutes: u64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, 0) => "No limit".to_string(),
+        (0, m) => format!("{} min", m),
+        (h, 0) => format!("{} h", h),
+        (h, m) => format!("{} h {} min", h, m),
+    }
+}
@@ -1,10 +1,20 @@
 //! Launcher application state management
 
-use shepherd_api::{ServiceStateSnapshot, EntryView, Event, EventPayload};
+use shepherd_api::{
+    CheckInRequest, Hold, ServiceStateSnapshot, EntryView, Event, EventPayload, PendingPowerAction,
+    SessionEndReason, SessionInfo, SessionState, SessionSummary, Theme,
+};
+use chrono::{DateTime, Local};
 use shepherd_util::SessionId;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::watch;
 
+use crate::cache::EntryCache;
+
+/// How long to keep showing cached entries before admitting shepherdd is down
+pub const OFFLINE_GRACE: Duration = Duration::from_secs(60);
+
 /// Current state of the launcher UI
 #[derive(Debug, Clone, Default)]
 pub enum LauncherState {
@@ -15,19 +25,33 @@ pub enum LauncherState {
     Connecting,
     /// Connected, no session running - show grid
     Idle { entries: Vec<EntryView> },
+    /// Not connected yet, showing the last-known grid read-only
+    Offline { entries: Vec<EntryView> },
     /// Launch requested, waiting for response
     Launching {
         #[allow(dead_code)]
         entry_id: String
     },
+    /// Entry asks before starting - show the granted time and wait for OK
+    ConfirmLaunch {
+        session_id: SessionId,
+        entry_label: String,
+        /// None means unlimited
+        max_duration: Option<Duration>,
+        deadline: Option<DateTime<Local>>,
+    },
     /// Session is running
     SessionActive {
         #[allow(dead_code)]
         session_id: SessionId,
         entry_label: String,
+        /// Where the session is, as far as the launcher knows
+        state: SessionState,
         #[allow(dead_code)]
         time_remaining: Option<Duration>,
     },
+    /// No config yet - show the first-run setup wizard
+    Setup,
     /// Error state
     Error { message: String },
 }
@@ -37,12 +61,67 @@ pub enum LauncherState {
 pub struct SharedState {
     sender: watch::Sender<LauncherState>,
     receiver: watch::Receiver<LauncherState>,
+    /// Curfew shutdown/suspend countdown, shown as an overlay over any view
+    power_sender: watch::Sender<Option<PendingPowerAction>>,
+    power_receiver: watch::Receiver<Option<PendingPowerAction>>,
+    /// Ended session awaiting a "How do you feel?" answer
+    check_in_sender: watch::Sender<Option<CheckInRequest>>,
+    check_in_receiver: watch::Receiver<Option<CheckInRequest>>,
+    /// Reward stars shown on the profile header
+    stars_sender: watch::Sender<u32>,
+    stars_receiver: watch::Receiver<u32>,
+    /// Admin launch hold, shown as a banner over the grid
+    hold_sender: watch::Sender<Option<Hold>>,
+    hold_receiver: watch::Receiver<Option<Hold>>,
+    /// Why the last launch failed, shown as a banner over the grid
+    launch_error_sender: watch::Sender<Option<String>>,
+    launch_error_receiver: watch::Receiver<Option<String>>,
+    /// What the last session used, shown as a card over the grid
+    summary_sender: watch::Sender<Option<SessionSummary>>,
+    summary_receiver: watch::Receiver<Option<SessionSummary>>,
+    /// Theme from the service config
+    theme_sender: watch::Sender<Theme>,
+    theme_receiver: watch::Receiver<Theme>,
+    /// Last-known entries, and since when we've been showing them offline
+    entry_cache: Arc<Mutex<EntryCache>>,
+    offline_since: Arc<Mutex<Option<Instant>>>,
 }
 
 impl SharedState {
     pub fn new() -> Self {
         let (sender, receiver) = watch::channel(LauncherState::default());
-        Self { sender, receiver }
+        let (power_sender, power_receiver) = watch::channel(None);
+        let (check_in_sender, check_in_receiver) = watch::channel(None);
+        let (stars_sender, stars_receiver) = watch::channel(0);
+        let (hold_sender, hold_receiver) = watch::channel(None);
+        let (launch_error_sender, launch_error_receiver) = watch::channel(None);
+        let (summary_sender, summary_receiver) = watch::channel(None);
+        let (theme_sender, theme_receiver) = watch::channel(Theme::default());
+        Self {
+            sender,
+            receiver,
+            power_sender,
+            power_receiver,
+            check_in_sender,
+            check_in_receiver,
+            stars_sender,
+            stars_receiver,
+            hold_sender,
+            hold_receiver,
+            launch_error_sender,
+            launch_error_receiver,
+            summary_sender,
+            summary_receiver,
+            theme_sender,
+            theme_receiver,
+            entry_cache: Arc::new(Mutex::new(EntryCache::default())),
+            offline_since: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Use entries saved by a previous run while shepherdd is unreachable
+    pub fn load_entry_cache(&self) {
+        *self.entry_cache.lock().unwrap() = EntryCache::load();
     }
 
     pub fn set(&self, state: LauncherState) {
@@ -57,6 +136,120 @@ impl SharedState {
         self.receiver.clone()
     }
 
+    /// shepherdd can't be reached. Show the cached grid read-only for a
+    /// while, since it's usually just restarting, then the disconnected
+    /// screen.
+    pub fn set_disconnected(&self) {
+        let now = Instant::now();
+        let mut offline_since = self.offline_since.lock().unwrap();
+        let since = *offline_since.get_or_insert(now);
+        let within_grace = now.duration_since(since) < OFFLINE_GRACE;
+        if within_grace && self.is_offline() {
+            // Already showing the cached grid; don't rebuild it on every retry
+            return;
+        }
+        let entries = self.entry_cache.lock().unwrap().entries().map(<[_]>::to_vec);
+        match entries {
+            Some(entries) if within_grace => {
+                self.set(LauncherState::Offline { entries })
+            }
+            _ => self.set(LauncherState::Disconnected),
+        }
+    }
+
+    /// Whether we're still showing the cached grid
+    pub fn is_offline(&self) -> bool {
+        matches!(*self.receiver.borrow(), LauncherState::Offline { .. })
+    }
+
+    pub fn set_power_action(&self, pending: Option<PendingPowerAction>) {
+        let _ = self.power_sender.send(pending);
+    }
+
+    pub fn subscribe_power_action(&self) -> watch::Receiver<Option<PendingPowerAction>> {
+        self.power_receiver.clone()
+    }
+
+    pub fn set_check_in(&self, pending: Option<CheckInRequest>) {
+        self.check_in_sender.send_if_modified(|current| {
+            if *current == pending {
+                return false;
+            }
+            *current = pending;
+            true
+        });
+    }
+
+    pub fn subscribe_check_in(&self) -> watch::Receiver<Option<CheckInRequest>> {
+        self.check_in_receiver.clone()
+    }
+
+    pub fn set_stars(&self, stars: u32) {
+        self.stars_sender.send_if_modified(|current| {
+            let changed = *current != stars;
+            *current = stars;
+            changed
+        });
+    }
+
+    pub fn subscribe_stars(&self) -> watch::Receiver<u32> {
+        self.stars_receiver.clone()
+    }
+
+    pub fn set_hold(&self, hold: Option<Hold>) {
+        self.hold_sender.send_if_modified(|current| {
+            if *current == hold {
+                return false;
+            }
+            *current = hold;
+            true
+        });
+    }
+
+    pub fn subscribe_hold(&self) -> watch::Receiver<Option<Hold>> {
+        self.hold_receiver.clone()
+    }
+
+    pub fn set_launch_error(&self, message: Option<String>) {
+        self.launch_error_sender.send_if_modified(|current| {
+            if *current == message {
+                return false;
+            }
+            *current = message;
+            true
+        });
+    }
+
+    pub fn subscribe_launch_error(&self) -> watch::Receiver<Option<String>> {
+        self.launch_error_receiver.clone()
+    }
+
+    pub fn set_session_summary(&self, summary: Option<SessionSummary>) {
+        self.summary_sender.send_if_modified(|current| {
+            if *current == summary {
+                return false;
+            }
+            *current = summary;
+            true
+        });
+    }
+
+    pub fn subscribe_session_summary(&self) -> watch::Receiver<Option<SessionSummary>> {
+        self.summary_receiver.clone()
+    }
+
+    pub fn set_theme(&self, theme: Theme) {
+        self.theme_sender.send_if_modified(|current| {
+            let changed = *current != theme;
+            *current = theme;
+            changed
+        });
+    }
+
+    pub fn subscribe_theme(&self) -> watch::Receiver<Theme> {
+        self.theme_receiver.clone()
+    }
+
     /// Update state from shepherdd event
     pub fn handle_event(&self, event: Event) {
         tracing::info!(event = ?event.payload, "Received event from shepherdd");
@@ -70,6 +263,7 @@ impl SharedState {
                 entry_id: _,
                 label,
                 deadline,
+                ..
             } => {
                 tracing::info!(session_id = %session_id, label = %label, "Session started event");
                 let now = shepherd_util::now();
@@ -84,20 +278,42 @@ impl SharedState {
                 self.set(LauncherState::SessionActive {
                     session_id,
                     entry_label: label,
+                    state: SessionState::Running,
                     time_remaining,
                 });
             }
             EventPayload::SessionEnded { session_id, entry_id, reason, .. } => {
                 tracing::info!(session_id = %session_id, entry_id = %entry_id, reason = ?reason, "Session ended event - setting Connecting");
+                if let SessionEndReason::LaunchFailed { error, diagnostics } = reason {
+                    self.set_launch_error(Some(match diagnostics {
+                        Some(d) => d.summary(),
+                        None => error,
+                    }));
+                }
                 // Will be followed by StateChanged, but set to connecting
                 // to ensure grid reloads
                 self.set(LauncherState::Connecting);
             }
-            EventPayload::SessionExpiring { .. } => {
-                // Time's up indicator handled by HUD
+            EventPayload::SessionSummary(summary) => {
+                // A failed launch is explained by the launch error banner
+                if !summary.show_for.is_zero()
+                    && !matches!(
+                    summary.reason,
+                    SessionEndReason::LaunchFailed { .. } | SessionEndReason::ServiceShutdown
+                ) {
+                    self.set_session_summary(Some(summary));
+                }
+            }
+            EventPayload::SessionExpiring { session_id } => {
+                // Time's up overlay shown by HUD
+                self.set_session_state(&session_id, SessionState::Expiring);
+            }
+            EventPayload::TimeRemaining { .. } => {
+                // Countdown shown by HUD
             }
-            EventPayload::WarningIssued { .. } => {
-                // Warnings handled by HUD
+            EventPayload::WarningIssued { session_id, .. } => {
+                // Warnings shown by HUD
+                self.set_session_state(&session_id, SessionState::Warned);
             }
             EventPayload::PolicyReloaded { .. } => {
                 // Request fresh state
@@ -108,40 +324,107 @@ impl SharedState {
                 self.set(LauncherState::Connecting);
             }
             EventPayload::Shutdown => {
-                // Service is shutting down
-                self.set(LauncherState::Disconnected);
+                // Service is shutting down, probably to restart
+                self.set_disconnected();
             }
             EventPayload::AuditEntry { .. } => {
                 // Audit events are for admin clients, ignore
             }
-            EventPayload::VolumeChanged { .. } => {
+            EventPayload::ClientReplaced { .. } => {
+                // The client loop exits if it's about this process
+            }
+            EventPayload::BudgetAlert { .. } => {
+                // Budget alerts are for parents, ignore
+            }
+            EventPayload::VolumeChanged { .. } | EventPayload::VolumeRestrictionsChanged { .. } => {
                 // Volume events are handled by HUD
             }
+            EventPayload::BatteryStatus { .. } => {
+                // Battery status is shown by HUD
+            }
+            EventPayload::MediaChanged { .. } => {
+                // Media is shown by HUD
+            }
+            EventPayload::PowerActionScheduled { action, deadline } => {
+                tracing::info!(action = ?action, deadline = %deadline, "Curfew power action scheduled");
+                self.set_power_action(Some(PendingPowerAction { action, deadline }));
+            }
+            EventPayload::PowerActionCancelled => {
+                self.set_power_action(None);
+            }
+            EventPayload::ThemeChanged { theme } => {
+                self.set_theme(theme);
+            }
         }
     }
 
-    fn apply_snapshot(&self, snapshot: ServiceStateSnapshot) {
-        if let Some(session) = snapshot.current_session {
-            let now = shepherd_util::now();
-            // For unlimited sessions (deadline=None), time_remaining is None
-            let time_remaining = session.deadline.and_then(|d| {
-                if d > now {
-                    (d - now).to_std().ok()
-                } else {
-                    Some(Duration::ZERO)
-                }
-            });
-            self.set(LauncherState::SessionActive {
-                session_id: session.session_id,
-                entry_label: session.label,
-                time_remaining,
-            });
+    pub fn apply_snapshot(&self, snapshot: ServiceStateSnapshot) {
+        *self.offline_since.lock().unwrap() = None;
+        if snapshot.policy_loaded {
+            self.entry_cache.lock().unwrap().update(&snapshot.entries);
+        }
+        self.set_power_action(snapshot.pending_power_action);
+        self.set_check_in(snapshot.pending_check_in);
+        self.set_stars(snapshot.total_stars);
+        self.set_hold(snapshot.hold);
+        self.set_theme(snapshot.theme);
+
+        if !snapshot.policy_loaded {
+            self.set(LauncherState::Setup);
+        } else if let Some(resumed) = snapshot.current_session.and_then(resume_session) {
+            self.set(resumed);
         } else {
             self.set(LauncherState::Idle {
                 entries: snapshot.entries,
             });
         }
     }
+
+    /// Move the shown session on to `state`; a later state never goes back
+    fn set_session_state(&self, id: &SessionId, state: SessionState) {
+        self.sender.send_if_modified(|current| match current {
+            LauncherState::SessionActive {
+                session_id,
+                state: shown,
+                ..
+            } if session_id == id && session_rank(*shown) < session_rank(state) => {
+                *shown = state;
+                true
+            }
+            _ => false,
+        });
+    }
+}
+
+/// The view for the session in a state snapshot, taken as it is rather
+/// than as the launcher last saw it, so a launcher that restarted
+/// mid-session picks up where the session is. A session that has ended but
+/// not yet been cleared shows the grid.
+pub fn resume_session(session: SessionInfo) -> Option<LauncherState> {
+    match session.state {
+        SessionState::Ended => None,
+        state @ (SessionState::Launching
+        | SessionState::Running
+        | SessionState::Warned
+        | SessionState::Expiring) => Some(LauncherState::SessionActive {
+            session_id: session.session_id,
+            entry_label: session.label,
+            state,
+            // The service's count, which allows for pauses
+            time_remaining: session.time_remaining,
+        }),
+    }
+}
+
+/// Order of a session's states
+fn session_rank(state: SessionState) -> u8 {
+    match state {
+        SessionState::Launching => 0,
+        SessionState::Running => 1,
+        SessionState::Warned => 2,
+        SessionState::Expiring => 3,
+        SessionState::Ended => 4,
+    }
 }
 
 impl Default for SharedState {
@@ -149,3 +432,90 @@ impl Default for SharedState {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shepherd_util::EntryId;
+
+    fn session(state: SessionState) -> SessionInfo {
+        SessionInfo {
+            session_id: SessionId::new(),
+            entry_id: EntryId::new("game"),
+            label: "Game".into(),
+            state,
+            started_at: shepherd_util::now(),
+            deadline: None,
+            time_remaining: Some(Duration::from_secs(600)),
+            elapsed: Duration::from_secs(60),
+            charged: Duration::from_secs(60),
+            warnings_issued: vec![],
+            device_access: Default::default(),
+            participants: vec![],
+        }
+    }
+
+    #[test]
+    fn test_resume_into_every_session_state() {
+        for state in [
+            SessionState::Launching,
+            SessionState::Running,
+            SessionState::Warned,
+            SessionState::Expiring,
+        ] {
+            let info = session(state);
+            let id = info.session_id.clone();
+            match resume_session(info) {
+                Some(LauncherState::SessionActive {
+                    session_id,
+                    entry_label,
+                    state: resumed,
+                    time_remaining,
+                }) => {
+                    assert_eq!(session_id, id);
+                    assert_eq!(entry_label, "Game");
+                    assert_eq!(resumed, state);
+                    assert_eq!(time_remaining, Some(Duration::from_secs(600)));
+                }
+                other => panic!("{:?} resumed as {:?}", state, other),
+            }
+        }
+
+        // Ended but not yet cleared: back to the grid
+        assert!(resume_session(session(SessionState::Ended)).is_none());
+    }
+
+    #[test]
+    fn test_session_state_follows_events() {
+        let shared = SharedState::new();
+        let info = session(SessionState::Running);
+        let id = info.session_id.clone();
+        shared.set(resume_session(info).unwrap());
+
+        let state_of = |shared: &SharedState| match shared.get() {
+            LauncherState::SessionActive { state, .. } => state,
+            other => panic!("expected an active session, got {:?}", other),
+        };
+
+        // Another session's events don't apply
+        shared.handle_event(Event::new(EventPayload::SessionExpiring {
+            session_id: SessionId::new(),
+        }));
+        assert_eq!(state_of(&shared), SessionState::Running);
+
+        shared.handle_event(Event::new(EventPayload::SessionExpiring {
+            session_id: id.clone(),
+        }));
+        assert_eq!(state_of(&shared), SessionState::Expiring);
+
+        // A late warning doesn't undo the expiry
+        shared.handle_event(Event::new(EventPayload::WarningIssued {
+            session_id: id,
+            threshold_seconds: 60,
+            time_remaining: Duration::from_secs(60),
+            severity: shepherd_api::WarningSeverity::Warn,
+            message: Some("One minute left".into()),
+        }));
+        assert_eq!(state_of(&shared), SessionState::Expiring);
+    }
+}
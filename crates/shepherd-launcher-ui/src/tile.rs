@@ -3,7 +3,7 @@
 use gtk4::glib;
 use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
-use shepherd_api::EntryView;
+use shepherd_api::{EntryView, Locale, ReasonCode};
 use std::cell::RefCell;
 
 mod imp {
@@ -14,6 +14,7 @@ mod imp {
         pub entry: RefCell<Option<EntryView>>,
         pub icon: gtk4::Image,
         pub label: gtk4::Label,
+        pub quota_label: gtk4::Label,
     }
 
     #[glib::object_subclass]
@@ -47,6 +48,11 @@ mod imp {
             self.label.add_css_class("tile-label");
             content.append(&self.label);
 
+            // Remaining time, including any earned time
+            self.quota_label.add_css_class("tile-quota");
+            self.quota_label.set_visible(false);
+            content.append(&self.quota_label);
+
             obj.set_child(Some(&content));
             obj.add_css_class("launcher-tile");
             obj.add_css_class("flat");
@@ -82,6 +88,7 @@ impl LauncherTile {
             shepherd_api::EntryKindTag::Flatpak => "application-x-executable",
             shepherd_api::EntryKindTag::Vm => "computer",
             shepherd_api::EntryKindTag::Media => "video-x-generic",
+            shepherd_api::EntryKindTag::Retro => "input-gaming",
             shepherd_api::EntryKindTag::Custom => "applications-other",
         };
 
@@ -125,10 +132,17 @@ impl LauncherTile {
         let available = entry.enabled && entry.reasons.is_empty();
         self.set_sensitive(available);
 
+        match quota_text(&entry) {
+            Some(text) => {
+                imp.quota_label.set_text(&text);
+                imp.quota_label.set_visible(true);
+            }
+            None => imp.quota_label.set_visible(false),
+        }
+
         // Add tooltip with reason if not available
         if !available && !entry.reasons.is_empty() {
-            // Format the first reason for tooltip
-            let reason_text = format!("{:?}", entry.reasons[0]);
+            let reason_text = entry.reasons[0].message(Locale::from_env());
             self.set_tooltip_text(Some(&reason_text));
         } else {
             self.set_tooltip_text(None);
@@ -146,6 +160,25 @@ impl LauncherTile {
     }
 }
 
+/// Format the remaining-time label, e.g. "25 min left (+10 earned)",
+/// or when downtime ends, e.g. "Homework until 18:00"
+fn quota_text(entry: &EntryView) -> Option<String> {
+    if let Some(downtime) = entry
+        .reasons
+        .iter()
+        .find(|r| matches!(r, ReasonCode::Downtime { .. }))
+    {
+        return Some(downtime.message(Locale::from_env()));
+    }
+
+    let remaining = entry.max_run_if_started_now.filter(|_| entry.enabled)?;
+    let mut text = format!("{} min left", remaining.as_secs().div_ceil(60));
+    if let Some(earned) = entry.time_bank {
+        text.push_str(&format!(" (+{} earned)", earned.as_secs() / 60));
+    }
+    Some(text)
+}
+
 impl Default for LauncherTile {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,138 @@
+//! shepherd-session - set up a kiosk session from a shepherd config
+//!
+//! Writes a sway config that starts the kiosk, systemd user units for
+//! shepherdd and the launcher and HUD (or shepherd-shell), and notes on
+//! the autologin and VT settings that need root. Running it again after
+//! changing the config brings the session up to date.
+
+mod render;
+
+use anyhow::{Context, Result, bail};
+use clap::Parser;
+use render::{GENERATED_MARKER, GeneratedFile, SessionOptions};
+use shepherd_util::default_config_path;
+use std::path::{Path, PathBuf};
+
+/// shepherd-session - generate the sway config and systemd user units for a kiosk
+#[derive(Parser, Debug)]
+#[command(name = "shepherd-session")]
+#[command(about = "Generate the sway config and systemd user units for a shepherd kiosk", long_about = None)]
+struct Args {
+    /// Configuration file shepherdd will run with
+    #[arg(short, long, default_value_os_t = default_config_path())]
+    config: PathBuf,
+
+    /// Directory to write into (default: $XDG_CONFIG_HOME or ~/.config)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Where the shepherd binaries are installed
+    #[arg(long, default_value = "/usr/local/bin")]
+    bindir: PathBuf,
+
+    /// Run the launcher and HUD in one shepherd-shell process
+    #[arg(long)]
+    combined: bool,
+
+    /// HUD anchor position (top, bottom)
+    #[arg(long, default_value = "top")]
+    hud_anchor: String,
+
+    /// The account the kiosk logs in as (default: the current user)
+    #[arg(long, env = "USER")]
+    user: String,
+
+    /// Overwrite existing files that shepherd-session didn't write
+    #[arg(short, long)]
+    force: bool,
+
+    /// Print what would be written without writing anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    // Units outlive this shell, so relative paths would break
+    let config_path = std::path::absolute(&args.config)
+        .with_context(|| format!("Invalid config path {:?}", args.config))?;
+    let policy = shepherd_config::load_config(&config_path)
+        .with_context(|| format!("Failed to load config from {:?}", config_path))?;
+    let options = SessionOptions {
+        config_path,
+        bindir: std::path::absolute(&args.bindir)?,
+        combined: args.combined,
+        hud_anchor: args.hud_anchor,
+        user: args.user,
+    };
+
+    let output = match args.output {
+        Some(output) => output,
+        None => default_output_dir()?,
+    };
+    let files = render::render(&policy, &options);
+
+    for warning in render::warnings(&policy, &options) {
+        eprintln!("warning: {}", warning);
+    }
+
+    if args.dry_run {
+        for file in &files {
+            println!("==> {} <==\n{}", output.join(&file.path).display(), file.contents);
+        }
+        return Ok(());
+    }
+
+    let changed = locally_changed(&output, &files);
+    if !changed.is_empty() && !args.force {
+        bail!(
+            "These files exist and were not written by shepherd-session (use --force to overwrite):\n  {}",
+            changed
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join("\n  ")
+        );
+    }
+
+    for file in &files {
+        let path = output.join(&file.path);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {:?}", dir))?;
+        }
+        std::fs::write(&path, &file.contents)
+            .with_context(|| format!("Failed to write {:?}", path))?;
+        println!("wrote {}", path.display());
+    }
+
+    println!();
+    println!("Next steps:");
+    println!("  systemctl --user daemon-reload");
+    println!("  Log in to sway as {} (see {})", options.user, output.join("shepherd/AUTOLOGIN.md").display());
+    Ok(())
+}
+
+fn default_output_dir() -> Result<PathBuf> {
+    if let Ok(config_home) = std::env::var("XDG_CONFIG_HOME") {
+        return Ok(PathBuf::from(config_home));
+    }
+    let home = std::env::var("HOME").context("Neither XDG_CONFIG_HOME nor HOME is set; pass --output")?;
+    Ok(PathBuf::from(home).join(".config"))
+}
+
+/// Existing files that would be replaced but weren't generated by us,
+/// e.g. a hand-written sway config
+fn locally_changed(output: &Path, files: &[GeneratedFile]) -> Vec<PathBuf> {
+    files
+        .iter()
+        .map(|file| (output.join(&file.path), &file.contents))
+        .filter(|(path, contents)| {
+            std::fs::read_to_string(path).is_ok_and(|existing| {
+                existing != **contents && !existing.contains(GENERATED_MARKER)
+            })
+        })
+        .map(|(path, _)| path)
+        .collect()
+}
@@ -0,0 +1,362 @@
+//! The generated files
+//!
+//! Everything here is a pure function of the policy and the options, so
+//! the output can be checked without touching the filesystem.
+
+use shepherd_api::EntryKind;
+use shepherd_config::Policy;
+use shepherd_util::SHEPHERD_SOCKET_ENV;
+use std::path::{Path, PathBuf};
+
+/// Every generated file says so, so re-running can replace it
+pub const GENERATED_MARKER: &str = "Generated by shepherd-session";
+
+const SWAY_TEMPLATE: &str = include_str!("sway.conf");
+const AUTOLOGIN_TEMPLATE: &str = include_str!("autologin.md");
+
+const MINECRAFT_RULES: &str = "
+# Minecraft Launcher and the game itself (the game's class includes its version)
+for_window [class=\"^Minecraft [0-9]\"] border none, focus
+for_window [instance=\"^Minecraft [0-9]\"] border none, focus
+for_window [class=\"^Minecraft Launcher$\"] border none
+for_window [instance=\"^minecraft-launcher$\"] border none
+";
+
+const STEAM_RULES: &str = "
+# Keep the Steam client's own windows out of sight while a game starts
+for_window [class=\"^[Ss]team$\"] floating enable, opacity 0, move position -9999 -9999
+for_window [instance=\"^[Ss]team$\"] floating enable, opacity 0, move position -9999 -9999
+for_window [title=\"^Steam$\"] floating enable, opacity 0, move position -9999 -9999
+for_window [title=\"^Sign in to Steam$\"] floating enable, opacity 0, move position -9999 -9999
+for_window [title=\"^Steam - News\"] floating enable, opacity 0, move position -9999 -9999
+";
+
+/// How the session should be put together
+#[derive(Debug, Clone)]
+pub struct SessionOptions {
+    /// Config shepherdd is started with
+    pub config_path: PathBuf,
+    /// Where the shepherd binaries are installed
+    pub bindir: PathBuf,
+    /// Run shepherd-shell instead of a separate launcher and HUD
+    pub combined: bool,
+    /// HUD anchor position (top, bottom)
+    pub hud_anchor: String,
+    /// The account the kiosk logs in as
+    pub user: String,
+}
+
+/// A file to write, relative to the output directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedFile {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+impl GeneratedFile {
+    fn new(path: impl Into<PathBuf>, contents: String) -> Self {
+        Self {
+            path: path.into(),
+            contents,
+        }
+    }
+}
+
+/// Everything the kiosk session needs
+pub fn render(policy: &Policy, options: &SessionOptions) -> Vec<GeneratedFile> {
+    let units = Path::new("systemd/user");
+    let mut files = vec![
+        GeneratedFile::new("sway/config", sway_config(policy, options)),
+        GeneratedFile::new(units.join("shepherd-kiosk.target"), kiosk_target(options)),
+        GeneratedFile::new(units.join("shepherdd.service"), daemon_unit(policy, options)),
+    ];
+    if options.combined {
+        files.push(GeneratedFile::new(
+            units.join("shepherd-shell.service"),
+            client_unit(
+                policy,
+                options,
+                "Shepherd launcher and HUD",
+                "shepherd-shell",
+                &["--anchor", &options.hud_anchor],
+            ),
+        ));
+    } else {
+        files.push(GeneratedFile::new(
+            units.join("shepherd-launcher.service"),
+            client_unit(policy, options, "Shepherd launcher", "shepherd-launcher", &[]),
+        ));
+        files.push(GeneratedFile::new(
+            units.join("shepherd-hud.service"),
+            client_unit(
+                policy,
+                options,
+                "Shepherd HUD",
+                "shepherd-hud",
+                &["--anchor", &options.hud_anchor],
+            ),
+        ));
+    }
+    files.push(GeneratedFile::new(
+        "shepherd/AUTOLOGIN.md",
+        AUTOLOGIN_TEMPLATE
+            .replace("{user}", &options.user)
+            .replace("{config_path}", &options.config_path.display().to_string()),
+    ));
+    files
+}
+
+/// Problems with the policy that would leave the generated session broken
+pub fn warnings(policy: &Policy, options: &SessionOptions) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let trusted = &policy.service.shell_executables;
+    if !trusted.is_empty() {
+        for binary in client_binaries(options) {
+            let path = options.bindir.join(binary);
+            if !trusted.contains(&path) {
+                warnings.push(format!(
+                    "shell_executables does not list {}; it will not be able to launch or stop sessions",
+                    path.display()
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+fn client_binaries(options: &SessionOptions) -> &'static [&'static str] {
+    if options.combined {
+        &["shepherd-shell"]
+    } else {
+        &["shepherd-launcher", "shepherd-hud"]
+    }
+}
+
+fn sway_config(policy: &Policy, options: &SessionOptions) -> String {
+    let mut app_rules = String::new();
+    if policy.entries.iter().any(|e| mentions(&e.kind, &["minecraft", "mc-installer", "prismlauncher"])) {
+        app_rules.push_str(MINECRAFT_RULES);
+    }
+    if policy.entries.iter().any(|e| mentions(&e.kind, &["steam"])) {
+        app_rules.push_str(STEAM_RULES);
+    }
+    SWAY_TEMPLATE
+        .replace("{config_path}", &options.config_path.display().to_string())
+        .replace("{app_rules}", &app_rules)
+}
+
+/// Whether an entry launches something named like one of `needles`
+fn mentions(kind: &EntryKind, needles: &[&str]) -> bool {
+    let name = match kind {
+        EntryKind::Process { command, .. } => command.as_str(),
+        EntryKind::Snap { snap_name, .. } => snap_name.as_str(),
+        EntryKind::Flatpak { app_id, .. } => app_id.as_str(),
+        _ => return false,
+    };
+    let name = name.to_lowercase();
+    needles.iter().any(|needle| name.contains(needle))
+}
+
+fn unit_header(options: &SessionOptions) -> String {
+    format!("# {} from {}\n", GENERATED_MARKER, options.config_path.display())
+}
+
+fn kiosk_target(options: &SessionOptions) -> String {
+    let mut wants = vec!["shepherdd.service".to_string()];
+    wants.extend(client_binaries(options).iter().map(|b| format!("{}.service", b)));
+    format!(
+        "{}[Unit]\n\
+         Description=Shepherd kiosk session\n\
+         Wants={}\n",
+        unit_header(options),
+        wants.join(" ")
+    )
+}
+
+fn daemon_unit(policy: &Policy, options: &SessionOptions) -> String {
+    let exec = exec_line(
+        &options.bindir.join("shepherdd"),
+        &[
+            "--config",
+            &options.config_path.display().to_string(),
+            "--log-format",
+            "journald",
+        ],
+    );
+    format!(
+        "{}[Unit]\n\
+         Description=Shepherd policy service\n\
+         PartOf=shepherd-kiosk.target\n\
+         \n\
+         [Service]\n\
+         ExecStart={}\n\
+         {}\n\
+         Restart=on-failure\n\
+         RestartSec=2\n",
+        unit_header(options),
+        exec,
+        socket_env(policy)
+    )
+}
+
+fn client_unit(
+    policy: &Policy,
+    options: &SessionOptions,
+    description: &str,
+    binary: &str,
+    args: &[&str],
+) -> String {
+    format!(
+        "{}[Unit]\n\
+         Description={}\n\
+         PartOf=shepherd-kiosk.target\n\
+         Wants=shepherdd.service\n\
+         After=shepherdd.service\n\
+         \n\
+         [Service]\n\
+         ExecStart={}\n\
+         {}\n\
+         Restart=always\n\
+         RestartSec=1\n",
+        unit_header(options),
+        description,
+        exec_line(&options.bindir.join(binary), args),
+        socket_env(policy)
+    )
+}
+
+/// Pin every unit to the socket the policy names, whatever the unit's
+/// own environment says
+fn socket_env(policy: &Policy) -> String {
+    format!(
+        "Environment={}",
+        quote(&format!(
+            "{}={}",
+            SHEPHERD_SOCKET_ENV,
+            policy.service.socket_path.display()
+        ))
+    )
+}
+
+fn exec_line(program: &Path, args: &[&str]) -> String {
+    std::iter::once(quote(&program.display().to_string()))
+        .chain(args.iter().map(|arg| quote(arg)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Quote a word for a unit file. `%` starts a specifier there, so it's
+/// always doubled.
+fn quote(word: &str) -> String {
+    let word = word.replace('%', "%%");
+    if word.is_empty() || word.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';')) {
+        format!("\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        word
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(extra: &str) -> Policy {
+        shepherd_config::parse_config(&format!(
+            r#"
+            config_version = 1
+            {}
+
+            [[entries]]
+            id = "supertux"
+            label = "SuperTux"
+            kind = {{ type = "process", command = "supertux2" }}
+            "#,
+            extra
+        ))
+        .unwrap()
+    }
+
+    fn options(combined: bool) -> SessionOptions {
+        SessionOptions {
+            config_path: PathBuf::from("/home/kid/.config/shepherd/config.toml"),
+            bindir: PathBuf::from("/usr/local/bin"),
+            combined,
+            hud_anchor: "top".into(),
+            user: "kid".into(),
+        }
+    }
+
+    fn file<'a>(files: &'a [GeneratedFile], path: &str) -> &'a str {
+        &files
+            .iter()
+            .find(|f| f.path == Path::new(path))
+            .unwrap_or_else(|| panic!("{} not generated", path))
+            .contents
+    }
+
+    #[test]
+    fn test_render_units() {
+        let policy = policy("[service]\nsocket_path = \"/run/shepherd/shepherdd.sock\"");
+        let files = render(&policy, &options(false));
+
+        let daemon = file(&files, "systemd/user/shepherdd.service");
+        assert!(daemon.contains(
+            "ExecStart=/usr/local/bin/shepherdd --config /home/kid/.config/shepherd/config.toml --log-format journald"
+        ));
+        assert!(daemon.contains("Environment=SHEPHERD_SOCKET=/run/shepherd/shepherdd.sock"));
+
+        let hud = file(&files, "systemd/user/shepherd-hud.service");
+        assert!(hud.contains("ExecStart=/usr/local/bin/shepherd-hud --anchor top"));
+        assert!(hud.contains("After=shepherdd.service"));
+
+        let target = file(&files, "systemd/user/shepherd-kiosk.target");
+        assert!(target.contains(
+            "Wants=shepherdd.service shepherd-launcher.service shepherd-hud.service"
+        ));
+        assert!(file(&files, "shepherd/AUTOLOGIN.md").contains("AutomaticLogin=kid"));
+        assert!(files.iter().all(|f| f.contents.contains(GENERATED_MARKER)));
+    }
+
+    #[test]
+    fn test_render_combined() {
+        let files = render(&policy(""), &options(true));
+        assert!(file(&files, "systemd/user/shepherd-kiosk.target").contains("shepherd-shell.service"));
+        assert!(file(&files, "systemd/user/shepherd-shell.service").contains("shepherd-shell --anchor top"));
+        assert!(!files.iter().any(|f| f.path.ends_with("shepherd-launcher.service")));
+    }
+
+    #[test]
+    fn test_sway_app_rules() {
+        let plain = render(&policy(""), &options(false));
+        assert!(!file(&plain, "sway/config").contains("Steam"));
+        assert!(!file(&plain, "sway/config").contains("{app_rules}"));
+
+        let steam = policy(
+            r#"
+            [[entries]]
+            id = "celeste"
+            label = "Celeste"
+            kind = { type = "snap", snap_name = "steam", args = ["steam://rungameid/504230"] }
+            "#,
+        );
+        let files = render(&steam, &options(false));
+        let sway = file(&files, "sway/config");
+        assert!(sway.contains("Sign in to Steam"));
+        assert!(!sway.contains("Minecraft"));
+    }
+
+    #[test]
+    fn test_warnings() {
+        let trusted = policy("[service]\nshell_executables = [\"/usr/local/bin/shepherd-launcher\"]");
+        assert_eq!(warnings(&trusted, &options(false)).len(), 1);
+        assert!(warnings(&trusted, &options(false))[0].contains("shepherd-hud"));
+        assert!(warnings(&policy(""), &options(false)).is_empty());
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("/usr/bin/shepherdd"), "/usr/bin/shepherdd");
+        assert_eq!(quote("/home/kid/My Games/config.toml"), "\"/home/kid/My Games/config.toml\"");
+        assert_eq!(quote("50%"), "50%%");
+    }
+}
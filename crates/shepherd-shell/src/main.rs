@@ -0,0 +1,147 @@
+//! Shepherd Shell - launcher and HUD in one process
+//!
+//! Runs the launcher grid and the HUD bar as two windows of one GTK
+//! application. A single event subscription feeds both, so the compositor
+//! has one client to keep alive and shepherdd one event stream to serve.
+//! The HUD's close button sends its stop on that connection too; the other
+//! commands (launch, volume) still go over short-lived connections, as in
+//! the separate binaries. The connection identifies as a shell, so it takes
+//! over from a separate launcher or HUD as well as an older shell.
+
+use anyhow::Result;
+use clap::Parser;
+use gtk4::prelude::*;
+use shepherd_api::{ClientKind, Command, Event, EventPayload, ResponsePayload, ResponseResult};
+use shepherd_ipc::IpcClient;
+use shepherd_hud::SharedState as HudShared;
+use shepherd_launcher_ui::LauncherApp;
+use shepherd_launcher_ui::SharedState as LauncherShared;
+use shepherd_launcher_ui::state::LauncherState;
+use shepherd_util::{default_socket_path, SeatId};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing_subscriber::EnvFilter;
+
+/// Shepherd Shell - Launcher and HUD for shepherdd in one process
+#[derive(Parser, Debug)]
+#[command(name = "shepherd-shell")]
+#[command(about = "GTK4 launcher and HUD for shepherdd in one process", long_about = None)]
+struct Args {
+    /// Socket path for shepherdd connection (or set SHEPHERD_SOCKET env var)
+    #[arg(short, long, env = "SHEPHERD_SOCKET")]
+    socket: Option<PathBuf>,
+
+    /// Seat this shell runs on, for multi-seat setups (or set SHEPHERD_SEAT env var)
+    #[arg(long, env = "SHEPHERD_SEAT")]
+    seat: Option<SeatId>,
+
+    /// Log level
+    #[arg(short, long, default_value = "info")]
+    log_level: String,
+
+    /// HUD anchor position (top, bottom)
+    #[arg(short, long, default_value = "top")]
+    anchor: String,
+
+    /// Height of the HUD bar in pixels
+    #[arg(long, default_value = "48")]
+    height: i32,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    // Initialize logging
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new(&args.log_level)),
+        )
+        .init();
+
+    tracing::info!("Starting Shepherd Shell");
+
+    // Determine socket path with fallback to default
+    let socket_path = args.socket.unwrap_or_else(default_socket_path);
+
+    let app = gtk4::Application::builder()
+        .application_id("org.shepherd.shell")
+        .build();
+
+    app.connect_activate(move |app| {
+        let launcher = LauncherShared::new();
+        launcher.load_entry_cache();
+        let hud = HudShared::new();
+
+        LauncherApp::build_window(app, &socket_path, args.seat.clone(), launcher.clone());
+        shepherd_hud::build_hud(app, &args.anchor, args.height, hud.clone());
+
+        let socket_path = socket_path.clone();
+        let seat = args.seat.clone();
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new()
+                .expect("Failed to create tokio runtime for event loop");
+            rt.block_on(run_event_loop(socket_path, seat, launcher, hud));
+        });
+    });
+
+    // Options are ours, not GTK's
+    let exit_code: i32 = app.run_with_args::<&str>(&[]).into();
+    std::process::exit(exit_code);
+}
+
+/// The one connection to shepherdd, feeding both the launcher and HUD state
+async fn run_event_loop(socket_path: PathBuf, seat: Option<SeatId>, launcher: LauncherShared, hud: HudShared) {
+    let mut hud_commands = hud.take_commands().unwrap_or_else(|| mpsc::unbounded_channel().1);
+    loop {
+        // Keep the cached grid up rather than flashing the spinner on every retry
+        if !launcher.is_offline() {
+            launcher.set(LauncherState::Connecting);
+        }
+        tracing::info!(path = %socket_path.display(), "Connecting to shepherdd");
+
+        if let Err(e) = connect_and_run(&socket_path, seat.clone(), &launcher, &hud, &mut hud_commands).await {
+            tracing::error!(error = %e, "Connection error");
+        }
+        launcher.set_disconnected();
+
+        // Wait before reconnecting
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+}
+
+async fn connect_and_run(
+    socket_path: &Path,
+    seat: Option<SeatId>,
+    launcher: &LauncherShared,
+    hud: &HudShared,
+    hud_commands: &mut mpsc::UnboundedReceiver<Command>,
+) -> Result<()> {
+    let mut client = IpcClient::connect(socket_path).await?;
+    tracing::info!("Connected to shepherdd");
+
+    // Take over from a launcher, HUD or shell left behind by a crash
+    let role = client.identify(ClientKind::Shell, seat).await?;
+    tracing::info!(role = ?role, "Identified as shell");
+
+    let response = client.send(Command::GetState { seat: None }).await?;
+    match response.result {
+        ResponseResult::Ok(ResponsePayload::State(snapshot)) => {
+            hud.handle_event(&Event::new(EventPayload::StateChanged(snapshot.clone())));
+            launcher.apply_snapshot(snapshot);
+        }
+        ResponseResult::Ok(other) => tracing::warn!(payload = ?other, "Unexpected GetState reply"),
+        ResponseResult::Err(e) => anyhow::bail!("GetState failed: {}", e.message),
+    }
+    shepherd_hud::fetch_initial_status(&mut client, hud).await;
+
+    let (events, sender) = client.subscribe_with_commands().await?;
+    tracing::info!("Subscribed to events");
+    let e = shepherd_hud::run_connection(events, sender, hud_commands, |event| {
+        hud.handle_event(&event);
+        launcher.handle_event(event);
+    })
+    .await;
+    Err(e.into())
+}
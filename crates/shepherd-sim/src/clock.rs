@@ -0,0 +1,62 @@
+//! Virtual clock
+
+use chrono::{DateTime, Local};
+use shepherd_util::MonotonicInstant;
+use std::time::Duration;
+
+/// Wall-clock and monotonic time that only move when told to
+#[derive(Debug, Clone, Copy)]
+pub struct VirtualClock {
+    wall: DateTime<Local>,
+    mono: MonotonicInstant,
+}
+
+impl VirtualClock {
+    /// Start at the given wall-clock time
+    pub fn new(start: DateTime<Local>) -> Self {
+        Self {
+            wall: start,
+            mono: MonotonicInstant::now(),
+        }
+    }
+
+    pub fn wall(&self) -> DateTime<Local> {
+        self.wall
+    }
+
+    pub fn mono(&self) -> MonotonicInstant {
+        self.mono
+    }
+
+    /// Let time pass on both clocks
+    pub fn advance(&mut self, by: Duration) {
+        self.wall += chrono::Duration::from_std(by).expect("duration out of range");
+        self.mono = self.mono + by;
+    }
+
+    /// Change the wall clock only, like a manual or NTP correction
+    pub fn jump_wall(&mut self, by: chrono::Duration) {
+        self.wall += by;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_virtual_clock() {
+        let start = Local.with_ymd_and_hms(2025, 1, 6, 8, 0, 0).unwrap();
+        let mut clock = VirtualClock::new(start);
+        let mono = clock.mono();
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.wall(), start + chrono::Duration::seconds(90));
+        assert_eq!(clock.mono().duration_since(mono), Duration::from_secs(90));
+
+        clock.jump_wall(chrono::Duration::hours(-2));
+        assert_eq!(clock.wall(), start + chrono::Duration::seconds(90 - 7200));
+        assert_eq!(clock.mono().duration_since(mono), Duration::from_secs(90));
+    }
+}
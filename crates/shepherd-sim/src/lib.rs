@@ -0,0 +1,21 @@
+//! Deterministic simulation harness for shepherdd
+//!
+//! Drives the [`CoreEngine`](shepherd_core::CoreEngine) the way the daemon
+//! does, against a [`MockHost`](shepherd_host_api::MockHost) and an in-memory
+//! store, on a virtual clock. Scenarios can run for days of simulated usage
+//! in milliseconds, restart the service, and jump the wall clock, while the
+//! harness checks invariants after every step:
+//!
+//! - the engine and host agree on whether a session is running
+//! - no session outlives its planned duration by more than the grace period
+//! - recorded usage never exceeds an entry's daily quota plus grace
+//!
+//! Scenarios live in `tests/`.
+
+mod clock;
+mod rng;
+mod sim;
+
+pub use clock::*;
+pub use rng::*;
+pub use sim::*;
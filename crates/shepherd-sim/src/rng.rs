@@ -0,0 +1,51 @@
+//! Seeded random numbers for reproducible scenarios
+
+/// Small xorshift generator; the same seed always yields the same script
+#[derive(Debug, Clone)]
+pub struct SimRng(u64);
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        // Zero is a fixed point of xorshift
+        Self(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// Uniform value in `range`
+    pub fn range(&mut self, range: std::ops::Range<u64>) -> u64 {
+        assert!(!range.is_empty(), "empty range");
+        range.start + self.next_u64() % (range.end - range.start)
+    }
+
+    /// True with the given probability in percent
+    pub fn chance(&mut self, percent: u64) -> bool {
+        self.range(0..100) < percent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sim_rng_is_deterministic() {
+        let a: Vec<_> = (0..5).map({
+            let mut rng = SimRng::new(42);
+            move |_| rng.range(0..1000)
+        }).collect();
+        let b: Vec<_> = (0..5).map({
+            let mut rng = SimRng::new(42);
+            move |_| rng.range(0..1000)
+        }).collect();
+        assert_eq!(a, b);
+        assert!(a.iter().all(|v| *v < 1000));
+    }
+}
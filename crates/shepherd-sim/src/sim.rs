@@ -0,0 +1,353 @@
+//! Simulation driver and invariant checks
+
+use chrono::{DateTime, Local, NaiveDate};
+use shepherd_api::{ReasonCode, SessionEndReason};
+use shepherd_config::Policy;
+use shepherd_core::{CoreEngine, CoreEvent, LaunchDecision, StopDecision};
+use shepherd_host_api::{
+    ExitStatus, HostAdapter, HostEvent, HostSessionHandle, MockHost, SpawnOptions, StopMode,
+};
+use shepherd_store::{SqliteStore, Store};
+use shepherd_util::{EntryId, SessionId};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::VirtualClock;
+
+/// Default engine tick while a session runs, matching the daemon
+pub const DEFAULT_TICK: Duration = Duration::from_secs(1);
+
+/// Step used while nothing is running
+const IDLE_TICK: Duration = Duration::from_secs(60);
+
+/// A session that has ended during the simulation
+#[derive(Debug, Clone)]
+pub struct EndedSession {
+    pub entry_id: EntryId,
+    pub ended_at: DateTime<Local>,
+    pub duration: Duration,
+    pub reason: SessionEndReason,
+}
+
+/// Core engine, mock host and in-memory store on a virtual clock
+pub struct Simulation {
+    clock: VirtualClock,
+    policy: Policy,
+    store: Arc<SqliteStore>,
+    host: MockHost,
+    host_events: mpsc::UnboundedReceiver<HostEvent>,
+    engine: CoreEngine,
+    tick: Duration,
+    grace: Duration,
+    /// Virtual time at which the running app quits on its own
+    app_exit_at: Option<shepherd_util::MonotonicInstant>,
+    ended: Vec<EndedSession>,
+}
+
+impl Simulation {
+    /// Start a simulation at `start` with a fresh store.
+    ///
+    /// The quota invariant compares usage with the configured daily quota,
+    /// so policies shouldn't rely on reward bonuses or banked time.
+    pub fn new(policy: Policy, start: DateTime<Local>) -> Self {
+        let store = Arc::new(SqliteStore::in_memory().expect("in-memory store"));
+        let host = MockHost::new();
+        let host_events = host.subscribe();
+        let engine = CoreEngine::new(policy.clone(), store.clone(), host.capabilities().clone());
+
+        Self {
+            clock: VirtualClock::new(start),
+            policy,
+            store,
+            host,
+            host_events,
+            engine,
+            tick: DEFAULT_TICK,
+            grace: DEFAULT_TICK,
+            app_exit_at: None,
+            ended: Vec::new(),
+        }
+    }
+
+    /// Tick the engine every `tick` while a session runs. The grace period
+    /// for the invariants grows with it, since expiry is noticed one tick late.
+    pub fn with_tick(mut self, tick: Duration) -> Self {
+        self.tick = tick;
+        self.grace = tick;
+        self
+    }
+
+    pub fn now(&self) -> DateTime<Local> {
+        self.clock.wall()
+    }
+
+    pub fn engine(&self) -> &CoreEngine {
+        &self.engine
+    }
+
+    pub fn store(&self) -> &Arc<SqliteStore> {
+        &self.store
+    }
+
+    /// Sessions that have ended so far, oldest first
+    pub fn ended_sessions(&self) -> &[EndedSession] {
+        &self.ended
+    }
+
+    /// Recorded usage of an entry on a local day
+    pub fn usage(&self, entry_id: &str, day: NaiveDate) -> Duration {
+        self.store
+            .get_usage(&EntryId::new(entry_id), day)
+            .expect("usage lookup")
+    }
+
+    /// Launch an entry the way the daemon does: ask the engine, start the
+    /// session, spawn it on the host and attach the handle.
+    pub async fn launch(&mut self, entry_id: &str) -> Result<SessionId, Vec<ReasonCode>> {
+        let plan = match self.engine.request_launch(&EntryId::new(entry_id), self.clock.wall()) {
+            LaunchDecision::Approved(plan) => plan,
+            // A simulated user always says yes straight away
+            LaunchDecision::NeedsConfirmation(plan) => {
+                match self.engine.confirm_launch(&plan.session_id, self.clock.wall()) {
+                    Some(LaunchDecision::Approved(plan)) => plan,
+                    Some(LaunchDecision::Denied { reasons }) => return Err(reasons),
+                    _ => unreachable!("a fresh launch is confirmed once"),
+                }
+            }
+            LaunchDecision::Denied { reasons } => return Err(reasons),
+        };
+
+        let entry = self
+            .policy
+            .get_entry(&plan.entry_id)
+            .expect("approved entry exists")
+            .kind
+            .clone();
+        let session_id = plan.session_id.clone();
+        self.engine.start_session(plan, self.clock.wall(), self.clock.mono());
+
+        let handle = self
+            .host
+            .spawn(session_id.clone(), &entry, SpawnOptions::default())
+            .await
+            .expect("mock spawn");
+        self.engine.attach_host_handle(handle);
+        self.app_exit_at = None;
+
+        self.check_invariants();
+        Ok(session_id)
+    }
+
+    /// Make the running app quit on its own after `after`
+    pub fn exit_app_after(&mut self, after: Duration) {
+        self.app_exit_at = Some(self.clock.mono() + after);
+    }
+
+    /// Stop the running session, as the launcher's stop button would
+    pub async fn stop(&mut self) {
+        let handle = self.current_handle();
+        if let StopDecision::Stopped(result) =
+            self.engine
+                .stop_current(SessionEndReason::UserStop, self.clock.mono(), self.clock.wall())
+        {
+            self.ended.push(EndedSession {
+                entry_id: result.entry_id,
+                ended_at: self.clock.wall(),
+                duration: result.duration,
+                reason: result.reason,
+            });
+        }
+        if let Some(handle) = handle {
+            let _ = self.host.stop(&handle, graceful()).await;
+        }
+        self.drain_host_events();
+        self.check_invariants();
+    }
+
+    /// Restart the service: stop the running session the way shutdown does,
+    /// then start a new engine on the same store.
+    pub async fn restart(&mut self) {
+        let handle = self.current_handle();
+        if let StopDecision::Stopped(result) = self.engine.stop_current(
+            SessionEndReason::ServiceShutdown,
+            self.clock.mono(),
+            self.clock.wall(),
+        ) {
+            self.ended.push(EndedSession {
+                entry_id: result.entry_id,
+                ended_at: self.clock.wall(),
+                duration: result.duration,
+                reason: result.reason,
+            });
+        }
+        if let Some(handle) = handle {
+            let _ = self.host.stop(&handle, graceful()).await;
+        }
+        self.app_exit_at = None;
+
+        self.engine = CoreEngine::new(
+            self.policy.clone(),
+            self.store.clone(),
+            self.host.capabilities().clone(),
+        );
+        self.drain_host_events();
+        self.check_invariants();
+    }
+
+    /// Change the wall clock without time passing
+    pub fn jump_wall(&mut self, by: chrono::Duration) {
+        self.clock.jump_wall(by);
+    }
+
+    /// Let `duration` pass, ticking the engine and handling its events
+    pub async fn advance(&mut self, duration: Duration) {
+        let mut left = duration;
+        while !left.is_zero() {
+            let step = if self.engine.has_active_session() {
+                self.tick
+            } else {
+                IDLE_TICK
+            }
+            .min(left);
+            left -= step;
+            self.step(step).await;
+        }
+    }
+
+    /// Advance until the wall clock reads `time` (local)
+    pub async fn advance_until(&mut self, time: DateTime<Local>) {
+        if let Ok(duration) = time.signed_duration_since(self.clock.wall()).to_std() {
+            self.advance(duration).await;
+        }
+    }
+
+    async fn step(&mut self, by: Duration) {
+        self.clock.advance(by);
+
+        if let Some(at) = self.app_exit_at
+            && at <= self.clock.mono()
+        {
+            self.app_exit_at = None;
+            if let Some(session) = self.engine.current_session() {
+                self.host
+                    .simulate_exit(&session.plan.session_id, ExitStatus::success());
+            }
+        }
+        self.drain_host_events();
+
+        for event in self.engine.tick(self.clock.mono(), self.clock.wall()) {
+            if let CoreEvent::ExpireDue { .. } = event
+                && let Some(handle) = self.current_handle()
+            {
+                self.host
+                    .stop(&handle, graceful())
+                    .await
+                    .expect("mock stop");
+            }
+        }
+        self.drain_host_events();
+
+        self.check_invariants();
+    }
+
+    fn current_handle(&self) -> Option<HostSessionHandle> {
+        self.engine
+            .current_session()
+            .and_then(|s| s.host_handle.clone())
+    }
+
+    /// Deliver host exit events to the engine
+    fn drain_host_events(&mut self) {
+        while let Ok(event) = self.host_events.try_recv() {
+            let HostEvent::Exited { handle, status } = event else {
+                continue;
+            };
+            let is_current = self
+                .engine
+                .current_session()
+                .is_some_and(|s| s.plan.session_id == handle.session_id);
+            if !is_current {
+                continue;
+            }
+            if let Some(CoreEvent::SessionEnded {
+                entry_id,
+                reason,
+                duration,
+                ..
+            }) = self.engine.notify_session_exited(
+                status.code,
+                self.clock.mono(),
+                self.clock.wall(),
+            ) {
+                self.ended.push(EndedSession {
+                    entry_id,
+                    ended_at: self.clock.wall(),
+                    duration,
+                    reason,
+                });
+            }
+        }
+    }
+
+    /// Panic with the current virtual time if an invariant is broken
+    pub fn check_invariants(&self) {
+        if let Err(violation) = self.invariants() {
+            panic!(
+                "invariant violated at {}: {}",
+                self.clock.wall().format("%Y-%m-%d %H:%M:%S"),
+                violation
+            );
+        }
+    }
+
+    fn invariants(&self) -> Result<(), String> {
+        let running = self.host.running_sessions();
+        match self.engine.current_session() {
+            Some(session) => {
+                if running != [session.plan.session_id.clone()] {
+                    return Err(format!(
+                        "engine session {} but host runs {:?}",
+                        session.plan.session_id, running
+                    ));
+                }
+                if let Some(max) = session.plan.max_duration {
+                    let charged = session.charged(self.clock.mono());
+                    if charged > max + self.grace {
+                        return Err(format!(
+                            "session {} ran {:?}, planned at most {:?}",
+                            session.plan.entry_id, charged, max
+                        ));
+                    }
+                }
+            }
+            None if !running.is_empty() => {
+                return Err(format!("no engine session but host runs {:?}", running));
+            }
+            None => {}
+        }
+
+        // Usage is only ever charged to the current day, so that's the one to check
+        let today = self.clock.wall().date_naive();
+        for entry in &self.policy.entries {
+            let Some(quota) = entry.limits.daily_quota else {
+                continue;
+            };
+            let used = self.store.get_usage(&entry.id, today).map_err(|e| e.to_string())?;
+            if used > quota + self.grace {
+                return Err(format!(
+                    "{} used {:?} on {}, quota {:?}",
+                    entry.id, used, today, quota
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn graceful() -> StopMode {
+    StopMode::Graceful {
+        timeout: Duration::from_secs(5),
+    }
+}
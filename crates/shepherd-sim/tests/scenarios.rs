@@ -0,0 +1,206 @@
+//! Long-running scenarios driven through the simulation harness.
+//!
+//! Dates are in mid-January so no timezone has a DST transition during a run.
+
+use chrono::{DateTime, Local, TimeZone};
+use shepherd_api::{ReasonCode, SessionEndReason};
+use shepherd_config::parse_config;
+use shepherd_sim::{SimRng, Simulation};
+use std::time::Duration;
+
+const CONFIG: &str = r#"
+    config_version = 1
+
+    [[entries]]
+    id = "game"
+    label = "Game"
+    kind = { type = "process", command = "sleep", args = ["999"] }
+
+    [entries.availability]
+    [[entries.availability.windows]]
+    days = "all"
+    start = "15:00"
+    end = "20:00"
+
+    [entries.limits]
+    max_run_seconds = 1800
+    daily_quota_seconds = 3600
+    cooldown_seconds = 600
+
+    [[entries]]
+    id = "drawing"
+    label = "Drawing"
+    kind = { type = "process", command = "sleep", args = ["999"] }
+
+    [entries.limits]
+    max_run_seconds = 2700
+
+    [[entries]]
+    id = "reader"
+    label = "Reader"
+    kind = { type = "process", command = "sleep", args = ["999"] }
+
+    [entries.availability]
+    [[entries.availability.windows]]
+    days = "weekdays"
+    start = "16:00"
+    end = "17:30"
+
+    [entries.limits]
+    max_run_seconds = 0
+    daily_quota_seconds = 2400
+"#;
+
+const ENTRIES: [&str; 3] = ["game", "drawing", "reader"];
+
+/// Local time on the given day of the simulated fortnight (day 0 is a Monday)
+fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+    Local
+        .with_ymd_and_hms(2025, 1, 6 + day, hour, minute, 0)
+        .unwrap()
+}
+
+fn simulation() -> Simulation {
+    Simulation::new(parse_config(CONFIG).unwrap(), at(0, 8, 0))
+}
+
+#[tokio::test]
+async fn daily_quota_holds_over_a_week() {
+    let mut sim = simulation();
+
+    for day in 0..7 {
+        sim.advance_until(at(day, 14, 0)).await;
+        // Keep asking for the game every five minutes until the evening
+        while sim.now() < at(day, 21, 0) {
+            if !sim.engine().has_active_session() {
+                let _ = sim.launch("game").await;
+            }
+            sim.advance(Duration::from_secs(300)).await;
+        }
+
+        let used = sim.usage("game", at(day, 12, 0).date_naive());
+        assert!(
+            (3600..=3601).contains(&used.as_secs()),
+            "day {} used {:?}",
+            day,
+            used
+        );
+    }
+
+    // Two half-hour sessions a day, both ended by expiry
+    let games: Vec<_> = sim
+        .ended_sessions()
+        .iter()
+        .filter(|s| s.entry_id.as_str() == "game")
+        .collect();
+    assert_eq!(games.len(), 14);
+    assert!(games.iter().all(|s| s.reason == SessionEndReason::Expired));
+}
+
+#[tokio::test]
+async fn restart_mid_session_keeps_usage() {
+    let mut sim = simulation();
+    sim.advance_until(at(0, 15, 0)).await;
+
+    sim.launch("game").await.unwrap();
+    sim.advance(Duration::from_secs(20 * 60)).await;
+    sim.restart().await;
+    assert!(!sim.engine().has_active_session());
+    assert_eq!(sim.usage("game", at(0, 0, 0).date_naive()), Duration::from_secs(20 * 60));
+
+    // The cooldown survives the restart
+    assert!(matches!(
+        sim.launch("game").await.unwrap_err().as_slice(),
+        [ReasonCode::CooldownActive { .. }]
+    ));
+
+    sim.advance(Duration::from_secs(10 * 60)).await;
+    sim.launch("game").await.unwrap();
+    sim.advance(Duration::from_secs(20 * 60)).await;
+    sim.restart().await;
+
+    // Only what's left of the quota is granted after the second restart
+    sim.advance(Duration::from_secs(10 * 60)).await;
+    sim.launch("game").await.unwrap();
+    let max = sim
+        .engine()
+        .current_session()
+        .and_then(|s| s.plan.max_duration)
+        .unwrap();
+    assert_eq!(max, Duration::from_secs(20 * 60));
+    sim.advance(Duration::from_secs(60 * 60)).await;
+
+    assert!(sim.usage("game", at(0, 0, 0).date_naive()) <= Duration::from_secs(3601));
+    let shutdowns = sim
+        .ended_sessions()
+        .iter()
+        .filter(|s| s.reason == SessionEndReason::ServiceShutdown)
+        .count();
+    assert_eq!(shutdowns, 2);
+}
+
+#[tokio::test]
+async fn wall_clock_jumps_dont_stretch_sessions() {
+    let mut sim = simulation();
+    sim.advance_until(at(0, 15, 0)).await;
+
+    // Setting the clock back mid-session doesn't extend it
+    sim.launch("game").await.unwrap();
+    sim.advance(Duration::from_secs(10 * 60)).await;
+    sim.jump_wall(chrono::Duration::hours(-2));
+    sim.advance(Duration::from_secs(25 * 60)).await;
+
+    let last = sim.ended_sessions().last().unwrap();
+    assert_eq!(last.reason, SessionEndReason::Expired);
+    assert!(last.duration <= Duration::from_secs(1801));
+
+    // Back outside the window, so nothing can start
+    assert!(sim.launch("game").await.is_err());
+
+    // Jumping ahead to tomorrow's window gives a fresh quota
+    sim.jump_wall(chrono::Duration::hours(26));
+    let max = {
+        sim.launch("game").await.unwrap();
+        sim.engine().current_session().unwrap().plan.max_duration.unwrap()
+    };
+    assert_eq!(max, Duration::from_secs(1800));
+    sim.stop().await;
+}
+
+#[tokio::test]
+async fn random_days_respect_invariants() {
+    for seed in [1, 7, 2025] {
+        let mut rng = SimRng::new(seed);
+        let mut sim = simulation().with_tick(Duration::from_secs(5));
+
+        for day in 0..7 {
+            sim.advance_until(at(day, 14, 0)).await;
+            while sim.now() < at(day, 21, 0) {
+                match rng.range(0..100) {
+                    0..=29 if !sim.engine().has_active_session() => {
+                        let entry = ENTRIES[rng.range(0..3) as usize];
+                        if sim.launch(entry).await.is_ok() && rng.chance(50) {
+                            sim.exit_app_after(Duration::from_secs(rng.range(60..3600)));
+                        }
+                    }
+                    30..=39 => sim.stop().await,
+                    40..=41 => sim.restart().await,
+                    42..=43 => {
+                        // Small clock corrections that stay within the day
+                        let minutes = rng.range(0..20) as i64 - 10;
+                        sim.jump_wall(chrono::Duration::minutes(minutes));
+                    }
+                    _ => {}
+                }
+                sim.advance(Duration::from_secs(rng.range(30..900))).await;
+            }
+        }
+
+        assert!(
+            sim.ended_sessions().len() >= 7,
+            "seed {} ran only {} sessions",
+            seed,
+            sim.ended_sessions().len()
+        );
+    }
+}
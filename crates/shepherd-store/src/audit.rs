@@ -2,8 +2,9 @@
 
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
-use shepherd_api::SessionEndReason;
-use shepherd_util::{EntryId, SessionId};
+use sha2::{Digest, Sha256};
+use shepherd_api::{EscapeRoute, Hold, PolicyPatch, RefundReason, SessionEndReason, TimelineEventKind};
+use shepherd_util::{EntryId, ProfileId, SessionId};
 use std::time::Duration;
 
 /// Types of audit events
@@ -16,6 +17,9 @@ pub enum AuditEventType {
     /// Service stopped
     ServiceStopped,
 
+    /// Service panicked; running sessions were killed on the way out
+    ServiceCrashed { message: String, sessions_killed: usize },
+
     /// Policy loaded/reloaded
     PolicyLoaded { entry_count: usize },
 
@@ -42,12 +46,34 @@ pub enum AuditEventType {
         duration: Duration,
     },
 
+    /// An expired session couldn't be stopped after every escalation step
+    EnforcementFailed {
+        session_id: SessionId,
+        entry_id: EntryId,
+        attempts: u32,
+    },
+
+    /// A session's time wasn't charged to the entry's quota
+    UsageRefunded {
+        session_id: SessionId,
+        entry_id: EntryId,
+        reason: RefundReason,
+        duration: Duration,
+    },
+
     /// Launch denied
     LaunchDenied {
         entry_id: EntryId,
         reasons: Vec<String>,
     },
 
+    /// Scheduled autostart came due. A denied launch also has its own
+    /// `LaunchDenied` record.
+    AutostartTriggered {
+        entry_id: EntryId,
+        launched: bool,
+    },
+
     /// Session extended (admin action)
     SessionExtended {
         session_id: SessionId,
@@ -55,9 +81,52 @@ pub enum AuditEventType {
         new_deadline: DateTime<Local>,
     },
 
+    /// Reward stars granted (admin action)
+    RewardGranted {
+        stars: u32,
+        reason: Option<String>,
+    },
+
+    /// Maintenance update of an entry's app (snap refresh, flatpak update)
+    EntryUpdated {
+        entry_id: EntryId,
+        success: bool,
+        error: Option<String>,
+    },
+
+    /// A new shepherdd release was installed; the service restarts into it
+    ServiceUpdated {
+        from_version: String,
+        to_version: String,
+    },
+
+    /// The family calendar feed changed
+    CalendarUpdated { exception_count: usize },
+
+    /// A calendar event began opening or closing entries
+    CalendarExceptionStarted {
+        label: String,
+        /// "open" or "close"
+        action: String,
+        /// Entry IDs or tags, "*" for all
+        targets: Vec<String>,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    },
+
+    /// Launch hold turned on or off (admin action)
+    HoldChanged { hold: Option<Hold> },
+
     /// Config reload requested
     ConfigReloaded { success: bool },
 
+    /// Policy edited at runtime (admin action)
+    PolicyPatched {
+        /// Edit that restores the previous value
+        old: PolicyPatch,
+        new: PolicyPatch,
+    },
+
     /// Client connected
     ClientConnected {
         client_id: String,
@@ -67,6 +136,97 @@ pub enum AuditEventType {
 
     /// Client disconnected
     ClientDisconnected { client_id: String },
+
+    /// A newer launcher, HUD or shell took over from an older process,
+    /// e.g. one left behind by a crash
+    ClientReplaced {
+        kind: String,
+        pid: u32,
+        by_pid: u32,
+    },
+
+    /// A profile used `percent` of its weekly budget
+    BudgetAlert {
+        profile_id: ProfileId,
+        percent: u8,
+        used: Duration,
+        budget: Duration,
+    },
+
+    /// Ways out of the kiosk found at startup, and whether each was closed
+    EscapeRoutesFound { routes: Vec<EscapeRoute> },
+
+    /// Keyboard and mouse given back during a controller-only session
+    InputReleased {
+        /// Released with the admin PIN rather than by an admin client
+        with_pin: bool,
+    },
+
+    /// A wrong admin PIN was given to release input
+    InputReleaseFailed {
+        /// Wrong PINs in a row so far
+        failures: u32,
+        /// How long further attempts are refused, if this one started a lockout
+        lockout: Option<Duration>,
+    },
+
+    /// Usage history imported from another parental control (admin action)
+    UsageImported {
+        source: String,
+        /// Daily totals added or raised
+        changed: usize,
+        /// Time in the export
+        total: Duration,
+    },
+}
+
+impl AuditEventType {
+    /// The session this event belongs to, if any
+    pub fn session_id(&self) -> Option<&SessionId> {
+        match self {
+            AuditEventType::SessionStarted { session_id, .. }
+            | AuditEventType::WarningIssued { session_id, .. }
+            | AuditEventType::SessionEnded { session_id, .. }
+            | AuditEventType::UsageRefunded { session_id, .. }
+            | AuditEventType::SessionExtended { session_id, .. } => Some(session_id),
+            _ => None,
+        }
+    }
+
+    /// How this event appears in a session timeline
+    pub fn timeline_kind(&self) -> Option<TimelineEventKind> {
+        match self {
+            AuditEventType::SessionStarted { entry_id, label, deadline, .. } => {
+                Some(TimelineEventKind::Started {
+                    entry_id: entry_id.clone(),
+                    label: label.clone(),
+                    deadline: *deadline,
+                })
+            }
+            AuditEventType::WarningIssued { threshold_seconds, .. } => {
+                Some(TimelineEventKind::Warning {
+                    threshold_seconds: *threshold_seconds,
+                })
+            }
+            AuditEventType::SessionExtended { extended_by, new_deadline, .. } => {
+                Some(TimelineEventKind::Extended {
+                    by: *extended_by,
+                    new_deadline: *new_deadline,
+                })
+            }
+            AuditEventType::SessionEnded { reason, duration, .. } => Some(TimelineEventKind::Ended {
+                reason: reason.clone(),
+                duration: *duration,
+            }),
+            AuditEventType::UsageRefunded { reason, duration, .. } => {
+                Some(TimelineEventKind::Refunded {
+                    reason: reason.clone(),
+                    duration: *duration,
+                })
+            }
+            _ => None,
+        }
+    }
 }
 
 /// Full audit event with metadata
@@ -91,3 +251,19 @@ impl AuditEvent {
         }
     }
 }
+
+/// Hash of an audit row in the log's chain: the previous row's hash
+/// (empty for the first hashed row) followed by the row's stored timestamp
+/// and event JSON, as lowercase hex
+pub(crate) fn audit_hash(prev_hash: &str, timestamp: &str, event_json: &str) -> String {
+    let mut hasher = Sha256::new();
+    for part in [prev_hash, timestamp, event_json] {
+        hasher.update(part.as_bytes());
+        hasher.update(b"\n");
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
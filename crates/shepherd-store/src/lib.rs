@@ -2,25 +2,37 @@
 //!
 //! Provides:
 //! - Audit log (append-only)
-//! - Usage accounting (per entry/day)
+//! - Usage accounting (per entry/day), merged across federated devices
 //! - Cooldown tracking
+//! - Post-session feelings check-ins
+//! - Per-profile usage for joint sessions
+//! - Reward stars ledger
+//! - Earned time bank
 //! - State snapshot for recovery
+//! - Per-session timelines
+//! - In-memory fallback when the database is unavailable
 
 mod audit;
+mod resilient;
 mod sqlite;
+mod timeline;
 mod traits;
 
 pub use audit::*;
+pub use resilient::*;
 pub use sqlite::*;
+pub use timeline::*;
 pub use traits::*;
 
+use rusqlite::ErrorCode as SqliteCode;
+use shepherd_api::ErrorKind;
 use thiserror::Error;
 
 /// Store errors
 #[derive(Debug, Error)]
 pub enum StoreError {
-    #[error("Database error: {0}")]
-    Database(String),
+    #[error("Database error: {context}")]
+    Database { kind: ErrorKind, context: String },
 
     #[error("Serialization error: {0}")]
     Serialization(String),
@@ -32,9 +44,46 @@ pub enum StoreError {
     NotFound(String),
 }
 
+impl StoreError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            StoreError::Database { kind, .. } => *kind,
+            StoreError::Serialization(_) => ErrorKind::Corrupt,
+            StoreError::Io(e) => ErrorKind::from_io(e),
+            StoreError::NotFound(_) => ErrorKind::NotFound,
+        }
+    }
+}
+
 impl From<rusqlite::Error> for StoreError {
     fn from(e: rusqlite::Error) -> Self {
-        StoreError::Database(e.to_string())
+        let kind = match &e {
+            rusqlite::Error::QueryReturnedNoRows => ErrorKind::NotFound,
+            rusqlite::Error::SqliteFailure(failure, _) => match failure.code {
+                // Another connection holds the lock; the next attempt may succeed
+                SqliteCode::DatabaseBusy | SqliteCode::DatabaseLocked => ErrorKind::Unavailable,
+                SqliteCode::PermissionDenied
+                | SqliteCode::ReadOnly
+                | SqliteCode::CannotOpen
+                | SqliteCode::AuthorizationForStatementDenied => ErrorKind::PermissionDenied,
+                SqliteCode::DatabaseCorrupt | SqliteCode::NotADatabase => ErrorKind::Corrupt,
+                // Writes can succeed again once space is freed
+                SqliteCode::DiskFull => ErrorKind::Unavailable,
+                _ => ErrorKind::Internal,
+            },
+            rusqlite::Error::FromSqlConversionFailure(..)
+            | rusqlite::Error::IntegralValueOutOfRange(..)
+            | rusqlite::Error::InvalidColumnType(..)
+            | rusqlite::Error::Utf8Error(..) => ErrorKind::Corrupt,
+            _ => ErrorKind::Internal,
+        };
+        let context = match &e {
+            rusqlite::Error::SqliteFailure(failure, _) if failure.code == SqliteCode::DiskFull => {
+                format!("disk full: {e}")
+            }
+            _ => e.to_string(),
+        };
+        StoreError::Database { kind, context }
     }
 }
 
@@ -45,3 +94,26 @@ impl From<serde_json::Error> for StoreError {
 }
 
 pub type StoreResult<T> = Result<T, StoreError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sqlite_error_kinds() {
+        let failure = |code| {
+            StoreError::from(rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(code), None))
+        };
+        assert_eq!(failure(rusqlite::ffi::SQLITE_BUSY).kind(), ErrorKind::Unavailable);
+        assert_eq!(failure(rusqlite::ffi::SQLITE_READONLY).kind(), ErrorKind::PermissionDenied);
+        assert_eq!(failure(rusqlite::ffi::SQLITE_CORRUPT).kind(), ErrorKind::Corrupt);
+        assert_eq!(failure(rusqlite::ffi::SQLITE_FULL).kind(), ErrorKind::Unavailable);
+        assert!(failure(rusqlite::ffi::SQLITE_FULL).to_string().contains("disk full"));
+        assert_eq!(failure(rusqlite::ffi::SQLITE_INTERNAL).kind(), ErrorKind::Internal);
+        assert_eq!(
+            StoreError::from(rusqlite::Error::QueryReturnedNoRows).kind(),
+            ErrorKind::NotFound
+        );
+        assert!(failure(rusqlite::ffi::SQLITE_BUSY).kind().is_retryable());
+    }
+}
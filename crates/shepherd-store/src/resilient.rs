@@ -0,0 +1,658 @@
+//! Store that keeps working when the database doesn't
+//!
+//! If the database file can't be opened (corrupt, unreadable) or a write
+//! fails (disk full, read-only filesystem), writes are kept in an in-memory
+//! journal instead of being lost, and reads combine what the database holds
+//! with the journal. The service keeps enforcing policy on that view while
+//! [`ResilientStore::retry`] is called periodically to reopen the database and
+//! replay the journal in order.
+
+use chrono::{DateTime, Local, NaiveDate};
+use shepherd_api::{AuditVerification, ErrorKind, Hold};
+use shepherd_util::{EntryId, ProfileId, SessionId};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::{
+    AuditEvent, AuditEventType, CooldownKey, RewardGrant, SessionCheckIn, SqliteStore, StateSnapshot, Store, StoreError,
+    StoreResult, UsageRecord,
+};
+
+/// Journal length beyond which the oldest audit events are dropped
+const MAX_PENDING: usize = 10_000;
+
+/// A write that hasn't reached the database yet
+#[derive(Debug, Clone)]
+enum PendingWrite {
+    Audit(AuditEvent),
+    Usage {
+        entry_id: EntryId,
+        day: NaiveDate,
+        duration: Duration,
+    },
+    ProfileUsage {
+        profile_id: ProfileId,
+        entry_id: EntryId,
+        day: NaiveDate,
+        duration: Duration,
+    },
+    TimeBank {
+        tag: String,
+        day: NaiveDate,
+        duration: Duration,
+    },
+    Cooldown {
+        key: CooldownKey,
+        until: DateTime<Local>,
+    },
+    ClearCooldown(CooldownKey),
+    LastUpdate {
+        entry_id: EntryId,
+        at: DateTime<Local>,
+    },
+    CheckIn(SessionCheckIn),
+    Reward(RewardGrant),
+    Hold(Option<Hold>),
+    Snapshot(StateSnapshot),
+}
+
+impl PendingWrite {
+    fn apply(&self, store: &dyn Store) -> StoreResult<()> {
+        match self {
+            PendingWrite::Audit(event) => store.append_audit(event.clone()),
+            PendingWrite::Usage { entry_id, day, duration } => {
+                store.add_usage(entry_id, *day, *duration)
+            }
+            PendingWrite::ProfileUsage { profile_id, entry_id, day, duration } => {
+                store.add_profile_usage(profile_id, entry_id, *day, *duration)
+            }
+            PendingWrite::TimeBank { tag, day, duration } => {
+                store.add_time_bank(tag, *day, *duration)
+            }
+            PendingWrite::Cooldown { key, until } => store.set_cooldown_until(key, *until),
+            PendingWrite::ClearCooldown(key) => store.clear_cooldown(key),
+            PendingWrite::LastUpdate { entry_id, at } => store.set_last_update(entry_id, *at),
+            PendingWrite::CheckIn(check_in) => store.record_check_in(check_in),
+            PendingWrite::Reward(grant) => store.add_reward(grant),
+            PendingWrite::Hold(hold) => store.set_hold(hold.as_ref()),
+            PendingWrite::Snapshot(snapshot) => store.save_snapshot(snapshot),
+        }
+    }
+}
+
+struct Inner {
+    /// None while the database can't be opened
+    primary: Option<SqliteStore>,
+    /// Writes not yet in the database, oldest first
+    pending: VecDeque<PendingWrite>,
+    degraded: bool,
+    last_audit_write: Option<DateTime<Local>>,
+}
+
+impl Inner {
+    /// Read from the database, or fall back to `T::default()` if it's gone
+    fn read<T: Default>(&mut self, what: &str, f: impl FnOnce(&SqliteStore) -> StoreResult<T>) -> T {
+        let Some(primary) = &self.primary else {
+            return T::default();
+        };
+        match f(primary) {
+            Ok(value) => value,
+            Err(e) => {
+                warn!(error = %e, what, "Store read failed, using in-memory data only");
+                self.degraded = true;
+                T::default()
+            }
+        }
+    }
+
+    fn applied(&mut self, write: &PendingWrite) {
+        if matches!(write, PendingWrite::Audit(_)) {
+            self.last_audit_write = Some(shepherd_util::now());
+        }
+    }
+
+    fn queue(&mut self, write: PendingWrite) {
+        self.pending.push_back(write);
+        if self.pending.len() > MAX_PENDING
+            && let Some(oldest) = self
+                .pending
+                .iter()
+                .position(|w| matches!(w, PendingWrite::Audit(_)))
+        {
+            self.pending.remove(oldest);
+            warn!("Store journal full, dropped the oldest pending audit event");
+        }
+    }
+
+    fn pending_sum(&self, matches: impl Fn(&PendingWrite) -> Option<Duration>) -> Duration {
+        self.pending.iter().filter_map(matches).sum()
+    }
+}
+
+/// [`SqliteStore`] with an in-memory journal to fall back on
+pub struct ResilientStore {
+    path: PathBuf,
+    inner: Mutex<Inner>,
+}
+
+impl ResilientStore {
+    /// Open the database at `path`. Never fails: if the database can't be
+    /// opened the store starts degraded and keeps retrying on [`retry`](Self::retry).
+    pub fn open(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let primary = match SqliteStore::open(&path) {
+            Ok(store) => Some(store),
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Failed to open store, running on an in-memory journal");
+                None
+            }
+        };
+        Self::with_primary(path, primary)
+    }
+
+    /// A store that only lasts as long as the process, for `shepherdd --dev-sandbox`
+    pub fn in_memory() -> StoreResult<Self> {
+        Ok(Self::with_primary(
+            PathBuf::from(":memory:"),
+            Some(SqliteStore::in_memory()?),
+        ))
+    }
+
+    fn with_primary(path: PathBuf, primary: Option<SqliteStore>) -> Self {
+        let degraded = primary.is_none();
+        Self {
+            path,
+            inner: Mutex::new(Inner {
+                primary,
+                pending: VecDeque::new(),
+                degraded,
+                last_audit_write: None,
+            }),
+        }
+    }
+
+    /// Whether writes are currently being kept in memory
+    pub fn is_degraded(&self) -> bool {
+        self.lock().degraded
+    }
+
+    /// Reopen the database if needed and replay pending writes in order,
+    /// stopping at the first one that still fails. Returns true once the
+    /// journal is empty and the database is healthy again.
+    pub fn retry(&self) -> bool {
+        let mut inner = self.lock();
+        if !inner.degraded {
+            return true;
+        }
+
+        let primary = match inner.primary.take() {
+            Some(primary) => primary,
+            None => match SqliteStore::open(&self.path) {
+                Ok(store) => store,
+                Err(e) => {
+                    debug!(error = %e, "Store still unavailable");
+                    return false;
+                }
+            },
+        };
+
+        let mut replayed = 0;
+        let mut stopped = None;
+        while let Some(write) = inner.pending.front() {
+            if let Err(e) = write.apply(&primary) {
+                stopped = Some(e);
+                break;
+            }
+            if let Some(write) = inner.pending.pop_front() {
+                inner.applied(&write);
+            }
+            replayed += 1;
+        }
+        let healthy = primary.is_healthy();
+        inner.primary = Some(primary);
+
+        if let Some(e) = stopped {
+            debug!(error = %e, replayed, remaining = inner.pending.len(), "Store replay stopped");
+            return false;
+        }
+        if !healthy {
+            return false;
+        }
+        inner.degraded = false;
+        info!(replayed, "Store recovered");
+        true
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        // Poisoning only means a panic elsewhere; the journal is still usable
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Write through to the database, or journal the write if that fails.
+    /// Anything already journalled goes first, so writes are never reordered.
+    fn write(&self, write: PendingWrite) -> StoreResult<()> {
+        let mut inner = self.lock();
+        if inner.pending.is_empty()
+            && let Some(primary) = &inner.primary
+        {
+            match write.apply(primary) {
+                Ok(()) => {
+                    inner.applied(&write);
+                    return Ok(());
+                }
+                // Retrying won't make this write serializable
+                Err(e @ StoreError::Serialization(_)) => return Err(e),
+                Err(e) => {
+                    warn!(error = %e, "Store write failed, keeping it in memory until the database is back");
+                    inner.degraded = true;
+                }
+            }
+        }
+        inner.queue(write);
+        Ok(())
+    }
+}
+
+impl Store for ResilientStore {
+    fn append_audit(&self, event: AuditEvent) -> StoreResult<()> {
+        self.write(PendingWrite::Audit(event))
+    }
+
+    fn get_recent_audits(&self, limit: usize) -> StoreResult<Vec<AuditEvent>> {
+        let mut inner = self.lock();
+        let mut events: Vec<AuditEvent> = inner
+            .pending
+            .iter()
+            .rev()
+            .filter_map(|w| match w {
+                PendingWrite::Audit(event) => Some(event.clone()),
+                _ => None,
+            })
+            .take(limit)
+            .collect();
+        let remaining = limit - events.len();
+        if remaining > 0 {
+            events.extend(inner.read("audits", |p| p.get_recent_audits(remaining)));
+        }
+        Ok(events)
+    }
+
+    fn get_session_audits(&self, session_id: &SessionId) -> StoreResult<Vec<AuditEvent>> {
+        let mut inner = self.lock();
+        let mut events = inner.read("session audits", |p| p.get_session_audits(session_id));
+        events.extend(inner.pending.iter().filter_map(|w| match w {
+            PendingWrite::Audit(event) if event.event.session_id() == Some(session_id) => {
+                Some(event.clone())
+            }
+            _ => None,
+        }));
+        Ok(events)
+    }
+
+    fn get_last_session_end(&self, entry_id: &EntryId) -> StoreResult<Option<DateTime<Local>>> {
+        let mut inner = self.lock();
+        let pending = inner.pending.iter().rev().find_map(|w| match w {
+            PendingWrite::Audit(AuditEvent {
+                timestamp,
+                event: AuditEventType::SessionEnded { entry_id: e, .. },
+                ..
+            }) if e == entry_id => Some(*timestamp),
+            _ => None,
+        });
+        match pending {
+            Some(at) => Ok(Some(at)),
+            None => Ok(inner.read("last session end", |p| p.get_last_session_end(entry_id))),
+        }
+    }
+
+    /// Only what reached the database; journalled events are chained when
+    /// they're replayed
+    fn verify_audit(&self, known_head: Option<&str>) -> StoreResult<AuditVerification> {
+        match &self.lock().primary {
+            Some(primary) => primary.verify_audit(known_head),
+            None => Err(StoreError::Database {
+                kind: ErrorKind::Unavailable,
+                context: "the database can't be opened".into(),
+            }),
+        }
+    }
+
+    fn get_usage(&self, entry_id: &EntryId, day: NaiveDate) -> StoreResult<Duration> {
+        let mut inner = self.lock();
+        let stored = inner.read("usage", |p| p.get_usage(entry_id, day));
+        Ok(stored
+            + inner.pending_sum(|w| match w {
+                PendingWrite::Usage { entry_id: e, day: d, duration } if e == entry_id && *d == day => {
+                    Some(*duration)
+                }
+                _ => None,
+            }))
+    }
+
+    fn add_usage(&self, entry_id: &EntryId, day: NaiveDate, duration: Duration) -> StoreResult<()> {
+        self.write(PendingWrite::Usage {
+            entry_id: entry_id.clone(),
+            day,
+            duration,
+        })
+    }
+
+    fn get_profile_usage(&self, profile_id: &ProfileId, day: NaiveDate) -> StoreResult<Duration> {
+        let mut inner = self.lock();
+        let stored = inner.read("profile usage", |p| p.get_profile_usage(profile_id, day));
+        Ok(stored
+            + inner.pending_sum(|w| match w {
+                PendingWrite::ProfileUsage { profile_id: p, day: d, duration, .. }
+                    if p == profile_id && *d == day =>
+                {
+                    Some(*duration)
+                }
+                _ => None,
+            }))
+    }
+
+    fn add_profile_usage(
+        &self,
+        profile_id: &ProfileId,
+        entry_id: &EntryId,
+        day: NaiveDate,
+        duration: Duration,
+    ) -> StoreResult<()> {
+        self.write(PendingWrite::ProfileUsage {
+            profile_id: profile_id.clone(),
+            entry_id: entry_id.clone(),
+            day,
+            duration,
+        })
+    }
+
+    fn usage_records_since(&self, device: &str, since: NaiveDate) -> StoreResult<Vec<UsageRecord>> {
+        let mut inner = self.lock();
+        let mut records = inner.read("usage records", |p| p.usage_records_since(device, since));
+        for write in &inner.pending {
+            let (profile_id, entry_id, day, duration) = match write {
+                PendingWrite::Usage { entry_id, day, duration } => (None, entry_id, *day, *duration),
+                PendingWrite::ProfileUsage { profile_id, entry_id, day, duration } => {
+                    (Some(profile_id), entry_id, *day, *duration)
+                }
+                _ => continue,
+            };
+            if day < since {
+                continue;
+            }
+            let existing = records.iter_mut().find(|r| {
+                r.device == device
+                    && r.profile_id.as_ref() == profile_id
+                    && r.entry_id == *entry_id
+                    && r.day == day
+            });
+            match existing {
+                Some(record) => record.duration_secs += duration.as_secs(),
+                None => records.push(UsageRecord {
+                    device: device.to_string(),
+                    profile_id: profile_id.cloned(),
+                    entry_id: entry_id.clone(),
+                    day,
+                    duration_secs: duration.as_secs(),
+                }),
+            }
+        }
+        Ok(records)
+    }
+
+    fn merge_usage_records(&self, device: &str, records: &[UsageRecord]) -> StoreResult<usize> {
+        // Not journalled: peers send their whole window on every sync, so
+        // the first sync after the database is back restores what was missed
+        let mut inner = self.lock();
+        if !inner.pending.is_empty() || inner.primary.is_none() {
+            return Err(StoreError::Database {
+                kind: ErrorKind::Unavailable,
+                context: "Store is degraded".into(),
+            });
+        }
+        let result = inner.read("remote usage", |p| p.merge_usage_records(device, records).map(Some));
+        result.ok_or_else(|| StoreError::Database {
+            kind: ErrorKind::Unavailable,
+            context: "Failed to merge remote usage".into(),
+        })
+    }
+
+    fn get_time_bank(&self, tag: &str, day: NaiveDate) -> StoreResult<Duration> {
+        let mut inner = self.lock();
+        let stored = inner.read("time bank", |p| p.get_time_bank(tag, day));
+        Ok(stored
+            + inner.pending_sum(|w| match w {
+                PendingWrite::TimeBank { tag: t, day: d, duration } if t == tag && *d == day => {
+                    Some(*duration)
+                }
+                _ => None,
+            }))
+    }
+
+    fn add_time_bank(&self, tag: &str, day: NaiveDate, duration: Duration) -> StoreResult<()> {
+        self.write(PendingWrite::TimeBank {
+            tag: tag.to_string(),
+            day,
+            duration,
+        })
+    }
+
+    fn get_cooldown_until(&self, key: &CooldownKey) -> StoreResult<Option<DateTime<Local>>> {
+        let mut inner = self.lock();
+        // The latest pending set or clear wins
+        let pending = inner.pending.iter().rev().find_map(|w| match w {
+            PendingWrite::Cooldown { key: k, until } if k == key => Some(Some(*until)),
+            PendingWrite::ClearCooldown(k) if k == key => Some(None),
+            _ => None,
+        });
+        match pending {
+            Some(until) => Ok(until),
+            None => Ok(inner.read("cooldown", |p| p.get_cooldown_until(key))),
+        }
+    }
+
+    fn set_cooldown_until(&self, key: &CooldownKey, until: DateTime<Local>) -> StoreResult<()> {
+        self.write(PendingWrite::Cooldown {
+            key: key.clone(),
+            until,
+        })
+    }
+
+    fn clear_cooldown(&self, key: &CooldownKey) -> StoreResult<()> {
+        self.write(PendingWrite::ClearCooldown(key.clone()))
+    }
+
+    fn get_last_update(&self, entry_id: &EntryId) -> StoreResult<Option<DateTime<Local>>> {
+        let mut inner = self.lock();
+        let pending = inner.pending.iter().rev().find_map(|w| match w {
+            PendingWrite::LastUpdate { entry_id: e, at } if e == entry_id => Some(*at),
+            _ => None,
+        });
+        match pending {
+            Some(at) => Ok(Some(at)),
+            None => Ok(inner.read("last update", |p| p.get_last_update(entry_id))),
+        }
+    }
+
+    fn set_last_update(&self, entry_id: &EntryId, at: DateTime<Local>) -> StoreResult<()> {
+        self.write(PendingWrite::LastUpdate {
+            entry_id: entry_id.clone(),
+            at,
+        })
+    }
+
+    fn record_check_in(&self, check_in: &SessionCheckIn) -> StoreResult<()> {
+        self.write(PendingWrite::CheckIn(check_in.clone()))
+    }
+
+    fn get_recent_check_ins(&self, limit: usize) -> StoreResult<Vec<SessionCheckIn>> {
+        let mut inner = self.lock();
+        let mut check_ins: Vec<SessionCheckIn> = inner
+            .pending
+            .iter()
+            .rev()
+            .filter_map(|w| match w {
+                PendingWrite::CheckIn(check_in) => Some(check_in.clone()),
+                _ => None,
+            })
+            .take(limit)
+            .collect();
+        let remaining = limit - check_ins.len();
+        if remaining > 0 {
+            check_ins.extend(inner.read("check-ins", |p| p.get_recent_check_ins(remaining)));
+        }
+        Ok(check_ins)
+    }
+
+    fn get_check_in(&self, session_id: &SessionId) -> StoreResult<Option<SessionCheckIn>> {
+        let mut inner = self.lock();
+        let pending = inner.pending.iter().rev().find_map(|w| match w {
+            PendingWrite::CheckIn(check_in) if &check_in.session_id == session_id => {
+                Some(check_in.clone())
+            }
+            _ => None,
+        });
+        match pending {
+            Some(check_in) => Ok(Some(check_in)),
+            None => Ok(inner.read("check-in", |p| p.get_check_in(session_id))),
+        }
+    }
+
+    fn add_reward(&self, grant: &RewardGrant) -> StoreResult<()> {
+        self.write(PendingWrite::Reward(grant.clone()))
+    }
+
+    fn get_total_stars(&self) -> StoreResult<u32> {
+        let mut inner = self.lock();
+        let stored = inner.read("stars", |p| p.get_total_stars());
+        let pending: u32 = inner
+            .pending
+            .iter()
+            .filter_map(|w| match w {
+                PendingWrite::Reward(grant) => Some(grant.stars),
+                _ => None,
+            })
+            .sum();
+        Ok(stored + pending)
+    }
+
+    fn get_hold(&self) -> StoreResult<Option<Hold>> {
+        let mut inner = self.lock();
+        let pending = inner.pending.iter().rev().find_map(|w| match w {
+            PendingWrite::Hold(hold) => Some(hold.clone()),
+            _ => None,
+        });
+        match pending {
+            Some(hold) => Ok(hold),
+            None => Ok(inner.read("hold", |p| p.get_hold())),
+        }
+    }
+
+    fn set_hold(&self, hold: Option<&Hold>) -> StoreResult<()> {
+        self.write(PendingWrite::Hold(hold.cloned()))
+    }
+
+    fn load_snapshot(&self) -> StoreResult<Option<StateSnapshot>> {
+        let mut inner = self.lock();
+        let pending = inner.pending.iter().rev().find_map(|w| match w {
+            PendingWrite::Snapshot(snapshot) => Some(snapshot.clone()),
+            _ => None,
+        });
+        match pending {
+            Some(snapshot) => Ok(Some(snapshot)),
+            None => Ok(inner.read("snapshot", |p| p.load_snapshot())),
+        }
+    }
+
+    fn save_snapshot(&self, snapshot: &StateSnapshot) -> StoreResult<()> {
+        self.write(PendingWrite::Snapshot(snapshot.clone()))
+    }
+
+    fn is_healthy(&self) -> bool {
+        let inner = self.lock();
+        !inner.degraded && inner.primary.as_ref().is_some_and(|p| p.is_healthy())
+    }
+
+    fn last_audit_write(&self) -> Option<DateTime<Local>> {
+        self.lock().last_audit_write
+    }
+
+    fn pending_writes(&self) -> usize {
+        self.lock().pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AuditEventType;
+
+    #[test]
+    fn test_healthy_store_writes_through() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shepherdd.db");
+        let entry_id = EntryId::new("game-1");
+        let today = shepherd_util::now().date_naive();
+
+        let store = ResilientStore::open(&path);
+        assert!(store.is_healthy());
+        store.add_usage(&entry_id, today, Duration::from_secs(60)).unwrap();
+        assert_eq!(store.pending_writes(), 0);
+
+        let sqlite = SqliteStore::open(&path).unwrap();
+        assert_eq!(sqlite.get_usage(&entry_id, today).unwrap(), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_corrupt_database_degrades_then_recovers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shepherdd.db");
+        std::fs::write(&path, b"this is not a sqlite database, just some bytes").unwrap();
+        let entry_id = EntryId::new("game-1");
+        let cooldown = CooldownKey::Entry(entry_id.clone());
+        let today = shepherd_util::now().date_naive();
+
+        let store = ResilientStore::open(&path);
+        assert!(!store.is_healthy());
+        assert!(store.is_degraded());
+
+        // Writes succeed and reads see them
+        store.append_audit(AuditEvent::new(AuditEventType::ServiceStarted)).unwrap();
+        store.add_usage(&entry_id, today, Duration::from_secs(300)).unwrap();
+        store.add_usage(&entry_id, today, Duration::from_secs(200)).unwrap();
+        let until = shepherd_util::now() + chrono::Duration::minutes(10);
+        store.set_cooldown_until(&cooldown, until).unwrap();
+        assert_eq!(store.get_usage(&entry_id, today).unwrap(), Duration::from_secs(500));
+        assert_eq!(store.get_cooldown_until(&cooldown).unwrap(), Some(until));
+        assert_eq!(store.get_recent_audits(10).unwrap().len(), 1);
+        assert_eq!(store.pending_writes(), 4);
+        assert!(store.last_audit_write().is_none());
+
+        // A later clear hides the pending cooldown
+        store.clear_cooldown(&cooldown).unwrap();
+        assert_eq!(store.get_cooldown_until(&cooldown).unwrap(), None);
+
+        // Still corrupt: nothing is lost by retrying
+        assert!(!store.retry());
+        assert_eq!(store.pending_writes(), 5);
+
+        // Once the file is moved aside, the journal is replayed
+        std::fs::remove_file(&path).unwrap();
+        assert!(store.retry());
+        assert!(store.is_healthy());
+        assert_eq!(store.pending_writes(), 0);
+        assert!(store.last_audit_write().is_some());
+        assert_eq!(store.get_usage(&entry_id, today).unwrap(), Duration::from_secs(500));
+        assert_eq!(store.get_cooldown_until(&cooldown).unwrap(), None);
+
+        let sqlite = SqliteStore::open(&path).unwrap();
+        assert_eq!(sqlite.get_usage(&entry_id, today).unwrap(), Duration::from_secs(500));
+        assert!(matches!(
+            sqlite.get_recent_audits(10).unwrap()[0].event,
+            AuditEventType::ServiceStarted
+        ));
+    }
+}
@@ -0,0 +1,112 @@
+//! Per-session timelines assembled from the audit log and check-ins
+
+use shepherd_api::{SessionTimeline, TimelineEvent, TimelineEventKind};
+use shepherd_util::SessionId;
+
+use crate::{Store, StoreResult};
+
+/// Everything recorded about a session, oldest first. None if the store
+/// knows nothing about it.
+pub fn session_timeline(store: &dyn Store, session_id: &SessionId) -> StoreResult<Option<SessionTimeline>> {
+    let mut events: Vec<TimelineEvent> = store
+        .get_session_audits(session_id)?
+        .into_iter()
+        .filter_map(|audit| {
+            Some(TimelineEvent {
+                timestamp: audit.timestamp,
+                kind: audit.event.timeline_kind()?,
+            })
+        })
+        .collect();
+
+    if let Some(check_in) = store.get_check_in(session_id)? {
+        events.push(TimelineEvent {
+            timestamp: check_in.timestamp,
+            kind: TimelineEventKind::CheckIn { mood: check_in.mood },
+        });
+    }
+
+    if events.is_empty() {
+        return Ok(None);
+    }
+    // Stable, so events logged in the same instant keep their audit order
+    events.sort_by_key(|event| event.timestamp);
+
+    Ok(Some(SessionTimeline {
+        session_id: session_id.clone(),
+        events,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuditEvent, AuditEventType, SessionCheckIn, SqliteStore};
+    use shepherd_api::{Mood, SessionEndReason};
+    use shepherd_util::EntryId;
+    use std::time::Duration;
+
+    #[test]
+    fn test_session_timeline() {
+        let store = SqliteStore::in_memory().unwrap();
+        let session_id = SessionId::new();
+        let other_session = SessionId::new();
+        let entry_id = EntryId::new("tuxmath");
+        let start = shepherd_util::now();
+        let at = |secs| start + chrono::Duration::seconds(secs);
+
+        let audit = |secs, event| AuditEvent {
+            id: 0,
+            timestamp: at(secs),
+            event,
+        };
+        store
+            .append_audit(audit(0, AuditEventType::SessionStarted {
+                session_id: session_id.clone(),
+                entry_id: entry_id.clone(),
+                label: "Tux Math".into(),
+                deadline: Some(at(600)),
+            }))
+            .unwrap();
+        store
+            .append_audit(audit(1, AuditEventType::SessionStarted {
+                session_id: other_session.clone(),
+                entry_id: entry_id.clone(),
+                label: "Tux Math".into(),
+                deadline: None,
+            }))
+            .unwrap();
+        store
+            .append_audit(audit(540, AuditEventType::WarningIssued {
+                session_id: session_id.clone(),
+                threshold_seconds: 60,
+            }))
+            .unwrap();
+        store
+            .append_audit(audit(600, AuditEventType::SessionEnded {
+                session_id: session_id.clone(),
+                entry_id: entry_id.clone(),
+                reason: SessionEndReason::Expired,
+                duration: Duration::from_secs(600),
+            }))
+            .unwrap();
+        store
+            .record_check_in(&SessionCheckIn {
+                session_id: session_id.clone(),
+                entry_id,
+                mood: Mood::Good,
+                timestamp: at(610),
+            })
+            .unwrap();
+
+        let timeline = session_timeline(&store, &session_id).unwrap().unwrap();
+        let kinds: Vec<&TimelineEventKind> = timeline.events.iter().map(|e| &e.kind).collect();
+        assert_eq!(kinds.len(), 4);
+        assert!(matches!(kinds[0], TimelineEventKind::Started { .. }));
+        assert_eq!(kinds[1], &TimelineEventKind::Warning { threshold_seconds: 60 });
+        assert!(matches!(kinds[2], TimelineEventKind::Ended { reason: SessionEndReason::Expired, .. }));
+        assert_eq!(kinds[3], &TimelineEventKind::CheckIn { mood: Mood::Good });
+
+        assert!(session_timeline(&store, &SessionId::new()).unwrap().is_none());
+    }
+}
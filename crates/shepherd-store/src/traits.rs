@@ -1,11 +1,55 @@
 //! Store trait definitions
 
 use chrono::{DateTime, Local, NaiveDate};
-use shepherd_util::{EntryId, SessionId};
+use shepherd_api::{AuditVerification, CooldownScope, Hold, Mood};
+use shepherd_util::{EntryId, ProfileId, SessionId};
 use std::time::Duration;
 
 use crate::{AuditEvent, StoreResult};
 
+/// What a stored cooldown pauses: one entry, one tag or everything
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CooldownKey {
+    Entry(EntryId),
+    Tag(String),
+    Global,
+}
+
+impl CooldownKey {
+    /// The key a session of `entry_id` sets for a cooldown of this scope
+    pub fn new(scope: &CooldownScope, entry_id: &EntryId) -> Self {
+        match scope {
+            CooldownScope::Entry => CooldownKey::Entry(entry_id.clone()),
+            CooldownScope::Tag { tag } => CooldownKey::Tag(tag.clone()),
+            CooldownScope::Global => CooldownKey::Global,
+        }
+    }
+
+    pub fn scope(&self) -> CooldownScope {
+        match self {
+            CooldownKey::Entry(_) => CooldownScope::Entry,
+            CooldownKey::Tag(tag) => CooldownScope::Tag { tag: tag.clone() },
+            CooldownKey::Global => CooldownScope::Global,
+        }
+    }
+
+    /// Stored form. Entry IDs can't contain ':' or '*', so an entry key
+    /// is the bare ID, as it was before cooldowns had scopes.
+    pub fn to_key_string(&self) -> String {
+        match self {
+            CooldownKey::Entry(entry_id) => entry_id.to_string(),
+            CooldownKey::Tag(tag) => format!("tag:{}", tag),
+            CooldownKey::Global => "*".into(),
+        }
+    }
+}
+
+impl std::fmt::Display for CooldownKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_key_string())
+    }
+}
+
 /// Main store trait
 pub trait Store: Send + Sync {
     // Audit log
@@ -16,28 +60,107 @@ pub trait Store: Send + Sync {
     /// Get recent audit events
     fn get_recent_audits(&self, limit: usize) -> StoreResult<Vec<AuditEvent>>;
 
+    /// Get the audit events of one session, oldest first
+    fn get_session_audits(&self, session_id: &SessionId) -> StoreResult<Vec<AuditEvent>>;
+
+    /// When the last session of an entry ended, from its `session_ended` audit event
+    fn get_last_session_end(&self, entry_id: &EntryId) -> StoreResult<Option<DateTime<Local>>>;
+
+    /// Check the audit log's hash chain, and whether `known_head` is still
+    /// one of its hashes
+    fn verify_audit(&self, known_head: Option<&str>) -> StoreResult<AuditVerification>;
+
     // Usage accounting
 
-    /// Get total usage for an entry on a specific day
+    /// Get total usage for an entry on a specific day, on this device and
+    /// on federated ones
     fn get_usage(&self, entry_id: &EntryId, day: NaiveDate) -> StoreResult<Duration>;
 
     /// Add usage for an entry on a specific day
     fn add_usage(&self, entry_id: &EntryId, day: NaiveDate, duration: Duration) -> StoreResult<()>;
 
+    /// Get total usage charged to a profile on a specific day, on this
+    /// device and on federated ones
+    fn get_profile_usage(&self, profile_id: &ProfileId, day: NaiveDate) -> StoreResult<Duration>;
+
+    /// Charge usage of an entry to a profile on a specific day
+    fn add_profile_usage(
+        &self,
+        profile_id: &ProfileId,
+        entry_id: &EntryId,
+        day: NaiveDate,
+        duration: Duration,
+    ) -> StoreResult<()>;
+
+    // Federation
+
+    /// Usage from `since` on, as records to send to federated devices: this
+    /// device's totals under `device`, and the totals merged from others
+    fn usage_records_since(&self, device: &str, since: NaiveDate) -> StoreResult<Vec<UsageRecord>>;
+
+    /// Merge usage totals from federated devices into the totals returned by
+    /// [`get_usage`](Self::get_usage) and [`get_profile_usage`](Self::get_profile_usage).
+    /// Keeps the larger total per device, entry and day; records of `device`
+    /// itself are ignored. Returns how many totals changed.
+    fn merge_usage_records(&self, device: &str, records: &[UsageRecord]) -> StoreResult<usize>;
+
+    // Time bank
+
+    /// Get time credited to a tag on a specific day
+    fn get_time_bank(&self, tag: &str, day: NaiveDate) -> StoreResult<Duration>;
+
+    /// Credit time to a tag on a specific day
+    fn add_time_bank(&self, tag: &str, day: NaiveDate, duration: Duration) -> StoreResult<()>;
+
     // Cooldown tracking
 
-    /// Get cooldown expiry time for an entry
-    fn get_cooldown_until(&self, entry_id: &EntryId) -> StoreResult<Option<DateTime<Local>>>;
+    /// Get cooldown expiry time for an entry, tag or everything
+    fn get_cooldown_until(&self, key: &CooldownKey) -> StoreResult<Option<DateTime<Local>>>;
 
-    /// Set cooldown expiry time for an entry
+    /// Set cooldown expiry time for an entry, tag or everything
     fn set_cooldown_until(
         &self,
-        entry_id: &EntryId,
+        key: &CooldownKey,
         until: DateTime<Local>,
     ) -> StoreResult<()>;
 
-    /// Clear cooldown for an entry
-    fn clear_cooldown(&self, entry_id: &EntryId) -> StoreResult<()>;
+    /// Clear a cooldown
+    fn clear_cooldown(&self, key: &CooldownKey) -> StoreResult<()>;
+
+    // App updates
+
+    /// Get when an update was last attempted for an entry
+    fn get_last_update(&self, entry_id: &EntryId) -> StoreResult<Option<DateTime<Local>>>;
+
+    /// Record that an update was attempted for an entry
+    fn set_last_update(&self, entry_id: &EntryId, at: DateTime<Local>) -> StoreResult<()>;
+
+    // Session check-ins
+
+    /// Record how the child felt after a session
+    fn record_check_in(&self, check_in: &SessionCheckIn) -> StoreResult<()>;
+
+    /// Get recent check-ins, newest first
+    fn get_recent_check_ins(&self, limit: usize) -> StoreResult<Vec<SessionCheckIn>>;
+
+    /// Get the check-in for a session, if one was answered
+    fn get_check_in(&self, session_id: &SessionId) -> StoreResult<Option<SessionCheckIn>>;
+
+    // Rewards ledger
+
+    /// Record stars granted by an admin
+    fn add_reward(&self, grant: &RewardGrant) -> StoreResult<()>;
+
+    /// Get the total number of stars earned
+    fn get_total_stars(&self) -> StoreResult<u32>;
+
+    // Launch hold
+
+    /// Get the launch hold, if one is set
+    fn get_hold(&self) -> StoreResult<Option<Hold>>;
+
+    /// Set or clear the launch hold
+    fn set_hold(&self, hold: Option<&Hold>) -> StoreResult<()>;
 
     // State snapshot
 
@@ -51,6 +174,47 @@ pub trait Store: Send + Sync {
 
     /// Check if store is healthy
     fn is_healthy(&self) -> bool;
+
+    /// When an audit event last reached durable storage, if tracked
+    fn last_audit_write(&self) -> Option<DateTime<Local>> {
+        None
+    }
+
+    /// Writes accepted but not yet durable
+    fn pending_writes(&self) -> usize {
+        0
+    }
+}
+
+/// Post-session feelings check-in
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SessionCheckIn {
+    pub session_id: SessionId,
+    pub entry_id: EntryId,
+    pub mood: Mood,
+    pub timestamp: DateTime<Local>,
+}
+
+/// One device's usage total for an entry on a day, as exchanged between
+/// federated services. Totals only grow, so merging keeps the larger one.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct UsageRecord {
+    /// Device the usage happened on
+    pub device: String,
+    /// Profile the usage was charged to; None for the entry's own total
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile_id: Option<ProfileId>,
+    pub entry_id: EntryId,
+    pub day: NaiveDate,
+    pub duration_secs: u64,
+}
+
+/// Stars granted by an admin
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RewardGrant {
+    pub stars: u32,
+    pub reason: Option<String>,
+    pub timestamp: DateTime<Local>,
 }
 
 /// State snapshot for crash recovery
@@ -0,0 +1,177 @@
+//! Many tasks writing to one store at once, the way the service's main loop,
+//! IPC handlers and subscribers share it. Nothing may be lost, reordered
+//! within a writer, or leave the store unhealthy.
+
+use chrono::NaiveDate;
+use shepherd_store::{
+    AuditEvent, AuditEventType, ResilientStore, SessionSnapshot, SqliteStore, StateSnapshot, Store,
+};
+use shepherd_util::{EntryId, SessionId};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+const WRITERS: usize = 16;
+const WRITES: u64 = 50;
+const ENTRIES: usize = 4;
+/// More audit events than are written
+const ALL: usize = 10_000;
+
+fn day() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 6, 2).unwrap()
+}
+
+fn entry(writer: usize) -> EntryId {
+    EntryId::new(format!("entry-{}", writer % ENTRIES))
+}
+
+fn snapshot(session_id: &SessionId, writer: usize, write: u64) -> StateSnapshot {
+    let now = shepherd_util::now();
+    StateSnapshot {
+        timestamp: now,
+        active_session: Some(SessionSnapshot {
+            session_id: session_id.clone(),
+            entry_id: entry(writer),
+            started_at: now,
+            deadline: now,
+            warnings_issued: (0..write).collect(),
+        }),
+    }
+}
+
+/// Every writer adds usage, appends audit events and saves snapshots;
+/// returns each writer's session ID
+async fn hammer(store: Arc<dyn Store>) -> Vec<SessionId> {
+    let mut tasks = Vec::new();
+    for writer in 0..WRITERS {
+        let store = store.clone();
+        tasks.push(tokio::spawn(async move {
+            let session_id = SessionId::new();
+            for write in 0..WRITES {
+                store.add_usage(&entry(writer), day(), Duration::from_secs(1)).unwrap();
+                store
+                    .append_audit(AuditEvent::new(AuditEventType::WarningIssued {
+                        session_id: session_id.clone(),
+                        threshold_seconds: write,
+                    }))
+                    .unwrap();
+                store.save_snapshot(&snapshot(&session_id, writer, write)).unwrap();
+                tokio::task::yield_now().await;
+            }
+            session_id
+        }));
+    }
+
+    let mut sessions = Vec::new();
+    for task in tasks {
+        sessions.push(task.await.unwrap());
+    }
+    sessions
+}
+
+fn check(store: &dyn Store, sessions: &[SessionId]) {
+    let per_entry = (WRITERS / ENTRIES) as u64 * WRITES;
+    for e in 0..ENTRIES {
+        assert_eq!(
+            store.get_usage(&entry(e), day()).unwrap(),
+            Duration::from_secs(per_entry),
+            "usage of {} was lost",
+            entry(e)
+        );
+    }
+
+    let audits = store.get_recent_audits(ALL).unwrap();
+    assert_eq!(audits.len(), WRITERS * WRITES as usize);
+    // Journalled events get their ID when they reach the database
+    let stored: Vec<i64> = audits.iter().map(|audit| audit.id).filter(|id| *id != 0).collect();
+    let ids: HashSet<i64> = stored.iter().copied().collect();
+    assert_eq!(ids.len(), stored.len(), "audit IDs are reused");
+
+    for session_id in sessions {
+        let thresholds: Vec<u64> = store
+            .get_session_audits(session_id)
+            .unwrap()
+            .into_iter()
+            .map(|audit| match audit.event {
+                AuditEventType::WarningIssued { threshold_seconds, .. } => threshold_seconds,
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        assert_eq!(thresholds, (0..WRITES).collect::<Vec<_>>(), "writes reordered");
+    }
+
+    // The snapshot is one writer's whole write, never a mix
+    let snapshot = store.load_snapshot().unwrap().unwrap();
+    let session = snapshot.active_session.unwrap();
+    let writer = sessions.iter().position(|id| *id == session.session_id).unwrap();
+    assert_eq!(session.entry_id, entry(writer));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_writers_lose_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shepherdd.db");
+
+    let store = Arc::new(SqliteStore::open(&path).unwrap());
+    let sessions = hammer(store.clone()).await;
+    assert!(store.is_healthy());
+    check(store.as_ref(), &sessions);
+
+    // And all of it reached the file
+    drop(store);
+    check(&SqliteStore::open(&path).unwrap(), &sessions);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_writers_lose_nothing_through_the_fallback() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shepherdd.db");
+
+    let store = Arc::new(ResilientStore::open(&path));
+    let sessions = hammer(store.clone()).await;
+    assert!(!store.is_degraded());
+    assert!(store.is_healthy());
+    check(store.as_ref(), &sessions);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_writers_lose_nothing_while_degraded() {
+    let dir = tempfile::tempdir().unwrap();
+    // A directory can't be opened as a database, so writes are journalled
+    let path = dir.path().join("shepherdd.db");
+    std::fs::create_dir(&path).unwrap();
+
+    let store = Arc::new(ResilientStore::open(&path));
+    assert!(store.is_degraded());
+    let sessions = hammer(store.clone()).await;
+    check(store.as_ref(), &sessions);
+
+    // Replaying the journal keeps every write and its order
+    std::fs::remove_dir(&path).unwrap();
+    assert!(store.retry());
+    assert!(store.is_healthy());
+    check(store.as_ref(), &sessions);
+    check(&SqliteStore::open(&path).unwrap(), &sessions);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn a_panicking_writer_leaves_the_store_usable() {
+    let store = Arc::new(SqliteStore::in_memory().unwrap());
+
+    let panicking = {
+        let store = store.clone();
+        tokio::spawn(async move {
+            store.add_usage(&entry(0), day(), Duration::from_secs(1)).unwrap();
+            panic!("writer crashed");
+        })
+    };
+    assert!(panicking.await.unwrap_err().is_panic());
+
+    let sessions = hammer(store.clone()).await;
+    assert_eq!(
+        store.get_usage(&entry(0), day()).unwrap(),
+        Duration::from_secs((WRITERS / ENTRIES) as u64 * WRITES + 1)
+    );
+    assert_eq!(store.get_recent_audits(ALL).unwrap().len(), sessions.len() * WRITES as usize);
+    assert!(store.is_healthy());
+}
@@ -0,0 +1,238 @@
+//! Dashboard state, fed by shepherdd's responses and events
+
+use chrono::{DateTime, Local};
+use shepherd_api::{
+    ClientInfo, DayOverview, ErrorCode, Event, EventPayload, Response, ResponsePayload,
+    ResponseResult, ServiceStateSnapshot,
+};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Lines kept in the audit tail
+const LOG_HISTORY: usize = 500;
+
+/// Lines moved by Page Up and Page Down
+const PAGE: usize = 10;
+
+/// One line of the audit tail
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub text: String,
+    /// Something an admin should look at, like an enforcement failure
+    pub alert: bool,
+}
+
+/// What a key press asks the dashboard loop to do
+#[derive(Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Refresh,
+    None,
+}
+
+#[derive(Default)]
+pub struct Dashboard {
+    pub state: Option<ServiceStateSnapshot>,
+    /// When `state` was fetched, to keep its countdown running
+    pub fetched: Option<Instant>,
+    pub day: Option<DayOverview>,
+    pub clients: Vec<ClientInfo>,
+    /// Why the client list isn't shown, once shepherdd refused it
+    pub clients_denied: Option<String>,
+    /// Request ID of the last client list request
+    pub clients_request: Option<u64>,
+    pub log: VecDeque<LogLine>,
+    /// Lines scrolled back from the newest; 0 follows new events
+    pub scroll: usize,
+    /// Last error from shepherdd
+    pub error: Option<String>,
+    pub updated: Option<DateTime<Local>>,
+}
+
+impl Dashboard {
+    /// Time elapsed since the state was fetched
+    pub fn since_fetch(&self) -> Duration {
+        self.fetched.map(|f| f.elapsed()).unwrap_or_default()
+    }
+
+    pub fn apply_response(&mut self, response: Response) {
+        let for_clients = self.clients_request == Some(response.request_id);
+        match response.result {
+            ResponseResult::Ok(ResponsePayload::State(state)) => {
+                self.state = Some(state);
+                self.fetched = Some(Instant::now());
+                self.updated = Some(shepherd_util::now());
+            }
+            ResponseResult::Ok(ResponsePayload::DayOverview(day)) => self.day = Some(day),
+            ResponseResult::Ok(ResponsePayload::Clients { clients }) => self.clients = clients,
+            ResponseResult::Ok(_) => {}
+            // Not an admin: leave the panel empty rather than failing
+            // again on every refresh
+            ResponseResult::Err(e) if for_clients && e.code == ErrorCode::PermissionDenied => {
+                self.clients_denied = Some(e.message);
+            }
+            ResponseResult::Err(e) => self.error = Some(e.message),
+        }
+    }
+
+    /// Take in an event, and report whether the panels are stale enough
+    /// to fetch again straight away
+    pub fn apply_event(&mut self, event: &Event) -> bool {
+        if let EventPayload::StateChanged(state) = &event.payload
+            && self.state.as_ref().is_none_or(|current| current.seat == state.seat)
+        {
+            self.state = Some(state.clone());
+            self.fetched = Some(Instant::now());
+        }
+        if let Some(line) = describe(event) {
+            self.push_log(line);
+        }
+        matches!(
+            event.payload,
+            EventPayload::SessionStarted { .. }
+                | EventPayload::SessionEnded { .. }
+                | EventPayload::PolicyReloaded { .. }
+        )
+    }
+
+    fn push_log(&mut self, line: LogLine) {
+        if self.log.len() == LOG_HISTORY {
+            self.log.pop_front();
+        }
+        self.log.push_back(line);
+        // Keep the lines being read in place
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + 1).min(self.log.len());
+        }
+    }
+
+    fn scroll_by(&mut self, lines: isize) {
+        self.scroll = self.scroll.saturating_add_signed(lines).min(self.log.len());
+    }
+
+    pub fn handle_key(&mut self, key: &KeyPress) -> Action {
+        match key {
+            KeyPress::Quit => return Action::Quit,
+            KeyPress::Refresh => {
+                self.error = None;
+                return Action::Refresh;
+            }
+            KeyPress::Up => self.scroll_by(1),
+            KeyPress::Down => self.scroll_by(-1),
+            KeyPress::PageUp => self.scroll_by(PAGE as isize),
+            KeyPress::PageDown => self.scroll_by(-(PAGE as isize)),
+            KeyPress::Oldest => self.scroll = self.log.len(),
+            KeyPress::Newest => self.scroll = 0,
+        }
+        Action::None
+    }
+}
+
+/// The keys the dashboard acts on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyPress {
+    Quit,
+    Refresh,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Oldest,
+    Newest,
+}
+
+/// One line for the audit tail, or None for events the panels already show
+pub fn describe(event: &Event) -> Option<LogLine> {
+    if matches!(
+        event.payload,
+        EventPayload::StateChanged(_) | EventPayload::TimeRemaining { .. }
+    ) {
+        return None;
+    }
+    let mut details = serde_json::to_value(&event.payload).ok()?;
+    let kind = details
+        .as_object_mut()
+        .and_then(|fields| fields.remove("type"))
+        .and_then(|kind| kind.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let seat = event.seat.as_ref().map(|s| format!(" [{}]", s)).unwrap_or_default();
+    let details = match details.as_object() {
+        Some(fields) if fields.is_empty() => String::new(),
+        _ => format!(" {}", details),
+    };
+    Some(LogLine {
+        text: format!("{} {}{}{}", event.timestamp.format("%H:%M:%S"), kind, seat, details),
+        alert: matches!(
+            event.payload,
+            EventPayload::AuditEntry { .. } | EventPayload::BudgetAlert { .. }
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shepherd_api::ErrorInfo;
+    use shepherd_util::EntryId;
+
+    fn availability(enabled: bool) -> Event {
+        Event::new(EventPayload::EntryAvailabilityChanged {
+            entry_id: EntryId::new("minecraft"),
+            enabled,
+        })
+    }
+
+    #[test]
+    fn test_describe() {
+        let line = describe(&availability(false)).unwrap();
+        assert!(line.text.contains("entry_availability_changed"));
+        assert!(line.text.contains("\"minecraft\""));
+        assert!(!line.alert);
+
+        let cancelled = describe(&Event::new(EventPayload::PowerActionCancelled)).unwrap();
+        assert!(cancelled.text.ends_with("power_action_cancelled"));
+
+        let failed = describe(&Event::new(EventPayload::AuditEntry {
+            event_type: "enforcement_failed".into(),
+            details: serde_json::json!({}),
+        }))
+        .unwrap();
+        assert!(failed.alert);
+    }
+
+    #[test]
+    fn test_scroll_keeps_place() {
+        let mut dashboard = Dashboard::default();
+        for _ in 0..5 {
+            dashboard.apply_event(&availability(true));
+        }
+        assert_eq!(dashboard.scroll, 0);
+
+        dashboard.handle_key(&KeyPress::Up);
+        dashboard.handle_key(&KeyPress::Up);
+        dashboard.apply_event(&availability(false));
+        assert_eq!(dashboard.scroll, 3);
+
+        dashboard.handle_key(&KeyPress::PageUp);
+        assert_eq!(dashboard.scroll, 6);
+        dashboard.handle_key(&KeyPress::PageDown);
+        assert_eq!(dashboard.scroll, 0);
+    }
+
+    #[test]
+    fn test_clients_denied() {
+        let mut dashboard = Dashboard {
+            clients_request: Some(7),
+            ..Default::default()
+        };
+        dashboard.apply_response(Response::error(
+            7,
+            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+        ));
+        assert_eq!(dashboard.clients_denied.as_deref(), Some("Admin role required"));
+        assert!(dashboard.error.is_none());
+
+        dashboard.apply_response(Response::error(8, ErrorInfo::new(ErrorCode::PermissionDenied, "Nope")));
+        assert_eq!(dashboard.error.as_deref(), Some("Nope"));
+    }
+}
@@ -0,0 +1,52 @@
+//! Text helpers shared by the dashboard and `shepherdctl`'s plain output
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::time::Duration;
+
+/// The serde name of an enum value, like "admin"
+pub fn name(value: &impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        Ok(other) => other.to_string(),
+        Err(_) => "?".to_string(),
+    }
+}
+
+/// A time of day, with the date if it isn't today
+pub fn time(at: DateTime<Local>) -> String {
+    if at.date_naive() == shepherd_util::now().date_naive() {
+        at.format("%H:%M").to_string()
+    } else {
+        at.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+/// Cells of a `width` cell bar filled for `used` out of `quota`
+pub fn filled_cells(used: Duration, quota: Duration, width: usize) -> usize {
+    if quota.is_zero() {
+        return width;
+    }
+    (((used.as_secs_f64() / quota.as_secs_f64()) * width as f64).round() as usize).min(width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shepherd_api::{AudioOutput, ClientRole};
+
+    #[test]
+    fn test_name() {
+        assert_eq!(name(&AudioOutput::Headphones), "headphones");
+        assert_eq!(name(&ClientRole::Admin), "admin");
+    }
+
+    #[test]
+    fn test_filled_cells() {
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+        assert_eq!(filled_cells(minutes(0), minutes(60), 16), 0);
+        assert_eq!(filled_cells(minutes(30), minutes(60), 16), 8);
+        assert_eq!(filled_cells(minutes(90), minutes(60), 16), 16);
+        assert_eq!(filled_cells(minutes(5), Duration::ZERO, 16), 16);
+    }
+}
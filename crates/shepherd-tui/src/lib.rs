@@ -0,0 +1,136 @@
+//! Shepherd terminal dashboard
+//!
+//! A live view of shepherdd for headless boxes and SSH sessions: the running
+//! session and its countdown, today's usage against each quota, the
+//! connected clients and a scrolling tail of the event stream. The
+//! `shepherd-tui` binary and `shepherdctl top` both run [`run`].
+//!
+//! Events come in on a subscribed connection, and the state, usage and
+//! client list are fetched again on the same connection every couple of
+//! seconds and whenever a session starts or ends.
+
+mod app;
+pub mod format;
+mod ui;
+
+use anyhow::{Context, Result, bail};
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event as TermEvent, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use shepherd_api::Command;
+use shepherd_ipc::{CommandSender, EventStream, IpcClient, StreamMessage};
+use std::io::IsTerminal;
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use app::{Action, Dashboard, KeyPress};
+
+/// How often the panels are fetched again
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Redraw at least this often, so the countdown keeps moving
+const REDRAW_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Forward terminal events from a blocking reader thread
+fn spawn_terminal_reader(tx: mpsc::UnboundedSender<TermEvent>) {
+    std::thread::spawn(move || {
+        while !tx.is_closed() {
+            match event::poll(Duration::from_millis(250)) {
+                Ok(true) => match event::read() {
+                    Ok(event) => {
+                        if tx.send(event).is_err() {
+                            return;
+                        }
+                    }
+                    Err(_) => return,
+                },
+                Ok(false) => {}
+                Err(_) => return,
+            }
+        }
+    });
+}
+
+fn key_press(key: &KeyEvent) -> Option<KeyPress> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+    Some(match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => KeyPress::Quit,
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => KeyPress::Quit,
+        KeyCode::Char('r') => KeyPress::Refresh,
+        KeyCode::Up | KeyCode::Char('k') => KeyPress::Up,
+        KeyCode::Down | KeyCode::Char('j') => KeyPress::Down,
+        KeyCode::PageUp => KeyPress::PageUp,
+        KeyCode::PageDown => KeyPress::PageDown,
+        KeyCode::Home | KeyCode::Char('g') => KeyPress::Oldest,
+        KeyCode::End | KeyCode::Char('G') => KeyPress::Newest,
+        _ => return None,
+    })
+}
+
+async fn refresh(commands: &mut CommandSender, dashboard: &mut Dashboard) -> Result<()> {
+    commands.send(Command::GetState { seat: None }).await?;
+    commands.send(Command::GetDayOverview).await?;
+    if dashboard.clients_denied.is_none() {
+        dashboard.clients_request = Some(commands.send(Command::ListClients).await?);
+    }
+    Ok(())
+}
+
+async fn run_loop(terminal: &mut DefaultTerminal, mut stream: EventStream, mut commands: CommandSender) -> Result<()> {
+    let mut dashboard = Dashboard::default();
+    let (term_tx, mut term_rx) = mpsc::unbounded_channel();
+    spawn_terminal_reader(term_tx);
+
+    let mut refresh_timer = tokio::time::interval(REFRESH_INTERVAL);
+    let mut redraw_timer = tokio::time::interval(REDRAW_INTERVAL);
+    loop {
+        terminal.draw(|frame| ui::draw(frame, &dashboard))?;
+        tokio::select! {
+            _ = refresh_timer.tick() => refresh(&mut commands, &mut dashboard).await?,
+            _ = redraw_timer.tick() => {}
+            message = stream.next_message() => match message.context("Lost the connection to shepherdd")? {
+                StreamMessage::Event(event) => {
+                    if dashboard.apply_event(&event) {
+                        refresh(&mut commands, &mut dashboard).await?;
+                    }
+                }
+                StreamMessage::Response(response) => dashboard.apply_response(response),
+            },
+            Some(event) = term_rx.recv() => {
+                let TermEvent::Key(key) = event else {
+                    continue;
+                };
+                let Some(key) = key_press(&key) else {
+                    continue;
+                };
+                match dashboard.handle_key(&key) {
+                    Action::Quit => return Ok(()),
+                    Action::Refresh => refresh(&mut commands, &mut dashboard).await?,
+                    Action::None => {}
+                }
+            }
+        }
+    }
+}
+
+/// Connect to shepherdd at `socket_path` and show the dashboard until the
+/// user quits
+pub async fn run(socket_path: &Path) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        bail!("The dashboard needs a terminal");
+    }
+    let client = IpcClient::connect(socket_path)
+        .await
+        .with_context(|| format!("Failed to connect to shepherdd at {:?}", socket_path))?;
+    let (stream, commands) = client
+        .subscribe_with_commands()
+        .await
+        .context("Failed to subscribe to events")?;
+
+    let mut terminal = ratatui::init();
+    let result = run_loop(&mut terminal, stream, commands).await;
+    ratatui::restore();
+    result
+}
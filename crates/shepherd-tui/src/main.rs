@@ -0,0 +1,26 @@
+//! Shepherd TUI - terminal dashboard for shepherdd
+//!
+//! Shows the live session, today's usage, connected clients and the event
+//! stream in a terminal, for headless boxes and SSH sessions.
+
+use anyhow::Result;
+use clap::Parser;
+use shepherd_util::default_socket_path;
+use std::path::PathBuf;
+
+/// Shepherd TUI - terminal dashboard for shepherdd
+#[derive(Parser, Debug)]
+#[command(name = "shepherd-tui")]
+#[command(about = "Terminal dashboard for shepherdd", long_about = None)]
+struct Args {
+    /// Socket path for shepherdd connection (or set SHEPHERD_SOCKET env var)
+    #[arg(short, long, env = "SHEPHERD_SOCKET")]
+    socket: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let socket_path = args.socket.unwrap_or_else(default_socket_path);
+    shepherd_tui::run(&socket_path).await
+}
@@ -0,0 +1,238 @@
+//! Drawing the dashboard
+
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Gauge, List, Paragraph, Row, Table};
+use shepherd_util::format_duration;
+use std::time::Duration;
+
+use crate::app::Dashboard;
+use crate::format::{filled_cells, name, time};
+
+/// Width of the usage bars in the today panel
+const BAR_WIDTH: usize = 16;
+
+/// Time left under which the countdown turns yellow, then red
+const LOW_TIME: Duration = Duration::from_secs(5 * 60);
+const CRITICAL_TIME: Duration = Duration::from_secs(60);
+
+fn time_color(remaining: Duration) -> Color {
+    if remaining < CRITICAL_TIME {
+        Color::Red
+    } else if remaining < LOW_TIME {
+        Color::Yellow
+    } else {
+        Color::Green
+    }
+}
+
+fn draw_session(frame: &mut Frame, area: Rect, dashboard: &Dashboard) {
+    let block = Block::bordered().title(" Session ");
+    let Some(state) = &dashboard.state else {
+        frame.render_widget(Paragraph::new("Waiting for shepherdd...").block(block), area);
+        return;
+    };
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let [text_area, gauge_area] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(inner);
+
+    let running = dashboard.since_fetch();
+    let mut lines = Vec::new();
+    match &state.current_session {
+        Some(session) => {
+            lines.push(Line::styled(
+                format!("{} ({})", session.label, session.entry_id),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            lines.push(Line::from(format!(
+                "{} on {}, started {}",
+                name(&session.state),
+                session.seat,
+                time(session.started_at)
+            )));
+            if !session.participants.is_empty() {
+                let names: Vec<String> = session.participants.iter().map(ToString::to_string).collect();
+                lines.push(Line::from(format!("With {}", names.join(", "))));
+            }
+
+            let elapsed = session.elapsed + running;
+            let gauge = match session.time_remaining {
+                Some(remaining) => {
+                    let remaining = remaining.saturating_sub(running);
+                    let total = elapsed + remaining;
+                    let ratio = if total.is_zero() {
+                        1.0
+                    } else {
+                        elapsed.as_secs_f64() / total.as_secs_f64()
+                    };
+                    Gauge::default()
+                        .gauge_style(Style::default().fg(time_color(remaining)))
+                        .ratio(ratio.clamp(0.0, 1.0))
+                        .label(format!("{} left", format_duration(remaining)))
+                }
+                None => Gauge::default()
+                    .gauge_style(Style::default().fg(Color::Green))
+                    .ratio(0.0)
+                    .label(format!("{} elapsed, no time limit", format_duration(elapsed))),
+            };
+            frame.render_widget(gauge, gauge_area);
+        }
+        None => lines.push(Line::from(format!("No session on {}", state.seat))),
+    }
+
+    let available = state.entries.iter().filter(|e| e.enabled).count();
+    lines.push(Line::from(format!(
+        "{} of {} entries available, {} star(s)",
+        available, state.entry_count, state.total_stars
+    )));
+    if let Some(hold) = &state.hold {
+        let text = match hold.until {
+            Some(until) => format!("Launches on hold until {}", time(until)),
+            None => "Launches on hold".to_string(),
+        };
+        lines.push(Line::styled(text, Style::default().fg(Color::Yellow)));
+    }
+    if let Some(power) = &state.pending_power_action {
+        lines.push(Line::styled(
+            format!("{} at {}", name(&power.action), time(power.deadline)),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    frame.render_widget(Paragraph::new(lines), text_area);
+}
+
+fn usage_lines(dashboard: &Dashboard) -> Vec<Line<'static>> {
+    let Some(day) = &dashboard.day else {
+        return vec![Line::from("Waiting for shepherdd...")];
+    };
+    let mut lines = Vec::new();
+    for category in &day.categories {
+        lines.push(Line::styled(
+            category.name.clone().unwrap_or_else(|| "Other".to_string()),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        for entry in &category.entries {
+            let label = format!("  {:<20.20} ", entry.label);
+            let line = match entry.quota {
+                Some(quota) => {
+                    let filled = filled_cells(entry.used, quota, BAR_WIDTH);
+                    let color = if entry.used >= quota { Color::Red } else { Color::Green };
+                    Line::from(vec![
+                        Span::raw(label),
+                        Span::styled("█".repeat(filled), Style::default().fg(color)),
+                        Span::styled("░".repeat(BAR_WIDTH - filled), Style::default().fg(Color::DarkGray)),
+                        Span::raw(format!(" {} of {}", format_duration(entry.used), format_duration(quota))),
+                    ])
+                }
+                None => Line::from(format!("{}{:BAR_WIDTH$} {}", label, "", format_duration(entry.used))),
+            };
+            lines.push(line);
+        }
+    }
+    if !day.windows.is_empty() {
+        lines.push(Line::styled("Still to come", Style::default().add_modifier(Modifier::BOLD)));
+        for window in &day.windows {
+            lines.push(Line::from(format!(
+                "  {}-{}  {}",
+                time(window.start),
+                time(window.end),
+                window.labels.join(", ")
+            )));
+        }
+    }
+    lines
+}
+
+fn draw_clients(frame: &mut Frame, area: Rect, dashboard: &Dashboard) {
+    let block = Block::bordered().title(" Clients ");
+    if let Some(reason) = &dashboard.clients_denied {
+        frame.render_widget(Paragraph::new(reason.as_str()).block(block), area);
+        return;
+    }
+    let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let rows = dashboard.clients.iter().map(|client| {
+        Row::new([
+            or_dash(client.pid.map(|p| p.to_string())),
+            or_dash(client.process_name.clone()),
+            or_dash(client.uid.map(|u| u.to_string())),
+            name(&client.role),
+            or_dash(client.kind.map(|k| name(&k))),
+            client.seat.clone().unwrap_or_default().to_string(),
+        ])
+    });
+    let widths = [
+        Constraint::Length(8),
+        Constraint::Min(16),
+        Constraint::Length(6),
+        Constraint::Length(9),
+        Constraint::Length(9),
+        Constraint::Length(8),
+    ];
+    let header = Row::new(["PID", "PROGRAM", "UID", "ROLE", "KIND", "SEAT"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    frame.render_widget(Table::new(rows, widths).header(header).block(block), area);
+}
+
+fn draw_log(frame: &mut Frame, area: Rect, dashboard: &Dashboard) {
+    let shown = area.height.saturating_sub(2) as usize;
+    // Newest at the bottom, like tail -f
+    let end = dashboard.log.len() - dashboard.scroll.min(dashboard.log.len());
+    let start = end.saturating_sub(shown);
+    let items: Vec<Line> = dashboard
+        .log
+        .range(start..end)
+        .map(|line| {
+            let style = if line.alert {
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            Line::styled(line.text.as_str(), style)
+        })
+        .collect();
+    let title = if dashboard.scroll > 0 {
+        format!(" Audit ({} newer) ", dashboard.scroll)
+    } else {
+        " Audit ".to_string()
+    };
+    frame.render_widget(List::new(items).block(Block::bordered().title(title)), area);
+}
+
+pub fn draw(frame: &mut Frame, dashboard: &Dashboard) {
+    let usage = usage_lines(dashboard);
+    let top_height = (usage.len() as u16 + 2).clamp(8, 16);
+    let clients_height = match dashboard.clients_denied {
+        Some(_) => 3,
+        None => (dashboard.clients.len() as u16 + 3).min(10),
+    };
+    let [top_area, clients_area, log_area, footer_area] = Layout::vertical([
+        Constraint::Length(top_height),
+        Constraint::Length(clients_height),
+        Constraint::Min(4),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [session_area, usage_area] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(top_area);
+
+    draw_session(frame, session_area, dashboard);
+    frame.render_widget(
+        Paragraph::new(usage).block(Block::bordered().title(" Today ")),
+        usage_area,
+    );
+    draw_clients(frame, clients_area, dashboard);
+    draw_log(frame, log_area, dashboard);
+
+    let mut footer = String::from("q quit  r refresh  ↑↓ PgUp PgDn scroll  End follow");
+    if let Some(updated) = dashboard.updated {
+        footer.push_str(&format!("  updated {}", updated.format("%H:%M:%S")));
+    }
+    let mut spans = vec![Span::raw(footer)];
+    if let Some(error) = &dashboard.error {
+        spans.push(Span::styled(format!("  error: {}", error), Style::default().fg(Color::Red)));
+    }
+    frame.render_widget(Paragraph::new(Line::from(spans)), footer_area);
+}
+
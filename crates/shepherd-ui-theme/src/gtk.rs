@@ -0,0 +1,49 @@
+//! Installing a stylesheet in GTK
+
+use gtk4::prelude::*;
+use std::cell::Cell;
+
+use crate::{Component, Theme, stylesheet};
+
+/// A component's stylesheet on the default display, in the current theme
+pub struct ThemeProvider {
+    provider: gtk4::CssProvider,
+    component: Component,
+    theme: Cell<Theme>,
+}
+
+impl ThemeProvider {
+    /// Style the default display. Must be called on the GTK main thread.
+    pub fn install(component: Component, theme: Theme) -> Self {
+        let provider = gtk4::CssProvider::new();
+        provider.load_from_data(&stylesheet(component, theme, today()));
+        gtk4::style_context_add_provider_for_display(
+            &gtk4::gdk::Display::default().expect("Could not get default display"),
+            &provider,
+            gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
+        Self {
+            provider,
+            component,
+            theme: Cell::new(theme),
+        }
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.theme.get()
+    }
+
+    /// Restyle with another theme; does nothing if it's already in use
+    pub fn set_theme(&self, theme: Theme) {
+        if theme == self.theme.replace(theme) {
+            return;
+        }
+        tracing::info!(theme = theme.as_str(), "Switching theme");
+        self.provider
+            .load_from_data(&stylesheet(self.component, theme, today()));
+    }
+}
+
+fn today() -> chrono::NaiveDate {
+    shepherd_util::now().date_naive()
+}
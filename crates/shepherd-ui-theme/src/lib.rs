@@ -0,0 +1,91 @@
+//! Shared styling for the launcher and HUD
+//!
+//! Each component has one stylesheet that takes all of its colours from CSS
+//! variables; a [`Theme`] supplies those variables. The theme is picked in
+//! the service config and reaches clients in state snapshots and
+//! `ThemeChanged` events.
+//!
+//! With the `gtk` feature, [`ThemeProvider`] installs the stylesheet on the
+//! default display and swaps it when the theme changes.
+
+#[cfg(feature = "gtk")]
+mod gtk;
+mod palette;
+
+#[cfg(feature = "gtk")]
+pub use gtk::*;
+pub use palette::*;
+
+use chrono::NaiveDate;
+pub use shepherd_api::Theme;
+
+const LAUNCHER_CSS: &str = include_str!("launcher.css");
+const HUD_CSS: &str = include_str!("hud.css");
+
+/// Which client a stylesheet is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Component {
+    Launcher,
+    Hud,
+}
+
+impl Component {
+    fn css(&self) -> &'static str {
+        match self {
+            Component::Launcher => LAUNCHER_CSS,
+            Component::Hud => HUD_CSS,
+        }
+    }
+}
+
+/// The complete stylesheet for `component` in `theme`
+pub fn stylesheet(component: Component, theme: Theme, date: NaiveDate) -> String {
+    let mut css = palette(theme, date).to_css();
+    css.push('\n');
+    css.push_str(component.css());
+    css
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Names of the variables a stylesheet reads
+    fn used_variables(css: &str) -> Vec<&str> {
+        css.match_indices("var(--")
+            .map(|(i, _)| {
+                let name = &css[i + "var(--".len()..];
+                &name[..name.find(')').unwrap()]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_every_variable_is_defined() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        for component in [Component::Launcher, Component::Hud] {
+            let used = used_variables(component.css());
+            assert!(!used.is_empty());
+            for theme in Theme::ALL {
+                let defined = palette(theme, date).variables().map(|(name, _)| name);
+                for name in &used {
+                    assert!(
+                        defined.contains(name),
+                        "{:?} uses --{} which {:?} doesn't define",
+                        component,
+                        name,
+                        theme
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stylesheet() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let css = stylesheet(Component::Hud, Theme::Light, date);
+        assert!(css.starts_with(":root {\n    --window-bg: #f4f1ea;\n"));
+        assert!(css.contains(".hud-bar {"));
+    }
+}
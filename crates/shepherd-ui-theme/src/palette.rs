@@ -0,0 +1,209 @@
+//! Colours for each theme
+//!
+//! The stylesheets only refer to these as CSS variables (`var(--surface)`),
+//! so a theme is just a different `:root` block in front of them.
+
+use chrono::{Datelike, NaiveDate};
+use shepherd_api::Theme;
+
+/// The colours a theme defines. Values are any GTK CSS colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    /// Launcher background
+    pub window_bg: &'static str,
+    /// Tiles and buttons
+    pub surface: &'static str,
+    pub surface_hover: &'static str,
+    pub surface_active: &'static str,
+    pub tile_border: &'static str,
+    /// Dialogs floating over the launcher (curfew, check-in, setup)
+    pub overlay_bg: &'static str,
+    /// Banners that explain why nothing can be launched
+    pub alert_bg: &'static str,
+    /// HUD bar
+    pub bar_bg: &'static str,
+    pub hover_bg: &'static str,
+    /// Headings and anything that must stand out
+    pub text_primary: &'static str,
+    pub text_body: &'static str,
+    pub text_secondary: &'static str,
+    pub text_muted: &'static str,
+    /// Focus and hover borders
+    pub accent: &'static str,
+    /// Stars and countdowns
+    pub highlight: &'static str,
+    pub color_info: &'static str,
+    pub color_warning: &'static str,
+    pub color_critical: &'static str,
+    pub color_success: &'static str,
+}
+
+const DARK: Palette = Palette {
+    window_bg: "#1a1a2e",
+    surface: "#16213e",
+    surface_hover: "#1f3460",
+    surface_active: "#0f3460",
+    tile_border: "transparent",
+    overlay_bg: "rgba(10, 10, 30, 0.92)",
+    alert_bg: "rgba(120, 40, 40, 0.92)",
+    bar_bg: "rgba(30, 30, 30, 0.95)",
+    hover_bg: "rgba(255, 255, 255, 0.1)",
+    text_primary: "#ffffff",
+    text_body: "#e0e0e0",
+    text_secondary: "#d8dee9",
+    text_muted: "#888888",
+    accent: "#4a90d9",
+    highlight: "#ffcc66",
+    color_info: "#88c0d0",
+    color_warning: "#ebcb8b",
+    color_critical: "#ff6b6b",
+    color_success: "#88cc88",
+};
+
+const LIGHT: Palette = Palette {
+    window_bg: "#f4f1ea",
+    surface: "#ffffff",
+    surface_hover: "#e6eefa",
+    surface_active: "#d0def5",
+    tile_border: "#d8d4cc",
+    overlay_bg: "rgba(250, 248, 243, 0.96)",
+    alert_bg: "rgba(255, 214, 214, 0.96)",
+    bar_bg: "rgba(245, 245, 245, 0.95)",
+    hover_bg: "rgba(0, 0, 0, 0.08)",
+    text_primary: "#1a1a1a",
+    text_body: "#2e2e2e",
+    text_secondary: "#4c566a",
+    text_muted: "#6b6b6b",
+    accent: "#2f6fc0",
+    highlight: "#b7791f",
+    color_info: "#2b7a99",
+    color_warning: "#a86b00",
+    color_critical: "#c62828",
+    color_success: "#2e7d32",
+};
+
+const HIGH_CONTRAST: Palette = Palette {
+    window_bg: "#000000",
+    surface: "#000000",
+    surface_hover: "#1a1a1a",
+    surface_active: "#333333",
+    tile_border: "#ffffff",
+    overlay_bg: "rgba(0, 0, 0, 0.97)",
+    alert_bg: "#8b0000",
+    bar_bg: "#000000",
+    hover_bg: "rgba(255, 255, 255, 0.25)",
+    text_primary: "#ffffff",
+    text_body: "#ffffff",
+    text_secondary: "#ffffff",
+    text_muted: "#e0e0e0",
+    accent: "#ffff00",
+    highlight: "#ffff00",
+    color_info: "#00ffff",
+    color_warning: "#ffff00",
+    color_critical: "#ff4040",
+    color_success: "#00ff00",
+};
+
+/// Part of the year the seasonal theme is dressed for (northern hemisphere)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Season {
+    Winter,
+    Spring,
+    Summer,
+    Autumn,
+}
+
+impl Season {
+    pub fn of(date: NaiveDate) -> Self {
+        match date.month() {
+            3..=5 => Season::Spring,
+            6..=8 => Season::Summer,
+            9..=11 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+}
+
+fn seasonal(season: Season) -> Palette {
+    let (window_bg, surface, surface_hover, surface_active, accent, highlight) = match season {
+        Season::Winter => ("#14213d", "#1b2a4a", "#26406b", "#12203a", "#9ecbff", "#e8f4ff"),
+        Season::Spring => ("#1a2a22", "#20382c", "#2c4d3c", "#182a21", "#7bc67b", "#ffb3d9"),
+        Season::Summer => ("#1f2438", "#2a2f4a", "#3a4166", "#202538", "#ffb347", "#ffe066"),
+        Season::Autumn => ("#2a1d17", "#3a281e", "#54392a", "#2a1c15", "#e07b39", "#f2c14e"),
+    };
+    Palette {
+        window_bg,
+        surface,
+        surface_hover,
+        surface_active,
+        accent,
+        highlight,
+        ..DARK
+    }
+}
+
+/// Colours for `theme` on `date`; only the seasonal theme looks at the date
+pub fn palette(theme: Theme, date: NaiveDate) -> Palette {
+    match theme {
+        Theme::Dark => DARK,
+        Theme::Light => LIGHT,
+        Theme::HighContrast => HIGH_CONTRAST,
+        Theme::Seasonal => seasonal(Season::of(date)),
+    }
+}
+
+impl Palette {
+    /// CSS variable names and values
+    pub fn variables(&self) -> [(&'static str, &'static str); 19] {
+        [
+            ("window-bg", self.window_bg),
+            ("surface", self.surface),
+            ("surface-hover", self.surface_hover),
+            ("surface-active", self.surface_active),
+            ("tile-border", self.tile_border),
+            ("overlay-bg", self.overlay_bg),
+            ("alert-bg", self.alert_bg),
+            ("bar-bg", self.bar_bg),
+            ("hover-bg", self.hover_bg),
+            ("text-primary", self.text_primary),
+            ("text-body", self.text_body),
+            ("text-secondary", self.text_secondary),
+            ("text-muted", self.text_muted),
+            ("accent", self.accent),
+            ("highlight", self.highlight),
+            ("color-info", self.color_info),
+            ("color-warning", self.color_warning),
+            ("color-critical", self.color_critical),
+            ("color-success", self.color_success),
+        ]
+    }
+
+    /// The `:root` block the stylesheets read their colours from
+    pub fn to_css(&self) -> String {
+        let mut css = String::from(":root {\n");
+        for (name, value) in self.variables() {
+            css.push_str(&format!("    --{}: {};\n", name, value));
+        }
+        css.push_str("}\n");
+        css
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_season() {
+        let date = |m| NaiveDate::from_ymd_opt(2025, m, 15).unwrap();
+        assert_eq!(Season::of(date(1)), Season::Winter);
+        assert_eq!(Season::of(date(4)), Season::Spring);
+        assert_eq!(Season::of(date(8)), Season::Summer);
+        assert_eq!(Season::of(date(10)), Season::Autumn);
+        assert_eq!(Season::of(date(12)), Season::Winter);
+
+        let october = palette(Theme::Seasonal, date(10));
+        assert_eq!(october.accent, "#e07b39");
+        assert_eq!(october.color_critical, DARK.color_critical);
+    }
+}
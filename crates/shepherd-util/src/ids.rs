@@ -1,11 +1,26 @@
 //! Strongly-typed identifiers for shepherdd
 
-use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+use ulid::Ulid;
 use uuid::Uuid;
 
+/// Longest accepted entry ID
+pub const MAX_ENTRY_ID_LEN: usize = 64;
+
+/// Error validating an entry ID
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid entry ID \"{id}\": {reason}")]
+pub struct InvalidEntryId {
+    pub id: String,
+    pub reason: &'static str,
+}
+
 /// Unique identifier for an entry in the policy whitelist
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub struct EntryId(String);
 
 impl EntryId {
@@ -13,6 +28,41 @@ impl EntryId {
         Self(id.into())
     }
 
+    /// An entry ID from configuration, checked with [`EntryId::validate`]
+    pub fn parse(id: &str) -> Result<Self, InvalidEntryId> {
+        Self::validate(id)?;
+        Ok(Self(id.to_string()))
+    }
+
+    /// Check that `id` is safe to use in file names, logs and URLs:
+    /// 1 to 64 ASCII letters, digits, `-`, `_` or `.`, starting with a
+    /// letter or digit
+    pub fn validate(id: &str) -> Result<(), InvalidEntryId> {
+        let invalid = |reason| {
+            Err(InvalidEntryId {
+                id: id.to_string(),
+                reason,
+            })
+        };
+
+        if id.is_empty() {
+            return invalid("must not be empty");
+        }
+        if id.len() > MAX_ENTRY_ID_LEN {
+            return invalid("must be at most 64 characters");
+        }
+        if !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return invalid("may only contain letters, digits, '-', '_' and '.'");
+        }
+        if !id.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+            return invalid("must start with a letter or digit");
+        }
+        Ok(())
+    }
+
     pub fn as_str(&self) -> &str {
         &self.0
     }
@@ -36,21 +86,153 @@ impl From<&str> for EntryId {
     }
 }
 
+/// Unique identifier for a child profile
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub struct ProfileId(String);
+
+impl ProfileId {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ProfileId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<&str> for ProfileId {
+    fn from(s: &str) -> Self {
+        Self(s.to_string())
+    }
+}
+
+/// A seat: one display with its own keyboard, mouse or controller, and its
+/// own launcher and HUD. Named like logind seats; `seat0` is the default
+/// and the only one on a single-seat box.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+pub struct SeatId(String);
+
+impl SeatId {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.0 == "seat0"
+    }
+}
+
+impl Default for SeatId {
+    fn default() -> Self {
+        Self::new("seat0")
+    }
+}
+
+impl fmt::Display for SeatId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<&str> for SeatId {
+    fn from(s: &str) -> Self {
+        Self(s.to_string())
+    }
+}
+
+/// Error parsing a session ID
+#[derive(Debug, Clone, Error)]
+#[error("invalid session ID \"{0}\"")]
+pub struct InvalidSessionId(String);
+
 /// Unique identifier for a running session
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub struct SessionId(Uuid);
+///
+/// A ULID: IDs sort by creation time and print as 26 characters
+/// (`01J9Z3K4M5N6P7Q8R9S0T1V2W3`). UUIDs from older records still parse,
+/// and keep printing as UUIDs so stored records can be looked up by them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, JsonSchema)]
+#[schemars(with = "String")]
+pub struct SessionId {
+    kind: SessionIdKind,
+    bytes: [u8; 16],
+}
+
+/// Legacy IDs sort before every ULID, as they were all made earlier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum SessionIdKind {
+    Uuid,
+    Ulid,
+}
 
 impl SessionId {
     pub fn new() -> Self {
-        Self(Uuid::new_v4())
+        Self::from_bytes(Ulid::new().to_bytes())
     }
 
+    /// A session recorded before IDs were ULIDs
     pub fn from_uuid(uuid: Uuid) -> Self {
-        Self(uuid)
+        Self {
+            kind: SessionIdKind::Uuid,
+            bytes: uuid.into_bytes(),
+        }
     }
 
-    pub fn as_uuid(&self) -> &Uuid {
-        &self.0
+    /// Compact 16-byte form, sorting the same as the ID itself for ULIDs
+    pub fn to_bytes(&self) -> [u8; 16] {
+        self.bytes
+    }
+
+    /// The ID whose [`to_bytes`](Self::to_bytes) form is `bytes`, read as a ULID
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self {
+            kind: SessionIdKind::Ulid,
+            bytes,
+        }
+    }
+
+    /// Milliseconds since the Unix epoch when the ID was created; 0 for
+    /// legacy IDs, which don't record it
+    pub fn timestamp_ms(&self) -> u64 {
+        match self.kind {
+            SessionIdKind::Uuid => 0,
+            SessionIdKind::Ulid => Ulid::from_bytes(self.bytes).timestamp_ms(),
+        }
+    }
+}
+
+impl FromStr for SessionId {
+    type Err = InvalidSessionId;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(ulid) = Ulid::from_string(s) {
+            return Ok(Self::from_bytes(ulid.to_bytes()));
+        }
+        Uuid::parse_str(s)
+            .map(Self::from_uuid)
+            .map_err(|_| InvalidSessionId(s.to_string()))
+    }
+}
+
+impl Serialize for SessionId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SessionId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
     }
 }
 
@@ -62,12 +244,15 @@ impl Default for SessionId {
 
 impl fmt::Display for SessionId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+        match self.kind {
+            SessionIdKind::Uuid => write!(f, "{}", Uuid::from_bytes(self.bytes)),
+            SessionIdKind::Ulid => write!(f, "{}", Ulid::from_bytes(self.bytes)),
+        }
     }
 }
 
 /// Unique identifier for a connected IPC client
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub struct ClientId(Uuid);
 
 impl ClientId {
@@ -113,6 +298,49 @@ mod tests {
         assert_ne!(s1, s2);
     }
 
+    #[test]
+    fn entry_id_validation() {
+        for id in ["tuxmath", "steam-celeste", "org.kde.krita", "game_2", "7billion"] {
+            assert_eq!(EntryId::parse(id).unwrap().as_str(), id);
+        }
+
+        for id in ["", "my game", "../etc", "games/doom", "a\\b", ".hidden", "-flag", "spielé"] {
+            assert!(EntryId::parse(id).is_err(), "{:?} accepted", id);
+        }
+        assert!(EntryId::validate(&"a".repeat(MAX_ENTRY_ID_LEN)).is_ok());
+        assert!(EntryId::validate(&"a".repeat(MAX_ENTRY_ID_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn session_id_ordering() {
+        let ids: Vec<SessionId> = (0..3)
+            .map(|_| {
+                std::thread::sleep(std::time::Duration::from_millis(2));
+                SessionId::new()
+            })
+            .collect();
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(ids, sorted);
+        assert!(ids[0].timestamp_ms() < ids[2].timestamp_ms());
+
+        // The byte form sorts the same way
+        assert!(ids[0].to_bytes() < ids[2].to_bytes());
+        assert_eq!(SessionId::from_bytes(ids[1].to_bytes()), ids[1]);
+    }
+
+    #[test]
+    fn session_id_parses_legacy_uuid() {
+        let uuid = Uuid::new_v4();
+        let parsed: SessionId = uuid.to_string().parse().unwrap();
+        assert_eq!(parsed, SessionId::from_uuid(uuid));
+
+        let parsed: SessionId = serde_json::from_str(&format!("\"{}\"", uuid)).unwrap();
+        assert_eq!(parsed, SessionId::from_uuid(uuid));
+
+        assert!("not-an-id".parse::<SessionId>().is_err());
+    }
+
     #[test]
     fn ids_serialize_deserialize() {
         let entry_id = EntryId::new("test-entry");
@@ -125,4 +353,40 @@ mod tests {
         let parsed: SessionId = serde_json::from_str(&json).unwrap();
         assert_eq!(session_id, parsed);
     }
+
+    #[test]
+    fn session_id_is_a_ulid() {
+        let session_id = SessionId::new();
+        let json = serde_json::to_string(&session_id).unwrap();
+        assert_eq!(json.len(), 26 + 2);
+        assert_eq!(session_id.to_string().parse::<SessionId>().unwrap(), session_id);
+    }
+
+    #[test]
+    fn session_id_keeps_legacy_form() {
+        // As stored in audit rows written before the upgrade
+        let stored = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        let parsed: SessionId = stored.parse().unwrap();
+        assert_eq!(parsed.to_string(), stored);
+
+        let json = serde_json::to_string(&parsed).unwrap();
+        assert_eq!(json, format!("\"{}\"", stored));
+        assert_eq!(serde_json::from_str::<SessionId>(&json).unwrap(), parsed);
+
+        // Older than any new session
+        assert!(parsed < SessionId::new());
+    }
+
+    #[test]
+    fn other_ids_serialize_deserialize() {
+        let client_id = ClientId::new();
+        let json = serde_json::to_string(&client_id).unwrap();
+        let parsed: ClientId = serde_json::from_str(&json).unwrap();
+        assert_eq!(client_id, parsed);
+
+        let profile_id = ProfileId::new("alex");
+        let json = serde_json::to_string(&profile_id).unwrap();
+        let parsed: ProfileId = serde_json::from_str(&json).unwrap();
+        assert_eq!(profile_id, parsed);
+    }
 }
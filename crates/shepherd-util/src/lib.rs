@@ -3,18 +3,24 @@
 //! This crate provides:
 //! - ID types (EntryId, SessionId, ClientId)
 //! - Time utilities (monotonic time, duration helpers)
+//! - Timezones with DST rules for availability windows
 //! - Error types
 //! - Rate limiting helpers
 //! - Default paths for socket, data, and log directories
+//! - Parent PIN hashing
 
 mod error;
 mod ids;
 mod paths;
+mod pin;
 mod rate_limit;
 mod time;
+mod timezone;
 
 pub use error::*;
 pub use ids::*;
 pub use paths::*;
+pub use pin::*;
 pub use rate_limit::*;
 pub use time::*;
+pub use timezone::*;
@@ -3,6 +3,7 @@
 //! Provides centralized path defaults that all crates can use.
 //! Paths are user-writable by default (no root required):
 //! - Socket: `$XDG_RUNTIME_DIR/shepherdd/shepherdd.sock` or `/tmp/shepherdd-$USER/shepherdd.sock`
+//! - Host helper socket: `host-helper.sock` next to the service socket
 //! - Data: `$XDG_DATA_HOME/shepherdd` or `~/.local/share/shepherdd`
 //! - Logs: `$XDG_STATE_HOME/shepherdd` or `~/.local/state/shepherdd`
 
@@ -17,6 +18,12 @@ pub const SHEPHERD_DATA_DIR_ENV: &str = "SHEPHERD_DATA_DIR";
 /// Socket filename within the socket directory
 const SOCKET_FILENAME: &str = "shepherdd.sock";
 
+/// Environment variable for overriding the host helper socket path
+pub const SHEPHERD_HOST_HELPER_ENV: &str = "SHEPHERD_HOST_HELPER";
+
+/// Host helper socket filename within the socket directory
+const HOST_HELPER_SOCKET_FILENAME: &str = "host-helper.sock";
+
 /// Application subdirectory name
 const APP_DIR: &str = "shepherdd";
 
@@ -115,6 +122,12 @@ pub fn socket_dir() -> PathBuf {
     })
 }
 
+/// Get the default host helper socket path, in the same directory as the
+/// service socket
+pub fn default_host_helper_socket_path() -> PathBuf {
+    socket_dir().join(HOST_HELPER_SOCKET_FILENAME)
+}
+
 /// Configuration subdirectory name (uses "shepherd" not "shepherdd")
 const CONFIG_APP_DIR: &str = "shepherd";
 
@@ -0,0 +1,123 @@
+//! Parent PIN hashing
+//!
+//! The PIN is stored in the config as a salted SHA-256 digest so that reading
+//! the config file doesn't reveal it. A short numeric PIN can still be brute
+//! forced from the digest; it keeps children out of parent settings, not
+//! determined attackers.
+
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+use uuid::Uuid;
+
+const PIN_HASH_SCHEME: &str = "sha256";
+
+/// Minimum and maximum PIN length in digits
+pub const PIN_LENGTH: std::ops::RangeInclusive<usize> = 4..=8;
+
+/// Salted hash of a parent PIN, formatted as `sha256$<salt>$<hex digest>`
+#[derive(Clone, PartialEq, Eq)]
+pub struct PinHash {
+    salt: String,
+    digest: String,
+}
+
+/// Error parsing a stored PIN hash
+#[derive(Debug, Clone, Error)]
+#[error("invalid PIN hash, expected \"sha256$<salt>$<hex digest>\"")]
+pub struct InvalidPinHash;
+
+impl PinHash {
+    /// Hash a PIN with a fresh random salt
+    pub fn new(pin: &str) -> Self {
+        let salt = Uuid::new_v4().simple().to_string();
+        let digest = digest(&salt, pin);
+        Self { salt, digest }
+    }
+
+    /// Check a PIN against this hash
+    pub fn verify(&self, pin: &str) -> bool {
+        digest(&self.salt, pin) == self.digest
+    }
+
+    /// Whether a PIN is acceptable: digits only, within [`PIN_LENGTH`]
+    pub fn is_valid_pin(pin: &str) -> bool {
+        PIN_LENGTH.contains(&pin.len()) && pin.chars().all(|c| c.is_ascii_digit())
+    }
+}
+
+fn digest(salt: &str, pin: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(pin.as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+impl fmt::Display for PinHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}${}${}", PIN_HASH_SCHEME, self.salt, self.digest)
+    }
+}
+
+// Keep the digest out of logs
+impl fmt::Debug for PinHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PinHash(..)")
+    }
+}
+
+impl FromStr for PinHash {
+    type Err = InvalidPinHash;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('$');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(PIN_HASH_SCHEME), Some(salt), Some(digest), None)
+                if !salt.is_empty()
+                    && digest.len() == 64
+                    && digest.chars().all(|c| c.is_ascii_hexdigit()) =>
+            {
+                Ok(Self {
+                    salt: salt.to_string(),
+                    digest: digest.to_ascii_lowercase(),
+                })
+            }
+            _ => Err(InvalidPinHash),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pin_hash_roundtrip() {
+        let hash = PinHash::new("1234");
+        assert!(hash.verify("1234"));
+        assert!(!hash.verify("4321"));
+
+        let parsed: PinHash = hash.to_string().parse().unwrap();
+        assert_eq!(parsed, hash);
+        assert!(parsed.verify("1234"));
+
+        // Salted, so the same PIN hashes differently
+        assert_ne!(PinHash::new("1234"), hash);
+
+        assert!("1234".parse::<PinHash>().is_err());
+        assert!("sha256$salt$abc".parse::<PinHash>().is_err());
+    }
+
+    #[test]
+    fn test_valid_pin() {
+        assert!(PinHash::is_valid_pin("0420"));
+        assert!(!PinHash::is_valid_pin("123"));
+        assert!(!PinHash::is_valid_pin("12a4"));
+        assert!(!PinHash::is_valid_pin("123456789"));
+    }
+}
@@ -5,20 +5,33 @@ use std::time::{Duration, Instant};
 
 use crate::ClientId;
 
-/// Simple token-bucket rate limiter
+/// How long a client must be idle before its bucket is dropped, by default
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Per-client token-bucket rate limiter
+///
+/// Each client's bucket holds up to `burst` tokens and refills continuously
+/// at the sustained rate, so a client can send a burst after being quiet and
+/// is then held to the average rate. Clients never share tokens, so one
+/// busy client can't starve the others.
+///
+/// Buckets of clients idle for longer than the idle timeout are dropped as
+/// a side effect of `check`, so callers don't need to report disconnects.
 #[derive(Debug)]
 pub struct RateLimiter {
-    /// Maximum tokens (requests) per bucket
-    max_tokens: u32,
-    /// How often tokens are replenished
-    refill_interval: Duration,
+    /// Tokens added per second
+    rate: f64,
+    /// Bucket capacity
+    burst: f64,
+    idle_timeout: Duration,
     /// Per-client state
     clients: HashMap<ClientId, ClientBucket>,
+    last_sweep: Option<Instant>,
 }
 
 #[derive(Debug)]
 struct ClientBucket {
-    tokens: u32,
+    tokens: f64,
     last_refill: Instant,
 }
 
@@ -26,55 +39,82 @@ impl RateLimiter {
     /// Create a new rate limiter
     ///
     /// # Arguments
-    /// * `max_requests` - Maximum requests allowed per interval
+    /// * `max_requests` - Sustained requests allowed per interval, and the
+    ///   default burst size
     /// * `interval` - Time interval for the limit
     pub fn new(max_requests: u32, interval: Duration) -> Self {
         Self {
-            max_tokens: max_requests,
-            refill_interval: interval,
+            rate: max_requests as f64 / interval.as_secs_f64(),
+            burst: max_requests as f64,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
             clients: HashMap::new(),
+            last_sweep: None,
         }
     }
 
+    /// Allow up to `burst` requests at once from a client that has been quiet
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1) as f64;
+        self
+    }
+
+    /// Forget clients that haven't sent a request for this long
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
     /// Check if a request should be allowed for the given client
     ///
     /// Returns `true` if allowed, `false` if rate limited
     pub fn check(&mut self, client_id: &ClientId) -> bool {
-        let now = Instant::now();
+        self.check_at(client_id, Instant::now())
+    }
+
+    /// [`check`](Self::check) at a given time
+    pub fn check_at(&mut self, client_id: &ClientId, now: Instant) -> bool {
+        self.sweep(now);
 
         let bucket = self.clients.entry(client_id.clone()).or_insert(ClientBucket {
-            tokens: self.max_tokens,
+            tokens: self.burst,
             last_refill: now,
         });
 
-        // Refill tokens if interval has passed
-        let elapsed = now.duration_since(bucket.last_refill);
-        if elapsed >= self.refill_interval {
-            let intervals = (elapsed.as_millis() / self.refill_interval.as_millis()) as u32;
-            bucket.tokens = (bucket.tokens + intervals * self.max_tokens).min(self.max_tokens);
-            bucket.last_refill = now;
-        }
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.rate).min(self.burst);
+        bucket.last_refill = now;
 
         // Try to consume a token
-        if bucket.tokens > 0 {
-            bucket.tokens -= 1;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
             true
         } else {
             false
         }
     }
 
-    /// Remove a client's rate limit state
-    pub fn remove_client(&mut self, client_id: &ClientId) {
-        self.clients.remove(client_id);
+    /// Number of clients currently tracked
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
     }
 
-    /// Clean up stale client entries
-    pub fn cleanup(&mut self, stale_after: Duration) {
-        let now = Instant::now();
+    /// Drop idle buckets, at most once per idle timeout. A dropped client
+    /// comes back with a full bucket, which it would have refilled to by now
+    /// anyway unless the timeout is shorter than the refill time.
+    fn sweep(&mut self, now: Instant) {
+        let due = match self.last_sweep {
+            Some(last) => now.saturating_duration_since(last) >= self.idle_timeout,
+            None => true,
+        };
+        if !due {
+            return;
+        }
+
+        let idle_timeout = self.idle_timeout;
         self.clients.retain(|_, bucket| {
-            now.duration_since(bucket.last_refill) < stale_after
+            now.saturating_duration_since(bucket.last_refill) < idle_timeout
         });
+        self.last_sweep = Some(now);
     }
 }
 
@@ -109,4 +149,77 @@ mod tests {
         assert!(limiter.check(&client2));
         assert!(limiter.check(&client2));
     }
+
+    #[test]
+    fn test_burst_then_sustained_rate() {
+        let mut limiter = RateLimiter::new(10, Duration::from_secs(1)).with_burst(20);
+        let client = ClientId::new();
+        let start = Instant::now();
+
+        let allowed = (0..30).filter(|_| limiter.check_at(&client, start)).count();
+        assert_eq!(allowed, 20);
+
+        // Tokens come back continuously: one every 100ms
+        let later = start + Duration::from_millis(100);
+        assert!(limiter.check_at(&client, later));
+        assert!(!limiter.check_at(&client, later));
+
+        // Never refills beyond the burst
+        let much_later = start + Duration::from_secs(60);
+        let allowed = (0..30).filter(|_| limiter.check_at(&client, much_later)).count();
+        assert_eq!(allowed, 20);
+    }
+
+    #[test]
+    fn test_fairness_across_many_clients() {
+        let mut limiter = RateLimiter::new(5, Duration::from_secs(1));
+        let clients: Vec<ClientId> = (0..100).map(|_| ClientId::new()).collect();
+        let noisy = ClientId::new();
+        let start = Instant::now();
+
+        // Over ten seconds, a client flooding requests every 10ms...
+        let mut noisy_allowed = 0;
+        let mut allowed = vec![0; clients.len()];
+        for tick in 0..1000 {
+            let now = start + Duration::from_millis(tick * 10);
+            if limiter.check_at(&noisy, now) {
+                noisy_allowed += 1;
+            }
+            // ...while the others each ask ten times a second
+            if tick % 10 == 0 {
+                for (client, count) in clients.iter().zip(allowed.iter_mut()) {
+                    if limiter.check_at(client, now) {
+                        *count += 1;
+                    }
+                }
+            }
+        }
+
+        // The flood gets its burst plus the sustained rate and no more
+        assert!((50..=56).contains(&noisy_allowed), "noisy client got {}", noisy_allowed);
+        // Every other client gets the same share, unaffected by the flood
+        assert!(allowed.iter().all(|&count| count == allowed[0]));
+        assert!((50..=56).contains(&allowed[0]), "quiet client got {}", allowed[0]);
+    }
+
+    #[test]
+    fn test_idle_clients_are_dropped() {
+        let mut limiter =
+            RateLimiter::new(5, Duration::from_secs(1)).with_idle_timeout(Duration::from_secs(60));
+        let start = Instant::now();
+
+        for _ in 0..50 {
+            limiter.check_at(&ClientId::new(), start);
+        }
+        let active = ClientId::new();
+        limiter.check_at(&active, start + Duration::from_secs(50));
+        assert_eq!(limiter.client_count(), 51);
+
+        // Not yet a full timeout since the last sweep
+        limiter.check_at(&active, start + Duration::from_secs(59));
+        assert_eq!(limiter.client_count(), 51);
+
+        limiter.check_at(&active, start + Duration::from_secs(61));
+        assert_eq!(limiter.client_count(), 1);
+    }
 }
@@ -15,80 +15,200 @@
 //! ```bash
 //! SHEPHERD_MOCK_TIME="2025-12-25 14:30:00" ./run-dev
 //! ```
+//!
+//! The clock can also be set, advanced, frozen and reset while running, through
+//! [`set_mock_time`], [`advance_mock_time`], [`freeze_mock_time`] and
+//! [`clear_mock_time`]. These only affect wall-clock time; monotonic session
+//! deadlines still follow the real clock. Release builds can opt in with
+//! [`enable_mock_time`], which `shepherdd --dev-sandbox` does.
 
 use chrono::{DateTime, Datelike, Local, NaiveDateTime, NaiveTime, TimeZone, Timelike, Weekday};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use thiserror::Error;
 use std::time::{Duration, Instant};
 
+use crate::Timezone;
+
 /// Environment variable name for mock time (debug builds only)
 pub const MOCK_TIME_ENV_VAR: &str = "SHEPHERD_MOCK_TIME";
 
-/// Cached mock time offset from the real time when the process started.
-/// This allows mock time to advance naturally.
-static MOCK_TIME_OFFSET: OnceLock<Option<chrono::Duration>> = OnceLock::new();
+/// Mock clock state, initialized from the environment variable on first use.
+static MOCK_CLOCK: OnceLock<Mutex<MockClock>> = OnceLock::new();
+
+/// Whether mock time was enabled at runtime, allowing it in release builds
+static MOCK_TIME_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Error returned when changing mock time in a release build
+#[derive(Debug, Clone, Error)]
+#[error("mock time is only available in debug builds")]
+pub struct MockTimeUnavailable;
+
+/// A clock that runs at real speed from an offset, or stands still
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct MockClock {
+    /// Offset from the real time. None means the real clock is used.
+    offset: Option<chrono::Duration>,
+    /// Time the clock is frozen at
+    frozen_at: Option<DateTime<Local>>,
+}
+
+impl MockClock {
+    fn now(&self, real_now: DateTime<Local>) -> DateTime<Local> {
+        self.frozen_at
+            .unwrap_or_else(|| real_now + self.offset.unwrap_or_default())
+    }
+
+    fn is_active(&self) -> bool {
+        self.offset.is_some() || self.frozen_at.is_some()
+    }
+
+    /// Jump to `time`, keeping the clock frozen if it was
+    fn set(&mut self, real_now: DateTime<Local>, time: DateTime<Local>) {
+        self.offset = Some(time.signed_duration_since(real_now));
+        if self.frozen_at.is_some() {
+            self.frozen_at = Some(time);
+        }
+    }
 
-/// Initialize the mock time offset based on the environment variable.
+    fn advance(&mut self, real_now: DateTime<Local>, by: chrono::Duration) {
+        let time = self.now(real_now) + by;
+        self.set(real_now, time);
+    }
+
+    fn freeze(&mut self, real_now: DateTime<Local>, frozen: bool) {
+        let time = self.now(real_now);
+        if frozen {
+            self.frozen_at = Some(time);
+        } else if self.frozen_at.take().is_some() {
+            // Resume from where the clock stopped
+            self.offset = Some(time.signed_duration_since(real_now));
+        }
+    }
+}
+
+/// Read the mock time from the environment variable.
 /// Returns the offset between mock time and real time at process start.
 #[allow(clippy::disallowed_methods)] // This is the internal implementation that wraps Local::now()
-fn get_mock_time_offset() -> Option<chrono::Duration> {
-    *MOCK_TIME_OFFSET.get_or_init(|| {
-        #[cfg(debug_assertions)]
-        {
-            if let Ok(mock_time_str) = std::env::var(MOCK_TIME_ENV_VAR) {
-                // Parse the mock time string
-                if let Ok(naive_dt) = NaiveDateTime::parse_from_str(&mock_time_str, "%Y-%m-%d %H:%M:%S") {
-                    if let Some(mock_dt) = Local.from_local_datetime(&naive_dt).single() {
-                        let real_now = chrono::Local::now();
-                        let offset = mock_dt.signed_duration_since(real_now);
-                        tracing::info!(
-                            mock_time = %mock_time_str,
-                            offset_secs = offset.num_seconds(),
-                            "Mock time enabled"
-                        );
-                        return Some(offset);
-                    } else {
-                        tracing::warn!(
-                            mock_time = %mock_time_str,
-                            "Failed to convert mock time to local timezone"
-                        );
-                    }
+fn mock_time_offset_from_env() -> Option<chrono::Duration> {
+    #[cfg(debug_assertions)]
+    {
+        if let Ok(mock_time_str) = std::env::var(MOCK_TIME_ENV_VAR) {
+            // Parse the mock time string
+            if let Ok(naive_dt) = NaiveDateTime::parse_from_str(&mock_time_str, "%Y-%m-%d %H:%M:%S") {
+                if let Some(mock_dt) = Local.from_local_datetime(&naive_dt).single() {
+                    let real_now = chrono::Local::now();
+                    let offset = mock_dt.signed_duration_since(real_now);
+                    tracing::info!(
+                        mock_time = %mock_time_str,
+                        offset_secs = offset.num_seconds(),
+                        "Mock time enabled"
+                    );
+                    return Some(offset);
                 } else {
                     tracing::warn!(
                         mock_time = %mock_time_str,
-                        expected_format = "%Y-%m-%d %H:%M:%S",
-                        "Invalid mock time format"
+                        "Failed to convert mock time to local timezone"
                     );
                 }
+            } else {
+                tracing::warn!(
+                    mock_time = %mock_time_str,
+                    expected_format = "%Y-%m-%d %H:%M:%S",
+                    "Invalid mock time format"
+                );
             }
-            None
         }
-        #[cfg(not(debug_assertions))]
-        {
-            None
-        }
-    })
+        None
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        None
+    }
+}
+
+fn mock_clock() -> std::sync::MutexGuard<'static, MockClock> {
+    MOCK_CLOCK
+        .get_or_init(|| {
+            Mutex::new(MockClock {
+                offset: mock_time_offset_from_env(),
+                frozen_at: None,
+            })
+        })
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Apply a change to the mock clock and return the new current time
+#[allow(clippy::disallowed_methods)] // Needs the real time to compute offsets
+fn update_mock_clock(
+    f: impl FnOnce(&mut MockClock, DateTime<Local>),
+) -> Result<DateTime<Local>, MockTimeUnavailable> {
+    if !cfg!(debug_assertions) && !MOCK_TIME_ENABLED.load(Ordering::Relaxed) {
+        return Err(MockTimeUnavailable);
+    }
+    let real_now = chrono::Local::now();
+    let mut clock = mock_clock();
+    f(&mut clock, real_now);
+    let now = clock.now(real_now);
+    tracing::info!(
+        now = %now,
+        frozen = clock.frozen_at.is_some(),
+        "Mock time changed"
+    );
+    Ok(now)
+}
+
+/// Allow the mock clock to be changed in release builds too. There is no
+/// way back: only for processes that never enforce real policy.
+pub fn enable_mock_time() {
+    MOCK_TIME_ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Set the mock clock to `time` (debug builds only).
+///
+/// The clock keeps running from there unless it is frozen.
+pub fn set_mock_time(time: DateTime<Local>) -> Result<DateTime<Local>, MockTimeUnavailable> {
+    update_mock_clock(|clock, real_now| clock.set(real_now, time))
+}
+
+/// Move the mock clock forward, or back for a negative duration (debug builds only)
+pub fn advance_mock_time(by: chrono::Duration) -> Result<DateTime<Local>, MockTimeUnavailable> {
+    update_mock_clock(|clock, real_now| clock.advance(real_now, by))
+}
+
+/// Stop or restart the clock (debug builds only)
+pub fn freeze_mock_time(frozen: bool) -> Result<DateTime<Local>, MockTimeUnavailable> {
+    update_mock_clock(|clock, real_now| clock.freeze(real_now, frozen))
+}
+
+/// Return to the real clock (debug builds only)
+pub fn clear_mock_time() -> Result<DateTime<Local>, MockTimeUnavailable> {
+    update_mock_clock(|clock, _| *clock = MockClock::default())
 }
 
 /// Returns whether mock time is currently active.
 pub fn is_mock_time_active() -> bool {
-    get_mock_time_offset().is_some()
+    mock_clock().is_active()
+}
+
+/// Returns whether the mock clock is frozen.
+pub fn is_mock_time_frozen() -> bool {
+    mock_clock().frozen_at.is_some()
 }
 
 /// Get the current local time, respecting mock time settings in debug builds.
 ///
 /// In release builds, this always returns the real system time.
-/// In debug builds, if `SHEPHERD_MOCK_TIME` is set, this returns a time
-/// that advances from the mock time at the same rate as real time.
+/// In debug builds, if `SHEPHERD_MOCK_TIME` is set or the clock was changed
+/// at runtime, this returns a time that advances from the mock time at the
+/// same rate as real time, or stands still while frozen.
 #[allow(clippy::disallowed_methods)] // This is the wrapper that provides mock time support
 pub fn now() -> DateTime<Local> {
     let real_now = chrono::Local::now();
-    
-    if let Some(offset) = get_mock_time_offset() {
-        real_now + offset
-    } else {
-        real_now
-    }
+    mock_clock().now(real_now)
 }
 
 /// Format a DateTime for display in the HUD clock.
@@ -143,7 +263,7 @@ impl std::ops::Add<Duration> for MonotonicInstant {
 }
 
 /// Wall-clock time for availability windows
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct WallClock {
     pub hour: u8,
     pub minute: u8,
@@ -188,8 +308,8 @@ impl Ord for WallClock {
     }
 }
 
-/// Days of the week mask
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+/// Days of the week mask: bit 0 is Monday through bit 6 for Sunday
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
 pub struct DaysOfWeek(u8);
 
 impl DaysOfWeek {
@@ -239,7 +359,7 @@ impl std::ops::BitOr for DaysOfWeek {
 }
 
 /// A time window during which an entry is available
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct TimeWindow {
     pub days: DaysOfWeek,
     pub start: WallClock,
@@ -253,12 +373,20 @@ impl TimeWindow {
 
     /// Check if the given local datetime falls within this window
     pub fn contains(&self, dt: &DateTime<Local>) -> bool {
-        let weekday = dt.weekday();
-        if !self.days.contains(weekday) {
+        self.contains_in(&Timezone::Local, dt)
+    }
+
+    /// Check if the given instant falls within this window, read in `tz`
+    pub fn contains_in(&self, tz: &Timezone, dt: &DateTime<Local>) -> bool {
+        self.contains_wall(&tz.wall_time(dt))
+    }
+
+    fn contains_wall(&self, wall: &NaiveDateTime) -> bool {
+        if !self.days.contains(wall.weekday()) {
             return false;
         }
 
-        let time = WallClock::from_naive_time(dt.time());
+        let time = WallClock::from_naive_time(wall.time());
 
         // Handle windows that don't cross midnight
         if self.start <= self.end {
@@ -271,29 +399,27 @@ impl TimeWindow {
 
     /// Calculate duration remaining in this window from the given time
     pub fn remaining_duration(&self, dt: &DateTime<Local>) -> Option<Duration> {
-        if !self.contains(dt) {
+        self.remaining_duration_in(&Timezone::Local, dt)
+    }
+
+    /// Calculate real time remaining in this window, read in `tz`.
+    ///
+    /// The end is resolved to an instant, so a DST transition inside the
+    /// window shortens or lengthens it by the shift.
+    pub fn remaining_duration_in(&self, tz: &Timezone, dt: &DateTime<Local>) -> Option<Duration> {
+        let wall = tz.wall_time(dt);
+        if !self.contains_wall(&wall) {
             return None;
         }
 
-        let now_time = WallClock::from_naive_time(dt.time());
-        let now_secs = now_time.as_seconds_from_midnight();
-        let end_secs = self.end.as_seconds_from_midnight();
-
-        let remaining_secs = if self.start <= self.end {
-            // Normal window
-            end_secs.saturating_sub(now_secs)
-        } else {
-            // Cross-midnight window
-            if now_secs >= self.start.as_seconds_from_midnight() {
-                // We're in the evening portion, count until midnight then add morning
-                (86400 - now_secs) + end_secs
-            } else {
-                // We're in the morning portion
-                end_secs.saturating_sub(now_secs)
-            }
-        };
+        // In the evening portion of a cross-midnight window, the end is tomorrow
+        let mut end_date = wall.date();
+        if self.start > self.end && WallClock::from_naive_time(wall.time()) >= self.start {
+            end_date = end_date.succ_opt()?;
+        }
+        let end = tz.next_instant(&end_date.and_time(self.end.to_naive_time()), dt)?;
 
-        Some(Duration::from_secs(remaining_secs as u64))
+        Some(end.signed_duration_since(*dt).to_std().unwrap_or(Duration::ZERO))
     }
 }
 
@@ -377,6 +503,89 @@ mod tests {
         assert_eq!(remaining, Duration::from_secs(3 * 3600)); // 3 hours
     }
 
+    fn new_york() -> Timezone {
+        "EST5EDT,M3.2.0,M11.1.0".parse().unwrap()
+    }
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Local> {
+        chrono::Utc
+            .with_ymd_and_hms(y, m, d, h, min, 0)
+            .unwrap()
+            .with_timezone(&Local)
+    }
+
+    #[test]
+    fn test_time_window_spring_forward() {
+        let tz = new_york();
+        let window = TimeWindow::new(
+            DaysOfWeek::ALL_DAYS,
+            WallClock::new(1, 0).unwrap(),
+            WallClock::new(3, 0).unwrap(),
+        );
+
+        // 2025-03-09 01:30 EST: the clock jumps from 02:00 to 03:00, so only 30 minutes remain
+        let dt = utc(2025, 3, 9, 6, 30);
+        assert!(window.contains_in(&tz, &dt));
+        assert_eq!(window.remaining_duration_in(&tz, &dt), Some(Duration::from_secs(30 * 60)));
+        // 03:00 EDT is already past the window
+        assert!(!window.contains_in(&tz, &utc(2025, 3, 9, 7, 0)));
+
+        // An end time inside the gap is reached when the clock jumps over it
+        let late = TimeWindow::new(
+            DaysOfWeek::ALL_DAYS,
+            WallClock::new(22, 0).unwrap(),
+            WallClock::new(2, 30).unwrap(),
+        );
+        let dt = utc(2025, 3, 9, 4, 0); // Saturday 23:00 EST
+        assert_eq!(late.remaining_duration_in(&tz, &dt), Some(Duration::from_secs(3 * 3600)));
+    }
+
+    #[test]
+    fn test_time_window_fall_back() {
+        let tz = new_york();
+        let window = TimeWindow::new(
+            DaysOfWeek::ALL_DAYS,
+            WallClock::new(22, 0).unwrap(),
+            WallClock::new(2, 0).unwrap(),
+        );
+
+        // 2025-11-01 23:00 EDT: 01:00-02:00 happens twice, so four real hours remain
+        let dt = utc(2025, 11, 2, 3, 0);
+        assert!(window.contains_in(&tz, &dt));
+        assert_eq!(window.remaining_duration_in(&tz, &dt), Some(Duration::from_secs(4 * 3600)));
+
+        // Both passes through 01:30 are in the window and end at 02:00 EST
+        let first = utc(2025, 11, 2, 5, 30);
+        let second = utc(2025, 11, 2, 6, 30);
+        assert_eq!(window.remaining_duration_in(&tz, &first), Some(Duration::from_secs(90 * 60)));
+        assert_eq!(window.remaining_duration_in(&tz, &second), Some(Duration::from_secs(30 * 60)));
+        assert!(!window.contains_in(&tz, &utc(2025, 11, 2, 7, 0)));
+
+        // Windows ending in the repeated hour close at the first pass
+        let early = TimeWindow::new(
+            DaysOfWeek::ALL_DAYS,
+            WallClock::new(0, 0).unwrap(),
+            WallClock::new(1, 30).unwrap(),
+        );
+        let dt = utc(2025, 11, 2, 5, 0); // 01:00 EDT
+        assert_eq!(early.remaining_duration_in(&tz, &dt), Some(Duration::from_secs(30 * 60)));
+    }
+
+    #[test]
+    fn test_time_window_weekday_in_timezone() {
+        let tz: Timezone = "<+13>-13".parse().unwrap();
+        let window = TimeWindow::new(
+            DaysOfWeek::WEEKENDS,
+            WallClock::new(9, 0).unwrap(),
+            WallClock::new(12, 0).unwrap(),
+        );
+
+        // Friday 21:00 UTC is Saturday 10:00 at +13
+        let dt = utc(2025, 12, 26, 21, 0);
+        assert!(window.contains_in(&tz, &dt));
+        assert_eq!(window.remaining_duration_in(&tz, &dt), Some(Duration::from_secs(2 * 3600)));
+    }
+
     #[test]
     fn test_format_duration() {
         assert_eq!(format_duration(Duration::from_secs(30)), "30s");
@@ -598,10 +807,43 @@ mod mock_time_integration_tests {
     fn test_is_mock_time_active_in_debug() {
         // In debug mode, is_mock_time_active() should return based on env var
         // Since we can't control the env var within a single test run due to OnceLock,
-        // we just verify the function doesn't panic
+        // we just verify the function doesn't panic. Runtime changes are covered by
+        // test_mock_clock without touching the process-wide clock.
         let _ = is_mock_time_active();
     }
 
+    #[test]
+    fn test_mock_clock() {
+        let real = Local.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        let later = real + chrono::Duration::minutes(10);
+        let mut clock = MockClock::default();
+        assert!(!clock.is_active());
+        assert_eq!(clock.now(real), real);
+
+        // Set: runs at real speed from the new time
+        let target = Local.with_ymd_and_hms(2025, 12, 25, 20, 0, 0).unwrap();
+        clock.set(real, target);
+        assert!(clock.is_active());
+        assert_eq!(clock.now(later), target + chrono::Duration::minutes(10));
+
+        // Freeze: stands still
+        clock.freeze(later, true);
+        let frozen = target + chrono::Duration::minutes(10);
+        assert_eq!(clock.now(later + chrono::Duration::hours(1)), frozen);
+
+        // Advance while frozen stays frozen
+        clock.advance(later, chrono::Duration::hours(2));
+        assert_eq!(clock.now(later + chrono::Duration::hours(5)), frozen + chrono::Duration::hours(2));
+
+        // Resume continues from the frozen time
+        let resumed_at = later + chrono::Duration::hours(5);
+        clock.freeze(resumed_at, false);
+        assert_eq!(
+            clock.now(resumed_at + chrono::Duration::minutes(1)),
+            frozen + chrono::Duration::hours(2) + chrono::Duration::minutes(1)
+        );
+    }
+
     #[test]
     fn test_now_consistency() {
         // now() should return consistent, advancing times
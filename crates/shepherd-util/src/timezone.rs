@@ -0,0 +1,513 @@
+//! Timezones for availability windows
+//!
+//! Windows are written in wall-clock time, so they need a timezone to map
+//! onto real instants. By default that is the system timezone; a config can
+//! pin an explicit zone instead, either as an IANA name (resolved through the
+//! POSIX rule at the end of its zoneinfo file) or as a POSIX TZ string such as
+//! `CET-1CEST,M3.5.0,M10.5.0/3`.
+//!
+//! Only the current rule of a zone is used, so historical offset changes are
+//! not reproduced. That is enough for evaluating windows around "now".
+
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, Local, LocalResult, NaiveDate,
+    NaiveDateTime, TimeZone, Utc,
+};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Where IANA zone files are looked up
+pub const ZONEINFO_DIR: &str = "/usr/share/zoneinfo";
+
+/// Longest spring-forward gap searched when a wall-clock time doesn't exist
+const MAX_GAP_MINUTES: i64 = 180;
+
+/// Error parsing a timezone
+#[derive(Debug, Clone, Error)]
+#[error("invalid timezone \"{name}\": {reason}")]
+pub struct InvalidTimezone {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Timezone used to evaluate wall-clock windows
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Timezone {
+    /// The system timezone
+    #[default]
+    Local,
+    /// A named zone with a fixed POSIX rule
+    Rule { name: String, rule: PosixTz },
+}
+
+impl Timezone {
+    /// Name as written in the config, or "local"
+    pub fn name(&self) -> &str {
+        match self {
+            Timezone::Local => "local",
+            Timezone::Rule { name, .. } => name,
+        }
+    }
+
+    /// Wall-clock time in this zone at the given instant
+    pub fn wall_time(&self, dt: &DateTime<Local>) -> NaiveDateTime {
+        match self {
+            Timezone::Local => dt.naive_local(),
+            Timezone::Rule { rule, .. } => {
+                let utc = dt.naive_utc();
+                utc + ChronoDuration::seconds(rule.offset_at(&utc) as i64)
+            }
+        }
+    }
+
+    /// Instants at which the wall clock reads `wall`, earliest first.
+    ///
+    /// Empty inside a spring-forward gap; two instants inside a fall-back
+    /// overlap.
+    pub fn instants(&self, wall: &NaiveDateTime) -> Vec<DateTime<Local>> {
+        match self {
+            Timezone::Local => match Local.from_local_datetime(wall) {
+                LocalResult::Single(dt) => vec![dt],
+                LocalResult::Ambiguous(a, b) => vec![a.min(b), a.max(b)],
+                LocalResult::None => Vec::new(),
+            },
+            Timezone::Rule { rule, .. } => {
+                let mut instants: Vec<_> = rule
+                    .offsets()
+                    .filter_map(|offset| {
+                        let utc = *wall - ChronoDuration::seconds(offset as i64);
+                        (rule.offset_at(&utc) == offset)
+                            .then(|| Utc.from_utc_datetime(&utc).with_timezone(&Local))
+                    })
+                    .collect();
+                instants.sort();
+                instants.dedup();
+                instants
+            }
+        }
+    }
+
+    /// First instant after `after` at which the wall clock has reached `wall`.
+    ///
+    /// A time skipped by a spring-forward gap is reached when the clock jumps
+    /// over it. Returns `after` itself if the time was already passed.
+    pub fn next_instant(
+        &self,
+        wall: &NaiveDateTime,
+        after: &DateTime<Local>,
+    ) -> Option<DateTime<Local>> {
+        let instants = self.instants(wall);
+        if !instants.is_empty() {
+            return Some(instants.into_iter().find(|i| i > after).unwrap_or(*after));
+        }
+
+        (1..=MAX_GAP_MINUTES)
+            .map(|minutes| self.instants(&(*wall + ChronoDuration::minutes(minutes))))
+            .find_map(|instants| instants.into_iter().next())
+            .map(|instant| instant.max(*after))
+    }
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = InvalidTimezone;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim();
+        let invalid = |reason: String| InvalidTimezone {
+            name: name.to_string(),
+            reason,
+        };
+
+        if name.is_empty() || name.eq_ignore_ascii_case("local") {
+            return Ok(Timezone::Local);
+        }
+
+        let rule = if is_zone_name(name) && Path::new(ZONEINFO_DIR).join(name).is_file() {
+            let data = std::fs::read(Path::new(ZONEINFO_DIR).join(name))
+                .map_err(|e| invalid(e.to_string()))?;
+            let footer = tzif_footer(&data)
+                .ok_or_else(|| invalid("zoneinfo file has no POSIX rule".into()))?;
+            footer.parse().map_err(invalid)?
+        } else {
+            name.parse().map_err(|e: String| {
+                if name.contains('/') {
+                    invalid(format!("unknown zone, not found in {}", ZONEINFO_DIR))
+                } else {
+                    invalid(e)
+                }
+            })?
+        };
+
+        Ok(Timezone::Rule {
+            name: name.to_string(),
+            rule,
+        })
+    }
+}
+
+/// Whether a string is safe to look up as a zoneinfo path
+fn is_zone_name(name: &str) -> bool {
+    !name.starts_with('/')
+        && name.split('/').all(|part| !part.is_empty() && part != "." && part != "..")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '-' | '+'))
+}
+
+/// Extract the POSIX TZ footer from a version 2+ TZif file
+fn tzif_footer(data: &[u8]) -> Option<&str> {
+    if !data.starts_with(b"TZif") || data.get(4).is_none_or(|v| *v == 0) {
+        return None;
+    }
+    let body = data.strip_suffix(b"\n")?;
+    let start = body.iter().rposition(|b| *b == b'\n')? + 1;
+    let footer = std::str::from_utf8(&body[start..]).ok()?;
+    (!footer.is_empty()).then_some(footer)
+}
+
+/// A POSIX TZ rule: a standard offset and an optional yearly DST rule.
+///
+/// Offsets are stored in seconds east of UTC (the TZ string uses the
+/// opposite sign).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PosixTz {
+    std_offset: i32,
+    dst: Option<DstRule>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DstRule {
+    offset: i32,
+    start: Transition,
+    end: Transition,
+}
+
+/// A yearly transition: a date rule and a local time in seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Transition {
+    date: DateRule,
+    time: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateRule {
+    /// `Jn`: day 1-365, never counting February 29
+    Julian(u16),
+    /// `n`: day 0-365, counting February 29
+    Ordinal(u16),
+    /// `Mm.w.d`: weekday `d` (0 = Sunday) of week `w` (5 = last) of month `m`
+    MonthWeekDay { month: u32, week: u32, weekday: u32 },
+}
+
+impl PosixTz {
+    /// UTC offset in seconds east at the given UTC time
+    pub fn offset_at(&self, utc: &NaiveDateTime) -> i32 {
+        let Some(dst) = &self.dst else {
+            return self.std_offset;
+        };
+
+        let year = (*utc + ChronoDuration::seconds(self.std_offset as i64)).year();
+        let start = dst.start.utc(year, self.std_offset);
+        let end = dst.end.utc(year, dst.offset);
+
+        let in_dst = if start < end {
+            *utc >= start && *utc < end
+        } else {
+            // Southern hemisphere: DST spans the new year
+            *utc >= start || *utc < end
+        };
+        if in_dst { dst.offset } else { self.std_offset }
+    }
+
+    /// All offsets this rule can produce
+    fn offsets(&self) -> impl Iterator<Item = i32> {
+        std::iter::once(self.std_offset).chain(self.dst.map(|d| d.offset))
+    }
+}
+
+impl Transition {
+    /// UTC time of this transition in `year`, given the offset in effect before it
+    fn utc(&self, year: i32, offset_before: i32) -> NaiveDateTime {
+        let midnight = self.date.date(year).and_hms_opt(0, 0, 0).unwrap();
+        midnight + ChronoDuration::seconds(self.time as i64 - offset_before as i64)
+    }
+}
+
+impl DateRule {
+    fn date(&self, year: i32) -> NaiveDate {
+        let last_of_year = NaiveDate::from_ymd_opt(year, 12, 31).unwrap();
+        match *self {
+            DateRule::Julian(day) => {
+                let leap = last_of_year.ordinal() == 366;
+                let ordinal = day as u32 + u32::from(leap && day >= 60);
+                NaiveDate::from_yo_opt(year, ordinal).unwrap_or(last_of_year)
+            }
+            DateRule::Ordinal(day) => {
+                NaiveDate::from_yo_opt(year, day as u32 + 1).unwrap_or(last_of_year)
+            }
+            DateRule::MonthWeekDay { month, week, weekday } => {
+                let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+                let first_weekday = first.weekday().num_days_from_sunday();
+                let mut day = 1 + (weekday + 7 - first_weekday) % 7 + (week - 1) * 7;
+                while NaiveDate::from_ymd_opt(year, month, day).is_none() {
+                    day -= 7;
+                }
+                NaiveDate::from_ymd_opt(year, month, day).unwrap()
+            }
+        }
+    }
+}
+
+impl FromStr for PosixTz {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { rest: s };
+
+        parser.name()?;
+        let std_offset = -parser.offset(24)?;
+        if parser.rest.is_empty() {
+            return Ok(Self {
+                std_offset,
+                dst: None,
+            });
+        }
+
+        parser.name()?;
+        let offset = if parser.rest.is_empty() || parser.rest.starts_with(',') {
+            std_offset + 3600
+        } else {
+            -parser.offset(24)?
+        };
+
+        let (start, end) = if parser.rest.is_empty() {
+            // POSIX leaves the default rule to the implementation; use the US one like glibc
+            (
+                Transition {
+                    date: DateRule::MonthWeekDay { month: 3, week: 2, weekday: 0 },
+                    time: 7200,
+                },
+                Transition {
+                    date: DateRule::MonthWeekDay { month: 11, week: 1, weekday: 0 },
+                    time: 7200,
+                },
+            )
+        } else {
+            parser.expect(',')?;
+            let start = parser.transition()?;
+            parser.expect(',')?;
+            let end = parser.transition()?;
+            (start, end)
+        };
+
+        if !parser.rest.is_empty() {
+            return Err(format!("unexpected \"{}\"", parser.rest));
+        }
+
+        Ok(Self {
+            std_offset,
+            dst: Some(DstRule { offset, start, end }),
+        })
+    }
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl Parser<'_> {
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.rest = self
+            .rest
+            .strip_prefix(c)
+            .ok_or_else(|| format!("expected '{}' at \"{}\"", c, self.rest))?;
+        Ok(())
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &str {
+        let end = self.rest.find(|c| !f(c)).unwrap_or(self.rest.len());
+        let (taken, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        taken
+    }
+
+    /// Zone abbreviation: `EST` or quoted `<+0530>`
+    fn name(&mut self) -> Result<(), String> {
+        let name = if let Some(rest) = self.rest.strip_prefix('<') {
+            let end = rest.find('>').ok_or("unterminated '<' in zone name")?;
+            self.rest = &rest[end + 1..];
+            &rest[..end]
+        } else {
+            self.take_while(|c| c.is_ascii_alphabetic())
+        };
+        if name.len() < 3 {
+            return Err("expected a zone abbreviation of at least 3 letters".into());
+        }
+        Ok(())
+    }
+
+    fn number(&mut self, max: u32) -> Result<u32, String> {
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        digits
+            .parse::<u32>()
+            .ok()
+            .filter(|n| *n <= max)
+            .ok_or_else(|| format!("expected a number up to {}", max))
+    }
+
+    /// `[+-]hh[:mm[:ss]]` in seconds
+    fn offset(&mut self, max_hours: u32) -> Result<i32, String> {
+        let sign = if let Some(rest) = self.rest.strip_prefix('-') {
+            self.rest = rest;
+            -1
+        } else {
+            self.rest = self.rest.strip_prefix('+').unwrap_or(self.rest);
+            1
+        };
+        let mut seconds = self.number(max_hours)? * 3600;
+        if self.rest.starts_with(':') {
+            self.expect(':')?;
+            seconds += self.number(59)? * 60;
+            if self.rest.starts_with(':') {
+                self.expect(':')?;
+                seconds += self.number(59)?;
+            }
+        }
+        Ok(sign * seconds as i32)
+    }
+
+    /// `date[/time]`
+    fn transition(&mut self) -> Result<Transition, String> {
+        let date = if let Some(rest) = self.rest.strip_prefix('M') {
+            self.rest = rest;
+            let month = self.number(12)?;
+            self.expect('.')?;
+            let week = self.number(5)?;
+            self.expect('.')?;
+            let weekday = self.number(6)?;
+            if month == 0 || week == 0 {
+                return Err("month and week start at 1".into());
+            }
+            DateRule::MonthWeekDay { month, week, weekday }
+        } else if let Some(rest) = self.rest.strip_prefix('J') {
+            self.rest = rest;
+            match self.number(365)? {
+                0 => return Err("Julian days start at 1".into()),
+                day => DateRule::Julian(day as u16),
+            }
+        } else {
+            DateRule::Ordinal(self.number(365)? as u16)
+        };
+
+        let time = if self.rest.starts_with('/') {
+            self.expect('/')?;
+            self.offset(167)?
+        } else {
+            7200
+        };
+
+        Ok(Transition { date, time })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Local> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0)
+            .unwrap()
+            .with_timezone(&Local)
+    }
+
+    fn wall(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    fn new_york() -> Timezone {
+        Timezone::Rule {
+            name: "America/New_York".into(),
+            rule: "EST5EDT,M3.2.0,M11.1.0".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_posix_tz() {
+        let tz: PosixTz = "CET-1CEST,M3.5.0,M10.5.0/3".parse().unwrap();
+        assert_eq!(tz.std_offset, 3600);
+        assert_eq!(tz.dst.unwrap().offset, 7200);
+        assert_eq!(tz.dst.unwrap().end.time, 3 * 3600);
+
+        let tz: PosixTz = "<+0530>-5:30".parse().unwrap();
+        assert_eq!(tz.std_offset, 5 * 3600 + 30 * 60);
+        assert!(tz.dst.is_none());
+
+        assert!("EST".parse::<PosixTz>().is_err());
+        assert!("EST5EDT,M3.2.0".parse::<PosixTz>().is_err());
+        assert!("E5".parse::<PosixTz>().is_err());
+        assert!("local".parse::<Timezone>().unwrap() == Timezone::Local);
+        assert!("Not/A_Zone".parse::<Timezone>().is_err());
+    }
+
+    #[test]
+    fn test_dst_offsets() {
+        let tz = new_york();
+        // 2025: DST from March 9 02:00 EST to November 2 02:00 EDT
+        assert_eq!(tz.wall_time(&utc(2025, 3, 9, 6, 59)), wall(2025, 3, 9, 1, 59));
+        assert_eq!(tz.wall_time(&utc(2025, 3, 9, 7, 0)), wall(2025, 3, 9, 3, 0));
+        assert_eq!(tz.wall_time(&utc(2025, 11, 2, 5, 30)), wall(2025, 11, 2, 1, 30));
+        assert_eq!(tz.wall_time(&utc(2025, 11, 2, 6, 30)), wall(2025, 11, 2, 1, 30));
+
+        // Southern hemisphere: DST spans the new year
+        let sydney = Timezone::Rule {
+            name: "Australia/Sydney".into(),
+            rule: "AEST-10AEDT,M10.1.0,M4.1.0/3".parse().unwrap(),
+        };
+        assert_eq!(sydney.wall_time(&utc(2025, 1, 1, 0, 0)), wall(2025, 1, 1, 11, 0));
+        assert_eq!(sydney.wall_time(&utc(2025, 7, 1, 0, 0)), wall(2025, 7, 1, 10, 0));
+    }
+
+    #[test]
+    fn test_instants_in_gap_and_overlap() {
+        let tz = new_york();
+        assert!(tz.instants(&wall(2025, 3, 9, 2, 30)).is_empty());
+        assert_eq!(
+            tz.instants(&wall(2025, 11, 2, 1, 30)),
+            vec![utc(2025, 11, 2, 5, 30), utc(2025, 11, 2, 6, 30)]
+        );
+
+        // A skipped time is reached when the clock jumps to 03:00 EDT
+        assert_eq!(
+            tz.next_instant(&wall(2025, 3, 9, 2, 30), &utc(2025, 3, 9, 6, 0)),
+            Some(utc(2025, 3, 9, 7, 0))
+        );
+        // During the second 01:xx, the next 01:30 is the EST one
+        assert_eq!(
+            tz.next_instant(&wall(2025, 11, 2, 1, 30), &utc(2025, 11, 2, 6, 0)),
+            Some(utc(2025, 11, 2, 6, 30))
+        );
+    }
+
+    #[test]
+    fn test_tzif_footer() {
+        let mut data = b"TZif2".to_vec();
+        data.extend_from_slice(&[0; 40]);
+        data.extend_from_slice(b"\nEST5EDT,M3.2.0,M11.1.0\n");
+        assert_eq!(tzif_footer(&data), Some("EST5EDT,M3.2.0,M11.1.0"));
+
+        let mut v1 = b"TZif\0".to_vec();
+        v1.extend_from_slice(b"\nEST5\n");
+        assert_eq!(tzif_footer(&v1), None);
+    }
+}
@@ -0,0 +1,543 @@
+//! Reading usage exports from other parental controls
+//!
+//! Google Family Link and Microsoft Family Safety both let parents download
+//! a child's activity as CSV or JSON, one row per app and day. The column
+//! names differ between services, languages and versions, so columns are
+//! recognised by name rather than position: an app, a date, the time used
+//! and, where the export has one, the app's daily limit. JSON exports are
+//! read the same way, from the first array of objects found; days holding
+//! a nested list of apps pass their date down to it.
+//!
+//! Rows are then mapped to entries with [`Policy::import_entry`], summed per
+//! entry and day, and each entry's most recent limit becomes its quota.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate};
+use serde_json::Value;
+use shepherd_api::ImportedUsage;
+use shepherd_config::Policy;
+use shepherd_util::EntryId;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// One row of an export
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportRow {
+    pub app: String,
+    pub day: NaiveDate,
+    pub used: Duration,
+    /// The app's daily limit that day, if the export has one
+    pub limit: Option<Duration>,
+}
+
+/// What a column holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    App,
+    Day,
+    /// Time used; bare numbers are in this unit
+    Used(Unit),
+    Limit(Unit),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    Seconds,
+    Minutes,
+    Hours,
+}
+
+impl Unit {
+    fn duration(self, amount: f64) -> Option<Duration> {
+        let secs = match self {
+            Unit::Seconds => amount,
+            Unit::Minutes => amount * 60.0,
+            Unit::Hours => amount * 3600.0,
+        };
+        (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs(secs.round() as u64))
+    }
+}
+
+/// Recognise a column by its header, ignoring case, spaces and punctuation
+fn column(header: &str) -> Option<Column> {
+    let name: String = header
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let unit = if name.contains("sec") {
+        Unit::Seconds
+    } else if name.contains("hour") {
+        Unit::Hours
+    } else {
+        Unit::Minutes
+    };
+
+    if name.contains("limit") || name.contains("allowance") {
+        return Some(Column::Limit(unit));
+    }
+    match name.as_str() {
+        "app" | "apps" | "appname" | "application" | "name" | "apporgame" | "appsandgames"
+        | "appsgames" | "activity" | "title" | "package" | "packagename" => Some(Column::App),
+        "date" | "day" | "datum" | "timestamp" => Some(Column::Day),
+        _ if ["usage", "timespent", "screentime", "duration", "timeused", "totaltime", "minutes", "seconds", "hours"]
+            .iter()
+            .any(|used| name.starts_with(used)) =>
+        {
+            Some(Column::Used(unit))
+        }
+        _ => None,
+    }
+}
+
+/// Read a duration like "45", "1:20", "01:20:00", "1h 20m" or "1 hr 5 min".
+/// Bare numbers are in `unit`.
+fn parse_duration(text: &str, unit: Unit) -> Option<Duration> {
+    let text = text.trim().to_lowercase();
+    if text.is_empty() || text == "-" {
+        return Some(Duration::ZERO);
+    }
+    if let Ok(amount) = text.replace(',', ".").parse::<f64>() {
+        return unit.duration(amount);
+    }
+
+    if text.contains(':') {
+        let parts: Vec<u64> = text.split(':').map(|p| p.trim().parse().ok()).collect::<Option<_>>()?;
+        let secs = match parts.as_slice() {
+            [h, m] => h * 3600 + m * 60,
+            [h, m, s] => h * 3600 + m * 60 + s,
+            _ => return None,
+        };
+        return Some(Duration::from_secs(secs));
+    }
+
+    // Amounts each followed by a unit: "1h 20m", "1 hr 5 min", "90 minutes"
+    let mut total = 0.0;
+    let mut rest = text.as_str();
+    while !rest.trim().is_empty() {
+        rest = rest.trim_start();
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+            .unwrap_or(rest.len());
+        let amount: f64 = rest[..number_len].replace(',', ".").parse().ok()?;
+        rest = rest[number_len..].trim_start();
+        let unit_len = rest.find(|c: char| !c.is_alphabetic()).unwrap_or(rest.len());
+        let unit = match &rest[..unit_len] {
+            "h" | "hr" | "hrs" | "hour" | "hours" => 3600.0,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60.0,
+            "s" | "sec" | "secs" | "second" | "seconds" => 1.0,
+            _ => return None,
+        };
+        total += amount * unit;
+        rest = &rest[unit_len..];
+    }
+    Some(Duration::from_secs(total.round() as u64))
+}
+
+/// Read a duration given on the command line, like "15", "1:30" or
+/// "1h 30m". Bare numbers are minutes.
+pub fn parse_minutes(text: &str) -> Option<Duration> {
+    parse_duration(text, Unit::Minutes)
+}
+
+/// Read a date like "2025-01-06", an RFC 3339 timestamp, "06.01.2025", or
+/// "01/06/2025" (day first with `day_first`)
+fn parse_day(text: &str, day_first: bool) -> Option<NaiveDate> {
+    let text = text.trim();
+    if let Ok(day) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Some(day);
+    }
+    if let Ok(at) = DateTime::parse_from_rfc3339(text) {
+        return Some(at.date_naive());
+    }
+    if let Some(day) = text.get(..10).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()) {
+        return Some(day);
+    }
+    if let Ok(day) = NaiveDate::parse_from_str(text, "%d.%m.%Y") {
+        return Some(day);
+    }
+    let slashed = if day_first { "%d/%m/%Y" } else { "%m/%d/%Y" };
+    NaiveDate::parse_from_str(text, slashed).ok()
+}
+
+/// Split CSV text into records. Handles quoted fields, doubled quotes and
+/// newlines inside quotes; the delimiter is ',' or, for spreadsheets in
+/// locales using decimal commas, ';'.
+fn csv_records(text: &str) -> Vec<Vec<String>> {
+    let text = text.trim_start_matches('\u{feff}');
+    let header = text.lines().next().unwrap_or_default();
+    let delimiter = if header.matches(';').count() > header.matches(',').count() {
+        ';'
+    } else {
+        ','
+    };
+
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    records
+}
+
+/// A row's fields, by what they hold
+#[derive(Debug, Default)]
+struct Fields {
+    app: Option<String>,
+    day: Option<String>,
+    used: Option<(String, Unit)>,
+    limit: Option<(String, Unit)>,
+}
+
+impl Fields {
+    fn set(&mut self, column: Column, value: String) {
+        let slot = match column {
+            Column::App => &mut self.app,
+            Column::Day => &mut self.day,
+            Column::Used(unit) => return self.used = self.used.take().or(Some((value, unit))),
+            Column::Limit(unit) => return self.limit = self.limit.take().or(Some((value, unit))),
+        };
+        slot.get_or_insert(value);
+    }
+
+    /// The row, or None for a row without an app or time used
+    fn row(&self, day_first: bool, line: usize) -> Result<Option<ExportRow>> {
+        let (Some(app), Some((used, unit))) = (&self.app, &self.used) else {
+            return Ok(None);
+        };
+        if app.trim().is_empty() {
+            return Ok(None);
+        }
+        let day = self.day.as_deref().unwrap_or_default();
+        let day = parse_day(day, day_first)
+            .with_context(|| format!("Row {}: can't read date {:?}", line, day))?;
+        let used = parse_duration(used, *unit)
+            .with_context(|| format!("Row {}: can't read time used {:?}", line, used))?;
+        let limit = match &self.limit {
+            Some((limit, unit)) if !limit.trim().is_empty() => Some(
+                parse_duration(limit, *unit)
+                    .with_context(|| format!("Row {}: can't read limit {:?}", line, limit))?,
+            ),
+            _ => None,
+        };
+        Ok(Some(ExportRow {
+            app: app.trim().to_string(),
+            day,
+            used,
+            limit,
+        }))
+    }
+}
+
+fn parse_csv(text: &str, day_first: bool) -> Result<Vec<ExportRow>> {
+    let mut records = csv_records(text).into_iter();
+    let header: Vec<Option<Column>> = records
+        .next()
+        .context("The export is empty")?
+        .iter()
+        .map(|h| column(h))
+        .collect();
+    for (needed, name) in [(Column::App, "app"), (Column::Day, "date")] {
+        if !header.contains(&Some(needed)) {
+            bail!("No {} column found in the export's header", name);
+        }
+    }
+    if !header.iter().any(|c| matches!(c, Some(Column::Used(_)))) {
+        bail!("No time used column found in the export's header");
+    }
+
+    let mut rows = Vec::new();
+    for (index, record) in records.enumerate() {
+        let mut fields = Fields::default();
+        for (column, value) in header.iter().zip(record) {
+            if let Some(column) = column {
+                fields.set(*column, value);
+            }
+        }
+        // The header is line 1
+        rows.extend(fields.row(day_first, index + 2)?);
+    }
+    Ok(rows)
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// The first array of objects in `value`, searching breadth first
+fn first_object_array(value: &Value) -> Option<&Vec<Value>> {
+    let mut queue = std::collections::VecDeque::from([value]);
+    while let Some(value) = queue.pop_front() {
+        match value {
+            Value::Array(items) if items.iter().any(Value::is_object) => return Some(items),
+            Value::Array(items) => queue.extend(items),
+            Value::Object(map) => queue.extend(map.values()),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn json_rows(
+    items: &[Value],
+    inherited_day: Option<&str>,
+    day_first: bool,
+    rows: &mut Vec<ExportRow>,
+) -> Result<()> {
+    for (index, item) in items.iter().enumerate() {
+        let Value::Object(map) = item else {
+            continue;
+        };
+        let mut fields = Fields::default();
+        for (key, value) in map {
+            if let (Some(column), Some(value)) = (column(key), scalar(value)) {
+                fields.set(column, value);
+            }
+        }
+        if fields.day.is_none() {
+            fields.day = inherited_day.map(str::to_string);
+        }
+        match fields.row(day_first, index + 1)? {
+            Some(row) => rows.push(row),
+            // A day with its apps nested inside
+            None => {
+                if let Some(children) = map.values().find_map(|v| v.as_array()) {
+                    json_rows(children, fields.day.as_deref(), day_first, rows)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_json(text: &str, day_first: bool) -> Result<Vec<ExportRow>> {
+    let value: Value = serde_json::from_str(text).context("Not valid JSON")?;
+    let items = first_object_array(&value).context("No list of apps found in the JSON export")?;
+    let mut rows = Vec::new();
+    json_rows(items, None, day_first, &mut rows)?;
+    Ok(rows)
+}
+
+/// Read a CSV or JSON export
+pub fn parse_export(text: &str, day_first: bool) -> Result<Vec<ExportRow>> {
+    let trimmed = text.trim_start_matches('\u{feff}').trim_start();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        parse_json(trimmed, day_first)
+    } else {
+        parse_csv(text, day_first)
+    }
+}
+
+/// What an export amounts to in this config
+#[derive(Debug, Default)]
+pub struct ImportPlan {
+    /// Time used per entry and day
+    pub usage: Vec<ImportedUsage>,
+    /// Each entry's limit on the latest day that had one
+    pub quotas: Vec<(EntryId, Duration)>,
+    /// Apps with no matching entry, and the time they were used
+    pub unmapped: Vec<(String, Duration)>,
+}
+
+/// Map rows to entries and sum them per entry and day
+pub fn plan(rows: &[ExportRow], policy: &Policy) -> ImportPlan {
+    let mut usage: HashMap<(EntryId, NaiveDate), Duration> = HashMap::new();
+    // Apps mapped to the same entry each had their own limit; keep the largest
+    let mut limits: HashMap<EntryId, (NaiveDate, Duration)> = HashMap::new();
+    let mut unmapped: HashMap<String, Duration> = HashMap::new();
+
+    for row in rows {
+        let Some(entry_id) = policy.import_entry(&row.app) else {
+            *unmapped.entry(row.app.clone()).or_default() += row.used;
+            continue;
+        };
+        *usage.entry((entry_id.clone(), row.day)).or_default() += row.used;
+        if let Some(limit) = row.limit {
+            let latest = limits.entry(entry_id.clone()).or_insert((row.day, limit));
+            if row.day > latest.0 {
+                *latest = (row.day, limit);
+            } else if row.day == latest.0 {
+                latest.1 = latest.1.max(limit);
+            }
+        }
+    }
+
+    let mut usage: Vec<ImportedUsage> = usage
+        .into_iter()
+        .filter(|(_, duration)| !duration.is_zero())
+        .map(|((entry_id, day), duration)| ImportedUsage { entry_id, day, duration })
+        .collect();
+    usage.sort_by(|a, b| (a.day, a.entry_id.as_str()).cmp(&(b.day, b.entry_id.as_str())));
+    let mut quotas: Vec<(EntryId, Duration)> = limits
+        .into_iter()
+        .map(|(entry_id, (_, limit))| (entry_id, limit))
+        .collect();
+    quotas.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+    let mut unmapped: Vec<(String, Duration)> = unmapped.into_iter().collect();
+    unmapped.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    ImportPlan {
+        usage,
+        quotas,
+        unmapped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mins(m: u64) -> Duration {
+        Duration::from_secs(m * 60)
+    }
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("45", Unit::Minutes), Some(mins(45)));
+        assert_eq!(parse_duration("2700", Unit::Seconds), Some(mins(45)));
+        assert_eq!(parse_duration("1,5", Unit::Hours), Some(mins(90)));
+        assert_eq!(parse_duration("1:20", Unit::Minutes), Some(mins(80)));
+        assert_eq!(parse_duration("01:20:30", Unit::Minutes), Some(mins(80) + Duration::from_secs(30)));
+        assert_eq!(parse_duration("1h 20m", Unit::Minutes), Some(mins(80)));
+        assert_eq!(parse_duration("1 hr 5 min", Unit::Minutes), Some(mins(65)));
+        assert_eq!(parse_duration("90 minutes", Unit::Seconds), Some(mins(90)));
+        assert_eq!(parse_duration("", Unit::Minutes), Some(Duration::ZERO));
+        assert_eq!(parse_duration("a while", Unit::Minutes), None);
+        assert_eq!(parse_duration("-5", Unit::Minutes), None);
+    }
+
+    #[test]
+    fn test_parse_day() {
+        assert_eq!(parse_day("2025-01-06", false), Some(day(6)));
+        assert_eq!(parse_day("2025-01-06T18:30:00+01:00", false), Some(day(6)));
+        assert_eq!(parse_day("06.01.2025", false), Some(day(6)));
+        assert_eq!(parse_day("01/06/2025", false), Some(day(6)));
+        assert_eq!(parse_day("06/01/2025", true), Some(day(6)));
+        assert_eq!(parse_day("yesterday", false), None);
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let export = "\u{feff}Date,App name,Time spent,Daily limit\r\n\
+            2025-01-06,\"Minecraft, Java Edition\",1 hr 5 min,2 hr\r\n\
+            2025-01-06,YouTube Kids,0:45,\r\n\
+            \r\n\
+            2025-01-07,Settings,,\r\n";
+        let rows = parse_export(export, false).unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ExportRow { app: "Minecraft, Java Edition".into(), day: day(6), used: mins(65), limit: Some(mins(120)) },
+                ExportRow { app: "YouTube Kids".into(), day: day(6), used: mins(45), limit: None },
+                ExportRow { app: "Settings".into(), day: day(7), used: Duration::ZERO, limit: None },
+            ]
+        );
+
+        // Semicolons, and minutes as bare numbers
+        let export = "Datum;App;Usage (minutes)\n06.01.2025;Minecraft;12,5\n";
+        let rows = parse_export(export, false).unwrap();
+        assert_eq!(rows[0].used, Duration::from_secs(750));
+
+        assert!(parse_export("Date,App\n2025-01-06,Minecraft\n", false).is_err());
+        assert!(parse_export("Date,App,Minutes\nsoon,Minecraft,5\n", false).is_err());
+    }
+
+    #[test]
+    fn test_parse_json() {
+        // Rows at the top level
+        let export = r#"[{"date": "2025-01-06", "app": "Minecraft", "minutes": 30, "limit": "1h"}]"#;
+        assert_eq!(
+            parse_export(export, false).unwrap(),
+            vec![ExportRow { app: "Minecraft".into(), day: day(6), used: mins(30), limit: Some(mins(60)) }]
+        );
+
+        // Days with their apps nested inside
+        let export = r#"{
+            "child": "Alex",
+            "activity": [
+                {"day": "2025-01-06", "apps": [
+                    {"name": "Minecraft", "screenTimeSeconds": 1800},
+                    {"name": "Calculator", "screenTimeSeconds": 60}
+                ]},
+                {"day": "2025-01-07", "apps": [{"name": "Minecraft", "screenTimeSeconds": 600}]}
+            ]
+        }"#;
+        let rows = parse_export(export, false).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0], ExportRow { app: "Minecraft".into(), day: day(6), used: mins(30), limit: None });
+        assert_eq!(rows[2].day, day(7));
+    }
+
+    #[test]
+    fn test_plan() {
+        let policy = shepherd_config::parse_config(
+            r#"
+            config_version = 1
+
+            [import.apps]
+            "Minecraft Education" = "minecraft"
+
+            [[entries]]
+            id = "minecraft"
+            label = "Minecraft"
+            kind = { type = "process", command = "minecraft-launcher" }
+            "#,
+        )
+        .unwrap();
+        let row = |app: &str, d, used, limit: Option<u64>| ExportRow {
+            app: app.into(),
+            day: day(d),
+            used: mins(used),
+            limit: limit.map(mins),
+        };
+        let rows = [
+            row("Minecraft", 6, 30, Some(60)),
+            row("Minecraft Education", 6, 15, Some(45)),
+            row("Minecraft", 7, 20, Some(90)),
+            row("YouTube", 6, 40, None),
+            row("YouTube", 7, 5, None),
+        ];
+
+        let plan = plan(&rows, &policy);
+        let minecraft = EntryId::new("minecraft");
+        assert_eq!(
+            plan.usage,
+            vec![
+                ImportedUsage { entry_id: minecraft.clone(), day: day(6), duration: mins(45) },
+                ImportedUsage { entry_id: minecraft.clone(), day: day(7), duration: mins(20) },
+            ]
+        );
+        // The latest day's limit
+        assert_eq!(plan.quotas, vec![(minecraft, mins(90))]);
+        assert_eq!(plan.unmapped, vec![("YouTube".to_string(), mins(45))]);
+    }
+}
@@ -0,0 +1,686 @@
+//! shepherdctl - command-line administration for shepherdd
+//!
+//! Talks to shepherdd over its IPC socket, so commands that change state
+//! need the admin role like the admin UI does. Everything the admin UI can
+//! do has a command here, so a household can be run over plain SSH.
+
+mod import;
+mod print;
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeZone};
+use clap::{Parser, Subcommand, ValueEnum};
+use shepherd_api::{
+    AuditVerification, Command, Locale, MockTimeOp, PolicyPatch, PolicyView, ResponsePayload, ResponseResult,
+    StopMode,
+};
+use shepherd_config::{Policy, load_config};
+use shepherd_ipc::IpcClient;
+use shepherd_store::{SqliteStore, Store};
+use shepherd_util::{EntryId, ProfileId, SeatId, SessionId, default_config_path, format_duration};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// shepherdctl - command-line administration for shepherdd
+#[derive(Parser, Debug)]
+#[command(name = "shepherdctl")]
+#[command(about = "Command-line administration for shepherdd", long_about = None)]
+struct Args {
+    /// Configuration file path (default: ~/.config/shepherd/config.toml)
+    #[arg(short, long, default_value_os_t = default_config_path())]
+    config: PathBuf,
+
+    /// Socket path override (or set SHEPHERD_SOCKET env var)
+    #[arg(short, long, env = "SHEPHERD_SOCKET")]
+    socket: Option<PathBuf>,
+
+    /// Print shepherdd's response as JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Action,
+}
+
+#[derive(Subcommand, Debug)]
+enum Action {
+    /// Show the running session, hold and stars
+    Status {
+        /// Another seat than seat0 ("seat1")
+        #[arg(long)]
+        seat: Option<SeatId>,
+    },
+
+    /// List entries and why blocked ones can't start
+    Entries {
+        /// Evaluate at this time instead of now ("18:30", "2025-01-06 18:30")
+        #[arg(long, value_parser = parse_time)]
+        at: Option<DateTime<Local>>,
+    },
+
+    /// Show today's usage against each quota and the windows still to come
+    Today,
+
+    /// List the programs connected to shepherdd
+    Clients,
+
+    /// Live dashboard of the session, today's usage, clients and events
+    Top,
+
+    /// Check shepherdd and its host, store and config
+    Health,
+
+    /// shepherdd's version, what its host supports and available updates
+    Capabilities,
+
+    /// Start an entry
+    Launch {
+        entry: EntryId,
+
+        /// Profile joining a joint session (repeat for more)
+        #[arg(long = "with")]
+        participants: Vec<ProfileId>,
+    },
+
+    /// Stop the running session
+    Stop {
+        /// Kill it at once instead of asking it to close
+        #[arg(long)]
+        force: bool,
+
+        /// Another seat than seat0 ("seat1")
+        #[arg(long)]
+        seat: Option<SeatId>,
+    },
+
+    /// Give the running session more time ("15", "1h 30m")
+    Extend {
+        #[arg(value_parser = parse_extension)]
+        by: Duration,
+
+        /// Another seat than seat0 ("seat1")
+        #[arg(long)]
+        seat: Option<SeatId>,
+    },
+
+    /// Block or allow all launches
+    Hold {
+        #[command(subcommand)]
+        action: HoldAction,
+    },
+
+    /// Grant reward stars
+    Reward {
+        stars: u32,
+
+        #[arg(long)]
+        reason: Option<String>,
+    },
+
+    /// Show or change the volume
+    Volume {
+        #[command(subcommand)]
+        action: Option<VolumeAction>,
+    },
+
+    /// List paired and connected controllers
+    Devices,
+
+    /// Show the host battery
+    Battery,
+
+    /// Show what the session's media player is playing
+    Media,
+
+    /// Show what happened during a session
+    Timeline { session: SessionId },
+
+    /// Cancel a pending curfew power off or suspend
+    CancelPower,
+
+    /// Probe every entry and disable broken ones
+    CheckEntries,
+
+    /// List apps installed on the host
+    Discover,
+
+    /// Reload the config file
+    Reload,
+
+    /// Show, replace or edit the config
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Move the daemon's clock (debug builds only)
+    MockTime {
+        #[command(subcommand)]
+        action: MockTimeAction,
+    },
+
+    /// Check that shepherdd answers
+    Ping,
+
+    /// Import usage history and app limits exported from another parental control
+    Import {
+        /// CSV or JSON export
+        file: PathBuf,
+
+        /// Service the export came from. Importing a later export from the
+        /// same service updates the days both cover instead of adding to them.
+        #[arg(long, value_enum)]
+        from: Source,
+
+        /// Also charge the usage to this profile
+        #[arg(long)]
+        profile: Option<ProfileId>,
+
+        /// Read dates like 06/01/2025 as day first
+        #[arg(long)]
+        day_first: bool,
+
+        /// Import usage only, leaving daily quotas as they are
+        #[arg(long)]
+        no_quotas: bool,
+
+        /// Show what would be imported without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Give keyboard and mouse back during a controller-only session
+    ReleaseInput {
+        /// Ask for the admin PIN, for accounts without the admin role
+        #[arg(long)]
+        pin: bool,
+    },
+
+    /// Check the audit log for tampering
+    Audit {
+        #[command(subcommand)]
+        action: AuditAction,
+    },
+
+    /// Print the JSON Schema of the IPC protocol
+    Schema,
+}
+
+#[derive(Subcommand, Debug)]
+enum AuditAction {
+    /// Check the audit log's hash chain for edited or deleted rows
+    Verify {
+        /// Head hash from an earlier check, to also catch rows removed
+        /// from the end or a rewritten log since
+        #[arg(long)]
+        head: Option<String>,
+
+        /// Check this database file instead of asking shepherdd, e.g. a copy
+        /// or while shepherdd is stopped
+        #[arg(long)]
+        db: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum HoldAction {
+    /// Block launches; a running session is left alone
+    On {
+        /// Shown on the launcher, like "Grounded until Friday"
+        #[arg(long)]
+        message: Option<String>,
+
+        /// Lift the hold by itself at this time
+        #[arg(long, value_parser = parse_time)]
+        until: Option<DateTime<Local>>,
+    },
+    /// Allow launches again
+    Off,
+}
+
+#[derive(Subcommand, Debug)]
+enum VolumeAction {
+    Set {
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=100))]
+        percent: u8,
+    },
+    Mute,
+    Unmute,
+    ToggleMute,
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Print the editable settings as JSON
+    Show,
+    /// Validate a config file, install it and load it
+    Save { file: PathBuf },
+    /// Apply edits from a JSON list of policy patches ("-" for stdin)
+    Patch { file: PathBuf },
+    /// Show entries as if the patches were applied, without saving
+    Simulate {
+        file: PathBuf,
+
+        #[arg(long, value_parser = parse_time)]
+        at: Option<DateTime<Local>>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum MockTimeAction {
+    /// Jump to a time
+    Set {
+        #[arg(value_parser = parse_time)]
+        time: DateTime<Local>,
+    },
+    /// Move forward, or back with a negative number
+    Advance {
+        #[arg(allow_hyphen_values = true)]
+        seconds: i64,
+    },
+    /// Stop the clock where it is
+    Freeze,
+    /// Let a frozen clock run again
+    Resume,
+    /// Return to the real clock
+    Clear,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Source {
+    /// Google Family Link
+    FamilyLink,
+    /// Microsoft Family Safety
+    FamilySafety,
+    /// Any other export with app, date and time used columns
+    Other,
+}
+
+impl Source {
+    fn name(self) -> &'static str {
+        match self {
+            Source::FamilyLink => "family-link",
+            Source::FamilySafety => "family-safety",
+            Source::Other => "other",
+        }
+    }
+}
+
+async fn send(socket_path: &Path, command: Command) -> Result<ResponsePayload> {
+    let mut client = IpcClient::connect(socket_path)
+        .await
+        .with_context(|| format!("Failed to connect to shepherdd at {:?}", socket_path))?;
+    match client.send(command).await?.result {
+        ResponseResult::Ok(payload) => Ok(payload),
+        ResponseResult::Err(e) => bail!("{}", e.message),
+    }
+}
+
+/// A time like "18:30" (today), "2025-01-06 18:30" or RFC 3339
+fn parse_time(text: &str) -> Result<DateTime<Local>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Local));
+    }
+    let local = if let Ok(time) = NaiveTime::parse_from_str(text, "%H:%M") {
+        shepherd_util::now().date_naive().and_time(time)
+    } else {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M")
+            .map_err(|_| format!("expected a time like 18:30 or 2025-01-06 18:30, got {:?}", text))?
+    };
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .ok_or_else(|| format!("{} doesn't exist in the local time zone", text))
+}
+
+fn parse_extension(text: &str) -> Result<Duration, String> {
+    match import::parse_minutes(text) {
+        Some(by) if !by.is_zero() => Ok(by),
+        _ => Err(format!("expected a duration like 15, 1:30 or 1h 30m, got {:?}", text)),
+    }
+}
+
+/// Read a file, or standard input for "-"
+fn read_input(file: &Path) -> Result<String> {
+    if file == Path::new("-") {
+        return std::io::read_to_string(std::io::stdin()).context("Failed to read standard input");
+    }
+    std::fs::read_to_string(file).with_context(|| format!("Failed to read {:?}", file))
+}
+
+fn read_patches(file: &Path) -> Result<Vec<PolicyPatch>> {
+    serde_json::from_str(&read_input(file)?).with_context(|| format!("{:?} is not a JSON list of policy patches", file))
+}
+
+fn show(payload: &ResponsePayload, json: bool) {
+    if json {
+        print::json(payload);
+    } else {
+        print::payload(payload);
+    }
+}
+
+/// Send a command and print its response
+async fn call(socket_path: &Path, command: Command, json: bool) -> Result<()> {
+    match send(socket_path, command).await? {
+        ResponsePayload::VolumeDenied { reason } => bail!("{}", reason),
+        payload => show(&payload, json),
+    }
+    Ok(())
+}
+
+/// Launch an entry, confirming right away if it asks first: the admin
+/// asking is the confirmation
+async fn launch(socket_path: &Path, entry_id: EntryId, participants: Vec<ProfileId>, json: bool) -> Result<()> {
+    let command = Command::Launch {
+        entry_id,
+        participants,
+        idempotency_key: None,
+    };
+    let payload = match send(socket_path, command).await? {
+        ResponsePayload::LaunchNeedsConfirmation { session_id, .. } => {
+            send(socket_path, Command::ConfirmLaunch { session_id }).await?
+        }
+        payload => payload,
+    };
+    if let ResponsePayload::LaunchDenied { reasons } = &payload
+        && !json
+    {
+        let locale = Locale::from_env();
+        let reasons: Vec<String> = reasons.iter().map(|r| r.message(locale)).collect();
+        bail!("Launch denied: {}", reasons.join("; "));
+    }
+    show(&payload, json);
+    Ok(())
+}
+
+async fn get_policy(socket_path: &Path) -> Result<PolicyView> {
+    match send(socket_path, Command::GetPolicy).await? {
+        ResponsePayload::Policy(policy) => Ok(policy),
+        other => bail!("Unexpected response: {:?}", other),
+    }
+}
+
+/// Limits edits giving entries their imported quotas, keeping their max run
+/// and cooldown
+fn quota_patches(plan: &import::ImportPlan, current: &PolicyView) -> Vec<PolicyPatch> {
+    plan.quotas
+        .iter()
+        .filter_map(|(entry_id, quota)| {
+            let entry = current.entries.iter().find(|e| &e.entry_id == entry_id)?;
+            (entry.daily_quota != Some(*quota)).then(|| PolicyPatch::SetLimits {
+                entry_id: entry_id.clone(),
+                max_run: entry.max_run,
+                daily_quota: Some(*quota),
+                cooldown: entry.cooldown,
+            })
+        })
+        .collect()
+}
+
+fn print_plan(plan: &import::ImportPlan, policy: &Policy) {
+    let mut totals: BTreeMap<&str, (Duration, usize)> = BTreeMap::new();
+    for usage in &plan.usage {
+        let total = totals.entry(usage.entry_id.as_str()).or_default();
+        total.0 += usage.duration;
+        total.1 += 1;
+    }
+    if let (Some(first), Some(last)) = (plan.usage.first(), plan.usage.last()) {
+        println!("Usage from {} to {}:", first.day, last.day);
+    }
+    for (entry_id, (used, days)) in &totals {
+        let label = policy
+            .entries
+            .iter()
+            .find(|e| e.id.as_str() == *entry_id)
+            .map_or(*entry_id, |e| e.label.as_str());
+        println!("  {:<24} {:>12} over {} day(s)", label, format_duration(*used), days);
+    }
+    for (entry_id, quota) in &plan.quotas {
+        println!("Daily quota for {}: {}", entry_id, format_duration(*quota));
+    }
+    if !plan.unmapped.is_empty() {
+        println!("Not imported, no matching entry (add them to [import.apps]):");
+        for (app, used) in &plan.unmapped {
+            println!("  {:<24} {:>12}", app, format_duration(*used));
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn import(
+    socket_path: &Path,
+    policy: &Policy,
+    file: &Path,
+    from: Source,
+    profile: Option<ProfileId>,
+    day_first: bool,
+    no_quotas: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let text = std::fs::read_to_string(file).with_context(|| format!("Failed to read {:?}", file))?;
+    let rows = import::parse_export(&text, day_first).with_context(|| format!("Failed to read {:?}", file))?;
+    let mut plan = import::plan(&rows, policy);
+    if no_quotas {
+        plan.quotas.clear();
+    }
+    print_plan(&plan, policy);
+    if plan.usage.is_empty() && plan.quotas.is_empty() {
+        bail!("Nothing to import");
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    if !plan.usage.is_empty() {
+        let command = Command::ImportUsage {
+            source: from.name().to_string(),
+            profile_id: profile,
+            usage: plan.usage.clone(),
+        };
+        match send(socket_path, command).await? {
+            ResponsePayload::UsageImported { changed } => println!("Imported usage, {} day total(s) changed", changed),
+            other => bail!("Unexpected response: {:?}", other),
+        }
+    }
+
+    if !plan.quotas.is_empty() {
+        let ops = quota_patches(&plan, &get_policy(socket_path).await?);
+        let count = ops.len();
+        if count > 0 {
+            match send(socket_path, Command::PatchPolicy { ops }).await? {
+                ResponsePayload::Policy(_) => {}
+                other => bail!("Unexpected response: {:?}", other),
+            }
+        }
+        println!("Updated {} daily quota(s)", count);
+    }
+    Ok(())
+}
+
+/// Read the admin PIN from the terminal or standard input
+fn read_pin() -> Result<String> {
+    eprint!("Admin PIN: ");
+    let mut pin = String::new();
+    std::io::stdin().read_line(&mut pin).context("Failed to read PIN")?;
+    Ok(pin.trim().to_string())
+}
+
+/// Print an audit log check, failing if the log was tampered with
+fn report_audit(report: &AuditVerification, json: bool) -> Result<()> {
+    if json {
+        print::json(report);
+    } else {
+        print::audit(report);
+    }
+    if !report.is_intact() {
+        bail!("The audit log has been tampered with");
+    }
+    Ok(())
+}
+
+async fn release_input(socket_path: &Path, ask_pin: bool) -> Result<()> {
+    let pin = if ask_pin { Some(read_pin()?) } else { None };
+    match send(socket_path, Command::ReleaseInput { pin }).await? {
+        ResponsePayload::InputReleased => println!("Keyboard and mouse released"),
+        other => bail!("Unexpected response: {:?}", other),
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    // Only needed for the socket path and imports, so an admin account
+    // without the config can still connect with --socket
+    let load_policy = || {
+        load_config(&args.config).with_context(|| format!("Failed to load config from {:?}", args.config))
+    };
+    // Neither needs shepherdd or its config
+    if let Action::Schema = args.command {
+        println!("{}", serde_json::to_string_pretty(&shepherd_api::protocol_schema())?);
+        return Ok(());
+    }
+    if let Action::Audit {
+        action: AuditAction::Verify { head, db: Some(db) },
+    } = &args.command
+    {
+        let store = SqliteStore::open_read_only(db).with_context(|| format!("Failed to open {:?}", db))?;
+        let report = store
+            .verify_audit(head.as_deref())
+            .with_context(|| format!("Failed to read the audit log in {:?}", db))?;
+        return report_audit(&report, args.json);
+    }
+
+    let socket_path = match &args.socket {
+        Some(socket_path) => socket_path.clone(),
+        None => load_policy()?.service.socket_path,
+    };
+
+    let json = args.json;
+    let socket = socket_path.as_path();
+    match args.command {
+        Action::Status { seat } => call(socket, Command::GetState { seat }, json).await,
+        Action::Entries { at } => call(socket, Command::ListEntries { at_time: at }, json).await,
+        Action::Today => call(socket, Command::GetDayOverview, json).await,
+        Action::Clients => call(socket, Command::ListClients, json).await,
+        Action::Top => shepherd_tui::run(socket).await,
+        Action::Health => call(socket, Command::GetHealth, json).await,
+        Action::Capabilities => call(socket, Command::GetCapabilities, json).await,
+        Action::Launch { entry, participants } => launch(socket, entry, participants, json).await,
+        Action::Stop { force, seat } => {
+            let mode = if force { StopMode::Force } else { StopMode::Graceful };
+            call(socket, Command::StopCurrent { mode, seat }, json).await
+        }
+        Action::Extend { by, seat } => call(socket, Command::ExtendCurrent { by, seat }, json).await,
+        Action::Hold { action } => {
+            let command = match action {
+                HoldAction::On { message, until } => Command::SetHold {
+                    enabled: true,
+                    message,
+                    until,
+                },
+                HoldAction::Off => Command::SetHold {
+                    enabled: false,
+                    message: None,
+                    until: None,
+                },
+            };
+            call(socket, command, json).await
+        }
+        Action::Reward { stars, reason } => call(socket, Command::GrantReward { stars, reason }, json).await,
+        Action::Volume { action } => {
+            let command = match action {
+                None => Command::GetVolume,
+                Some(VolumeAction::Set { percent }) => Command::SetVolume { percent },
+                Some(VolumeAction::Mute) => Command::SetMute { muted: true },
+                Some(VolumeAction::Unmute) => Command::SetMute { muted: false },
+                Some(VolumeAction::ToggleMute) => Command::ToggleMute,
+            };
+            call(socket, command, json).await
+        }
+        Action::Devices => call(socket, Command::GetInputDevices, json).await,
+        Action::Battery => call(socket, Command::GetBattery, json).await,
+        Action::Media => call(socket, Command::GetMedia, json).await,
+        Action::Timeline { session } => {
+            call(socket, Command::GetSessionTimeline { session_id: session }, json).await
+        }
+        Action::CancelPower => call(socket, Command::CancelPowerAction, json).await,
+        Action::CheckEntries => call(socket, Command::CheckEntryHealth, json).await,
+        Action::Discover => call(socket, Command::DiscoverApps, json).await,
+        Action::Reload => call(socket, Command::ReloadConfig, json).await,
+        Action::Config { action } => {
+            let command = match action {
+                ConfigAction::Show => Command::GetPolicy,
+                ConfigAction::Save { file } => Command::SaveConfig {
+                    config: read_input(&file)?,
+                },
+                ConfigAction::Patch { file } => Command::PatchPolicy {
+                    ops: read_patches(&file)?,
+                },
+                ConfigAction::Simulate { file, at } => Command::SimulatePolicy {
+                    ops: read_patches(&file)?,
+                    at_time: at,
+                },
+            };
+            call(socket, command, json).await
+        }
+        Action::MockTime { action } => {
+            let op = match action {
+                MockTimeAction::Set { time } => MockTimeOp::Set { time },
+                MockTimeAction::Advance { seconds } => MockTimeOp::Advance { seconds },
+                MockTimeAction::Freeze => MockTimeOp::Freeze,
+                MockTimeAction::Resume => MockTimeOp::Resume,
+                MockTimeAction::Clear => MockTimeOp::Clear,
+            };
+            call(socket, Command::SetMockTime { op }, json).await
+        }
+        Action::Ping => call(socket, Command::Ping, json).await,
+        Action::Import {
+            file,
+            from,
+            profile,
+            day_first,
+            no_quotas,
+            dry_run,
+        } => {
+            let policy = load_policy()?;
+            import(&socket_path, &policy, &file, from, profile, day_first, no_quotas, dry_run).await
+        }
+        Action::ReleaseInput { pin } => release_input(&socket_path, pin).await,
+        Action::Audit {
+            action: AuditAction::Verify { head, .. },
+        } => match send(socket, Command::VerifyAudit { known_head: head }).await? {
+            ResponsePayload::AuditVerified(report) => report_audit(&report, json),
+            other => bail!("Unexpected response: {:?}", other),
+        },
+        Action::Schema => unreachable!("printed without connecting"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    #[test]
+    fn test_parse_time() {
+        let evening = parse_time("18:30").unwrap();
+        assert_eq!((evening.hour(), evening.minute()), (18, 30));
+        assert_eq!(evening.date_naive(), shepherd_util::now().date_naive());
+
+        let friday = parse_time("2025-01-10 17:00").unwrap();
+        assert_eq!(friday.naive_local().to_string(), "2025-01-10 17:00:00");
+        assert!(parse_time("2025-01-10T17:00:00+01:00").is_ok());
+        assert!(parse_time("friday").is_err());
+    }
+
+    #[test]
+    fn test_parse_extension() {
+        assert_eq!(parse_extension("15"), Ok(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_extension("1h 30m"), Ok(Duration::from_secs(90 * 60)));
+        assert!(parse_extension("0").is_err());
+        assert!(parse_extension("soon").is_err());
+    }
+}
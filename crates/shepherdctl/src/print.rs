@@ -0,0 +1,308 @@
+//! Plain-text rendering of shepherdd responses
+//!
+//! Everything prints as short lines that read well over SSH and grep well
+//! in scripts; `--json` prints the response itself instead.
+
+use serde::Serialize;
+use shepherd_api::{
+    AuditVerification, ClientInfo, DayOverview, EntryView, HealthStatus, Hold, Locale, ResponsePayload,
+    ServiceCapabilities, ServiceStateSnapshot, SessionTimeline,
+};
+use shepherd_tui::format::{filled_cells, name, time};
+use shepherd_util::format_duration;
+use std::time::Duration;
+
+/// Width of the usage bars in `today`
+const BAR_WIDTH: usize = 20;
+
+fn limit(duration: Option<Duration>) -> String {
+    duration.map_or_else(|| "unlimited".to_string(), format_duration)
+}
+
+/// `used` out of `quota` as a bar of [`BAR_WIDTH`] cells
+pub fn bar(used: Duration, quota: Duration) -> String {
+    let filled = filled_cells(used, quota, BAR_WIDTH);
+    format!("{}{}", "#".repeat(filled), "-".repeat(BAR_WIDTH - filled))
+}
+
+fn print_hold(hold: &Option<Hold>) {
+    match hold {
+        Some(hold) => {
+            let until = hold.until.map_or_else(|| "turned off".to_string(), time);
+            println!("Launches on hold until {}", until);
+            if let Some(message) = &hold.message {
+                println!("  \"{}\"", message);
+            }
+        }
+        None => println!("Launches allowed"),
+    }
+}
+
+fn print_state(state: &ServiceStateSnapshot) {
+    match &state.current_session {
+        Some(session) => {
+            println!("Session:   {} ({}) on {}", session.label, session.entry_id, session.seat);
+            println!("  id       {}", session.session_id);
+            println!("  state    {}", name(&session.state));
+            println!("  started  {}", time(session.started_at));
+            println!("  elapsed  {}", format_duration(session.elapsed));
+            match (session.time_remaining, session.deadline) {
+                (Some(remaining), Some(deadline)) => {
+                    println!("  left     {} (until {})", format_duration(remaining), time(deadline))
+                }
+                _ => println!("  left     unlimited"),
+            }
+            if !session.participants.is_empty() {
+                let names: Vec<String> = session.participants.iter().map(ToString::to_string).collect();
+                println!("  with     {}", names.join(", "));
+            }
+        }
+        None => println!("Session:   none on {}", state.seat),
+    }
+    let available = state.entries.iter().filter(|e| e.enabled).count();
+    println!("Entries:   {} of {} available", available, state.entry_count);
+    println!("Stars:     {}", state.total_stars);
+    print_hold(&state.hold);
+    if let Some(power) = &state.pending_power_action {
+        println!("Pending {} at {}", name(&power.action), time(power.deadline));
+    }
+}
+
+fn print_entries(entries: &[EntryView]) {
+    let locale = Locale::from_env();
+    for entry in entries {
+        let status = if entry.enabled {
+            format!("available, {}", limit(entry.max_run_if_started_now))
+        } else {
+            let reasons: Vec<String> = entry.reasons.iter().map(|r| r.message(locale)).collect();
+            format!("blocked: {}", reasons.join("; "))
+        };
+        println!("{:<24} {:<24} {}", entry.entry_id.to_string(), entry.label, status);
+    }
+}
+
+fn print_day(day: &DayOverview) {
+    for category in &day.categories {
+        println!("{}", category.name.as_deref().unwrap_or("Other"));
+        for entry in &category.entries {
+            match entry.quota {
+                Some(quota) => println!(
+                    "  {:<24} [{}] {} of {}",
+                    entry.label,
+                    bar(entry.used, quota),
+                    format_duration(entry.used),
+                    format_duration(quota)
+                ),
+                None => println!("  {:<24} {:>22} {}", entry.label, "", format_duration(entry.used)),
+            }
+        }
+    }
+    if !day.windows.is_empty() {
+        println!("Still to come");
+        for window in &day.windows {
+            println!("  {}-{}  {}", time(window.start), time(window.end), window.labels.join(", "));
+        }
+    }
+}
+
+fn print_health(health: &HealthStatus) {
+    let ok = |ok: bool| if ok { "ok" } else { "FAILING" };
+    println!("Overall:   {}", if health.is_healthy() { "healthy" } else { "unhealthy" });
+    println!("Policy:    {}", ok(health.policy_loaded));
+    println!("Host:      {} ({})", ok(health.host_adapter_ok), name(&health.host_connection));
+    println!(
+        "Store:     {} ({} us, {} pending writes)",
+        ok(health.store_ok),
+        health.store.latency_us,
+        health.store.pending_writes
+    );
+    println!("Clients:   {}", health.ipc_clients);
+    println!("Ticks:     {}, last took {} us", health.tick.ticks, health.tick.last_duration_us);
+    if let Some(modified) = health.config_modified {
+        println!("Config:    modified {}", time(modified));
+    }
+    if let Some(update) = &health.update_available {
+        let staged = if update.staged { ", staged" } else { "" };
+        println!("Update:    {} -> {}{}", update.current_version, update.available_version, staged);
+    }
+    for warning in &health.config_warnings {
+        println!("Warning:   {}", warning);
+    }
+    for route in &health.escape_routes {
+        let mitigated = if route.mitigated { " (closed)" } else { "" };
+        println!("Escape:    {}: {}{}", name(&route.kind), route.detail, mitigated);
+    }
+}
+
+fn print_capabilities(caps: &ServiceCapabilities) {
+    let kinds: Vec<_> = caps.spawn_kinds.iter().map(name).collect();
+    let features: Vec<_> = [
+        ("freeze", caps.can_freeze_session),
+        ("input", caps.can_inhibit_input),
+        ("battery", caps.can_read_battery),
+        ("idle", caps.can_detect_idle),
+        ("media", caps.can_control_media),
+    ]
+    .into_iter()
+    .filter_map(|(feature, supported)| supported.then_some(feature))
+    .collect();
+    println!("Version:   {}", caps.version);
+    println!("Launches:  {}", kinds.join(", "));
+    println!("Features:  {}", if features.is_empty() { "-".to_string() } else { features.join(", ") });
+    if let Some(update) = &caps.update_available {
+        let staged = if update.staged { ", staged" } else { "" };
+        println!("Update:    {} -> {}{}", update.current_version, update.available_version, staged);
+    }
+}
+
+fn print_timeline(timeline: &SessionTimeline) {
+    println!("Session {}", timeline.session_id);
+    for event in &timeline.events {
+        let mut details = serde_json::to_value(&event.kind).unwrap_or_default();
+        let kind = details
+            .as_object_mut()
+            .and_then(|fields| fields.remove("type"))
+            .and_then(|kind| kind.as_str().map(str::to_string))
+            .unwrap_or_default();
+        println!("  {}  {:<12} {}", event.timestamp.format("%H:%M:%S"), kind, details);
+    }
+}
+
+fn print_clients(clients: &[ClientInfo]) {
+    println!("{:<8} {:<24} {:<6} {:<9} {:<9} SEAT", "PID", "PROGRAM", "UID", "ROLE", "KIND");
+    for client in clients {
+        let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:<8} {:<24} {:<6} {:<9} {:<9} {}",
+            or_dash(client.pid.map(|p| p.to_string())),
+            or_dash(client.process_name.clone()),
+            or_dash(client.uid.map(|u| u.to_string())),
+            name(&client.role),
+            or_dash(client.kind.map(|k| name(&k))),
+            client.seat.clone().unwrap_or_default()
+        );
+    }
+}
+
+pub fn audit(report: &AuditVerification) {
+    let state = if report.is_intact() { "intact" } else { "TAMPERED WITH" };
+    println!("Audit log: {} rows, {}", report.rows, state);
+    if report.unhashed > 0 {
+        println!("  {} row(s) from before hashing weren't checked", report.unhashed);
+    }
+    if !report.mismatched.is_empty() {
+        let ids: Vec<String> = report.mismatched.iter().map(ToString::to_string).collect();
+        println!("  Edited, or right after deleted rows: {}", ids.join(", "));
+    }
+    match report.known_head_found {
+        Some(true) => println!("  Earlier head is still in the log"),
+        Some(false) => println!("  Earlier head is gone: rows were removed from the end or the log was rewritten"),
+        None => {}
+    }
+    if let Some(head) = &report.head {
+        println!("  Head {}", head);
+    }
+}
+
+/// Print a successful response
+pub fn payload(payload: &ResponsePayload) {
+    match payload {
+        ResponsePayload::State(state) => print_state(state),
+        ResponsePayload::Entries { entries } => print_entries(entries),
+        ResponsePayload::LaunchApproved { session_id, deadline } => {
+            let ends = deadline.map_or_else(|| "no time limit".to_string(), |d| format!("ends at {}", time(d)));
+            println!("Launched session {}, {}", session_id, ends);
+        }
+        ResponsePayload::Stopped => println!("Session stopped"),
+        ResponsePayload::ConfigReloaded => println!("Configuration reloaded"),
+        ResponsePayload::Health(health) => print_health(health),
+        ResponsePayload::Capabilities(caps) => print_capabilities(caps),
+        ResponsePayload::Extended { new_deadline } => match new_deadline {
+            Some(deadline) => println!("Session now ends at {}", time(*deadline)),
+            None => println!("Session has no time limit"),
+        },
+        ResponsePayload::Volume(volume) => {
+            if !volume.available {
+                println!("No volume control");
+                return;
+            }
+            let muted = if volume.muted { ", muted" } else { "" };
+            println!(
+                "Volume {}%{} ({}, {})",
+                volume.percent,
+                muted,
+                volume.backend.as_deref().unwrap_or("unknown backend"),
+                name(&volume.output)
+            );
+        }
+        ResponsePayload::VolumeSet => println!("Volume set"),
+        ResponsePayload::InputDevices { devices } => {
+            for device in devices {
+                let state = if device.connected { "connected" } else if device.paired { "paired" } else { "seen" };
+                let battery = device.battery_percent.map(|p| format!(", battery {}%", p)).unwrap_or_default();
+                println!("{:<28} {:<18} {:<8} {}{}", device.name, device.address, name(&device.kind), state, battery);
+            }
+        }
+        ResponsePayload::PowerActionCancelled => println!("Power action cancelled"),
+        ResponsePayload::InputReleased => println!("Keyboard and mouse released"),
+        ResponsePayload::RewardGranted { total_stars } => println!("{} star(s) in total", total_stars),
+        ResponsePayload::HoldSet { hold } => print_hold(hold),
+        ResponsePayload::EntryHealth { entries } => {
+            for entry in entries {
+                println!("{:<24} {}", entry.entry_id.to_string(), entry.problem.as_deref().unwrap_or("ok"));
+            }
+        }
+        ResponsePayload::DiscoveredApps { apps } => {
+            for app in apps {
+                println!("{:<32} {}", app.id, app.label);
+            }
+        }
+        ResponsePayload::ConfigSaved { entry_count } => println!("Configuration saved, {} entries", entry_count),
+        ResponsePayload::Battery { battery, low } => {
+            let percent = battery.percent.map_or_else(|| "unknown".to_string(), |p| format!("{}%", p));
+            let charging = if battery.charging { ", charging" } else if battery.ac_connected { ", on AC" } else { "" };
+            let low = if *low { " (low)" } else { "" };
+            println!("Battery {}{}{}", percent, charging, low);
+        }
+        ResponsePayload::MockTime { now, frozen } => {
+            let frozen = if *frozen { " (frozen)" } else { "" };
+            println!("Clock at {}{}", now.format("%Y-%m-%d %H:%M:%S"), frozen);
+        }
+        ResponsePayload::SessionTimeline(timeline) => print_timeline(timeline),
+        ResponsePayload::DayOverview(day) => print_day(day),
+        ResponsePayload::Media { media } => match media {
+            Some(media) => {
+                let title = media.title.as_deref().unwrap_or("untitled");
+                let artist = media.artist.as_deref().map(|a| format!(" by {}", a)).unwrap_or_default();
+                println!("{} {}{} ({})", name(&media.status), title, artist, media.player);
+            }
+            None => println!("No media player"),
+        },
+        ResponsePayload::UsageImported { changed } => println!("Imported usage, {} day total(s) changed", changed),
+        ResponsePayload::Clients { clients } => print_clients(clients),
+        ResponsePayload::AuditVerified(report) => audit(report),
+        ResponsePayload::Pong => println!("shepherdd is up"),
+        other => json(other),
+    }
+}
+
+pub fn json(value: &impl Serialize) {
+    match serde_json::to_string_pretty(value) {
+        Ok(text) => println!("{}", text),
+        Err(e) => eprintln!("Failed to print response: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bar() {
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+        assert_eq!(bar(minutes(0), minutes(60)), "-".repeat(20));
+        assert_eq!(bar(minutes(30), minutes(60)), format!("{}{}", "#".repeat(10), "-".repeat(10)));
+        assert_eq!(bar(minutes(90), minutes(60)), "#".repeat(20));
+        assert_eq!(bar(minutes(5), Duration::ZERO), "#".repeat(20));
+    }
+}
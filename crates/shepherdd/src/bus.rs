@@ -0,0 +1,351 @@
+//! Internal event bus
+//!
+//! The main loop and command handlers publish what happened as
+//! [`DaemonEvent`]s; small subscriber tasks (see `subscribers`) turn them
+//! into IPC broadcasts, display changes and audit records. A new subsystem,
+//! such as notifications or webhooks, subscribes here instead of adding
+//! another arm to the select loop.
+//!
+//! Events published while handling a client request carry that request
+//! (see [`EventBus::caused_by`]) so clients and logs can trace them back.
+//!
+//! Enforcement that must not wait behind a slow subscriber (stopping an
+//! expired session, powering off at curfew) stays in the main loop.
+
+use shepherd_api::{ClientInfo, Correlation, EventPayload, MediaInfo, VolumeRestrictions};
+use shepherd_ipc::ClientReplaced;
+use shepherd_core::{CoreEvent, StopResult};
+use shepherd_util::{ClientId, ProfileId, SeatId, SessionId};
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
+/// Events buffered per subscriber before the slowest one starts losing them
+const CAPACITY: usize = 1024;
+
+/// Something that happened inside the daemon
+#[derive(Debug, Clone)]
+pub enum DaemonEvent {
+    /// Emitted by the core engine, including sessions ended by host exits
+    Core(CoreEvent),
+    /// Engine state changed without a core event (rewards, config edits, clock changes)
+    StateChanged,
+    /// Output volume or mute changed
+    VolumeChanged { percent: u8, muted: bool },
+    /// The volume restrictions in effect changed without a session starting or ending
+    VolumeRestrictionsChanged(VolumeRestrictions),
+    /// What the session's media player is playing changed
+    MediaChanged(Option<MediaInfo>),
+    ClientConnected { client_id: ClientId, info: ClientInfo },
+    ClientDisconnected { client_id: ClientId },
+    /// A newer launcher, HUD or shell demoted an older one to observer
+    ClientReplaced(ClientReplaced),
+    /// A profile used `percent` of its weekly budget (see `reporting`)
+    BudgetAlert {
+        profile_id: ProfileId,
+        label: String,
+        percent: u8,
+        used: Duration,
+        budget: Duration,
+    },
+}
+
+impl DaemonEvent {
+    /// `SessionEnded` for a session the daemon stopped itself
+    pub fn session_ended(result: StopResult) -> Self {
+        Self::Core(CoreEvent::SessionEnded {
+            session_id: result.session_id,
+            entry_id: result.entry_id,
+            seat: result.seat,
+            reason: result.reason,
+            duration: result.duration,
+            summary: result.summary,
+        })
+    }
+}
+
+/// A published event and the request that caused it
+#[derive(Debug, Clone)]
+pub struct Published {
+    pub event: DaemonEvent,
+    pub cause: Option<Correlation>,
+}
+
+/// Fan-out of [`DaemonEvent`]s to every subscriber, in publish order
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<Published>,
+    cause: Option<Correlation>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(CAPACITY);
+        Self { tx, cause: None }
+    }
+
+    /// A handle to the same bus whose events are attributed to `cause`
+    pub fn caused_by(&self, cause: Correlation) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            cause: Some(cause),
+        }
+    }
+
+    pub fn publish(&self, event: DaemonEvent) {
+        // No subscribers is fine, e.g. during startup
+        let _ = self.tx.send(Published {
+            event,
+            cause: self.cause.clone(),
+        });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Published> {
+        self.tx.subscribe()
+    }
+
+    /// Run `handler` for every event on its own task until the bus is dropped.
+    ///
+    /// Events are handled one at a time; a subscriber that falls too far
+    /// behind skips the oldest ones and logs how many it missed.
+    pub fn spawn_subscriber<F, Fut>(&self, name: &'static str, mut handler: F)
+    where
+        F: FnMut(Published) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let mut rx = self.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => handler(event).await,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(subscriber = name, skipped, "Event subscriber fell behind");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The client-facing event for a core event, if clients see it at all
+pub fn ipc_payload(event: &CoreEvent) -> Option<EventPayload> {
+    let payload = match event {
+        CoreEvent::Warning {
+            session_id,
+            threshold_seconds,
+            time_remaining,
+            severity,
+            message,
+        } => EventPayload::WarningIssued {
+            session_id: session_id.clone(),
+            threshold_seconds: *threshold_seconds,
+            time_remaining: *time_remaining,
+            severity: *severity,
+            message: message.clone(),
+        },
+        CoreEvent::TimeRemaining {
+            session_id,
+            time_remaining,
+            deadline,
+        } => EventPayload::TimeRemaining {
+            session_id: session_id.clone(),
+            time_remaining: *time_remaining,
+            deadline: *deadline,
+        },
+        // Lets shells show the "time is up" overlay for the grace window
+        CoreEvent::ExpireDue { session_id } => EventPayload::SessionExpiring {
+            session_id: session_id.clone(),
+        },
+        CoreEvent::SessionStarted {
+            session_id,
+            entry_id,
+            label,
+            deadline,
+            device_access,
+            ..
+        } => EventPayload::SessionStarted {
+            session_id: session_id.clone(),
+            entry_id: entry_id.clone(),
+            label: label.clone(),
+            deadline: *deadline,
+            device_access: *device_access,
+        },
+        CoreEvent::SessionEnded {
+            session_id,
+            entry_id,
+            reason,
+            duration,
+            ..
+        } => EventPayload::SessionEnded {
+            session_id: session_id.clone(),
+            entry_id: entry_id.clone(),
+            reason: reason.clone(),
+            duration: *duration,
+        },
+        CoreEvent::PolicyReloaded { entry_count } => EventPayload::PolicyReloaded {
+            entry_count: *entry_count,
+        },
+        CoreEvent::EntryAvailabilityChanged { entry_id, enabled } => {
+            EventPayload::EntryAvailabilityChanged {
+                entry_id: entry_id.clone(),
+                enabled: *enabled,
+            }
+        }
+        CoreEvent::BatteryStatus { battery, low } => EventPayload::BatteryStatus {
+            battery: *battery,
+            low: *low,
+        },
+        CoreEvent::PowerActionScheduled(pending) => EventPayload::PowerActionScheduled {
+            action: pending.action,
+            deadline: pending.deadline,
+        },
+        CoreEvent::PowerActionCancelled => EventPayload::PowerActionCancelled,
+        CoreEvent::ThemeChanged(theme) => EventPayload::ThemeChanged { theme: *theme },
+        // Admin clients should know a session is out of control
+        CoreEvent::EnforcementFailed {
+            session_id,
+            entry_id,
+        } => EventPayload::AuditEntry {
+            event_type: "enforcement_failed".into(),
+            details: serde_json::json!({
+                "session_id": session_id,
+                "entry_id": entry_id,
+            }),
+        },
+        CoreEvent::DisplaySettingsChanged(_)
+        | CoreEvent::QuietHoursChanged(_)
+        | CoreEvent::EnforcementRetry { .. }
+        | CoreEvent::LowBatteryStop { .. }
+        | CoreEvent::PowerActionDue { .. }
+        | CoreEvent::AutostartDue { .. }
+        | CoreEvent::AvailabilitySetChanged => return None,
+    };
+    Some(payload)
+}
+
+/// The session a core event is about, if any
+pub fn event_session(event: &CoreEvent) -> Option<&SessionId> {
+    match event {
+        CoreEvent::SessionStarted { session_id, .. }
+        | CoreEvent::SessionEnded { session_id, .. }
+        | CoreEvent::Warning { session_id, .. }
+        | CoreEvent::TimeRemaining { session_id, .. }
+        | CoreEvent::ExpireDue { session_id }
+        | CoreEvent::EnforcementRetry { session_id, .. }
+        | CoreEvent::EnforcementFailed { session_id, .. }
+        | CoreEvent::LowBatteryStop { session_id } => Some(session_id),
+        _ => None,
+    }
+}
+
+/// The seat a core event names. Other session events are on their
+/// session's seat, which the engine knows while it runs.
+pub fn event_seat(event: &CoreEvent) -> Option<&SeatId> {
+    match event {
+        CoreEvent::SessionStarted { seat, .. } | CoreEvent::SessionEnded { seat, .. } => Some(seat),
+        _ => None,
+    }
+}
+
+/// Whether clients need a fresh state snapshot after this event
+pub fn refreshes_state(event: &DaemonEvent) -> bool {
+    matches!(
+        event,
+        DaemonEvent::StateChanged
+            | DaemonEvent::Core(CoreEvent::SessionEnded { .. })
+            | DaemonEvent::Core(CoreEvent::AvailabilitySetChanged)
+            | DaemonEvent::Core(CoreEvent::PolicyReloaded { .. })
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shepherd_api::{SessionEndReason, SessionSummary};
+    use shepherd_util::{EntryId, SessionId};
+    use std::time::Duration;
+
+    fn ended() -> DaemonEvent {
+        let (session_id, entry_id) = (SessionId::new(), EntryId::new("game"));
+        DaemonEvent::session_ended(StopResult {
+            session_id: session_id.clone(),
+            entry_id: entry_id.clone(),
+            seat: SeatId::default(),
+            reason: SessionEndReason::UserStop,
+            duration: Duration::from_secs(60),
+            summary: Box::new(SessionSummary {
+                session_id,
+                entry_id,
+                label: "Game".into(),
+                reason: SessionEndReason::UserStop,
+                duration: Duration::from_secs(60),
+                quota_used: Duration::from_secs(60),
+                remaining_today: None,
+                warnings_shown: vec![],
+                next_available: None,
+                show_for: Duration::from_secs(15),
+            }),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_see_events_in_order() {
+        let bus = EventBus::new();
+        let mut a = bus.subscribe();
+        let mut b = bus.subscribe();
+
+        bus.publish(DaemonEvent::StateChanged);
+        bus.publish(ended());
+
+        for rx in [&mut a, &mut b] {
+            assert!(matches!(rx.recv().await.unwrap().event, DaemonEvent::StateChanged));
+            assert!(matches!(
+                rx.recv().await.unwrap().event,
+                DaemonEvent::Core(CoreEvent::SessionEnded { .. })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_caused_by() {
+        let bus = EventBus::new();
+        let mut rx = bus.subscribe();
+        let cause = Correlation {
+            client_id: ClientId::new(),
+            request_id: 3,
+        };
+
+        bus.caused_by(cause.clone()).publish(ended());
+        bus.publish(DaemonEvent::StateChanged);
+
+        assert_eq!(rx.recv().await.unwrap().cause, Some(cause));
+        assert_eq!(rx.recv().await.unwrap().cause, None);
+    }
+
+    #[test]
+    fn test_ipc_payload() {
+        let DaemonEvent::Core(event) = ended() else {
+            unreachable!();
+        };
+        assert!(matches!(
+            ipc_payload(&event),
+            Some(EventPayload::SessionEnded { .. })
+        ));
+        assert!(ipc_payload(&CoreEvent::AvailabilitySetChanged).is_none());
+
+        assert!(refreshes_state(&ended()));
+        assert!(refreshes_state(&DaemonEvent::Core(CoreEvent::AvailabilitySetChanged)));
+        assert!(!refreshes_state(&DaemonEvent::VolumeChanged {
+            percent: 50,
+            muted: false
+        }));
+    }
+}
@@ -0,0 +1,39 @@
+//! Polling the family calendar
+//!
+//! The feed is an ICS file, fetched like the release feed: `curl` for
+//! http(s), the filesystem for `file://` URLs and plain paths. CalDAV
+//! servers are read through their ICS export URL. A feed that can't be
+//! fetched or isn't a calendar leaves the previous exceptions in place.
+
+use anyhow::{bail, Result};
+use shepherd_config::CalendarPolicy;
+use shepherd_core::{parse_ics, CalendarException};
+use shepherd_util::Timezone;
+use std::time::Duration;
+
+use crate::self_update::fetch;
+
+#[derive(Debug)]
+pub struct CalendarFeed {
+    policy: CalendarPolicy,
+}
+
+impl CalendarFeed {
+    pub fn new(policy: CalendarPolicy) -> Self {
+        Self { policy }
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        self.policy.poll_interval
+    }
+
+    /// Fetch the feed and read its exceptions; floating times are in `tz`
+    pub async fn fetch(&self, tz: &Timezone) -> Result<Vec<CalendarException>> {
+        let data = fetch(&self.policy.url).await?;
+        let text = String::from_utf8_lossy(&data);
+        if !text.contains("BEGIN:VCALENDAR") {
+            bail!("{} is not an iCalendar feed", self.policy.url);
+        }
+        Ok(parse_ics(&text, tz))
+    }
+}
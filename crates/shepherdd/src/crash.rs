@@ -0,0 +1,102 @@
+//! Crash cleanup
+//!
+//! A panic anywhere in shepherdd, including inside a spawned task, ends the
+//! process, since the engine's state can't be trusted afterwards. On the way
+//! out the hook kills running sessions so a game isn't left running
+//! unmanaged, records the crash in the audit log and removes the IPC socket.
+
+use shepherd_host_api::HostAdapter;
+use shepherd_ipc::IpcServer;
+use shepherd_store::{AuditEvent, AuditEventType, Store};
+use std::any::Any;
+use std::panic::Location;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+use tracing::error;
+
+/// Exit code after a panic, matching Rust's default for a panicking main thread
+const EXIT_CODE: i32 = 101;
+
+/// How long to wait for the store; it may be locked by the panicking thread
+const AUDIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+static CRASHING: AtomicBool = AtomicBool::new(false);
+
+/// Replace the panic hook with one that cleans up and exits.
+///
+/// The default hook still runs first, so the message and backtrace are
+/// printed as usual.
+pub fn install(host: Arc<dyn HostAdapter>, store: Arc<dyn Store>, ipc: Arc<IpcServer>) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+
+        // A second panic during cleanup, or in another thread meanwhile
+        if CRASHING.swap(true, Ordering::SeqCst) {
+            std::process::abort();
+        }
+
+        let message = panic_message(info.payload(), info.location());
+        let sessions_killed = host.kill_all_now();
+        error!(message = %message, sessions_killed, "shepherdd panicked, sessions killed");
+
+        write_audit(
+            store.clone(),
+            AuditEvent::new(AuditEventType::ServiceCrashed {
+                message,
+                sessions_killed,
+            }),
+        );
+        ipc.shutdown();
+
+        std::process::exit(EXIT_CODE);
+    }));
+}
+
+/// Append from another thread so a store lock held by the panicking thread
+/// can't hang the exit
+fn write_audit(store: Arc<dyn Store>, event: AuditEvent) {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(store.append_audit(event));
+    });
+    match rx.recv_timeout(AUDIT_TIMEOUT) {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => error!(error = %e, "Failed to record crash"),
+        Err(_) => error!("Store unavailable, crash not recorded"),
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send), location: Option<&Location<'_>>) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload");
+    match location {
+        Some(location) => format!("{} at {}", message, location),
+        None => message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_message() {
+        let payload: Box<dyn Any + Send> = Box::new("boom");
+        assert_eq!(panic_message(payload.as_ref(), None), "boom");
+
+        let payload: Box<dyn Any + Send> = Box::new(format!("bad entry {}", 3));
+        let location = Location::caller();
+        assert_eq!(
+            panic_message(payload.as_ref(), Some(location)),
+            format!("bad entry 3 at {}", location)
+        );
+
+        let payload: Box<dyn Any + Send> = Box::new(42);
+        assert_eq!(panic_message(payload.as_ref(), None), "non-string panic payload");
+    }
+}
@@ -0,0 +1,51 @@
+//! Dev sandbox
+//!
+//! `shepherdd --dev-sandbox` runs the real service against a fake backend,
+//! so the launcher and HUD can be worked on without touching the system: a
+//! built-in demo policy, a host that only pretends to launch entries, fake
+//! volume and brightness, an in-memory store, and mock time control even in
+//! release builds.
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+/// The demo policy
+pub const DEMO_POLICY: &str = include_str!("dev_sandbox.toml");
+
+/// Scratch directory for this run. The demo policy is written here so
+/// SaveConfig and PatchPolicy have a file to work on.
+pub fn scratch_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("shepherdd-dev-sandbox-{}", std::process::id()))
+}
+
+/// Write the demo policy into the scratch directory, returning its path
+pub fn write_demo_config() -> Result<PathBuf> {
+    let dir = scratch_dir();
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create dev sandbox directory {:?}", dir))?;
+    let path = dir.join("config.toml");
+    std::fs::write(&path, DEMO_POLICY)
+        .with_context(|| format!("Failed to write demo config to {:?}", path))?;
+    Ok(path)
+}
+
+/// Remove the scratch directory
+pub fn cleanup() {
+    let _ = std::fs::remove_dir_all(scratch_dir());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_demo_policy_is_valid() {
+        let policy = shepherd_config::parse_config(DEMO_POLICY).unwrap();
+        assert!(policy.entries.len() >= 4);
+        // The mock host can only pretend to run processes
+        assert!(policy
+            .entries
+            .iter()
+            .all(|e| matches!(e.kind, shepherd_api::EntryKind::Process { .. })));
+    }
+}
@@ -0,0 +1,329 @@
+//! Usage sharing between devices
+//!
+//! With `[service.federation]`, shepherdd exchanges usage totals over TCP
+//! with shepherdd on the family's other devices (a tablet, a second PC), so
+//! an entry's daily quota and a profile's budgets count time spent on all of
+//! them. Entries and profiles are matched by ID.
+//!
+//! Every `sync_interval` this device connects to each peer and sends its
+//! usage for the last [`SYNC_DAYS`] days: its own totals and the ones it
+//! merged from other devices, so usage also reaches devices that aren't
+//! peers of each other. The peer merges them and answers with its own. Each
+//! total belongs to one device and only grows, so merging keeps the larger
+//! one (see [`Store::merge_usage_records`]); syncs are safe to repeat,
+//! reorder or lose.
+//!
+//! Messages are one line of JSON each, signed with HMAC-SHA256 over the
+//! shared secret, so a forged total that could lock a child out is turned
+//! away. They are not encrypted, so anyone on the path can read the usage
+//! and drop syncs. Federation is meant for a trusted link: the home network
+//! or a VPN between the devices. Config validation refuses public IP
+//! addresses for `listen` and `peers`. Usage is recorded as sessions end,
+//! so a session on another device counts once it's over.
+
+use anyhow::{bail, Context, Result};
+use chrono::Days;
+use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use shepherd_config::FederationPolicy;
+use shepherd_core::CoreEngine;
+use shepherd_store::{Store, UsageRecord};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Mutex, Notify};
+use tracing::{debug, info, warn};
+
+use crate::bus::{DaemonEvent, EventBus};
+
+/// Days of usage sent on every sync, today included
+const SYNC_DAYS: u64 = 7;
+
+/// Longest a connection may take to connect, send or answer
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest message accepted, in bytes
+const MAX_MESSAGE: u64 = 4 * 1024 * 1024;
+
+/// What goes over the wire: a [`SyncMessage`] as JSON, and its signature
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    body: String,
+    mac: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SyncMessage {
+    /// Sending device
+    device: String,
+    records: Vec<UsageRecord>,
+}
+
+fn mac(secret: &str, body: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(body.as_bytes());
+    mac
+}
+
+/// HMAC-SHA256 of `body` under `secret`, hex-encoded
+fn sign(secret: &str, body: &str) -> String {
+    mac(secret, body)
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Check a hex-encoded signature in constant time, so timing doesn't
+/// reveal how much of a forged one was right
+fn verify(secret: &str, body: &str, signature: &str) -> bool {
+    if !signature.len().is_multiple_of(2) {
+        return false;
+    }
+    let bytes: Option<Vec<u8>> = (0..signature.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(signature.get(i..i + 2)?, 16).ok())
+        .collect();
+    bytes.is_some_and(|bytes| mac(secret, body).verify_slice(&bytes).is_ok())
+}
+
+async fn send(stream: &mut (impl AsyncWrite + Unpin), secret: &str, message: &SyncMessage) -> Result<()> {
+    let body = serde_json::to_string(message)?;
+    let envelope = Envelope {
+        mac: sign(secret, &body),
+        body,
+    };
+    let mut line = serde_json::to_vec(&envelope)?;
+    line.push(b'\n');
+    stream.write_all(&line).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+async fn receive(stream: &mut (impl AsyncRead + Unpin), secret: &str) -> Result<SyncMessage> {
+    let mut line = String::new();
+    BufReader::new(stream.take(MAX_MESSAGE))
+        .read_line(&mut line)
+        .await?;
+    if !line.ends_with('\n') {
+        bail!("Message truncated or larger than {} bytes", MAX_MESSAGE);
+    }
+    let envelope: Envelope = serde_json::from_str(&line).context("Malformed message")?;
+    if !verify(secret, &envelope.body, &envelope.mac) {
+        bail!("Bad signature; is the secret the same on both devices?");
+    }
+    serde_json::from_str(&envelope.body).context("Malformed message body")
+}
+
+/// One device's side of usage sharing
+pub struct Federation {
+    policy: FederationPolicy,
+    store: Arc<dyn Store>,
+    /// Notified after a sync changed any usage total
+    merged: Notify,
+}
+
+impl Federation {
+    pub fn new(policy: FederationPolicy, store: Arc<dyn Store>) -> Self {
+        Self {
+            policy,
+            store,
+            merged: Notify::new(),
+        }
+    }
+
+    /// Usage to send, leaving out what `peer` recorded itself
+    fn outgoing(&self, peer: Option<&str>) -> SyncMessage {
+        let today = shepherd_util::now().date_naive();
+        let since = today - Days::new(SYNC_DAYS - 1);
+        let records = self
+            .store
+            .usage_records_since(&self.policy.device, since)
+            .unwrap_or_else(|e| {
+                warn!(error = %e, "Failed to read usage to share");
+                Vec::new()
+            })
+            .into_iter()
+            .filter(|record| Some(record.device.as_str()) != peer)
+            .collect();
+        SyncMessage {
+            device: self.policy.device.clone(),
+            records,
+        }
+    }
+
+    /// Merge a peer's usage, returning how many totals changed
+    fn merge(&self, message: &SyncMessage) -> Result<usize> {
+        if message.device == self.policy.device {
+            bail!("Peer is also named {:?}; every device needs its own name", message.device);
+        }
+        let changed = self
+            .store
+            .merge_usage_records(&self.policy.device, &message.records)?;
+        if changed > 0 {
+            self.merged.notify_one();
+        }
+        Ok(changed)
+    }
+
+    /// Send usage to `peer` and merge its answer
+    pub async fn sync_with(&self, peer: &str) -> Result<usize> {
+        tokio::time::timeout(IO_TIMEOUT, async {
+            let mut stream = TcpStream::connect(peer).await?;
+            send(&mut stream, &self.policy.secret, &self.outgoing(None)).await?;
+            let answer = receive(&mut stream, &self.policy.secret).await?;
+            self.merge(&answer)
+        })
+        .await
+        .context("Timed out")?
+    }
+
+    /// Answer one peer that connected to us
+    async fn answer(&self, mut stream: TcpStream) -> Result<usize> {
+        tokio::time::timeout(IO_TIMEOUT, async {
+            let message = receive(&mut stream, &self.policy.secret).await?;
+            let changed = self.merge(&message)?;
+            send(&mut stream, &self.policy.secret, &self.outgoing(Some(&message.device))).await?;
+            Ok(changed)
+        })
+        .await
+        .context("Timed out")?
+    }
+
+    /// Answer peers connecting to `listener`, forever
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) {
+        loop {
+            let (stream, addr) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!(error = %e, "Failed to accept federation peer");
+                    continue;
+                }
+            };
+            let federation = self.clone();
+            tokio::spawn(async move {
+                match federation.answer(stream).await {
+                    Ok(changed) => debug!(%addr, changed, "Federation peer synced"),
+                    Err(e) => warn!(%addr, error = format!("{:#}", e), "Federation sync failed"),
+                }
+            });
+        }
+    }
+
+    /// Sync with every configured peer each `sync_interval`, forever
+    async fn sync_peers(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(self.policy.sync_interval);
+        loop {
+            interval.tick().await;
+            for peer in &self.policy.peers {
+                match self.sync_with(peer).await {
+                    Ok(changed) => debug!(peer, changed, "Synced usage with peer"),
+                    Err(e) => warn!(peer, error = format!("{:#}", e), "Federation sync failed"),
+                }
+            }
+        }
+    }
+}
+
+/// Start sharing usage with the configured peers
+pub async fn spawn(
+    policy: FederationPolicy,
+    store: Arc<dyn Store>,
+    bus: EventBus,
+    engine: Arc<Mutex<CoreEngine>>,
+) -> Result<()> {
+    let listen = policy.listen;
+    let federation = Arc::new(Federation::new(policy, store));
+
+    if let Some(addr) = listen {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to listen for federation peers on {}", addr))?;
+        info!(%addr, "Accepting federation peers");
+        tokio::spawn(federation.clone().serve(listener));
+    }
+    tokio::spawn(federation.clone().sync_peers());
+
+    // Quotas depend on the merged totals, so re-read them
+    tokio::spawn(async move {
+        loop {
+            federation.merged.notified().await;
+            engine.lock().await.reload_store();
+            bus.publish(DaemonEvent::StateChanged);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shepherd_store::SqliteStore;
+    use shepherd_util::{EntryId, ProfileId};
+
+    fn federation(device: &str, secret: &str) -> (Arc<Federation>, Arc<dyn Store>) {
+        let store: Arc<dyn Store> = Arc::new(SqliteStore::in_memory().unwrap());
+        let policy = FederationPolicy {
+            device: device.into(),
+            secret: secret.into(),
+            listen: None,
+            peers: Vec::new(),
+            sync_interval: shepherd_config::DEFAULT_FEDERATION_SYNC_INTERVAL,
+        };
+        (Arc::new(Federation::new(policy, store.clone())), store)
+    }
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        let signature = sign("Jefe", "body");
+        assert!(verify("Jefe", "body", &signature));
+        assert!(!verify("Jefe", "other body", &signature));
+        assert!(!verify("Jefe", "body", &signature[..62]));
+        assert!(!verify("Jefe", "body", "not hex"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_over_tcp() {
+        const SECRET: &str = "correct horse battery staple";
+        let (pc, pc_store) = federation("pc", SECRET);
+        let (tablet, tablet_store) = federation("tablet", SECRET);
+        let game = EntryId::new("minecraft");
+        let alex = ProfileId::new("alex");
+        let today = shepherd_util::now().date_naive();
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+
+        pc_store.add_usage(&game, today, minutes(30)).unwrap();
+        pc_store.add_profile_usage(&alex, &game, today, minutes(30)).unwrap();
+        tablet_store.add_usage(&game, today, minutes(20)).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(tablet.clone().serve(listener));
+
+        // Each side gets the other's totals
+        assert_eq!(pc.sync_with(&addr).await.unwrap(), 1);
+        assert_eq!(pc_store.get_usage(&game, today).unwrap(), minutes(50));
+        assert_eq!(tablet_store.get_usage(&game, today).unwrap(), minutes(50));
+        assert_eq!(tablet_store.get_profile_usage(&alex, today).unwrap(), minutes(30));
+
+        // Syncing again changes nothing; new usage is picked up
+        assert_eq!(pc.sync_with(&addr).await.unwrap(), 0);
+        tablet_store.add_usage(&game, today, minutes(10)).unwrap();
+        assert_eq!(pc.sync_with(&addr).await.unwrap(), 1);
+        assert_eq!(pc_store.get_usage(&game, today).unwrap(), minutes(60));
+        assert_eq!(tablet_store.get_usage(&game, today).unwrap(), minutes(60));
+
+        // A device with the wrong secret is turned away
+        let (intruder, _) = federation("intruder", "not the family secret");
+        assert!(intruder.sync_with(&addr).await.is_err());
+        assert_eq!(tablet_store.get_usage(&game, today).unwrap(), minutes(60));
+    }
+}
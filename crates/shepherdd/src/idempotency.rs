@@ -0,0 +1,135 @@
+//! Replayed launch results
+//!
+//! A UI that times out waiting for a `Launch` response can't tell whether
+//! the launch happened. If it sent an idempotency key, it can retry with the
+//! same key: a launch already handled returns its original result instead
+//! of starting (and charging) a second session. Keys aren't tied to a
+//! connection, since most clients reconnect for each command, but to the
+//! peer's UID and role: another client sending the same key gets a launch
+//! of its own, not someone else's result.
+
+use shepherd_api::{ClientRole, ResponseResult};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How long a key is remembered
+const TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Keys remembered at once; the oldest are forgotten first
+const CAPACITY: usize = 128;
+
+/// Longest key accepted, so the cache stays small
+pub const MAX_KEY_LEN: usize = 128;
+
+/// Who sent an idempotency key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyOwner {
+    pub uid: Option<u32>,
+    pub role: ClientRole,
+}
+
+/// A launch result and whose key it was recorded under
+#[derive(Debug)]
+struct Recent {
+    owner: KeyOwner,
+    key: String,
+    at: Instant,
+    result: ResponseResult,
+}
+
+/// Results of recent launches, by owner and idempotency key
+#[derive(Debug, Default)]
+pub struct RecentLaunches {
+    entries: VecDeque<Recent>,
+}
+
+impl RecentLaunches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The result of the launch `owner` sent with `key`, if it's recent
+    pub fn get(&mut self, owner: &KeyOwner, key: &str, now: Instant) -> Option<ResponseResult> {
+        self.expire(now);
+        self.entries
+            .iter()
+            .find(|recent| recent.owner == *owner && recent.key == key)
+            .map(|recent| recent.result.clone())
+    }
+
+    /// Remember the result of a launch. Errors worth retrying (rate
+    /// limiting, a busy host) aren't remembered, so a retry gets another go.
+    pub fn insert(&mut self, owner: KeyOwner, key: String, result: &ResponseResult, now: Instant) {
+        if let ResponseResult::Err(e) = result
+            && e.code.is_retryable()
+        {
+            return;
+        }
+        self.expire(now);
+        while self.entries.len() >= CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Recent {
+            owner,
+            key,
+            at: now,
+            result: result.clone(),
+        });
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while let Some(recent) = self.entries.front()
+            && now.duration_since(recent.at) > TTL
+        {
+            self.entries.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shepherd_api::{ErrorCode, ErrorInfo, ResponsePayload};
+
+    #[test]
+    fn test_recent_launches() {
+        let mut recent = RecentLaunches::new();
+        let start = Instant::now();
+        let stopped = ResponseResult::Ok(ResponsePayload::Stopped);
+        let shell = KeyOwner { uid: Some(1000), role: ClientRole::Shell };
+
+        recent.insert(shell.clone(), "a".into(), &stopped, start);
+        assert!(matches!(
+            recent.get(&shell, "a", start + Duration::from_secs(60)),
+            Some(ResponseResult::Ok(ResponsePayload::Stopped))
+        ));
+        assert!(recent.get(&shell, "b", start).is_none());
+        assert!(recent.get(&shell, "a", start + TTL + Duration::from_secs(1)).is_none());
+
+        let busy = ResponseResult::Err(ErrorInfo::new(ErrorCode::Unavailable, "busy"));
+        recent.insert(shell.clone(), "c".into(), &busy, start);
+        assert!(recent.get(&shell, "c", start).is_none());
+
+        for i in 0..=CAPACITY {
+            recent.insert(shell.clone(), i.to_string(), &stopped, start);
+        }
+        assert!(recent.get(&shell, "0", start).is_none());
+        assert!(recent.get(&shell, &CAPACITY.to_string(), start).is_some());
+    }
+
+    #[test]
+    fn test_keys_per_owner() {
+        let mut recent = RecentLaunches::new();
+        let now = Instant::now();
+        let stopped = ResponseResult::Ok(ResponsePayload::Stopped);
+        let shell = KeyOwner { uid: Some(1000), role: ClientRole::Shell };
+        recent.insert(shell.clone(), "k".into(), &stopped, now);
+
+        // Same key from another user, or the same user in another role
+        let other_user = KeyOwner { uid: Some(1001), ..shell.clone() };
+        let observer = KeyOwner { role: ClientRole::Observer, ..shell.clone() };
+        assert!(recent.get(&other_user, "k", now).is_none());
+        assert!(recent.get(&observer, "k", now).is_none());
+        assert!(recent.get(&shell, "k", now).is_some());
+    }
+}
@@ -0,0 +1,37 @@
+//! shepherdd - The shepherd background service
+//!
+//! The [`Service`] wires together all the components:
+//! - Store initialization
+//! - Core engine
+//! - Host adapter (Linux)
+//! - IPC server
+//! - Volume control
+//! - Scheduled display brightness
+//! - Crash cleanup (kill sessions if the service panics)
+//!
+//! The `shepherdd` binary adds configuration loading, logging (pretty, JSON
+//! or journald), optional Landlock/seccomp self-sandboxing and a dev sandbox
+//! with a fake backend for UI work.
+//!
+//! Components talk through an internal event bus (see `bus`) rather than
+//! through the main loop.
+
+mod bus;
+mod calendar;
+mod crash;
+pub mod dev_sandbox;
+mod federation;
+mod idempotency;
+pub mod logging;
+mod media;
+mod pin_attempts;
+mod reporting;
+mod rollover;
+pub mod sandbox;
+mod self_update;
+mod service;
+mod subscribers;
+mod ticker;
+mod volume;
+
+pub use service::{log_config_warnings, Backend, Service, ServiceOptions};
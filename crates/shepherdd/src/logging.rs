@@ -0,0 +1,64 @@
+//! Log output setup
+//!
+//! Logs use the same field names everywhere (`session_id`, `entry_id`,
+//! `client_id`) so they can be queried once shipped: as JSON keys with
+//! `--log-format json`, or as journal fields (`journalctl SESSION_ID=...`)
+//! with `--log-format journald`.
+
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::EnvFilter;
+
+/// Log output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines on stderr
+    #[default]
+    Pretty,
+    /// One JSON object per line on stderr
+    Json,
+    /// Structured entries sent straight to the systemd journal
+    Journald,
+}
+
+/// Install the global subscriber.
+///
+/// Falls back to pretty output if the journal socket isn't reachable.
+pub fn init(format: LogFormat, filter: EnvFilter) {
+    match format {
+        LogFormat::Pretty => {
+            tracing_subscriber::fmt()
+                .with_env_filter(filter)
+                .with_target(true)
+                .init();
+        }
+        LogFormat::Json => {
+            tracing_subscriber::fmt()
+                .json()
+                .flatten_event(true)
+                .with_current_span(false)
+                .with_span_list(true)
+                .with_env_filter(filter)
+                .with_target(true)
+                .init();
+        }
+        LogFormat::Journald => match tracing_journald::layer() {
+            Ok(layer) => {
+                tracing_subscriber::registry()
+                    .with(filter)
+                    .with(
+                        layer
+                            .with_syslog_identifier("shepherdd".into())
+                            .with_field_prefix(None),
+                    )
+                    .init();
+            }
+            Err(e) => {
+                tracing_subscriber::fmt()
+                    .with_env_filter(filter)
+                    .with_target(true)
+                    .init();
+                tracing::warn!(error = %e, "Journal unavailable, logging to stderr");
+            }
+        },
+    }
+}
@@ -0,0 +1,224 @@
+//! Media players in the running session (MPRIS on Linux)
+//!
+//! While a session runs, its player is polled and changes are broadcast as
+//! `MediaChanged`, so the HUD can show what is playing ([`spawn_poller`]).
+//! With `pause_on_warnings`, a playing player is paused while a warning is
+//! issued and resumed a few seconds later ([`spawn_warning_pause`]).
+//! Pausing before an expired session is stopped happens in the main loop,
+//! next to the stop itself.
+
+use shepherd_api::{MediaInfo, PlaybackStatus};
+use shepherd_core::{CoreEngine, CoreEvent};
+use shepherd_host_api::{HostAdapter, HostSessionHandle};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::bus::{DaemonEvent, EventBus, Published};
+
+/// How often the session's player is read
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How long a warning keeps the player paused
+const WARNING_PAUSE: Duration = Duration::from_secs(5);
+
+async fn session_handle(engine: &Mutex<CoreEngine>) -> Option<HostSessionHandle> {
+    engine
+        .lock()
+        .await
+        .current_session()
+        .and_then(|session| session.host_handle.clone())
+}
+
+/// What the running session's player is playing, for `GetMedia`
+pub async fn current(engine: &Mutex<CoreEngine>, host: &Arc<dyn HostAdapter>) -> Option<MediaInfo> {
+    let handle = session_handle(engine).await?;
+    host.media_status(&handle).await.unwrap_or_else(|e| {
+        debug!(error = %e, "Failed to read the session's media player");
+        None
+    })
+}
+
+/// Poll the session's player and publish `MediaChanged` when it changes
+pub fn spawn_poller(bus: EventBus, engine: Arc<Mutex<CoreEngine>>, host: Arc<dyn HostAdapter>) {
+    if !host.capabilities().can_control_media {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        let mut last = None;
+        loop {
+            interval.tick().await;
+            let media = match session_handle(&engine).await {
+                Some(handle) => match host.media_status(&handle).await {
+                    Ok(media) => media,
+                    // Keep what was shown rather than flicker on a slow bus
+                    Err(e) => {
+                        debug!(error = %e, "Failed to read the session's media player");
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            if media != last {
+                last = media.clone();
+                bus.publish(DaemonEvent::MediaChanged(media));
+            }
+        }
+    });
+}
+
+/// When a warning is issued, pause a playing player and resume it after
+/// [`WARNING_PAUSE`]
+pub fn spawn_warning_pause(bus: &EventBus, engine: Arc<Mutex<CoreEngine>>, host: Arc<dyn HostAdapter>) {
+    // Bumped by every warning, so only the last one's timer resumes
+    let generation = Arc::new(AtomicU64::new(0));
+    // Whether the player is paused because of a warning, not by the child
+    let paused = Arc::new(AtomicBool::new(false));
+    bus.spawn_subscriber("media", move |Published { event, .. }| {
+        let engine = engine.clone();
+        let host = host.clone();
+        let generation = generation.clone();
+        let paused = paused.clone();
+        async move {
+            let DaemonEvent::Core(CoreEvent::Warning { session_id, .. }) = event else {
+                return;
+            };
+            if !host.capabilities().can_control_media {
+                return;
+            }
+            let handle = {
+                let eng = engine.lock().await;
+                if !eng.policy().media.pause_on_warnings {
+                    return;
+                }
+                match eng.current_session() {
+                    Some(session) if session.plan.session_id == session_id => session.host_handle.clone(),
+                    _ => None,
+                }
+            };
+            let Some(handle) = handle else {
+                return;
+            };
+
+            let playing = host
+                .media_status(&handle)
+                .await
+                .ok()
+                .flatten()
+                .is_some_and(|media| media.status == PlaybackStatus::Playing);
+            if playing {
+                if let Err(e) = host.set_media_playing(&handle, false).await {
+                    warn!(error = %e, "Failed to pause media for a warning");
+                    return;
+                }
+                paused.store(true, Ordering::SeqCst);
+            } else if !paused.load(Ordering::SeqCst) {
+                // Nothing playing, or paused by the child: leave it alone
+                return;
+            }
+
+            let warning = generation.fetch_add(1, Ordering::SeqCst) + 1;
+            tokio::spawn(async move {
+                tokio::time::sleep(WARNING_PAUSE).await;
+                if generation.load(Ordering::SeqCst) != warning || !paused.swap(false, Ordering::SeqCst) {
+                    return;
+                }
+                if let Err(e) = host.set_media_playing(&handle, true).await {
+                    debug!(error = %e, "Failed to resume media after a warning");
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shepherd_api::WarningSeverity;
+    use shepherd_core::LaunchDecision;
+    use shepherd_host_api::{HostCapabilities, HostHandlePayload, MockHost};
+    use shepherd_store::SqliteStore;
+    use shepherd_util::{EntryId, MonotonicInstant, SessionId};
+
+    /// An engine running "game" under a mock handle, and a host whose
+    /// session plays a video
+    async fn playing(config: &str) -> (Arc<Mutex<CoreEngine>>, Arc<MockHost>, SessionId) {
+        let policy = shepherd_config::parse_config(config).unwrap();
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        let mut engine = CoreEngine::new(policy, store, HostCapabilities::minimal());
+
+        let now = shepherd_util::now();
+        let LaunchDecision::Approved(plan) = engine.request_launch(&EntryId::new("game"), now) else {
+            panic!("launch denied");
+        };
+        let session_id = plan.session_id.clone();
+        engine.start_session(plan, now, MonotonicInstant::now());
+        engine.attach_host_handle(HostSessionHandle::new(
+            session_id.clone(),
+            HostHandlePayload::Mock { id: 1 },
+        ));
+
+        let host = MockHost::new().with_capabilities(HostCapabilities {
+            can_control_media: true,
+            ..HostCapabilities::minimal()
+        });
+        *host.media.lock().unwrap() = Some(MediaInfo {
+            player: "vlc".into(),
+            status: PlaybackStatus::Playing,
+            title: Some("Big Buck Bunny".into()),
+            artist: None,
+        });
+        (Arc::new(Mutex::new(engine)), Arc::new(host), session_id)
+    }
+
+    const CONFIG: &str = r#"
+        config_version = 1
+
+        [service.media]
+        pause_on_warnings = true
+
+        [[entries]]
+        id = "game"
+        label = "Game"
+        kind = { type = "process", command = "/usr/bin/game" }
+    "#;
+
+    #[tokio::test]
+    async fn changes_are_published() {
+        let (engine, host, _) = playing(CONFIG).await;
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        spawn_poller(bus.clone(), engine.clone(), host.clone());
+
+        let published = events.recv().await.unwrap();
+        let DaemonEvent::MediaChanged(Some(media)) = published.event else {
+            panic!("unexpected event {:?}", published.event);
+        };
+        assert_eq!(media.title.as_deref(), Some("Big Buck Bunny"));
+        let host: Arc<dyn HostAdapter> = host;
+        assert_eq!(current(&engine, &host).await, Some(media));
+    }
+
+    #[tokio::test]
+    async fn warnings_pause_the_player() {
+        let (engine, host, session_id) = playing(CONFIG).await;
+        let bus = EventBus::new();
+        spawn_warning_pause(&bus, engine, host.clone());
+
+        bus.publish(DaemonEvent::Core(CoreEvent::Warning {
+            session_id,
+            threshold_seconds: 60,
+            time_remaining: Duration::from_secs(60),
+            severity: WarningSeverity::Warn,
+            message: None,
+        }));
+        let status = || host.media.lock().unwrap().as_ref().unwrap().status;
+        while status() == PlaybackStatus::Playing {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(status(), PlaybackStatus::Paused);
+    }
+}
@@ -0,0 +1,88 @@
+//! Admin PIN guessing limits
+//!
+//! A four-digit PIN falls to a script in minutes without a limit. Wrong
+//! PINs are counted across every client, since most clients reconnect for
+//! each command and a new connection must not reset the count. After a few
+//! misses in a row further attempts are refused for a lockout that doubles
+//! with each miss after it. The right PIN clears the count.
+
+use std::time::{Duration, Instant};
+
+/// Wrong PINs allowed in a row before the first lockout
+const FREE_ATTEMPTS: u32 = 3;
+
+/// Lockout after the first miss past the free attempts
+const FIRST_LOCKOUT: Duration = Duration::from_secs(30);
+
+/// Longest lockout
+const MAX_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Wrong admin PINs given recently
+#[derive(Debug, Default)]
+pub struct PinAttempts {
+    /// Wrong PINs in a row
+    failures: u32,
+    locked_until: Option<Instant>,
+}
+
+impl PinAttempts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long until another PIN is checked, while locked out
+    pub fn locked_for(&self, now: Instant) -> Option<Duration> {
+        self.locked_until
+            .map(|until| until.saturating_duration_since(now))
+            .filter(|left| !left.is_zero())
+    }
+
+    /// Count a wrong PIN. Returns the misses in a row and the lockout this
+    /// one starts, if any.
+    pub fn failed(&mut self, now: Instant) -> (u32, Option<Duration>) {
+        self.failures += 1;
+        let lockout = (self.failures > FREE_ATTEMPTS).then(|| {
+            let doublings = (self.failures - FREE_ATTEMPTS - 1).min(16);
+            (FIRST_LOCKOUT * 2u32.pow(doublings)).min(MAX_LOCKOUT)
+        });
+        if let Some(lockout) = lockout {
+            self.locked_until = Some(now + lockout);
+        }
+        (self.failures, lockout)
+    }
+
+    pub fn succeeded(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout_doubles() {
+        let mut attempts = PinAttempts::new();
+        let start = Instant::now();
+
+        for failures in 1..=FREE_ATTEMPTS {
+            assert_eq!(attempts.failed(start), (failures, None));
+            assert!(attempts.locked_for(start).is_none());
+        }
+
+        assert_eq!(attempts.failed(start), (4, Some(FIRST_LOCKOUT)));
+        assert_eq!(attempts.locked_for(start), Some(FIRST_LOCKOUT));
+        assert!(attempts.locked_for(start + FIRST_LOCKOUT).is_none());
+
+        let later = start + FIRST_LOCKOUT;
+        assert_eq!(attempts.failed(later), (5, Some(FIRST_LOCKOUT * 2)));
+        for _ in 0..20 {
+            attempts.failed(later);
+        }
+        assert_eq!(attempts.locked_for(later), Some(MAX_LOCKOUT));
+
+        attempts.succeeded();
+        assert!(attempts.locked_for(later).is_none());
+        assert_eq!(attempts.failed(later), (1, None));
+    }
+}
@@ -0,0 +1,199 @@
+//! Weekly budget reports
+//!
+//! Profiles can set a weekly budget, Monday to Sunday, and alerts at a
+//! share of it ("80% used by Wednesday"). Time is charged to profiles when
+//! a session ends, so this subscriber reads each profile's week so far when
+//! a session starts and again when it ends, and publishes a
+//! [`DaemonEvent::BudgetAlert`] for every threshold the session crossed.
+//! Parents get alerts the way they get other events: broadcast to clients
+//! and kept in the audit log.
+//!
+//! Only crossings raise an alert, so a restart doesn't repeat the alerts a
+//! profile already got this week.
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use shepherd_config::{BudgetAlert, Profile};
+use shepherd_core::{CoreEngine, CoreEvent};
+use shepherd_store::Store;
+use shepherd_util::ProfileId;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::bus::{DaemonEvent, EventBus, Published};
+
+/// Week usage per profile when the current session started
+#[derive(Debug, Default)]
+struct Baseline {
+    week: Option<NaiveDate>,
+    used: HashMap<ProfileId, Duration>,
+}
+
+/// The Monday starting the week `day` is in
+fn week_start(day: NaiveDate) -> NaiveDate {
+    day - Days::new(day.weekday().num_days_from_monday().into())
+}
+
+/// Time charged to `profile` from Monday through `today`
+fn week_usage(store: &dyn Store, profile: &ProfileId, today: NaiveDate) -> Duration {
+    week_start(today)
+        .iter_days()
+        .take_while(|day| *day <= today)
+        .map(|day| {
+            store.get_profile_usage(profile, day).unwrap_or_else(|e| {
+                warn!(profile_id = %profile, error = %e, "Failed to read profile usage");
+                Duration::ZERO
+            })
+        })
+        .sum()
+}
+
+/// The alerts crossed going from `before` to `after`, on `today`
+fn crossed(profile: &Profile, before: Duration, after: Duration, today: Weekday) -> Vec<BudgetAlert> {
+    let Some(budget) = profile.weekly_budget else {
+        return Vec::new();
+    };
+    profile
+        .budget_alerts
+        .iter()
+        .filter(|alert| {
+            alert
+                .by
+                .is_none_or(|by| today.num_days_from_monday() <= by.num_days_from_monday())
+        })
+        .filter(|alert| {
+            let threshold = budget * u32::from(alert.percent) / 100;
+            before < threshold && threshold <= after
+        })
+        .copied()
+        .collect()
+}
+
+/// Alert on weekly budgets as sessions end
+pub fn spawn(bus: &EventBus, engine: Arc<Mutex<CoreEngine>>, store: Arc<dyn Store>) {
+    let baseline = Arc::new(std::sync::Mutex::new(Baseline::default()));
+    let publisher = bus.clone();
+    bus.spawn_subscriber("reporting", move |Published { event, .. }| {
+        let engine = engine.clone();
+        let store = store.clone();
+        let baseline = baseline.clone();
+        let bus = publisher.clone();
+        async move {
+            let ended = match event {
+                DaemonEvent::Core(CoreEvent::SessionStarted { .. }) => false,
+                DaemonEvent::Core(CoreEvent::SessionEnded { .. }) => true,
+                _ => return,
+            };
+            let profiles: Vec<Profile> = engine
+                .lock()
+                .await
+                .policy()
+                .profiles
+                .iter()
+                .filter(|profile| !profile.budget_alerts.is_empty())
+                .cloned()
+                .collect();
+
+            let today = shepherd_util::now().date_naive();
+            let week = week_start(today);
+            let mut baseline = baseline.lock().unwrap_or_else(|e| e.into_inner());
+            if baseline.week != Some(week) {
+                baseline.week = Some(week);
+                baseline.used.clear();
+            }
+
+            for profile in &profiles {
+                let used = week_usage(store.as_ref(), &profile.id, today);
+                let before = baseline.used.insert(profile.id.clone(), used);
+                // A session that started before shepherdd did has no baseline
+                let (true, Some(before)) = (ended, before) else {
+                    continue;
+                };
+                for alert in crossed(profile, before, used, today.weekday()) {
+                    let budget = profile.weekly_budget.unwrap_or_default();
+                    info!(
+                        profile_id = %profile.id,
+                        percent = alert.percent,
+                        used_secs = used.as_secs(),
+                        budget_secs = budget.as_secs(),
+                        "Weekly budget alert"
+                    );
+                    bus.publish(DaemonEvent::BudgetAlert {
+                        profile_id: profile.id.clone(),
+                        label: profile.label.clone(),
+                        percent: alert.percent,
+                        used,
+                        budget,
+                    });
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shepherd_store::SqliteStore;
+    use shepherd_util::EntryId;
+
+    fn mins(m: u64) -> Duration {
+        Duration::from_secs(m * 60)
+    }
+
+    fn profile(alerts: Vec<BudgetAlert>) -> Profile {
+        Profile {
+            id: ProfileId::new("alex"),
+            label: "Alex".into(),
+            weekly_budget: Some(mins(600)),
+            budget_alerts: alerts,
+        }
+    }
+
+    #[test]
+    fn test_week_usage() {
+        let store = SqliteStore::in_memory().unwrap();
+        let alex = ProfileId::new("alex");
+        let game = EntryId::new("game");
+        // Sunday before, then Monday and Wednesday of the week
+        let sunday = NaiveDate::from_ymd_opt(2025, 1, 5).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let wednesday = NaiveDate::from_ymd_opt(2025, 1, 8).unwrap();
+        for (day, used) in [(sunday, 100), (monday, 30), (wednesday, 45)] {
+            store.add_profile_usage(&alex, &game, day, mins(used)).unwrap();
+        }
+
+        assert_eq!(week_start(wednesday), monday);
+        assert_eq!(week_start(monday), monday);
+        assert_eq!(week_usage(&store, &alex, wednesday), mins(75));
+        assert_eq!(week_usage(&store, &alex, monday), mins(30));
+    }
+
+    #[test]
+    fn test_crossed() {
+        let early = BudgetAlert { percent: 80, by: Some(Weekday::Wed) };
+        let used_up = BudgetAlert { percent: 100, by: None };
+        let alex = profile(vec![early, used_up]);
+
+        // 480 minutes is 80%
+        assert_eq!(crossed(&alex, mins(400), mins(480), Weekday::Tue), vec![early]);
+        assert_eq!(crossed(&alex, mins(400), mins(480), Weekday::Wed), vec![early]);
+        // Too late in the week to be worth saying
+        assert!(crossed(&alex, mins(400), mins(480), Weekday::Thu).is_empty());
+        // Already past it before this session
+        assert!(crossed(&alex, mins(480), mins(500), Weekday::Tue).is_empty());
+        // One session can cross both
+        assert_eq!(
+            crossed(&alex, mins(400), mins(650), Weekday::Mon),
+            vec![early, used_up]
+        );
+
+        let no_budget = Profile {
+            weekly_budget: None,
+            ..profile(vec![used_up])
+        };
+        assert!(crossed(&no_budget, Duration::ZERO, mins(1000), Weekday::Mon).is_empty());
+    }
+}
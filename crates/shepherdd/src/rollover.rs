@@ -0,0 +1,115 @@
+//! Day rollover
+//!
+//! Quotas and banked time are counted per day, and windows are written in
+//! wall-clock time, so the start of a day can change what is available
+//! without anything else happening. This job wakes at local midnight, in
+//! the system timezone and in the config's `timezone` if it sets one, and
+//! whenever the UTC offset changes (the timezone was changed, or daylight
+//! saving time started or ended). The engine then re-reads what it cached
+//! from the store and re-evaluates every entry, and clients get a fresh
+//! state snapshot.
+//!
+//! Sleeps use the monotonic clock, so the job also looks every
+//! [`CHECK_INTERVAL`] in case the wall clock jumped or the machine slept.
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Offset};
+use shepherd_core::CoreEngine;
+use shepherd_util::Timezone;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::bus::{DaemonEvent, EventBus};
+
+/// Longest sleep between checks for a new day
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What a new day, or a timezone change, is detected by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Day {
+    /// Date in the system timezone, which quotas are counted in
+    local: NaiveDate,
+    /// Date in the config's timezone, which windows are evaluated in
+    zoned: NaiveDate,
+    /// UTC offset of the system timezone, in seconds
+    offset: i32,
+}
+
+fn day(now: &DateTime<Local>, tz: &Timezone) -> Day {
+    Day {
+        local: now.date_naive(),
+        zoned: tz.wall_time(now).date(),
+        offset: now.offset().fix().local_minus_utc(),
+    }
+}
+
+/// The next midnight, in the system timezone or in `tz`, whichever comes
+/// first. A midnight skipped by a daylight saving change is taken to be the
+/// first time after it.
+fn next_midnight(now: &DateTime<Local>, tz: &Timezone) -> Option<DateTime<Local>> {
+    let local = now.date_naive().succ_opt()?.and_time(NaiveTime::MIN);
+    let zoned = tz.wall_time(now).date().succ_opt()?.and_time(NaiveTime::MIN);
+    [
+        Timezone::Local.next_instant(&local, now),
+        tz.next_instant(&zoned, now),
+    ]
+    .into_iter()
+    .flatten()
+    .min()
+}
+
+/// Roll the engine over to each new day as it starts
+pub fn spawn(bus: EventBus, engine: Arc<Mutex<CoreEngine>>) {
+    tokio::spawn(async move {
+        let timezone = || async { engine.lock().await.policy().service.timezone.clone() };
+        let mut last = day(&shepherd_util::now(), &timezone().await);
+        loop {
+            let now = shepherd_util::now();
+            let wait = next_midnight(&now, &timezone().await)
+                .and_then(|midnight| (midnight - now).to_std().ok())
+                .map_or(CHECK_INTERVAL, |wait| wait.min(CHECK_INTERVAL));
+            tokio::time::sleep(wait).await;
+
+            let now = shepherd_util::now();
+            let today = day(&now, &timezone().await);
+            if today == last {
+                continue;
+            }
+            last = today;
+
+            // Clients need new snapshots either way: remaining quotas changed
+            match engine.lock().await.roll_over_day(now) {
+                Some(event) => bus.publish(DaemonEvent::Core(event)),
+                None => bus.publish(DaemonEvent::StateChanged),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_next_midnight() {
+        let noon = Local.with_ymd_and_hms(2025, 1, 6, 12, 0, 0).unwrap();
+        let midnight = Local.with_ymd_and_hms(2025, 1, 7, 0, 0, 0).unwrap();
+        assert_eq!(next_midnight(&noon, &Timezone::Local), Some(midnight));
+
+        // New York's next day starts at 05:00 UTC; whichever is first wins
+        let tz: Timezone = "EST5".parse().unwrap();
+        let new_york = Utc.with_ymd_and_hms(2025, 1, 7, 5, 0, 0).unwrap().with_timezone(&Local);
+        assert_eq!(next_midnight(&noon, &tz), Some(midnight.min(new_york)));
+    }
+
+    #[test]
+    fn test_day_changes() {
+        let tz: Timezone = "EST5".parse().unwrap();
+        let evening = Local.with_ymd_and_hms(2025, 1, 6, 23, 59, 0).unwrap();
+        let later = Local.with_ymd_and_hms(2025, 1, 6, 23, 59, 30).unwrap();
+        let midnight = Local.with_ymd_and_hms(2025, 1, 7, 0, 0, 0).unwrap();
+        assert_eq!(day(&evening, &tz), day(&later, &tz));
+        assert_ne!(day(&evening, &tz), day(&midnight, &tz));
+    }
+}
@@ -0,0 +1,301 @@
+//! Self-sandboxing
+//!
+//! With `--sandbox`, shepherdd confines itself once the config is loaded, so
+//! a bug in request parsing can't be turned into access to the rest of the
+//! system:
+//!
+//! - **Landlock**: writes only to the data directory, the config directory
+//!   and the socket directory, plus the binary's directory when updates are
+//!   staged; read and execute access to system paths so brightness and
+//!   Bluetooth tools still run
+//! - **seccomp**: refuses syscalls shepherdd never needs (tracing other
+//!   processes, mounts, kernel modules, namespaces, BPF) and any socket
+//!   that isn't a Unix socket, or an IP socket when something fetches from
+//!   or talks to other machines (see [`needs_network`])
+//!
+//! Both are inherited by child processes, which is why sandboxing requires
+//! the host helper: apps are launched by the helper, outside the sandbox.
+//! On kernels without Landlock the filesystem rules are skipped with a
+//! warning.
+
+use anyhow::{Context, Result};
+use shepherd_config::Policy;
+use landlock::{
+    path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr,
+    RulesetStatus, ABI,
+};
+use seccompiler::{
+    BpfProgram, SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter,
+    SeccompRule, TargetArch,
+};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Paths the sandboxed service needs to write
+#[derive(Debug, Clone)]
+pub struct SandboxPaths {
+    /// SaveConfig replaces this file through a temporary file beside it
+    pub config_path: PathBuf,
+    pub socket_path: PathBuf,
+    pub data_dir: PathBuf,
+    /// Binary a staged self-update replaces through a temporary file beside it
+    pub update_install_path: Option<PathBuf>,
+}
+
+impl SandboxPaths {
+    pub fn new(config_path: PathBuf, socket_path: PathBuf, data_dir: PathBuf, policy: &Policy) -> Self {
+        let update_install_path = policy
+            .service
+            .self_update
+            .as_ref()
+            .filter(|update| update.stage)
+            .and_then(crate::self_update::install_path);
+        Self {
+            config_path,
+            socket_path,
+            data_dir,
+            update_install_path,
+        }
+    }
+}
+
+/// Whether the policy needs IP sockets: federation, and the `curl` that
+/// fetches release and calendar feeds, which inherits the filter
+pub fn needs_network(policy: &Policy) -> bool {
+    policy.service.federation.is_some()
+        || policy.service.self_update.is_some()
+        || policy.calendar.is_some()
+}
+
+/// Readable and executable, for the tools shepherdd runs itself
+/// (brightnessctl, gammastep, bluetoothctl) and the time zone database
+const READ_ONLY: &[&str] = &["/usr", "/bin", "/sbin", "/lib", "/lib64", "/etc", "/proc", "/sys"];
+
+/// Writable system paths: the backlight, spawned tools' stdio and SQLite
+/// temporary files
+const SYSTEM_READ_WRITE: &[&str] = &["/sys/class/backlight", "/sys/devices", "/dev/null", "/tmp"];
+
+/// Apply Landlock and seccomp to the calling thread and every thread or
+/// process it starts afterwards. `network` allows IP sockets, see
+/// [`needs_network`].
+pub fn apply(paths: &SandboxPaths, network: bool) -> Result<()> {
+    let writable = writable_dirs(paths);
+    for dir in &writable {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    }
+
+    apply_landlock(&writable)?;
+    apply_seccomp(network)?;
+    Ok(())
+}
+
+fn writable_dirs(paths: &SandboxPaths) -> Vec<PathBuf> {
+    let parent_of = |path: &Path| {
+        path.parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf()
+    };
+
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for dir in [
+        Some(paths.data_dir.clone()),
+        Some(parent_of(&paths.config_path)),
+        Some(parent_of(&paths.socket_path)),
+        paths.update_install_path.as_deref().map(parent_of),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+fn apply_landlock(writable: &[PathBuf]) -> Result<()> {
+    let abi = ABI::V3;
+    let status = Ruleset::default()
+        .handle_access(AccessFs::from_all(abi))?
+        .create()?
+        .add_rules(path_beneath_rules(READ_ONLY, AccessFs::from_read(abi)))?
+        .add_rules(path_beneath_rules(SYSTEM_READ_WRITE, AccessFs::from_all(abi)))?
+        .add_rules(path_beneath_rules(writable, AccessFs::from_all(abi)))?
+        .restrict_self()
+        .context("Failed to apply Landlock rules")?;
+
+    match status.ruleset {
+        RulesetStatus::FullyEnforced => info!(writable = ?writable, "Landlock sandbox enforced"),
+        RulesetStatus::PartiallyEnforced => {
+            info!(writable = ?writable, "Landlock sandbox partially enforced by this kernel")
+        }
+        RulesetStatus::NotEnforced => {
+            warn!("Landlock not supported by this kernel, filesystem sandbox not enforced")
+        }
+    }
+    Ok(())
+}
+
+fn apply_seccomp(network: bool) -> Result<()> {
+    let Ok(arch) = TargetArch::try_from(std::env::consts::ARCH) else {
+        warn!(arch = std::env::consts::ARCH, "seccomp filter not available on this architecture");
+        return Ok(());
+    };
+
+    let program = seccomp_program(arch, network)?;
+    // Landlock already set no_new_privs, which an unprivileged filter requires
+    seccompiler::apply_filter(&program).context("Failed to apply seccomp filter")?;
+    info!(denied = denied_syscalls().len(), "seccomp filter enforced");
+    Ok(())
+}
+
+/// Syscalls refused outright; everything else is allowed
+fn denied_syscalls() -> Vec<i64> {
+    #[allow(unused_mut)]
+    let mut syscalls = vec![
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_unshare,
+        libc::SYS_setns,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_kexec_load,
+        libc::SYS_kexec_file_load,
+        libc::SYS_reboot,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_userfaultfd,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_open_by_handle_at,
+        libc::SYS_acct,
+        libc::SYS_settimeofday,
+        libc::SYS_clock_settime,
+        libc::SYS_clock_adjtime,
+        libc::SYS_adjtimex,
+        libc::SYS_personality,
+        libc::SYS_syslog,
+    ];
+    #[cfg(target_arch = "x86_64")]
+    syscalls.extend([libc::SYS_iopl, libc::SYS_ioperm]);
+    syscalls
+}
+
+fn seccomp_program(arch: TargetArch, network: bool) -> Result<BpfProgram> {
+    let mut rules: BTreeMap<i64, Vec<SeccompRule>> = denied_syscalls()
+        .into_iter()
+        // No conditions: always matches
+        .map(|syscall| (syscall, Vec::new()))
+        .collect();
+
+    // Unix sockets only: IPC, the host helper, D-Bus and the journal. IP
+    // sockets too for federation and feeds.
+    let mut domains = vec![libc::AF_UNIX];
+    if network {
+        domains.extend([libc::AF_INET, libc::AF_INET6]);
+    }
+    let other_domain = domains
+        .into_iter()
+        .map(|domain| SeccompCondition::new(0, SeccompCmpArgLen::Dword, SeccompCmpOp::Ne, domain as u64))
+        .collect::<Result<Vec<_>, _>>()?;
+    rules.insert(libc::SYS_socket, vec![SeccompRule::new(other_domain)?]);
+
+    let filter = SeccompFilter::new(
+        rules,
+        SeccompAction::Allow,
+        SeccompAction::Errno(libc::EPERM as u32),
+        arch,
+    )?;
+    Ok(filter.try_into()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shepherd_config::{CalendarPolicy, SelfUpdatePolicy};
+
+    #[test]
+    fn test_writable_dirs() {
+        let dirs = writable_dirs(&SandboxPaths {
+            config_path: PathBuf::from("/etc/shepherd/config.toml"),
+            socket_path: PathBuf::from("/run/shepherd/shepherdd.sock"),
+            data_dir: PathBuf::from("/var/lib/shepherdd"),
+            update_install_path: None,
+        });
+        assert_eq!(
+            dirs,
+            vec![
+                PathBuf::from("/var/lib/shepherdd"),
+                PathBuf::from("/etc/shepherd"),
+                PathBuf::from("/run/shepherd"),
+            ]
+        );
+
+        // A bare file name lives in the working directory
+        let dirs = writable_dirs(&SandboxPaths {
+            config_path: PathBuf::from("config.toml"),
+            socket_path: PathBuf::from("/run/shepherd/shepherdd.sock"),
+            data_dir: PathBuf::from("/run/shepherd"),
+            update_install_path: None,
+        });
+        assert_eq!(dirs, vec![PathBuf::from("/run/shepherd"), PathBuf::from(".")]);
+    }
+
+    #[test]
+    fn test_self_update_in_sandbox() {
+        let mut policy = Policy::unconfigured();
+        assert!(!needs_network(&policy));
+
+        policy.service.self_update = Some(SelfUpdatePolicy {
+            feed: "https://example.org/shepherdd.json".into(),
+            check_interval: std::time::Duration::from_secs(3600),
+            stage: false,
+            install_path: Some(PathBuf::from("/opt/shepherd/bin/shepherdd")),
+            public_key: None,
+        });
+        assert!(needs_network(&policy));
+        let paths = |policy: &Policy| {
+            SandboxPaths::new(
+                PathBuf::from("/etc/shepherd/config.toml"),
+                PathBuf::from("/run/shepherd/shepherdd.sock"),
+                PathBuf::from("/var/lib/shepherdd"),
+                policy,
+            )
+        };
+        // Only checking: nothing is installed
+        assert!(!writable_dirs(&paths(&policy)).contains(&PathBuf::from("/opt/shepherd/bin")));
+
+        policy.service.self_update.as_mut().unwrap().stage = true;
+        assert!(writable_dirs(&paths(&policy)).contains(&PathBuf::from("/opt/shepherd/bin")));
+    }
+
+    #[test]
+    fn test_calendar_needs_network() {
+        let mut policy = Policy::unconfigured();
+        policy.calendar = Some(CalendarPolicy {
+            url: "https://example.org/family.ics".into(),
+            poll_interval: std::time::Duration::from_secs(900),
+        });
+        assert!(needs_network(&policy));
+    }
+
+    #[test]
+    fn test_seccomp_program_compiles() {
+        let arch = TargetArch::try_from(std::env::consts::ARCH).unwrap();
+        let program = seccomp_program(arch, false).unwrap();
+        assert!(!program.is_empty());
+        let networked = seccomp_program(arch, true).unwrap();
+        assert!(networked.len() > program.len());
+    }
+}
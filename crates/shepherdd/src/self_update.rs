@@ -0,0 +1,350 @@
+//! Checking for new shepherdd releases
+//!
+//! The release feed is a small JSON document naming the latest version and
+//! where to download it:
+//!
+//! ```json
+//! {"version": "0.4.0", "url": "https://example.org/shepherdd-0.4.0", "sha256": "..."}
+//! ```
+//!
+//! https feeds and downloads are fetched with `curl`; `file://` URLs and
+//! plain paths are read directly. A newer version is reported in GetHealth
+//! and GetCapabilities. With `stage = true` the binary is downloaded to
+//! `<data_dir>/updates` and checked against `sha256` and its minisign
+//! signature, `<url>.minisig` unless the feed names another `signature`.
+//! Only releases signed with the configured `public_key` are staged, so a
+//! compromised feed can't install anything. The service installs it over
+//! `install_path` at the next gap between sessions and restarts into it.
+
+use anyhow::{bail, Context, Result};
+use semver::Version;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use shepherd_api::UpdateInfo;
+use shepherd_config::SelfUpdatePolicy;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// Longest a feed request or download may take
+const FETCH_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// What the release feed announces
+#[derive(Debug, Clone, Deserialize)]
+struct Release {
+    version: String,
+    url: String,
+    /// Hex digest of the binary at `url`
+    sha256: String,
+    /// Detached minisign signature of the binary (default: `<url>.minisig`)
+    #[serde(default)]
+    signature: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Newest release seen that is newer than this one
+    available: Option<Version>,
+    /// Verified binary waiting to be installed
+    staged: Option<(Version, PathBuf)>,
+}
+
+/// Periodic release check, shared between the check task and the main loop
+pub struct SelfUpdater {
+    policy: SelfUpdatePolicy,
+    current: Version,
+    staging_dir: PathBuf,
+    /// Resolved at startup: once replaced, /proc/self/exe reads "(deleted)"
+    install_path: Option<PathBuf>,
+    state: Mutex<State>,
+}
+
+impl SelfUpdater {
+    pub fn new(policy: SelfUpdatePolicy, data_dir: &Path) -> Self {
+        let install_path = install_path(&policy);
+        Self {
+            policy,
+            current: Version::parse(env!("CARGO_PKG_VERSION")).expect("crate version is semver"),
+            staging_dir: data_dir.join("updates"),
+            install_path,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn check_interval(&self) -> Duration {
+        self.policy.check_interval
+    }
+
+    /// Fetch the feed and, if it announces a newer release and staging is
+    /// enabled, download and verify it
+    pub async fn check(&self) -> Result<()> {
+        let feed = fetch(&self.policy.feed)
+            .await
+            .with_context(|| format!("Failed to fetch release feed {}", self.policy.feed))?;
+        let release: Release = serde_json::from_slice(&feed).context("Invalid release feed")?;
+        let version = Version::parse(release.version.trim())
+            .with_context(|| format!("Invalid release version \"{}\"", release.version))?;
+
+        if version <= self.current {
+            debug!(current = %self.current, latest = %version, "shepherdd is up to date");
+            self.state.lock().unwrap().available = None;
+            return Ok(());
+        }
+
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.staged.as_ref().is_some_and(|(staged, _)| *staged == version) {
+                return Ok(());
+            }
+            if state.available.as_ref() != Some(&version) {
+                info!(current = %self.current, available = %version, "shepherdd update available");
+            }
+            state.available = Some(version.clone());
+        }
+
+        if self.policy.stage {
+            let path = self.stage(&release, &version).await?;
+            info!(version = %version, path = %path.display(), "shepherdd update staged");
+            self.state.lock().unwrap().staged = Some((version, path));
+        }
+        Ok(())
+    }
+
+    /// Download the release into the staging directory and verify it
+    async fn stage(&self, release: &Release, version: &Version) -> Result<PathBuf> {
+        let Some(public_key) = &self.policy.public_key else {
+            bail!("Not staging unsigned releases; set self_update.public_key");
+        };
+        let signature_url = release
+            .signature
+            .clone()
+            .unwrap_or_else(|| format!("{}.minisig", release.url));
+        for url in [&release.url, &signature_url] {
+            if url.starts_with("http://") {
+                bail!("Refusing to download {} over plain http", url);
+            }
+        }
+
+        let binary = fetch(&release.url)
+            .await
+            .with_context(|| format!("Failed to download {}", release.url))?;
+        verify_sha256(&binary, &release.sha256)?;
+        let signature = fetch(&signature_url)
+            .await
+            .with_context(|| format!("Failed to download {}", signature_url))?;
+        verify_signature(&binary, &signature, public_key)?;
+
+        tokio::fs::create_dir_all(&self.staging_dir)
+            .await
+            .with_context(|| format!("Failed to create {:?}", self.staging_dir))?;
+        let path = self.staging_dir.join(format!("shepherdd-{}", version));
+        let partial = self.staging_dir.join(format!("shepherdd-{}.partial", version));
+        tokio::fs::write(&partial, &binary).await?;
+        tokio::fs::set_permissions(&partial, std::fs::Permissions::from_mode(0o755)).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(path)
+    }
+
+    /// Update details for GetHealth and GetCapabilities
+    pub fn status(&self) -> Option<UpdateInfo> {
+        let state = self.state.lock().unwrap();
+        let available = state.available.as_ref()?;
+        Some(UpdateInfo {
+            current_version: self.current.to_string(),
+            available_version: available.to_string(),
+            staged: state.staged.is_some(),
+        })
+    }
+
+    pub fn has_staged(&self) -> bool {
+        self.state.lock().unwrap().staged.is_some()
+    }
+
+    /// Replace the installed binary with the staged release, returning the
+    /// installed path and version. A failed install isn't retried until the
+    /// next check stages the release again.
+    pub fn install_staged(&self) -> Result<Option<(PathBuf, Version)>> {
+        let Some((version, staged)) = self.state.lock().unwrap().staged.take() else {
+            return Ok(None);
+        };
+        let Some(target) = &self.install_path else {
+            bail!("Don't know where shepherdd is installed; set self_update.install_path");
+        };
+
+        // Copy beside the target first so the rename is atomic
+        let file_name = target
+            .file_name()
+            .context("install_path has no file name")?
+            .to_string_lossy();
+        let temp = target.with_file_name(format!(".{}.new", file_name));
+        let install = || -> std::io::Result<()> {
+            std::fs::copy(&staged, &temp)?;
+            std::fs::set_permissions(&temp, std::fs::Permissions::from_mode(0o755))?;
+            std::fs::rename(&temp, target)
+        };
+        if let Err(e) = install() {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e).with_context(|| format!("Failed to install update to {:?}", target));
+        }
+
+        let _ = std::fs::remove_file(&staged);
+        Ok(Some((target.clone(), version)))
+    }
+
+    pub fn current_version(&self) -> &Version {
+        &self.current
+    }
+}
+
+/// The binary an update replaces: `install_path`, or the running executable
+pub(crate) fn install_path(policy: &SelfUpdatePolicy) -> Option<PathBuf> {
+    policy
+        .install_path
+        .clone()
+        .or_else(|| std::env::current_exe().ok())
+}
+
+/// Read a feed or download: curl for http(s), the filesystem otherwise
+pub(crate) async fn fetch(location: &str) -> Result<Vec<u8>> {
+    if location.starts_with("https://") || location.starts_with("http://") {
+        let output = tokio::process::Command::new("curl")
+            .args(["--fail", "--silent", "--show-error", "--location", "--max-time"])
+            .arg(FETCH_TIMEOUT.as_secs().to_string())
+            .arg(location)
+            .kill_on_drop(true)
+            .output()
+            .await
+            .context("Failed to run curl")?;
+        if !output.status.success() {
+            bail!("curl: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(output.stdout)
+    } else {
+        let path = location.strip_prefix("file://").unwrap_or(location);
+        Ok(tokio::fs::read(path).await?)
+    }
+}
+
+fn verify_sha256(data: &[u8], expected: &str) -> Result<()> {
+    let actual: String = Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        bail!("Checksum mismatch: expected {}, got {}", expected.trim(), actual);
+    }
+    Ok(())
+}
+
+/// Check a detached minisign signature. Only prehashed (`minisign -H`,
+/// the default since 0.10) signatures are accepted.
+fn verify_signature(data: &[u8], signature: &[u8], public_key: &str) -> Result<()> {
+    let public_key = minisign_verify::PublicKey::from_base64(public_key.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid public key: {}", e))?;
+    let signature = std::str::from_utf8(signature)
+        .ok()
+        .and_then(|text| minisign_verify::Signature::decode(text).ok())
+        .context("Invalid signature file")?;
+    public_key
+        .verify(data, &signature, false)
+        .map_err(|e| anyhow::anyhow!("Signature check failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vector from minisign-verify: a prehashed signature of b"test"
+    const PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==
+";
+
+    fn updater(dir: &Path, feed: &Path, stage: bool) -> SelfUpdater {
+        SelfUpdater::new(
+            SelfUpdatePolicy {
+                feed: format!("file://{}", feed.display()),
+                check_interval: Duration::from_secs(3600),
+                stage,
+                install_path: Some(dir.join("bin/shepherdd")),
+                public_key: Some(PUBLIC_KEY.into()),
+            },
+            &dir.join("data"),
+        )
+    }
+
+    fn write_feed(dir: &Path, version: &str, binary: &[u8], sha256: Option<&str>) -> PathBuf {
+        let binary_path = dir.join(format!("release-{}", version));
+        std::fs::write(&binary_path, binary).unwrap();
+        std::fs::write(dir.join(format!("release-{}.minisig", version)), SIGNATURE).unwrap();
+        let digest: String = Sha256::digest(binary).iter().map(|b| format!("{:02x}", b)).collect();
+        let feed = dir.join("latest.json");
+        std::fs::write(
+            &feed,
+            serde_json::json!({
+                "version": version,
+                "url": binary_path.display().to_string(),
+                "sha256": sha256.unwrap_or(&digest),
+            })
+            .to_string(),
+        )
+        .unwrap();
+        feed
+    }
+
+    #[tokio::test]
+    async fn test_reports_newer_release_only() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let feed = write_feed(dir.path(), env!("CARGO_PKG_VERSION"), b"same", None);
+        let updater = updater(dir.path(), &feed, false);
+        updater.check().await.unwrap();
+        assert!(updater.status().is_none());
+
+        write_feed(dir.path(), "999.0.0", b"newer", None);
+        updater.check().await.unwrap();
+        let status = updater.status().unwrap();
+        assert_eq!(status.available_version, "999.0.0");
+        assert!(!status.staged);
+        assert!(!updater.has_staged());
+    }
+
+    #[tokio::test]
+    async fn test_stage_and_install() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("bin")).unwrap();
+        std::fs::write(dir.path().join("bin/shepherdd"), b"old").unwrap();
+
+        // A corrupted download is refused
+        let feed = write_feed(dir.path(), "999.0.0", b"test", Some("00ff"));
+        let updater = updater(dir.path(), &feed, true);
+        assert!(updater.check().await.is_err());
+        assert!(!updater.has_staged());
+
+        // So is one the signature doesn't cover, even with a matching digest
+        write_feed(dir.path(), "999.0.0", b"Test", None);
+        assert!(updater.check().await.is_err());
+        assert!(!updater.has_staged());
+
+        write_feed(dir.path(), "999.0.0", b"test", None);
+        std::fs::remove_file(dir.path().join("release-999.0.0.minisig")).unwrap();
+        assert!(updater.check().await.is_err());
+        assert!(!updater.has_staged());
+
+        write_feed(dir.path(), "999.0.0", b"test", None);
+        updater.check().await.unwrap();
+        assert!(updater.status().unwrap().staged);
+
+        let (path, version) = updater.install_staged().unwrap().unwrap();
+        assert_eq!(path, dir.path().join("bin/shepherdd"));
+        assert_eq!(version, Version::new(999, 0, 0));
+        assert_eq!(std::fs::read(&path).unwrap(), b"test");
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert!(updater.install_staged().unwrap().is_none());
+    }
+}
@@ -0,0 +1,2261 @@
+//! The service: engine, host, store and IPC server, and the main loop
+//! between them
+
+use anyhow::{Context, Result};
+use shepherd_api::{
+    ClientRole, Command, Correlation, EntryHealth, ErrorCode, ErrorInfo, ErrorKind, EscapeRoute, HealthStatus, Hold, HostConnection, MockTimeOp,
+    Response, ResponsePayload, ResponseResult, ServiceCapabilities, SessionEndReason, StopMode, StoreHealth, VolumeInfo,
+    API_VERSION,
+};
+use shepherd_config::{load_raw_config, parse_config, parse_raw_config, patch_config, patch_document, policy_view, write_config_file, GamescopePolicy, HookFailure, HookPolicy, MinecraftPolicy, Policy, PrecheckPolicy};
+use shepherd_core::{CoreEngine, CoreEvent, EnforcementStep, LaunchDecision, StopDecision};
+use shepherd_host_api::{
+    BrightnessController, GamescopeOptions, HostAdapter, HostEvent, InputDeviceMonitor, LaunchPrechecks, MinecraftOptions, MockBrightnessController, MockHost, MockVolumeController, SessionHook, StopMode as HostStopMode, VolumeController, WineOptions,
+};
+use shepherd_host_linux::{BluetoothInputMonitor, HelperClient, LinuxBrightnessController, LinuxHost, LinuxVolumeController};
+use shepherd_ipc::{IpcServer, ServerMessage};
+use shepherd_store::{AuditEvent, AuditEventType, ResilientStore, Store};
+use shepherd_util::{format_duration, ClientId, MonotonicInstant, RateLimiter, SeatId};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::Mutex;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+
+use crate::bus::{DaemonEvent, EventBus};
+use crate::calendar::CalendarFeed;
+use crate::idempotency::{self, KeyOwner, RecentLaunches};
+use crate::pin_attempts::PinAttempts;
+use crate::self_update::SelfUpdater;
+use crate::ticker::Ticker;
+use crate::volume::{self, VolumeCache};
+use crate::{crash, federation, media, reporting, rollover, subscribers};
+
+/// How long to wait for shepherd-host-helper to come up
+const HOST_HELPER_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often to retry the database while the store is degraded
+const STORE_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Where the service keeps its files and how it reaches the host
+#[derive(Clone)]
+pub struct ServiceOptions {
+    /// Where SaveConfig writes the config
+    pub config_path: PathBuf,
+    pub socket_path: PathBuf,
+    pub data_dir: PathBuf,
+    /// Keep usage and the audit log in memory rather than in `data_dir`
+    pub in_memory_store: bool,
+    pub backend: Backend,
+}
+
+/// What carries out host operations
+#[derive(Clone)]
+pub enum Backend {
+    /// This process, on the local machine
+    Linux,
+    /// shepherd-host-helper listening on this socket
+    Helper(PathBuf),
+    /// A host that only pretends to launch entries, and no real volume or
+    /// brightness control: the dev sandbox and tests
+    Mock(Arc<MockHost>),
+}
+
+/// Everything `GetHealth` reports on
+struct HealthSources<'a> {
+    host: &'a Arc<dyn HostAdapter>,
+    ipc: &'a Arc<IpcServer>,
+    store: &'a Arc<dyn Store>,
+    updater: &'a Option<Arc<SelfUpdater>>,
+    ticker: &'a Ticker,
+    config_path: &'a Path,
+    config_warnings: Vec<String>,
+    escape_routes: Vec<EscapeRoute>,
+}
+
+/// The handles command and event handlers share with the main loop
+#[derive(Clone)]
+struct CommandContext {
+    engine: Arc<Mutex<CoreEngine>>,
+    host: Arc<dyn HostAdapter>,
+    volume: Arc<VolumeCache>,
+    input_devices: Arc<BluetoothInputMonitor>,
+    ipc: Arc<IpcServer>,
+    /// Correlated with the request being handled, if any
+    bus: EventBus,
+    store: Arc<dyn Store>,
+    rate_limiter: Arc<Mutex<RateLimiter>>,
+    recent_launches: Arc<Mutex<RecentLaunches>>,
+    pin_attempts: Arc<Mutex<PinAttempts>>,
+    updater: Option<Arc<SelfUpdater>>,
+    ticker: Arc<Ticker>,
+    /// Where SaveConfig writes the config
+    config_path: PathBuf,
+}
+
+/// Main service state
+pub struct Service {
+    engine: CoreEngine,
+    host: Arc<dyn HostAdapter>,
+    volume: Arc<VolumeCache>,
+    brightness: Arc<dyn BrightnessController>,
+    input_devices: Arc<BluetoothInputMonitor>,
+    ipc: Arc<IpcServer>,
+    store: Arc<ResilientStore>,
+    rate_limiter: RateLimiter,
+    /// Release checks, if configured
+    updater: Option<Arc<SelfUpdater>>,
+    /// Where SaveConfig writes the config
+    config_path: PathBuf,
+}
+
+/// Log what's likely wrong in a policy that loaded anyway
+pub fn log_config_warnings(policy: &Policy) {
+    for warning in &policy.warnings {
+        warn!(%warning, "Config warning");
+    }
+}
+
+impl Service {
+    /// Set everything up and start listening on the socket
+    pub async fn new(options: ServiceOptions, policy: Policy, unconfigured: bool) -> Result<Self> {
+        let ServiceOptions { config_path, socket_path, data_dir, in_memory_store, backend } = options;
+        let mock = matches!(backend, Backend::Mock(_));
+
+        // Create data directory
+        std::fs::create_dir_all(&data_dir)
+            .with_context(|| format!("Failed to create data directory {:?}", data_dir))?;
+
+        // Initialize store. A corrupt or unwritable database doesn't stop the
+        // service: writes are kept in memory until it can be reopened.
+        let store = if in_memory_store {
+            info!("Store initialized in memory");
+            Arc::new(ResilientStore::in_memory()?)
+        } else {
+            let db_path = data_dir.join("shepherdd.db");
+            let store = Arc::new(ResilientStore::open(&db_path));
+            info!(db_path = %db_path.display(), degraded = store.is_degraded(), "Store initialized");
+            store
+        };
+
+        // Log service start
+        store.append_audit(AuditEvent::new(AuditEventType::ServiceStarted))?;
+
+        // Initialize host adapter and volume controller, either in-process or
+        // through the session's host helper
+        let (host, volume): (Arc<dyn HostAdapter>, Arc<dyn VolumeController>) =
+            match &backend {
+                Backend::Mock(host) => (host.clone(), Arc::new(MockVolumeController::new())),
+                Backend::Helper(helper_path) => {
+                    let helper = Arc::new(
+                        HelperClient::connect(helper_path, HOST_HELPER_CONNECT_TIMEOUT)
+                            .await
+                            .with_context(|| {
+                                format!("Failed to connect to host helper at {:?}", helper_path)
+                            })?,
+                    );
+                    (helper.clone(), helper)
+                }
+                Backend::Linux => {
+                    let host = LinuxHost::new();
+                    host.start_monitor();
+                    (Arc::new(host), Arc::new(LinuxVolumeController::new()))
+                }
+            };
+
+        if volume.capabilities().available {
+            info!(
+                backend = ?volume.capabilities().backend,
+                "Volume controller initialized"
+            );
+        } else {
+            warn!("No sound backend detected, volume control unavailable");
+        }
+        let volume = Arc::new(VolumeCache::new(volume));
+
+        // Initialize brightness controller (backlight and night light)
+        let brightness: Arc<dyn BrightnessController> = if mock {
+            Arc::new(MockBrightnessController::new())
+        } else {
+            Arc::new(LinuxBrightnessController::new())
+        };
+        if !policy.display.is_empty() && !brightness.capabilities().available {
+            warn!("Display schedule configured but brightness control is unavailable");
+        }
+        let headphones_only = policy.volume.require_headphones
+            || policy.volume.windows.iter().any(|w| w.limits.require_headphones)
+            || policy
+                .entries
+                .iter()
+                .any(|e| e.volume.as_ref().is_some_and(|v| v.require_headphones));
+        if headphones_only && !volume.controller().capabilities().can_detect_output {
+            warn!("Headphones required by policy but the sound backend can't tell headphones from speakers");
+        }
+        let ducking = policy.volume.duck_warnings.is_some()
+            || policy
+                .entries
+                .iter()
+                .any(|e| e.volume.as_ref().is_some_and(|v| v.duck_warnings.is_some()));
+        if ducking && !volume.controller().capabilities().can_duck {
+            warn!("Warning ducking configured but the sound backend has no per-stream volume (needs pactl)");
+        }
+
+        // Look for ways out of the kiosk; findings are audited and reported
+        // by GetHealth
+        let escape_routes = host
+            .audit_escape_routes(policy.service.mitigate_escape_routes)
+            .await;
+
+        // Like the socket path, a changed panic key needs a restart
+        if let Some(combo) = &policy.service.panic_key
+            && let Err(e) = host.register_panic_key(combo).await
+        {
+            warn!(combo = %combo, error = %e, "Panic key unavailable");
+        }
+
+        // Initialize input device monitor (Bluetooth controllers)
+        let input_devices = Arc::new(BluetoothInputMonitor::new());
+
+        // Read before the engine takes the policy; changes need a restart,
+        // like the socket path
+        let shell_executables = policy.service.shell_executables.clone();
+        let updater = policy
+            .service
+            .self_update
+            .clone()
+            .map(|self_update| Arc::new(SelfUpdater::new(self_update, &data_dir)));
+
+        // Initialize core engine
+        let mut engine = CoreEngine::new(policy, store.clone(), host.capabilities().clone());
+        if unconfigured {
+            engine.set_unconfigured();
+        }
+        engine.set_escape_routes(escape_routes);
+
+        // Initialize IPC server
+        let mut ipc = IpcServer::new(&socket_path)
+            .with_shell_executables(shell_executables);
+        ipc.start().await?;
+
+        info!(socket_path = %socket_path.display(), "IPC server started");
+
+        // Rate limiter: 30 requests per second per client, with room for the
+        // flurry of requests a shell sends when it connects
+        let rate_limiter = RateLimiter::new(30, Duration::from_secs(1)).with_burst(60);
+
+        Ok(Self {
+            engine,
+            host,
+            volume,
+            brightness,
+            input_devices,
+            ipc: Arc::new(ipc),
+            store,
+            rate_limiter,
+            updater,
+            config_path,
+        })
+    }
+
+    /// The IPC server, e.g. to connect in-process clients
+    pub fn ipc(&self) -> Arc<IpcServer> {
+        self.ipc.clone()
+    }
+
+    /// From here on a panic must not leave a session running unmanaged:
+    /// the process kills every session and exits instead
+    pub fn install_crash_cleanup(&self) {
+        crash::install(self.host.clone(), self.store.clone(), self.ipc.clone());
+    }
+
+    /// Run until shutdown. Returns the binary to restart into when an
+    /// update was installed.
+    pub async fn run(self) -> Result<Option<PathBuf>> {
+        // Start host process monitor
+        // Get channels
+        let mut host_events = self.host.subscribe();
+        let ipc_ref = self.ipc.clone();
+
+        let mut ipc_messages = ipc_ref
+            .take_message_receiver()
+            .await
+            .expect("Message receiver should be available");
+
+        // Read before the engine is shared; like the release feed, a
+        // changed calendar URL needs a restart
+        let calendar = self.engine.policy().calendar.clone().map(CalendarFeed::new);
+        let federation = self.engine.policy().service.federation.clone();
+
+        // Wrap mutable state
+        let engine = Arc::new(Mutex::new(self.engine));
+        let rate_limiter = Arc::new(Mutex::new(self.rate_limiter));
+        let recent_launches = Arc::new(Mutex::new(RecentLaunches::new()));
+        let pin_attempts = Arc::new(Mutex::new(PinAttempts::new()));
+        let host = self.host.clone();
+        let volume = self.volume.clone();
+        let brightness = self.brightness.clone();
+        let input_devices = self.input_devices.clone();
+        let store: Arc<dyn Store> = self.store.clone();
+        let config_path = self.config_path.clone();
+        let updater = self.updater.clone();
+
+        // Fan events out to the subsystems that react to them
+        let bus = EventBus::new();
+        subscribers::spawn_ipc_forwarder(&bus, engine.clone(), ipc_ref.clone());
+        subscribers::spawn_display(&bus, brightness.clone());
+        subscribers::spawn_audit(&bus, store.clone());
+        volume.spawn_watcher(bus.clone(), engine.clone());
+        volume::spawn_quiet_hours(&bus, engine.clone(), volume.clone());
+        volume::spawn_warning_ducking(&bus, engine.clone(), volume.clone());
+        media::spawn_poller(bus.clone(), engine.clone(), host.clone());
+        media::spawn_warning_pause(&bus, engine.clone(), host.clone());
+        rollover::spawn(bus.clone(), engine.clone());
+        reporting::spawn(&bus, engine.clone(), store.clone());
+
+        // Share usage with shepherdd on the family's other devices. Without
+        // it quotas still hold on this device, so a port in use isn't fatal.
+        if let Some(federation) = federation
+            && let Err(e) = federation::spawn(federation, store.clone(), bus.clone(), engine.clone()).await
+        {
+            error!(error = format!("{:#}", e), "Failed to start usage sharing");
+        }
+
+        // Replay writes kept in memory while the database was unavailable
+        let resilient_store = self.store.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(STORE_RETRY_INTERVAL);
+            loop {
+                interval.tick().await;
+                if resilient_store.is_degraded() {
+                    resilient_store.retry();
+                }
+            }
+        });
+
+        // Look for new shepherdd releases; the first tick fires immediately
+        if let Some(updater) = updater.clone() {
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(updater.check_interval());
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = updater.check().await {
+                        warn!(error = format!("{:#}", e), "Update check failed");
+                    }
+                }
+            });
+        }
+
+        // Pick up one-off exceptions from the family calendar
+        if let Some(feed) = calendar {
+            let engine = engine.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(feed.poll_interval());
+                loop {
+                    interval.tick().await;
+                    let tz = engine.lock().await.policy().service.timezone.clone();
+                    match feed.fetch(&tz).await {
+                        Ok(exceptions) => engine.lock().await.set_calendar(exceptions),
+                        Err(e) => warn!(error = format!("{:#}", e), "Calendar fetch failed"),
+                    }
+                }
+            });
+        }
+        let mut restart_into = None;
+        let mut host_lost = false;
+
+        // Spawn IPC accept task
+        let ipc_accept = ipc_ref.clone();
+        tokio::spawn(async move {
+            if let Err(e) = ipc_accept.run().await {
+                error!(error = %e, "IPC server error");
+            }
+        });
+
+        // Set up signal handlers
+        let mut sigterm = signal(SignalKind::terminate())
+            .context("Failed to create SIGTERM handler")?;
+        let mut sigint = signal(SignalKind::interrupt())
+            .context("Failed to create SIGINT handler")?;
+        let mut sighup = signal(SignalKind::hangup())
+            .context("Failed to create SIGHUP handler")?;
+
+        // Probe entries for broken installs; the first tick fires immediately
+        let health_interval = engine.lock().await.policy().service.health_check_interval;
+        let mut health_timer = tokio::time::interval(
+            health_interval.unwrap_or(shepherd_config::DEFAULT_HEALTH_CHECK_INTERVAL),
+        );
+
+        // Snap/Flatpak updates wait for a gap between sessions
+        let mut maintenance_timer = tokio::time::interval(Duration::from_secs(60));
+
+        // Engine ticks are scheduled for when the engine needs one; the
+        // first runs immediately
+        let ticker = Arc::new(Ticker::new());
+        let tick_sleep = tokio::time::sleep(Duration::ZERO);
+        tokio::pin!(tick_sleep);
+
+        // Battery readings change slowly; poll well below the tick rate
+        let mut battery_timer = tokio::time::interval(Duration::from_secs(30));
+
+        let ctx = CommandContext {
+            engine: engine.clone(),
+            host: host.clone(),
+            volume,
+            input_devices,
+            ipc: ipc_ref.clone(),
+            bus,
+            store: store.clone(),
+            rate_limiter,
+            recent_launches,
+            pin_attempts,
+            updater: updater.clone(),
+            ticker: ticker.clone(),
+            config_path,
+        };
+
+        info!("Service running");
+
+        loop {
+            tokio::select! {
+                // Signal: SIGTERM or SIGINT - graceful shutdown
+                _ = sigterm.recv() => {
+                    info!("Received SIGTERM, shutting down gracefully");
+                    break;
+                }
+                _ = sigint.recv() => {
+                    info!("Received SIGINT, shutting down gracefully");
+                    break;
+                }
+
+                // Signal: SIGHUP - graceful shutdown (sent by sway on exit)
+                _ = sighup.recv() => {
+                    info!("Received SIGHUP, shutting down gracefully");
+                    break;
+                }
+
+                // Tick - check warnings and expiry
+                _ = &mut tick_sleep => {
+                    let started = Instant::now();
+                    let now_mono = MonotonicInstant::now();
+                    let now = shepherd_util::now();
+
+                    let events = {
+                        let mut engine = engine.lock().await;
+                        engine.tick(now_mono, now)
+                    };
+                    ticker.record(started);
+
+                    for event in events {
+                        Self::handle_core_event(&ctx, event, now_mono, now).await;
+                    }
+                }
+
+                // Battery poll - low-battery warnings and session stop
+                _ = battery_timer.tick() => {
+                    if host.capabilities().can_read_battery {
+                        match host.battery_status().await {
+                            Ok(battery) => {
+                                let now_mono = MonotonicInstant::now();
+                                let now = shepherd_util::now();
+
+                                let events = {
+                                    let mut engine = engine.lock().await;
+                                    engine.update_battery(battery)
+                                };
+
+                                for event in events {
+                                    Self::handle_core_event(&ctx, event, now_mono, now).await;
+                                }
+                            }
+                            Err(e) => debug!(error = %e, "Failed to read battery status"),
+                        }
+                    }
+                }
+
+                // Entry health probes - disable entries whose app is gone
+                _ = health_timer.tick(), if health_interval.is_some() => {
+                    let (_, events) = Self::probe_entry_health(&engine, &host).await;
+                    let now_mono = MonotonicInstant::now();
+                    let now = shepherd_util::now();
+                    for event in events {
+                        Self::handle_core_event(&ctx, event, now_mono, now).await;
+                    }
+                }
+
+                // Maintenance - update one app at a time in the background
+                _ = maintenance_timer.tick() => {
+                    Self::start_due_update(&engine, &host).await;
+
+                    // A staged release is installed between sessions
+                    if let Some(updater) = &updater
+                        && updater.has_staged()
+                        && engine.lock().await.is_idle()
+                    {
+                        match updater.install_staged() {
+                            Ok(Some((path, version))) => {
+                                info!(version = %version, path = %path.display(), "Installed shepherdd update, restarting");
+                                let _ = store.append_audit(AuditEvent::new(AuditEventType::ServiceUpdated {
+                                    from_version: updater.current_version().to_string(),
+                                    to_version: version.to_string(),
+                                }));
+                                restart_into = Some(path);
+                                break;
+                            }
+                            Ok(None) => {}
+                            Err(e) => warn!(error = format!("{:#}", e), "Failed to install shepherdd update"),
+                        }
+                    }
+                }
+
+                // Host events (process exit)
+                host_event = host_events.recv() => match host_event {
+                    Some(host_event) => Self::handle_host_event(&engine, &host, &ctx.bus, host_event).await,
+                    // The helper killed every session when the connection
+                    // dropped. Exit with an error so the service manager
+                    // restarts shepherdd, which then waits for the helper.
+                    None => {
+                        error!("Lost the host helper, shutting down");
+                        host_lost = true;
+                        break;
+                    }
+                },
+
+                // IPC messages
+                Some(msg) = ipc_messages.recv() => {
+                    Self::handle_ipc_message(&ctx, msg).await;
+                }
+            }
+
+            // Whatever just happened may have moved the next deadline
+            let next = {
+                let engine = engine.lock().await;
+                let wanted = engine.next_tick(MonotonicInstant::now(), shepherd_util::now());
+                ticker.schedule(wanted, engine.policy().service.max_tick_interval)
+            };
+            tick_sleep.as_mut().reset(next.into());
+        }
+
+        // Graceful shutdown
+        info!("Shutting down shepherdd");
+
+        // Stop all running sessions, recording their usage so a restart
+        // doesn't hand out the time again
+        {
+            let mut engine = engine.lock().await;
+            let seats: Vec<SeatId> = engine.sessions().map(|(seat, _)| seat.clone()).collect();
+            for seat in seats {
+                let handle = engine.session_on(&seat).and_then(|s| s.host_handle.clone());
+                if let StopDecision::Stopped(result) = engine.stop_on(
+                    &seat,
+                    SessionEndReason::ServiceShutdown,
+                    MonotonicInstant::now(),
+                    shepherd_util::now(),
+                ) {
+                    info!(session_id = %result.session_id, seat = %seat, "Stopping active session");
+                }
+                if let Some(handle) = handle && let Err(e) = host.stop(&handle, HostStopMode::Graceful {
+                    timeout: Duration::from_secs(5),
+                }).await {
+                    warn!(error = %e, "Failed to stop session gracefully");
+                }
+            }
+        }
+
+        // Never leave input blocked behind us
+        Self::release_input(&host).await;
+
+        // Log shutdown
+        if let Err(e) = store.append_audit(AuditEvent::new(AuditEventType::ServiceStopped)) {
+            warn!(error = %e, "Failed to log service shutdown");
+        }
+
+        info!("Shutdown complete");
+        if host_lost {
+            anyhow::bail!("Lost connection to the host helper");
+        }
+        Ok(restart_into)
+    }
+
+    /// Enforce what can't wait for a subscriber, then publish the event
+    async fn handle_core_event(
+        ctx: &CommandContext,
+        event: CoreEvent,
+        now_mono: MonotonicInstant,
+        now: chrono::DateTime<chrono::Local>,
+    ) {
+        let CommandContext { engine, host, bus, .. } = ctx;
+        // Publish first so shells hear about expiry before the app closes
+        bus.publish(DaemonEvent::Core(event.clone()));
+
+        match event {
+            CoreEvent::Warning {
+                session_id,
+                threshold_seconds,
+                time_remaining,
+                ..
+            } => {
+                info!(
+                    session_id = %session_id,
+                    threshold = threshold_seconds,
+                    remaining = ?time_remaining,
+                    "Warning issued"
+                );
+            }
+
+            CoreEvent::ExpireDue { session_id } => {
+                info!(session_id = %session_id, "Session expired, stopping");
+
+                // Get the host handle and stop it
+                let (handle, pause_media, shared_input) = {
+                    let engine = engine.lock().await;
+                    let handle = engine
+                        .session_by_id(&session_id)
+                        .and_then(|s| s.host_handle.clone());
+                    (handle, engine.policy().media.pause_on_expiry, !engine.policy().seats.is_empty())
+                };
+
+                // Block input so the application can't be used while it shuts
+                // down. Input is shared by every seat, so not with several.
+                if host.capabilities().can_inhibit_input
+                    && !shared_input
+                    && let Err(e) = host.inhibit_input().await
+                {
+                    warn!(error = %e, "Failed to inhibit input during expiry");
+                }
+
+                // Silence the player right away; the app may take a while to exit
+                if let Some(handle) = &handle
+                    && pause_media
+                    && host.capabilities().can_control_media
+                    && let Err(e) = host.set_media_playing(handle, false).await
+                {
+                    debug!(error = %e, "Failed to pause media during expiry");
+                }
+
+                if let Some(handle) = handle
+                    && let Err(e) = host
+                        .stop(
+                            &handle,
+                            HostStopMode::Graceful {
+                                timeout: Duration::from_secs(5),
+                            },
+                        )
+                        .await
+                    {
+                        warn!(error = %e, "Failed to stop session gracefully, forcing");
+                        let _ = host.stop(&handle, HostStopMode::Force).await;
+                    }
+            }
+
+            CoreEvent::SessionEnded { .. } => {
+                Self::release_input(host).await;
+            }
+
+            CoreEvent::LowBatteryStop { session_id } => {
+                let mut eng = engine.lock().await;
+
+                // The session may have ended since the reading was taken
+                let Some(seat) = eng.seat_of(&session_id) else {
+                    return;
+                };
+                let handle = eng.session_on(&seat).and_then(|s| s.host_handle.clone());
+
+                if let StopDecision::Stopped(result) =
+                    eng.stop_on(&seat, SessionEndReason::LowBattery, now_mono, now)
+                {
+                    drop(eng); // Release lock before host operations
+                    bus.publish(DaemonEvent::session_ended(result));
+
+                    if let Some(h) = handle
+                        && let Err(e) = host
+                            .stop(&h, HostStopMode::Graceful { timeout: Duration::from_secs(5) })
+                            .await
+                    {
+                        warn!(error = %e, "Failed to stop session on low battery");
+                    }
+                    Self::release_input(host).await;
+                }
+            }
+
+            CoreEvent::PowerActionDue { action } => {
+                info!(action = ?action, "Curfew countdown elapsed");
+                if let Err(e) = host.power_action(action).await {
+                    error!(error = %e, action = ?action, "Failed to perform curfew power action");
+                }
+            }
+
+            CoreEvent::AutostartDue { entry_id } => {
+                let mut eng = engine.lock().await;
+                let decision = eng.request_autostart(&entry_id, now);
+                // Internal launch: nobody waits for the response
+                let response =
+                    Self::finish_launch(ctx, eng, decision, 0, now, now_mono).await;
+                if let ResponseResult::Err(e) = response.result {
+                    warn!(entry_id = %entry_id, error = %e.message, "Autostart failed");
+                }
+            }
+
+            CoreEvent::EnforcementRetry {
+                session_id,
+                attempt,
+                step,
+            } => {
+                let (seat, handle) = {
+                    let eng = engine.lock().await;
+                    let Some(session) = eng.session_by_id(&session_id) else {
+                        return;
+                    };
+                    (session.plan.seat.clone(), session.host_handle.clone())
+                };
+
+                // A process that's gone without an exit event (missing pid,
+                // reaped elsewhere) would otherwise hold the session forever
+                let alive = handle.as_ref().and_then(|h| host.is_session_alive(h));
+                let Some(handle) = handle.filter(|_| alive != Some(false)) else {
+                    info!(session_id = %session_id, "Expired session is gone, ending it");
+                    let mut eng = engine.lock().await;
+                    if let Some(event) = eng.notify_exited_on(&seat, None, now_mono, now) {
+                        bus.publish(DaemonEvent::Core(event));
+                    }
+                    return;
+                };
+
+                let result = match step {
+                    EnforcementStep::Force => host.stop(&handle, HostStopMode::Force).await,
+                    EnforcementStep::KillCgroup => host.kill_cgroup(&handle).await,
+                };
+                if let Err(e) = result {
+                    warn!(session_id = %session_id, attempt, step = ?step, error = %e, "Escalated stop failed");
+                }
+            }
+
+            // Handled by bus subscribers
+            CoreEvent::SessionStarted { .. }
+            | CoreEvent::EnforcementFailed { .. }
+            | CoreEvent::PolicyReloaded { .. }
+            | CoreEvent::ThemeChanged(_)
+            | CoreEvent::EntryAvailabilityChanged { .. }
+            | CoreEvent::DisplaySettingsChanged(_)
+            | CoreEvent::QuietHoursChanged(_)
+            | CoreEvent::BatteryStatus { .. }
+            | CoreEvent::PowerActionScheduled(_)
+            | CoreEvent::PowerActionCancelled
+            | CoreEvent::AvailabilitySetChanged
+            | CoreEvent::TimeRemaining { .. } => {}
+        }
+    }
+
+    async fn handle_host_event(
+        engine: &Arc<Mutex<CoreEngine>>,
+        host: &Arc<dyn HostAdapter>,
+        bus: &EventBus,
+        event: HostEvent,
+    ) {
+        match event {
+            HostEvent::Exited { handle, status } => {
+                let now_mono = MonotonicInstant::now();
+                let now = shepherd_util::now();
+
+                info!(
+                    session_id = %handle.session_id,
+                    status = ?status,
+                    "Host process exited - will end session"
+                );
+
+                let core_event = {
+                    let mut engine = engine.lock().await;
+                    // A late exit from a session that already ended must not
+                    // end the one that replaced it
+                    if let Some(seat) = engine.seat_of(&handle.session_id) {
+                        engine.notify_exited_on(&seat, status.code, now_mono, now)
+                    } else {
+                        debug!(session_id = %handle.session_id, "Ignoring exit of a session that is no longer current");
+                        None
+                    }
+                };
+
+                info!(has_event = core_event.is_some(), "notify_session_exited result");
+
+                if let Some(event @ CoreEvent::SessionEnded { .. }) = core_event {
+                    Self::release_input(host).await;
+                    bus.publish(DaemonEvent::Core(event));
+                }
+            }
+
+            HostEvent::WindowReady { handle } => {
+                debug!(session_id = %handle.session_id, "Window ready");
+            }
+
+            HostEvent::SpawnFailed { session_id, diagnostics } => {
+                error!(session_id = %session_id, diagnostics = %diagnostics, "Spawn failed");
+
+                // End the session here so the `Exited` that follows is stale
+                let now_mono = MonotonicInstant::now();
+                let now = shepherd_util::now();
+                let result = {
+                    let mut engine = engine.lock().await;
+                    if let Some(seat) = engine.seat_of(&session_id) {
+                        let reason = SessionEndReason::LaunchFailed {
+                            error: diagnostics.to_string(),
+                            diagnostics: Some(diagnostics),
+                        };
+                        match engine.stop_on(&seat, reason, now_mono, now) {
+                            StopDecision::Stopped(result) => Some(result),
+                            StopDecision::NoActiveSession => None,
+                        }
+                    } else {
+                        None
+                    }
+                };
+
+                if let Some(result) = result {
+                    Self::release_input(host).await;
+                    bus.publish(DaemonEvent::session_ended(result));
+                }
+            }
+
+            HostEvent::PanicKeyPressed => {
+                let now_mono = MonotonicInstant::now();
+                let now = shepherd_util::now();
+
+                // Every seat shares the compositor, so stop them all
+                let stopped: Vec<_> = {
+                    let mut engine = engine.lock().await;
+                    let seats: Vec<SeatId> = engine.sessions().map(|(seat, _)| seat.clone()).collect();
+                    seats
+                        .into_iter()
+                        .filter_map(|seat| {
+                            let handle = engine.session_on(&seat).and_then(|s| s.host_handle.clone());
+                            match engine.stop_on(&seat, SessionEndReason::AdminStop, now_mono, now) {
+                                StopDecision::Stopped(result) => Some((result, handle)),
+                                StopDecision::NoActiveSession => None,
+                            }
+                        })
+                        .collect()
+                };
+                warn!(sessions = stopped.len(), "Panic key pressed, force-stopping sessions");
+
+                for (result, handle) in stopped {
+                    bus.publish(DaemonEvent::session_ended(result));
+                    if let Some(handle) = handle
+                        && let Err(e) = host.stop(&handle, HostStopMode::Force).await
+                    {
+                        warn!(session_id = %handle.session_id, error = %e, "Failed to force-stop session");
+                    }
+                }
+                Self::release_input(host).await;
+                if let Err(e) = host.ensure_shell_visible().await {
+                    warn!(error = %e, "Failed to bring back the launcher");
+                }
+            }
+        }
+    }
+
+    async fn handle_ipc_message(ctx: &CommandContext, msg: ServerMessage) {
+        let CommandContext { ipc, bus, rate_limiter, .. } = ctx;
+        match msg {
+            ServerMessage::Request { client_id, request } => {
+                // Rate limiting
+                {
+                    let mut limiter = rate_limiter.lock().await;
+                    if !limiter.check(&client_id) {
+                        let response = Response::error(
+                            request.request_id,
+                            ErrorInfo::new(ErrorCode::RateLimited, "Too many requests"),
+                        );
+                        let _ = ipc.send_response(&client_id, response).await;
+                        return;
+                    }
+                }
+
+                let span = info_span!("request", client_id = %client_id, request_id = request.request_id);
+                let ctx = &CommandContext {
+                    bus: bus.caused_by(Correlation {
+                        client_id: client_id.clone(),
+                        request_id: request.request_id,
+                    }),
+                    ..ctx.clone()
+                };
+                let response =
+                    Self::handle_command(ctx, &client_id, request.request_id, request.command)
+                        .instrument(span)
+                        .await;
+
+                let _ = ipc.send_response(&client_id, response).await;
+            }
+
+            ServerMessage::ClientConnected { client_id, info } => {
+                info!(
+                    client_id = %client_id,
+                    role = ?info.role,
+                    uid = ?info.uid,
+                    pid = ?info.pid,
+                    "Client connected"
+                );
+
+                bus.publish(DaemonEvent::ClientConnected { client_id, info });
+            }
+
+            ServerMessage::ClientDisconnected { client_id } => {
+                debug!(client_id = %client_id, "Client disconnected");
+
+                bus.publish(DaemonEvent::ClientDisconnected { client_id });
+            }
+        }
+    }
+
+    async fn handle_command(
+        ctx: &CommandContext,
+        client_id: &ClientId,
+        request_id: u64,
+        command: Command,
+    ) -> Response {
+        let CommandContext {
+            engine,
+            host,
+            volume,
+            input_devices,
+            ipc,
+            bus,
+            store,
+            recent_launches,
+            pin_attempts,
+            updater,
+            ticker,
+            config_path,
+            ..
+        } = ctx;
+        let now = shepherd_util::now();
+        let now_mono = MonotonicInstant::now();
+
+        match command {
+            Command::GetState { seat } => {
+                let seat = match Self::command_seat(ipc, client_id, seat).await {
+                    Ok(seat) => seat,
+                    Err(e) => return Response::error(request_id, e),
+                };
+                let state = engine.lock().await.get_state_on(&seat);
+                Response::success(request_id, ResponsePayload::State(state))
+            }
+
+            Command::ListEntries { at_time } => {
+                let seat = Self::client_seat(ipc, client_id).await;
+                let eng = engine.lock().await;
+                let entries = match at_time {
+                    Some(time) => eng.list_entries_on(&seat, time),
+                    None => eng.entries_on(&seat),
+                };
+                Response::success(request_id, ResponsePayload::Entries { entries })
+            }
+
+            Command::Launch { entry_id, participants, idempotency_key } => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_launch() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Shell or admin role required"),
+                        );
+                    }
+
+                if !participants.is_empty()
+                    && let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_launch_joint() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required for joint sessions"),
+                        );
+                    }
+
+                if idempotency_key.as_ref().is_some_and(|key| key.len() > idempotency::MAX_KEY_LEN) {
+                    return Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::InvalidRequest, "Idempotency key is too long"),
+                    );
+                }
+                // Only ever replayed to the same peer
+                let idempotency_key = match (idempotency_key, ipc.get_client_info(client_id).await) {
+                    (Some(key), Some(info)) => Some((KeyOwner { uid: info.uid, role: info.role }, key)),
+                    _ => None,
+                };
+                if let Some((owner, key)) = &idempotency_key
+                    && let Some(result) = recent_launches.lock().await.get(owner, key, Instant::now())
+                {
+                    info!(entry_id = %entry_id, "Repeated launch request, returning the original result");
+                    return Response {
+                        request_id,
+                        api_version: API_VERSION,
+                        result,
+                    };
+                }
+
+                let seat = Self::client_seat(ipc, client_id).await;
+                let mut eng = engine.lock().await;
+
+                let decision = if participants.is_empty() {
+                    eng.request_launch_on(&seat, &entry_id, now)
+                } else {
+                    eng.request_joint_launch(&seat, &entry_id, participants, now)
+                };
+
+                let response =
+                    Self::finish_launch(ctx, eng, decision, request_id, now, now_mono).await;
+                if let Some((owner, key)) = idempotency_key {
+                    recent_launches.lock().await.insert(owner, key, &response.result, Instant::now());
+                }
+                response
+            }
+
+            Command::ConfirmLaunch { session_id } => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_launch() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Shell or admin role required"),
+                        );
+                    }
+
+                let mut eng = engine.lock().await;
+                match eng.confirm_launch(&session_id, now) {
+                    Some(decision) => {
+                        Self::finish_launch(ctx, eng, decision, request_id, now, now_mono).await
+                    }
+                    None => Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::NotFound, "No launch awaiting confirmation"),
+                    ),
+                }
+            }
+
+            Command::StopCurrent { mode, seat } => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_stop() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Shell or admin role required"),
+                        );
+                    }
+
+                let seat = match Self::command_seat(ipc, client_id, seat).await {
+                    Ok(seat) => seat,
+                    Err(e) => return Response::error(request_id, e),
+                };
+                let mut eng = engine.lock().await;
+
+                // Get handle before stopping in engine
+                let handle = eng
+                    .session_on(&seat)
+                    .and_then(|s| s.host_handle.clone());
+
+                let reason = match mode {
+                    StopMode::Graceful => SessionEndReason::UserStop,
+                    StopMode::Force => SessionEndReason::AdminStop,
+                };
+
+                match eng.stop_on(&seat, reason.clone(), now_mono, now) {
+                    StopDecision::Stopped(result) => {
+                        info!(
+                            session_id = %result.session_id,
+                            reason = ?result.reason,
+                            "Session stopped by request"
+                        );
+                        drop(eng); // Release lock before host operations
+                        bus.publish(DaemonEvent::session_ended(result));
+
+                        // Stop the actual process
+                        if let Some(h) = handle {
+                            let host_mode = match mode {
+                                StopMode::Graceful => HostStopMode::Graceful {
+                                    timeout: Duration::from_secs(5),
+                                },
+                                StopMode::Force => HostStopMode::Force,
+                            };
+                            let _ = host.stop(&h, host_mode).await;
+                        }
+                        Self::release_input(host).await;
+
+                        Response::success(request_id, ResponsePayload::Stopped)
+                    }
+                    StopDecision::NoActiveSession => Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::NoActiveSession, "No active session"),
+                    ),
+                }
+            }
+
+            Command::ReloadConfig => {
+                // Check permission
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_reload_config() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+                        );
+                    }
+
+                // TODO: Reload from original config path
+                Response::error(
+                    request_id,
+                    ErrorInfo::new(ErrorCode::InternalError, "Reload not yet implemented"),
+                )
+            }
+
+            Command::Identify { kind, seat } => {
+                if let Some(seat) = &seat
+                    && !engine.lock().await.policy().has_seat(seat)
+                {
+                    return Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::InvalidRequest, format!("Unknown seat: {}", seat)),
+                    );
+                }
+                for replaced in ipc.identify(client_id, kind, seat).await {
+                    warn!(
+                        kind = ?replaced.kind,
+                        pid = replaced.pid,
+                        by_pid = replaced.by_pid,
+                        "Newer client instance took over, demoting the old one to observer"
+                    );
+                    bus.publish(DaemonEvent::ClientReplaced(replaced));
+                }
+                let role = ipc
+                    .get_client_info(client_id)
+                    .await
+                    .map_or(ClientRole::Observer, |info| info.role);
+                Response::success(request_id, ResponsePayload::Identified { role })
+            }
+
+            Command::SubscribeEvents => {
+                Response::success(
+                    request_id,
+                    ResponsePayload::Subscribed {
+                        client_id: client_id.clone(),
+                    },
+                )
+            }
+
+            Command::UnsubscribeEvents => {
+                Response::success(request_id, ResponsePayload::Unsubscribed)
+            }
+
+            Command::GetHealth => {
+                let (config_warnings, escape_routes) = {
+                    let engine = engine.lock().await;
+                    let warnings = engine.policy().warnings.iter().map(ToString::to_string).collect();
+                    (warnings, engine.escape_routes().to_vec())
+                };
+                let health = Self::health_status(HealthSources {
+                    host,
+                    ipc,
+                    store,
+                    updater,
+                    ticker,
+                    config_path,
+                    config_warnings,
+                    escape_routes,
+                })
+                .await;
+                Response::success(request_id, ResponsePayload::Health(health))
+            }
+
+            Command::GetCapabilities => {
+                let caps = host.capabilities();
+                let mut spawn_kinds: Vec<_> = caps.spawn_kinds_supported.iter().copied().collect();
+                spawn_kinds.sort_by_key(|kind| *kind as u8);
+                Response::success(
+                    request_id,
+                    ResponsePayload::Capabilities(ServiceCapabilities {
+                        version: env!("CARGO_PKG_VERSION").to_string(),
+                        spawn_kinds,
+                        can_freeze_session: caps.can_freeze_session,
+                        can_inhibit_input: caps.can_inhibit_input,
+                        can_read_battery: caps.can_read_battery,
+                        can_detect_idle: caps.can_detect_idle,
+                        can_control_media: caps.can_control_media,
+                        update_available: updater.as_ref().and_then(|u| u.status()),
+                    }),
+                )
+            }
+
+            Command::ExtendCurrent { by, seat } => {
+                // Check permission
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_extend() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+                        );
+                    }
+
+                let seat = match Self::command_seat(ipc, client_id, seat).await {
+                    Ok(seat) => seat,
+                    Err(e) => return Response::error(request_id, e),
+                };
+                let mut eng = engine.lock().await;
+                match eng.extend_on(&seat, by, now_mono, now) {
+                    Some(new_deadline) => {
+                        // Countdowns jump to the new time now, not at the next sync
+                        if let Some(event) = eng.sync_time_remaining_on(&seat, now_mono) {
+                            bus.publish(DaemonEvent::Core(event));
+                        }
+                        Response::success(request_id, ResponsePayload::Extended { new_deadline: Some(new_deadline) })
+                    }
+                    None => Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::NoActiveSession, "No active session or session is unlimited"),
+                    ),
+                }
+            }
+
+            Command::GrantReward { stars, reason } => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_grant_reward() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+                        );
+                    }
+
+                if stars == 0 {
+                    return Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::InvalidRequest, "Stars must be greater than 0"),
+                    );
+                }
+
+                let mut eng = engine.lock().await;
+                let total_stars = eng.grant_reward(stars, reason, now);
+
+                // Stars may unlock entries, so refresh everyone's entry list
+                bus.publish(DaemonEvent::StateChanged);
+                Response::success(request_id, ResponsePayload::RewardGranted { total_stars })
+            }
+
+            Command::SetHold { enabled, message, until } => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_set_hold() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+                        );
+                    }
+
+                if enabled && until.is_some_and(|until| until <= now) {
+                    return Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::InvalidRequest, "Hold end must be in the future"),
+                    );
+                }
+
+                let hold = enabled.then_some(Hold { message, until });
+                engine.lock().await.set_hold(hold.clone());
+
+                // Every entry changes availability at once
+                bus.publish(DaemonEvent::StateChanged);
+                Response::success(request_id, ResponsePayload::HoldSet { hold })
+            }
+
+            Command::GetVolume => {
+                let restrictions = volume::current_restrictions(engine).await;
+
+                match volume.status().await {
+                    Ok(status) => {
+                        let info = VolumeInfo {
+                            percent: status.percent,
+                            muted: status.muted,
+                            available: volume.controller().capabilities().available,
+                            backend: volume.controller().capabilities().backend.clone(),
+                            restrictions,
+                            output: status.output,
+                        };
+                        Response::success(request_id, ResponsePayload::Volume(info))
+                    }
+                    Err(e) => {
+                        let info = VolumeInfo {
+                            percent: 0,
+                            muted: false,
+                            available: false,
+                            backend: None,
+                            restrictions,
+                            output: Default::default(),
+                        };
+                        warn!(error = %e, "Failed to get volume status");
+                        Response::success(request_id, ResponsePayload::Volume(info))
+                    }
+                }
+            }
+
+            Command::SetVolume { percent } => {
+                let restrictions = volume::current_restrictions(engine).await;
+
+                if !restrictions.allow_change {
+                    return Response::success(
+                        request_id,
+                        ResponsePayload::VolumeDenied {
+                            reason: "Volume changes are not allowed".into(),
+                        },
+                    );
+                }
+
+                let clamped = restrictions.clamp_volume(percent);
+
+                match volume.controller().set_volume(clamped).await {
+                    Ok(()) => {
+                        volume.changed(bus).await;
+                        Response::success(request_id, ResponsePayload::VolumeSet)
+                    }
+                    Err(e) => Response::success(
+                        request_id,
+                        ResponsePayload::VolumeDenied {
+                            reason: e.to_string(),
+                        },
+                    ),
+                }
+            }
+
+            Command::ToggleMute => {
+                let restrictions = volume::current_restrictions(engine).await;
+
+                if !restrictions.allow_mute {
+                    return Response::success(
+                        request_id,
+                        ResponsePayload::VolumeDenied {
+                            reason: "Mute toggle is not allowed".into(),
+                        },
+                    );
+                }
+
+                if let Ok(status) = volume.status().await
+                    && status.muted
+                    && restrictions.must_mute(status.output)
+                {
+                    return Response::success(
+                        request_id,
+                        ResponsePayload::VolumeDenied {
+                            reason: "Headphones are required".into(),
+                        },
+                    );
+                }
+
+                match volume.controller().toggle_mute().await {
+                    Ok(()) => {
+                        volume.changed(bus).await;
+                        Response::success(request_id, ResponsePayload::VolumeSet)
+                    }
+                    Err(e) => Response::success(
+                        request_id,
+                        ResponsePayload::VolumeDenied {
+                            reason: e.to_string(),
+                        },
+                    ),
+                }
+            }
+
+            Command::SetMute { muted } => {
+                let restrictions = volume::current_restrictions(engine).await;
+
+                if !restrictions.allow_mute {
+                    return Response::success(
+                        request_id,
+                        ResponsePayload::VolumeDenied {
+                            reason: "Mute toggle is not allowed".into(),
+                        },
+                    );
+                }
+
+                if !muted
+                    && let Ok(status) = volume.status().await
+                    && restrictions.must_mute(status.output)
+                {
+                    return Response::success(
+                        request_id,
+                        ResponsePayload::VolumeDenied {
+                            reason: "Headphones are required".into(),
+                        },
+                    );
+                }
+
+                match volume.controller().set_mute(muted).await {
+                    Ok(()) => {
+                        volume.changed(bus).await;
+                        Response::success(request_id, ResponsePayload::VolumeSet)
+                    }
+                    Err(e) => Response::success(
+                        request_id,
+                        ResponsePayload::VolumeDenied {
+                            reason: e.to_string(),
+                        },
+                    ),
+                }
+            }
+
+            Command::GetInputDevices => match input_devices.list_devices().await {
+                Ok(devices) => {
+                    Response::success(request_id, ResponsePayload::InputDevices { devices })
+                }
+                Err(e) => {
+                    debug!(error = %e, "Failed to list input devices");
+                    Response::error(
+                        request_id,
+                        ErrorInfo::new(e.kind().code(), e.to_string()),
+                    )
+                }
+            },
+
+            Command::GetMedia => {
+                let media = media::current(engine, host).await;
+                Response::success(request_id, ResponsePayload::Media { media })
+            }
+
+            Command::GetBattery => {
+                let eng = engine.lock().await;
+                match eng.battery() {
+                    Some(battery) => Response::success(
+                        request_id,
+                        ResponsePayload::Battery {
+                            battery,
+                            low: eng.is_battery_low(&battery),
+                        },
+                    ),
+                    None => Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::Unsupported, "No battery status available"),
+                    ),
+                }
+            }
+
+            Command::GetSessionTimeline { session_id } => {
+                match shepherd_store::session_timeline(store.as_ref(), &session_id) {
+                    Ok(Some(timeline)) => {
+                        Response::success(request_id, ResponsePayload::SessionTimeline(timeline))
+                    }
+                    Ok(None) => Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::NotFound, format!("No record of session {}", session_id)),
+                    ),
+                    Err(e) => Response::error(
+                        request_id,
+                        ErrorInfo::new(e.kind().code(), format!("Failed to read session history: {}", e)),
+                    ),
+                }
+            }
+
+            Command::GetDayOverview => {
+                let overview = engine.lock().await.day_overview(now);
+                Response::success(request_id, ResponsePayload::DayOverview(overview))
+            }
+
+            Command::CancelPowerAction => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_cancel_power_action() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Shell or admin role required"),
+                        );
+                    }
+
+                if engine.lock().await.cancel_power_action() {
+                    bus.publish(DaemonEvent::Core(CoreEvent::PowerActionCancelled));
+                }
+                Response::success(request_id, ResponsePayload::PowerActionCancelled)
+            }
+
+            Command::ReleaseInput { pin } => {
+                let admin = ipc
+                    .get_client_info(client_id)
+                    .await
+                    .is_some_and(|info| info.role.can_release_input());
+                if !admin {
+                    let admin_pin = engine.lock().await.policy().service.admin_pin.clone();
+                    let Some(admin_pin) = admin_pin else {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required; no admin PIN is set"),
+                        );
+                    };
+
+                    // Locked-out attempts aren't checked, so they can't
+                    // guess either
+                    let mut attempts = pin_attempts.lock().await;
+                    if let Some(left) = attempts.locked_for(Instant::now()) {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(
+                                ErrorCode::RateLimited,
+                                format!("Too many wrong PINs, try again in {}", format_duration(left)),
+                            ),
+                        );
+                    }
+                    if !pin.as_deref().is_some_and(|pin| admin_pin.verify(pin)) {
+                        let (failures, lockout) = attempts.failed(Instant::now());
+                        warn!(failures, lockout = ?lockout, "Wrong admin PIN to release input");
+                        if let Err(e) = store.append_audit(AuditEvent::new(AuditEventType::InputReleaseFailed { failures, lockout })) {
+                            warn!(error = %e, "Failed to log wrong PIN");
+                        }
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Wrong PIN"),
+                        );
+                    }
+                    attempts.succeeded();
+                }
+
+                if !host.capabilities().can_inhibit_input {
+                    return Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::Unsupported, "Input blocking isn't supported on this host"),
+                    );
+                }
+                if let Err(e) = host.release_input().await {
+                    return Response::error(
+                        request_id,
+                        ErrorInfo::new(e.kind().code(), format!("Failed to release input: {}", e)),
+                    );
+                }
+                info!(with_pin = !admin, "Input released");
+                if let Err(e) = store.append_audit(AuditEvent::new(AuditEventType::InputReleased { with_pin: !admin })) {
+                    warn!(error = %e, "Failed to log input release");
+                }
+                Response::success(request_id, ResponsePayload::InputReleased)
+            }
+
+            Command::SubmitCheckIn { session_id, mood } => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_submit_check_in() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Shell or admin role required"),
+                        );
+                    }
+
+                let mut eng = engine.lock().await;
+                if !eng.submit_check_in(&session_id, mood, now) {
+                    return Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::InvalidRequest, "No check-in pending for this session"),
+                    );
+                }
+
+                // Let other launcher instances dismiss the prompt
+                bus.publish(DaemonEvent::StateChanged);
+                Response::success(request_id, ResponsePayload::CheckInRecorded)
+            }
+
+            Command::CheckEntryHealth => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_check_entry_health() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+                        );
+                    }
+
+                let (entries, events) = Self::probe_entry_health(engine, host).await;
+                for event in events {
+                    bus.publish(DaemonEvent::Core(event));
+                }
+                Response::success(request_id, ResponsePayload::EntryHealth { entries })
+            }
+
+            Command::DiscoverApps => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_reload_config() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+                        );
+                    }
+
+                let apps = host.discover_apps().await;
+                Response::success(request_id, ResponsePayload::DiscoveredApps { apps })
+            }
+
+            Command::ListClients => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_list_clients() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+                        );
+                    }
+
+                let mut clients = ipc.clients().await;
+                clients.sort_by_key(|c| c.pid);
+                Response::success(request_id, ResponsePayload::Clients { clients })
+            }
+
+            Command::VerifyAudit { known_head } => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_verify_audit() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+                        );
+                    }
+
+                match store.verify_audit(known_head.as_deref()) {
+                    Ok(report) => Response::success(request_id, ResponsePayload::AuditVerified(report)),
+                    Err(e) => Response::error(
+                        request_id,
+                        ErrorInfo::new(e.kind().code(), format!("Failed to read the audit log: {}", e)),
+                    ),
+                }
+            }
+
+            Command::SaveConfig { config } => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_reload_config() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+                        );
+                    }
+
+                // Never write a config the service couldn't load on restart
+                let policy = match parse_config(&config) {
+                    Ok(policy) => policy,
+                    Err(e) => {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::InvalidRequest, format!("Invalid config: {}", e)),
+                        );
+                    }
+                };
+
+                if let Err(e) = write_config_file(config_path, &config) {
+                    error!(config_path = %config_path.display(), error = %e, "Failed to write config");
+                    return Response::error(
+                        request_id,
+                        ErrorInfo::new(
+                            ErrorKind::from_io(&e).code(),
+                            format!("Failed to write config: {}", e),
+                        ),
+                    );
+                }
+
+                let entry_count = policy.entries.len();
+                info!(config_path = %config_path.display(), entry_count, "Config saved");
+                let _ = store.append_audit(AuditEvent::new(AuditEventType::ConfigReloaded { success: true }));
+                log_config_warnings(&policy);
+
+                for event in engine.lock().await.reload_policy(policy) {
+                    bus.publish(DaemonEvent::Core(event));
+                }
+                Response::success(request_id, ResponsePayload::ConfigSaved { entry_count })
+            }
+
+            Command::GetPolicy => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_reload_config() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+                        );
+                    }
+
+                match load_raw_config(config_path) {
+                    Ok(raw) => Response::success(request_id, ResponsePayload::Policy(policy_view(&raw))),
+                    Err(e) => Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::ConfigError, format!("Failed to read config: {}", e)),
+                    ),
+                }
+            }
+
+            Command::PatchPolicy { ops } => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_reload_config() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+                        );
+                    }
+
+                let loaded = std::fs::read_to_string(config_path)
+                    .map_err(Into::into)
+                    .and_then(|content| parse_raw_config(&content).map(|raw| (content, raw)));
+                let (content, raw) = match loaded {
+                    Ok(loaded) => loaded,
+                    Err(e) => {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::ConfigError, format!("Failed to read config: {}", e)),
+                        );
+                    }
+                };
+                let patched = match patch_config(&raw, &ops) {
+                    Ok(patched) => patched,
+                    Err(e) => {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::InvalidRequest, format!("Invalid policy edit: {}", e)),
+                        );
+                    }
+                };
+
+                if patched.changes.is_empty() {
+                    return Response::success(request_id, ResponsePayload::Policy(policy_view(&patched.raw)));
+                }
+
+                // Edit the parent's file in place rather than writing a normalized dump
+                let written = patch_document(&content, &patched.raw, &ops).and_then(|content| {
+                    write_config_file(config_path, &content).map_err(|e| e.to_string())
+                });
+                if let Err(e) = written {
+                    error!(config_path = %config_path.display(), error = %e, "Failed to write config");
+                    return Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::InternalError, format!("Failed to write config: {}", e)),
+                    );
+                }
+
+                let entry_count = patched.policy.entries.len();
+                info!(changes = patched.changes.len(), entry_count, "Policy patched");
+                for change in patched.changes {
+                    let _ = store.append_audit(AuditEvent::new(AuditEventType::PolicyPatched {
+                        old: change.old,
+                        new: change.new,
+                    }));
+                }
+
+                let view = policy_view(&patched.raw);
+                log_config_warnings(&patched.policy);
+                for event in engine.lock().await.reload_policy(patched.policy) {
+                    bus.publish(DaemonEvent::Core(event));
+                }
+                Response::success(request_id, ResponsePayload::Policy(view))
+            }
+
+            Command::SimulatePolicy { ops, at_time } => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_reload_config() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+                        );
+                    }
+
+                let policy = match load_raw_config(config_path)
+                    .and_then(|raw| patch_config(&raw, &ops))
+                {
+                    Ok(patched) => patched.policy,
+                    Err(e) => {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::InvalidRequest, format!("Invalid policy edit: {}", e)),
+                        );
+                    }
+                };
+
+                let time = at_time.unwrap_or(now);
+                let entries = engine.lock().await.simulate_entries(policy, time);
+                Response::success(request_id, ResponsePayload::Entries { entries })
+            }
+
+            Command::ImportUsage { source, profile_id, usage } => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_import_usage() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+                        );
+                    }
+
+                let mut eng = engine.lock().await;
+                if source.trim().is_empty() {
+                    return Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::InvalidRequest, "Import source must not be empty"),
+                    );
+                }
+                if let Some(profile_id) = &profile_id
+                    && eng.policy().get_profile(profile_id).is_none()
+                {
+                    return Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::NotFound, format!("Unknown profile: {}", profile_id)),
+                    );
+                }
+                if let Some(unknown) = usage.iter().find(|u| eng.policy().get_entry(&u.entry_id).is_none()) {
+                    return Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::EntryNotFound, format!("Unknown entry: {}", unknown.entry_id)),
+                    );
+                }
+
+                match eng.import_usage(&source, profile_id.as_ref(), &usage) {
+                    Ok(changed) => {
+                        drop(eng);
+                        bus.publish(DaemonEvent::StateChanged);
+                        Response::success(request_id, ResponsePayload::UsageImported { changed })
+                    }
+                    Err(e) => Response::error(
+                        request_id,
+                        ErrorInfo::new(e.kind().code(), format!("Failed to import usage: {}", e)),
+                    ),
+                }
+            }
+
+            Command::SetMockTime { op } => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_set_mock_time() {
+                        return Response::error(
+                            request_id,
+                            ErrorInfo::new(ErrorCode::PermissionDenied, "Admin role required"),
+                        );
+                    }
+
+                let result = match op {
+                    MockTimeOp::Set { time } => shepherd_util::set_mock_time(time),
+                    MockTimeOp::Advance { seconds } => {
+                        shepherd_util::advance_mock_time(chrono::Duration::seconds(seconds))
+                    }
+                    MockTimeOp::Freeze => shepherd_util::freeze_mock_time(true),
+                    MockTimeOp::Resume => shepherd_util::freeze_mock_time(false),
+                    MockTimeOp::Clear => shepherd_util::clear_mock_time(),
+                };
+
+                match result {
+                    Ok(now) => {
+                        // Availability may have changed with the clock
+                        bus.publish(DaemonEvent::StateChanged);
+                        Response::success(
+                            request_id,
+                            ResponsePayload::MockTime {
+                                now,
+                                frozen: shepherd_util::is_mock_time_frozen(),
+                            },
+                        )
+                    }
+                    Err(e) => Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::InvalidRequest, e.to_string()),
+                    ),
+                }
+            }
+
+            Command::Ping => Response::success(request_id, ResponsePayload::Pong),
+        }
+    }
+
+    /// Precheck and spawn an approved launch, or turn any other decision
+    /// into its response
+    async fn finish_launch(
+        ctx: &CommandContext,
+        mut eng: tokio::sync::MutexGuard<'_, CoreEngine>,
+        decision: LaunchDecision,
+        request_id: u64,
+        now: chrono::DateTime<chrono::Local>,
+        now_mono: MonotonicInstant,
+    ) -> Response {
+        let CommandContext { engine, host, bus, .. } = ctx;
+        // Catch missing programs, a full disk, etc. before spawning
+        let decision = match decision {
+            LaunchDecision::Approved(plan) => {
+                let reasons = match eng.policy().get_entry(&plan.entry_id) {
+                    Some(entry) => {
+                        let checks = Self::convert_precheck_policy(&entry.prechecks);
+                        host.precheck(&entry.kind, &checks).await
+                    }
+                    None => Vec::new(),
+                };
+                if reasons.is_empty() {
+                    LaunchDecision::Approved(plan)
+                } else {
+                    eng.reject_launch(&plan.entry_id, reasons)
+                }
+            }
+            other => other,
+        };
+        match decision {
+            LaunchDecision::Approved(plan) => {
+                // Start the session in the engine
+                let event = eng.start_session(plan.clone(), now, now_mono);
+
+                // Get the entry kind for spawning
+                let entry_kind = eng
+                    .policy()
+                    .get_entry(&plan.entry_id)
+                    .map(|e| e.kind.clone());
+                let gamescope = eng
+                    .policy()
+                    .get_entry(&plan.entry_id)
+                    .and_then(|e| e.gamescope.as_ref())
+                    .map(Self::convert_gamescope_policy);
+                let hooks = eng
+                    .policy()
+                    .get_entry(&plan.entry_id)
+                    .map(|e| e.hooks.clone())
+                    .unwrap_or_default();
+                let kill_by_name = eng
+                    .policy()
+                    .get_entry(&plan.entry_id)
+                    .is_some_and(|e| e.kill_by_name);
+                let minecraft = eng
+                    .policy()
+                    .get_entry(&plan.entry_id)
+                    .and_then(|e| e.minecraft.as_ref())
+                    .map(Self::convert_minecraft_policy);
+                let wine = eng
+                    .policy()
+                    .get_entry(&plan.entry_id)
+                    .and_then(|e| e.wine.as_ref())
+                    .map(|wine| WineOptions {
+                        prefix: wine.prefix.clone(),
+                        start_timeout: wine.start_timeout,
+                    });
+                let pre_launch = hooks.pre_launch.as_ref().map(Self::convert_hook_policy);
+                let post_exit = hooks.post_exit.as_ref().map(Self::convert_hook_policy);
+                let wayland_display = eng
+                    .policy()
+                    .get_seat(&plan.seat)
+                    .and_then(|s| s.wayland_display.clone());
+                let controller_only = eng
+                    .policy()
+                    .get_entry(&plan.entry_id)
+                    .is_some_and(|e| e.controller_only);
+                let shared_input = !eng.policy().seats.is_empty();
+
+                // Build spawn options with log path if capture_child_output is enabled
+                let spawn_options = if eng.policy().service.capture_child_output {
+                    let log_dir = &eng.policy().service.child_log_dir;
+                    // Create log filename: <entry_id>_<session_id>_<timestamp>.log
+                    let timestamp = now.format("%Y%m%d_%H%M%S").to_string();
+                    let log_filename = format!(
+                        "{}_{}.log",
+                        plan.entry_id,
+                        timestamp
+                    );
+                    let log_path = log_dir.join(log_filename);
+                    shepherd_host_api::SpawnOptions {
+                        capture_stdout: true,
+                        capture_stderr: true,
+                        log_path: Some(log_path),
+                        gamescope,
+                        device_access: plan.device_access,
+                        pre_launch,
+                        post_exit,
+                        minecraft,
+                        wine,
+                        kill_by_name,
+                        wayland_display,
+                        fullscreen: true,
+                        foreground: true,
+                    }
+                } else {
+                    shepherd_host_api::SpawnOptions {
+                        gamescope,
+                        device_access: plan.device_access,
+                        pre_launch,
+                        post_exit,
+                        minecraft,
+                        wine,
+                        kill_by_name,
+                        wayland_display,
+                        fullscreen: true,
+                        foreground: true,
+                        ..Default::default()
+                    }
+                };
+
+                drop(eng); // Release lock before spawning
+
+                if let Some(kind) = entry_kind {
+                    match host
+                        .spawn(
+                            plan.session_id.clone(),
+                            &kind,
+                            spawn_options,
+                        )
+                        .await
+                    {
+                        Ok(handle) => {
+                            if controller_only {
+                                Self::inhibit_controller_only(host, shared_input).await;
+                            }
+
+                            // Attach handle to session
+                            let mut eng = engine.lock().await;
+                            eng.attach_host_handle(handle);
+
+                            if let CoreEvent::SessionStarted {
+                                ref session_id,
+                                deadline,
+                                ..
+                            } = event
+                            {
+                                let session_id = session_id.clone();
+                                bus.publish(DaemonEvent::Core(event));
+
+                                Response::success(
+                                    request_id,
+                                    ResponsePayload::LaunchApproved {
+                                        session_id,
+                                        deadline,
+                                    },
+                                )
+                            } else {
+                                Response::error(
+                                    request_id,
+                                    ErrorInfo::new(ErrorCode::InternalError, "Unexpected event"),
+                                )
+                            }
+                        }
+                        Err(e) => {
+                            // End the session as a failed launch and broadcast to subscribers
+                            let diagnostics = e.spawn_diagnostics();
+                            let reason = SessionEndReason::LaunchFailed {
+                                error: e.to_string(),
+                                diagnostics: diagnostics.clone(),
+                            };
+                            let mut eng = engine.lock().await;
+                            if let StopDecision::Stopped(result) = eng.stop_on(&plan.seat, reason, now_mono, now) {
+                                bus.publish(DaemonEvent::session_ended(result));
+                            }
+
+                            let message = match &diagnostics {
+                                Some(d) => d.summary(),
+                                None => format!("Spawn failed: {}", e),
+                            };
+                            Response::error(
+                                request_id,
+                                ErrorInfo::new(e.kind().code(), message)
+                                    .with_spawn_diagnostics(diagnostics),
+                            )
+                        }
+                    }
+                } else {
+                    Response::error(
+                        request_id,
+                        ErrorInfo::new(ErrorCode::EntryNotFound, "Entry not found"),
+                    )
+                }
+            }
+            LaunchDecision::NeedsConfirmation(plan) => {
+                let label = eng
+                    .policy()
+                    .get_entry(&plan.entry_id)
+                    .map(|e| e.label.clone())
+                    .unwrap_or_default();
+                let deadline = plan
+                    .max_duration
+                    .and_then(|d| chrono::Duration::from_std(d).ok())
+                    .map(|d| now + d);
+                Response::success(
+                    request_id,
+                    ResponsePayload::LaunchNeedsConfirmation {
+                        session_id: plan.session_id,
+                        entry_id: plan.entry_id,
+                        label,
+                        max_duration: plan.max_duration,
+                        deadline,
+                    },
+                )
+            }
+            LaunchDecision::Denied { reasons } => {
+                Response::success(request_id, ResponsePayload::LaunchDenied { reasons })
+            }
+        }
+    }
+
+    /// Probe every entry through the host and record the results in the engine
+    async fn probe_entry_health(
+        engine: &Arc<Mutex<CoreEngine>>,
+        host: &Arc<dyn HostAdapter>,
+    ) -> (Vec<EntryHealth>, Vec<CoreEvent>) {
+        // Don't hold the engine lock while probes shell out
+        let kinds: Vec<_> = {
+            let eng = engine.lock().await;
+            eng.policy()
+                .entries
+                .iter()
+                .map(|e| (e.id.clone(), e.kind.clone()))
+                .collect()
+        };
+
+        let mut results = Vec::with_capacity(kinds.len());
+        for (entry_id, kind) in kinds {
+            let problem = host.check_entry_health(&kind).await;
+            results.push(EntryHealth { entry_id, problem });
+        }
+
+        let now = shepherd_util::now();
+        let mut eng = engine.lock().await;
+        let events = results
+            .iter()
+            .filter_map(|h| eng.set_entry_health(&h.entry_id, h.problem.clone(), now))
+            .collect();
+        (results, events)
+    }
+
+    /// Start the next due app update, if any. The entry stays disabled until
+    /// the update finishes.
+    async fn start_due_update(engine: &Arc<Mutex<CoreEngine>>, host: &Arc<dyn HostAdapter>) {
+        let next = {
+            let mut eng = engine.lock().await;
+            let Some(entry_id) = eng.next_update(shepherd_util::now()) else {
+                return;
+            };
+            let Some(kind) = eng.policy().get_entry(&entry_id).map(|e| e.kind.clone()) else {
+                return;
+            };
+            eng.begin_update(&entry_id);
+            (entry_id, kind)
+        };
+
+        let engine = engine.clone();
+        let host = host.clone();
+        tokio::spawn(async move {
+            let (entry_id, kind) = next;
+            let result = host.update_entry(&kind).await.map_err(|e| e.to_string());
+            engine
+                .lock()
+                .await
+                .finish_update(&entry_id, result, shepherd_util::now());
+        });
+    }
+
+    async fn health_status(sources: HealthSources<'_>) -> HealthStatus {
+        let HealthSources {
+            host,
+            ipc,
+            store,
+            updater,
+            ticker,
+            config_path,
+            config_warnings,
+            escape_routes,
+        } = sources;
+        let started = std::time::Instant::now();
+        let store_ok = store.is_healthy();
+        let store_latency = started.elapsed();
+
+        let host_connection = match (host.is_remote(), host.is_healthy()) {
+            (false, _) => HostConnection::InProcess,
+            (true, true) => HostConnection::Connected,
+            (true, false) => HostConnection::Disconnected,
+        };
+
+        HealthStatus {
+            live: true,
+            ready: true,
+            policy_loaded: true,
+            host_adapter_ok: host.is_healthy(),
+            store_ok,
+            store: StoreHealth {
+                latency_us: store_latency.as_micros() as u64,
+                last_audit_write: store.last_audit_write(),
+                pending_writes: store.pending_writes(),
+            },
+            ipc_clients: ipc.client_count().await,
+            host_monitor_age_ms: host
+                .last_monitor_tick()
+                .map(|tick| tick.elapsed().as_millis() as u64),
+            host_connection,
+            config_modified: std::fs::metadata(config_path)
+                .and_then(|m| m.modified())
+                .ok()
+                .map(chrono::DateTime::<chrono::Local>::from),
+            update_available: updater.as_ref().and_then(|u| u.status()),
+            config_warnings,
+            escape_routes,
+            tick: ticker.health(),
+        }
+    }
+
+    /// Seat a client identified with, or the default seat
+    async fn client_seat(ipc: &IpcServer, client_id: &ClientId) -> SeatId {
+        ipc.get_client_info(client_id)
+            .await
+            .and_then(|info| info.seat)
+            .unwrap_or_default()
+    }
+
+    /// Seat a command acts on: the one it names, which only admins may
+    /// pick, or the client's own
+    async fn command_seat(
+        ipc: &IpcServer,
+        client_id: &ClientId,
+        seat: Option<SeatId>,
+    ) -> Result<SeatId, ErrorInfo> {
+        let own = Self::client_seat(ipc, client_id).await;
+        match seat {
+            Some(seat) if seat != own => {
+                if let Some(info) = ipc.get_client_info(client_id).await
+                    && !info.role.can_pick_seat() {
+                        return Err(ErrorInfo::new(
+                            ErrorCode::PermissionDenied,
+                            "Admin role required for another seat",
+                        ));
+                    }
+                Ok(seat)
+            }
+            _ => Ok(own),
+        }
+    }
+
+    /// Leave only game controllers working for a controller-only session.
+    /// Released with the rest of input blocking when the session ends.
+    async fn inhibit_controller_only(host: &Arc<dyn HostAdapter>, shared_input: bool) {
+        if shared_input {
+            warn!("Controller-only input isn't applied with several seats, which share input devices");
+        } else if !host.capabilities().can_inhibit_input {
+            warn!("Controller-only input isn't supported on this host");
+        } else if let Err(e) = host.inhibit_input().await {
+            warn!(error = %e, "Failed to block keyboard and mouse for controller-only session");
+        }
+    }
+
+    async fn release_input(host: &Arc<dyn HostAdapter>) {
+        if host.capabilities().can_inhibit_input
+            && let Err(e) = host.release_input().await
+        {
+            warn!(error = %e, "Failed to release input");
+        }
+    }
+
+    fn convert_gamescope_policy(policy: &GamescopePolicy) -> GamescopeOptions {
+        GamescopeOptions {
+            resolution: policy.resolution,
+            fps_limit: policy.fps_limit,
+            fullscreen: policy.fullscreen,
+            extra_args: policy.extra_args.clone(),
+        }
+    }
+
+    fn convert_hook_policy(policy: &HookPolicy) -> SessionHook {
+        let mut argv = vec![policy.command.clone()];
+        argv.extend(policy.args.iter().cloned());
+        SessionHook {
+            argv,
+            timeout: policy.timeout,
+            abort_on_failure: policy.on_failure == HookFailure::Abort,
+        }
+    }
+
+    fn convert_minecraft_policy(policy: &MinecraftPolicy) -> MinecraftOptions {
+        MinecraftOptions {
+            game_dir: policy.game_dir.clone(),
+            servers: policy.servers.as_ref().map(|servers| {
+                servers
+                    .iter()
+                    .map(|s| shepherd_host_api::MinecraftServer {
+                        name: s.name.clone(),
+                        address: s.address.clone(),
+                    })
+                    .collect()
+            }),
+            disable_chat: policy.disable_chat,
+            backups: policy.backups,
+        }
+    }
+
+    fn convert_precheck_policy(policy: &PrecheckPolicy) -> LaunchPrechecks {
+        LaunchPrechecks {
+            check_binary: policy.check_binary,
+            require_env: policy.require_env.clone(),
+            min_free_disk: policy.min_free_disk.clone(),
+            require_display: policy.require_display,
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Event bus subscribers
+
+use shepherd_api::{Event, EventPayload};
+use shepherd_core::{CoreEngine, CoreEvent};
+use shepherd_host_api::BrightnessController;
+use shepherd_ipc::IpcServer;
+use shepherd_store::{AuditEvent, AuditEventType, Store};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::bus::{self, DaemonEvent, EventBus, Published};
+
+/// Forward events to subscribed IPC clients, followed by a fresh state
+/// snapshot for each seat whenever the entry list or a session may have
+/// changed. Events about a session only go to the clients on its seat.
+pub fn spawn_ipc_forwarder(bus: &EventBus, engine: Arc<Mutex<CoreEngine>>, ipc: Arc<IpcServer>) {
+    bus.spawn_subscriber("ipc", move |Published { event, cause }| {
+        let engine = engine.clone();
+        let ipc = ipc.clone();
+        async move {
+            let payload = match &event {
+                DaemonEvent::Core(core) => bus::ipc_payload(core),
+                DaemonEvent::VolumeChanged { percent, muted } => Some(EventPayload::VolumeChanged {
+                    percent: *percent,
+                    muted: *muted,
+                }),
+                DaemonEvent::VolumeRestrictionsChanged(restrictions) => {
+                    Some(EventPayload::VolumeRestrictionsChanged {
+                        restrictions: restrictions.clone(),
+                    })
+                }
+                DaemonEvent::MediaChanged(media) => Some(EventPayload::MediaChanged {
+                    media: media.clone(),
+                }),
+                // Tells the old instance to exit
+                DaemonEvent::ClientReplaced(replaced) => Some(EventPayload::ClientReplaced {
+                    kind: replaced.kind,
+                    pid: replaced.pid,
+                    by_pid: replaced.by_pid,
+                }),
+                DaemonEvent::BudgetAlert {
+                    profile_id,
+                    label,
+                    percent,
+                    used,
+                    budget,
+                } => Some(EventPayload::BudgetAlert {
+                    profile_id: profile_id.clone(),
+                    label: label.clone(),
+                    percent: *percent,
+                    used: *used,
+                    budget: *budget,
+                }),
+                _ => None,
+            };
+            let seat = match &event {
+                DaemonEvent::Core(core) => match (bus::event_seat(core), bus::event_session(core)) {
+                    (Some(seat), _) => Some(seat.clone()),
+                    (None, Some(session_id)) => engine.lock().await.seat_of(session_id),
+                    (None, None) => None,
+                },
+                _ => None,
+            };
+            if let Some(payload) = payload {
+                ipc.broadcast_event(Event::new(payload).caused_by(cause.clone()).for_seat(seat.clone()));
+            }
+            if let DaemonEvent::Core(CoreEvent::SessionEnded { summary, .. }) = &event {
+                let payload = EventPayload::SessionSummary(summary.as_ref().clone());
+                ipc.broadcast_event(Event::new(payload).caused_by(cause.clone()).for_seat(seat));
+            }
+
+            if bus::refreshes_state(&event) {
+                let engine = engine.lock().await;
+                for seat in engine.policy().seat_ids() {
+                    let state = engine.get_state_on(&seat);
+                    ipc.broadcast_event(
+                        Event::new(EventPayload::StateChanged(state))
+                            .caused_by(cause.clone())
+                            .for_seat(Some(seat)),
+                    );
+                }
+            }
+        }
+    });
+}
+
+/// Apply scheduled brightness and night light
+pub fn spawn_display(bus: &EventBus, brightness: Arc<dyn BrightnessController>) {
+    bus.spawn_subscriber("display", move |Published { event, .. }| {
+        let brightness = brightness.clone();
+        async move {
+            let DaemonEvent::Core(CoreEvent::DisplaySettingsChanged(settings)) = event else {
+                return;
+            };
+
+            info!(
+                brightness = ?settings.brightness,
+                night_light = ?settings.night_light,
+                "Applying scheduled display settings"
+            );
+
+            if let Some(percent) = settings.brightness
+                && brightness.capabilities().available
+                && let Err(e) = brightness.set_brightness(percent).await
+            {
+                warn!(error = %e, "Failed to set brightness");
+            }
+
+            if brightness.capabilities().can_night_light
+                && let Err(e) = brightness.set_night_light(settings.night_light).await
+            {
+                warn!(error = %e, "Failed to set night light");
+            }
+        }
+    });
+}
+
+/// Record client connections, takeovers and budget alerts in the audit log. Session and policy events
+/// are audited by the engine itself.
+pub fn spawn_audit(bus: &EventBus, store: Arc<dyn Store>) {
+    bus.spawn_subscriber("audit", move |Published { event, .. }| {
+        let store = store.clone();
+        async move {
+            let event_type = match event {
+                DaemonEvent::ClientConnected { client_id, info } => AuditEventType::ClientConnected {
+                    client_id: client_id.to_string(),
+                    role: format!("{:?}", info.role),
+                    uid: info.uid,
+                },
+                DaemonEvent::ClientDisconnected { client_id } => AuditEventType::ClientDisconnected {
+                    client_id: client_id.to_string(),
+                },
+                DaemonEvent::ClientReplaced(replaced) => AuditEventType::ClientReplaced {
+                    kind: format!("{:?}", replaced.kind),
+                    pid: replaced.pid,
+                    by_pid: replaced.by_pid,
+                },
+                DaemonEvent::BudgetAlert {
+                    profile_id,
+                    percent,
+                    used,
+                    budget,
+                    ..
+                } => AuditEventType::BudgetAlert {
+                    profile_id,
+                    percent,
+                    used,
+                    budget,
+                },
+                _ => return,
+            };
+            if let Err(e) = store.append_audit(AuditEvent::new(event_type)) {
+                warn!(error = %e, "Failed to write audit event");
+            }
+        }
+    });
+}
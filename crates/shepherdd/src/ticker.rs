@@ -0,0 +1,104 @@
+//! When the main loop ticks the engine
+//!
+//! Instead of a fixed rate, the loop asks the engine when it next needs a
+//! tick (`CoreEngine::next_tick`) and sleeps until then, so an idle kiosk
+//! wakes about once a minute. `max_tick_interval` bounds the sleep in case a
+//! change made outside the loop, or a wall-clock jump, moves a deadline
+//! closer. Every tick is counted for `GetHealth`.
+
+use shepherd_api::TickHealth;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Shortest sleep, so a deadline that is already due doesn't spin the loop
+const MIN_WAIT: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Default)]
+struct Stats {
+    ticks: u64,
+    last_started: Option<Instant>,
+    last_duration: Duration,
+    max_duration: Duration,
+    last_interval: Option<Duration>,
+    next_at: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+pub struct Ticker {
+    stats: Mutex<Stats>,
+}
+
+impl Ticker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn stats(&self) -> std::sync::MutexGuard<'_, Stats> {
+        self.stats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record a tick that started at `started` and has just finished
+    pub fn record(&self, started: Instant) {
+        let duration = started.elapsed();
+        let mut stats = self.stats();
+        stats.ticks += 1;
+        stats.last_interval = stats.last_started.map(|last| started.duration_since(last));
+        stats.last_started = Some(started);
+        stats.last_duration = duration;
+        stats.max_duration = stats.max_duration.max(duration);
+    }
+
+    /// When to tick next, given what the engine asked for and the longest
+    /// allowed wait
+    pub fn schedule(&self, wanted: Duration, max_interval: Duration) -> Instant {
+        let at = Instant::now() + wanted.clamp(MIN_WAIT, max_interval.max(MIN_WAIT));
+        self.stats().next_at = Some(at);
+        at
+    }
+
+    pub fn health(&self) -> TickHealth {
+        let stats = self.stats();
+        TickHealth {
+            ticks: stats.ticks,
+            last_duration_us: stats.last_duration.as_micros() as u64,
+            max_duration_us: stats.max_duration.as_micros() as u64,
+            last_interval_ms: stats.last_interval.map(|i| i.as_millis() as u64),
+            next_tick_in_ms: stats
+                .next_at
+                .map(|at| at.saturating_duration_since(Instant::now()).as_millis() as u64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_is_bounded() {
+        let ticker = Ticker::new();
+        let max = Duration::from_secs(10);
+
+        let at = ticker.schedule(Duration::from_secs(45), max);
+        assert!(at <= Instant::now() + max);
+        let at = ticker.schedule(Duration::ZERO, max);
+        assert!(at > Instant::now());
+
+        let health = ticker.health();
+        assert!(health.next_tick_in_ms.unwrap() <= MIN_WAIT.as_millis() as u64);
+    }
+
+    #[test]
+    fn test_record() {
+        let ticker = Ticker::new();
+        assert_eq!(ticker.health().ticks, 0);
+
+        let first = Instant::now();
+        ticker.record(first);
+        ticker.record(first + Duration::from_millis(5));
+        let health = ticker.health();
+        assert_eq!(health.ticks, 2);
+        assert_eq!(health.last_interval_ms, Some(5));
+        assert!(health.max_duration_us >= health.last_duration_us);
+    }
+}
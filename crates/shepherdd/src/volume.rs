@@ -0,0 +1,653 @@
+//! Cached output volume
+//!
+//! Reading the volume spawns `wpctl` or `pactl`, so the HUD asking on every
+//! redraw used to cost a process each time. When the controller can push
+//! changes ([`VolumeController::watch`]), the last pushed status answers
+//! `GetVolume` and every change, including ones made outside shepherd
+//! (hardware keys, a game's own slider), is broadcast as `VolumeChanged`.
+//! Otherwise the volume is read on each request as before.
+//!
+//! Changes made outside shepherd are held to the same [`VolumePolicy`] as
+//! `SetVolume`: a volume above the cap is turned back down, and one that
+//! may not change at all is put back.
+//!
+//! Quiet hours lower the cap for a while; when they start, a louder volume
+//! is turned down ([`spawn_quiet_hours`]).
+//!
+//! Where headphones are required, the speakers are muted as soon as the
+//! backend reports sound going to them, e.g. when headphones are unplugged
+//! mid-game. The engine is told the output too, so it can refuse launches.
+//!
+//! With `duck_warnings` set, a warning turns the session's own streams
+//! down for a few seconds so it gets noticed over the game
+//! ([`spawn_warning_ducking`]).
+
+use shepherd_api::VolumeRestrictions;
+use shepherd_config::VolumePolicy;
+use shepherd_core::{CoreEngine, CoreEvent};
+use shepherd_host_api::{VolumeController, VolumeResult, VolumeStatus};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::bus::{DaemonEvent, EventBus, Published};
+
+/// How long a warning keeps the session's sound turned down
+const DUCK_DURATION: Duration = Duration::from_secs(5);
+
+pub struct VolumeCache {
+    controller: Arc<dyn VolumeController>,
+    status: Mutex<Option<VolumeStatus>>,
+    /// Whether pushed changes keep `status` current
+    watched: AtomicBool,
+    changes: Mutex<Option<mpsc::UnboundedReceiver<VolumeStatus>>>,
+}
+
+impl VolumeCache {
+    pub fn new(controller: Arc<dyn VolumeController>) -> Self {
+        let changes = controller.watch();
+        Self {
+            watched: AtomicBool::new(changes.is_some()),
+            changes: Mutex::new(changes),
+            status: Mutex::new(None),
+            controller,
+        }
+    }
+
+    pub fn controller(&self) -> &Arc<dyn VolumeController> {
+        &self.controller
+    }
+
+    pub fn is_watched(&self) -> bool {
+        self.watched.load(Ordering::SeqCst)
+    }
+
+    /// The current volume, from the cache when changes are watched
+    pub async fn status(&self) -> VolumeResult<VolumeStatus> {
+        if self.is_watched()
+            && let Some(status) = self.status.lock().unwrap().clone()
+        {
+            return Ok(status);
+        }
+        let status = self.controller.get_status().await?;
+        *self.status.lock().unwrap() = Some(status.clone());
+        Ok(status)
+    }
+
+    /// Record a new status; true if it differs from the last one
+    pub fn update(&self, status: VolumeStatus) -> bool {
+        let mut cached = self.status.lock().unwrap();
+        if cached.as_ref() == Some(&status) {
+            return false;
+        }
+        *cached = Some(status);
+        true
+    }
+
+    /// After this service changed the volume: announce the new status,
+    /// unless the watcher is about to
+    pub async fn changed(&self, bus: &EventBus) {
+        if self.is_watched() {
+            return;
+        }
+        if let Ok(status) = self.controller.get_status().await {
+            self.update(status.clone());
+            bus.publish(DaemonEvent::VolumeChanged {
+                percent: status.percent,
+                muted: status.muted,
+            });
+        }
+    }
+
+    /// Publish pushed changes until the controller stops sending them,
+    /// then fall back to reading on request. Changes the policy forbids
+    /// are undone instead; the corrected volume is published when it
+    /// comes back.
+    pub fn spawn_watcher(
+        self: &Arc<Self>,
+        bus: EventBus,
+        engine: Arc<tokio::sync::Mutex<CoreEngine>>,
+    ) {
+        let Some(mut changes) = self.changes.lock().unwrap().take() else {
+            return;
+        };
+        let cache = self.clone();
+        tokio::spawn(async move {
+            while let Some(status) = changes.recv().await {
+                engine.lock().await.set_audio_output(status.output);
+                let previous = cache.status.lock().unwrap().clone();
+                let restrictions = current_restrictions(&engine).await;
+                if let Some(allowed) = enforce(previous.as_ref(), &status, &restrictions) {
+                    info!(
+                        percent = status.percent,
+                        muted = status.muted,
+                        allowed_percent = allowed.percent,
+                        allowed_muted = allowed.muted,
+                        "Volume changed against policy, reverting"
+                    );
+                    match cache.apply(&status, &allowed).await {
+                        Ok(()) => continue,
+                        Err(e) => warn!(error = %e, "Failed to revert volume change"),
+                    }
+                }
+                if cache.update(status.clone()) {
+                    bus.publish(DaemonEvent::VolumeChanged {
+                        percent: status.percent,
+                        muted: status.muted,
+                    });
+                }
+            }
+            warn!("Volume changes no longer pushed, reading on request");
+            cache.watched.store(false, Ordering::SeqCst);
+        });
+    }
+
+    /// Move from `status` to `target`
+    async fn apply(&self, status: &VolumeStatus, target: &VolumeStatus) -> VolumeResult<()> {
+        if target.percent != status.percent {
+            self.controller.set_volume(target.percent).await?;
+        }
+        if target.muted != status.muted {
+            self.controller.set_mute(target.muted).await?;
+        }
+        Ok(())
+    }
+}
+
+/// The volume rules in force: the running entry's, or the global ones,
+/// limited further by quiet hours. Headphones are required if any of them
+/// requires them.
+pub async fn current_restrictions(engine: &tokio::sync::Mutex<CoreEngine>) -> VolumeRestrictions {
+    let eng = engine.lock().await;
+    let global = &eng.policy().volume;
+
+    // The active session's volume restrictions, falling back to global policy
+    let mut restrictions = match eng
+        .current_session()
+        .and_then(|session| eng.policy().get_entry(&session.plan.entry_id))
+        .and_then(|entry| entry.volume.as_ref())
+    {
+        Some(vol_policy) => convert_volume_policy(vol_policy),
+        None => convert_volume_policy(global),
+    };
+
+    let quiet_hours = eng.quiet_hours();
+    if let Some(cap) = quiet_hours.max_volume {
+        restrictions.max_volume = Some(restrictions.max_volume.map_or(cap, |max| max.min(cap)));
+        restrictions.min_volume = restrictions.min_volume.map(|min| min.min(cap));
+    }
+    restrictions.require_headphones |= global.require_headphones || quiet_hours.require_headphones;
+    restrictions
+}
+
+/// When quiet hours start or end, tell clients the new range, turn a
+/// volume above the new cap down and mute the speakers if headphones are
+/// now required
+pub fn spawn_quiet_hours(
+    bus: &EventBus,
+    engine: Arc<tokio::sync::Mutex<CoreEngine>>,
+    volume: Arc<VolumeCache>,
+) {
+    let publisher = bus.clone();
+    bus.spawn_subscriber("quiet-hours", move |Published { event, .. }| {
+        let engine = engine.clone();
+        let volume = volume.clone();
+        let bus = publisher.clone();
+        async move {
+            let DaemonEvent::Core(CoreEvent::QuietHoursChanged(quiet_hours)) = event else {
+                return;
+            };
+            info!(
+                max_volume = ?quiet_hours.max_volume,
+                require_headphones = quiet_hours.require_headphones,
+                "Quiet hours changed"
+            );
+
+            let restrictions = current_restrictions(&engine).await;
+            bus.publish(DaemonEvent::VolumeRestrictionsChanged(restrictions.clone()));
+
+            let Ok(status) = volume.status().await else {
+                return;
+            };
+            if let Some(allowed) = enforce(None, &status, &restrictions) {
+                match volume.apply(&status, &allowed).await {
+                    Ok(()) => volume.changed(&bus).await,
+                    Err(e) => warn!(error = %e, "Failed to apply quiet hours"),
+                }
+            }
+        }
+    });
+}
+
+/// When a warning is issued, turn the session's streams down to the
+/// entry's (or the global) `duck_warnings` and back up after
+/// [`DUCK_DURATION`]
+pub fn spawn_warning_ducking(
+    bus: &EventBus,
+    engine: Arc<tokio::sync::Mutex<CoreEngine>>,
+    volume: Arc<VolumeCache>,
+) {
+    // Bumped by every duck, so only the last warning's timer restores
+    let generation = Arc::new(AtomicU64::new(0));
+    bus.spawn_subscriber("ducking", move |Published { event, .. }| {
+        let engine = engine.clone();
+        let controller = volume.controller().clone();
+        let generation = generation.clone();
+        async move {
+            let DaemonEvent::Core(CoreEvent::Warning { session_id, .. }) = event else {
+                return;
+            };
+            let (handle, percent) = {
+                let eng = engine.lock().await;
+                let Some(session) = eng
+                    .current_session()
+                    .filter(|session| session.plan.session_id == session_id)
+                else {
+                    return;
+                };
+                let duck = eng
+                    .policy()
+                    .get_entry(&session.plan.entry_id)
+                    .and_then(|entry| entry.volume.as_ref())
+                    .and_then(|volume| volume.duck_warnings)
+                    .or(eng.policy().volume.duck_warnings);
+                match (session.host_handle.clone(), duck) {
+                    (Some(handle), Some(percent)) => (handle, percent),
+                    _ => return,
+                }
+            };
+
+            if let Err(e) = controller.duck_session(&handle, Some(percent)).await {
+                debug!(error = %e, "Failed to duck the session for a warning");
+                return;
+            }
+            let duck = generation.fetch_add(1, Ordering::SeqCst) + 1;
+            tokio::spawn(async move {
+                tokio::time::sleep(DUCK_DURATION).await;
+                if generation.load(Ordering::SeqCst) != duck {
+                    return;
+                }
+                if let Err(e) = controller.duck_session(&handle, None).await {
+                    warn!(error = %e, "Failed to restore the session's volume");
+                }
+            });
+        }
+    });
+}
+
+fn convert_volume_policy(policy: &VolumePolicy) -> VolumeRestrictions {
+    VolumeRestrictions {
+        max_volume: policy.max_volume,
+        min_volume: policy.min_volume,
+        allow_mute: policy.allow_mute,
+        allow_change: policy.allow_change,
+        require_headphones: policy.require_headphones,
+    }
+}
+
+/// What a change made outside shepherd has to be turned into, if
+/// `restrictions` forbid it. Without a `previous` volume to go back to,
+/// only the range and the headphone rule are enforced.
+pub fn enforce(
+    previous: Option<&VolumeStatus>,
+    status: &VolumeStatus,
+    restrictions: &VolumeRestrictions,
+) -> Option<VolumeStatus> {
+    let mut allowed = status.clone();
+    if let Some(previous) = previous {
+        if !restrictions.allow_change {
+            allowed.percent = previous.percent;
+        }
+        if !restrictions.allow_mute {
+            allowed.muted = previous.muted;
+        }
+    }
+    allowed.percent = restrictions.clamp_volume(allowed.percent);
+    if restrictions.must_mute(status.output) {
+        allowed.muted = true;
+    }
+    (allowed != *status).then_some(allowed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use shepherd_api::AudioOutput;
+    use shepherd_host_api::{HostCapabilities, MockVolumeController, VolumeCapabilities};
+    use shepherd_store::SqliteStore;
+    use std::sync::atomic::AtomicUsize;
+
+    fn engine(config: &str) -> Arc<tokio::sync::Mutex<CoreEngine>> {
+        let policy = shepherd_config::parse_config(config).unwrap();
+        let store = Arc::new(SqliteStore::in_memory().unwrap());
+        Arc::new(tokio::sync::Mutex::new(CoreEngine::new(
+            policy,
+            store,
+            HostCapabilities::minimal(),
+        )))
+    }
+
+    /// Counts reads and pushes whatever the test sends
+    struct Pushing {
+        inner: MockVolumeController,
+        reads: AtomicUsize,
+        changes: Mutex<Option<mpsc::UnboundedReceiver<VolumeStatus>>>,
+    }
+
+    #[async_trait]
+    impl VolumeController for Pushing {
+        fn capabilities(&self) -> &VolumeCapabilities {
+            self.inner.capabilities()
+        }
+        async fn get_status(&self) -> VolumeResult<VolumeStatus> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.inner.get_status().await
+        }
+        async fn set_volume(&self, percent: u8) -> VolumeResult<()> {
+            self.inner.set_volume(percent).await
+        }
+        async fn volume_up(&self, step: u8) -> VolumeResult<()> {
+            self.inner.volume_up(step).await
+        }
+        async fn volume_down(&self, step: u8) -> VolumeResult<()> {
+            self.inner.volume_down(step).await
+        }
+        async fn toggle_mute(&self) -> VolumeResult<()> {
+            self.inner.toggle_mute().await
+        }
+        async fn set_mute(&self, muted: bool) -> VolumeResult<()> {
+            self.inner.set_mute(muted).await
+        }
+        fn watch(&self) -> Option<mpsc::UnboundedReceiver<VolumeStatus>> {
+            self.changes.lock().unwrap().take()
+        }
+    }
+
+    #[tokio::test]
+    async fn pushed_changes_are_cached_and_published() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let controller = Arc::new(Pushing {
+            inner: MockVolumeController::new(),
+            reads: AtomicUsize::new(0),
+            changes: Mutex::new(Some(rx)),
+        });
+        let cache = Arc::new(VolumeCache::new(controller.clone()));
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        cache.spawn_watcher(bus.clone(), engine("config_version = 1"));
+
+        // The first read fills the cache; later ones don't touch the backend
+        assert_eq!(cache.status().await.unwrap().percent, 50);
+        assert_eq!(cache.status().await.unwrap().percent, 50);
+        assert_eq!(controller.reads.load(Ordering::SeqCst), 1);
+
+        // A change made elsewhere, sent twice
+        let status = VolumeStatus {
+            percent: 20,
+            muted: false,
+            ..Default::default()
+        };
+        tx.send(status.clone()).unwrap();
+        tx.send(status).unwrap();
+        let published = events.recv().await.unwrap();
+        assert!(matches!(
+            published.event,
+            DaemonEvent::VolumeChanged {
+                percent: 20,
+                muted: false
+            }
+        ));
+        assert_eq!(cache.status().await.unwrap().percent, 20);
+        assert_eq!(controller.reads.load(Ordering::SeqCst), 1);
+
+        // Without pushes it goes back to asking
+        drop(tx);
+        while cache.is_watched() {
+            tokio::task::yield_now().await;
+        }
+        assert!(events.try_recv().is_err());
+        cache.status().await.unwrap();
+        assert_eq!(controller.reads.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn changes_against_policy_are_reverted() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let controller = Arc::new(Pushing {
+            inner: MockVolumeController::new(),
+            reads: AtomicUsize::new(0),
+            changes: Mutex::new(Some(rx)),
+        });
+        let cache = Arc::new(VolumeCache::new(controller.clone()));
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        cache.spawn_watcher(
+            bus.clone(),
+            engine("config_version = 1\n[service.volume]\nmax_volume = 60\n"),
+        );
+        cache.status().await.unwrap();
+
+        // Turned up to 90 with the hardware keys: back down to 60, and only
+        // the allowed volume is published
+        tx.send(VolumeStatus {
+            percent: 90,
+            muted: false,
+            ..Default::default()
+        })
+        .unwrap();
+        while controller.inner.get_status().await.unwrap().percent != 60 {
+            tokio::task::yield_now().await;
+        }
+        tx.send(VolumeStatus {
+            percent: 60,
+            muted: false,
+            ..Default::default()
+        })
+        .unwrap();
+        let published = events.recv().await.unwrap();
+        assert!(matches!(
+            published.event,
+            DaemonEvent::VolumeChanged { percent: 60, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn warnings_duck_the_session() {
+        use shepherd_api::WarningSeverity;
+        use shepherd_core::LaunchDecision;
+        use shepherd_host_api::{HostHandlePayload, HostSessionHandle};
+        use shepherd_util::{EntryId, MonotonicInstant};
+
+        let controller = Arc::new(MockVolumeController::new());
+        let cache = Arc::new(VolumeCache::new(controller.clone()));
+        let engine = engine(
+            r#"
+            config_version = 1
+
+            [service.volume]
+            duck_warnings = 30
+
+            [[entries]]
+            id = "game"
+            label = "Game"
+            kind = { type = "process", command = "/usr/bin/game" }
+            volume = { duck_warnings = 10 }
+            "#,
+        );
+        let bus = EventBus::new();
+        spawn_warning_ducking(&bus, engine.clone(), cache);
+
+        let session_id = {
+            let mut eng = engine.lock().await;
+            let now = shepherd_util::now();
+            let LaunchDecision::Approved(plan) = eng.request_launch(&EntryId::new("game"), now) else {
+                panic!("launch denied");
+            };
+            let session_id = plan.session_id.clone();
+            eng.start_session(plan, now, MonotonicInstant::now());
+            eng.attach_host_handle(HostSessionHandle::new(
+                session_id.clone(),
+                HostHandlePayload::Mock { id: 1 },
+            ));
+            session_id
+        };
+
+        bus.publish(DaemonEvent::Core(CoreEvent::Warning {
+            session_id,
+            threshold_seconds: 60,
+            time_remaining: Duration::from_secs(60),
+            severity: WarningSeverity::Warn,
+            message: None,
+        }));
+        // The entry's own setting wins over the global one
+        while controller.ducked.lock().unwrap().is_none() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(*controller.ducked.lock().unwrap(), Some(10));
+    }
+
+    #[tokio::test]
+    async fn quiet_hours_turn_the_volume_down() {
+        use chrono::TimeZone;
+        use shepherd_util::MonotonicInstant;
+
+        let controller = Arc::new(MockVolumeController::new());
+        let cache = Arc::new(VolumeCache::new(controller.clone()));
+        let engine = engine(
+            r#"
+            config_version = 1
+
+            [service.volume]
+            max_volume = 80
+
+            [[service.volume.windows]]
+            days = "all"
+            start = "20:00"
+            end = "07:00"
+            max_volume = 30
+            "#,
+        );
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        spawn_quiet_hours(&bus, engine.clone(), cache.clone());
+
+        let night = chrono::Local.with_ymd_and_hms(2025, 1, 6, 21, 0, 0).unwrap();
+        for event in engine.lock().await.tick(MonotonicInstant::now(), night) {
+            bus.publish(DaemonEvent::Core(event));
+        }
+
+        let mut restrictions = None;
+        loop {
+            match events.recv().await.unwrap().event {
+                DaemonEvent::VolumeRestrictionsChanged(r) => restrictions = Some(r),
+                DaemonEvent::VolumeChanged { percent, .. } => {
+                    assert_eq!(percent, 30);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(restrictions.unwrap().max_volume, Some(30));
+        assert_eq!(controller.get_status().await.unwrap().percent, 30);
+        assert_eq!(current_restrictions(&engine).await.max_volume, Some(30));
+    }
+
+    #[tokio::test]
+    async fn speakers_are_muted_when_headphones_are_required() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let controller = Arc::new(Pushing {
+            inner: MockVolumeController::new(),
+            reads: AtomicUsize::new(0),
+            changes: Mutex::new(Some(rx)),
+        });
+        let cache = Arc::new(VolumeCache::new(controller.clone()));
+        let engine = engine("config_version = 1\n[service.volume]\nrequire_headphones = true\n");
+        cache.spawn_watcher(EventBus::new(), engine.clone());
+        cache.status().await.unwrap();
+
+        // Headphones unplugged: the sound moves to the speakers
+        tx.send(VolumeStatus {
+            percent: 50,
+            muted: false,
+            output: AudioOutput::Speakers,
+        })
+        .unwrap();
+        while !controller.inner.get_status().await.unwrap().muted {
+            tokio::task::yield_now().await;
+        }
+        assert!(current_restrictions(&engine).await.require_headphones);
+    }
+
+    #[test]
+    fn enforce_rules() {
+        let at = |percent, muted| VolumeStatus {
+            percent,
+            muted,
+            ..Default::default()
+        };
+        let rules = |max_volume, allow_mute, allow_change| VolumeRestrictions {
+            max_volume,
+            min_volume: Some(10),
+            allow_mute,
+            allow_change,
+            require_headphones: false,
+        };
+
+        // Within the rules
+        assert_eq!(
+            enforce(
+                Some(&at(50, false)),
+                &at(40, true),
+                &rules(Some(80), true, true)
+            ),
+            None
+        );
+        // Out of range
+        assert_eq!(
+            enforce(
+                Some(&at(50, false)),
+                &at(95, false),
+                &rules(Some(80), true, true)
+            ),
+            Some(at(80, false))
+        );
+        assert_eq!(
+            enforce(None, &at(0, false), &rules(None, true, true)),
+            Some(at(10, false))
+        );
+        // Not allowed to change or mute: back to what it was
+        assert_eq!(
+            enforce(
+                Some(&at(50, false)),
+                &at(30, true),
+                &rules(None, false, false)
+            ),
+            Some(at(50, false))
+        );
+        assert_eq!(
+            enforce(None, &at(30, true), &rules(None, false, false)),
+            None
+        );
+        // Headphones required: speakers are muted, even when muting isn't
+        // otherwise allowed; an unknown output is left alone
+        let headphones = VolumeRestrictions {
+            require_headphones: true,
+            ..rules(None, false, true)
+        };
+        let speakers = VolumeStatus {
+            output: AudioOutput::Speakers,
+            ..at(40, false)
+        };
+        assert_eq!(
+            enforce(Some(&at(40, false)), &speakers, &headphones),
+            Some(VolumeStatus {
+                muted: true,
+                ..speakers
+            })
+        );
+        assert_eq!(enforce(None, &at(40, false), &headphones), None);
+    }
+}
@@ -2,12 +2,17 @@
 //!
 //! These tests verify the end-to-end behavior of shepherdd.
 
-use shepherd_api::{EntryKind, WarningSeverity, WarningThreshold};
+use shepherd_api::{
+    ClientInfo, ClientRole, Command, EntryKind, ErrorCode, ResponsePayload, ResponseResult, StopMode, WarningSeverity,
+    WarningThreshold,
+};
 use shepherd_config::{AvailabilityPolicy, Entry, LimitsPolicy, Policy};
 use shepherd_core::{CoreEngine, CoreEvent, LaunchDecision};
-use shepherd_host_api::{HostCapabilities, MockHost};
-use shepherd_store::{SqliteStore, Store};
-use shepherd_util::{self, EntryId, MonotonicInstant};
+use shepherd_host_api::{ExitStatus, HostCapabilities, InputCalls, MockHost};
+use shepherd_ipc::{IpcClient, IpcServer};
+use shepherd_store::{AuditEventType, SqliteStore, Store};
+use shepherd_util::{self, EntryId, MonotonicInstant, PinHash, SeatId};
+use shepherdd::{Backend, Service, ServiceOptions};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
@@ -32,8 +37,14 @@ fn make_test_policy() -> Policy {
                 },
                 limits: LimitsPolicy {
                     max_run: Some(Duration::from_secs(10)), // Short for testing
+                    min_run: None,
                     daily_quota: None,
                     cooldown: None,
+                    cooldown_scope: Default::default(),
+                    cooldown_after: None,
+                    min_gap: None,
+                    refund_under: None,
+                    refund_crash_under: None,
                 },
                 warnings: vec![
                     WarningThreshold {
@@ -48,13 +59,37 @@ fn make_test_policy() -> Policy {
                     },
                 ],
                 volume: None,
+                gamescope: None,
+                device_access: Default::default(),
+                tags: vec![],
+                prechecks: Default::default(),
+                confirm_launch: false,
+                autostart: Vec::new(),
+                hooks: Default::default(),
+                minecraft: None,
+                wine: None,
+                kill_by_name: false,
+                controller_only: false,
                 disabled: false,
                 disabled_reason: None,
             },
         ],
+        profiles: vec![],
+        seats: vec![],
         default_warnings: vec![],
         default_max_run: Some(Duration::from_secs(3600)),
         volume: Default::default(),
+        display: Default::default(),
+        curfew: None,
+        downtime: Vec::new(),
+        battery: Default::default(),
+        media: Default::default(),
+        rewards: Default::default(),
+        time_exchange: None,
+        maintenance: None,
+        calendar: None,
+        import_apps: Default::default(),
+        warnings: Vec::new(),
     }
 }
 
@@ -85,7 +120,7 @@ fn test_launch_approval() {
     let policy = make_test_policy();
     let store = Arc::new(SqliteStore::in_memory().unwrap());
     let caps = HostCapabilities::minimal();
-    let engine = CoreEngine::new(policy, store, caps);
+    let mut engine = CoreEngine::new(policy, store, caps);
 
     let entry_id = EntryId::new("test-game");
     let decision = engine.request_launch(&entry_id, shepherd_util::now());
@@ -107,7 +142,7 @@ fn test_session_lifecycle() {
     // Launch
     let plan = match engine.request_launch(&entry_id, now) {
         LaunchDecision::Approved(p) => p,
-        LaunchDecision::Denied { .. } => panic!("Launch should be approved"),
+        _ => panic!("Launch should be approved"),
     };
 
     // Start session
@@ -122,6 +157,14 @@ fn test_session_lifecycle() {
     assert!(matches!(decision, LaunchDecision::Denied { .. }));
 }
 
+/// The warnings among `events`, leaving out the time syncs sent alongside
+fn warnings(events: Vec<CoreEvent>) -> Vec<CoreEvent> {
+    events
+        .into_iter()
+        .filter(|e| matches!(e, CoreEvent::Warning { .. }))
+        .collect()
+}
+
 #[test]
 fn test_warning_emission() {
     let policy = make_test_policy();
@@ -140,26 +183,26 @@ fn test_warning_emission() {
     };
     engine.start_session(plan, now, now_mono);
 
-    // No warnings at start
+    // No warnings at start, only the time left
     let events = engine.tick(now_mono, now);
-    assert!(events.is_empty());
+    assert!(matches!(events.as_slice(), [CoreEvent::TimeRemaining { .. }]));
 
     // At 6 seconds (4 seconds remaining), 5-second warning should fire
     let at_6s_mono = now_mono + Duration::from_secs(6);
     let at_6s = now + chrono::Duration::seconds(6);
-    let events = engine.tick(at_6s_mono, at_6s);
+    let events = warnings(engine.tick(at_6s_mono, at_6s));
     assert_eq!(events.len(), 1);
     assert!(matches!(&events[0], CoreEvent::Warning { threshold_seconds: 5, .. }));
 
     // At 9 seconds (1 second remaining), 2-second warning should fire
     let at_9s_mono = now_mono + Duration::from_secs(9);
     let at_9s = now + chrono::Duration::seconds(9);
-    let events = engine.tick(at_9s_mono, at_9s);
+    let events = warnings(engine.tick(at_9s_mono, at_9s));
     assert_eq!(events.len(), 1);
     assert!(matches!(&events[0], CoreEvent::Warning { threshold_seconds: 2, .. }));
 
     // Warnings shouldn't repeat
-    let events = engine.tick(at_9s_mono, at_9s);
+    let events = warnings(engine.tick(at_9s_mono, at_9s));
     assert!(events.is_empty());
 }
 
@@ -325,3 +368,322 @@ fn test_session_extension() {
     let extension = new_deadline.signed_duration_since(original_deadline);
     assert!(extension.num_seconds() >= 299 && extension.num_seconds() <= 301);
 }
+
+/// shepherdd running in-process on a mock host that can block input
+struct TestService {
+    host: Arc<MockHost>,
+    ipc: Arc<IpcServer>,
+    dir: tempfile::TempDir,
+    task: tokio::task::JoinHandle<anyhow::Result<Option<std::path::PathBuf>>>,
+}
+
+impl TestService {
+    async fn start(policy: Policy) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let mut caps = HostCapabilities::minimal();
+        caps.can_inhibit_input = true;
+        let host = Arc::new(MockHost::new().with_capabilities(caps));
+        let options = ServiceOptions {
+            config_path: dir.path().join("config.toml"),
+            socket_path: dir.path().join("shepherdd.sock"),
+            data_dir: dir.path().join("data"),
+            in_memory_store: false,
+            backend: Backend::Mock(host.clone()),
+        };
+        let service = Service::new(options, policy, false).await.unwrap();
+        let ipc = service.ipc();
+        let task = tokio::spawn(service.run());
+        Self { host, ipc, dir, task }
+    }
+
+    /// A client connected with `role`, whatever this process's UID
+    async fn client(&self, role: ClientRole) -> IpcClient {
+        let (_, stream) = self.ipc.connect_local(ClientInfo::new(role)).await.unwrap();
+        IpcClient::from_stream(stream)
+    }
+
+    /// A client with `role` serving `seat`
+    async fn client_on(&self, role: ClientRole, seat: &str) -> IpcClient {
+        let mut info = ClientInfo::new(role);
+        info.seat = Some(SeatId::new(seat));
+        let (_, stream) = self.ipc.connect_local(info).await.unwrap();
+        IpcClient::from_stream(stream)
+    }
+
+    /// A client with `role` whose peer is `uid`
+    async fn client_as(&self, role: ClientRole, uid: u32) -> IpcClient {
+        let mut info = ClientInfo::new(role);
+        info.uid = Some(uid);
+        let (_, stream) = self.ipc.connect_local(info).await.unwrap();
+        IpcClient::from_stream(stream)
+    }
+
+    fn input_calls(&self) -> InputCalls {
+        *self.host.input_calls.lock().unwrap()
+    }
+
+    fn input_blocked(&self) -> bool {
+        *self.host.input_blocked.lock().unwrap()
+    }
+
+    fn audits(&self) -> Vec<AuditEventType> {
+        let store = SqliteStore::open_read_only(self.dir.path().join("data/shepherdd.db")).unwrap();
+        let mut audits: Vec<_> = store.get_recent_audits(1000).unwrap().into_iter().map(|a| a.event).collect();
+        audits.reverse();
+        audits
+    }
+}
+
+impl Drop for TestService {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn send(client: &mut IpcClient, command: Command) -> ResponseResult {
+    client.send(command).await.unwrap().result
+}
+
+fn error_code(result: ResponseResult) -> ErrorCode {
+    match result {
+        ResponseResult::Err(e) => e.code,
+        ResponseResult::Ok(payload) => panic!("Expected an error, got {:?}", payload),
+    }
+}
+
+fn controller_only_policy(admin_pin: Option<&str>) -> Policy {
+    let mut policy = make_test_policy();
+    policy.entries[0].controller_only = true;
+    policy.entries[0].limits.max_run = Some(Duration::from_secs(3600));
+    policy.service.admin_pin = admin_pin.map(PinHash::new);
+    policy
+}
+
+async fn launch(client: &mut IpcClient) {
+    let result = send(
+        client,
+        Command::Launch {
+            entry_id: EntryId::new("test-game"),
+            participants: Vec::new(),
+            idempotency_key: None,
+        },
+    )
+    .await;
+    assert!(
+        matches!(result, ResponseResult::Ok(ResponsePayload::LaunchApproved { .. })),
+        "{:?}",
+        result
+    );
+}
+
+#[tokio::test]
+async fn test_release_input_roles() {
+    let service = TestService::start(controller_only_policy(None)).await;
+    let mut admin = service.client(ClientRole::Admin).await;
+    let mut shell = service.client(ClientRole::Shell).await;
+
+    launch(&mut admin).await;
+    assert!(service.input_blocked());
+
+    // Without an admin PIN only admins can give input back
+    let result = send(&mut shell, Command::ReleaseInput { pin: Some("1234".into()) }).await;
+    assert_eq!(error_code(result), ErrorCode::PermissionDenied);
+    assert!(service.input_blocked());
+
+    // An admin needs no PIN
+    let result = send(&mut admin, Command::ReleaseInput { pin: None }).await;
+    assert!(matches!(result, ResponseResult::Ok(ResponsePayload::InputReleased)));
+    assert!(!service.input_blocked());
+    let audits = service.audits();
+    assert!(audits.iter().any(|a| matches!(a, AuditEventType::InputReleased { with_pin: false })));
+}
+
+#[tokio::test]
+async fn test_release_input_pin() {
+    let service = TestService::start(controller_only_policy(Some("2468"))).await;
+    let mut admin = service.client(ClientRole::Admin).await;
+    let mut shell = service.client(ClientRole::Shell).await;
+    launch(&mut admin).await;
+
+    let result = send(&mut shell, Command::ReleaseInput { pin: Some("1111".into()) }).await;
+    assert_eq!(error_code(result), ErrorCode::PermissionDenied);
+    let result = send(&mut shell, Command::ReleaseInput { pin: None }).await;
+    assert_eq!(error_code(result), ErrorCode::PermissionDenied);
+    assert!(service.input_blocked());
+
+    let result = send(&mut shell, Command::ReleaseInput { pin: Some("2468".into()) }).await;
+    assert!(matches!(result, ResponseResult::Ok(ResponsePayload::InputReleased)));
+    assert!(!service.input_blocked());
+
+    let audits = service.audits();
+    assert!(audits.iter().any(|a| matches!(a, AuditEventType::InputReleaseFailed { failures: 1, lockout: None })));
+    assert!(audits.iter().any(|a| matches!(a, AuditEventType::InputReleaseFailed { failures: 2, lockout: None })));
+    assert!(audits.iter().any(|a| matches!(a, AuditEventType::InputReleased { with_pin: true })));
+
+    // The right PIN cleared the count; guessing on locks everyone out,
+    // even with the right PIN and on a new connection
+    for _ in 0..3 {
+        let result = send(&mut shell, Command::ReleaseInput { pin: Some("0000".into()) }).await;
+        assert_eq!(error_code(result), ErrorCode::PermissionDenied);
+    }
+    let result = send(&mut shell, Command::ReleaseInput { pin: Some("0000".into()) }).await;
+    assert_eq!(error_code(result), ErrorCode::PermissionDenied);
+    let audits = service.audits();
+    assert!(audits.iter().any(|a| matches!(
+        a,
+        AuditEventType::InputReleaseFailed { failures: 4, lockout: Some(lockout) } if lockout.as_secs() == 30
+    )));
+
+    let mut reconnected = service.client(ClientRole::Shell).await;
+    let result = send(&mut reconnected, Command::ReleaseInput { pin: Some("2468".into()) }).await;
+    assert_eq!(error_code(result), ErrorCode::RateLimited);
+
+    // Admins aren't locked out
+    let result = send(&mut admin, Command::ReleaseInput { pin: None }).await;
+    assert!(matches!(result, ResponseResult::Ok(ResponsePayload::InputReleased)));
+}
+
+#[tokio::test]
+async fn test_controller_only_blocks_input_again() {
+    let service = TestService::start(controller_only_policy(None)).await;
+    let mut admin = service.client(ClientRole::Admin).await;
+
+    launch(&mut admin).await;
+    assert_eq!(service.input_calls(), InputCalls { inhibit: 1, release: 0 });
+    send(&mut admin, Command::ReleaseInput { pin: None }).await;
+    assert!(!service.input_blocked());
+
+    let result = send(&mut admin, Command::StopCurrent { mode: StopMode::Graceful, seat: None }).await;
+    assert!(matches!(result, ResponseResult::Ok(ResponsePayload::Stopped)), "{:?}", result);
+
+    // Released for one session only
+    launch(&mut admin).await;
+    assert!(service.input_blocked());
+    assert_eq!(service.input_calls().inhibit, 2);
+}
+
+/// The seat's running entry, as `client` sees it
+async fn running_on(client: &mut IpcClient, seat: Option<&str>) -> Option<EntryId> {
+    let seat = seat.map(SeatId::new);
+    match send(client, Command::GetState { seat }).await {
+        ResponseResult::Ok(ResponsePayload::State(state)) => state.current_session.map(|s| s.entry_id),
+        other => panic!("Expected state, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_admin_stops_another_seat() {
+    let mut policy = make_test_policy();
+    policy.entries[0].limits.max_run = Some(Duration::from_secs(3600));
+    let service = TestService::start(policy).await;
+    let mut admin = service.client(ClientRole::Admin).await;
+    let mut den = service.client_on(ClientRole::Shell, "seat1").await;
+    let mut shell = service.client(ClientRole::Shell).await;
+
+    launch(&mut den).await;
+    assert_eq!(running_on(&mut den, None).await, Some(EntryId::new("test-game")));
+    assert_eq!(running_on(&mut admin, None).await, None);
+    assert_eq!(running_on(&mut admin, Some("seat1")).await, Some(EntryId::new("test-game")));
+
+    // Only admins reach past their own seat
+    let result = send(&mut shell, Command::GetState { seat: Some(SeatId::new("seat1")) }).await;
+    assert_eq!(error_code(result), ErrorCode::PermissionDenied);
+    let stop = |seat: &str| Command::StopCurrent {
+        mode: StopMode::Force,
+        seat: Some(SeatId::new(seat)),
+    };
+    let result = send(&mut shell, stop("seat1")).await;
+    assert_eq!(error_code(result), ErrorCode::PermissionDenied);
+
+    let result = send(
+        &mut admin,
+        Command::ExtendCurrent { by: Duration::from_secs(600), seat: Some(SeatId::new("seat1")) },
+    )
+    .await;
+    assert!(matches!(result, ResponseResult::Ok(ResponsePayload::Extended { new_deadline: Some(_) })), "{:?}", result);
+
+    let result = send(&mut admin, stop("seat1")).await;
+    assert!(matches!(result, ResponseResult::Ok(ResponsePayload::Stopped)), "{:?}", result);
+    assert_eq!(running_on(&mut den, None).await, None);
+}
+
+/// Wait up to a few seconds for `done`, for what the service does on its own
+async fn eventually(mut done: impl FnMut() -> bool) {
+    for _ in 0..100 {
+        if done() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("Timed out waiting for the service");
+}
+
+#[tokio::test]
+async fn test_input_released_after_every_ending() {
+    // Expiry blocks input while the app closes and gives it back after
+    let mut policy = make_test_policy();
+    policy.entries[0].limits.max_run = Some(Duration::from_secs(1));
+    policy.entries[0].warnings.clear();
+    let service = TestService::start(policy).await;
+    let mut admin = service.client(ClientRole::Admin).await;
+    launch(&mut admin).await;
+    eventually(|| service.input_calls().release > 0).await;
+    assert_eq!(service.input_calls(), InputCalls { inhibit: 1, release: 1 });
+    assert!(!service.input_blocked());
+    assert!(service.host.running_sessions().is_empty());
+    drop(service);
+
+    // A controller-only session stopped by an admin
+    let service = TestService::start(controller_only_policy(None)).await;
+    let mut admin = service.client(ClientRole::Admin).await;
+    launch(&mut admin).await;
+    let result = send(&mut admin, Command::StopCurrent { mode: StopMode::Graceful, seat: None }).await;
+    assert!(matches!(result, ResponseResult::Ok(ResponsePayload::Stopped)), "{:?}", result);
+    assert_eq!(service.input_calls(), InputCalls { inhibit: 1, release: 1 });
+    assert!(!service.input_blocked());
+
+    // ...and one that crashed
+    launch(&mut admin).await;
+    assert!(service.input_blocked());
+    let session_id = service.host.running_sessions().pop().unwrap();
+    service.host.simulate_exit(&session_id, ExitStatus::with_code(1));
+    eventually(|| !service.input_blocked()).await;
+    assert_eq!(service.input_calls(), InputCalls { inhibit: 2, release: 2 });
+}
+
+#[tokio::test]
+async fn test_idempotency_keys_per_client() {
+    let mut policy = make_test_policy();
+    policy.entries[0].limits.max_run = Some(Duration::from_secs(3600));
+    let service = TestService::start(policy).await;
+    let mut alice = service.client_as(ClientRole::Shell, 1000).await;
+    let mut bob = service.client_as(ClientRole::Shell, 1001).await;
+    let launch = || Command::Launch {
+        entry_id: EntryId::new("test-game"),
+        participants: Vec::new(),
+        idempotency_key: Some("tile-1".into()),
+    };
+
+    let first = send(&mut alice, launch()).await;
+    let ResponseResult::Ok(ResponsePayload::LaunchApproved { session_id, .. }) = first else {
+        panic!("Expected a launch, got {:?}", first);
+    };
+
+    // Retrying with the key gets the original result back...
+    let retry = send(&mut alice, launch()).await;
+    assert!(
+        matches!(&retry, ResponseResult::Ok(ResponsePayload::LaunchApproved { session_id: id, .. }) if *id == session_id),
+        "{:?}",
+        retry
+    );
+
+    // ...but another client with the same key is a new launch, refused
+    // because the seat is busy
+    let other = send(&mut bob, launch()).await;
+    assert!(
+        !matches!(other, ResponseResult::Ok(ResponsePayload::LaunchApproved { .. })),
+        "{:?}",
+        other
+    );
+    assert_eq!(service.host.running_sessions().len(), 1);
+}
@@ -14,6 +14,8 @@ SHEPHERD_BINARIES=(
     "shepherdd"
     "shepherd-launcher"
     "shepherd-hud"
+    "shepherd-shell"
+    "shepherd-session"
 )
 
 # Get the target directory for binaries
@@ -85,6 +85,7 @@ install_sway_config() {
     sed \
         -e "s|./target/debug/shepherd-launcher|$bindir/shepherd-launcher|g" \
         -e "s|./target/debug/shepherd-hud|$bindir/shepherd-hud|g" \
+        -e "s|./target/debug/shepherd-shell|$bindir/shepherd-shell|g" \
         -e "s|./target/debug/shepherdd|$bindir/shepherdd|g" \
         -e "s|./config.example.toml|~/.config/shepherd/config.toml|g" \
         -e "s|-c ./sway.conf|-c $dst_config|g" \
@@ -34,6 +34,7 @@ sway_cleanup() {
     pkill -x "shepherdd" 2>/dev/null || true
     pkill -x "shepherd-launcher" 2>/dev/null || true
     pkill -x "shepherd-hud" 2>/dev/null || true
+    pkill -x "shepherd-shell" 2>/dev/null || true
     
     # Remove socket
     if [[ -n "${SHEPHERD_SOCKET:-}" ]]; then
@@ -48,6 +49,7 @@ sway_kill_existing() {
     pkill -x "shepherdd" 2>/dev/null || true
     pkill -x "shepherd-launcher" 2>/dev/null || true
     pkill -x "shepherd-hud" 2>/dev/null || true
+    pkill -x "shepherd-shell" 2>/dev/null || true
     
     # Remove stale socket if it exists
     if [[ -n "${SHEPHERD_SOCKET:-}" ]] && [[ -e "$SHEPHERD_SOCKET" ]]; then